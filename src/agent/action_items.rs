@@ -0,0 +1,76 @@
+use crate::tasks::parse_due_ms;
+use chrono::Local;
+use serde_json::Value;
+
+pub const ACTION_ITEMS_SYSTEM_PROMPT: &str =
+    "You extract commitments from conversations. Respond only with valid JSON.";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActionItem {
+    pub title: String,
+    pub due_at_ms: Option<i64>,
+}
+
+pub fn action_items_prompt(exchange: &[String]) -> String {
+    format!(
+        "Find concrete commitments or follow-ups in this exchange, such as \"I'll send the doc Friday\" \
+or \"remind me to call the bank tomorrow\". Ignore vague intentions, questions, and things already done.\n\n\
+The current local time is {now}.\n\n\
+Return a JSON object with one key \"items\": an array of objects with:\n\
+- \"title\": a short imperative description (e.g. \"Send the doc to Alice\")\n\
+- \"due\": the resolved due date as \"YYYY-MM-DD\" or \"YYYY-MM-DD HH:MM\" local time, or null if none was given\n\n\
+Return {{\"items\": []}} when there is nothing to track.\n\n\
+## Exchange\n{exchange}\n\n\
+Respond with ONLY valid JSON, no markdown fences.",
+        now = Local::now().format("%Y-%m-%d %H:%M (%A)"),
+        exchange = exchange.join("\n")
+    )
+}
+
+pub fn parse_action_items(value: &Value) -> Vec<ActionItem> {
+    value
+        .get("items")
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| {
+                    let title = item.get("title").and_then(Value::as_str)?.trim();
+                    if title.is_empty() {
+                        return None;
+                    }
+                    Some(ActionItem {
+                        title: title.to_string(),
+                        due_at_ms: item
+                            .get("due")
+                            .and_then(Value::as_str)
+                            .and_then(parse_due_ms),
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_action_items;
+    use serde_json::json;
+
+    #[test]
+    fn parse_action_items_skips_blank_titles_and_bad_dates() {
+        let items = parse_action_items(&json!({
+            "items": [
+                { "title": " Send the doc ", "due": "2026-03-06" },
+                { "title": "Call the bank", "due": "next week" },
+                { "title": "", "due": null },
+                { "due": "2026-03-07" }
+            ]
+        }));
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title, "Send the doc");
+        assert!(items[0].due_at_ms.is_some());
+        assert_eq!(items[1].due_at_ms, None);
+        assert!(parse_action_items(&json!({})).is_empty());
+    }
+}
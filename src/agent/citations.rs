@@ -0,0 +1,168 @@
+use serde_json::{Map, Value};
+use std::path::Path;
+
+const MAX_SOURCES: usize = 8;
+// Search results that stand in as sources when nothing was fetched or read.
+const SEARCH_FALLBACK: usize = 3;
+
+// What the tools of one turn looked at: pages fetched, files read and search
+// results. `sources` decides which of them the answer drew on.
+#[derive(Debug, Clone, Default)]
+pub struct Citations {
+    consulted: Vec<String>,
+    searched: Vec<String>,
+}
+
+impl Citations {
+    pub fn record(&mut self, tool: &str, arguments: &Map<String, Value>, result: &str) {
+        if result.starts_with("Error") {
+            return;
+        }
+        match tool {
+            "web_fetch" => {
+                let Ok(page) = serde_json::from_str::<Value>(result) else {
+                    return;
+                };
+                if page.get("error").is_some()
+                    || page["status"].as_u64().is_some_and(|status| status >= 400)
+                {
+                    return;
+                }
+                if let Some(url) = page["finalUrl"].as_str().or(page["url"].as_str()) {
+                    push_unique(&mut self.consulted, url);
+                }
+            }
+            "read_file" => {
+                if let Some(path) = arguments.get("path").and_then(Value::as_str) {
+                    push_unique(&mut self.consulted, path);
+                }
+            }
+            "web_search" => {
+                for url in urls_in(result) {
+                    push_unique(&mut self.searched, url);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Fetched pages and read files always count. Search results count when
+    // the answer mentions them or their site; if nothing else was consulted,
+    // the top few results are what the answer came from.
+    pub fn sources(&self, answer: &str) -> Vec<String> {
+        let answer = answer.to_lowercase();
+        let mut sources = self.consulted.clone();
+        let mentioned = self
+            .searched
+            .iter()
+            .filter(|url| {
+                answer.contains(&url.to_lowercase())
+                    || host(url).is_some_and(|host| answer.contains(&host))
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        let cited = if mentioned.is_empty() && sources.is_empty() {
+            self.searched
+                .iter()
+                .take(SEARCH_FALLBACK)
+                .cloned()
+                .collect()
+        } else {
+            mentioned
+        };
+        for url in cited {
+            push_unique(&mut sources, &url);
+        }
+        sources
+    }
+
+    // "Sources:" block appended to the reply; files are shown relative to the
+    // workspace.
+    pub fn render(&self, answer: &str, workspace: &Path) -> Option<String> {
+        let sources = self.sources(answer);
+        if sources.is_empty() {
+            return None;
+        }
+        let mut lines = vec!["Sources:".to_string()];
+        for (idx, source) in sources.iter().take(MAX_SOURCES).enumerate() {
+            let shown = Path::new(source)
+                .strip_prefix(workspace)
+                .map(|rel| rel.display().to_string())
+                .unwrap_or_else(|_| source.clone());
+            lines.push(format!("{}. {shown}", idx + 1));
+        }
+        if sources.len() > MAX_SOURCES {
+            lines.push(format!("(+{} more)", sources.len() - MAX_SOURCES));
+        }
+        Some(lines.join("\n"))
+    }
+}
+
+fn push_unique(list: &mut Vec<String>, item: &str) {
+    if !list.iter().any(|existing| existing == item) {
+        list.push(item.to_string());
+    }
+}
+
+fn urls_in(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| matches!(c, '(' | ')' | '<' | '>' | '"' | ',')))
+        .filter(|word| word.starts_with("https://") || word.starts_with("http://"))
+}
+
+// "example.com" for "https://www.example.com/path".
+fn host(url: &str) -> Option<String> {
+    let rest = url.split_once("://")?.1;
+    let host = rest.split(['/', '?', '#']).next()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn cites_consulted_sources_and_mentioned_search_results() {
+        let search = "Results for: rust 2024 (brave)\n\n1. Rust Blog\n   https://blog.rust-lang.org/2024\n   Edition news\n2. Reddit\n   https://www.reddit.com/r/rust/x";
+        let no_args = Map::new();
+        let mut citations = Citations::default();
+        citations.record("web_search", &no_args, search);
+        assert_eq!(
+            citations.sources("The edition ships in 1.85."),
+            vec![
+                "https://blog.rust-lang.org/2024".to_string(),
+                "https://www.reddit.com/r/rust/x".to_string()
+            ]
+        );
+
+        let page = json!({ "url": "https://a.dev", "finalUrl": "https://a.dev/", "status": 200 });
+        citations.record("web_fetch", &no_args, &page.to_string());
+        citations.record(
+            "web_fetch",
+            &no_args,
+            &json!({ "error": "blocked" }).to_string(),
+        );
+        let read = json!({ "path": "/ws/notes/plan.md" });
+        citations.record("read_file", read.as_object().unwrap(), "# Plan");
+        let missing = json!({ "path": "/ws/gone.md" });
+        citations.record(
+            "read_file",
+            missing.as_object().unwrap(),
+            "Error: File not found",
+        );
+        citations.record("exec", &no_args, "https://not.a.source");
+
+        let answer = "Per reddit.com and the plan, ship it.";
+        assert_eq!(
+            citations.render(answer, Path::new("/ws")).unwrap(),
+            "Sources:\n1. https://a.dev/\n2. notes/plan.md\n3. https://www.reddit.com/r/rust/x"
+        );
+        assert!(
+            Citations::default()
+                .render(answer, Path::new("/ws"))
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,390 @@
+use crate::bus::InboundMessage;
+use crate::cron::reminder::{add_reminder, parse_when};
+use crate::cron::time::format_local;
+use crate::cron::{CronSchedule, CronService};
+use crate::tasks::{TaskStore, parse_due_ms};
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveTime, Timelike};
+use serde_json::Value;
+
+const MINUTE_MS: i64 = 60 * 1000;
+const HOUR_MS: i64 = 60 * MINUTE_MS;
+const DEFAULT_CLOCK: &str = "09:00";
+
+// (as written, cron field, shown)
+const WEEKDAYS: &[(&str, &str, &str)] = &[
+    ("monday", "Mon", "Monday"),
+    ("tuesday", "Tue", "Tuesday"),
+    ("wednesday", "Wed", "Wednesday"),
+    ("thursday", "Thu", "Thursday"),
+    ("friday", "Fri", "Friday"),
+    ("saturday", "Sat", "Saturday"),
+    ("sunday", "Sun", "Sunday"),
+];
+
+const USAGE: &str = "Commands handled without the agent:\n\
+REMIND: <text> <when> - e.g. \"REMIND: pay rent 1st of month\", \"REMIND: stretch every weekday at 15:00\", \"REMIND: call mom tomorrow 18:00\"\n\
+TASK: <title> [due <when>] - e.g. \"TASK: renew passport due 2030-03-01\"";
+
+#[derive(Debug, Clone)]
+pub enum When {
+    Once(i64),
+    Repeating {
+        schedule: CronSchedule,
+        label: String,
+    },
+}
+
+// A "REMIND:" or "TASK:" line turned into a cron job or task directly,
+// without an LLM turn.
+#[derive(Debug, Clone)]
+pub enum StructuredCommand {
+    Remind {
+        text: String,
+        when: When,
+    },
+    Task {
+        title: String,
+        due_at_ms: Option<i64>,
+    },
+}
+
+// Where a command is written: an email's subject, otherwise the message's
+// first line.
+pub fn command_line(msg: &InboundMessage) -> &str {
+    msg.metadata
+        .get("subject")
+        .and_then(Value::as_str)
+        .filter(|subject| !subject.trim().is_empty())
+        .unwrap_or_else(|| msg.content.lines().next().unwrap_or_default())
+        .trim()
+}
+
+// `None` when the line is not a command; an error when it is one that
+// could not be understood.
+pub fn parse(line: &str, now: DateTime<Local>) -> Option<Result<StructuredCommand, String>> {
+    let (keyword, rest) = line.split_once(':')?;
+    let rest = rest.trim();
+    match keyword.trim().to_ascii_uppercase().as_str() {
+        "REMIND" | "REMINDER" => Some(parse_reminder(rest, now)),
+        "TASK" | "TODO" => Some(parse_task(rest, now)),
+        _ => None,
+    }
+}
+
+fn parse_reminder(rest: &str, now: DateTime<Local>) -> Result<StructuredCommand, String> {
+    let words = rest.split_whitespace().collect::<Vec<_>>();
+    // The schedule is the longest tail that reads as one; the words before
+    // it are what to remind about.
+    for split in 1..words.len() {
+        if let Some(when) = parse_schedule(&words[split..].join(" "), now) {
+            return Ok(StructuredCommand::Remind {
+                text: words[..split].join(" "),
+                when,
+            });
+        }
+    }
+    Err(format!("Couldn't tell when to remind you in \"{rest}\"."))
+}
+
+fn parse_task(rest: &str, now: DateTime<Local>) -> Result<StructuredCommand, String> {
+    if rest.is_empty() {
+        return Err("A task needs a title.".to_string());
+    }
+    let lowered = rest.to_ascii_lowercase();
+    for marker in [" due ", " by "] {
+        if let Some(idx) = lowered.rfind(marker) {
+            let when = &rest[idx + marker.len()..];
+            let due = parse_due_ms(when).or_else(|| parse_when(when, now).ok());
+            if let Some(due_at_ms) = due {
+                return Ok(StructuredCommand::Task {
+                    title: rest[..idx].trim().to_string(),
+                    due_at_ms: Some(due_at_ms),
+                });
+            }
+        }
+    }
+    Ok(StructuredCommand::Task {
+        title: rest.to_string(),
+        due_at_ms: None,
+    })
+}
+
+fn parse_schedule(raw: &str, now: DateTime<Local>) -> Option<When> {
+    parse_repeating(raw).or_else(|| parse_when(raw, now).ok().map(When::Once))
+}
+
+// "every 2 hours", "every day", "weekdays at 08:30", "every friday 17:00",
+// "1st of month", "on the 15th of each month at 10:00", "monthly".
+fn parse_repeating(raw: &str) -> Option<When> {
+    let lowered = raw.trim().to_ascii_lowercase();
+    let text = ["on the ", "on "]
+        .iter()
+        .find_map(|prefix| lowered.strip_prefix(prefix))
+        .unwrap_or(&lowered);
+    if let Some(every_ms) = text.strip_prefix("every ").and_then(interval_ms) {
+        return Some(When::Repeating {
+            schedule: CronSchedule {
+                kind: "every".to_string(),
+                every_ms: Some(every_ms),
+                ..CronSchedule::default()
+            },
+            label: text.to_string(),
+        });
+    }
+    let (days, clock) = split_clock(text)?;
+    let (day_of_month, day_of_week, label) = day_fields(days)?;
+    Some(When::Repeating {
+        schedule: CronSchedule {
+            kind: "cron".to_string(),
+            expr: Some(format!(
+                "0 {} {} {day_of_month} * {day_of_week}",
+                clock.minute(),
+                clock.hour()
+            )),
+            tz: Some("local".to_string()),
+            ..CronSchedule::default()
+        },
+        label: format!("{label} at {}", clock.format("%H:%M")),
+    })
+}
+
+fn interval_ms(text: &str) -> Option<i64> {
+    let (amount, unit) = match text.split_once(' ') {
+        Some((amount, unit)) => (amount.parse::<i64>().ok()?, unit),
+        None => (1, text),
+    };
+    let unit_ms = match unit {
+        "min" | "mins" | "minute" | "minutes" => MINUTE_MS,
+        "hour" | "hours" => HOUR_MS,
+        _ => return None,
+    };
+    (amount > 0).then_some(amount * unit_ms)
+}
+
+// Splits "weekdays at 08:30" or "every monday 08:30"; no time means 09:00.
+fn split_clock(text: &str) -> Option<(&str, NaiveTime)> {
+    let parse = |raw: &str| NaiveTime::parse_from_str(raw.trim(), "%H:%M").ok();
+    if let Some((days, clock)) = text.rsplit_once(" at ") {
+        return Some((days.trim(), parse(clock)?));
+    }
+    if let Some((days, last)) = text.rsplit_once(' ')
+        && let Some(clock) = parse(last)
+    {
+        return Some((days.trim(), clock));
+    }
+    Some((text, parse(DEFAULT_CLOCK)?))
+}
+
+// (day-of-month, day-of-week, shown) cron fields for the day part.
+fn day_fields(days: &str) -> Option<(String, String, String)> {
+    let every = days.strip_prefix("every ");
+    let text = every.unwrap_or(days);
+    let any = || "*".to_string();
+    let monthly = |day: u32| {
+        Some((
+            day.to_string(),
+            "*".to_string(),
+            format!("on the {} of every month", ordinal(day)),
+        ))
+    };
+    match (every.is_some(), text) {
+        (true, "day") | (false, "daily") => {
+            return Some((any(), any(), "every day".to_string()));
+        }
+        (_, "weekday" | "weekdays") => {
+            return Some((any(), "Mon-Fri".to_string(), "every weekday".to_string()));
+        }
+        (true, "month") | (false, "monthly") => return monthly(1),
+        _ => {}
+    }
+    if let Some((_, field, shown)) = WEEKDAYS.iter().find(|(name, _, _)| {
+        text.strip_suffix('s') == Some(name) || (every.is_some() && text == *name)
+    }) {
+        return Some((any(), field.to_string(), format!("every {shown}")));
+    }
+    let day = match text.split_once(" of ") {
+        Some((day, "month" | "the month" | "each month" | "every month")) => day,
+        _ => text
+            .strip_prefix("month on the ")
+            .filter(|_| every.is_some())?,
+    };
+    let day = day
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .parse::<u32>()
+        .ok()
+        .filter(|day| (1..=31).contains(day))?;
+    monthly(day)
+}
+
+fn ordinal(day: u32) -> String {
+    let suffix = match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{day}{suffix}")
+}
+
+// Carries out a command and returns the confirmation (or the problem and
+// usage) to send back.
+pub async fn run(
+    command: Result<StructuredCommand, String>,
+    cron: Option<&CronService>,
+    tasks: &TaskStore,
+    msg: &InboundMessage,
+    session_key: &str,
+) -> Result<String> {
+    let now_ms = Local::now().timestamp_millis();
+    let command = match command {
+        Ok(command) => command,
+        Err(problem) => return Ok(format!("⚠️ {problem}\n\n{USAGE}")),
+    };
+    match command {
+        StructuredCommand::Remind { text, when } => {
+            let Some(cron) = cron else {
+                return Ok("Reminders are not available here.".to_string());
+            };
+            let owner =
+                (!msg.sender_id.is_empty()).then(|| format!("{}:{}", msg.channel, msg.sender_id));
+            let (schedule, label) = match when {
+                When::Once(at_ms) => (
+                    CronSchedule {
+                        kind: "at".to_string(),
+                        at_ms: Some(at_ms),
+                        ..CronSchedule::default()
+                    },
+                    None,
+                ),
+                When::Repeating { schedule, label } => (schedule, Some(label)),
+            };
+            let job =
+                add_reminder(cron, &text, schedule, &msg.channel, &msg.chat_id, owner).await?;
+            let next = job
+                .state
+                .next_run_at_ms
+                .map(|at_ms| format_local(at_ms, now_ms))
+                .unwrap_or_else(|| "not scheduled".to_string());
+            Ok(match label {
+                Some(label) => format!(
+                    "✅ Reminder \"{text}\" set {label}, next {next} (id: {})",
+                    job.id
+                ),
+                None => format!("✅ Reminder \"{text}\" set for {next} (id: {})", job.id),
+            })
+        }
+        StructuredCommand::Task { title, due_at_ms } => {
+            let task = tasks.add(&title, due_at_ms, Some(session_key.to_string()), "command")?;
+            let due = task
+                .due_at_ms
+                .map(|at_ms| format!(", due {}", format_local(at_ms, now_ms)))
+                .unwrap_or_default();
+            Ok(format!(
+                "✅ Task added: {}{due} (id: {})",
+                task.title, task.id
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::str::FromStr;
+
+    fn repeating(line: &str) -> (String, CronSchedule, String) {
+        let now = Local::now();
+        match parse(line, now) {
+            Some(Ok(StructuredCommand::Remind {
+                text,
+                when: When::Repeating { schedule, label },
+            })) => (text, schedule, label),
+            other => panic!("{line}: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_reminders_and_tasks_from_command_lines() {
+        let (text, schedule, label) = repeating("REMIND: pay rent 1st of month");
+        assert_eq!(text, "pay rent");
+        assert_eq!(schedule.expr.as_deref(), Some("0 0 9 1 * *"));
+        assert_eq!(schedule.tz.as_deref(), Some("local"));
+        assert_eq!(label, "on the 1st of every month at 09:00");
+
+        let cases = [
+            (
+                "Remind: stretch every weekday at 15:30",
+                "0 30 15 * * Mon-Fri",
+            ),
+            ("REMIND: gym on fridays 18:00", "0 0 18 * * Fri"),
+            ("REMIND: water plants every day", "0 0 9 * * *"),
+            (
+                "REMIND: invoice on the 22nd of each month at 10:00",
+                "0 0 10 22 * *",
+            ),
+        ];
+        for (line, expr) in cases {
+            let (_, schedule, _) = repeating(line);
+            assert_eq!(schedule.expr.as_deref(), Some(expr), "{line}");
+            assert!(cron::Schedule::from_str(expr).is_ok(), "{expr}");
+        }
+        let (text, schedule, label) = repeating("REMINDER: drink water every 2 hours");
+        assert_eq!(text, "drink water");
+        assert_eq!(schedule.every_ms, Some(2 * HOUR_MS));
+        assert_eq!(label, "every 2 hours");
+
+        let now = Local
+            .with_ymd_and_hms(2030, 1, 15, 20, 0, 0)
+            .single()
+            .expect("valid local time");
+        match parse("REMIND: call mom at 21:30", now) {
+            Some(Ok(StructuredCommand::Remind {
+                text,
+                when: When::Once(at_ms),
+            })) => {
+                assert_eq!(text, "call mom");
+                assert_eq!(
+                    at_ms,
+                    (now + chrono::Duration::minutes(90)).timestamp_millis()
+                );
+            }
+            other => panic!("{other:?}"),
+        }
+        // "monday" alone is a date, not a schedule this parser knows.
+        assert!(matches!(parse("REMIND: gym monday", now), Some(Err(_))));
+        assert!(matches!(parse("REMIND: something", now), Some(Err(_))));
+
+        match parse("TODO: Renew passport DUE 2030-03-01", now) {
+            Some(Ok(StructuredCommand::Task { title, due_at_ms })) => {
+                assert_eq!(title, "Renew passport");
+                assert_eq!(due_at_ms, parse_due_ms("2030-03-01"));
+            }
+            other => panic!("{other:?}"),
+        }
+        match parse("TASK: stand by me", now) {
+            Some(Ok(StructuredCommand::Task { title, due_at_ms })) => {
+                assert_eq!(title, "stand by me");
+                assert_eq!(due_at_ms, None);
+            }
+            other => panic!("{other:?}"),
+        }
+        assert!(parse("Note: remind me later", now).is_none());
+        assert!(parse("no command here", now).is_none());
+
+        let mut email = InboundMessage::new(
+            "email",
+            "a@example.com",
+            "a@example.com",
+            "Email received.\nSubject: REMIND: x 10m",
+        );
+        email
+            .metadata
+            .insert("subject".to_string(), Value::from("REMIND: x 10m"));
+        assert_eq!(command_line(&email), "REMIND: x 10m");
+        let chat = InboundMessage::new("telegram", "u1", "c1", " TASK: buy milk\nthanks");
+        assert_eq!(command_line(&chat), "TASK: buy milk");
+    }
+}
@@ -1,14 +1,112 @@
+use crate::channels::delivery::window_is_open;
+use crate::config::{ContextSectionConfig, DeliveryWindow, PersonaRule};
 use crate::memory::MemoryStore;
+use crate::providers::models::ModelCapabilities;
 use crate::skills::SkillsLoader;
 use base64::Engine;
-use chrono::Local;
+use chrono::{Local, NaiveDateTime};
 use serde_json::{Value, json};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+// Rough chars-per-token heuristic; good enough to compare components.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+#[derive(Debug, Clone)]
+pub struct ContextSection {
+    pub kind: String,
+    pub name: String,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ContextReport {
+    pub sections: Vec<ContextSection>,
+    pub model: Option<ModelCapabilities>,
+}
+
+impl ContextReport {
+    pub fn push(&mut self, kind: &str, name: &str, content: &str) {
+        self.sections.push(ContextSection {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            tokens: estimate_tokens(content),
+        });
+    }
+
+    pub fn total_tokens(&self) -> usize {
+        self.sections.iter().map(|section| section.tokens).sum()
+    }
+
+    pub fn render(&self) -> String {
+        let mut lines = vec!["Context assembled for the last turn (~tokens):".to_string()];
+        for section in &self.sections {
+            lines.push(format!(
+                "- [{}] {}: ~{}",
+                section.kind, section.name, section.tokens
+            ));
+        }
+        let total = self.total_tokens();
+        match self.model {
+            Some(caps) if caps.context_window > 0 => {
+                lines.push(format!(
+                    "Total: ~{total} of {} ({}% of the context window)",
+                    caps.context_window,
+                    total * 100 / caps.context_window
+                ));
+                if caps.input_cost_per_mtok > 0.0 {
+                    lines.push(format!(
+                        "Estimated input cost per call: ${:.4}",
+                        caps.estimate_cost(total, 0)
+                    ));
+                }
+            }
+            _ => lines.push(format!("Total: ~{total}")),
+        }
+        lines.join("\n")
+    }
+}
+
+// What a context provider knows about the turn being assembled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContextRequest<'a> {
+    pub channel: Option<&'a str>,
+    pub chat_id: Option<&'a str>,
+    pub message: &'a str,
+    // Guest or read-only channel; private sections should stay out.
+    pub read_only: bool,
+}
+
+// Extra system-prompt section (calendar, open tasks, weather, alerts...).
+// Returning None or an empty string skips the section for this turn.
+pub trait ContextProvider: Send + Sync {
+    fn name(&self) -> &str;
+    fn title(&self) -> &str;
+    fn render(&self, request: &ContextRequest<'_>) -> Option<String>;
+}
+
+// Lower order renders first; max_tokens 0 means no budget.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SectionOptions {
+    pub order: i32,
+    pub max_tokens: usize,
+}
+
+struct RegisteredProvider {
+    provider: Arc<dyn ContextProvider>,
+    options: SectionOptions,
+}
 
 pub struct ContextBuilder {
     workspace: PathBuf,
     memory: MemoryStore,
     skills: SkillsLoader,
+    providers: RwLock<Vec<RegisteredProvider>>,
+    section_config: HashMap<String, ContextSectionConfig>,
+    personas: Vec<PersonaRule>,
 }
 
 impl ContextBuilder {
@@ -19,10 +117,119 @@ impl ContextBuilder {
             workspace,
             memory,
             skills,
+            providers: RwLock::new(Vec::new()),
+            section_config: HashMap::new(),
+            personas: Vec::new(),
         })
     }
 
+    // Per-section overrides from config, keyed by provider name.
+    pub fn set_section_config(&mut self, sections: HashMap<String, ContextSectionConfig>) {
+        self.section_config = sections;
+    }
+
+    pub fn set_personas(&mut self, personas: Vec<PersonaRule>) {
+        for rule in &personas {
+            let bad_time = rule.when.as_ref().is_some_and(|when| {
+                [&when.start, &when.end].iter().any(|time| {
+                    !time.trim().is_empty()
+                        && chrono::NaiveTime::parse_from_str(time.trim(), "%H:%M").is_err()
+                })
+            });
+            if bad_time {
+                eprintln!(
+                    "Warning: persona '{}' has a start/end that is not HH:MM; it will apply all day",
+                    rule.name
+                );
+            }
+        }
+        self.personas = personas;
+    }
+
+    // The persona in force at `now` and its instructions; rules whose text
+    // is empty (or whose file is missing) are passed over.
+    pub fn active_persona(&self, now: NaiveDateTime) -> Option<(String, String)> {
+        self.personas
+            .iter()
+            .filter(|rule| {
+                rule.when
+                    .as_ref()
+                    .is_none_or(|when| persona_window_open(when, now))
+            })
+            .find_map(|rule| {
+                let text = match &rule.file {
+                    Some(file) => std::fs::read_to_string(self.workspace.join(file)).ok()?,
+                    None => rule.prompt.clone(),
+                };
+                let text = text.trim();
+                (!text.is_empty()).then(|| (rule.name.clone(), text.to_string()))
+            })
+    }
+
+    // Registering a provider under an existing name replaces it.
+    pub fn register_provider(&self, provider: Arc<dyn ContextProvider>, options: SectionOptions) {
+        let Ok(mut providers) = self.providers.write() else {
+            return;
+        };
+        providers.retain(|p| p.provider.name() != provider.name());
+        providers.push(RegisteredProvider { provider, options });
+    }
+
     pub fn build_system_prompt(&self, skill_names: Option<&[String]>) -> String {
+        self.build_system_prompt_with_report(
+            skill_names,
+            &ContextRequest::default(),
+            &mut ContextReport::default(),
+        )
+    }
+
+    fn provider_sections(
+        &self,
+        request: &ContextRequest<'_>,
+        report: &mut ContextReport,
+    ) -> Vec<String> {
+        let Ok(providers) = self.providers.read() else {
+            return Vec::new();
+        };
+        let mut active = providers
+            .iter()
+            .filter_map(|registered| {
+                let name = registered.provider.name();
+                let mut options = registered.options;
+                if let Some(config) = self.section_config.get(name) {
+                    if !config.enabled {
+                        return None;
+                    }
+                    options.order = config.order.unwrap_or(options.order);
+                    options.max_tokens = config.max_tokens.unwrap_or(options.max_tokens);
+                }
+                Some((options, registered.provider.clone()))
+            })
+            .collect::<Vec<_>>();
+        active.sort_by(|(a, pa), (b, pb)| a.order.cmp(&b.order).then(pa.name().cmp(pb.name())));
+
+        let mut sections = Vec::new();
+        for (options, provider) in active {
+            let Some(content) = provider
+                .render(request)
+                .map(|content| content.trim().to_string())
+                .filter(|content| !content.is_empty())
+            else {
+                continue;
+            };
+            let content = truncate_to_tokens(&content, options.max_tokens);
+            report.push("provider", provider.name(), &content);
+            sections.push(format!("# {}\n\n{content}", provider.title()));
+        }
+        sections
+    }
+
+    fn build_system_prompt_with_report(
+        &self,
+        skill_names: Option<&[String]>,
+        request: &ContextRequest<'_>,
+        report: &mut ContextReport,
+    ) -> String {
         let mut parts = Vec::new();
 
         let now = Local::now().format("%Y-%m-%d %H:%M (%A)").to_string();
@@ -36,15 +243,18 @@ impl ContextBuilder {
         };
         let runtime = format!("{} {}", std::env::consts::OS, std::env::consts::ARCH);
         let workspace = self.workspace.display().to_string();
-        parts.push(format!(
-            "# nanobot-rs\n\nYou are nanobot, a helpful AI assistant.\n\n## Current Time\n{now} ({tz})\n\n## Runtime\n{runtime}\n\n## Workspace\n{workspace}\n- Long-term memory: {workspace}/memory/MEMORY.md\n- History log: {workspace}/memory/HISTORY.md (grep-searchable)\n\nIMPORTANT: Respond directly in text for normal chat.\nOnly use the 'message' tool for proactive channel messages.\nAlways be helpful, accurate, and concise. When using tools, think step by step: what you know, what you need, and why you chose this tool.\nWhen remembering something important, write to {workspace}/memory/MEMORY.md\nTo recall past events, grep {workspace}/memory/HISTORY.md"
-        ));
+        let identity = format!(
+            "# nanobot-rs\n\nYou are nanobot, a helpful AI assistant.\n\n## Current Time\n{now} ({tz})\n\n## Runtime\n{runtime}\n\n## Workspace\n{workspace}\n- Long-term memory: {workspace}/memory/MEMORY.md\n- History log: {workspace}/memory/HISTORY.md (indexed by history_search)\n\nIMPORTANT: Respond directly in text for normal chat.\nOnly use the 'message' tool for proactive channel messages.\nAlways be helpful, accurate, and concise. When using tools, think step by step: what you know, what you need, and why you chose this tool.\nWhen remembering something important, write to {workspace}/memory/MEMORY.md\nTo recall past events or decisions, use the history_search tool"
+        );
+        report.push("runtime", "identity, time and workspace", &identity);
+        parts.push(identity);
 
         let bootstrap_files = ["AGENTS.md", "SOUL.md", "USER.md", "TOOLS.md", "IDENTITY.md"];
         let mut bootstrap_parts = Vec::new();
         for filename in bootstrap_files {
             let path = self.workspace.join(filename);
             if let Ok(content) = std::fs::read_to_string(&path) {
+                report.push("file", filename, &content);
                 bootstrap_parts.push(format!("## {filename}\n\n{content}"));
             }
         }
@@ -52,14 +262,21 @@ impl ContextBuilder {
             parts.push(bootstrap_parts.join("\n\n"));
         }
 
+        // After SOUL.md so the time-of-day tone takes precedence over it.
+        if let Some((name, text)) = self.active_persona(Local::now().naive_local()) {
+            report.push("persona", &name, &text);
+            parts.push(format!("# Persona: {name}\n\n{text}"));
+        }
+
         let memory_context = self.memory.get_memory_context();
         if !memory_context.is_empty() {
+            report.push("memory", "memory/MEMORY.md", &memory_context);
             parts.push(format!("# Memory\n\n{memory_context}"));
         }
 
         let always_skills = self.skills.get_always_skills();
         if !always_skills.is_empty() {
-            let content = self.skills.load_skills_for_context(&always_skills);
+            let content = self.load_skills_with_report(&always_skills, "always", report);
             if !content.is_empty() {
                 parts.push(format!("# Active Skills\n\n{content}"));
             }
@@ -67,7 +284,7 @@ impl ContextBuilder {
 
         if let Some(skill_names) = skill_names {
             if !skill_names.is_empty() {
-                let content = self.skills.load_skills_for_context(skill_names);
+                let content = self.load_skills_with_report(skill_names, "requested", report);
                 if !content.is_empty() {
                     parts.push(format!("# Requested Skills\n\n{content}"));
                 }
@@ -76,28 +293,49 @@ impl ContextBuilder {
 
         let summary = self.skills.build_skills_summary();
         if !summary.is_empty() {
+            report.push("skills", "available skills summary", &summary);
             parts.push(format!(
                 "# Skills\n\nThe following skills extend your capabilities. To use a skill, read its SKILL.md file using the read_file tool.\n\n{summary}"
             ));
         }
 
+        parts.extend(self.provider_sections(request, report));
+
         parts.join("\n\n---\n\n")
     }
 
+    fn load_skills_with_report(
+        &self,
+        skill_names: &[String],
+        reason: &str,
+        report: &mut ContextReport,
+    ) -> String {
+        for name in skill_names {
+            let content = self
+                .skills
+                .load_skills_for_context(std::slice::from_ref(name));
+            if !content.is_empty() {
+                report.push("skill", &format!("{name} ({reason})"), &content);
+            }
+        }
+        self.skills.load_skills_for_context(skill_names)
+    }
+
     pub fn build_messages(
         &self,
         history: &[Value],
-        current_message: &str,
+        request: &ContextRequest<'_>,
         skill_names: Option<&[String]>,
-        channel: Option<&str>,
-        chat_id: Option<&str>,
         media: Option<&[String]>,
-    ) -> Vec<Value> {
-        let mut system_prompt = self.build_system_prompt(skill_names);
-        if let (Some(channel), Some(chat_id)) = (channel, chat_id) {
-            system_prompt.push_str(&format!(
-                "\n\n## Current Session\nChannel: {channel}\nChat ID: {chat_id}"
-            ));
+    ) -> (Vec<Value>, ContextReport) {
+        let mut report = ContextReport::default();
+        let current_message = request.message;
+        let mut system_prompt =
+            self.build_system_prompt_with_report(skill_names, request, &mut report);
+        if let (Some(channel), Some(chat_id)) = (request.channel, request.chat_id) {
+            let session = format!("\n\n## Current Session\nChannel: {channel}\nChat ID: {chat_id}");
+            report.push("runtime", "current session", &session);
+            system_prompt.push_str(&session);
         }
 
         let mut messages = Vec::new();
@@ -105,13 +343,26 @@ impl ContextBuilder {
             "role": "system",
             "content": system_prompt,
         }));
+        if !history.is_empty() {
+            let history_text = history
+                .iter()
+                .filter_map(|m| m.get("content").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n");
+            report.push(
+                "history",
+                &format!("{} messages", history.len()),
+                &history_text,
+            );
+        }
         messages.extend(history.iter().cloned());
         let user_content = build_user_content(current_message, media);
+        report.push("message", "current user message", current_message);
         messages.push(json!({
             "role": "user",
             "content": user_content,
         }));
-        messages
+        (messages, report)
     }
 
     pub fn add_tool_result(
@@ -152,6 +403,104 @@ impl ContextBuilder {
     }
 }
 
+// Persona windows may leave the times empty to mean whole days.
+fn persona_window_open(when: &DeliveryWindow, now: NaiveDateTime) -> bool {
+    if when.start.trim().is_empty() && when.end.trim().is_empty() {
+        let all_day = DeliveryWindow {
+            start: "00:00".to_string(),
+            end: "00:00".to_string(),
+            days: when.days.clone(),
+        };
+        return window_is_open(&all_day, now);
+    }
+    window_is_open(when, now)
+}
+
+// Window assumed when the model's is unknown.
+const FALLBACK_WINDOW_TOKENS: usize = 8_000;
+
+// Shrinks a turn that overflowed the model's context window: system sections
+// and tool results are cut to a fraction of the window, and every tool round
+// but the last is folded into one short summary. The last round stays intact
+// so its tool calls still pair with their results.
+pub fn compact_messages(messages: &[Value], context_window: usize) -> Vec<Value> {
+    let window = if context_window == 0 {
+        FALLBACK_WINDOW_TOKENS
+    } else {
+        context_window
+    };
+    let system_budget = window / 4;
+    let tool_budget = (window / 20).max(200);
+    fn role(message: &Value) -> &str {
+        message["role"].as_str().unwrap_or_default()
+    }
+    let first_user = messages
+        .iter()
+        .position(|m| role(m) == "user")
+        .unwrap_or(messages.len());
+    let last_round = messages
+        .iter()
+        .rposition(|m| role(m) == "assistant" && m.get("tool_calls").is_some())
+        .filter(|idx| *idx > first_user)
+        .unwrap_or(messages.len());
+
+    let mut compacted = Vec::new();
+    for message in &messages[..first_user] {
+        let mut message = message.clone();
+        if let Some(content) = message["content"].as_str() {
+            message["content"] = Value::String(truncate_to_tokens(content, system_budget));
+        }
+        compacted.push(message);
+    }
+    if let Some(user) = messages.get(first_user) {
+        compacted.push(user.clone());
+    }
+    let folded = messages
+        .get(first_user + 1..last_round)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|m| match role(m) {
+            "tool" => {
+                let name = m["name"].as_str().unwrap_or("tool");
+                let result = m["content"].as_str().unwrap_or_default();
+                let line = result.split_whitespace().collect::<Vec<_>>().join(" ");
+                Some(format!(
+                    "- {name}: {}",
+                    line.chars().take(160).collect::<String>()
+                ))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    if !folded.is_empty() {
+        compacted.push(json!({
+            "role": "assistant",
+            "content": format!(
+                "Earlier tool results in this turn, shortened to fit the context window:\n{}",
+                folded.join("\n")
+            ),
+        }));
+    }
+    for message in messages.get(last_round..).unwrap_or_default() {
+        let mut message = message.clone();
+        if role(&message) == "tool"
+            && let Some(content) = message["content"].as_str()
+        {
+            message["content"] = Value::String(truncate_to_tokens(content, tool_budget));
+        }
+        compacted.push(message);
+    }
+    compacted
+}
+
+fn truncate_to_tokens(content: &str, max_tokens: usize) -> String {
+    if max_tokens == 0 || estimate_tokens(content) <= max_tokens {
+        return content.to_string();
+    }
+    let kept = content.chars().take(max_tokens * 4).collect::<String>();
+    format!("{}\n... (truncated)", kept.trim_end())
+}
+
 fn build_user_content(text: &str, media: Option<&[String]>) -> Value {
     let Some(media_paths) = media else {
         return Value::String(text.to_string());
@@ -191,10 +540,154 @@ fn build_user_content(text: &str, media: Option<&[String]>) -> Value {
 
 #[cfg(test)]
 mod tests {
-    use super::build_user_content;
-    use serde_json::Value;
+    use super::{
+        ContextBuilder, ContextProvider, ContextReport, ContextRequest, SectionOptions,
+        build_user_content, compact_messages, estimate_tokens,
+    };
+    use crate::config::{ContextSectionConfig, DeliveryWindow, PersonaRule};
+    use chrono::NaiveDate;
+    use serde_json::{Value, json};
+    use std::collections::HashMap;
+    use std::sync::Arc;
     use uuid::Uuid;
 
+    fn cli_request(message: &str) -> ContextRequest<'_> {
+        ContextRequest {
+            channel: Some("cli"),
+            chat_id: Some("direct"),
+            message,
+            read_only: false,
+        }
+    }
+
+    #[test]
+    fn build_messages_reports_each_prompt_component() {
+        let workspace = std::env::temp_dir().join(format!("nanobot-rs-ctx-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(workspace.join("memory")).expect("create workspace");
+        std::fs::write(workspace.join("SOUL.md"), "Be kind.").expect("write soul");
+        std::fs::write(workspace.join("memory").join("MEMORY.md"), "Likes tea.")
+            .expect("write memory");
+
+        let builder = ContextBuilder::new(workspace.clone()).expect("builder");
+        let (messages, report) = builder.build_messages(&[], &cli_request("hello"), None, None);
+        let system = messages[0]["content"].as_str().unwrap_or_default();
+        assert!(system.contains("## SOUL.md\n\nBe kind."));
+        assert!(system.contains("# Memory\n\n## Long-term Memory\nLikes tea."));
+
+        let names = report
+            .sections
+            .iter()
+            .map(|section| section.name.as_str())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"SOUL.md"));
+        assert!(names.contains(&"memory/MEMORY.md"));
+        assert!(names.contains(&"current session"));
+        assert!(names.contains(&"current user message"));
+        assert!(report.render().contains("Total: ~"));
+
+        let _ = std::fs::remove_dir_all(workspace);
+    }
+
+    struct StaticProvider(&'static str, &'static str);
+
+    impl ContextProvider for StaticProvider {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn title(&self) -> &str {
+            self.0
+        }
+
+        fn render(&self, request: &ContextRequest<'_>) -> Option<String> {
+            Some(format!("{} for {}", self.1, request.chat_id.unwrap_or("?")))
+        }
+    }
+
+    #[test]
+    fn provider_sections_follow_order_budget_and_config() {
+        let workspace = std::env::temp_dir().join(format!("nanobot-rs-ctx-{}", Uuid::new_v4()));
+        let mut builder = ContextBuilder::new(workspace.clone()).expect("builder");
+        builder.set_section_config(HashMap::from([(
+            "alerts".to_string(),
+            ContextSectionConfig {
+                order: Some(-1),
+                ..Default::default()
+            },
+        )]));
+        builder.register_provider(
+            Arc::new(StaticProvider("calendar", "Standup at 10:00")),
+            SectionOptions::default(),
+        );
+        builder.register_provider(
+            Arc::new(StaticProvider("alerts", "Disk 91% full")),
+            SectionOptions {
+                order: 10,
+                max_tokens: 3,
+            },
+        );
+
+        let (messages, report) = builder.build_messages(&[], &cli_request("hi"), None, None);
+        let system = messages[0]["content"].as_str().unwrap_or_default();
+        let alerts = system.find("# alerts\n\nDisk 91% ful\n... (truncated)");
+        let calendar = system.find("# calendar\n\nStandup at 10:00 for direct");
+        assert!(alerts.is_some() && calendar.is_some());
+        assert!(alerts < calendar);
+        assert!(report.sections.iter().any(|s| s.kind == "provider"));
+
+        let _ = std::fs::remove_dir_all(workspace);
+    }
+
+    #[test]
+    fn compacts_an_overflowing_turn() {
+        let big = "x".repeat(40_000);
+        let call = |id: &str| {
+            json!({
+                "role": "assistant",
+                "content": "",
+                "tool_calls": [{ "id": id, "type": "function", "function": { "name": "read_file", "arguments": "{}" } }]
+            })
+        };
+        let result = |id: &str| json!({ "role": "tool", "tool_call_id": id, "name": "read_file", "content": big });
+        let messages = vec![
+            json!({ "role": "system", "content": big }),
+            json!({ "role": "user", "content": "summarize the logs" }),
+            call("a"),
+            result("a"),
+            json!({ "role": "user", "content": "Reflect on the results and decide next steps." }),
+            call("b"),
+            result("b"),
+        ];
+        let compacted = compact_messages(&messages, 8_000);
+        let roles = compacted
+            .iter()
+            .map(|m| m["role"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(roles, ["system", "user", "assistant", "assistant", "tool"]);
+        assert!(
+            compacted[2]["content"]
+                .as_str()
+                .unwrap()
+                .contains("- read_file: xxx")
+        );
+        assert_eq!(compacted[3]["tool_calls"][0]["id"], "b");
+        assert_eq!(compacted[4]["tool_call_id"], "b");
+        let total = compacted
+            .iter()
+            .map(|m| estimate_tokens(&m["content"].to_string()))
+            .sum::<usize>();
+        assert!(total < 8_000);
+        // Nothing to fold before the first tool round.
+        assert_eq!(compact_messages(&messages[..2], 0).len(), 2);
+    }
+
+    #[test]
+    fn estimate_tokens_rounds_up() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(ContextReport::default().total_tokens(), 0);
+    }
+
     #[test]
     fn build_user_content_returns_plain_text_without_media() {
         let value = build_user_content("hello", None);
@@ -215,4 +708,66 @@ mod tests {
 
         let _ = std::fs::remove_file(temp);
     }
+
+    #[test]
+    fn personas_follow_the_time_of_day() {
+        let workspace = std::env::temp_dir().join(format!("nanobot-rs-persona-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(workspace.join("personas")).expect("workspace");
+        std::fs::write(workspace.join("personas/weekend.md"), "Be playful.\n").expect("write");
+        let mut builder = ContextBuilder::new(workspace.clone()).expect("builder");
+        let window = |start: &str, end: &str, days: &[&str]| DeliveryWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+            days: days.iter().map(|day| day.to_string()).collect(),
+        };
+        builder.set_personas(vec![
+            PersonaRule {
+                name: "work".to_string(),
+                when: Some(window(
+                    "09:00",
+                    "18:00",
+                    &["mon", "tue", "wed", "thu", "fri"],
+                )),
+                prompt: "Be terse.".to_string(),
+                file: None,
+            },
+            PersonaRule {
+                name: "weekend".to_string(),
+                when: Some(window("", "", &["sat", "sun"])),
+                file: Some("personas/weekend.md".to_string()),
+                ..Default::default()
+            },
+            PersonaRule {
+                name: "relaxed".to_string(),
+                prompt: "Be warm.".to_string(),
+                ..Default::default()
+            },
+        ]);
+        // 2026-03-09 is a Monday.
+        let at = |day: u32, hour: u32| {
+            NaiveDate::from_ymd_opt(2026, 3, day)
+                .and_then(|date| date.and_hms_opt(hour, 0, 0))
+                .expect("valid time")
+        };
+        let name = |day, hour| builder.active_persona(at(day, hour)).map(|(name, _)| name);
+        assert_eq!(name(9, 10).as_deref(), Some("work"));
+        assert_eq!(name(9, 19).as_deref(), Some("relaxed"));
+        assert_eq!(
+            builder.active_persona(at(14, 10)),
+            Some(("weekend".to_string(), "Be playful.".to_string()))
+        );
+
+        let request = ContextRequest {
+            message: "hi",
+            ..Default::default()
+        };
+        let (_, report) = builder.build_messages(&[], &request, None, None);
+        assert!(
+            report
+                .sections
+                .iter()
+                .any(|section| section.kind == "persona")
+        );
+        let _ = std::fs::remove_dir_all(workspace);
+    }
 }
@@ -0,0 +1,168 @@
+use crate::bus::InboundMessage;
+use serde_json::{Map, Value};
+use std::path::Path;
+
+// Session message field saying whether a group message may feed long-term
+// memory. Messages without it (direct chats, older sessions) always may.
+const MEMORY_FIELD: &str = "memory";
+const SNIPPET_CHARS: usize = 80;
+
+pub const LOCKED_MEMORY_ERROR: &str = "Error: MEMORY.md only takes facts from other group members once the owner confirms them with \"remember this\"";
+
+// Tags the turn just stored (user message, then reply) with its sender and
+// whether it may reach MEMORY.md: the owner's may, other participants' wait
+// for "remember this".
+pub fn mark_turn(messages: &mut [Value], msg: &InboundMessage, cleared: bool) {
+    let [.., user, reply] = messages else {
+        return;
+    };
+    if let Some(fields) = user.as_object_mut() {
+        fields.insert("sender".to_string(), Value::String(msg.sender_id.clone()));
+        if let Some(id) = &msg.message_id {
+            fields.insert("message_id".to_string(), Value::String(id.clone()));
+        }
+    }
+    for message in [user, reply] {
+        if let Some(fields) = message.as_object_mut() {
+            fields.insert(MEMORY_FIELD.to_string(), Value::Bool(cleared));
+        }
+    }
+}
+
+pub fn is_remember_command(content: &str) -> bool {
+    let content = content
+        .trim()
+        .trim_end_matches(['.', '!'])
+        .to_ascii_lowercase();
+    matches!(content.as_str(), "remember this" | "/remember")
+}
+
+fn cleared(message: &Value) -> bool {
+    message.get(MEMORY_FIELD).and_then(Value::as_bool) != Some(false)
+}
+
+// Clears the message the owner replied to, or else the latest one still
+// waiting, together with the assistant's answer to it. Returns the sender
+// and the start of the message.
+pub fn approve(messages: &mut [Value], reply_to: Option<&str>) -> Option<(String, String)> {
+    let idx = reply_to
+        .and_then(|id| {
+            messages
+                .iter()
+                .position(|m| m.get("message_id").and_then(Value::as_str) == Some(id))
+        })
+        .filter(|&idx| !cleared(&messages[idx]))
+        .or_else(|| {
+            messages
+                .iter()
+                .rposition(|m| !cleared(m) && m.get("role").and_then(Value::as_str) == Some("user"))
+        })?;
+    let answered = messages
+        .get(idx + 1)
+        .is_some_and(|m| m.get("role").and_then(Value::as_str) == Some("assistant"));
+    let end = if answered { idx + 2 } else { idx + 1 };
+    for message in &mut messages[idx..end] {
+        if let Some(fields) = message.as_object_mut()
+            && fields.contains_key(MEMORY_FIELD)
+        {
+            fields.insert(MEMORY_FIELD.to_string(), Value::Bool(true));
+        }
+    }
+    let message = &messages[idx];
+    let sender = message
+        .get("sender")
+        .and_then(Value::as_str)
+        .unwrap_or("someone")
+        .to_string();
+    let content = message
+        .get("content")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let mut snippet = content.chars().take(SNIPPET_CHARS).collect::<String>();
+    if content.chars().count() > SNIPPET_CHARS {
+        snippet.push('…');
+    }
+    Some((sender, snippet))
+}
+
+// What memory consolidation may read: everything except group messages
+// nobody confirmed.
+pub fn consolidation_input(messages: &[Value]) -> Vec<Value> {
+    messages.iter().filter(|m| cleared(m)).cloned().collect()
+}
+
+// write_file/edit_file aimed at memory/MEMORY.md.
+pub fn writes_long_term_memory(tool: &str, arguments: &Map<String, Value>) -> bool {
+    if !matches!(tool, "write_file" | "edit_file") {
+        return false;
+    }
+    let Some(path) = arguments.get("path").and_then(Value::as_str) else {
+        return false;
+    };
+    let path = Path::new(path);
+    path.file_name().is_some_and(|name| name == "MEMORY.md")
+        && path
+            .parent()
+            .and_then(Path::file_name)
+            .is_some_and(|dir| dir == "memory")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keeps_unconfirmed_group_messages_out_of_memory() {
+        let mut owner = InboundMessage::new("telegram", "owner", "-100", "I moved to Lyon");
+        owner.message_id = Some("1".to_string());
+        let mut bob = InboundMessage::new("telegram", "bob", "-100", "My PIN is 4321");
+        bob.message_id = Some("2".to_string());
+        let mut carol = InboundMessage::new("telegram", "carol", "-100", "I'm allergic to nuts");
+        carol.message_id = Some("4".to_string());
+
+        let mut messages = vec![json!({ "role": "user", "content": "from a direct chat" })];
+        for (msg, cleared) in [(&owner, true), (&bob, false), (&carol, false)] {
+            messages.push(json!({ "role": "user", "content": msg.content }));
+            messages.push(json!({ "role": "assistant", "content": "ok" }));
+            mark_turn(&mut messages, msg, cleared);
+        }
+        assert!(messages[0].get(MEMORY_FIELD).is_none());
+
+        let input = consolidation_input(&messages);
+        assert_eq!(input.len(), 3);
+        assert_eq!(input[1]["content"], "I moved to Lyon");
+
+        // A reply picks its message; otherwise the latest waiting one.
+        let (sender, snippet) = approve(&mut messages, Some("2")).expect("bob's message");
+        assert_eq!(
+            (sender.as_str(), snippet.as_str()),
+            ("bob", "My PIN is 4321")
+        );
+        assert_eq!(consolidation_input(&messages).len(), 5);
+        let (sender, _) = approve(&mut messages, None).expect("carol's message");
+        assert_eq!(sender, "carol");
+        assert!(approve(&mut messages, None).is_none());
+        assert_eq!(consolidation_input(&messages).len(), messages.len());
+
+        assert!(is_remember_command(" Remember this!"));
+        assert!(!is_remember_command("remember this: buy milk"));
+        let args = |path: &str| json!({ "path": path }).as_object().cloned().unwrap();
+        assert!(writes_long_term_memory(
+            "edit_file",
+            &args("/ws/memory/MEMORY.md")
+        ));
+        assert!(writes_long_term_memory(
+            "write_file",
+            &args("memory/MEMORY.md")
+        ));
+        assert!(!writes_long_term_memory(
+            "write_file",
+            &args("notes/MEMORY.md")
+        ));
+        assert!(!writes_long_term_memory(
+            "read_file",
+            &args("memory/MEMORY.md")
+        ));
+    }
+}
@@ -1,28 +1,69 @@
-use crate::agent::context::ContextBuilder;
+use crate::abuse::{AbuseMonitor, Escalation, IncidentKind, Standing};
+use crate::agent::action_items::{
+    ACTION_ITEMS_SYSTEM_PROMPT, action_items_prompt, parse_action_items,
+};
+use crate::agent::citations::Citations;
+use crate::agent::commands;
+use crate::agent::context::{
+    ContextBuilder, ContextProvider, ContextReport, ContextRequest, SectionOptions,
+    compact_messages, estimate_tokens,
+};
+use crate::agent::group_memory::{self, LOCKED_MEMORY_ERROR};
+use crate::agent::status::{ActivityTracker, render_status};
 use crate::agent::subagent::SubagentManager;
+use crate::agent::summary::{SUMMARY_SYSTEM_PROMPT, SessionSummary, summary_prompt};
+use crate::agent::turn_error::{self, FailedTurns};
 use crate::agent::turn_guard::TurnGuard;
+use crate::batch::BatchService;
 use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
-use crate::config::WebSearchConfig;
+use crate::channels::format::channel_capabilities;
+use crate::config::{
+    ContextSectionConfig, ModelCapabilitiesOverride, PersonaRule, SubagentProfile, WebSearchConfig,
+};
 use crate::cron::CronService;
-use crate::memory::MemoryStore;
-use crate::providers::base::LLMProvider;
-use crate::session::SessionManager;
+use crate::cron::reminder::FiredReminders;
+use crate::events::{self, EventLevel};
+use crate::feedback::{FeedbackStore, TurnRecord};
+use crate::fulltext::HistoryIndex;
+use crate::memory::{ConsolidationRecord, MemoryStore};
+use crate::message_templates::MessageTemplates;
+use crate::metrics::MetricStore;
+use crate::pause::{self, PauseSwitch};
+use crate::providers::base::{
+    LLMProvider, LLMResponse, ProviderFactory, SamplingParams, context_overflow,
+};
+use crate::providers::models::{ModelRegistry, UNKNOWN_MODEL};
+use crate::session::{Session, SessionManager, transcript_lines};
+use crate::tasks::TaskStore;
+use crate::tools::base::Tool;
+use crate::tools::batch::BatchTool;
 use crate::tools::cron::CronTool;
-use crate::tools::filesystem::{EditFileTool, ListDirTool, ReadFileTool, WriteFileTool};
+use crate::tools::filesystem::{
+    EditFileTool, FileChanges, ListDirTool, ReadFileTool, WriteFileTool, full_diff,
+    render_diff_preview,
+};
+use crate::tools::history::HistorySearchTool;
 use crate::tools::http::HttpRequestTool;
 use crate::tools::message::MessageTool;
+use crate::tools::metrics::MetricTool;
 use crate::tools::registry::ToolRegistry;
+use crate::tools::remind::RemindTool;
 use crate::tools::sessions::{SessionsHistoryTool, SessionsListTool, SessionsSendTool};
 use crate::tools::shell::ExecTool;
 use crate::tools::spawn::SpawnTool;
+use crate::tools::status::AgentStatusTool;
+use crate::tools::tasks::TaskTool;
 use crate::tools::web::{WebFetchTool, WebSearchTool};
+use crate::utils::get_data_path;
+use crate::workspace_git::{WorkspaceGit, change_message};
 use anyhow::{Context, Result};
 use chrono::Local;
-use serde_json::{Value, json};
-use std::path::PathBuf;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
-use tokio::time::{Duration, timeout};
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::time::{Duration, Instant, timeout};
 
 pub struct AgentLoop {
     bus: Arc<MessageBus>,
@@ -33,18 +74,60 @@ pub struct AgentLoop {
     memory_window: usize,
     context: ContextBuilder,
     sessions: Arc<SessionManager>,
-    tools: ToolRegistry,
+    tools: Arc<ToolRegistry>,
+    // Built-in tools switched off via `tools.disabled`, kept to switch back on.
+    disabled_tools: Mutex<HashMap<String, Arc<dyn Tool>>>,
     message_tool: Arc<MessageTool>,
     sessions_send_tool: Arc<SessionsSendTool>,
     spawn_tool: Arc<SpawnTool>,
     cron_tool: Option<Arc<CronTool>>,
+    remind_tool: Option<Arc<RemindTool>>,
+    cron: Option<Arc<CronService>>,
+    batch_tool: Option<Arc<BatchTool>>,
+    task_tool: Arc<TaskTool>,
+    task_store: TaskStore,
+    pause: PauseSwitch,
+    // Messages whose turn failed, for /retry.
+    failed_turns: FailedTurns,
+    feedback: FeedbackStore,
     subagents: Arc<SubagentManager>,
+    read_only_channels: Vec<String>,
+    owners: Vec<String>,
+    extract_action_items: bool,
+    sampling: SamplingParams,
+    maintenance_sampling: SamplingParams,
+    last_context: Mutex<HashMap<String, ContextReport>>,
+    provider_factory: Option<ProviderFactory>,
+    file_changes: FileChanges,
+    activity: ActivityTracker,
+    model_providers: Mutex<HashMap<String, Arc<dyn LLMProvider>>>,
+    models: ModelRegistry,
+    abuse: Option<AbuseMonitor>,
+    workspace_git: Option<WorkspaceGit>,
+    // Whether replies get a sources list; per-channel settings win.
+    citations: bool,
+    citation_channels: HashMap<String, bool>,
+    // Answer "REMIND:"/"TASK:" lines directly instead of running a turn.
+    structured_commands: bool,
+    // Tool calls refused during the current turn, reported to `abuse`.
+    refused_tool_calls: AtomicU32,
+    // Group messages from anyone but an owner stay out of MEMORY.md until
+    // an owner says "remember this"; set while such a turn runs.
+    confirm_group_memory: bool,
+    memory_locked: AtomicBool,
     running: AtomicBool,
 }
 
 impl AgentLoop {
-    fn available_tools_text(&self) -> String {
-        let mut tool_names = self.tools.tool_names();
+    fn available_tools_text(&self, model: &str, read_only: bool) -> String {
+        if !self.models.lookup(model).tool_calling {
+            return "(none)".to_string();
+        }
+        let mut tool_names = if read_only {
+            self.tools.read_only_tool_names()
+        } else {
+            self.tools.tool_names()
+        };
         tool_names.sort();
         if tool_names.is_empty() {
             "(none)".to_string()
@@ -53,16 +136,56 @@ impl AgentLoop {
         }
     }
 
-    fn runtime_facts_message(&self) -> serde_json::Value {
-        let tools_text = self.available_tools_text();
+    fn tool_definitions(&self, model: &str, read_only: bool) -> Vec<Value> {
+        if !self.models.lookup(model).tool_calling {
+            Vec::new()
+        } else if read_only {
+            self.tools.get_read_only_definitions()
+        } else {
+            self.tools.get_definitions()
+        }
+    }
+
+    async fn execute_tool(
+        &self,
+        name: &str,
+        arguments: &Map<String, Value>,
+        read_only: bool,
+    ) -> String {
+        if read_only && !self.tools.is_read_only(name) {
+            self.refused_tool_calls.fetch_add(1, Ordering::Relaxed);
+            return format!("Error: Tool '{name}' is disabled on this read-only channel");
+        }
+        if self.memory_locked.load(Ordering::Relaxed)
+            && group_memory::writes_long_term_memory(name, arguments)
+        {
+            return LOCKED_MEMORY_ERROR.to_string();
+        }
+        let result = self.tools.execute(name, arguments).await;
+        if result.starts_with("Error: Command blocked by safety guard") {
+            self.refused_tool_calls.fetch_add(1, Ordering::Relaxed);
+        }
+        match self.bus.script_hooks() {
+            Some(hooks) => hooks.on_tool_result(name, arguments, result),
+            None => result,
+        }
+    }
+
+    fn runtime_facts_message(&self, model: &str, read_only: bool) -> serde_json::Value {
+        let tools_text = self.available_tools_text(model, read_only);
+        let mode_text = if read_only {
+            " This channel is read-only: you cannot modify files, run commands, schedule jobs, or message other chats. \
+        Answer questions directly and decline requests that need those actions."
+        } else {
+            ""
+        };
 
         json!({
             "role": "system",
             "content": format!(
                 "Runtime facts (authoritative): active model is '{model}'; available tools are: {tools}. \
         If a user asks for external actions (network/file/command/scheduling), do not claim tools are unavailable; call the matching tool directly. \
-        Focus on the current user message only; do not summarize prior tasks unless explicitly requested.",
-                model = self.model,
+        Focus on the current user message only; do not summarize prior tasks unless explicitly requested.{mode_text}",
                 tools = tools_text
             )
         })
@@ -70,24 +193,239 @@ impl AgentLoop {
 
     fn build_turn_messages(
         &self,
+        session_key: &str,
+        model: &str,
         history: &[Value],
-        current_message: &str,
-        channel: &str,
-        chat_id: &str,
+        request: &ContextRequest<'_>,
         media: Option<&[String]>,
     ) -> Vec<Value> {
-        let mut messages = self.context.build_messages(
-            history,
-            current_message,
-            None,
-            Some(channel),
-            Some(chat_id),
-            media,
+        let read_only = request.read_only;
+        let (mut messages, mut report) = self.context.build_messages(history, request, None, media);
+        let runtime_facts = self.runtime_facts_message(model, read_only);
+        report.push(
+            "runtime",
+            "runtime facts",
+            runtime_facts["content"].as_str().unwrap_or_default(),
+        );
+        let tool_defs = self.tool_definitions(model, read_only);
+        report.push(
+            "tools",
+            &format!("{} tool definitions", tool_defs.len()),
+            &Value::Array(tool_defs).to_string(),
         );
-        messages.insert(1, self.runtime_facts_message());
+        report.model = Some(self.models.lookup(model));
+        messages.insert(1, runtime_facts);
+        if let Ok(mut guard) = self.last_context.lock() {
+            guard.insert(session_key.to_string(), report);
+        }
         messages
     }
 
+    fn session_provider(&self, session: &Session) -> (Arc<dyn LLMProvider>, String) {
+        let Some(model) = session
+            .metadata
+            .get("model")
+            .and_then(Value::as_str)
+            .filter(|model| !model.is_empty() && *model != self.model)
+        else {
+            return (self.provider.clone(), self.model.clone());
+        };
+        match self.provider_for_model(model) {
+            Ok(provider) => (provider, model.to_string()),
+            Err(err) => {
+                eprintln!("Warning: session model '{model}' unavailable, using default: {err}");
+                (self.provider.clone(), self.model.clone())
+            }
+        }
+    }
+
+    // Per-turn `model` and `sampling` overrides from the message metadata.
+    // Like `/model`, they are the owner's: anyone else, and any read-only
+    // turn, runs on the session's settings.
+    fn turn_overrides<'a>(&self, msg: &'a InboundMessage) -> (Option<&'a str>, Option<&'a Value>) {
+        if !self.is_owner(msg) || self.is_read_only(msg) {
+            return (None, None);
+        }
+        let model = msg
+            .metadata
+            .get("model")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|model| !model.is_empty());
+        (model, msg.metadata.get("sampling"))
+    }
+
+    // A `model` override replaces the session model for this turn only. It is
+    // checked against the capability registry, together with any `sampling`
+    // overrides, before a request goes out.
+    fn turn_provider(
+        &self,
+        msg: &InboundMessage,
+        session: &Session,
+    ) -> Result<(Arc<dyn LLMProvider>, String)> {
+        let (requested, sampling) = self.turn_overrides(msg);
+        let (provider, model) = match requested {
+            None => self.session_provider(session),
+            Some(model) if model == self.model => (self.provider.clone(), self.model.clone()),
+            Some(model) => {
+                if !self.models.is_known(model) {
+                    anyhow::bail!(
+                        "model '{model}' is not in the capability registry; describe it under `models` in config.json"
+                    );
+                }
+                if !msg.media.is_empty() && !self.models.lookup(model).vision {
+                    anyhow::bail!("model '{model}' does not accept images");
+                }
+                let provider = self
+                    .provider_for_model(model)
+                    .with_context(|| format!("model '{model}' is unavailable"))?;
+                (provider, model.to_string())
+            }
+        };
+        SamplingParams::check_overrides(sampling, &self.models.lookup(&model))
+            .map_err(|err| anyhow::anyhow!("invalid sampling for {model}: {err}"))?;
+        Ok((provider, model))
+    }
+
+    // A request that overflows the context window is compacted and retried
+    // once instead of failing the turn. The event tells owners how far over
+    // they were, so they can lower the memory window or section budgets.
+    async fn chat_recovering_overflow(
+        &self,
+        provider: &dyn LLMProvider,
+        messages: &mut Vec<Value>,
+        tools: Option<&[Value]>,
+        model: &str,
+        sampling: &SamplingParams,
+        session_key: &str,
+    ) -> Result<LLMResponse> {
+        let result = provider
+            .chat_with_sampling(messages, tools, Some(model), sampling)
+            .await;
+        let Some(error) = context_overflow(&result) else {
+            return result;
+        };
+        let tokens = |messages: &[Value]| {
+            messages
+                .iter()
+                .map(|m| estimate_tokens(&m.to_string()))
+                .sum::<usize>()
+        };
+        let before = tokens(messages);
+        *messages = compact_messages(messages, self.models.lookup(model).context_window);
+        let after = tokens(messages);
+        let channel = session_key.split_once(':').map(|(channel, _)| channel);
+        events::record(
+            EventLevel::Warn,
+            "context_overflow",
+            channel,
+            format!(
+                "{session_key}: {model} rejected ~{before} tokens, retrying with ~{after}; consider a smaller agents.defaults.memoryWindow or contextSections budgets ({})",
+                error.chars().take(200).collect::<String>()
+            ),
+        );
+        provider
+            .chat_with_sampling(messages, tools, Some(model), sampling)
+            .await
+    }
+
+    fn provider_for_model(&self, model: &str) -> Result<Arc<dyn LLMProvider>> {
+        if let Some(provider) = self
+            .model_providers
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(model).cloned())
+        {
+            return Ok(provider);
+        }
+        let factory = self
+            .provider_factory
+            .as_ref()
+            .context("model switching is not available in this mode")?;
+        let provider = factory(model)?;
+        if let Ok(mut cache) = self.model_providers.lock() {
+            cache.insert(model.to_string(), provider.clone());
+        }
+        Ok(provider)
+    }
+
+    fn switch_session_model(&self, session: &mut Session, requested: &str) -> Result<String> {
+        let current = session
+            .metadata
+            .get("model")
+            .and_then(Value::as_str)
+            .unwrap_or(&self.model)
+            .to_string();
+        if requested.is_empty() {
+            return Ok(format!(
+                "Current model: {current} (default: {})",
+                self.model
+            ));
+        }
+        if requested.eq_ignore_ascii_case("default") || requested == self.model {
+            session.metadata.remove("model");
+            self.sessions.save(session)?;
+            return Ok(format!("Switched this session back to {}.", self.model));
+        }
+        if let Err(err) = self.provider_for_model(requested) {
+            return Ok(format!("Cannot switch to {requested}: {err}"));
+        }
+        session
+            .metadata
+            .insert("model".to_string(), Value::String(requested.to_string()));
+        self.sessions.save(session)?;
+        if !self.models.is_known(requested) {
+            return Ok(format!(
+                "Switched this session to {requested}. Its capabilities are unknown, so a {}-token context window without vision is assumed; describe it under `models` in config.json to change that.",
+                UNKNOWN_MODEL.context_window
+            ));
+        }
+        Ok(format!("Switched this session to {requested}."))
+    }
+
+    fn attach_file_changes(&self, outbound: &mut OutboundMessage) {
+        let changes = self.file_changes.take();
+        if changes.is_empty() {
+            return;
+        }
+        if self.workspace_git.is_some() {
+            let paths = changes
+                .iter()
+                .map(|change| change.path.clone())
+                .collect::<Vec<_>>();
+            let added = changes.iter().map(|change| change.added).sum();
+            let removed = changes.iter().map(|change| change.removed).sum();
+            let session = format!("{}:{}", outbound.channel, outbound.chat_id);
+            self.commit_workspace(&paths, &change_message(&paths, added, removed, &session));
+        }
+        let (preview, truncated) = render_diff_preview(&changes);
+        outbound.content = format!("{}\n\n{preview}", outbound.content);
+        if truncated {
+            let path = get_data_path().map(|dir| {
+                dir.join("diffs")
+                    .join(format!("{}.diff", Local::now().format("%Y%m%d-%H%M%S")))
+            });
+            match path.and_then(|path| {
+                std::fs::create_dir_all(path.parent().unwrap_or(&self.workspace))?;
+                std::fs::write(&path, full_diff(&changes))?;
+                Ok(path)
+            }) {
+                // Channels that can't upload the file get its path instead.
+                Ok(path) if channel_capabilities(&outbound.channel).attachments => {
+                    outbound.media.push(path.display().to_string())
+                }
+                Ok(path) => outbound
+                    .content
+                    .push_str(&format!("\n\nFull diff: {}", path.display())),
+                Err(err) => eprintln!("Warning: failed to save full diff: {err}"),
+            }
+        }
+    }
+
+    pub fn last_context_report(&self, session_key: &str) -> Option<ContextReport> {
+        self.last_context.lock().ok()?.get(session_key).cloned()
+    }
+
     fn extract_json_object(text: &str) -> Option<Value> {
         let trimmed = text.trim();
         if let Ok(value) = serde_json::from_str::<Value>(trimmed)
@@ -124,7 +462,7 @@ impl AgentLoop {
     ) -> Result<Self> {
         let context = ContextBuilder::new(workspace.clone())?;
         let sessions = session_manager.unwrap_or(Arc::new(SessionManager::new()?));
-        let mut tools = ToolRegistry::new();
+        let tools = ToolRegistry::new();
         let model_name = model.unwrap_or_else(|| provider.default_model().to_string());
 
         let allowed_dir = if restrict_to_workspace {
@@ -134,8 +472,13 @@ impl AgentLoop {
         };
 
         tools.register(Arc::new(ReadFileTool::new(allowed_dir.clone())));
-        tools.register(Arc::new(WriteFileTool::new(allowed_dir.clone())));
-        tools.register(Arc::new(EditFileTool::new(allowed_dir.clone())));
+        let file_changes = FileChanges::new();
+        tools.register(Arc::new(
+            WriteFileTool::new(allowed_dir.clone()).with_changes(file_changes.clone()),
+        ));
+        tools.register(Arc::new(
+            EditFileTool::new(allowed_dir.clone()).with_changes(file_changes.clone()),
+        ));
         tools.register(Arc::new(ListDirTool::new(allowed_dir.clone())));
         tools.register(Arc::new(ExecTool::new(
             exec_timeout_s,
@@ -148,10 +491,17 @@ impl AgentLoop {
         tools.register(Arc::new(WebFetchTool::new(50_000)));
         tools.register(Arc::new(HttpRequestTool::new(30, 50_000)));
 
-        let message_tool = Arc::new(MessageTool::new(bus.outbound_sender()));
+        let message_tool = Arc::new(
+            MessageTool::new(bus.outbound_sender())
+                .with_templates(MessageTemplates::new(&workspace)),
+        );
         tools.register(message_tool.clone());
         tools.register(Arc::new(SessionsListTool::new(sessions.clone())));
         tools.register(Arc::new(SessionsHistoryTool::new(sessions.clone())));
+        tools.register(Arc::new(HistorySearchTool::new(HistoryIndex::new(
+            sessions.clone(),
+            &workspace,
+        )?)));
         let sessions_send_tool = Arc::new(SessionsSendTool::new(bus.outbound_sender()));
         tools.register(sessions_send_tool.clone());
 
@@ -166,15 +516,41 @@ impl AgentLoop {
         ));
         let spawn_tool = Arc::new(SpawnTool::new(subagents.clone()));
         tools.register(spawn_tool.clone());
+        let activity = ActivityTracker::new();
+        tools.register(Arc::new(AgentStatusTool::new(
+            activity.clone(),
+            subagents.clone(),
+            bus.clone(),
+        )));
 
-        let cron_tool = if let Some(cron_service) = cron_service {
-            let tool = Arc::new(CronTool::new(cron_service));
+        let cron = cron_service.clone();
+        let (cron_tool, remind_tool) = if let Some(cron_service) = cron_service {
+            let tool = Arc::new(
+                CronTool::new(cron_service.clone())
+                    .with_templates(MessageTemplates::new(&workspace)),
+            );
             tools.register(tool.clone());
-            Some(tool)
+            let remind = Arc::new(RemindTool::new(cron_service, FiredReminders::new()?));
+            tools.register(remind.clone());
+            (Some(tool), Some(remind))
         } else {
-            None
+            (None, None)
         };
 
+        let task_store = TaskStore::new()?;
+        let task_tool = Arc::new(TaskTool::new(task_store.clone()));
+        tools.register(task_tool.clone());
+        tools.register(Arc::new(MetricTool::new(MetricStore::new(
+            workspace.join("metrics"),
+        ))));
+        context.register_provider(
+            Arc::new(task_store.clone()),
+            SectionOptions {
+                order: 100,
+                max_tokens: 500,
+            },
+        );
+
         Ok(Self {
             bus,
             provider: provider.clone(),
@@ -184,44 +560,400 @@ impl AgentLoop {
             memory_window,
             context,
             sessions,
-            tools,
+            tools: Arc::new(tools),
+            disabled_tools: Mutex::new(HashMap::new()),
             message_tool,
             sessions_send_tool,
             spawn_tool,
             cron_tool,
+            remind_tool,
+            cron,
+            batch_tool: None,
+            task_tool,
+            task_store,
+            pause: PauseSwitch::new()?,
+            failed_turns: FailedTurns::default(),
+            feedback: FeedbackStore::new()?,
             subagents,
+            read_only_channels: Vec::new(),
+            owners: Vec::new(),
+            extract_action_items: false,
+            sampling: SamplingParams::default(),
+            maintenance_sampling: SamplingParams {
+                max_tokens: 1200,
+                temperature: 0.0,
+                top_p: None,
+            },
+            last_context: Mutex::new(HashMap::new()),
+            provider_factory: None,
+            file_changes,
+            activity,
+            model_providers: Mutex::new(HashMap::new()),
+            models: ModelRegistry::default(),
+            abuse: None,
+            workspace_git: None,
+            citations: false,
+            citation_channels: HashMap::new(),
+            structured_commands: false,
+            refused_tool_calls: AtomicU32::new(0),
+            confirm_group_memory: false,
+            memory_locked: AtomicBool::new(false),
             running: AtomicBool::new(false),
         })
     }
 
+    // Channels whose turns get the read-only tool set.
+    pub fn with_read_only_channels(mut self, channels: Vec<String>) -> Self {
+        self.read_only_channels = channels;
+        self
+    }
+
+    // Pull commitments ("I'll send it Friday") into tasks after each turn.
+    pub fn with_action_item_extraction(mut self, enabled: bool) -> Self {
+        self.extract_action_items = enabled;
+        self
+    }
+
+    // Defaults for every turn and subagent; per-turn overrides still apply.
+    pub fn with_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.subagents.set_sampling(sampling);
+        self.sampling = sampling;
+        self
+    }
+
+    // Sampling for consolidation, summaries and action-item extraction.
+    pub fn with_maintenance_sampling(mut self, sampling: SamplingParams) -> Self {
+        self.maintenance_sampling = sampling;
+        self
+    }
+
+    pub fn with_provider_factory(mut self, factory: ProviderFactory) -> Self {
+        self.subagents.set_provider_factory(factory.clone());
+        self.provider_factory = Some(factory);
+        self
+    }
+
+    pub fn with_subagent_profiles(self, profiles: HashMap<String, SubagentProfile>) -> Self {
+        self.subagents.set_profiles(profiles);
+        self
+    }
+
+    pub fn with_batch_service(mut self, service: Arc<BatchService>) -> Self {
+        let tool = Arc::new(BatchTool::new(service));
+        self.tools.register(tool.clone());
+        self.batch_tool = Some(tool);
+        self
+    }
+
+    pub fn with_model_overrides(
+        mut self,
+        overrides: HashMap<String, ModelCapabilitiesOverride>,
+    ) -> Self {
+        self.models = ModelRegistry::new(overrides);
+        self
+    }
+
+    pub fn with_context_sections(
+        mut self,
+        sections: HashMap<String, ContextSectionConfig>,
+    ) -> Self {
+        self.context.set_section_config(sections);
+        self
+    }
+
+    pub fn with_personas(mut self, personas: Vec<PersonaRule>) -> Self {
+        self.context.set_personas(personas);
+        self
+    }
+
+    pub fn with_abuse_monitor(mut self, monitor: AbuseMonitor) -> Self {
+        self.abuse = Some(monitor);
+        self
+    }
+
+    pub fn with_git_versioning(mut self, enabled: bool) -> Self {
+        if !enabled {
+            return self;
+        }
+        match WorkspaceGit::open(&self.workspace) {
+            Ok(repo) => self.workspace_git = Some(repo),
+            Err(err) => eprintln!("Warning: workspace git versioning disabled: {err}"),
+        }
+        self
+    }
+
+    pub fn with_citations(mut self, enabled: bool, per_channel: HashMap<String, bool>) -> Self {
+        self.citations = enabled;
+        self.citation_channels = per_channel;
+        self
+    }
+
+    pub fn with_group_memory_confirmation(mut self, enabled: bool) -> Self {
+        self.confirm_group_memory = enabled;
+        self
+    }
+
+    pub fn with_structured_commands(mut self, enabled: bool) -> Self {
+        self.structured_commands = enabled;
+        self
+    }
+
+    fn cites_sources(&self, channel: &str) -> bool {
+        self.citation_channels
+            .get(channel)
+            .copied()
+            .unwrap_or(self.citations)
+    }
+
+    fn commit_workspace(&self, paths: &[String], message: &str) {
+        let Some(repo) = &self.workspace_git else {
+            return;
+        };
+        if let Err(err) = repo.commit_paths(paths, message) {
+            events::record(
+                EventLevel::Warn,
+                "workspace_git",
+                None,
+                format!("auto-commit failed: {err}"),
+            );
+        }
+    }
+
+    // Keeps turn records, tasks and the search index under `dir` instead of ~/.nanobot, for
+    // sandboxed runs such as `simulate`.
+    pub fn with_data_dir(mut self, dir: &Path) -> Self {
+        self.feedback = FeedbackStore::from_path(dir.join("feedback").join("turns.json"));
+        self.task_store = TaskStore::from_path(dir.join("tasks").join("tasks.json"));
+        self.pause = PauseSwitch::from_path(dir.join("paused.json"));
+        self.task_tool = Arc::new(TaskTool::new(self.task_store.clone()));
+        self.tools.register(self.task_tool.clone());
+        self.tools
+            .register(Arc::new(HistorySearchTool::new(HistoryIndex::from_path(
+                dir.join("search").join("index.json"),
+                self.sessions.clone(),
+                self.workspace.join("memory").join("HISTORY.md"),
+            ))));
+        self.context.register_provider(
+            Arc::new(self.task_store.clone()),
+            SectionOptions {
+                order: 100,
+                max_tokens: 500,
+            },
+        );
+        self
+    }
+
+    pub fn with_owners(mut self, owners: Vec<String>) -> Self {
+        self.owners = owners;
+        self
+    }
+
+    // Turns started from the CLI or internally (process_direct, sender "user")
+    // act as the owner; chat users only if listed in `channels.owners`.
+    fn is_owner(&self, msg: &InboundMessage) -> bool {
+        msg.channel == "cli"
+            || msg.sender_id == "user"
+            || self.owners.iter().any(|owner| {
+                owner == &msg.sender_id || *owner == format!("{}:{}", msg.channel, msg.sender_id)
+            })
+    }
+
+    fn is_muted(&self, msg: &InboundMessage) -> bool {
+        self.abuse
+            .as_ref()
+            .filter(|_| msg.channel != "system" && !self.is_owner(msg))
+            .is_some_and(|abuse| {
+                matches!(
+                    abuse.standing(&msg.channel, &msg.sender_id),
+                    Standing::Muted
+                )
+            })
+    }
+
+    // Read-only channels, and guests admitted through the pairing challenge,
+    // get the restricted tool set.
+    fn is_read_only(&self, msg: &InboundMessage) -> bool {
+        self.read_only_channels.contains(&msg.channel)
+            || msg.metadata.get("guest").and_then(Value::as_bool) == Some(true)
+    }
+
+    pub fn with_disabled_tools(self, names: &[String]) -> Self {
+        self.apply_disabled_tools(names);
+        self
+    }
+
+    // Shared handle for code that adds or removes tools while the agent runs.
+    pub fn tool_registry(&self) -> Arc<ToolRegistry> {
+        self.tools.clone()
+    }
+
+    // Makes `names` the set of switched-off tools: listed tools are removed
+    // from the registry and previously removed ones not listed come back.
+    // Returns (disabled, enabled) names.
+    pub fn apply_disabled_tools(&self, names: &[String]) -> (Vec<String>, Vec<String>) {
+        let Ok(mut parked) = self.disabled_tools.lock() else {
+            return (Vec::new(), Vec::new());
+        };
+        let mut disabled = Vec::new();
+        for name in names {
+            if let Some(tool) = self.tools.unregister(name) {
+                parked.insert(name.clone(), tool);
+                disabled.push(name.clone());
+            }
+        }
+        let restore = parked
+            .keys()
+            .filter(|name| !names.contains(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut enabled = Vec::new();
+        for name in restore {
+            if let Some(tool) = parked.remove(&name) {
+                self.tools.register(tool);
+                enabled.push(name);
+            }
+        }
+        (disabled, enabled)
+    }
+
+    pub fn register_context_provider(
+        &self,
+        provider: Arc<dyn ContextProvider>,
+        options: SectionOptions,
+    ) {
+        self.context.register_provider(provider, options);
+    }
+
     pub async fn run(&self) -> Result<()> {
         self.running.store(true, Ordering::Relaxed);
-        while self.running.load(Ordering::Relaxed) {
-            let message = timeout(Duration::from_secs(1), self.bus.consume_inbound()).await;
-            let Some(msg) = (match message {
-                Ok(v) => v,
-                Err(_) => continue,
-            }) else {
-                continue;
-            };
+        // Turns run one at a time, but `!busy` is answered straight from the
+        // dispatcher so it still works while a long turn is in flight.
+        let (work_tx, mut work_rx) = tokio::sync::mpsc::unbounded_channel::<InboundMessage>();
+        let dispatcher = async move {
+            while self.running.load(Ordering::Relaxed) {
+                let message = timeout(Duration::from_secs(1), self.bus.consume_inbound()).await;
+                let Some(msg) = (match message {
+                    Ok(v) => v,
+                    Err(_) => continue,
+                }) else {
+                    continue;
+                };
 
-            let response = match self.process_message(msg.clone(), None).await {
-                Ok(resp) => resp,
-                Err(err) => {
-                    let mut out = OutboundMessage::new(
-                        msg.channel.clone(),
-                        msg.chat_id.clone(),
-                        format!("Sorry, I encountered an error: {err}"),
-                    );
-                    out.metadata = msg.metadata.clone();
-                    out
+                if is_busy_command(&msg.content) {
+                    // Muted senders are dropped here too, not only in `handle_inbound`.
+                    if self.is_muted(&msg) {
+                        continue;
+                    }
+                    let read_only = self.is_read_only(&msg);
+                    let out = msg.reply(self.busy_report(&msg, read_only).await);
+                    let _ = self.bus.publish_outbound(out).await;
+                    continue;
                 }
-            };
-            let _ = self.bus.publish_outbound(response).await;
-        }
+                self.activity.queue();
+                if work_tx.send(msg).is_err() {
+                    break;
+                }
+            }
+        };
+        let worker = async {
+            while let Some(msg) = work_rx.recv().await {
+                self.activity.dequeue();
+                self.handle_inbound(msg).await;
+            }
+        };
+        tokio::join!(dispatcher, worker);
         Ok(())
     }
 
+    // Runs one turn and publishes the reply; `run` calls this for each
+    // queued message.
+    pub async fn handle_inbound(&self, msg: InboundMessage) {
+        let mut read_only = self.is_read_only(&msg);
+        let screened = self
+            .abuse
+            .as_ref()
+            .filter(|_| msg.channel != "system" && !self.is_owner(&msg));
+        if let Some(abuse) = screened {
+            if let Some((kind, detail)) = abuse.scan(&msg.content) {
+                self.report_abuse(abuse, &msg, kind, detail).await;
+            }
+            match abuse.standing(&msg.channel, &msg.sender_id) {
+                Standing::Muted => return,
+                Standing::Demoted => read_only = true,
+                Standing::Normal => {}
+            }
+        }
+        // A retry runs the failed message again as if it had just arrived.
+        let msg = match turn_error::parse_retry(&msg.content) {
+            Some(id) => match self.failed_turns.take(id, &msg, self.is_owner(&msg)) {
+                Some(failed) => failed,
+                None => {
+                    let reply = msg.reply("Nothing to retry: no failed message of yours here.");
+                    let _ = self.bus.publish_outbound(reply).await;
+                    return;
+                }
+            },
+            None => msg,
+        };
+        self.refused_tool_calls.store(0, Ordering::Relaxed);
+        let response = match self.process_message(msg.clone(), None, read_only).await {
+            Ok(resp) => resp,
+            Err(err) => {
+                events::record(
+                    EventLevel::Error,
+                    "turn",
+                    Some(&msg.channel),
+                    format!("{}: {err}", msg.session_key()),
+                );
+                let retry_id = self.failed_turns.remember(&msg);
+                turn_error::error_card(&msg, &format!("{err:#}"), &retry_id)
+            }
+        };
+        let refused = self.refused_tool_calls.swap(0, Ordering::Relaxed);
+        if let Some(abuse) = screened
+            && refused > 0
+        {
+            let detail = format!("{refused} tool call(s) refused");
+            self.report_abuse(abuse, &msg, IncidentKind::RejectedTool, detail)
+                .await;
+        }
+        let _ = self.bus.publish_outbound(response).await;
+    }
+
+    async fn report_abuse(
+        &self,
+        abuse: &AbuseMonitor,
+        msg: &InboundMessage,
+        kind: IncidentKind,
+        detail: String,
+    ) {
+        let incident = abuse.record(msg, kind, detail);
+        events::record(
+            EventLevel::Warn,
+            "abuse",
+            Some(&msg.channel),
+            incident.describe(),
+        );
+        if matches!(
+            incident.escalation,
+            Some(Escalation::Notify | Escalation::Mute)
+        ) && let Some((channel, chat_id)) = abuse.notify_target()
+        {
+            let alert = format!("⚠️ Abuse alert: {}", incident.describe());
+            let _ = self
+                .bus
+                .publish_outbound(OutboundMessage::new(channel, chat_id, alert))
+                .await;
+        }
+    }
+
+    async fn busy_report(&self, msg: &InboundMessage, read_only: bool) -> String {
+        if read_only || !self.is_owner(msg) {
+            return "Only the owner can see what the agent is working on.".to_string();
+        }
+        render_status(&self.activity, &self.subagents, &self.bus).await
+    }
+
     pub fn stop(&self) {
         self.running.store(false, Ordering::Relaxed);
     }
@@ -230,6 +962,7 @@ impl AgentLoop {
         &self,
         msg: InboundMessage,
         session_key: Option<&str>,
+        read_only: bool,
     ) -> Result<OutboundMessage> {
         if msg.channel == "system" {
             return self.process_system_message(msg).await;
@@ -247,24 +980,121 @@ impl AgentLoop {
             session.messages.clear();
             self.sessions.save(&session)?;
 
-            let mut outbound = OutboundMessage::new(
-                msg.channel,
-                msg.chat_id,
-                "🐈 New session started. Memory consolidated.".to_string(),
-            );
-            outbound.metadata = msg.metadata;
-            return Ok(outbound);
+            return Ok(msg.reply("🐈 New session started. Memory consolidated."));
+        }
+        if cmd == "/summary" {
+            let summary = self.summarize_session(&session.key).await?;
+            return Ok(msg.reply(summary.render()));
+        }
+        if cmd == "/model" || cmd.starts_with("/model ") {
+            // The model sets the session's cost, so only the owner picks it.
+            let content = if read_only || !self.is_owner(&msg) {
+                "Only the owner can switch the model.".to_string()
+            } else {
+                let requested = msg.content.trim()["/model".len()..].trim();
+                self.switch_session_model(&mut session, requested)?
+            };
+            return Ok(msg.reply(content));
+        }
+        if is_busy_command(&cmd) {
+            let content = self.busy_report(&msg, read_only).await;
+            return Ok(msg.reply(content));
+        }
+        if cmd == "/debug context" {
+            // The report holds workspace files and memory: owner only.
+            let content = if read_only || !self.is_owner(&msg) {
+                "Only the owner can see the prompt report.".to_string()
+            } else {
+                self.last_context_report(&session.key)
+                    .map(|report| report.render())
+                    .unwrap_or_else(|| {
+                        "No turn has been processed in this session yet.".to_string()
+                    })
+            };
+            return Ok(msg.reply(content));
+        }
+        if cmd == "/snooze" || cmd.starts_with("/snooze ") {
+            let mut args = cmd["/snooze".len()..].split_whitespace();
+            let content = match (&self.remind_tool, args.next()) {
+                _ if read_only => {
+                    "Reminders can't be snoozed on this read-only channel.".to_string()
+                }
+                (None, _) => "Reminders are not available here.".to_string(),
+                (Some(_), None) => {
+                    "Usage: /snooze <reminder id> [10m|1h|tomorrow|HH:MM]".to_string()
+                }
+                (Some(tool), Some(id)) => {
+                    let when = args.collect::<Vec<_>>().join(" ");
+                    let when = if when.is_empty() { "10m" } else { &when };
+                    tool.snooze(id, when, &msg, self.is_owner(&msg)).await?
+                }
+            };
+            return Ok(msg.reply(content));
+        }
+        if cmd == "/pause" || cmd.starts_with("/pause ") || cmd == "/resume" {
+            let content = if read_only || !self.is_owner(&msg) {
+                "Only the owner can pause or resume proactive messages.".to_string()
+            } else if cmd == "/resume" {
+                pause::resume_reply(&self.pause, chrono::Utc::now().timestamp_millis())?
+            } else {
+                let by = format!("{}:{}", msg.channel, msg.sender_id);
+                pause::pause_reply(&self.pause, &cmd["/pause".len()..], &by, Local::now())
+                    .unwrap_or_else(|err| {
+                        format!("{err}\nUsage: /pause [7d|tomorrow|YYYY-MM-DD HH:MM]")
+                    })
+            };
+            return Ok(msg.reply(content));
+        }
+        if self.structured_commands
+            && let Some(command) = commands::parse(commands::command_line(&msg), Local::now())
+        {
+            let content = if read_only {
+                "Commands are disabled on this read-only channel.".to_string()
+            } else {
+                commands::run(
+                    command,
+                    self.cron.as_deref(),
+                    &self.task_store,
+                    &msg,
+                    &session.key,
+                )
+                .await?
+            };
+            return Ok(msg.reply(content));
+        }
+        if self.confirm_group_memory
+            && msg.is_group()
+            && group_memory::is_remember_command(&msg.content)
+        {
+            let content = if !self.is_owner(&msg) {
+                "Only the owner can add messages from this group to long-term memory.".to_string()
+            } else if let Some((sender, snippet)) =
+                group_memory::approve(&mut session.messages, msg.reply_to.as_deref())
+            {
+                self.sessions.save(&session)?;
+                format!("🧠 \"{snippet}\" from {sender} will go into long-term memory.")
+            } else {
+                "Nothing from other participants is waiting to be remembered.".to_string()
+            };
+            return Ok(msg.reply(content));
         }
         if cmd == "/help" {
-            let mut outbound = OutboundMessage::new(
-                msg.channel,
-                msg.chat_id,
-                "🐈 nanobot commands:\n/new - Start a new conversation\n/help - Show available commands".to_string(),
-            );
-            outbound.metadata = msg.metadata;
-            return Ok(outbound);
+            return Ok(msg.reply("🐈 nanobot commands:\n/new - Start a new conversation\n/summary - Summarize this conversation\n/model [name] - Show or switch the model for this session\n/snooze <id> [when] - Push a reminder back (default 10m)\n/pause [until] - Stop heartbeat and scheduled jobs (/resume to restart)\n/retry - Run your last failed message again\n/debug context - Show what went into the last prompt\nREMIND: <text> <when> / TASK: <title> [due <when>] - Schedule without a full turn\n!busy - Show what the agent is working on\n/help - Show available commands"));
         }
 
+        let (provider, model) = match self.turn_provider(&msg, &session) {
+            Ok(selected) => selected,
+            Err(err) => {
+                events::record(
+                    EventLevel::Warn,
+                    "turn_override",
+                    Some(&msg.channel),
+                    format!("{}: {err}", session.key),
+                );
+                return Err(err);
+            }
+        };
+
         if session.messages.len() > self.memory_window {
             if let Err(err) = self.consolidate_memory(&mut session, false).await {
                 eprintln!("Warning: memory consolidation failed: {err}");
@@ -272,40 +1102,104 @@ impl AgentLoop {
         }
         self.message_tool
             .set_context(msg.channel.clone(), msg.chat_id.clone());
+        self.message_tool.set_read_only(read_only);
+        let third_party = self.confirm_group_memory && msg.is_group() && !self.is_owner(&msg);
+        self.memory_locked.store(third_party, Ordering::Relaxed);
+        self.message_tool.set_thread(msg.thread_id.clone());
+        // In a direct chat the sender is who a template reply addresses.
+        self.message_tool.set_recipient_name(
+            msg.metadata
+                .get("sender_name")
+                .and_then(Value::as_str)
+                .filter(|_| !msg.is_group())
+                .map(ToOwned::to_owned),
+        );
+        self.message_tool.set_proactive(
+            msg.metadata
+                .get("proactive")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        );
         self.sessions_send_tool
             .set_context(msg.channel.clone(), msg.chat_id.clone());
         self.spawn_tool
             .set_context(msg.channel.clone(), msg.chat_id.clone());
+        self.spawn_tool.set_thread(msg.thread_id.clone());
         if let Some(cron_tool) = &self.cron_tool {
             cron_tool.set_context(msg.channel.clone(), msg.chat_id.clone());
+            cron_tool.set_requester(msg.sender_id.clone(), self.is_owner(&msg));
         }
+        if let Some(remind_tool) = &self.remind_tool {
+            remind_tool.set_context(
+                msg.channel.clone(),
+                msg.chat_id.clone(),
+                msg.sender_id.clone(),
+            );
+        }
+        if let Some(batch_tool) = &self.batch_tool {
+            batch_tool.set_context(msg.channel.clone(), msg.chat_id.clone());
+        }
+        self.task_tool
+            .set_context(msg.channel.clone(), msg.chat_id.clone());
+        self.task_tool.set_owner(!read_only && self.is_owner(&msg));
+        // Drop edits left over from a turn that failed before replying.
+        self.file_changes.take();
 
+        // Deterministic anti-contamination: only current turn is sent to the model.
+        let history = session.get_history(0);
+        let content = attributed_content(&msg);
+        let turn = self.activity.begin_turn(&session.key);
+        let _typing = self.bus.begin_activity(&msg);
+        let started = Instant::now();
+        let caps = self.models.lookup(&model);
         let media = if msg.media.is_empty() {
             None
+        } else if !caps.vision {
+            eprintln!(
+                "Warning: {model} does not accept images; dropping {} attachment(s)",
+                msg.media.len()
+            );
+            None
         } else {
             Some(msg.media.as_slice())
         };
-        // Deterministic anti-contamination: only current turn is sent to the model.
-        let history = session.get_history(0);
+        let request = ContextRequest {
+            channel: Some(&msg.channel),
+            chat_id: Some(&msg.chat_id),
+            message: &content,
+            read_only,
+        };
         let mut messages =
-            self.build_turn_messages(&history, &msg.content, &msg.channel, &msg.chat_id, media);
+            self.build_turn_messages(&session.key, &model, &history, &request, media);
 
         let mut final_content: Option<String> = None;
         let mut retried_with_fresh_context = false;
         let mut tools_used: Vec<String> = Vec::new();
+        let mut citations = Citations::default();
         let mut iterations_run = 0u32;
+        let sampling = self
+            .sampling
+            .with_overrides(self.turn_overrides(&msg).1)
+            .capped_for(&caps);
         let turn_guard = TurnGuard::new(
-            self.provider.as_ref(),
-            &self.model,
-            self.available_tools_text(),
+            provider.as_ref(),
+            &model,
+            self.available_tools_text(&model, read_only),
             self.max_iterations,
         );
         for iteration in 1..=self.max_iterations {
             iterations_run = iteration;
-            let tool_defs = self.tools.get_definitions();
+            turn.set_stage(format!("calling {model} (iteration {iteration})"));
+            let tool_defs = self.tool_definitions(&model, read_only);
             let response = self
-                .provider
-                .chat(&messages, Some(&tool_defs), Some(&self.model), 4096, 0.7)
+                .chat_recovering_overflow(
+                    provider.as_ref(),
+                    &mut messages,
+                    (!tool_defs.is_empty()).then_some(tool_defs.as_slice()),
+                    &model,
+                    &sampling,
+                    &session.key,
+                )
                 .await?;
 
             if response.has_tool_calls() {
@@ -331,11 +1225,12 @@ impl AgentLoop {
                 );
 
                 for tool_call in response.tool_calls {
+                    turn.set_stage(format!("running tool {}", tool_call.name));
                     tools_used.push(tool_call.name.clone());
                     let result = self
-                        .tools
-                        .execute(&tool_call.name, &tool_call.arguments)
+                        .execute_tool(&tool_call.name, &tool_call.arguments, read_only)
                         .await;
+                    citations.record(&tool_call.name, &tool_call.arguments, &result);
                     self.context.add_tool_result(
                         &mut messages,
                         &tool_call.id,
@@ -353,13 +1248,8 @@ impl AgentLoop {
                     .await
                 {
                     if !retried_with_fresh_context {
-                        messages = self.build_turn_messages(
-                            &[],
-                            &msg.content,
-                            &msg.channel,
-                            &msg.chat_id,
-                            media,
-                        );
+                        messages =
+                            self.build_turn_messages(&session.key, &model, &[], &request, media);
                         messages.push(turn_guard.correction_message());
                         retried_with_fresh_context = true;
                         continue;
@@ -372,7 +1262,7 @@ impl AgentLoop {
             }
         }
 
-        let answer = final_content.unwrap_or_else(|| {
+        let mut answer = final_content.unwrap_or_else(|| {
             if iterations_run >= self.max_iterations {
                 format!(
                     "Reached {} iterations without completion.",
@@ -382,13 +1272,93 @@ impl AgentLoop {
                 "I've completed processing but have no response to give.".to_string()
             }
         });
+        if self.cites_sources(&msg.channel)
+            && let Some(sources) = citations.render(&answer, &self.workspace)
+        {
+            answer = format!("{answer}\n\n{sources}");
+        }
 
-        session.add_message("user", &msg.content);
+        session.add_message("user", &content);
         session.add_message_with_tools("assistant", &answer, Some(&tools_used));
+        if self.confirm_group_memory && msg.is_group() {
+            group_memory::mark_turn(&mut session.messages, &msg, !third_party);
+        }
         self.sessions.save(&session)?;
 
-        let mut outbound = OutboundMessage::new(msg.channel, msg.chat_id, answer);
-        outbound.metadata = msg.metadata;
+        if self.extract_action_items && !read_only {
+            let provider = provider.clone();
+            let model = model.clone();
+            let sampling = self.maintenance_sampling;
+            let store = self.task_store.clone();
+            let session_key = session.key.clone();
+            let lines = vec![format!("USER: {content}"), format!("ASSISTANT: {answer}")];
+            let job = self
+                .activity
+                .begin_background(format!("action item extraction for {session_key}"));
+            tokio::spawn(async move {
+                let _job = job;
+                if let Err(err) =
+                    Self::extract_action_items(provider, model, sampling, store, session_key, lines)
+                        .await
+                {
+                    eprintln!("Warning: action item extraction failed: {err}");
+                }
+            });
+        }
+
+        events::record(
+            EventLevel::Info,
+            "turn",
+            Some(&msg.channel),
+            format!(
+                "{}: {model}, {iterations_run} iteration(s), {:.1}s, tools: {}",
+                session.key,
+                started.elapsed().as_secs_f32(),
+                if tools_used.is_empty() {
+                    "none".to_string()
+                } else {
+                    tools_used.join(", ")
+                }
+            ),
+        );
+
+        let turn_id = if msg.channel == "cli" {
+            None
+        } else {
+            let record = TurnRecord::new(
+                &session.key,
+                &msg.channel,
+                &msg.chat_id,
+                &model,
+                &msg.content,
+                &answer,
+                &tools_used,
+            );
+            let turn_id = record.id.clone();
+            match self.feedback.record_turn(record) {
+                Ok(()) => Some(turn_id),
+                Err(err) => {
+                    eprintln!("Warning: failed to record turn for feedback: {err}");
+                    None
+                }
+            }
+        };
+
+        let mut outbound = msg.reply(answer);
+        if let Some(turn_id) = turn_id {
+            outbound
+                .metadata
+                .insert("turn_id".to_string(), Value::String(turn_id));
+        }
+        // Lets the gateway measure receipt-to-delivery latency per model.
+        outbound.metadata.insert(
+            "received_at_ms".to_string(),
+            Value::from(msg.timestamp.timestamp_millis()),
+        );
+        outbound
+            .metadata
+            .insert("model".to_string(), Value::String(model));
+        self.attach_file_changes(&mut outbound);
         Ok(outbound)
     }
 
@@ -401,39 +1371,67 @@ impl AgentLoop {
 
         self.message_tool
             .set_context(origin_channel.clone(), origin_chat_id.clone());
+        self.message_tool.set_read_only(false);
+        self.memory_locked.store(false, Ordering::Relaxed);
+        self.message_tool.set_thread(msg.thread_id.clone());
+        self.message_tool.set_recipient_name(None);
+        self.message_tool.set_proactive(false);
         self.sessions_send_tool
             .set_context(origin_channel.clone(), origin_chat_id.clone());
         self.spawn_tool
             .set_context(origin_channel.clone(), origin_chat_id.clone());
+        self.spawn_tool.set_thread(msg.thread_id.clone());
         if let Some(cron_tool) = &self.cron_tool {
             cron_tool.set_context(origin_channel.clone(), origin_chat_id.clone());
+            cron_tool.set_requester(msg.sender_id.clone(), true);
         }
+        if let Some(remind_tool) = &self.remind_tool {
+            remind_tool.set_context(origin_channel.clone(), origin_chat_id.clone(), "");
+        }
+        if let Some(batch_tool) = &self.batch_tool {
+            batch_tool.set_context(origin_channel.clone(), origin_chat_id.clone());
+        }
+        self.task_tool
+            .set_context(origin_channel.clone(), origin_chat_id.clone());
+        // System turns (heartbeat digests) close tasks from any chat.
+        self.task_tool.set_owner(true);
+        self.file_changes.take();
 
         let session_key = format!("{origin_channel}:{origin_chat_id}");
         let mut session = self.sessions.get_or_create(&session_key);
         // Deterministic anti-contamination: only current turn is sent to the model.
         let history = session.get_history(0);
-        let mut messages = self.build_turn_messages(
-            &history,
-            &msg.content,
-            &origin_channel,
-            &origin_chat_id,
-            None,
-        );
+        let turn = self.activity.begin_turn(&session.key);
+        let (provider, model) = self.session_provider(&session);
+        let request = ContextRequest {
+            channel: Some(&origin_channel),
+            chat_id: Some(&origin_chat_id),
+            message: &msg.content,
+            read_only: false,
+        };
+        let mut messages = self.build_turn_messages(&session_key, &model, &history, &request, None);
 
         let mut final_content: Option<String> = None;
         let mut retried_with_fresh_context = false;
+        let sampling = self.sampling.capped_for(&self.models.lookup(&model));
         let turn_guard = TurnGuard::new(
-            self.provider.as_ref(),
-            &self.model,
-            self.available_tools_text(),
+            provider.as_ref(),
+            &model,
+            self.available_tools_text(&model, false),
             self.max_iterations,
         );
         for iteration in 1..=self.max_iterations {
-            let tool_defs = self.tools.get_definitions();
+            turn.set_stage(format!("calling {model} (iteration {iteration})"));
+            let tool_defs = self.tool_definitions(&model, false);
             let response = self
-                .provider
-                .chat(&messages, Some(&tool_defs), Some(&self.model), 4096, 0.7)
+                .chat_recovering_overflow(
+                    provider.as_ref(),
+                    &mut messages,
+                    (!tool_defs.is_empty()).then_some(tool_defs.as_slice()),
+                    &model,
+                    &sampling,
+                    &session.key,
+                )
                 .await?;
 
             if response.has_tool_calls() {
@@ -459,9 +1457,9 @@ impl AgentLoop {
                 );
 
                 for tool_call in response.tool_calls {
+                    turn.set_stage(format!("running tool {}", tool_call.name));
                     let result = self
-                        .tools
-                        .execute(&tool_call.name, &tool_call.arguments)
+                        .execute_tool(&tool_call.name, &tool_call.arguments, false)
                         .await;
                     self.context.add_tool_result(
                         &mut messages,
@@ -480,13 +1478,8 @@ impl AgentLoop {
                     .await
                 {
                     if !retried_with_fresh_context {
-                        messages = self.build_turn_messages(
-                            &[],
-                            &msg.content,
-                            &origin_channel,
-                            &origin_chat_id,
-                            None,
-                        );
+                        messages =
+                            self.build_turn_messages(&session_key, &model, &[], &request, None);
                         messages.push(turn_guard.correction_message());
                         retried_with_fresh_context = true;
                         continue;
@@ -507,7 +1500,10 @@ impl AgentLoop {
         session.add_message("assistant", &answer);
         self.sessions.save(&session)?;
 
-        Ok(OutboundMessage::new(origin_channel, origin_chat_id, answer))
+        let mut outbound = OutboundMessage::new(origin_channel, origin_chat_id, answer);
+        outbound.thread_id = msg.thread_id;
+        self.attach_file_changes(&mut outbound);
+        Ok(outbound)
     }
 
     async fn consolidate_memory(
@@ -530,49 +1526,9 @@ impl AgentLoop {
         }
 
         let split_idx = session.messages.len().saturating_sub(keep_count);
-        let old_messages = &session.messages[..split_idx];
-        let mut lines = Vec::new();
-        for msg in old_messages {
-            let Some(content) = msg.get("content").and_then(Value::as_str) else {
-                continue;
-            };
-            if content.trim().is_empty() {
-                continue;
-            }
-            let timestamp = msg
-                .get("timestamp")
-                .and_then(Value::as_str)
-                .unwrap_or("?")
-                .chars()
-                .take(16)
-                .collect::<String>();
-            let role = msg
-                .get("role")
-                .and_then(Value::as_str)
-                .unwrap_or("user")
-                .to_ascii_uppercase();
-            let tools_suffix = msg
-                .get("tools_used")
-                .and_then(Value::as_array)
-                .filter(|tools| !tools.is_empty())
-                .map(|tools| {
-                    let list = tools
-                        .iter()
-                        .filter_map(Value::as_str)
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                    if list.is_empty() {
-                        String::new()
-                    } else {
-                        format!(" [tools: {list}]")
-                    }
-                })
-                .unwrap_or_default();
-            lines.push(format!(
-                "[{timestamp}] {role}{tools_suffix}: {content}",
-                content = content.trim()
-            ));
-        }
+        let lines = transcript_lines(&group_memory::consolidation_input(
+            &session.messages[..split_idx],
+        ));
 
         if lines.is_empty() {
             session.messages = session.messages[split_idx..].to_vec();
@@ -597,9 +1553,10 @@ Respond with ONLY valid JSON, no markdown fences.",
             conversation = lines.join("\n")
         );
 
+        let started = Instant::now();
         let response = self
             .provider
-            .chat(
+            .chat_with_sampling(
                 &[
                     json!({
                         "role": "system",
@@ -612,8 +1569,7 @@ Respond with ONLY valid JSON, no markdown fences.",
                 ],
                 None,
                 Some(&self.model),
-                1200,
-                0.0,
+                &self.maintenance_sampling,
             )
             .await?;
 
@@ -623,16 +1579,42 @@ Respond with ONLY valid JSON, no markdown fences.",
             .and_then(Self::extract_json_object)
             .context("memory consolidation returned non-JSON content")?;
 
+        let mut history_chars = 0;
         if let Some(entry) = parsed.get("history_entry").and_then(Value::as_str)
             && !entry.trim().is_empty()
         {
             memory.append_history(entry)?;
+            history_chars = entry.trim().chars().count();
         }
+        let mut memory_after = current_memory.clone();
         if let Some(update) = parsed.get("memory_update").and_then(Value::as_str)
             && update.trim() != current_memory.trim()
         {
             memory.write_long_term(update)?;
+            memory_after = update.to_string();
         }
+        let record = ConsolidationRecord {
+            id: uuid::Uuid::new_v4().simple().to_string()[..8].to_string(),
+            at_ms: chrono::Utc::now().timestamp_millis(),
+            session_key: session.key.clone(),
+            model: self.model.clone(),
+            duration_ms: started.elapsed().as_millis() as u64,
+            input_messages: split_idx,
+            input_chars: lines.iter().map(|line| line.chars().count()).sum(),
+            history_chars,
+            memory_before: current_memory,
+            memory_after,
+            rolled_back: false,
+        };
+        if let Err(err) = memory.record_consolidation(record) {
+            eprintln!("Warning: failed to log memory consolidation: {err}");
+        }
+        let memory_files =
+            [&memory.memory_file, &memory.history_file].map(|path| path.display().to_string());
+        self.commit_workspace(
+            &memory_files,
+            &format!("memory: consolidate {}", session.key),
+        );
 
         if keep_count == 0 {
             session.messages.clear();
@@ -643,12 +1625,101 @@ Respond with ONLY valid JSON, no markdown fences.",
         Ok(())
     }
 
+    pub async fn summarize_session(&self, session_key: &str) -> Result<SessionSummary> {
+        let session = self.sessions.get_or_create(session_key);
+        let lines = transcript_lines(&session.messages);
+        if lines.is_empty() {
+            return Ok(SessionSummary::default());
+        }
+
+        let response = self
+            .provider
+            .chat_with_sampling(
+                &[
+                    json!({
+                        "role": "system",
+                        "content": SUMMARY_SYSTEM_PROMPT
+                    }),
+                    json!({
+                        "role": "user",
+                        "content": summary_prompt(&lines)
+                    }),
+                ],
+                None,
+                Some(&self.model),
+                &self.maintenance_sampling,
+            )
+            .await?;
+
+        let parsed = response
+            .content
+            .as_deref()
+            .and_then(Self::extract_json_object)
+            .context("session summary returned non-JSON content")?;
+        Ok(SessionSummary::from_json(&parsed))
+    }
+
+    async fn extract_action_items(
+        provider: Arc<dyn LLMProvider>,
+        model: String,
+        sampling: SamplingParams,
+        store: TaskStore,
+        session_key: String,
+        lines: Vec<String>,
+    ) -> Result<usize> {
+        let response = provider
+            .chat_with_sampling(
+                &[
+                    json!({
+                        "role": "system",
+                        "content": ACTION_ITEMS_SYSTEM_PROMPT
+                    }),
+                    json!({
+                        "role": "user",
+                        "content": action_items_prompt(&lines)
+                    }),
+                ],
+                None,
+                Some(&model),
+                &sampling,
+            )
+            .await?;
+
+        let parsed = response
+            .content
+            .as_deref()
+            .and_then(Self::extract_json_object)
+            .context("action item extraction returned non-JSON content")?;
+        let items = parse_action_items(&parsed);
+        for item in &items {
+            store.add(
+                &item.title,
+                item.due_at_ms,
+                Some(session_key.clone()),
+                "extracted",
+            )?;
+        }
+        Ok(items.len())
+    }
+
     pub async fn process_direct(
         &self,
         content: &str,
         session_key: Option<&str>,
         channel: Option<&str>,
         chat_id: Option<&str>,
+    ) -> Result<String> {
+        self.process_direct_with_metadata(content, session_key, channel, chat_id, Map::new())
+            .await
+    }
+
+    pub async fn process_direct_with_metadata(
+        &self,
+        content: &str,
+        session_key: Option<&str>,
+        channel: Option<&str>,
+        chat_id: Option<&str>,
+        metadata: Map<String, Value>,
     ) -> Result<String> {
         let session_key = session_key.unwrap_or("cli:direct");
         let (default_channel, default_chat_id) = session_key
@@ -658,11 +1729,17 @@ Respond with ONLY valid JSON, no markdown fences.",
         let channel = channel.unwrap_or(&default_channel);
         let chat_id = chat_id.unwrap_or(&default_chat_id);
 
-        let msg = InboundMessage::new(channel, "user", chat_id, content);
-        let response = self.process_message(msg, Some(session_key)).await?;
+        let mut msg = InboundMessage::new(channel, "user", chat_id, content);
+        msg.metadata = metadata;
+        let response = self.process_message(msg, Some(session_key), false).await?;
         Ok(response.content)
     }
 
+    // Every registered tool in OpenAI function-calling format.
+    pub fn tool_schemas(&self) -> Vec<Value> {
+        self.tools.export_definitions()
+    }
+
     pub fn workspace(&self) -> &PathBuf {
         &self.workspace
     }
@@ -671,3 +1748,21 @@ Respond with ONLY valid JSON, no markdown fences.",
         self.subagents.get_running_count().await
     }
 }
+
+// Prefixes the speaker's display name when the channel resolved one, so the
+// model can tell group members apart and refer to them by name.
+fn attributed_content(msg: &InboundMessage) -> String {
+    match msg
+        .metadata
+        .get("sender_name")
+        .and_then(Value::as_str)
+        .map(str::trim)
+    {
+        Some(name) if !name.is_empty() => format!("[{name}] {}", msg.content),
+        _ => msg.content.clone(),
+    }
+}
+
+fn is_busy_command(content: &str) -> bool {
+    content.trim().eq_ignore_ascii_case("!busy")
+}
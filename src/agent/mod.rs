@@ -1,6 +1,13 @@
+pub mod action_items;
+pub mod citations;
+pub mod commands;
 pub mod context;
+pub mod group_memory;
 pub mod r#loop;
+pub mod status;
 pub mod subagent;
+pub mod summary;
+pub mod turn_error;
 pub mod turn_guard;
 
 pub use r#loop::AgentLoop;
@@ -0,0 +1,226 @@
+use crate::agent::subagent::SubagentManager;
+use crate::bus::MessageBus;
+use chrono::{DateTime, Local};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct ActivityInfo {
+    pub label: String,
+    pub stage: String,
+    pub started_at: DateTime<Local>,
+}
+
+#[derive(Default)]
+struct ActivityState {
+    next_id: u64,
+    turns: HashMap<u64, ActivityInfo>,
+    background: HashMap<u64, ActivityInfo>,
+}
+
+// Tracks in-flight turns, queued turns and background jobs so `!busy` and the
+// agent_status tool can tell "busy" apart from "stuck".
+#[derive(Clone, Default)]
+pub struct ActivityTracker {
+    state: Arc<Mutex<ActivityState>>,
+    queued: Arc<AtomicUsize>,
+}
+
+pub struct ActivityGuard {
+    tracker: ActivityTracker,
+    id: u64,
+    background: bool,
+}
+
+impl ActivityGuard {
+    pub fn set_stage(&self, stage: impl Into<String>) {
+        if let Ok(mut state) = self.tracker.state.lock() {
+            let entries = if self.background {
+                &mut state.background
+            } else {
+                &mut state.turns
+            };
+            if let Some(info) = entries.get_mut(&self.id) {
+                info.stage = stage.into();
+            }
+        }
+    }
+}
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.tracker.state.lock() {
+            if self.background {
+                state.background.remove(&self.id);
+            } else {
+                state.turns.remove(&self.id);
+            }
+        }
+    }
+}
+
+impl ActivityTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn begin(&self, label: String, background: bool) -> ActivityGuard {
+        let mut id = 0;
+        if let Ok(mut state) = self.state.lock() {
+            state.next_id += 1;
+            id = state.next_id;
+            let info = ActivityInfo {
+                label,
+                stage: "starting".to_string(),
+                started_at: Local::now(),
+            };
+            if background {
+                state.background.insert(id, info);
+            } else {
+                state.turns.insert(id, info);
+            }
+        }
+        ActivityGuard {
+            tracker: self.clone(),
+            id,
+            background,
+        }
+    }
+
+    pub fn begin_turn(&self, session_key: &str) -> ActivityGuard {
+        self.begin(session_key.to_string(), false)
+    }
+
+    pub fn begin_background(&self, label: impl Into<String>) -> ActivityGuard {
+        self.begin(label.into(), true)
+    }
+
+    pub fn queue(&self) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn dequeue(&self) {
+        let _ = self
+            .queued
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| v.checked_sub(1));
+    }
+
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    pub fn turns(&self) -> Vec<ActivityInfo> {
+        self.snapshot(false)
+    }
+
+    pub fn background(&self) -> Vec<ActivityInfo> {
+        self.snapshot(true)
+    }
+
+    fn snapshot(&self, background: bool) -> Vec<ActivityInfo> {
+        let Ok(state) = self.state.lock() else {
+            return Vec::new();
+        };
+        let entries = if background {
+            &state.background
+        } else {
+            &state.turns
+        };
+        let mut items = entries.values().cloned().collect::<Vec<_>>();
+        items.sort_by_key(|info| info.started_at);
+        items
+    }
+}
+
+fn elapsed(started_at: DateTime<Local>, now: DateTime<Local>) -> String {
+    let secs = (now - started_at).num_seconds().max(0);
+    if secs >= 3600 {
+        format!("{}h{}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m{}s", secs / 60, secs % 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+pub async fn render_status(
+    tracker: &ActivityTracker,
+    subagents: &SubagentManager,
+    bus: &MessageBus,
+) -> String {
+    let now = Local::now();
+    let mut lines = Vec::new();
+
+    let turns = tracker.turns();
+    lines.push(format!("In-flight turns: {}", turns.len()));
+    for turn in &turns {
+        lines.push(format!(
+            "- {} ({}, {} elapsed)",
+            turn.label,
+            turn.stage,
+            elapsed(turn.started_at, now)
+        ));
+    }
+
+    let running = subagents.list_running().await;
+    lines.push(format!("Running subagents: {}", running.len()));
+    for info in &running {
+        lines.push(format!(
+            "- [{}] {} for {} ({} elapsed)",
+            info.id,
+            info.label,
+            info.origin,
+            elapsed(info.started_at, now)
+        ));
+    }
+
+    lines.push(format!(
+        "Queued messages: {} waiting for a turn, {} inbound, {} outbound",
+        tracker.queued(),
+        bus.inbound_size(),
+        bus.outbound_size()
+    ));
+
+    let background = tracker.background();
+    lines.push(format!("Background tasks: {}", background.len()));
+    for task in &background {
+        lines.push(format!(
+            "- {} ({}, {} elapsed)",
+            task.label,
+            task.stage,
+            elapsed(task.started_at, now)
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn guards_remove_entries_on_drop() {
+        let tracker = ActivityTracker::new();
+        let turn = tracker.begin_turn("telegram:1");
+        turn.set_stage("calling model (iteration 1)");
+        let job = tracker.begin_background("action item extraction");
+        assert_eq!(tracker.turns()[0].stage, "calling model (iteration 1)");
+        assert_eq!(tracker.background().len(), 1);
+
+        drop(turn);
+        drop(job);
+        assert!(tracker.turns().is_empty());
+        assert!(tracker.background().is_empty());
+    }
+
+    #[test]
+    fn dequeue_never_underflows() {
+        let tracker = ActivityTracker::new();
+        tracker.queue();
+        tracker.dequeue();
+        tracker.dequeue();
+        assert_eq!(tracker.queued(), 0);
+    }
+}
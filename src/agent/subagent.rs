@@ -1,8 +1,10 @@
 use crate::bus::{InboundMessage, MessageBus};
-use crate::config::WebSearchConfig;
-use crate::providers::base::LLMProvider;
+use crate::config::{SubagentProfile, WebSearchConfig};
+use crate::providers::base::{LLMProvider, ProviderFactory, SamplingParams};
+use crate::tools::base::Tool;
 use crate::tools::filesystem::{EditFileTool, ListDirTool, ReadFileTool, WriteFileTool};
 use crate::tools::http::HttpRequestTool;
+use crate::tools::message::MessageTool;
 use crate::tools::registry::ToolRegistry;
 use crate::tools::shell::ExecTool;
 use crate::tools::web::{WebFetchTool, WebSearchTool};
@@ -10,10 +12,23 @@ use chrono::Local;
 use serde_json::json;
 use std::collections::HashMap;
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 
+// Minimum gap between progress updates a subagent posts to the origin chat.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_ITERATIONS: u32 = 15;
+
+#[derive(Debug, Clone)]
+pub struct SubagentInfo {
+    pub id: String,
+    pub label: String,
+    pub origin: String,
+    pub started_at: chrono::DateTime<Local>,
+}
+
 pub struct SubagentManager {
     provider: Arc<dyn LLMProvider>,
     workspace: PathBuf,
@@ -22,7 +37,11 @@ pub struct SubagentManager {
     web_search: WebSearchConfig,
     exec_timeout_s: u64,
     restrict_to_workspace: bool,
+    sampling: RwLock<SamplingParams>,
     running_tasks: Arc<Mutex<HashMap<String, tokio::task::JoinHandle<()>>>>,
+    task_info: Arc<Mutex<HashMap<String, SubagentInfo>>>,
+    profiles: RwLock<HashMap<String, SubagentProfile>>,
+    provider_factory: RwLock<Option<ProviderFactory>>,
 }
 
 impl SubagentManager {
@@ -43,17 +62,108 @@ impl SubagentManager {
             web_search,
             exec_timeout_s,
             restrict_to_workspace,
+            sampling: RwLock::new(SamplingParams::default()),
             running_tasks: Arc::new(Mutex::new(HashMap::new())),
+            task_info: Arc::new(Mutex::new(HashMap::new())),
+            profiles: RwLock::new(HashMap::new()),
+            provider_factory: RwLock::new(None),
+        }
+    }
+
+    pub fn set_sampling(&self, sampling: SamplingParams) {
+        if let Ok(mut guard) = self.sampling.write() {
+            *guard = sampling;
+        }
+    }
+
+    pub fn set_profiles(&self, profiles: HashMap<String, SubagentProfile>) {
+        if let Ok(mut guard) = self.profiles.write() {
+            *guard = profiles;
+        }
+    }
+
+    // Needed for profiles that name a model other than the agent's own.
+    pub fn set_provider_factory(&self, factory: ProviderFactory) {
+        if let Ok(mut guard) = self.provider_factory.write() {
+            *guard = Some(factory);
         }
     }
 
+    // (name, description), sorted by name.
+    pub fn profiles(&self) -> Vec<(String, String)> {
+        let mut profiles = self
+            .profiles
+            .read()
+            .map(|guard| {
+                guard
+                    .iter()
+                    .map(|(name, profile)| (name.clone(), profile.description.clone()))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        profiles.sort();
+        profiles
+    }
+
+    fn resolve_profile(
+        &self,
+        name: Option<&str>,
+    ) -> anyhow::Result<(SubagentProfile, String, Arc<dyn LLMProvider>)> {
+        let profile = match name {
+            Some(name) => self
+                .profiles
+                .read()
+                .ok()
+                .and_then(|guard| guard.get(name).cloned())
+                .ok_or_else(|| {
+                    let known = self
+                        .profiles()
+                        .into_iter()
+                        .map(|(name, _)| name)
+                        .collect::<Vec<_>>();
+                    anyhow::anyhow!(
+                        "unknown subagent profile '{name}' (available: {})",
+                        if known.is_empty() {
+                            "none configured".to_string()
+                        } else {
+                            known.join(", ")
+                        }
+                    )
+                })?,
+            None => SubagentProfile::default(),
+        };
+        let model = profile
+            .model
+            .clone()
+            .filter(|model| !model.trim().is_empty())
+            .unwrap_or_else(|| self.model.clone());
+        let provider = if model == self.model {
+            self.provider.clone()
+        } else {
+            let factory = self
+                .provider_factory
+                .read()
+                .ok()
+                .and_then(|guard| guard.clone())
+                .ok_or_else(|| anyhow::anyhow!("profile models are not available in this mode"))?;
+            factory(&model)?
+        };
+        Ok((profile, model, provider))
+    }
+
     pub async fn spawn(
         &self,
         task: String,
         label: Option<String>,
+        profile: Option<&str>,
         origin_channel: String,
         origin_chat_id: String,
+        origin_thread: Option<String>,
     ) -> String {
+        let (profile, model, provider) = match self.resolve_profile(profile) {
+            Ok(resolved) => resolved,
+            Err(err) => return format!("Error: {err}"),
+        };
         let task_id = Uuid::new_v4().simple().to_string()[..8].to_string();
         let display_label = label.unwrap_or_else(|| {
             if task.len() > 30 {
@@ -63,31 +173,50 @@ impl SubagentManager {
             }
         });
 
-        let provider = self.provider.clone();
         let workspace = self.workspace.clone();
-        let model = self.model.clone();
+        let model_for_run = model.clone();
         let web_search = self.web_search.clone();
         let exec_timeout_s = self.exec_timeout_s;
         let restrict_to_workspace = self.restrict_to_workspace;
+        let sampling = self.sampling.read().map(|s| *s).unwrap_or_default();
         let bus = self.bus.clone();
         let task_id_for_cleanup = task_id.clone();
-        let task_id_for_run = task_id.clone();
         let running_map = self.running_tasks.clone();
+        let info_map = self.task_info.clone();
+        info_map.lock().await.insert(
+            task_id.clone(),
+            SubagentInfo {
+                id: task_id.clone(),
+                label: display_label.clone(),
+                origin: format!("{origin_channel}:{origin_chat_id}"),
+                started_at: Local::now(),
+            },
+        );
         let task_for_run = task.clone();
         let label_for_run = display_label.clone();
+        // Progress goes only to the chat (and thread) that spawned the task.
+        let progress = Arc::new(
+            MessageTool::new(bus.outbound_sender())
+                .with_prefix(format!("[{display_label}] "))
+                .with_min_interval(PROGRESS_INTERVAL),
+        );
+        progress.set_context(origin_channel.clone(), origin_chat_id.clone());
+        progress.set_thread(origin_thread.clone());
+        progress.set_read_only(true);
 
         let handle = tokio::spawn(async move {
-            let result = run_subagent(
+            let result = run_subagent(SubagentRun {
                 provider,
                 workspace,
-                model,
+                model: model_for_run,
                 web_search,
                 exec_timeout_s,
                 restrict_to_workspace,
-                task_id_for_run.clone(),
-                task_for_run.clone(),
-                label_for_run.clone(),
-            )
+                sampling,
+                profile,
+                task: task_for_run.clone(),
+                progress,
+            })
             .await;
 
             let (status, content) = match result {
@@ -105,16 +234,17 @@ impl SubagentManager {
                 "[Subagent '{label_for_run}' {status_text}]\n\nTask: {task_for_run}\n\nResult:\n{content}\n\nSummarize this naturally for the user. Keep it brief (1-2 sentences). Do not mention technical details like \"subagent\" or task IDs."
             );
 
-            let _ = bus
-                .publish_inbound(InboundMessage::new(
-                    "system",
-                    "subagent",
-                    format!("{origin_channel}:{origin_chat_id}"),
-                    announce,
-                ))
-                .await;
+            let mut msg = InboundMessage::new(
+                "system",
+                "subagent",
+                format!("{origin_channel}:{origin_chat_id}"),
+                announce,
+            );
+            msg.thread_id = origin_thread;
+            let _ = bus.publish_inbound(msg).await;
 
             running_map.lock().await.remove(&task_id_for_cleanup);
+            info_map.lock().await.remove(&task_id_for_cleanup);
         });
 
         self.running_tasks
@@ -122,46 +252,83 @@ impl SubagentManager {
             .await
             .insert(task_id.clone(), handle);
         format!(
-            "Subagent [{display_label}] started (id: {task_id}). I'll notify you when it completes."
+            "Subagent [{display_label}] started (id: {task_id}, model: {model}). I'll notify you when it completes."
         )
     }
 
     pub async fn get_running_count(&self) -> usize {
         self.running_tasks.lock().await.len()
     }
+
+    pub async fn list_running(&self) -> Vec<SubagentInfo> {
+        let mut running = self
+            .task_info
+            .lock()
+            .await
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        running.sort_by_key(|info| info.started_at);
+        running
+    }
 }
 
-async fn run_subagent(
+// Everything a spawned subagent needs, captured before it leaves for its task.
+struct SubagentRun {
     provider: Arc<dyn LLMProvider>,
     workspace: PathBuf,
     model: String,
     web_search: WebSearchConfig,
     exec_timeout_s: u64,
     restrict_to_workspace: bool,
-    _task_id: String,
+    sampling: SamplingParams,
+    profile: SubagentProfile,
     task: String,
-    _label: String,
-) -> anyhow::Result<String> {
-    let mut tools = ToolRegistry::new();
+    progress: Arc<MessageTool>,
+}
+
+async fn run_subagent(run: SubagentRun) -> anyhow::Result<String> {
+    let SubagentRun {
+        provider,
+        workspace,
+        model,
+        web_search,
+        exec_timeout_s,
+        restrict_to_workspace,
+        sampling,
+        profile,
+        task,
+        progress,
+    } = run;
     let allowed_dir = if restrict_to_workspace {
         Some(workspace.clone())
     } else {
         None
     };
-    tools.register(Arc::new(ReadFileTool::new(allowed_dir.clone())));
-    tools.register(Arc::new(WriteFileTool::new(allowed_dir.clone())));
-    tools.register(Arc::new(EditFileTool::new(allowed_dir.clone())));
-    tools.register(Arc::new(ListDirTool::new(allowed_dir.clone())));
-    tools.register(Arc::new(ExecTool::new(
-        exec_timeout_s,
-        Some(workspace.clone()),
-        None,
-        None,
-        restrict_to_workspace,
-    )));
-    tools.register(Arc::new(WebSearchTool::from_config(web_search)));
-    tools.register(Arc::new(WebFetchTool::new(50_000)));
-    tools.register(Arc::new(HttpRequestTool::new(30, 50_000)));
+    let available: Vec<Arc<dyn Tool>> = vec![
+        Arc::new(ReadFileTool::new(allowed_dir.clone())),
+        Arc::new(WriteFileTool::new(allowed_dir.clone())),
+        Arc::new(EditFileTool::new(allowed_dir.clone())),
+        Arc::new(ListDirTool::new(allowed_dir.clone())),
+        Arc::new(ExecTool::new(
+            exec_timeout_s,
+            Some(workspace.clone()),
+            None,
+            None,
+            restrict_to_workspace,
+        )),
+        Arc::new(WebSearchTool::from_config(web_search)),
+        Arc::new(WebFetchTool::new(50_000)),
+        Arc::new(HttpRequestTool::new(30, 50_000)),
+    ];
+    let tools = ToolRegistry::new();
+    for tool in available {
+        if profile.tools.is_empty() || profile.tools.iter().any(|name| name == tool.name()) {
+            tools.register(tool);
+        }
+    }
+    // Progress updates stay available whatever the profile allows.
+    tools.register(progress);
 
     let now = Local::now();
     let now_text = now.format("%Y-%m-%d %H:%M (%A)").to_string();
@@ -169,10 +336,17 @@ async fn run_subagent(
     let tz = if tz.is_empty() { "UTC" } else { &tz };
 
     let system_prompt = format!(
-        "# Subagent\n\n## Current Time\n{now_text} ({tz})\n\nYou are a subagent spawned by the main agent to complete a specific task.\n\n## Rules\n1. Stay focused - complete only the assigned task, nothing else\n2. Your final response will be reported back to the main agent\n3. Do not initiate conversations or take on side tasks\n4. Be concise but informative in your findings\n\n## What You Can Do\n- Read, write, and edit files in the workspace\n- Execute shell commands\n- Search the web and fetch web pages\n\n## What You Cannot Do\n- Send messages directly to users\n- Spawn other subagents\n\n## Workspace\n{}\nSkills are available at: {}/skills/ (read SKILL.md files as needed)\n",
+        "# Subagent\n\n## Current Time\n{now_text} ({tz})\n\nYou are a subagent spawned by the main agent to complete a specific task.\n\n## Rules\n1. Stay focused - complete only the assigned task, nothing else\n2. Your final response will be reported back to the main agent\n3. Do not initiate conversations or take on side tasks\n4. Be concise but informative in your findings\n\n## What You Can Do\n- Read, write, and edit files in the workspace\n- Execute shell commands\n- Search the web and fetch web pages\n- On long tasks, post a one-line progress update to the user with `message` (e.g. \"found 12 candidate papers, now summarizing\"); at most one every {}s, so only at real milestones\n\n## What You Cannot Do\n- Message any chat other than the one that started the task\n- Spawn other subagents\n\n## Workspace\n{}\nSkills are available at: {}/skills/ (read SKILL.md files as needed)\n",
+        PROGRESS_INTERVAL.as_secs(),
         workspace.display(),
         workspace.display()
     );
+    let system_prompt = match profile.system_prompt.as_deref().map(str::trim) {
+        Some(extra) if !extra.is_empty() => {
+            format!("{system_prompt}\n## Profile Instructions\n{extra}\n")
+        }
+        _ => system_prompt,
+    };
 
     let mut messages = vec![
         json!({"role":"system","content":system_prompt}),
@@ -180,10 +354,10 @@ async fn run_subagent(
     ];
 
     let mut final_result = None;
-    for _ in 0..15 {
+    for _ in 0..profile.max_iterations.unwrap_or(DEFAULT_MAX_ITERATIONS) {
         let tool_defs = tools.get_definitions();
         let response = provider
-            .chat(&messages, Some(&tool_defs), Some(&model), 4096, 0.7)
+            .chat_with_sampling(&messages, Some(&tool_defs), Some(&model), &sampling)
             .await?;
 
         if response.has_tool_calls() {
@@ -224,3 +398,57 @@ async fn run_subagent(
     Ok(final_result
         .unwrap_or_else(|| "Task completed but no final response was generated.".to_string()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::litellm::LiteLLMProvider;
+
+    #[test]
+    fn resolves_profiles_to_models() {
+        let provider: Arc<dyn LLMProvider> =
+            Arc::new(LiteLLMProvider::new("sk-test", None, "gpt-4o", None, None));
+        let manager = SubagentManager::new(
+            provider,
+            std::env::temp_dir(),
+            Arc::new(MessageBus::new(4)),
+            "gpt-4o".to_string(),
+            WebSearchConfig::default(),
+            30,
+            true,
+        );
+        manager.set_profiles(HashMap::from([
+            (
+                "scraper".to_string(),
+                SubagentProfile {
+                    description: "cheap page fetching".to_string(),
+                    model: Some("gpt-4o-mini".to_string()),
+                    tools: vec!["web_fetch".to_string()],
+                    ..Default::default()
+                },
+            ),
+            ("careful".to_string(), SubagentProfile::default()),
+        ]));
+        assert_eq!(manager.profiles()[1].1, "cheap page fetching");
+
+        let (_, model, _) = manager.resolve_profile(Some("careful")).expect("profile");
+        assert_eq!(model, "gpt-4o");
+        let err = manager
+            .resolve_profile(Some("poet"))
+            .err()
+            .expect("unknown");
+        assert!(err.to_string().contains("available: careful, scraper"));
+        // A different model needs the provider factory.
+        assert!(manager.resolve_profile(Some("scraper")).is_err());
+
+        manager.set_provider_factory(Arc::new(|model: &str| {
+            Ok(
+                Arc::new(LiteLLMProvider::new("sk-test", None, model, None, None))
+                    as Arc<dyn LLMProvider>,
+            )
+        }));
+        let (profile, model, _) = manager.resolve_profile(Some("scraper")).expect("profile");
+        assert_eq!(model, "gpt-4o-mini");
+        assert_eq!(profile.tools, vec!["web_fetch".to_string()]);
+    }
+}
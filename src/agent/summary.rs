@@ -0,0 +1,129 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const SUMMARY_SYSTEM_PROMPT: &str =
+    "You are a conversation summarizer. Respond only with valid JSON.";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub summary: String,
+    pub decisions: Vec<String>,
+    pub action_items: Vec<String>,
+    pub open_questions: Vec<String>,
+}
+
+pub fn summary_prompt(conversation: &[String]) -> String {
+    format!(
+        "Summarize this conversation and return a JSON object with exactly four keys:\n\n\
+1. \"summary\": A short paragraph (2-4 sentences) describing what the conversation covered.\n\n\
+2. \"decisions\": An array of decisions that were made or agreed on.\n\n\
+3. \"action_items\": An array of concrete follow-ups, including who owns them and any due date mentioned.\n\n\
+4. \"open_questions\": An array of questions that are still unresolved.\n\n\
+Use empty arrays when nothing applies. Do not invent items that are not in the conversation.\n\n\
+## Conversation\n{conversation}\n\n\
+Respond with ONLY valid JSON, no markdown fences.",
+        conversation = conversation.join("\n")
+    )
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    match value {
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(ToOwned::to_owned)
+            .collect(),
+        Some(Value::String(item)) if !item.trim().is_empty() => vec![item.trim().to_string()],
+        _ => Vec::new(),
+    }
+}
+
+impl SessionSummary {
+    pub fn from_json(value: &Value) -> Self {
+        Self {
+            summary: value
+                .get("summary")
+                .and_then(Value::as_str)
+                .map(str::trim)
+                .unwrap_or_default()
+                .to_string(),
+            decisions: string_list(value.get("decisions")),
+            action_items: string_list(value.get("action_items")),
+            open_questions: string_list(value.get("open_questions")),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.summary.is_empty()
+            && self.decisions.is_empty()
+            && self.action_items.is_empty()
+            && self.open_questions.is_empty()
+    }
+
+    pub fn render(&self) -> String {
+        if self.is_empty() {
+            return "Nothing to summarize yet.".to_string();
+        }
+
+        let mut parts = Vec::new();
+        if !self.summary.is_empty() {
+            parts.push(format!("Summary\n{}", self.summary));
+        }
+        for (title, items) in [
+            ("Decisions", &self.decisions),
+            ("Action items", &self.action_items),
+            ("Open questions", &self.open_questions),
+        ] {
+            let body = if items.is_empty() {
+                "- (none)".to_string()
+            } else {
+                items
+                    .iter()
+                    .map(|item| format!("- {item}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            parts.push(format!("{title}\n{body}"));
+        }
+        parts.join("\n\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SessionSummary;
+    use serde_json::json;
+
+    #[test]
+    fn from_json_tolerates_missing_and_scalar_fields() {
+        let summary = SessionSummary::from_json(&json!({
+            "summary": " Planned the release. ",
+            "decisions": "Ship on Friday",
+            "action_items": ["Alice drafts notes", ""],
+        }));
+        assert_eq!(summary.summary, "Planned the release.");
+        assert_eq!(summary.decisions, vec!["Ship on Friday".to_string()]);
+        assert_eq!(summary.action_items, vec!["Alice drafts notes".to_string()]);
+        assert!(summary.open_questions.is_empty());
+    }
+
+    #[test]
+    fn render_lists_every_section() {
+        let summary = SessionSummary {
+            summary: "Talked about the boiler.".to_string(),
+            decisions: vec!["Replace the valve".to_string()],
+            action_items: Vec::new(),
+            open_questions: vec!["Which plumber?".to_string()],
+        };
+        let text = summary.render();
+        assert!(text.contains("Decisions\n- Replace the valve"));
+        assert!(text.contains("Action items\n- (none)"));
+        assert!(text.contains("Open questions\n- Which plumber?"));
+        assert_eq!(
+            SessionSummary::default().render(),
+            "Nothing to summarize yet."
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use crate::bus::{InboundMessage, OutboundMessage};
+use crate::channels::format::BUTTON_CHANNELS;
+use crate::providers::base::{is_context_overflow, is_quota_error};
+use serde_json::{Value, json};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+// Failed turns kept for /retry; older ones are dropped first.
+const MAX_FAILED_TURNS: usize = 32;
+const CAUSE_CHARS: usize = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    RateLimit,
+    ContextTooLong,
+    Auth,
+    Timeout,
+    Network,
+    Provider,
+    Internal,
+}
+
+const AUTH_MARKERS: &[&str] = &[
+    "401",
+    "403",
+    "unauthorized",
+    "forbidden",
+    "invalid api key",
+    "invalid_api_key",
+    "incorrect api key",
+    "no api key",
+    "authentication",
+];
+const TIMEOUT_MARKERS: &[&str] = &["timed out", "timeout", "deadline has elapsed"];
+const NETWORK_MARKERS: &[&str] = &[
+    "error sending request",
+    "connection",
+    "dns error",
+    "network",
+];
+const PROVIDER_MARKERS: &[&str] = &[
+    "500",
+    "502",
+    "503",
+    "504",
+    "overloaded",
+    "bad gateway",
+    "service unavailable",
+    "internal server error",
+];
+
+impl ErrorCategory {
+    pub fn classify(error: &str) -> Self {
+        let lower = error.to_lowercase();
+        let has = |markers: &[&str]| markers.iter().any(|marker| lower.contains(marker));
+        // Overflow first: its messages often mention tokens and limits too.
+        if is_context_overflow(error) {
+            Self::ContextTooLong
+        } else if is_quota_error(error) {
+            Self::RateLimit
+        } else if has(AUTH_MARKERS) {
+            Self::Auth
+        } else if has(TIMEOUT_MARKERS) {
+            Self::Timeout
+        } else if has(NETWORK_MARKERS) {
+            Self::Network
+        } else if has(PROVIDER_MARKERS) {
+            Self::Provider
+        } else {
+            Self::Internal
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::RateLimit => "Rate limit or quota reached",
+            Self::ContextTooLong => "Conversation too long for the model",
+            Self::Auth => "Provider rejected the API key",
+            Self::Timeout => "The model took too long to answer",
+            Self::Network => "Could not reach the provider",
+            Self::Provider => "The provider had an error",
+            Self::Internal => "Something went wrong",
+        }
+    }
+
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::RateLimit => "Wait a minute, then retry.",
+            Self::ContextTooLong => "Send /new to start over, or retry after trimming the request.",
+            Self::Auth => "Check the provider key in config.json (nanobot doctor).",
+            Self::Timeout | Self::Network | Self::Provider => "This is usually temporary; retry.",
+            Self::Internal => "Retry, or check `nanobot events --level error` for details.",
+        }
+    }
+
+    // Whether running the same message again can succeed without the owner
+    // changing anything.
+    pub fn retryable(&self) -> bool {
+        !matches!(self, Self::Auth)
+    }
+}
+
+// The first line of the error, shortened for a chat message.
+fn short_cause(error: &str) -> String {
+    let line = error.lines().next().unwrap_or_default().trim();
+    let mut cause = line.chars().take(CAUSE_CHARS).collect::<String>();
+    if line.chars().count() > CAUSE_CHARS {
+        cause.push('…');
+    }
+    cause
+}
+
+// Inbound messages whose turn failed, so `/retry` can run them again
+// without the user retyping.
+#[derive(Default)]
+pub struct FailedTurns {
+    turns: Mutex<VecDeque<(String, InboundMessage)>>,
+}
+
+impl FailedTurns {
+    pub fn remember(&self, msg: &InboundMessage) -> String {
+        let id = Uuid::new_v4().simple().to_string()[..8].to_string();
+        if let Ok(mut turns) = self.turns.lock() {
+            turns.push_back((id.clone(), msg.clone()));
+            while turns.len() > MAX_FAILED_TURNS {
+                turns.pop_front();
+            }
+        }
+        id
+    }
+
+    // Removes and returns the failed message `id` names, or the latest one
+    // in the requester's chat. Only its sender may retry it unless `owner`.
+    pub fn take(
+        &self,
+        id: Option<&str>,
+        requester: &InboundMessage,
+        owner: bool,
+    ) -> Option<InboundMessage> {
+        let mut turns = self.turns.lock().ok()?;
+        let idx = turns.iter().rposition(|(turn_id, failed)| {
+            id.is_none_or(|id| id == turn_id)
+                && failed.channel == requester.channel
+                && failed.chat_id == requester.chat_id
+                && (owner || failed.sender_id == requester.sender_id)
+        })?;
+        turns.remove(idx).map(|(_, failed)| failed)
+    }
+}
+
+// `/retry` or `/retry <id>`; None for any other message.
+pub fn parse_retry(content: &str) -> Option<Option<&str>> {
+    let rest = content.trim().strip_prefix("/retry")?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(rest.split_whitespace().next())
+}
+
+// The reply to a failed turn: category, short cause and what to do next, with
+// a retry button on channels that have buttons.
+pub fn error_card(msg: &InboundMessage, error: &str, retry_id: &str) -> OutboundMessage {
+    let category = ErrorCategory::classify(error);
+    let mut content = format!(
+        "⚠️ {}\n{}\n\n{}",
+        category.label(),
+        short_cause(error),
+        category.hint()
+    );
+    let mut out = msg.reply(String::new());
+    let buttons = BUTTON_CHANNELS.contains(&msg.channel.as_str());
+    if category.retryable() && buttons {
+        out.metadata.insert(
+            "buttons".to_string(),
+            Value::Array(vec![
+                json!({ "text": "🔁 Retry", "command": format!("/retry {retry_id}") }),
+            ]),
+        );
+    } else if category.retryable() {
+        content.push_str("\nSend /retry to run your message again.");
+    }
+    out.content = content;
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_errors_and_retries_the_failed_message() {
+        let cases = [
+            (
+                "failed to call litellm-rs completion: 429 Too Many Requests",
+                ErrorCategory::RateLimit,
+            ),
+            (
+                "prompt is too long: 210000 tokens > 200000 maximum",
+                ErrorCategory::ContextTooLong,
+            ),
+            ("401 Unauthorized: invalid api key", ErrorCategory::Auth),
+            ("operation timed out", ErrorCategory::Timeout),
+            (
+                "error sending request for url (https://api.example.com)",
+                ErrorCategory::Network,
+            ),
+            ("503 Service Unavailable", ErrorCategory::Provider),
+            ("tool registry poisoned", ErrorCategory::Internal),
+        ];
+        for (error, category) in cases {
+            assert_eq!(ErrorCategory::classify(error), category, "{error}");
+        }
+
+        let msg = InboundMessage::new("telegram", "alice", "42", "summarize my inbox");
+        let failed = FailedTurns::default();
+        let id = failed.remember(&msg);
+
+        let card = error_card(&msg, "503 Service Unavailable\nretry-after: 5", &id);
+        assert!(
+            card.content
+                .starts_with("⚠️ The provider had an error\n503 Service Unavailable\n")
+        );
+        assert_eq!(
+            card.metadata["buttons"][0]["command"],
+            format!("/retry {id}")
+        );
+        let slack = InboundMessage::new("slack", "alice", "C1", "hi");
+        let text_only = error_card(&slack, "timed out", "abcd1234");
+        assert!(
+            text_only
+                .content
+                .ends_with("Send /retry to run your message again.")
+        );
+        assert!(!text_only.metadata.contains_key("buttons"));
+        let auth = error_card(&msg, "invalid api key", &id);
+        assert!(!auth.metadata.contains_key("buttons"));
+
+        assert_eq!(parse_retry("/retry"), Some(None));
+        assert_eq!(parse_retry(" /retry abcd1234 "), Some(Some("abcd1234")));
+        assert_eq!(parse_retry("/retrying"), None);
+
+        let bob = InboundMessage::new("telegram", "bob", "42", "/retry");
+        assert!(failed.take(None, &bob, false).is_none());
+        assert!(failed.take(Some("nope"), &bob, true).is_none());
+        let again = failed.take(Some(&id), &bob, true).expect("owner may retry");
+        assert_eq!(again.content, "summarize my inbox");
+        assert!(failed.take(None, &msg, false).is_none());
+    }
+}
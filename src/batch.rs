@@ -0,0 +1,396 @@
+use crate::bus::{InboundMessage, MessageBus};
+use crate::cluster::LeaderLease;
+use crate::config::Config;
+use crate::providers::batch::{BatchApi, BatchItem, BatchPoll, BatchResult};
+use crate::storage::locate;
+use crate::utils::get_data_path;
+use anyhow::{Result, bail};
+use chrono::{Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+pub const BATCH_POLL_INTERVAL_S: u64 = 60;
+pub const MAX_BATCH_ITEMS: usize = 10_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchJob {
+    pub id: String,
+    pub label: String,
+    pub provider: String,
+    pub model: String,
+    pub batch_id: String,
+    pub channel: String,
+    pub chat_id: String,
+    pub item_count: usize,
+    pub status: String, // pending | done | failed
+    #[serde(default)]
+    pub detail: Option<String>,
+    #[serde(default)]
+    pub results_path: Option<String>,
+    pub submitted_at_ms: i64,
+    #[serde(default)]
+    pub finished_at_ms: Option<i64>,
+}
+
+impl BatchJob {
+    pub fn describe(&self) -> String {
+        let submitted = Local
+            .timestamp_millis_opt(self.submitted_at_ms)
+            .single()
+            .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let mut line = format!(
+            "- {} [{}] {} ({} items, {}, submitted {submitted})",
+            self.id, self.status, self.label, self.item_count, self.model
+        );
+        if let Some(path) = &self.results_path {
+            line.push_str(&format!(" -> {path}"));
+        }
+        if let Some(detail) = &self.detail {
+            line.push_str(&format!(": {detail}"));
+        }
+        line
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BatchStore {
+    path: PathBuf,
+}
+
+impl BatchStore {
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_path(
+            get_data_path()?.join("batches").join("jobs.json"),
+        ))
+    }
+
+    pub fn from_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn list(&self) -> Result<Vec<BatchJob>> {
+        Ok(match locate(&self.path).read()? {
+            Some(raw) => serde_json::from_str(&raw).unwrap_or_default(),
+            None => Vec::new(),
+        })
+    }
+
+    fn save(&self, jobs: &[BatchJob]) -> Result<()> {
+        locate(&self.path).write(&serde_json::to_string_pretty(jobs)?)
+    }
+
+    pub fn upsert(&self, job: &BatchJob) -> Result<()> {
+        let mut jobs = self.list()?;
+        match jobs.iter_mut().find(|existing| existing.id == job.id) {
+            Some(existing) => *existing = job.clone(),
+            None => jobs.push(job.clone()),
+        }
+        self.save(&jobs)
+    }
+}
+
+pub fn render_results(job: &BatchJob, results: &[BatchResult]) -> String {
+    let mut out = format!("# {}\n\nBatch {} via {}\n", job.label, job.id, job.model);
+    for result in results {
+        out.push_str(&format!("\n## {}\n\n", result.custom_id));
+        match (&result.text, &result.error) {
+            (Some(text), _) => out.push_str(text.trim()),
+            (None, Some(error)) => out.push_str(&format!("(failed: {error})")),
+            (None, None) => out.push_str("(no output)"),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+// One bulk submission: the same instruction applied to every item.
+#[derive(Debug, Clone)]
+pub struct BatchRequest {
+    pub model: Option<String>,
+    pub label: String,
+    pub instruction: String,
+    pub items: Vec<BatchItem>,
+    pub max_tokens: u32,
+}
+
+// Submits bulk prompts to provider batch APIs and reports back to the
+// originating chat once the provider has finished them.
+pub struct BatchService {
+    config: Config,
+    store: BatchStore,
+    bus: Arc<MessageBus>,
+    lease: Option<LeaderLease>,
+    running: Arc<AtomicBool>,
+    task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl BatchService {
+    pub fn new(config: Config, store: BatchStore, bus: Arc<MessageBus>) -> Self {
+        Self {
+            config,
+            store,
+            bus,
+            lease: None,
+            running: Arc::new(AtomicBool::new(false)),
+            task: Mutex::new(None),
+        }
+    }
+
+    // In cluster mode only the leader polls, so results are delivered once.
+    pub fn with_leader_lease(mut self, lease: LeaderLease) -> Self {
+        self.lease = Some(lease);
+        self
+    }
+
+    pub fn jobs(&self) -> Result<Vec<BatchJob>> {
+        self.store.list()
+    }
+
+    pub async fn submit(
+        &self,
+        request: BatchRequest,
+        channel: &str,
+        chat_id: &str,
+    ) -> Result<BatchJob> {
+        let BatchRequest {
+            model,
+            label,
+            instruction,
+            items,
+            max_tokens,
+        } = request;
+        if items.is_empty() {
+            bail!("a batch needs at least one item");
+        }
+        if items.len() > MAX_BATCH_ITEMS {
+            bail!("a batch can hold at most {MAX_BATCH_ITEMS} items");
+        }
+        let model = model
+            .as_deref()
+            .unwrap_or(&self.config.agents.defaults.model);
+        let (api, model) = BatchApi::for_model(&self.config, model)?;
+        let batch_id = api.submit(&model, &instruction, &items, max_tokens).await?;
+        let job = BatchJob {
+            id: Uuid::new_v4().simple().to_string()[..8].to_string(),
+            label,
+            provider: api.provider().to_string(),
+            model,
+            batch_id,
+            channel: channel.to_string(),
+            chat_id: chat_id.to_string(),
+            item_count: items.len(),
+            status: "pending".to_string(),
+            detail: None,
+            results_path: None,
+            submitted_at_ms: Utc::now().timestamp_millis(),
+            finished_at_ms: None,
+        };
+        self.store.upsert(&job)?;
+        Ok(job)
+    }
+
+    async fn poll_job(&self, mut job: BatchJob) -> Result<()> {
+        let api = BatchApi::for_provider(&self.config, &job.provider)?;
+        let results = match api.poll(&job.batch_id).await? {
+            BatchPoll::Pending(status) => {
+                if job.detail.as_deref() != Some(status.as_str()) {
+                    job.detail = Some(status);
+                    self.store.upsert(&job)?;
+                }
+                return Ok(());
+            }
+            BatchPoll::Failed(reason) => {
+                job.status = "failed".to_string();
+                job.detail = Some(reason.clone());
+                job.finished_at_ms = Some(Utc::now().timestamp_millis());
+                self.store.upsert(&job)?;
+                self.announce(&job, &format!("failed ({reason})")).await;
+                return Ok(());
+            }
+            BatchPoll::Done(results) => results,
+        };
+
+        let dir = self.config.workspace_path().join("batches");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.md", job.id));
+        std::fs::write(&path, render_results(&job, &results))?;
+        let succeeded = results.iter().filter(|r| r.text.is_some()).count();
+        job.status = "done".to_string();
+        job.detail = Some(format!("{succeeded}/{} succeeded", job.item_count));
+        job.results_path = Some(path.display().to_string());
+        job.finished_at_ms = Some(Utc::now().timestamp_millis());
+        self.store.upsert(&job)?;
+        self.announce(
+            &job,
+            &format!(
+                "finished: {succeeded}/{} items succeeded. Results are in {}",
+                job.item_count,
+                path.display()
+            ),
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn announce(&self, job: &BatchJob, outcome: &str) {
+        let content = format!(
+            "[Batch job '{}' {outcome}]\n\nRead the results if needed and continue with whatever the batch was submitted for. Keep the reply to the user brief.",
+            job.label
+        );
+        let msg = InboundMessage::new(
+            "system",
+            "batch",
+            format!("{}:{}", job.channel, job.chat_id),
+            content,
+        );
+        let _ = self.bus.publish_inbound(msg).await;
+    }
+
+    // Jobs still waiting on the provider, whichever instance submitted them.
+    pub fn pending(&self) -> Result<Vec<BatchJob>> {
+        Ok(self
+            .store
+            .list()?
+            .into_iter()
+            .filter(|job| job.status == "pending")
+            .collect())
+    }
+
+    pub async fn poll_pending(&self) -> Result<()> {
+        for job in self.pending()? {
+            let id = job.id.clone();
+            if let Err(err) = self.poll_job(job).await {
+                eprintln!("Warning: failed to poll batch job {id}: {err}");
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn start(self: &Arc<Self>) {
+        self.running.store(true, Ordering::Relaxed);
+        let service = self.clone();
+        let handle = tokio::spawn(async move {
+            while service.running.load(Ordering::Relaxed) {
+                let leader = service.lease.as_ref().is_none_or(LeaderLease::is_leader);
+                if leader && let Err(err) = service.poll_pending().await {
+                    eprintln!("Warning: failed to read batch jobs: {err}");
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(BATCH_POLL_INTERVAL_S)).await;
+            }
+        });
+        *self.task.lock().await = Some(handle);
+    }
+
+    pub async fn stop(&self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(handle) = self.task.lock().await.take() {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn store_round_trips_and_renders_results() {
+        let dir = std::env::temp_dir().join(format!("nanobot-batch-{}", Uuid::new_v4()));
+        let store = BatchStore::from_path(dir.join("jobs.json"));
+        let mut job = BatchJob {
+            id: "abc12345".to_string(),
+            label: "feed digest".to_string(),
+            provider: "anthropic".to_string(),
+            model: "claude-haiku-4-5".to_string(),
+            batch_id: "msgbatch_1".to_string(),
+            channel: "telegram".to_string(),
+            chat_id: "42".to_string(),
+            item_count: 2,
+            status: "pending".to_string(),
+            detail: None,
+            results_path: None,
+            submitted_at_ms: 0,
+            finished_at_ms: None,
+        };
+        store.upsert(&job).expect("save");
+        job.status = "done".to_string();
+        store.upsert(&job).expect("update");
+        let jobs = store.list().expect("list");
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].status, "done");
+
+        let rendered = render_results(
+            &job,
+            &[
+                BatchResult {
+                    custom_id: "item-1".to_string(),
+                    text: Some("Rust 1.90 released.".to_string()),
+                    error: None,
+                },
+                BatchResult {
+                    custom_id: "item-2".to_string(),
+                    text: None,
+                    error: Some("overloaded".to_string()),
+                },
+            ],
+        );
+        assert!(rendered.contains("## item-1\n\nRust 1.90 released."));
+        assert!(rendered.contains("(failed: overloaded)"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn leader_sees_batches_submitted_on_a_follower() {
+        let dir = std::env::temp_dir().join(format!("nanobot-batch-{}", Uuid::new_v4()));
+        let lease = |id: &str| {
+            LeaderLease::from_path(
+                dir.join("leader.json"),
+                id.to_string(),
+                std::time::Duration::from_secs(30),
+            )
+        };
+        let service = |lease: LeaderLease| {
+            BatchService::new(
+                Config::default(),
+                BatchStore::from_path(dir.join("jobs.json")),
+                Arc::new(MessageBus::new(8)),
+            )
+            .with_leader_lease(lease)
+        };
+        let (leader_lease, follower_lease) = (lease("a"), lease("b"));
+        leader_lease.step(0).expect("claim");
+        assert!(leader_lease.step(1).expect("renew"));
+        let leader = service(leader_lease);
+        let follower = service(follower_lease);
+        assert!(leader.pending().expect("pending").is_empty());
+
+        // What `submit` stores once the provider has accepted the batch.
+        let job = BatchJob {
+            id: "abc12345".to_string(),
+            label: "feed digest".to_string(),
+            provider: "anthropic".to_string(),
+            model: "claude-haiku-4-5".to_string(),
+            batch_id: "msgbatch_1".to_string(),
+            channel: "telegram".to_string(),
+            chat_id: "42".to_string(),
+            item_count: 2,
+            status: "pending".to_string(),
+            detail: None,
+            results_path: None,
+            submitted_at_ms: 0,
+            finished_at_ms: None,
+        };
+        follower.store.upsert(&job).expect("submit");
+        let pending = leader.pending().expect("pending");
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].batch_id, "msgbatch_1");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
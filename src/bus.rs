@@ -1,8 +1,12 @@
+use crate::channels::botloop::BotLoopGuard;
+use crate::channels::dedup::InboundDedup;
+use crate::hooks::ScriptHooks;
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 use std::sync::atomic::{AtomicUsize, Ordering};
-use tokio::sync::{Mutex, mpsc};
+use std::sync::{Arc, OnceLock};
+use tokio::sync::{Mutex, broadcast, mpsc};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InboundMessage {
@@ -12,6 +16,11 @@ pub struct InboundMessage {
     pub content: String,
     pub timestamp: DateTime<Local>,
     pub media: Vec<String>,
+    // Platform ids: this message, the thread it was posted in, and the message
+    // it answers. Channels without native threading leave them empty.
+    pub message_id: Option<String>,
+    pub thread_id: Option<String>,
+    pub reply_to: Option<String>,
     pub metadata: Map<String, Value>,
 }
 
@@ -29,6 +38,9 @@ impl InboundMessage {
             content: content.into(),
             timestamp: Local::now(),
             media: Vec::new(),
+            message_id: None,
+            thread_id: None,
+            reply_to: None,
             metadata: Map::new(),
         }
     }
@@ -36,6 +48,35 @@ impl InboundMessage {
     pub fn session_key(&self) -> String {
         format!("{}:{}", self.channel, self.chat_id)
     }
+
+    // Whether the chat has other participants besides the sender, from
+    // whatever each channel reports.
+    pub fn is_group(&self) -> bool {
+        let field = |key: &str| self.metadata.get(key).and_then(Value::as_str);
+        self.metadata.get("is_group").and_then(Value::as_bool) == Some(true)
+            || field("chat_type") == Some("group")
+            || field("guild_id").is_some_and(|id| !id.is_empty())
+            || field("conversation_type") == Some("2")
+            || field("target_kind") == Some("panel")
+            || self
+                .metadata
+                .get("slack")
+                .and_then(|slack| slack.get("channel_type"))
+                .and_then(Value::as_str)
+                .is_some_and(|kind| matches!(kind, "channel" | "group" | "mpim"))
+    }
+
+    // Response routed back to where this message came from: same thread, and
+    // quoting it when the sender was themselves replying to something.
+    pub fn reply(&self, content: impl Into<String>) -> OutboundMessage {
+        let mut out = OutboundMessage::new(self.channel.clone(), self.chat_id.clone(), content);
+        out.thread_id = self.thread_id.clone();
+        if self.reply_to.is_some() {
+            out.reply_to = self.message_id.clone();
+        }
+        out.metadata = self.metadata.clone();
+        out
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,6 +84,7 @@ pub struct OutboundMessage {
     pub channel: String,
     pub chat_id: String,
     pub content: String,
+    pub thread_id: Option<String>,
     pub reply_to: Option<String>,
     pub media: Vec<String>,
     pub metadata: Map<String, Value>,
@@ -58,6 +100,7 @@ impl OutboundMessage {
             channel: channel.into(),
             chat_id: chat_id.into(),
             content: content.into(),
+            thread_id: None,
             reply_to: None,
             media: Vec::new(),
             metadata: Map::new(),
@@ -65,6 +108,31 @@ impl OutboundMessage {
     }
 }
 
+// The agent started (`active`) or finished working on a reply for a chat, so
+// channels can show a typing or progress indicator meanwhile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnActivity {
+    pub channel: String,
+    pub chat_id: String,
+    pub thread_id: Option<String>,
+    pub message_id: Option<String>,
+    pub active: bool,
+}
+
+// Signals the end of a turn when dropped, however the turn finished.
+pub struct TurnActivityGuard {
+    tx: broadcast::Sender<TurnActivity>,
+    activity: TurnActivity,
+}
+
+impl Drop for TurnActivityGuard {
+    fn drop(&mut self) {
+        let mut done = self.activity.clone();
+        done.active = false;
+        let _ = self.tx.send(done);
+    }
+}
+
 pub struct MessageBus {
     inbound_tx: mpsc::Sender<InboundMessage>,
     inbound_rx: Mutex<mpsc::Receiver<InboundMessage>>,
@@ -72,12 +140,17 @@ pub struct MessageBus {
     outbound_rx: Mutex<mpsc::Receiver<OutboundMessage>>,
     inbound_size: AtomicUsize,
     outbound_size: AtomicUsize,
+    activity_tx: broadcast::Sender<TurnActivity>,
+    dedup: OnceLock<InboundDedup>,
+    loop_guard: OnceLock<Arc<BotLoopGuard>>,
+    script_hooks: OnceLock<Arc<ScriptHooks>>,
 }
 
 impl MessageBus {
     pub fn new(capacity: usize) -> Self {
         let (inbound_tx, inbound_rx) = mpsc::channel(capacity);
         let (outbound_tx, outbound_rx) = mpsc::channel(capacity);
+        let (activity_tx, _) = broadcast::channel(64);
         Self {
             inbound_tx,
             inbound_rx: Mutex::new(inbound_rx),
@@ -85,9 +158,31 @@ impl MessageBus {
             outbound_rx: Mutex::new(outbound_rx),
             inbound_size: AtomicUsize::new(0),
             outbound_size: AtomicUsize::new(0),
+            activity_tx,
+            dedup: OnceLock::new(),
+            loop_guard: OnceLock::new(),
+            script_hooks: OnceLock::new(),
         }
     }
 
+    // Once set, inbound messages whose id was already published for the same
+    // chat are dropped.
+    pub fn set_dedup(&self, dedup: InboundDedup) {
+        let _ = self.dedup.set(dedup);
+    }
+
+    pub fn set_loop_guard(&self, guard: Arc<BotLoopGuard>) {
+        let _ = self.loop_guard.set(guard);
+    }
+
+    pub fn set_script_hooks(&self, hooks: Arc<ScriptHooks>) {
+        let _ = self.script_hooks.set(hooks);
+    }
+
+    pub fn script_hooks(&self) -> Option<&Arc<ScriptHooks>> {
+        self.script_hooks.get()
+    }
+
     pub fn inbound_sender(&self) -> mpsc::Sender<InboundMessage> {
         self.inbound_tx.clone()
     }
@@ -97,6 +192,23 @@ impl MessageBus {
     }
 
     pub async fn publish_inbound(&self, msg: InboundMessage) -> anyhow::Result<()> {
+        if let (Some(dedup), Some(message_id)) = (self.dedup.get(), &msg.message_id)
+            && !dedup.first_sighting(&msg.channel, &msg.chat_id, message_id)
+        {
+            return Ok(());
+        }
+        if let Some(guard) = self.loop_guard.get()
+            && !guard.admit(&msg)
+        {
+            return Ok(());
+        }
+        let msg = match self.script_hooks.get() {
+            Some(hooks) => match hooks.on_inbound(msg) {
+                Some(msg) => msg,
+                None => return Ok(()),
+            },
+            None => msg,
+        };
         self.inbound_size.fetch_add(1, Ordering::Relaxed);
         if let Err(err) = self.inbound_tx.send(msg).await {
             self.inbound_size.fetch_sub(1, Ordering::Relaxed);
@@ -132,6 +244,27 @@ impl MessageBus {
         msg
     }
 
+    // Announces that a turn for `msg`'s chat started; the returned guard
+    // announces its end. Nobody listening (CLI, tests) is fine.
+    pub fn begin_activity(&self, msg: &InboundMessage) -> TurnActivityGuard {
+        let activity = TurnActivity {
+            channel: msg.channel.clone(),
+            chat_id: msg.chat_id.clone(),
+            thread_id: msg.thread_id.clone(),
+            message_id: msg.message_id.clone(),
+            active: true,
+        };
+        let _ = self.activity_tx.send(activity.clone());
+        TurnActivityGuard {
+            tx: self.activity_tx.clone(),
+            activity,
+        }
+    }
+
+    pub fn subscribe_activity(&self) -> broadcast::Receiver<TurnActivity> {
+        self.activity_tx.subscribe()
+    }
+
     pub fn inbound_size(&self) -> usize {
         self.inbound_size.load(Ordering::Relaxed)
     }
@@ -140,3 +273,28 @@ impl MessageBus {
         self.outbound_size.load(Ordering::Relaxed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn activity_guard_signals_start_and_end() {
+        let bus = MessageBus::new(8);
+        let mut rx = bus.subscribe_activity();
+        let mut msg = InboundMessage::new("slack", "U1", "C1", "hi");
+        msg.message_id = Some("171.2".to_string());
+        {
+            let _turn = bus.begin_activity(&msg);
+            let started = rx.recv().await.expect("start");
+            assert!(started.active);
+            assert_eq!(started.message_id.as_deref(), Some("171.2"));
+        }
+        let ended = rx.recv().await.expect("end");
+        assert!(!ended.active);
+        assert_eq!(
+            (ended.channel.as_str(), ended.chat_id.as_str()),
+            ("slack", "C1")
+        );
+    }
+}
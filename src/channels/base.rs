@@ -1,6 +1,10 @@
 use crate::bus::OutboundMessage;
-use crate::bus::{InboundMessage, MessageBus};
-use crate::pairing::{issue_pairing, pairing_prompt};
+use crate::bus::{InboundMessage, MessageBus, TurnActivity};
+use crate::channels::format::{ChannelCapabilities, FormattedMessage, format_message};
+use crate::pairing::{
+    answer_challenge, challenge_prompt, configured_challenge, is_guest, issue_pairing,
+    pairing_prompt,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use serde_json::{Map, Value};
@@ -17,10 +21,30 @@ pub trait Channel: Send + Sync {
     async fn stop(&self) -> Result<()>;
     async fn send(&self, msg: &crate::bus::OutboundMessage) -> Result<()>;
 
+    fn capabilities(&self) -> ChannelCapabilities {
+        ChannelCapabilities::default()
+    }
+
+    fn format(&self, content: &str) -> FormattedMessage {
+        format_message(self.capabilities(), content)
+    }
+
+    // Called when the agent starts and finishes a turn for one of this
+    // channel's chats. Channels with a typing or progress indicator show it.
+    async fn set_typing(&self, _activity: &TurnActivity) -> Result<()> {
+        Ok(())
+    }
+
     fn is_allowed(&self, sender_id: &str) -> bool {
         is_allowed_sender(sender_id, self.allow_from())
     }
 
+    // Channels whose allowlist depends on more than the sender (per-account
+    // email allowlists) override this.
+    fn admits(&self, msg: &InboundMessage) -> bool {
+        self.is_allowed(&msg.sender_id)
+    }
+
     async fn handle_message(
         &self,
         sender_id: String,
@@ -29,19 +53,39 @@ pub trait Channel: Send + Sync {
         media: Vec<String>,
         metadata: Map<String, Value>,
     ) -> Result<()> {
-        if !self.is_allowed(&sender_id) {
-            if let Ok(issue) = issue_pairing(self.name(), &sender_id, &chat_id) {
-                let prompt = pairing_prompt(&issue);
+        let mut msg = InboundMessage::new(self.name(), sender_id, chat_id, content);
+        msg.media = media;
+        msg.metadata = metadata;
+        self.handle_inbound(msg).await
+    }
+
+    async fn handle_inbound(&self, mut msg: InboundMessage) -> Result<()> {
+        if !self.admits(&msg) {
+            if is_guest(self.name(), &msg.sender_id) {
+                msg.metadata.insert("guest".to_string(), Value::Bool(true));
+                self.bus().publish_inbound(msg).await?;
+                return Ok(());
+            }
+            let prompt = match configured_challenge() {
+                Some(challenge) => answer_challenge(
+                    &challenge,
+                    self.name(),
+                    &msg.sender_id,
+                    &msg.chat_id,
+                    &msg.content,
+                )
+                .map(|outcome| challenge_prompt(&outcome)),
+                None => issue_pairing(self.name(), &msg.sender_id, &msg.chat_id)
+                    .map(|issue| pairing_prompt(&issue)),
+            };
+            if let Ok(prompt) = prompt {
                 let _ = self
                     .bus()
-                    .publish_outbound(OutboundMessage::new(self.name(), chat_id.clone(), prompt))
+                    .publish_outbound(OutboundMessage::new(self.name(), msg.chat_id, prompt))
                     .await;
             }
             return Ok(());
         }
-        let mut msg = InboundMessage::new(self.name(), sender_id, chat_id, content);
-        msg.media = media;
-        msg.metadata = metadata;
         self.bus().publish_inbound(msg).await?;
         Ok(())
     }
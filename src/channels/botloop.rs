@@ -0,0 +1,124 @@
+use crate::bus::{InboundMessage, OutboundMessage};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+const RECENT_SENT: usize = 20;
+// Short replies like "ok" or "thanks" repeat naturally and say nothing about
+// an echo loop.
+const MIN_ECHO_CHARS: usize = 20;
+
+#[derive(Default)]
+struct ChatState {
+    bot_streak: u32,
+    recent_sent: VecDeque<String>,
+}
+
+// Stops bot-to-bot ping-pong: counts consecutive inbound messages per chat that
+// come from a bot, or that repeat something the agent just sent (bridges
+// relaying our own output back), and drops them past the limit. Any human
+// message resets the streak.
+pub struct BotLoopGuard {
+    limit: u32,
+    chats: Mutex<HashMap<(String, String), ChatState>>,
+}
+
+impl BotLoopGuard {
+    pub fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            chats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record_sent(&self, msg: &OutboundMessage) {
+        let text = normalize(&msg.content);
+        if text.chars().count() < MIN_ECHO_CHARS {
+            return;
+        }
+        let mut chats = self.chats.lock().unwrap_or_else(|e| e.into_inner());
+        let state = chats
+            .entry((msg.channel.clone(), msg.chat_id.clone()))
+            .or_default();
+        state.recent_sent.push_back(text);
+        while state.recent_sent.len() > RECENT_SENT {
+            state.recent_sent.pop_front();
+        }
+    }
+
+    // Returns false when the message should be dropped.
+    pub fn admit(&self, msg: &InboundMessage) -> bool {
+        let mut chats = self.chats.lock().unwrap_or_else(|e| e.into_inner());
+        let state = chats
+            .entry((msg.channel.clone(), msg.chat_id.clone()))
+            .or_default();
+        let text = normalize(&msg.content);
+        let echoed = state.recent_sent.iter().any(|sent| *sent == text);
+        if !is_bot_message(msg) && !echoed {
+            state.bot_streak = 0;
+            return true;
+        }
+        state.bot_streak += 1;
+        if state.bot_streak == self.limit + 1 {
+            eprintln!(
+                "Bot loop suspected in {}:{} after {} exchanges; ignoring bot messages until a human replies",
+                msg.channel, msg.chat_id, self.limit
+            );
+        }
+        state.bot_streak <= self.limit
+    }
+}
+
+pub fn is_bot_message(msg: &InboundMessage) -> bool {
+    msg.metadata
+        .get("is_bot")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_bot(content: &str) -> InboundMessage {
+        let mut msg = InboundMessage::new("telegram", "other_bot", "-100", content);
+        msg.metadata.insert("is_bot".to_string(), Value::Bool(true));
+        msg
+    }
+
+    #[test]
+    fn stops_after_limit_until_a_human_speaks() {
+        let guard = BotLoopGuard::new(2);
+        assert!(guard.admit(&from_bot("hello")));
+        assert!(guard.admit(&from_bot("hello again")));
+        assert!(!guard.admit(&from_bot("and again")));
+
+        assert!(guard.admit(&InboundMessage::new("telegram", "alice", "-100", "stop it")));
+        assert!(guard.admit(&from_bot("hello")));
+    }
+
+    #[test]
+    fn treats_echoes_of_own_output_as_bot_messages() {
+        let guard = BotLoopGuard::new(0);
+        guard.record_sent(&OutboundMessage::new(
+            "whatsapp",
+            "123",
+            "Here is the weather for  today: sunny",
+        ));
+        let echo = InboundMessage::new(
+            "whatsapp",
+            "bridge",
+            "123",
+            "here is the weather for today: sunny",
+        );
+        assert!(!guard.admit(&echo));
+        assert!(guard.admit(&InboundMessage::new("whatsapp", "bob", "123", "thanks")));
+    }
+}
@@ -0,0 +1,140 @@
+use crate::bus::OutboundMessage;
+use crate::channels::format::split_at_lines;
+use crate::channels::markdown::{BlockKind, block_ranges};
+
+// Splits a markdown reply into parts of at most `limit` characters, breaking
+// between blocks and preferring to start a part at a heading. Oversized blocks
+// fall back to line breaks; code fences are closed and reopened across parts.
+pub fn split_reply(content: &str, limit: usize) -> Vec<String> {
+    if limit == 0 || content.chars().count() <= limit {
+        return vec![content.to_string()];
+    }
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    for (kind, range) in block_ranges(content) {
+        let block = content[range].trim_end();
+        if block.trim().is_empty() {
+            continue;
+        }
+        let len = block.chars().count();
+        let joined = current.chars().count() + 2 + len;
+        // A heading opens a new part once the current one is half full, so
+        // sections are not cut right after their title.
+        let heading_break = kind == BlockKind::Heading && current.chars().count() * 2 >= limit;
+        if !current.is_empty() && (joined > limit || heading_break) {
+            parts.push(std::mem::take(&mut current));
+        }
+        if len > limit {
+            parts.extend(split_block(block, limit));
+            continue;
+        }
+        if !current.is_empty() {
+            current.push_str("\n\n");
+        }
+        current.push_str(block);
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    if parts.is_empty() {
+        parts.push(content.to_string());
+    }
+    parts
+}
+
+fn split_block(block: &str, limit: usize) -> Vec<String> {
+    let Some((open, body)) = block.split_once('\n') else {
+        return split_at_lines(block, limit);
+    };
+    let fence = open.trim_start();
+    let marker = if fence.starts_with("```") {
+        "```"
+    } else if fence.starts_with("~~~") {
+        "~~~"
+    } else {
+        return split_at_lines(block, limit);
+    };
+    let body = body
+        .trim_end()
+        .strip_suffix(marker)
+        .unwrap_or(body)
+        .trim_end_matches('\n');
+    let overhead = open.chars().count() + marker.len() + 2;
+    if limit <= overhead {
+        return split_at_lines(block, limit);
+    }
+    split_at_lines(body, limit - overhead)
+        .into_iter()
+        .map(|code| format!("{open}\n{code}\n{marker}"))
+        .collect()
+}
+
+// One outbound message per part. Every part keeps the reply's routing and
+// metadata; attachments ride on the last part so they follow the full text.
+pub fn split_outbound(msg: &OutboundMessage, limit: usize) -> Vec<OutboundMessage> {
+    let parts = split_reply(&msg.content, limit);
+    if parts.len() == 1 {
+        return vec![msg.clone()];
+    }
+    let last = parts.len() - 1;
+    parts
+        .into_iter()
+        .enumerate()
+        .map(|(i, content)| OutboundMessage {
+            content,
+            media: if i == last {
+                msg.media.clone()
+            } else {
+                Vec::new()
+            },
+            ..msg.clone()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_at_headings_and_paragraphs_within_limit() {
+        let intro = "Intro paragraph that is fairly long.";
+        let body = "Body paragraph under the section.";
+        let content = format!("{intro}\n\n## Section\n\n{body}\n\n{body}");
+        assert_eq!(split_reply(&content, 0), vec![content.clone()]);
+        assert_eq!(split_reply("short", 100), vec!["short".to_string()]);
+
+        let parts = split_reply(&content, 70);
+        assert_eq!(
+            parts,
+            vec![
+                intro.to_string(),
+                format!("## Section\n\n{body}"),
+                body.to_string()
+            ]
+        );
+        assert!(parts.iter().all(|part| part.chars().count() <= 70));
+    }
+
+    #[test]
+    fn reopens_code_fences_across_parts() {
+        let lines = (0..20)
+            .map(|i| format!("let v{i} = {i};"))
+            .collect::<Vec<_>>();
+        let content = format!("```rust\n{}\n```", lines.join("\n"));
+        let parts = split_reply(&content, 80);
+        assert!(parts.len() > 1);
+        for part in &parts {
+            assert!(part.starts_with("```rust\n"));
+            assert!(part.ends_with("\n```"));
+            assert!(part.chars().count() <= 80);
+        }
+
+        let mut msg = OutboundMessage::new("telegram", "42", content);
+        msg.media.push("/tmp/chart.png".to_string());
+        let split = split_outbound(&msg, 80);
+        assert_eq!(split.len(), parts.len());
+        assert!(split[0].media.is_empty());
+        assert_eq!(split[split.len() - 1].media, msg.media);
+    }
+}
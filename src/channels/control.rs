@@ -0,0 +1,228 @@
+use crate::agent::AgentLoop;
+use crate::channels::latency::LatencyRow;
+use crate::channels::manager::ChannelManager;
+use crate::config::load_config;
+use anyhow::{Result, anyhow};
+use serde_json::{Value, json};
+use std::sync::Arc;
+use tiny_http::{Header, Method, Response, Server, StatusCode};
+use tokio::runtime::Handle;
+
+// Loopback-only control API of a running gateway:
+//   GET  /api/channels                -> adapters and whether they run
+//   POST /api/channels/<name>/enable  -> start it from the current config.json
+//   POST /api/channels/<name>/disable -> stop it, leaving the others running
+//   GET  /api/metrics                 -> reply latency percentiles
+//   GET  /api/tools                   -> tool schemas offered to the model
+//   POST /api/tools/reload            -> re-apply tools.disabled from config.json
+pub fn serve(manager: Arc<ChannelManager>, agent: Arc<AgentLoop>, port: u16) -> Result<()> {
+    let server = Server::http(("127.0.0.1", port)).map_err(|err| anyhow!(err.to_string()))?;
+    let runtime = Handle::current();
+    std::thread::spawn(move || {
+        for req in server.incoming_requests() {
+            let (status, body) = if req.url().starts_with("/api/tools") {
+                route_tools(&agent, req.method(), req.url())
+            } else {
+                runtime.block_on(route(&manager, req.method(), req.url()))
+            };
+            let mut response =
+                Response::from_string(body.to_string()).with_status_code(StatusCode(status));
+            if let Ok(header) = Header::from_bytes(b"Content-Type".as_slice(), b"application/json")
+            {
+                response.add_header(header);
+            }
+            let _ = req.respond(response);
+        }
+    });
+    Ok(())
+}
+
+fn route_tools(agent: &AgentLoop, method: &Method, url: &str) -> (u16, Value) {
+    match (method, url) {
+        (Method::Get, "/api/tools") => (200, Value::Array(agent.tool_schemas())),
+        (Method::Post, "/api/tools/reload") => match load_config(None) {
+            Ok(config) => {
+                let (disabled, enabled) = agent.apply_disabled_tools(&config.tools.disabled);
+                let mut changes = disabled
+                    .iter()
+                    .map(|name| format!("{name} disabled"))
+                    .chain(enabled.iter().map(|name| format!("{name} enabled")))
+                    .collect::<Vec<_>>();
+                if changes.is_empty() {
+                    changes.push("no tool changes".to_string());
+                }
+                (200, json!({"ok": true, "message": changes.join(", ")}))
+            }
+            Err(err) => (400, json!({"ok": false, "error": err.to_string()})),
+        },
+        _ => (404, json!({"error": "not found"})),
+    }
+}
+
+async fn route(manager: &ChannelManager, method: &Method, url: &str) -> (u16, Value) {
+    if *method == Method::Get && url == "/api/channels" {
+        return (200, manager.get_status());
+    }
+    if *method == Method::Get && url == "/api/metrics" {
+        return (200, json!({ "latency": manager.latency() }));
+    }
+    let Some((name, action)) = url
+        .strip_prefix("/api/channels/")
+        .and_then(|rest| rest.split_once('/'))
+    else {
+        return (404, json!({"error": "not found"}));
+    };
+    if *method != Method::Post {
+        return (405, json!({"error": "use POST"}));
+    }
+    let result = match action {
+        "enable" => match load_config(None) {
+            Ok(config) => manager.enable_channel(&config, name).await.map(|started| {
+                if started {
+                    format!("{name} started")
+                } else {
+                    format!("{name} already running")
+                }
+            }),
+            Err(err) => Err(err),
+        },
+        "disable" => manager.disable_channel(name).await.map(|stopped| {
+            if stopped {
+                format!("{name} stopped")
+            } else {
+                format!("{name} was not running")
+            }
+        }),
+        _ => return (404, json!({"error": format!("unknown action: {action}")})),
+    };
+    match result {
+        Ok(message) => (200, json!({"ok": true, "message": message})),
+        Err(err) => (400, json!({"ok": false, "error": err.to_string()})),
+    }
+}
+
+// Asks a gateway on this host to start or stop one channel.
+pub async fn set_channel(port: u16, name: &str, enabled: bool) -> Result<String> {
+    let action = if enabled { "enable" } else { "disable" };
+    post(port, &format!("/api/channels/{name}/{action}")).await
+}
+
+// Asks a gateway on this host to pick up `tools.disabled` from config.json.
+pub async fn reload_tools(port: u16) -> Result<String> {
+    post(port, "/api/tools/reload").await
+}
+
+// Reply latency percentiles from a gateway on this host.
+pub async fn latency(port: u16) -> Result<Vec<LatencyRow>> {
+    let body: Value = reqwest::Client::new()
+        .get(format!("http://127.0.0.1:{port}/api/metrics"))
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await?
+        .json()
+        .await?;
+    Ok(serde_json::from_value(
+        body.get("latency").cloned().unwrap_or_else(|| json!([])),
+    )?)
+}
+
+async fn post(port: u16, path: &str) -> Result<String> {
+    let body: Value = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{port}{path}"))
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await?
+        .json()
+        .await?;
+    match body.get("message").and_then(Value::as_str) {
+        Some(message) => Ok(message.to_string()),
+        None => Err(anyhow!(
+            "{}",
+            body.get("error")
+                .and_then(Value::as_str)
+                .unwrap_or("unexpected response")
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::MessageBus;
+    use crate::config::WebSearchConfig;
+    use crate::providers::base::{LLMProvider, LLMResponse};
+    use crate::session::SessionManager;
+    use async_trait::async_trait;
+
+    struct IdleProvider;
+
+    #[async_trait]
+    impl LLMProvider for IdleProvider {
+        async fn chat(
+            &self,
+            _messages: &[Value],
+            _tools: Option<&[Value]>,
+            _model: Option<&str>,
+            _max_tokens: u32,
+            _temperature: f32,
+        ) -> Result<LLMResponse> {
+            Err(anyhow!("not used"))
+        }
+
+        fn default_model(&self) -> &str {
+            "idle"
+        }
+    }
+
+    #[test]
+    fn tools_route_lists_the_schemas_offered_to_the_model() {
+        let root = std::env::temp_dir().join(format!("nanobot-control-{}", uuid::Uuid::new_v4()));
+        let workspace = root.join("workspace");
+        std::fs::create_dir_all(&workspace).expect("workspace");
+        let agent = AgentLoop::new(
+            Arc::new(MessageBus::new(10)),
+            Arc::new(IdleProvider),
+            workspace,
+            None,
+            5,
+            20,
+            WebSearchConfig::default(),
+            60,
+            true,
+            None,
+            Some(Arc::new(
+                SessionManager::from_dir(root.join("sessions")).expect("sessions"),
+            )),
+        )
+        .expect("agent")
+        .with_data_dir(&root);
+
+        let (status, body) = route_tools(&agent, &Method::Get, "/api/tools");
+        assert_eq!(status, 200);
+        let names = body
+            .as_array()
+            .expect("array")
+            .iter()
+            .map(|schema| {
+                assert_eq!(schema["type"], "function");
+                assert!(schema["function"]["parameters"].is_object());
+                schema["function"]["name"].as_str().expect("name")
+            })
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"read_file"));
+        let mut sorted = names.clone();
+        sorted.sort();
+        assert_eq!(names, sorted);
+
+        agent.apply_disabled_tools(&["read_file".to_string()]);
+        let (_, body) = route_tools(&agent, &Method::Get, "/api/tools");
+        assert!(
+            body.as_array()
+                .expect("array")
+                .iter()
+                .all(|schema| schema["function"]["name"] != "read_file")
+        );
+        assert_eq!(route_tools(&agent, &Method::Get, "/api/tools/x").0, 404);
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::utils::get_data_path;
+use anyhow::Result;
+use std::collections::{HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+const MAX_SEEN_IDS: usize = 2000;
+
+#[derive(Default)]
+struct SeenIds {
+    order: VecDeque<String>,
+    seen: HashSet<String>,
+}
+
+// Remembers the most recent inbound message ids across all channels, persisted
+// so webhook redeliveries and reconnect replays after a restart are dropped.
+pub struct InboundDedup {
+    path: PathBuf,
+    state: Mutex<SeenIds>,
+}
+
+impl InboundDedup {
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_path(
+            get_data_path()?.join("inbox").join("seen.json"),
+        ))
+    }
+
+    pub fn from_path(path: PathBuf) -> Self {
+        let order = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|raw| serde_json::from_str::<VecDeque<String>>(&raw).ok())
+            .unwrap_or_default();
+        let seen = order.iter().cloned().collect();
+        Self {
+            path,
+            state: Mutex::new(SeenIds { order, seen }),
+        }
+    }
+
+    // True the first time a message id is observed in a chat. Ids are scoped
+    // by chat because several platforms number messages per conversation.
+    pub fn first_sighting(&self, channel: &str, chat_id: &str, message_id: &str) -> bool {
+        let key = format!("{channel}:{chat_id}:{message_id}");
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.seen.insert(key.clone()) {
+            return false;
+        }
+        state.order.push_back(key);
+        while state.order.len() > MAX_SEEN_IDS {
+            if let Some(old) = state.order.pop_front() {
+                state.seen.remove(&old);
+            }
+        }
+        if let Err(err) = self.save(&state.order) {
+            eprintln!("Warning: failed to persist inbound dedup state: {err}");
+        }
+        true
+    }
+
+    fn save(&self, order: &VecDeque<String>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string(order)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_repeats_across_restarts() {
+        let dir = std::env::temp_dir().join(format!("nanobot-dedup-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("seen.json");
+
+        let dedup = InboundDedup::from_path(path.clone());
+        assert!(dedup.first_sighting("telegram", "1", "42"));
+        assert!(!dedup.first_sighting("telegram", "1", "42"));
+        assert!(dedup.first_sighting("telegram", "2", "42"));
+        assert!(dedup.first_sighting("discord", "1", "42"));
+
+        let restarted = InboundDedup::from_path(path);
+        assert!(!restarted.first_sighting("telegram", "1", "42"));
+        assert!(restarted.first_sighting("telegram", "1", "43"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,290 @@
+use crate::bus::OutboundMessage;
+use crate::config::DeliveryWindow;
+use crate::events::{self, EventLevel};
+use crate::storage::locate;
+use crate::utils::get_data_path;
+use anyhow::Result;
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+pub fn is_proactive(msg: &OutboundMessage) -> bool {
+    msg.metadata
+        .get("proactive")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+pub fn is_urgent(msg: &OutboundMessage) -> bool {
+    msg.metadata
+        .get("urgent")
+        .and_then(Value::as_bool)
+        .unwrap_or(false)
+}
+
+pub fn mark_proactive(msg: &mut OutboundMessage, urgent: bool) {
+    msg.metadata
+        .insert("proactive".to_string(), Value::Bool(true));
+    if urgent {
+        msg.metadata.insert("urgent".to_string(), Value::Bool(true));
+    }
+}
+
+// Inbound metadata for agent turns that nobody asked for (cron, heartbeat);
+// messages sent during such turns are subject to delivery windows.
+pub fn proactive_metadata(urgent: bool) -> Map<String, Value> {
+    let mut metadata = Map::new();
+    metadata.insert("proactive".to_string(), Value::Bool(true));
+    if urgent {
+        metadata.insert("urgent".to_string(), Value::Bool(true));
+    }
+    metadata
+}
+
+fn parse_hm(raw: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(raw.trim(), "%H:%M").ok()
+}
+
+fn day_allowed(window: &DeliveryWindow, date: NaiveDate) -> bool {
+    if window.days.is_empty() {
+        return true;
+    }
+    let today = date.weekday().to_string().to_lowercase();
+    window
+        .days
+        .iter()
+        .any(|day| day.trim().to_lowercase().get(..3) == Some(&today[..3]))
+}
+
+// A window with unparseable times never holds messages back.
+pub fn window_is_open(window: &DeliveryWindow, now: NaiveDateTime) -> bool {
+    let (Some(start), Some(end)) = (parse_hm(&window.start), parse_hm(&window.end)) else {
+        return true;
+    };
+    let t = now.time();
+    let date = now.date();
+    if start == end {
+        day_allowed(window, date)
+    } else if start < end {
+        start <= t && t < end && day_allowed(window, date)
+    } else if t >= start {
+        day_allowed(window, date)
+    } else if t < end {
+        // Early-morning tail of a window that opened the previous day.
+        date.pred_opt()
+            .is_some_and(|prev| day_allowed(window, prev))
+    } else {
+        false
+    }
+}
+
+pub fn next_window_start(window: &DeliveryWindow, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let start = parse_hm(&window.start)?;
+    (0..=7)
+        .map(|offset| now.date() + Duration::days(offset))
+        .filter(|date| day_allowed(window, *date))
+        .map(|date| date.and_time(start))
+        .find(|candidate| *candidate > now)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HeldMessage {
+    message: OutboundMessage,
+    held_at: String,
+}
+
+// Proactive messages waiting for their channel's delivery window, persisted so
+// a restart does not drop them.
+#[derive(Debug, Clone)]
+pub struct HeldQueue {
+    path: PathBuf,
+    lock: Arc<Mutex<()>>,
+}
+
+impl HeldQueue {
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_path(
+            get_data_path()?.join("outbox").join("held.json"),
+        ))
+    }
+
+    pub fn from_path(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    fn load(&self) -> Result<Vec<HeldMessage>> {
+        Ok(match locate(&self.path).read()? {
+            Some(raw) => serde_json::from_str(&raw).unwrap_or_default(),
+            None => Vec::new(),
+        })
+    }
+
+    fn save(&self, items: &[HeldMessage]) -> Result<()> {
+        locate(&self.path).write(&serde_json::to_string_pretty(items)?)
+    }
+
+    pub fn hold(&self, msg: &OutboundMessage) -> Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut items = self.load()?;
+        items.push(HeldMessage {
+            message: msg.clone(),
+            held_at: Local::now().to_rfc3339(),
+        });
+        self.save(&items)
+    }
+
+    pub fn take_ready(
+        &self,
+        ready: impl Fn(&OutboundMessage) -> bool,
+    ) -> Result<Vec<OutboundMessage>> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let items = self.load()?;
+        if items.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (due, kept): (Vec<_>, Vec<_>) = items.into_iter().partition(|h| ready(&h.message));
+        if !due.is_empty() {
+            self.save(&kept)?;
+        }
+        Ok(due.into_iter().map(|h| h.message).collect())
+    }
+
+    pub fn len(&self) -> usize {
+        self.load().map(|items| items.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct DeliveryPolicy {
+    windows: HashMap<String, DeliveryWindow>,
+    held: Option<HeldQueue>,
+}
+
+impl DeliveryPolicy {
+    pub fn new(windows: HashMap<String, DeliveryWindow>, held: Option<HeldQueue>) -> Self {
+        Self { windows, held }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.windows.is_empty() && self.held.is_some()
+    }
+
+    // Holds proactive, non-urgent messages whose channel window is closed.
+    // Returns false when the message should be sent now.
+    pub fn hold_if_closed(&self, msg: &OutboundMessage, now: NaiveDateTime) -> bool {
+        let (Some(window), Some(held)) = (self.windows.get(&msg.channel), &self.held) else {
+            return false;
+        };
+        if !is_proactive(msg) || is_urgent(msg) || window_is_open(window, now) {
+            return false;
+        }
+        match held.hold(msg) {
+            Ok(()) => {
+                let until = next_window_start(window, now)
+                    .map(|at| at.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "the next window".to_string());
+                events::record(
+                    EventLevel::Info,
+                    "delivery",
+                    Some(&msg.channel),
+                    format!("held message for {} until {until}", msg.chat_id),
+                );
+                true
+            }
+            Err(err) => {
+                eprintln!("Warning: failed to hold message, sending now: {err}");
+                false
+            }
+        }
+    }
+
+    pub fn take_ready(&self, now: NaiveDateTime) -> Vec<OutboundMessage> {
+        let Some(held) = &self.held else {
+            return Vec::new();
+        };
+        held.take_ready(|msg| {
+            self.windows
+                .get(&msg.channel)
+                .is_none_or(|window| window_is_open(window, now))
+        })
+        .unwrap_or_else(|err| {
+            eprintln!("Warning: failed to read held messages: {err}");
+            Vec::new()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str, time: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(&format!("{date} {time}"), "%Y-%m-%d %H:%M")
+            .expect("valid datetime")
+    }
+
+    fn window(start: &str, end: &str, days: &[&str]) -> DeliveryWindow {
+        DeliveryWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+            days: days.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn windows_handle_days_and_midnight_wrap() {
+        // 2026-03-06 is a Friday.
+        let office = window("09:00", "18:00", &["mon", "tue", "wed", "thu", "fri"]);
+        assert!(window_is_open(&office, at("2026-03-06", "09:00")));
+        assert!(!window_is_open(&office, at("2026-03-06", "18:00")));
+        assert!(!window_is_open(&office, at("2026-03-07", "10:00")));
+        assert_eq!(
+            next_window_start(&office, at("2026-03-06", "20:00")),
+            Some(at("2026-03-09", "09:00"))
+        );
+
+        let late = window("20:00", "02:00", &["fri"]);
+        assert!(window_is_open(&late, at("2026-03-06", "23:30")));
+        assert!(window_is_open(&late, at("2026-03-07", "01:00")));
+        assert!(!window_is_open(&late, at("2026-03-08", "01:00")));
+    }
+
+    #[test]
+    fn only_non_urgent_proactive_messages_are_held() {
+        let dir = std::env::temp_dir().join(format!("nanobot-held-{}", uuid::Uuid::new_v4()));
+        let queue = HeldQueue::from_path(dir.join("held.json"));
+        let windows = HashMap::from([("telegram".to_string(), window("08:00", "22:00", &[]))]);
+        let policy = DeliveryPolicy::new(windows, Some(queue.clone()));
+        let night = at("2026-03-06", "23:00");
+
+        let reply = OutboundMessage::new("telegram", "1", "reply");
+        assert!(!policy.hold_if_closed(&reply, night));
+
+        let mut urgent = OutboundMessage::new("telegram", "1", "server down");
+        mark_proactive(&mut urgent, true);
+        assert!(!policy.hold_if_closed(&urgent, night));
+
+        let mut digest = OutboundMessage::new("telegram", "1", "daily digest");
+        mark_proactive(&mut digest, false);
+        assert!(policy.hold_if_closed(&digest, night));
+        assert_eq!(queue.len(), 1);
+
+        assert!(policy.take_ready(night).is_empty());
+        let released = policy.take_ready(at("2026-03-07", "08:00"));
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].content, "daily digest");
+        assert!(queue.is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
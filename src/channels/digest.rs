@@ -0,0 +1,118 @@
+use crate::bus::OutboundMessage;
+use crate::channels::delivery::mark_proactive;
+use chrono::{DateTime, Local};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct PendingDigest {
+    opened_at: Instant,
+    items: Vec<(DateTime<Local>, OutboundMessage)>,
+}
+
+// Buffers proactive messages per chat and releases them as one message once
+// the oldest has waited a full interval.
+pub struct DigestBuffer {
+    interval: Duration,
+    pending: Mutex<HashMap<(String, String), PendingDigest>>,
+}
+
+impl DigestBuffer {
+    pub fn new(minutes: u64) -> Self {
+        Self {
+            interval: Duration::from_secs(minutes * 60),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn push(&self, msg: OutboundMessage) {
+        let Ok(mut pending) = self.pending.lock() else {
+            return;
+        };
+        pending
+            .entry((msg.channel.clone(), msg.chat_id.clone()))
+            .or_insert_with(|| PendingDigest {
+                opened_at: Instant::now(),
+                items: Vec::new(),
+            })
+            .items
+            .push((Local::now(), msg));
+    }
+
+    pub fn take_due(&self, now: Instant) -> Vec<OutboundMessage> {
+        self.take(|digest| now.duration_since(digest.opened_at) >= self.interval)
+    }
+
+    pub fn take_all(&self) -> Vec<OutboundMessage> {
+        self.take(|_| true)
+    }
+
+    fn take(&self, due: impl Fn(&PendingDigest) -> bool) -> Vec<OutboundMessage> {
+        let Ok(mut pending) = self.pending.lock() else {
+            return Vec::new();
+        };
+        let keys = pending
+            .iter()
+            .filter(|(_, digest)| due(digest))
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        keys.into_iter()
+            .filter_map(|key| pending.remove(&key))
+            .filter_map(|digest| combine(digest.items))
+            .collect()
+    }
+}
+
+fn combine(mut items: Vec<(DateTime<Local>, OutboundMessage)>) -> Option<OutboundMessage> {
+    if items.len() <= 1 {
+        return items.pop().map(|(_, msg)| msg);
+    }
+    let first = &items[0].1;
+    let mut sections = vec![format!("**Digest: {} updates**", items.len())];
+    let mut media = Vec::new();
+    for (at, msg) in &items {
+        sections.push(format!(
+            "**{}**\n{}",
+            at.format("%H:%M"),
+            msg.content.trim()
+        ));
+        media.extend(msg.media.iter().cloned());
+    }
+    let mut digest = OutboundMessage::new(
+        first.channel.clone(),
+        first.chat_id.clone(),
+        sections.join("\n\n"),
+    );
+    digest.media = media;
+    mark_proactive(&mut digest, false);
+    digest
+        .metadata
+        .insert("digest_count".to_string(), Value::from(items.len()));
+    Some(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn coalesces_per_chat_after_interval() {
+        let buffer = DigestBuffer::new(10);
+        buffer.push(OutboundMessage::new("telegram", "1", "backup finished"));
+        buffer.push(OutboundMessage::new("telegram", "1", "feed: 3 new posts"));
+        buffer.push(OutboundMessage::new("telegram", "2", "alert"));
+
+        assert!(buffer.take_due(Instant::now()).is_empty());
+
+        let later = Instant::now() + Duration::from_secs(601);
+        let mut released = buffer.take_due(later);
+        released.sort_by(|a, b| a.chat_id.cmp(&b.chat_id));
+        assert_eq!(released.len(), 2);
+        assert!(released[0].content.starts_with("**Digest: 2 updates**"));
+        assert!(released[0].content.contains("feed: 3 new posts"));
+        assert_eq!(released[0].metadata["digest_count"], 2);
+        assert_eq!(released[1].content, "alert");
+        assert!(buffer.take_all().is_empty());
+    }
+}
@@ -1,5 +1,6 @@
 use crate::bus::{MessageBus, OutboundMessage};
 use crate::channels::base::Channel;
+use crate::channels::format::{ChannelCapabilities, channel_capabilities};
 use crate::config::DingTalkConfig;
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
@@ -59,6 +60,10 @@ impl Channel for DingTalkChannel {
         self.bus.clone()
     }
 
+    fn capabilities(&self) -> ChannelCapabilities {
+        channel_capabilities("dingtalk")
+    }
+
     async fn start(&self) -> Result<()> {
         self.running.store(true, Ordering::Relaxed);
 
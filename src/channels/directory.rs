@@ -0,0 +1,81 @@
+use regex::Regex;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Names change rarely; refresh them a few times a day.
+const NAME_TTL: Duration = Duration::from_secs(6 * 3600);
+
+// Display names by platform user id, filled from message payloads and
+// user-directory lookups.
+#[derive(Default)]
+pub struct NameCache {
+    names: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl NameCache {
+    pub fn get(&self, id: &str) -> Option<String> {
+        let names = self.names.lock().unwrap_or_else(|e| e.into_inner());
+        names
+            .get(id)
+            .filter(|(_, at)| at.elapsed() < NAME_TTL)
+            .map(|(name, _)| name.clone())
+    }
+
+    pub fn insert(&self, id: &str, name: &str) {
+        let name = name.trim();
+        if id.is_empty() || name.is_empty() {
+            return;
+        }
+        let mut names = self.names.lock().unwrap_or_else(|e| e.into_inner());
+        names.insert(id.to_string(), (name.to_string(), Instant::now()));
+    }
+}
+
+// Ids captured by group 1 of `pattern`, deduplicated in order of appearance.
+pub fn mention_ids(text: &str, pattern: &Regex) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for caps in pattern.captures_iter(text) {
+        let id = caps[1].to_string();
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+// Rewrites mentions like `<@U0123>` to `@Priya`; unknown ids stay as they are.
+pub fn replace_mentions(text: &str, pattern: &Regex, names: &HashMap<String, String>) -> String {
+    pattern
+        .replace_all(text, |caps: &regex::Captures<'_>| {
+            match names.get(&caps[1]) {
+                Some(name) => format!("@{name}"),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_known_mentions_and_caches_names() {
+        let pattern = Regex::new(r"<@([UW][A-Z0-9]+)(?:\|[^>]*)?>").expect("valid regex");
+        let text = "ask <@U01> and <@U02|bob>, cc <@U01>";
+        assert_eq!(mention_ids(text, &pattern), vec!["U01", "U02"]);
+
+        let names = HashMap::from([("U01".to_string(), "Priya".to_string())]);
+        assert_eq!(
+            replace_mentions(text, &pattern, &names),
+            "ask @Priya and <@U02|bob>, cc @Priya"
+        );
+
+        let cache = NameCache::default();
+        cache.insert("U01", " Priya ");
+        cache.insert("U02", "");
+        assert_eq!(cache.get("U01").as_deref(), Some("Priya"));
+        assert_eq!(cache.get("U02"), None);
+    }
+}
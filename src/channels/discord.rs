@@ -1,18 +1,22 @@
-use crate::bus::{MessageBus, OutboundMessage};
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage, TurnActivity};
 use crate::channels::base::Channel;
+use crate::channels::directory::{NameCache, mention_ids, replace_mentions};
+use crate::channels::format::{ChannelCapabilities, channel_capabilities};
+use crate::channels::typing::TypingLoops;
 use crate::config::DiscordConfig;
+use crate::feedback::{classify_reaction, link_sent_message, record_reaction};
 use crate::pairing::{issue_pairing, pairing_prompt};
 use anyhow::Result;
 use async_trait::async_trait;
 use futures_util::{SinkExt, StreamExt};
+use regex::Regex;
 use reqwest::Client;
-use serde_json::{Map, Value, json};
+use serde_json::{Value, json};
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::{Mutex, mpsc};
-use tokio::task::JoinHandle;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
 const DISCORD_API_BASE: &str = "https://discord.com/api/v10";
@@ -24,7 +28,9 @@ pub struct DiscordChannel {
     running: AtomicBool,
     seq: Arc<Mutex<Option<i64>>>,
     http: Client,
-    typing_tasks: Mutex<HashMap<String, JoinHandle<()>>>,
+    typing: TypingLoops,
+    names: NameCache,
+    mention_re: Regex,
 }
 
 impl DiscordChannel {
@@ -35,10 +41,99 @@ impl DiscordChannel {
             running: AtomicBool::new(false),
             seq: Arc::new(Mutex::new(None)),
             http: Client::new(),
-            typing_tasks: Mutex::new(HashMap::new()),
+            typing: TypingLoops::default(),
+            names: NameCache::default(),
+            mention_re: Regex::new(r"<@!?(\d+)>").expect("valid mention regex"),
         }
     }
 
+    // Server nickname, then global display name, then username.
+    fn display_name(user: &Value, member: Option<&Value>) -> Option<String> {
+        member
+            .and_then(|m| m.get("nick"))
+            .into_iter()
+            .chain(
+                [user.get("global_name"), user.get("username")]
+                    .into_iter()
+                    .flatten(),
+            )
+            .filter_map(Value::as_str)
+            .map(str::trim)
+            .find(|name| !name.is_empty())
+            .map(ToOwned::to_owned)
+    }
+
+    async fn user_name(&self, user_id: &str) -> Option<String> {
+        if let Some(name) = self.names.get(user_id) {
+            return Some(name);
+        }
+        let user: Value = self
+            .http
+            .get(format!("{DISCORD_API_BASE}/users/{user_id}"))
+            .header("Authorization", format!("Bot {}", self.config.token))
+            .send()
+            .await
+            .ok()?
+            .json()
+            .await
+            .ok()?;
+        let name = Self::display_name(&user, None)?;
+        self.names.insert(user_id, &name);
+        Some(name)
+    }
+
+    // Caches the author and mentioned users from the payload, then rewrites
+    // `<@id>` mentions to names.
+    async fn resolve_mentions(&self, payload: &Value, text: &str) -> String {
+        for user in payload
+            .get("mentions")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            if let (Some(id), Some(name)) = (
+                user.get("id").and_then(Value::as_str),
+                Self::display_name(user, user.get("member")),
+            ) {
+                self.names.insert(id, &name);
+            }
+        }
+        let mut names = HashMap::new();
+        for id in mention_ids(text, &self.mention_re) {
+            if let Some(name) = self.user_name(&id).await {
+                names.insert(id, name);
+            }
+        }
+        replace_mentions(text, &self.mention_re, &names)
+    }
+
+    fn handle_reaction(&self, payload: &Value, added: bool) {
+        let field = |key: &str| payload.get(key).and_then(Value::as_str).unwrap_or_default();
+        let (user_id, channel_id, message_id) =
+            (field("user_id"), field("channel_id"), field("message_id"));
+        if user_id.is_empty() || channel_id.is_empty() || message_id.is_empty() {
+            return;
+        }
+        if !self.is_allowed(user_id) {
+            return;
+        }
+        let Some(rating) = payload
+            .get("emoji")
+            .and_then(|e| e.get("name"))
+            .and_then(Value::as_str)
+            .and_then(classify_reaction)
+        else {
+            return;
+        };
+        record_reaction(
+            self.name(),
+            channel_id,
+            message_id,
+            user_id,
+            if added { Some(rating) } else { None },
+        );
+    }
+
     async fn handle_message_create(&self, payload: &Value) -> Result<()> {
         let author = payload.get("author").cloned().unwrap_or_else(|| json!({}));
         if author.get("bot").and_then(Value::as_bool).unwrap_or(false) {
@@ -74,10 +169,14 @@ impl DiscordChannel {
             return Ok(());
         }
 
+        let sender_name = Self::display_name(&author, payload.get("member"));
+        if let Some(name) = &sender_name {
+            self.names.insert(&sender_id, name);
+        }
         let mut content_parts = Vec::new();
         if let Some(content) = payload.get("content").and_then(Value::as_str) {
             if !content.is_empty() {
-                content_parts.push(content.to_string());
+                content_parts.push(self.resolve_mentions(payload, content).await);
             }
         }
         let mut media_paths = Vec::new();
@@ -126,64 +225,37 @@ impl DiscordChannel {
             }
         }
 
-        let mut metadata = Map::new();
-        metadata.insert(
-            "message_id".to_string(),
-            payload.get("id").cloned().unwrap_or(Value::Null),
-        );
-        metadata.insert(
-            "guild_id".to_string(),
-            payload.get("guild_id").cloned().unwrap_or(Value::Null),
-        );
-        metadata.insert(
-            "reply_to".to_string(),
-            payload
-                .get("referenced_message")
-                .and_then(|v| v.get("id"))
-                .cloned()
-                .unwrap_or(Value::Null),
-        );
-
-        self.handle_message(
+        let mut inbound = InboundMessage::new(
+            self.name(),
             sender_id,
-            channel_id.clone(),
+            channel_id,
             if content_parts.is_empty() {
                 "[empty message]".to_string()
             } else {
                 content_parts.join("\n")
             },
-            media_paths,
-            metadata,
-        )
-        .await?;
-
-        self.start_typing(channel_id).await;
-        Ok(())
-    }
-
-    async fn start_typing(&self, channel_id: String) {
-        self.stop_typing(&channel_id).await;
-        let channel_for_task = channel_id.clone();
-        let token = self.config.token.clone();
-        let http = self.http.clone();
-        let task = tokio::spawn(async move {
-            let url = format!("{DISCORD_API_BASE}/channels/{channel_for_task}/typing");
-            loop {
-                let _ = http
-                    .post(&url)
-                    .header("Authorization", format!("Bot {token}"))
-                    .send()
-                    .await;
-                tokio::time::sleep(std::time::Duration::from_secs(8)).await;
-            }
-        });
-        self.typing_tasks.lock().await.insert(channel_id, task);
-    }
-
-    async fn stop_typing(&self, channel_id: &str) {
-        if let Some(task) = self.typing_tasks.lock().await.remove(channel_id) {
-            task.abort();
+        );
+        inbound.media = media_paths;
+        inbound.metadata.insert(
+            "guild_id".to_string(),
+            payload.get("guild_id").cloned().unwrap_or(Value::Null),
+        );
+        if let Some(name) = sender_name {
+            inbound
+                .metadata
+                .insert("sender_name".to_string(), Value::String(name));
         }
+        // Discord threads are channels of their own, so chat_id already scopes
+        // the conversation; only replies need mapping.
+        inbound.message_id = payload
+            .get("id")
+            .and_then(Value::as_str)
+            .map(ToOwned::to_owned);
+        inbound.reply_to = payload
+            .pointer("/message_reference/message_id")
+            .and_then(Value::as_str)
+            .map(ToOwned::to_owned);
+        self.handle_inbound(inbound).await
     }
 }
 
@@ -205,6 +277,10 @@ impl Channel for DiscordChannel {
         self.bus.clone()
     }
 
+    fn capabilities(&self) -> ChannelCapabilities {
+        channel_capabilities("discord")
+    }
+
     async fn start(&self) -> Result<()> {
         if self.config.token.is_empty() {
             return Ok(());
@@ -297,6 +373,13 @@ impl Channel for DiscordChannel {
                             let _ = self.handle_message_create(data).await;
                         }
                     }
+                    0 if event_type == "MESSAGE_REACTION_ADD"
+                        || event_type == "MESSAGE_REACTION_REMOVE" =>
+                    {
+                        if let Some(data) = payload.get("d") {
+                            self.handle_reaction(data, event_type == "MESSAGE_REACTION_ADD");
+                        }
+                    }
                     7 | 9 => {
                         break;
                     }
@@ -308,10 +391,7 @@ impl Channel for DiscordChannel {
                 task.abort();
             }
             writer_task.abort();
-            let mut typing = self.typing_tasks.lock().await;
-            for (_, task) in typing.drain() {
-                task.abort();
-            }
+            self.typing.stop_all().await;
 
             if self.running.load(Ordering::Relaxed) {
                 tokio::time::sleep(std::time::Duration::from_secs(5)).await;
@@ -323,15 +403,39 @@ impl Channel for DiscordChannel {
 
     async fn stop(&self) -> Result<()> {
         self.running.store(false, Ordering::Relaxed);
-        let mut typing = self.typing_tasks.lock().await;
-        for (_, task) in typing.drain() {
-            task.abort();
+        self.typing.stop_all().await;
+        Ok(())
+    }
+
+    async fn set_typing(&self, activity: &TurnActivity) -> Result<()> {
+        if !activity.active {
+            self.typing.stop(&activity.chat_id).await;
+            return Ok(());
         }
+        let url = format!("{DISCORD_API_BASE}/channels/{}/typing", activity.chat_id);
+        let token = self.config.token.clone();
+        let http = self.http.clone();
+        self.typing
+            .start(
+                &activity.chat_id,
+                std::time::Duration::from_secs(8),
+                move || {
+                    let request = http
+                        .post(&url)
+                        .header("Authorization", format!("Bot {token}"))
+                        .send();
+                    async move {
+                        let _ = request.await;
+                    }
+                },
+            )
+            .await;
         Ok(())
     }
 
     async fn send(&self, msg: &OutboundMessage) -> Result<()> {
-        let url = format!("{DISCORD_API_BASE}/channels/{}/messages", msg.chat_id);
+        let channel_id = msg.thread_id.as_deref().unwrap_or(&msg.chat_id);
+        let url = format!("{DISCORD_API_BASE}/channels/{channel_id}/messages");
         let mut payload = json!({ "content": msg.content });
         if let Some(reply_to) = &msg.reply_to {
             payload["message_reference"] = json!({ "message_id": reply_to });
@@ -367,12 +471,15 @@ impl Channel for DiscordChannel {
                 continue;
             }
             if response.status().is_success() {
-                self.stop_typing(&msg.chat_id).await;
+                if let Ok(sent) = response.json::<Value>().await
+                    && let Some(id) = sent.get("id").and_then(Value::as_str)
+                {
+                    link_sent_message(msg, id);
+                }
                 return Ok(());
             }
             tokio::time::sleep(std::time::Duration::from_secs(1)).await;
         }
-        self.stop_typing(&msg.chat_id).await;
         Ok(())
     }
 }
@@ -1,51 +1,101 @@
-use crate::bus::{MessageBus, OutboundMessage};
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
 use crate::channels::base::Channel;
+use crate::channels::format::{ChannelCapabilities, channel_capabilities};
+use crate::channels::oauth::{OAuthSession, XOAuth2};
 use crate::config::EmailConfig;
+use crate::events::{self, EventLevel};
 use anyhow::{Context, Result, anyhow};
 use async_trait::async_trait;
+use futures_util::future::join_all;
 use html_escape::decode_html_entities;
 use imap::{ClientBuilder, ConnectionMode};
 use lettre::message::header::{InReplyTo, References};
-use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
 use lettre::{Message, SmtpTransport, Transport};
 use mailparse::{DispositionType, MailAddr, MailHeaderMap, ParsedMail, addrparse, parse_mail};
 use regex::Regex;
-use serde_json::{Map, Value};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, Ordering};
 
 const MAX_PROCESSED_UIDS: usize = 100_000;
+const IDENTICAL_BODY_WINDOW_MS: i64 = 86_400_000;
+const NO_REPLY_LOCAL_PARTS: &[&str] = &[
+    "noreply",
+    "no-reply",
+    "no_reply",
+    "donotreply",
+    "do-not-reply",
+    "do_not_reply",
+    "mailer-daemon",
+    "postmaster",
+    "bounce",
+    "bounces",
+];
 
 #[derive(Debug, Clone)]
 struct InboundEmail {
     sender: String,
     subject: String,
     message_id: String,
+    in_reply_to: String,
+    thread_root: String,
+    // Why the mail looks machine-sent, if it does.
+    automated: Option<&'static str>,
+    body_digest: u64,
     date_value: String,
     content: String,
     uid: String,
 }
 
-pub struct EmailChannel {
+// One mailbox: the top-level `channels.email` settings, or an entry of
+// `channels.email.accounts` whose id prefixes its chat ids.
+struct EmailAccount {
+    id: String,
     config: EmailConfig,
-    bus: Arc<MessageBus>,
-    running: AtomicBool,
     last_subject_by_chat: Mutex<HashMap<String, String>>,
     last_message_id_by_chat: Mutex<HashMap<String, String>>,
     processed_uids: Mutex<HashSet<String>>,
+    // Body digest -> arrival times within the last day.
+    recent_bodies: Mutex<HashMap<u64, Vec<i64>>>,
+    oauth: Option<OAuthSession>,
 }
 
-impl EmailChannel {
-    pub fn new(config: EmailConfig, bus: Arc<MessageBus>) -> Self {
+impl EmailAccount {
+    fn new(id: String, config: EmailConfig) -> Self {
+        let oauth = config.oauth.clone().and_then(|oauth| {
+            OAuthSession::new(oauth, &config.imap_username)
+                .inspect_err(|err| eprintln!("email oauth disabled: {err}"))
+                .ok()
+        });
         Self {
+            id,
             config,
-            bus,
-            running: AtomicBool::new(false),
             last_subject_by_chat: Mutex::new(HashMap::new()),
             last_message_id_by_chat: Mutex::new(HashMap::new()),
             processed_uids: Mutex::new(HashSet::new()),
+            recent_bodies: Mutex::new(HashMap::new()),
+            oauth,
+        }
+    }
+
+    fn label(&self) -> String {
+        if self.id.is_empty() {
+            "email channel".to_string()
+        } else {
+            format!("email account '{}'", self.id)
+        }
+    }
+
+    fn chat_id(&self, address: &str) -> String {
+        if self.id.is_empty() {
+            address.to_string()
+        } else {
+            format!("{}:{address}", self.id)
         }
     }
 
@@ -57,7 +107,8 @@ impl EmailChannel {
         if self.config.imap_username.trim().is_empty() {
             missing.push("imapUsername");
         }
-        if self.config.imap_password.trim().is_empty() {
+        let oauth = self.config.oauth.is_some();
+        if !oauth && self.config.imap_password.trim().is_empty() {
             missing.push("imapPassword");
         }
         if self.config.smtp_host.trim().is_empty() {
@@ -66,7 +117,7 @@ impl EmailChannel {
         if self.config.smtp_username.trim().is_empty() {
             missing.push("smtpUsername");
         }
-        if self.config.smtp_password.trim().is_empty() {
+        if !oauth && self.config.smtp_password.trim().is_empty() {
             missing.push("smtpPassword");
         }
 
@@ -74,7 +125,8 @@ impl EmailChannel {
             Ok(())
         } else {
             Err(anyhow!(
-                "email channel not configured, missing: {}",
+                "{} not configured, missing: {}",
+                self.label(),
                 missing.join(", ")
             ))
         }
@@ -97,80 +149,33 @@ impl EmailChannel {
         format!("{prefix}{subject}")
     }
 
-    fn html_to_text(raw_html: &str) -> String {
-        let br_re = Regex::new(r"(?i)<\s*br\s*/?>").expect("valid html br regex");
-        let p_end_re = Regex::new(r"(?i)<\s*/\s*p\s*>").expect("valid html p regex");
-        let tag_re = Regex::new(r"(?is)<[^>]+>").expect("valid html tag regex");
-        let text = br_re.replace_all(raw_html, "\n");
-        let text = p_end_re.replace_all(&text, "\n");
-        let text = tag_re.replace_all(&text, "");
-        decode_html_entities(&text).to_string()
-    }
-
-    fn extract_sender(from_header: &str) -> String {
-        if from_header.trim().is_empty() {
-            return String::new();
+    // Records the arrival and reports whether this body has now been seen more
+    // often than `max_identical_bodies` within a day.
+    fn repeated_body(&self, digest: u64, now_ms: i64) -> bool {
+        let limit = self.config.max_identical_bodies;
+        if limit == 0 {
+            return false;
         }
-
-        if let Ok(parsed) = addrparse(from_header) {
-            for addr in parsed.into_inner() {
-                match addr {
-                    MailAddr::Single(s) => return s.addr.trim().to_lowercase(),
-                    MailAddr::Group(g) => {
-                        if let Some(first) = g.addrs.first() {
-                            return first.addr.trim().to_lowercase();
-                        }
-                    }
-                }
-            }
-        }
-
-        let raw = from_header.trim();
-        if let (Some(start), Some(end)) = (raw.find('<'), raw.rfind('>'))
-            && start < end
-        {
-            return raw[start + 1..end].trim().to_lowercase();
-        }
-        raw.to_lowercase()
+        let mut recent = self.recent_bodies.lock().expect("poisoned mutex");
+        recent.retain(|_, seen| {
+            seen.retain(|at| now_ms - at < IDENTICAL_BODY_WINDOW_MS);
+            !seen.is_empty()
+        });
+        let seen = recent.entry(digest).or_default();
+        seen.push(now_ms);
+        seen.len() > limit as usize
     }
 
-    fn extract_text_body(parsed: &ParsedMail<'_>) -> String {
-        if !parsed.subparts.is_empty() {
-            let mut plain_parts = Vec::new();
-            let mut html_parts = Vec::new();
-            for part in &parsed.subparts {
-                if part.get_content_disposition().disposition == DispositionType::Attachment {
-                    continue;
-                }
-                let payload = part.get_body().unwrap_or_default();
-                let mime = part.ctype.mimetype.to_lowercase();
-                if mime == "text/plain" {
-                    plain_parts.push(payload);
-                } else if mime == "text/html" {
-                    html_parts.push(payload);
-                }
-            }
-            if !plain_parts.is_empty() {
-                return plain_parts.join("\n\n").trim().to_string();
-            }
-            if !html_parts.is_empty() {
-                return Self::html_to_text(&html_parts.join("\n\n"))
-                    .trim()
-                    .to_string();
-            }
-            return String::new();
-        }
-
-        let payload = parsed.get_body().unwrap_or_default();
-        if parsed.ctype.mimetype.eq_ignore_ascii_case("text/html") {
-            Self::html_to_text(&payload).trim().to_string()
-        } else {
-            payload.trim().to_string()
+    // The XOAUTH2 access token when OAuth is configured, refreshed as needed.
+    async fn access_token(&self) -> Result<Option<String>> {
+        match &self.oauth {
+            Some(oauth) => Ok(Some(oauth.access_token().await?)),
+            None => Ok(None),
         }
     }
 
-    fn fetch_new_messages(&self) -> Result<Vec<InboundEmail>> {
-        self.fetch_messages("UNSEEN", self.config.mark_seen, true, 0)
+    fn fetch_new_messages(&self, access_token: Option<&str>) -> Result<Vec<InboundEmail>> {
+        self.fetch_messages("UNSEEN", self.config.mark_seen, true, 0, access_token)
     }
 
     fn fetch_messages(
@@ -179,6 +184,7 @@ impl EmailChannel {
         mark_seen: bool,
         dedupe: bool,
         limit: usize,
+        access_token: Option<&str>,
     ) -> Result<Vec<InboundEmail>> {
         let mut client = ClientBuilder::new(self.config.imap_host.as_str(), self.config.imap_port);
         client = if self.config.imap_use_ssl {
@@ -189,12 +195,23 @@ impl EmailChannel {
         let imap_client = client
             .connect()
             .context("failed to connect to IMAP server")?;
-        let mut session = imap_client
-            .login(
-                self.config.imap_username.as_str(),
-                self.config.imap_password.as_str(),
-            )
-            .map_err(|(err, _)| anyhow!("failed to login IMAP: {err}"))?;
+        let mut session = match access_token {
+            Some(access_token) => imap_client
+                .authenticate(
+                    "XOAUTH2",
+                    &XOAuth2 {
+                        user: self.config.imap_username.as_str(),
+                        access_token,
+                    },
+                )
+                .map_err(|(err, _)| anyhow!("failed to authenticate IMAP (XOAUTH2): {err}"))?,
+            None => imap_client
+                .login(
+                    self.config.imap_username.as_str(),
+                    self.config.imap_password.as_str(),
+                )
+                .map_err(|(err, _)| anyhow!("failed to login IMAP: {err}"))?,
+        };
 
         let result = (|| -> Result<Vec<InboundEmail>> {
             let mailbox = if self.config.imap_mailbox.trim().is_empty() {
@@ -229,7 +246,7 @@ impl EmailChannel {
                     }
 
                     let parsed = parse_mail(raw_bytes).context("failed to parse email body")?;
-                    let sender = Self::extract_sender(
+                    let sender = EmailChannel::extract_sender(
                         &parsed.headers.get_first_value("From").unwrap_or_default(),
                     );
                     if sender.is_empty() {
@@ -247,10 +264,30 @@ impl EmailChannel {
                         .unwrap_or_default()
                         .trim()
                         .to_string();
-                    let mut body = Self::extract_text_body(&parsed);
+                    let in_reply_to = parsed
+                        .headers
+                        .get_first_value("In-Reply-To")
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string();
+                    // The first References entry is the message that started the thread.
+                    let thread_root = parsed
+                        .headers
+                        .get_first_value("References")
+                        .and_then(|refs| refs.split_whitespace().next().map(ToOwned::to_owned))
+                        .unwrap_or_else(|| {
+                            if in_reply_to.is_empty() {
+                                message_id.clone()
+                            } else {
+                                in_reply_to.clone()
+                            }
+                        });
+                    let automated = EmailChannel::automated_reason(&parsed.headers, &sender);
+                    let mut body = EmailChannel::extract_text_body(&parsed);
                     if body.is_empty() {
                         body = "(empty email body)".to_string();
                     }
+                    let body_digest = EmailChannel::body_digest(&body);
                     let body = body
                         .chars()
                         .take(self.config.max_body_chars)
@@ -263,6 +300,10 @@ impl EmailChannel {
                         sender,
                         subject,
                         message_id,
+                        in_reply_to,
+                        thread_root,
+                        automated,
+                        body_digest,
                         date_value,
                         content,
                         uid: uid.clone(),
@@ -288,10 +329,12 @@ impl EmailChannel {
         result
     }
 
-    fn smtp_send(&self, email_msg: Message) -> Result<()> {
+    fn smtp_send(&self, email_msg: Message, access_token: Option<&str>) -> Result<()> {
         let creds = Credentials::new(
             self.config.smtp_username.clone(),
-            self.config.smtp_password.clone(),
+            access_token
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|| self.config.smtp_password.clone()),
         );
         let builder = if self.config.smtp_use_ssl {
             SmtpTransport::relay(self.config.smtp_host.as_str())?.port(self.config.smtp_port)
@@ -302,7 +345,11 @@ impl EmailChannel {
             SmtpTransport::builder_dangerous(self.config.smtp_host.as_str())
                 .port(self.config.smtp_port)
         };
-        let sender = builder.credentials(creds).build();
+        let mut builder = builder.credentials(creds);
+        if access_token.is_some() {
+            builder = builder.authentication(vec![Mechanism::Xoauth2]);
+        }
+        let sender = builder.build();
         sender
             .send(&email_msg)
             .context("failed to send SMTP message")?;
@@ -310,82 +357,304 @@ impl EmailChannel {
     }
 }
 
-#[async_trait]
-impl Channel for EmailChannel {
-    fn name(&self) -> &str {
-        "email"
+pub struct EmailChannel {
+    config: EmailConfig,
+    bus: Arc<MessageBus>,
+    running: AtomicBool,
+    accounts: Vec<EmailAccount>,
+}
+
+impl EmailChannel {
+    pub fn new(config: EmailConfig, bus: Arc<MessageBus>) -> Self {
+        let mut accounts: Vec<EmailAccount> = Vec::new();
+        if config.accounts.is_empty() || !config.imap_host.trim().is_empty() {
+            accounts.push(EmailAccount::new(String::new(), config.clone()));
+        }
+        for account in &config.accounts {
+            let id = account.id.trim();
+            if id.is_empty() || id.contains(':') || accounts.iter().any(|a| a.id == id) {
+                eprintln!("email account ignored: id '{id}' is empty, contains ':' or is repeated");
+                continue;
+            }
+            accounts.push(EmailAccount::new(id.to_string(), account.clone()));
+        }
+        Self {
+            config,
+            bus,
+            running: AtomicBool::new(false),
+            accounts,
+        }
     }
 
-    fn is_running(&self) -> bool {
-        self.running.load(Ordering::Relaxed)
+    // "work:alice@example.com" belongs to account "work"; bare addresses to the
+    // first account.
+    fn route<'a>(&self, chat_id: &'a str) -> Option<(&EmailAccount, &'a str)> {
+        if let Some((id, address)) = chat_id.split_once(':')
+            && let Some(account) = self
+                .accounts
+                .iter()
+                .find(|account| !account.id.is_empty() && account.id == id)
+        {
+            return Some((account, address));
+        }
+        self.accounts.first().map(|account| (account, chat_id))
     }
 
-    fn allow_from(&self) -> &[String] {
-        &self.config.allow_from
+    fn html_to_text(raw_html: &str) -> String {
+        let br_re = Regex::new(r"(?i)<\s*br\s*/?>").expect("valid html br regex");
+        let p_end_re = Regex::new(r"(?i)<\s*/\s*p\s*>").expect("valid html p regex");
+        let tag_re = Regex::new(r"(?is)<[^>]+>").expect("valid html tag regex");
+        let text = br_re.replace_all(raw_html, "\n");
+        let text = p_end_re.replace_all(&text, "\n");
+        let text = tag_re.replace_all(&text, "");
+        decode_html_entities(&text).to_string()
     }
 
-    fn bus(&self) -> Arc<MessageBus> {
-        self.bus.clone()
+    // Autoresponders and mailing-list robots (RFC 3834 and common vendor headers).
+    fn is_automated(headers: &[mailparse::MailHeader]) -> bool {
+        let value = |name: &str| {
+            headers
+                .get_first_value(name)
+                .map(|v| v.trim().to_ascii_lowercase())
+        };
+        value("Auto-Submitted").is_some_and(|v| v != "no")
+            || value("Precedence")
+                .is_some_and(|v| matches!(v.as_str(), "bulk" | "junk" | "list" | "auto_reply"))
+            || value("X-Autoreply").is_some()
+            || value("X-Autorespond").is_some()
     }
 
-    async fn start(&self) -> Result<()> {
-        if !self.config.consent_granted {
-            eprintln!(
-                "Email channel disabled: consent_granted=false. Grant explicit permission before mailbox access."
-            );
-            return Ok(());
+    // Newsletters and notification senders on top of `is_automated`.
+    fn automated_reason(headers: &[mailparse::MailHeader], sender: &str) -> Option<&'static str> {
+        if Self::is_automated(headers) {
+            return Some("auto-generated");
         }
-        if let Err(err) = self.validate_config() {
-            eprintln!("{err}");
-            return Ok(());
+        if headers.get_first_value("List-Id").is_some()
+            || headers.get_first_value("List-Unsubscribe").is_some()
+        {
+            return Some("mailing list");
+        }
+        let local = sender.split('@').next().unwrap_or_default();
+        let local = local.split('+').next().unwrap_or_default();
+        if NO_REPLY_LOCAL_PARTS.contains(&local) {
+            return Some("no-reply sender");
         }
+        None
+    }
 
-        self.running.store(true, Ordering::Relaxed);
-        let poll_seconds = self.config.poll_interval_seconds.max(5);
+    fn body_digest(body: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for word in body.split_whitespace() {
+            word.to_lowercase().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    fn extract_sender(from_header: &str) -> String {
+        if from_header.trim().is_empty() {
+            return String::new();
+        }
+
+        if let Ok(parsed) = addrparse(from_header) {
+            for addr in parsed.into_inner() {
+                match addr {
+                    MailAddr::Single(s) => return s.addr.trim().to_lowercase(),
+                    MailAddr::Group(g) => {
+                        if let Some(first) = g.addrs.first() {
+                            return first.addr.trim().to_lowercase();
+                        }
+                    }
+                }
+            }
+        }
+
+        let raw = from_header.trim();
+        if let (Some(start), Some(end)) = (raw.find('<'), raw.rfind('>'))
+            && start < end
+        {
+            return raw[start + 1..end].trim().to_lowercase();
+        }
+        raw.to_lowercase()
+    }
+
+    fn extract_text_body(parsed: &ParsedMail<'_>) -> String {
+        if !parsed.subparts.is_empty() {
+            let mut plain_parts = Vec::new();
+            let mut html_parts = Vec::new();
+            for part in &parsed.subparts {
+                if part.get_content_disposition().disposition == DispositionType::Attachment {
+                    continue;
+                }
+                let payload = part.get_body().unwrap_or_default();
+                let mime = part.ctype.mimetype.to_lowercase();
+                if mime == "text/plain" {
+                    plain_parts.push(payload);
+                } else if mime == "text/html" {
+                    html_parts.push(payload);
+                }
+            }
+            if !plain_parts.is_empty() {
+                return plain_parts.join("\n\n").trim().to_string();
+            }
+            if !html_parts.is_empty() {
+                return Self::html_to_text(&html_parts.join("\n\n"))
+                    .trim()
+                    .to_string();
+            }
+            return String::new();
+        }
+
+        let payload = parsed.get_body().unwrap_or_default();
+        if parsed.ctype.mimetype.eq_ignore_ascii_case("text/html") {
+            Self::html_to_text(&payload).trim().to_string()
+        } else {
+            payload.trim().to_string()
+        }
+    }
+
+    async fn poll(&self, account: &EmailAccount) {
+        let poll_seconds = account.config.poll_interval_seconds.max(5);
         while self.running.load(Ordering::Relaxed) {
-            match self.fetch_new_messages() {
+            let fetched = match account.access_token().await {
+                Ok(access_token) => account.fetch_new_messages(access_token.as_deref()),
+                Err(err) => Err(err),
+            };
+            match fetched {
                 Ok(inbound_items) => {
                     for item in inbound_items {
+                        let repeated = account
+                            .repeated_body(item.body_digest, chrono::Utc::now().timestamp_millis());
+                        let skip_reason = if repeated {
+                            Some("repeated identical body")
+                        } else {
+                            item.automated.filter(|_| account.config.skip_automated)
+                        };
+                        if let Some(reason) = skip_reason {
+                            events::record(
+                                EventLevel::Info,
+                                "email",
+                                Some(self.name()),
+                                format!(
+                                    "ignored mail from {} ({reason}): {}",
+                                    item.sender, item.subject
+                                ),
+                            );
+                            continue;
+                        }
                         if !item.subject.is_empty() {
-                            self.last_subject_by_chat
+                            account
+                                .last_subject_by_chat
                                 .lock()
                                 .expect("poisoned mutex")
                                 .insert(item.sender.clone(), item.subject.clone());
                         }
                         if !item.message_id.is_empty() {
-                            self.last_message_id_by_chat
+                            account
+                                .last_message_id_by_chat
                                 .lock()
                                 .expect("poisoned mutex")
                                 .insert(item.sender.clone(), item.message_id.clone());
                         }
-                        let mut metadata = Map::new();
-                        metadata.insert("message_id".to_string(), Value::String(item.message_id));
-                        metadata.insert("subject".to_string(), Value::String(item.subject));
-                        metadata.insert("date".to_string(), Value::String(item.date_value));
-                        metadata.insert(
-                            "sender_email".to_string(),
-                            Value::String(item.sender.clone()),
+                        let non_empty = |id: String| (!id.is_empty()).then_some(id);
+                        let mut inbound = InboundMessage::new(
+                            self.name(),
+                            item.sender.clone(),
+                            account.chat_id(&item.sender),
+                            item.content,
                         );
-                        metadata.insert("uid".to_string(), Value::String(item.uid));
-
-                        let _ = self
-                            .handle_message(
-                                item.sender.clone(),
-                                item.sender,
-                                item.content,
-                                Vec::new(),
-                                metadata,
-                            )
-                            .await;
+                        inbound.message_id = non_empty(item.message_id);
+                        inbound.thread_id = non_empty(item.thread_root);
+                        inbound.reply_to = non_empty(item.in_reply_to);
+                        inbound
+                            .metadata
+                            .insert("subject".to_string(), Value::String(item.subject));
+                        inbound
+                            .metadata
+                            .insert("date".to_string(), Value::String(item.date_value));
+                        inbound
+                            .metadata
+                            .insert("sender_email".to_string(), Value::String(item.sender));
+                        inbound
+                            .metadata
+                            .insert("uid".to_string(), Value::String(item.uid));
+                        if !account.id.is_empty() {
+                            inbound.metadata.insert(
+                                "email_account".to_string(),
+                                Value::String(account.id.clone()),
+                            );
+                        }
+                        inbound
+                            .metadata
+                            .insert("is_bot".to_string(), Value::Bool(item.automated.is_some()));
+
+                        let _ = self.handle_inbound(inbound).await;
                     }
                 }
                 Err(err) => {
-                    eprintln!("email polling error: {err}");
+                    eprintln!("{} polling error: {err}", account.label());
                 }
             }
 
             tokio::time::sleep(std::time::Duration::from_secs(poll_seconds)).await;
         }
+    }
+}
+
+#[async_trait]
+impl Channel for EmailChannel {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    fn allow_from(&self) -> &[String] {
+        &self.config.allow_from
+    }
+
+    fn is_allowed(&self, sender_id: &str) -> bool {
+        sender_allowed(sender_id, &self.config.allow_from)
+    }
+
+    fn admits(&self, msg: &InboundMessage) -> bool {
+        self.route(&msg.chat_id)
+            .is_some_and(|(account, _)| sender_allowed(&msg.sender_id, &account.config.allow_from))
+    }
+
+    fn capabilities(&self) -> ChannelCapabilities {
+        channel_capabilities("email")
+    }
+
+    fn bus(&self) -> Arc<MessageBus> {
+        self.bus.clone()
+    }
+
+    async fn start(&self) -> Result<()> {
+        if !self.config.consent_granted {
+            eprintln!(
+                "Email channel disabled: consent_granted=false. Grant explicit permission before mailbox access."
+            );
+            return Ok(());
+        }
+        let accounts = self
+            .accounts
+            .iter()
+            .filter(|account| match account.validate_config() {
+                Ok(()) => true,
+                Err(err) => {
+                    eprintln!("{err}");
+                    false
+                }
+            })
+            .collect::<Vec<_>>();
+        if accounts.is_empty() {
+            return Ok(());
+        }
+
+        self.running.store(true, Ordering::Relaxed);
+        join_all(accounts.into_iter().map(|account| self.poll(account))).await;
         Ok(())
     }
 
@@ -400,46 +669,50 @@ impl Channel for EmailChannel {
             return Ok(());
         }
 
+        let Some((account, to_addr)) = self.route(msg.chat_id.trim()) else {
+            eprintln!("skip email send: no account configured");
+            return Ok(());
+        };
+        let to_addr = to_addr.trim();
         let force_send = msg
             .metadata
             .get("force_send")
             .and_then(Value::as_bool)
             .unwrap_or(false);
-        if !self.config.auto_reply_enabled && !force_send {
+        if !account.config.auto_reply_enabled && !force_send {
             return Ok(());
         }
-        if self.config.smtp_host.trim().is_empty() {
-            eprintln!("email channel SMTP host not configured");
+        if account.config.smtp_host.trim().is_empty() {
+            eprintln!("{} SMTP host not configured", account.label());
             return Ok(());
         }
 
-        let to_addr = msg.chat_id.trim();
         if to_addr.is_empty() {
             return Ok(());
         }
 
-        let base_subject = self
+        let base_subject = account
             .last_subject_by_chat
             .lock()
             .expect("poisoned mutex")
             .get(to_addr)
             .cloned()
             .unwrap_or_else(|| "nanobot reply".to_string());
-        let mut subject = self.reply_subject(&base_subject);
+        let mut subject = account.reply_subject(&base_subject);
         if let Some(s) = msg.metadata.get("subject").and_then(Value::as_str)
             && !s.trim().is_empty()
         {
             subject = s.trim().to_string();
         }
 
-        let from_addr = if self.config.from_address.trim().is_empty() {
-            if !self.config.smtp_username.trim().is_empty() {
-                self.config.smtp_username.as_str()
+        let from_addr = if account.config.from_address.trim().is_empty() {
+            if !account.config.smtp_username.trim().is_empty() {
+                account.config.smtp_username.as_str()
             } else {
-                self.config.imap_username.as_str()
+                account.config.imap_username.as_str()
             }
         } else {
-            self.config.from_address.as_str()
+            account.config.from_address.as_str()
         };
 
         let mut builder = Message::builder()
@@ -447,27 +720,57 @@ impl Channel for EmailChannel {
             .to(to_addr.parse().context("invalid recipient email address")?)
             .subject(subject);
 
-        if let Some(in_reply_to) = self
-            .last_message_id_by_chat
-            .lock()
-            .expect("poisoned mutex")
-            .get(to_addr)
-            .cloned()
-            .filter(|v| !v.trim().is_empty())
-        {
-            builder = builder.header(InReplyTo::from(in_reply_to.clone()));
-            builder = builder.header(References::from(in_reply_to));
+        let in_reply_to = msg.reply_to.clone().or_else(|| {
+            account
+                .last_message_id_by_chat
+                .lock()
+                .expect("poisoned mutex")
+                .get(to_addr)
+                .cloned()
+        });
+        if let Some(in_reply_to) = in_reply_to.filter(|v| !v.trim().is_empty()) {
+            let references = match &msg.thread_id {
+                Some(root) if *root != in_reply_to => format!("{root} {in_reply_to}"),
+                _ => in_reply_to.clone(),
+            };
+            builder = builder.header(InReplyTo::from(in_reply_to));
+            builder = builder.header(References::from(references));
         }
 
-        let email_msg = builder.body(msg.content.clone())?;
-        self.smtp_send(email_msg)
+        let email_msg = builder.body(self.format(&msg.content).text)?;
+        let access_token = account.access_token().await?;
+        account.smtp_send(email_msg, access_token.as_deref())
     }
 }
 
+// `allow_from` entries are addresses or domain wildcards: "*@example.com"
+// (also "@example.com") for one domain, "*@*.example.com" for its subdomains.
+fn sender_allowed(sender: &str, allow_from: &[String]) -> bool {
+    if allow_from.is_empty() {
+        return true;
+    }
+    let sender = sender.trim().to_lowercase();
+    let domain = sender.rsplit_once('@').map(|(_, d)| d).unwrap_or_default();
+    allow_from.iter().any(|entry| {
+        let entry = entry.trim().to_lowercase();
+        match entry.strip_prefix("*@").or_else(|| entry.strip_prefix('@')) {
+            Some(pattern) => match pattern.strip_prefix("*.") {
+                Some(parent) => domain
+                    .strip_suffix(parent)
+                    .is_some_and(|sub| sub.ends_with('.')),
+                None => domain == pattern,
+            },
+            None => entry == sender,
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::EmailChannel;
+    use super::{EmailChannel, IDENTICAL_BODY_WINDOW_MS, sender_allowed};
+    use crate::bus::InboundMessage;
     use crate::bus::MessageBus;
+    use crate::channels::base::Channel;
     use crate::config::EmailConfig;
     use std::sync::Arc;
 
@@ -494,14 +797,24 @@ mod tests {
             max_body_chars: 12_000,
             subject_prefix: "Re: ".to_string(),
             allow_from: Vec::new(),
+            read_only: false,
+            delivery_window: None,
+            max_reply_chars: None,
+            citations: None,
+            skip_automated: true,
+            max_identical_bodies: 2,
+            oauth: None,
+            id: String::new(),
+            accounts: Vec::new(),
         }
     }
 
     #[test]
     fn reply_subject_keeps_existing_re_prefix() {
         let channel = EmailChannel::new(test_config(), Arc::new(MessageBus::new(4)));
-        assert_eq!(channel.reply_subject("Re: status"), "Re: status");
-        assert_eq!(channel.reply_subject(""), "Re: nanobot reply");
+        let account = &channel.accounts[0];
+        assert_eq!(account.reply_subject("Re: status"), "Re: status");
+        assert_eq!(account.reply_subject(""), "Re: nanobot reply");
     }
 
     #[test]
@@ -511,9 +824,93 @@ mod tests {
         assert!(out.contains("world"));
     }
 
+    #[test]
+    fn autoresponder_headers_mark_mail_automated() {
+        let auto = mailparse::parse_mail(
+            b"From: a@example.com\r\nAuto-Submitted: auto-replied\r\n\r\nOut of office",
+        )
+        .expect("parse");
+        assert!(EmailChannel::is_automated(&auto.headers));
+        let human = mailparse::parse_mail(b"From: a@example.com\r\nAuto-Submitted: no\r\n\r\nhi")
+            .expect("parse");
+        assert!(!EmailChannel::is_automated(&human.headers));
+    }
+
     #[test]
     fn extract_sender_prefers_address() {
         let sender = EmailChannel::extract_sender("Alice <alice@example.com>");
         assert_eq!(sender, "alice@example.com");
     }
+
+    #[test]
+    fn allow_from_accepts_domain_wildcards() {
+        let allow = vec![
+            "Boss@Example.org".to_string(),
+            "*@example.com".to_string(),
+            "*@*.corp.net".to_string(),
+        ];
+        assert!(sender_allowed("boss@example.org", &allow));
+        assert!(sender_allowed("anyone@example.com", &allow));
+        assert!(!sender_allowed("anyone@mail.example.com", &allow));
+        assert!(sender_allowed("dev@eu.corp.net", &allow));
+        assert!(!sender_allowed("dev@corp.net", &allow));
+        assert!(!sender_allowed("dev@evilcorp.net", &allow));
+        assert!(sender_allowed("x@y.z", &[]));
+    }
+
+    #[test]
+    fn newsletters_and_repeats_are_flagged() {
+        let list = mailparse::parse_mail(
+            b"From: news@shop.com\r\nList-Unsubscribe: <mailto:u@shop.com>\r\n\r\nSale!",
+        )
+        .expect("parse");
+        assert_eq!(
+            EmailChannel::automated_reason(&list.headers, "news@shop.com"),
+            Some("mailing list")
+        );
+        let plain = mailparse::parse_mail(b"From: a@b.com\r\n\r\nhi").expect("parse");
+        assert_eq!(
+            EmailChannel::automated_reason(&plain.headers, "no-reply+alerts@b.com"),
+            Some("no-reply sender")
+        );
+        assert_eq!(
+            EmailChannel::automated_reason(&plain.headers, "a@b.com"),
+            None
+        );
+
+        let channel = EmailChannel::new(test_config(), Arc::new(MessageBus::new(4)));
+        let account = &channel.accounts[0];
+        let digest = EmailChannel::body_digest("Win  a PRIZE now");
+        assert_eq!(digest, EmailChannel::body_digest("win a prize\nnow"));
+        assert!(!account.repeated_body(digest, 0));
+        assert!(!account.repeated_body(digest, 1_000));
+        assert!(account.repeated_body(digest, 2_000));
+        assert!(!account.repeated_body(digest, 2_000 + IDENTICAL_BODY_WINDOW_MS));
+    }
+
+    #[test]
+    fn accounts_route_by_chat_id_prefix() {
+        let mut config = test_config();
+        config.allow_from = vec!["boss@example.org".to_string()];
+        config.accounts = vec![EmailConfig {
+            id: "work".to_string(),
+            imap_username: "me@corp.net".to_string(),
+            allow_from: vec!["*@corp.net".to_string()],
+            ..EmailConfig::default()
+        }];
+        let channel = EmailChannel::new(config, Arc::new(MessageBus::new(4)));
+        assert_eq!(channel.accounts.len(), 2);
+        assert_eq!(channel.accounts[1].chat_id("a@corp.net"), "work:a@corp.net");
+
+        let (account, address) = channel.route("work:a@corp.net").expect("work");
+        assert_eq!((account.id.as_str(), address), ("work", "a@corp.net"));
+        let (account, address) = channel.route("boss@example.org").expect("default");
+        assert_eq!((account.id.as_str(), address), ("", "boss@example.org"));
+
+        let inbound = |sender: &str, chat: &str| InboundMessage::new("email", sender, chat, "hi");
+        assert!(channel.admits(&inbound("a@corp.net", "work:a@corp.net")));
+        assert!(!channel.admits(&inbound("a@corp.net", "a@corp.net")));
+        assert!(channel.admits(&inbound("boss@example.org", "boss@example.org")));
+        assert!(!channel.admits(&inbound("boss@example.org", "work:boss@example.org")));
+    }
 }
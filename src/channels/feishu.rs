@@ -1,11 +1,12 @@
-use crate::bus::{MessageBus, OutboundMessage};
+use crate::bus::{MessageBus, OutboundMessage, TurnActivity};
 use crate::channels::base::Channel;
+use crate::channels::format::{ChannelCapabilities, channel_capabilities};
 use crate::config::FeishuConfig;
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
-use regex::Regex;
 use reqwest::Client;
 use serde_json::{Value, json};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::Mutex;
@@ -20,18 +21,6 @@ use crate::pairing::{issue_pairing, pairing_prompt};
 use open_lark::client::ws_client::LarkWsClient;
 #[cfg(feature = "feishu-websocket")]
 use open_lark::prelude::{AppType, EventDispatcherHandler, LarkClient, P2ImMessageReceiveV1};
-#[cfg(feature = "feishu-websocket")]
-use std::collections::{HashSet, VecDeque};
-
-#[cfg(feature = "feishu-websocket")]
-const MAX_DEDUP_IDS: usize = 1000;
-
-#[cfg(feature = "feishu-websocket")]
-#[derive(Default)]
-struct DedupState {
-    order: VecDeque<String>,
-    seen: HashSet<String>,
-}
 
 pub struct FeishuChannel {
     config: FeishuConfig,
@@ -39,10 +28,10 @@ pub struct FeishuChannel {
     running: Arc<AtomicBool>,
     http: Client,
     tenant_access_token: Mutex<Option<String>>,
+    // "Thinking" cards shown while a turn runs, by chat id.
+    placeholders: Mutex<HashMap<String, String>>,
     #[cfg(feature = "feishu-websocket")]
     ws_thread: Mutex<Option<std::thread::JoinHandle<()>>>,
-    #[cfg(feature = "feishu-websocket")]
-    dedup: Arc<Mutex<DedupState>>,
 }
 
 impl FeishuChannel {
@@ -53,10 +42,9 @@ impl FeishuChannel {
             running: Arc::new(AtomicBool::new(false)),
             http: Client::new(),
             tenant_access_token: Mutex::new(None),
+            placeholders: Mutex::new(HashMap::new()),
             #[cfg(feature = "feishu-websocket")]
             ws_thread: Mutex::new(None),
-            #[cfg(feature = "feishu-websocket")]
-            dedup: Arc::new(Mutex::new(DedupState::default())),
         }
     }
 
@@ -87,167 +75,68 @@ impl FeishuChannel {
         Ok(token)
     }
 
-    fn parse_md_table(table_text: &str) -> Option<Value> {
-        let lines = table_text
-            .lines()
-            .map(str::trim)
-            .filter(|line| !line.is_empty())
-            .collect::<Vec<_>>();
-        if lines.len() < 3 {
-            return None;
-        }
-        let split_row = |line: &str| {
-            line.trim_matches('|')
-                .split('|')
-                .map(|c| c.trim().to_string())
-                .collect::<Vec<_>>()
-        };
-        let headers = split_row(lines[0]);
-        let rows = lines
-            .iter()
-            .skip(2)
-            .map(|line| split_row(line))
-            .collect::<Vec<_>>();
-        let columns = headers
-            .iter()
-            .enumerate()
-            .map(|(i, header)| {
-                json!({
-                    "tag": "column",
-                    "name": format!("c{i}"),
-                    "display_name": header,
-                    "width": "auto"
-                })
-            })
-            .collect::<Vec<_>>();
-        let row_values = rows
-            .iter()
-            .map(|row| {
-                let mut map = serde_json::Map::new();
-                for (i, _) in headers.iter().enumerate() {
-                    map.insert(
-                        format!("c{i}"),
-                        Value::String(row.get(i).cloned().unwrap_or_default()),
-                    );
-                }
-                Value::Object(map)
-            })
-            .collect::<Vec<_>>();
-        Some(json!({
-            "tag": "table",
-            "page_size": row_values.len() + 1,
-            "columns": columns,
-            "rows": row_values,
-        }))
-    }
-
-    fn build_card_elements(&self, content: &str) -> Vec<Value> {
-        let table_re = Regex::new(
-            r"(?m)((?:^[ \t]*\|.+\|[ \t]*\n)(?:^[ \t]*\|[-:\s|]+\|[ \t]*\n)(?:^[ \t]*\|.+\|[ \t]*\n?)+)",
-        )
-        .expect("valid feishu table regex");
-        let mut elements = Vec::new();
-        let mut last_end = 0usize;
-        for m in table_re.find_iter(content) {
-            let before = &content[last_end..m.start()];
-            if !before.trim().is_empty() {
-                elements.extend(Self::split_headings(before));
-            }
-            let raw_table = m.as_str();
-            if let Some(parsed) = Self::parse_md_table(raw_table) {
-                elements.push(parsed);
-            } else {
-                elements.push(json!({"tag":"markdown","content": raw_table}));
-            }
-            last_end = m.end();
-        }
-        let remaining = &content[last_end..];
-        if !remaining.trim().is_empty() {
-            elements.extend(Self::split_headings(remaining));
-        }
-        if elements.is_empty() {
-            elements.push(json!({"tag":"markdown","content": content}));
-        }
-        elements
-    }
-
-    fn split_headings(content: &str) -> Vec<Value> {
-        let heading_re = Regex::new(r"(?m)^(#{1,6})\s+(.+)$").expect("valid heading regex");
-        let code_block_re = Regex::new(r"(?ms)(```[\s\S]*?```)").expect("valid code block regex");
-
-        let mut protected = content.to_string();
-        let mut code_blocks = Vec::new();
-        for cap in code_block_re.captures_iter(content) {
-            if let Some(m) = cap.get(1) {
-                code_blocks.push(m.as_str().to_string());
-            }
-        }
-        for (idx, block) in code_blocks.iter().enumerate() {
-            let token = format!("\u{0000}CODE{idx}\u{0000}");
-            protected = protected.replacen(block, &token, 1);
-        }
-
-        let mut elements = Vec::new();
-        let mut last_end = 0usize;
-        for cap in heading_re.captures_iter(&protected) {
-            let Some(m) = cap.get(0) else {
-                continue;
-            };
-            let before = protected[last_end..m.start()].trim();
-            if !before.is_empty() {
-                elements.push(json!({"tag":"markdown","content": before}));
-            }
-            let text = cap.get(2).map(|v| v.as_str().trim()).unwrap_or_default();
-            elements.push(json!({
-                "tag":"div",
-                "text": {
-                    "tag":"lark_md",
-                    "content": format!("**{text}**"),
-                }
-            }));
-            last_end = m.end();
-        }
-        let remaining = protected[last_end..].trim();
-        if !remaining.is_empty() {
-            elements.push(json!({"tag":"markdown","content": remaining}));
-        }
-
-        for (idx, block) in code_blocks.iter().enumerate() {
-            let token = format!("\u{0000}CODE{idx}\u{0000}");
-            for element in &mut elements {
-                if element.get("tag").and_then(Value::as_str) == Some("markdown")
-                    && let Some(content) = element.get_mut("content")
-                    && let Some(text) = content.as_str()
-                {
-                    *content = Value::String(text.replace(&token, block));
-                }
-            }
-        }
-
-        if elements.is_empty() {
-            vec![json!({"tag":"markdown","content": content})]
+    // Posts an interactive card and returns its message id.
+    async fn post_card(
+        &self,
+        chat_id: &str,
+        thread_id: Option<&str>,
+        reply_to: Option<&str>,
+        card: &Value,
+    ) -> Result<Option<String>> {
+        let token = self.get_tenant_access_token().await?;
+        let receive_id_type = if chat_id.starts_with("oc_") {
+            "chat_id"
         } else {
-            elements
+            "open_id"
+        };
+        let url = format!(
+            "https://open.feishu.cn/open-apis/im/v1/messages?receive_id_type={receive_id_type}"
+        );
+        // Inside a thread, answer through the reply API so the card stays in it.
+        let request = match thread_id {
+            Some(root_id) => self
+                .http
+                .post(format!(
+                    "https://open.feishu.cn/open-apis/im/v1/messages/{}/reply",
+                    reply_to.unwrap_or(root_id)
+                ))
+                .json(&json!({
+                    "msg_type": "interactive",
+                    "content": card.to_string(),
+                    "reply_in_thread": true,
+                })),
+            None => self.http.post(url).json(&json!({
+                "receive_id": chat_id,
+                "msg_type": "interactive",
+                "content": card.to_string(),
+            })),
+        };
+        let resp = request.bearer_auth(token).send().await?;
+        if !resp.status().is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(anyhow!("failed to send feishu message: {body}"));
         }
+        let sent: Value = resp.json().await.unwrap_or_default();
+        Ok(sent
+            .pointer("/data/message_id")
+            .and_then(Value::as_str)
+            .map(ToOwned::to_owned))
     }
 
     #[cfg(feature = "feishu-websocket")]
     fn build_event_handler(
         bus: Arc<MessageBus>,
         allow_from: Vec<String>,
-        dedup: Arc<Mutex<DedupState>>,
         verification_token: String,
         encrypt_key: String,
     ) -> Result<EventDispatcherHandler> {
         let bus_outer = bus.clone();
         let allow_from_outer = allow_from.clone();
-        let dedup_outer = dedup.clone();
 
         let builder = EventDispatcherHandler::builder().register_p2_im_message_receive_v1(
             move |event: P2ImMessageReceiveV1| {
                 let bus = bus_outer.clone();
                 let allow_from = allow_from_outer.clone();
-                let dedup = dedup_outer.clone();
                 tokio::spawn(async move {
                     let message = event.event.message;
                     let sender = event.event.sender;
@@ -256,19 +145,6 @@ impl FeishuChannel {
                     }
 
                     let message_id = message.message_id.clone();
-                    {
-                        let mut state = dedup.lock().await;
-                        if state.seen.contains(&message_id) {
-                            return;
-                        }
-                        state.seen.insert(message_id.clone());
-                        state.order.push_back(message_id.clone());
-                        while state.order.len() > MAX_DEDUP_IDS {
-                            if let Some(old) = state.order.pop_front() {
-                                state.seen.remove(&old);
-                            }
-                        }
-                    }
 
                     let sender_id = sender.sender_id.open_id;
                     if !is_allowed_sender(&sender_id, &allow_from) {
@@ -313,9 +189,9 @@ impl FeishuChannel {
                     };
 
                     let mut inbound = InboundMessage::new("feishu", sender_id, chat_id, content);
-                    inbound
-                        .metadata
-                        .insert("message_id".to_string(), Value::String(message_id));
+                    inbound.message_id = Some(message_id);
+                    inbound.thread_id = message.root_id.filter(|id| !id.is_empty());
+                    inbound.reply_to = message.parent_id.filter(|id| !id.is_empty());
                     inbound
                         .metadata
                         .insert("chat_type".to_string(), Value::String(message.chat_type));
@@ -348,7 +224,6 @@ impl FeishuChannel {
         let encrypt_key = self.config.encrypt_key.clone();
         let running = self.running.clone();
         let bus = self.bus.clone();
-        let dedup = self.dedup.clone();
 
         std::thread::spawn(move || {
             let runtime = tokio::runtime::Builder::new_current_thread()
@@ -371,7 +246,6 @@ impl FeishuChannel {
                     let handler = Self::build_event_handler(
                         bus.clone(),
                         allow_from.clone(),
-                        dedup.clone(),
                         verification_token.clone(),
                         encrypt_key.clone(),
                     );
@@ -404,6 +278,10 @@ impl Channel for FeishuChannel {
         &self.config.allow_from
     }
 
+    fn capabilities(&self) -> ChannelCapabilities {
+        channel_capabilities("feishu")
+    }
+
     fn bus(&self) -> Arc<MessageBus> {
         self.bus.clone()
     }
@@ -438,35 +316,53 @@ impl Channel for FeishuChannel {
     }
 
     async fn send(&self, msg: &OutboundMessage) -> Result<()> {
-        let token = self.get_tenant_access_token().await?;
-        let receive_id_type = if msg.chat_id.starts_with("oc_") {
-            "chat_id"
-        } else {
-            "open_id"
+        let mut card = self.format(&msg.content).rich.unwrap_or_default();
+        if let Some(title) = msg.metadata.get("title").and_then(Value::as_str) {
+            card["header"] = json!({
+                "title": {"tag": "plain_text", "content": title},
+                "template": "blue",
+            });
+        }
+        self.post_card(
+            &msg.chat_id,
+            msg.thread_id.as_deref(),
+            msg.reply_to.as_deref(),
+            &card,
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn set_typing(&self, activity: &TurnActivity) -> Result<()> {
+        if activity.active {
+            let card = self.format("⏳ Thinking…").rich.unwrap_or_default();
+            let id = self
+                .post_card(
+                    &activity.chat_id,
+                    activity.thread_id.as_deref(),
+                    None,
+                    &card,
+                )
+                .await?;
+            if let Some(id) = id {
+                self.placeholders
+                    .lock()
+                    .await
+                    .insert(activity.chat_id.clone(), id);
+            }
+            return Ok(());
+        }
+        let Some(id) = self.placeholders.lock().await.remove(&activity.chat_id) else {
+            return Ok(());
         };
-        let url = format!(
-            "https://open.feishu.cn/open-apis/im/v1/messages?receive_id_type={receive_id_type}"
-        );
-        let elements = self.build_card_elements(&msg.content);
-        let card = json!({
-            "config": {"wide_screen_mode": true},
-            "elements": elements,
-        });
-        let resp = self
-            .http
-            .post(url)
+        let token = self.get_tenant_access_token().await?;
+        self.http
+            .delete(format!(
+                "https://open.feishu.cn/open-apis/im/v1/messages/{id}"
+            ))
             .bearer_auth(token)
-            .json(&json!({
-                "receive_id": msg.chat_id,
-                "msg_type": "interactive",
-                "content": card.to_string(),
-            }))
             .send()
             .await?;
-        if !resp.status().is_success() {
-            let body = resp.text().await.unwrap_or_default();
-            return Err(anyhow!("failed to send feishu message: {body}"));
-        }
         Ok(())
     }
 }
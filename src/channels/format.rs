@@ -0,0 +1,330 @@
+use crate::channels::markdown::{
+    BlockKind, PlainText, SlackMrkdwn, TelegramHtml, render, table_rows, top_level_blocks,
+};
+use serde_json::{Value, json};
+
+const SLACK_SECTION_LIMIT: usize = 3000;
+const SLACK_HEADER_LIMIT: usize = 150;
+const SLACK_MAX_BLOCKS: usize = 50;
+
+// Channels that render the "buttons" metadata ([{text, command}]); others
+// need the commands spelled out in the text.
+pub const BUTTON_CHANNELS: &[&str] = &["telegram"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Markup {
+    #[default]
+    Markdown,
+    TelegramHtml,
+    SlackBlocks,
+    FeishuCard,
+    PlainText,
+}
+
+// Describes what a channel can render, so adapters share one formatter
+// instead of each converting markdown on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ChannelCapabilities {
+    pub markup: Markup,
+    // Longest message the platform accepts, in characters; 0 means no limit.
+    pub max_message_chars: usize,
+    // Whether the adapter uploads the local files in `media`; others only
+    // get paths the user can open on the host.
+    pub attachments: bool,
+}
+
+impl ChannelCapabilities {
+    pub const fn new(markup: Markup) -> Self {
+        Self {
+            markup,
+            max_message_chars: 0,
+            attachments: false,
+        }
+    }
+
+    pub const fn with_max_chars(mut self, max_message_chars: usize) -> Self {
+        self.max_message_chars = max_message_chars;
+        self
+    }
+
+    pub const fn with_attachments(mut self) -> Self {
+        self.attachments = true;
+        self
+    }
+}
+
+// What each built-in adapter renders and how long its messages may be.
+pub fn channel_capabilities(channel: &str) -> ChannelCapabilities {
+    match channel {
+        "telegram" => ChannelCapabilities::new(Markup::TelegramHtml).with_max_chars(4096),
+        "discord" => ChannelCapabilities::default().with_max_chars(2000),
+        "slack" => ChannelCapabilities::new(Markup::SlackBlocks).with_max_chars(12000),
+        "feishu" => ChannelCapabilities::new(Markup::FeishuCard).with_max_chars(10000),
+        "qq" => ChannelCapabilities::new(Markup::PlainText).with_max_chars(2000),
+        "whatsapp" => ChannelCapabilities::new(Markup::PlainText).with_max_chars(65536),
+        "dingtalk" => ChannelCapabilities::default().with_max_chars(5000),
+        "email" => ChannelCapabilities::new(Markup::PlainText),
+        _ => ChannelCapabilities::default(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormattedMessage {
+    // Text in the channel's native markup; also the fallback for rich payloads.
+    pub text: String,
+    // Structured payload (Slack blocks, Feishu card) when the channel has one.
+    pub rich: Option<Value>,
+}
+
+pub fn format_message(capabilities: ChannelCapabilities, content: &str) -> FormattedMessage {
+    match capabilities.markup {
+        Markup::Markdown => FormattedMessage {
+            text: content.to_string(),
+            rich: None,
+        },
+        Markup::TelegramHtml => FormattedMessage {
+            text: markdown_to_telegram_html(content),
+            rich: None,
+        },
+        Markup::SlackBlocks => FormattedMessage {
+            text: markdown_to_slack_mrkdwn(content),
+            rich: Some(Value::Array(markdown_to_slack_blocks(content))),
+        },
+        Markup::FeishuCard => FormattedMessage {
+            text: content.to_string(),
+            rich: Some(json!({
+                "config": {"wide_screen_mode": true},
+                "elements": feishu_card_elements(content),
+            })),
+        },
+        Markup::PlainText => FormattedMessage {
+            text: markdown_to_plain_text(content),
+            rich: None,
+        },
+    }
+}
+
+pub fn markdown_to_telegram_html(text: &str) -> String {
+    render(text, &TelegramHtml)
+}
+
+pub fn markdown_to_plain_text(text: &str) -> String {
+    render(text, &PlainText)
+}
+
+pub fn markdown_to_slack_mrkdwn(text: &str) -> String {
+    render(text, &SlackMrkdwn)
+}
+
+pub fn markdown_to_slack_blocks(text: &str) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    for (kind, range) in top_level_blocks(text) {
+        let source = &text[range];
+        if kind == BlockKind::Heading {
+            let title = render(source, &PlainText)
+                .chars()
+                .take(SLACK_HEADER_LIMIT)
+                .collect::<String>();
+            blocks.push(json!({
+                "type": "header",
+                "text": {"type": "plain_text", "text": title},
+            }));
+            continue;
+        }
+        for chunk in split_at_lines(&render(source, &SlackMrkdwn), SLACK_SECTION_LIMIT) {
+            blocks.push(json!({
+                "type": "section",
+                "text": {"type": "mrkdwn", "text": chunk},
+            }));
+        }
+    }
+    blocks.truncate(SLACK_MAX_BLOCKS);
+    blocks
+}
+
+pub(crate) fn split_at_lines(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    for line in text.split('\n') {
+        let mut line = line;
+        while line.chars().count() > limit {
+            let cut = line
+                .char_indices()
+                .nth(limit)
+                .map(|(idx, _)| idx)
+                .unwrap_or(line.len());
+            if !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+            }
+            chunks.push(line[..cut].to_string());
+            line = &line[cut..];
+        }
+        let needed = current.chars().count() + line.chars().count() + 1;
+        if !current.is_empty() && needed > limit {
+            chunks.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.push('\n');
+        }
+        current.push_str(line);
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+fn feishu_table(rows: &[Vec<String>]) -> Option<Value> {
+    let (headers, body) = rows.split_first()?;
+    let columns = headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            json!({
+                "tag": "column",
+                "name": format!("c{i}"),
+                "display_name": header,
+                "width": "auto"
+            })
+        })
+        .collect::<Vec<_>>();
+    let row_values = body
+        .iter()
+        .map(|row| {
+            let mut map = serde_json::Map::new();
+            for (i, _) in headers.iter().enumerate() {
+                map.insert(
+                    format!("c{i}"),
+                    Value::String(row.get(i).cloned().unwrap_or_default()),
+                );
+            }
+            Value::Object(map)
+        })
+        .collect::<Vec<_>>();
+    Some(json!({
+        "tag": "table",
+        "page_size": row_values.len() + 1,
+        "columns": columns,
+        "rows": row_values,
+    }))
+}
+
+// Feishu's markdown element renders most syntax natively; only headings and
+// tables need dedicated card elements.
+pub fn feishu_card_elements(content: &str) -> Vec<Value> {
+    let mut elements = Vec::new();
+    for (kind, range) in top_level_blocks(content) {
+        let source = &content[range];
+        match kind {
+            BlockKind::Heading => elements.push(json!({
+                "tag": "div",
+                "text": {
+                    "tag": "lark_md",
+                    "content": format!("**{}**", render(source, &PlainText)),
+                }
+            })),
+            BlockKind::Table => elements.push(
+                feishu_table(&table_rows(source))
+                    .unwrap_or_else(|| json!({"tag": "markdown", "content": source})),
+            ),
+            BlockKind::Other => {
+                if !source.trim().is_empty() {
+                    elements.push(json!({"tag": "markdown", "content": source.trim()}));
+                }
+            }
+        }
+    }
+    if elements.is_empty() {
+        elements.push(json!({"tag": "markdown", "content": content}));
+    }
+    elements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_attachments_are_opt_in_per_channel() {
+        assert!(!channel_capabilities("telegram").attachments);
+        assert!(!channel_capabilities("cli").attachments);
+        let caps = ChannelCapabilities::default().with_attachments();
+        assert!(caps.attachments);
+        assert_eq!(caps.max_message_chars, 0);
+    }
+
+    #[test]
+    fn markdown_converter_preserves_code_blocks_and_escapes_html() {
+        let input = "```rust\nlet x = 1 < 2;\n```\ntext";
+        let out = markdown_to_telegram_html(input);
+        assert!(out.contains("<pre><code>let x = 1 &lt; 2;\n</code></pre>"));
+        assert!(out.contains("text"));
+    }
+
+    #[test]
+    fn markdown_converter_formats_links_and_styles() {
+        let input = "[site](https://example.com) **b** _i_ ~~s~~";
+        let out = markdown_to_telegram_html(input);
+        assert!(out.contains(r#"<a href="https://example.com">site</a>"#));
+        assert!(out.contains("<b>b</b>"));
+        assert!(out.contains("<i>i</i>"));
+        assert!(out.contains("<s>s</s>"));
+    }
+
+    #[test]
+    fn plain_text_strips_markup_but_keeps_code_and_urls() {
+        let input =
+            "# Title\n**bold** and _it_ see [docs](https://x.io)\n* item\n```sh\nls -la\n```";
+        let out = markdown_to_plain_text(input);
+        assert_eq!(
+            out,
+            "Title\n\nbold and it see docs (https://x.io)\n\n- item\n\nls -la"
+        );
+    }
+
+    #[test]
+    fn slack_mrkdwn_rewrites_emphasis_and_links() {
+        let input = "**b** *i* ~~s~~ [site](https://example.com) `a**b**`";
+        let out = markdown_to_slack_mrkdwn(input);
+        assert_eq!(out, "*b* _i_ ~s~ <https://example.com|site> `a**b**`");
+    }
+
+    #[test]
+    fn slack_blocks_split_headings_and_skip_code() {
+        let input = "## Plan\nstep one\n```\n# not a heading\n```";
+        let blocks = markdown_to_slack_blocks(input);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0]["type"], "header");
+        assert_eq!(blocks[0]["text"]["text"], "Plan");
+        assert_eq!(blocks[1]["type"], "section");
+        assert!(
+            blocks[1]["text"]["text"]
+                .as_str()
+                .unwrap_or_default()
+                .contains("# not a heading")
+        );
+    }
+
+    #[test]
+    fn capabilities_select_the_formatter() {
+        let content = "**hi**";
+        let plain = format_message(ChannelCapabilities::new(Markup::PlainText), content);
+        assert_eq!(plain.text, "hi");
+        assert!(plain.rich.is_none());
+        let md = format_message(ChannelCapabilities::default(), content);
+        assert_eq!(md.text, content);
+        let card = format_message(ChannelCapabilities::new(Markup::FeishuCard), content);
+        assert!(card.rich.is_some_and(|v| v["elements"].is_array()));
+    }
+
+    #[test]
+    fn feishu_card_builds_heading_and_table_elements() {
+        let input = "## Status\nAll good\n\n| svc | ok |\n|---|---|\n| api | yes |";
+        let elements = feishu_card_elements(input);
+        assert_eq!(elements.len(), 3);
+        assert_eq!(elements[0]["text"]["content"], "**Status**");
+        assert_eq!(elements[1]["content"], "All good");
+        assert_eq!(elements[2]["tag"], "table");
+        assert_eq!(elements[2]["rows"][0]["c0"], "api");
+    }
+}
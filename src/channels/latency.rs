@@ -0,0 +1,148 @@
+use crate::bus::OutboundMessage;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+
+// Recent samples kept per (channel, model); older ones roll off.
+const MAX_SAMPLES: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LatencyRow {
+    pub channel: String,
+    pub model: String,
+    pub count: usize,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+}
+
+// End-to-end turn latency, from inbound receipt to the reply being handed to
+// the platform, segmented by channel and model.
+#[derive(Default)]
+pub struct LatencyStats {
+    samples: Mutex<BTreeMap<(String, String), VecDeque<u64>>>,
+}
+
+// Nearest-rank percentile of an ascending slice.
+pub fn percentile(sorted: &[u64], pct: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (pct / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+impl LatencyStats {
+    pub fn record(&self, channel: &str, model: &str, ms: u64) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = samples
+            .entry((channel.to_string(), model.to_string()))
+            .or_default();
+        if entry.len() == MAX_SAMPLES {
+            entry.pop_front();
+        }
+        entry.push_back(ms);
+    }
+
+    // Records a delivered agent reply; messages without the agent's timing
+    // metadata (pairing prompts, cron, subagent progress) are skipped.
+    pub fn record_delivery(&self, msg: &OutboundMessage, now_ms: i64) {
+        let Some(received) = msg.metadata.get("received_at_ms").and_then(Value::as_i64) else {
+            return;
+        };
+        let model = msg
+            .metadata
+            .get("model")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown");
+        self.record(&msg.channel, model, (now_ms - received).max(0) as u64);
+    }
+
+    pub fn rows(&self) -> Vec<LatencyRow> {
+        let samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        samples
+            .iter()
+            .map(|((channel, model), values)| {
+                let mut sorted = values.iter().copied().collect::<Vec<_>>();
+                sorted.sort_unstable();
+                LatencyRow {
+                    channel: channel.clone(),
+                    model: model.clone(),
+                    count: sorted.len(),
+                    p50_ms: percentile(&sorted, 50.0),
+                    p95_ms: percentile(&sorted, 95.0),
+                    p99_ms: percentile(&sorted, 99.0),
+                }
+            })
+            .collect()
+    }
+}
+
+fn format_ms(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{ms}ms")
+    } else {
+        format!("{:.1}s", ms as f64 / 1000.0)
+    }
+}
+
+pub fn render_latency(rows: &[LatencyRow]) -> String {
+    if rows.is_empty() {
+        return "No replies delivered since the gateway started.".to_string();
+    }
+    let mut out = format!(
+        "{:<10} {:<32} {:>6} {:>8} {:>8} {:>8}\n",
+        "CHANNEL", "MODEL", "TURNS", "P50", "P95", "P99"
+    );
+    for row in rows {
+        out.push_str(&format!(
+            "{:<10} {:<32} {:>6} {:>8} {:>8} {:>8}\n",
+            row.channel,
+            row.model,
+            row.count,
+            format_ms(row.p50_ms),
+            format_ms(row.p95_ms),
+            format_ms(row.p99_ms)
+        ));
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn computes_percentiles_per_channel_and_model() {
+        let sorted = (1..=100).collect::<Vec<u64>>();
+        assert_eq!(percentile(&sorted, 50.0), 50);
+        assert_eq!(percentile(&sorted, 95.0), 95);
+        assert_eq!(percentile(&sorted, 99.0), 99);
+        assert_eq!(percentile(&[7], 99.0), 7);
+        assert_eq!(percentile(&[], 50.0), 0);
+
+        let stats = LatencyStats::default();
+        for ms in [400, 100, 300, 200] {
+            stats.record("telegram", "gpt-4o", ms);
+        }
+        let mut reply = OutboundMessage::new("slack", "C1", "hi");
+        reply
+            .metadata
+            .insert("received_at_ms".to_string(), json!(1_000));
+        reply.metadata.insert("model".to_string(), json!("claude"));
+        stats.record_delivery(&reply, 3_500);
+        stats.record_delivery(&OutboundMessage::new("slack", "C1", "pairing"), 9_000);
+
+        let rows = stats.rows();
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].channel.as_str(), rows[0].count), ("slack", 1));
+        assert_eq!(rows[0].p99_ms, 2_500);
+        assert_eq!((rows[1].p50_ms, rows[1].p95_ms), (200, 400));
+        let table = render_latency(&rows);
+        assert!(table.contains("2.5s"));
+        assert!(table.contains("200ms"));
+    }
+}
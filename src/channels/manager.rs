@@ -1,113 +1,164 @@
-use crate::bus::MessageBus;
+use crate::bus::{MessageBus, OutboundMessage};
 use crate::channels::base::Channel;
+use crate::channels::botloop::BotLoopGuard;
+use crate::channels::chunking::split_outbound;
+use crate::channels::dedup::InboundDedup;
+use crate::channels::delivery::{DeliveryPolicy, HeldQueue, is_proactive, is_urgent};
+use crate::channels::digest::DigestBuffer;
 use crate::channels::dingtalk::DingTalkChannel;
 use crate::channels::discord::DiscordChannel;
 use crate::channels::email::EmailChannel;
 use crate::channels::feishu::FeishuChannel;
+use crate::channels::latency::{LatencyRow, LatencyStats};
 use crate::channels::mochat::MochatChannel;
 use crate::channels::qq::QQChannel;
 use crate::channels::slack::SlackChannel;
 use crate::channels::telegram::TelegramChannel;
 use crate::channels::whatsapp::WhatsAppChannel;
-use crate::config::Config;
+use crate::config::{CHANNEL_NAMES, Config};
+use crate::events::{self, Event, EventLevel};
+use anyhow::{Result, anyhow};
+use chrono::Local;
 use std::collections::HashMap;
-use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use tokio::sync::Mutex;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, broadcast};
+use tokio::task::{AbortHandle, JoinHandle};
+
+type ChannelMap = Arc<RwLock<HashMap<String, Arc<dyn Channel>>>>;
+
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(300);
+// An adapter that stayed up this long restarts from the shortest delay again.
+const STABLE_RUN: Duration = Duration::from_secs(120);
+const RESTART_MARKER: &str = "restarting in";
+pub const FLAP_WINDOW_MS: i64 = 60 * 60 * 1000;
+pub const FLAP_RESTARTS: usize = 5;
+
+// Aborts the adapter's task if its supervisor is aborted first.
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+// 1s, 2s, 4s, ... capped at five minutes.
+pub fn restart_delay(failures: u32) -> Duration {
+    Duration::from_secs(1u64 << failures.min(16)).min(RESTART_BACKOFF_MAX)
+}
+
+// Channels the supervisor restarted at least FLAP_RESTARTS times among
+// `events`, with their restart counts.
+pub fn flapping_channels(events: &[Event]) -> Vec<(String, usize)> {
+    let mut counts = HashMap::<String, usize>::new();
+    for event in events {
+        if event.kind == "channel"
+            && event.message.contains(RESTART_MARKER)
+            && let Some(channel) = &event.channel
+        {
+            *counts.entry(channel.clone()).or_default() += 1;
+        }
+    }
+    let mut flapping = counts
+        .into_iter()
+        .filter(|(_, count)| *count >= FLAP_RESTARTS)
+        .collect::<Vec<_>>();
+    flapping.sort();
+    flapping
+}
+
+fn lookup(channels: &ChannelMap, name: &str) -> Option<Arc<dyn Channel>> {
+    channels
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(name)
+        .cloned()
+}
+
+// Long replies go out as several messages, split at markdown block boundaries
+// so no adapter has to truncate them; the first failed part aborts the rest.
+async fn send_in_parts(
+    channel: &dyn Channel,
+    msg: &OutboundMessage,
+    limits: &HashMap<String, usize>,
+) -> Result<()> {
+    let limit = limits
+        .get(&msg.channel)
+        .copied()
+        .unwrap_or_else(|| channel.capabilities().max_message_chars);
+    for part in split_outbound(msg, limit) {
+        channel.send(&part).await?;
+    }
+    Ok(())
+}
+
+// Builds the adapter for `name` from its config section, enabled or not.
+fn build_channel(config: &Config, name: &str, bus: Arc<MessageBus>) -> Option<Arc<dyn Channel>> {
+    let channels = &config.channels;
+    let channel: Arc<dyn Channel> = match name {
+        "telegram" => Arc::new(TelegramChannel::new(
+            channels.telegram.clone(),
+            bus,
+            config.providers.groq.api_key.clone(),
+        )),
+        "whatsapp" => Arc::new(WhatsAppChannel::new(channels.whatsapp.clone(), bus)),
+        "discord" => Arc::new(DiscordChannel::new(channels.discord.clone(), bus)),
+        "feishu" => Arc::new(FeishuChannel::new(channels.feishu.clone(), bus)),
+        "mochat" => Arc::new(MochatChannel::new(channels.mochat.clone(), bus)),
+        "dingtalk" => Arc::new(DingTalkChannel::new(channels.dingtalk.clone(), bus)),
+        "email" => Arc::new(EmailChannel::new(channels.email.clone(), bus)),
+        "slack" => Arc::new(SlackChannel::new(channels.slack.clone(), bus)),
+        "qq" => Arc::new(QQChannel::new(channels.qq.clone(), bus)),
+        _ => return None,
+    };
+    Some(channel)
+}
 
 pub struct ChannelManager {
     bus: Arc<MessageBus>,
-    channels: HashMap<String, Arc<dyn Channel>>,
+    channels: ChannelMap,
+    delivery: DeliveryPolicy,
+    digest: Option<Arc<DigestBuffer>>,
+    loop_guard: Option<Arc<BotLoopGuard>>,
     running: Arc<AtomicBool>,
-    dispatch_task: Mutex<Option<tokio::task::JoinHandle<()>>>,
-    channel_tasks: Mutex<Vec<tokio::task::JoinHandle<()>>>,
+    dispatch_task: Mutex<Option<JoinHandle<()>>>,
+    channel_tasks: Mutex<Vec<JoinHandle<()>>>,
+    adapter_tasks: Mutex<HashMap<String, JoinHandle<()>>>,
+    restarts: Arc<std::sync::Mutex<HashMap<String, u32>>>,
+    latency: Arc<LatencyStats>,
+    // Per-channel `maxReplyChars` overrides of the adapter's own limit.
+    reply_limits: Arc<HashMap<String, usize>>,
 }
 
 impl ChannelManager {
     pub fn new(config: &Config, bus: Arc<MessageBus>) -> Self {
-        let mut channels: HashMap<String, Arc<dyn Channel>> = HashMap::new();
-
-        if config.channels.telegram.enabled {
-            channels.insert(
-                "telegram".to_string(),
-                Arc::new(TelegramChannel::new(
-                    config.channels.telegram.clone(),
-                    bus.clone(),
-                    config.providers.groq.api_key.clone(),
-                )),
-            );
-        }
-        if config.channels.whatsapp.enabled {
-            channels.insert(
-                "whatsapp".to_string(),
-                Arc::new(WhatsAppChannel::new(
-                    config.channels.whatsapp.clone(),
-                    bus.clone(),
-                )),
-            );
+        let channels = CHANNEL_NAMES
+            .iter()
+            .filter(|name| config.channels.is_enabled(name))
+            .filter_map(|name| {
+                build_channel(config, name, bus.clone()).map(|ch| (name.to_string(), ch))
+            })
+            .collect::<HashMap<_, _>>();
+
+        match InboundDedup::new() {
+            Ok(dedup) => bus.set_dedup(dedup),
+            Err(err) => eprintln!("Warning: inbound deduplication disabled: {err}"),
         }
-        if config.channels.discord.enabled {
-            channels.insert(
-                "discord".to_string(),
-                Arc::new(DiscordChannel::new(
-                    config.channels.discord.clone(),
-                    bus.clone(),
-                )),
-            );
-        }
-        if config.channels.feishu.enabled {
-            channels.insert(
-                "feishu".to_string(),
-                Arc::new(FeishuChannel::new(
-                    config.channels.feishu.clone(),
-                    bus.clone(),
-                )),
-            );
+        let loop_guard = Arc::new(BotLoopGuard::new(config.channels.max_bot_exchanges));
+        bus.set_loop_guard(loop_guard.clone());
+        let mut manager = Self::from_channels(bus, channels);
+        manager.loop_guard = Some(loop_guard);
+        let windows = config.channels.delivery_windows();
+        if !windows.is_empty() {
+            manager.delivery = DeliveryPolicy::new(windows, HeldQueue::new().ok());
         }
-        if config.channels.mochat.enabled {
-            channels.insert(
-                "mochat".to_string(),
-                Arc::new(MochatChannel::new(
-                    config.channels.mochat.clone(),
-                    bus.clone(),
-                )),
-            );
+        manager.reply_limits = Arc::new(config.channels.reply_limits());
+        if config.channels.digest_minutes > 0 {
+            manager.digest = Some(Arc::new(DigestBuffer::new(config.channels.digest_minutes)));
         }
-        if config.channels.dingtalk.enabled {
-            channels.insert(
-                "dingtalk".to_string(),
-                Arc::new(DingTalkChannel::new(
-                    config.channels.dingtalk.clone(),
-                    bus.clone(),
-                )),
-            );
-        }
-        if config.channels.email.enabled {
-            channels.insert(
-                "email".to_string(),
-                Arc::new(EmailChannel::new(
-                    config.channels.email.clone(),
-                    bus.clone(),
-                )),
-            );
-        }
-        if config.channels.slack.enabled {
-            channels.insert(
-                "slack".to_string(),
-                Arc::new(SlackChannel::new(
-                    config.channels.slack.clone(),
-                    bus.clone(),
-                )),
-            );
-        }
-        if config.channels.qq.enabled {
-            channels.insert(
-                "qq".to_string(),
-                Arc::new(QQChannel::new(config.channels.qq.clone(), bus.clone())),
-            );
-        }
-
-        Self::from_channels(bus, channels)
+        manager
     }
 
     pub(crate) fn from_channels(
@@ -116,88 +167,329 @@ impl ChannelManager {
     ) -> Self {
         Self {
             bus,
-            channels,
+            channels: Arc::new(RwLock::new(channels)),
+            delivery: DeliveryPolicy::default(),
+            digest: None,
+            loop_guard: None,
             running: Arc::new(AtomicBool::new(false)),
             dispatch_task: Mutex::new(None),
             channel_tasks: Mutex::new(Vec::new()),
+            adapter_tasks: Mutex::new(HashMap::new()),
+            restarts: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            latency: Arc::new(LatencyStats::default()),
+            reply_limits: Arc::new(HashMap::new()),
         }
     }
 
     pub fn enabled_channels(&self) -> Vec<String> {
-        let mut names: Vec<String> = self.channels.keys().cloned().collect();
+        let mut names: Vec<String> = self
+            .channels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect();
         names.sort();
         names
     }
 
-    pub async fn start_all(&self) {
-        if self.channels.is_empty() {
-            return;
-        }
+    // Runs the adapter under supervision: if its connection loop returns,
+    // fails or panics while the channel is still enabled, it is restarted
+    // with exponential backoff.
+    fn spawn_adapter(&self, name: String, channel: Arc<dyn Channel>) -> JoinHandle<()> {
+        let running = self.running.clone();
+        let channels = self.channels.clone();
+        let restarts = self.restarts.clone();
+        tokio::spawn(async move {
+            let mut failures = 0;
+            loop {
+                events::record(EventLevel::Info, "channel", Some(&name), "started");
+                let started = Instant::now();
+                let adapter = channel.clone();
+                let inner = tokio::spawn(async move { adapter.start().await });
+                let _abort = AbortOnDrop(inner.abort_handle());
+                let outcome = inner.await;
+                // A disabled channel is removed from the map before it stops.
+                let wanted = running.load(Ordering::Relaxed) && lookup(&channels, &name).is_some();
+                let (level, reason) = match outcome {
+                    Ok(Ok(())) if !wanted => {
+                        events::record(EventLevel::Info, "channel", Some(&name), "stopped");
+                        return;
+                    }
+                    Ok(Ok(())) => (EventLevel::Warn, "disconnected".to_string()),
+                    Ok(Err(err)) => (EventLevel::Error, format!("disconnected: {err}")),
+                    Err(err) if err.is_panic() => (EventLevel::Error, "panicked".to_string()),
+                    Err(_) => return,
+                };
+                if !wanted {
+                    events::record(level, "channel", Some(&name), reason);
+                    return;
+                }
+                if started.elapsed() >= STABLE_RUN {
+                    failures = 0;
+                }
+                let delay = restart_delay(failures);
+                failures += 1;
+                *restarts
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .entry(name.clone())
+                    .or_default() += 1;
+                events::record(
+                    level,
+                    "channel",
+                    Some(&name),
+                    format!("{reason}; {RESTART_MARKER} {}s", delay.as_secs()),
+                );
+                tokio::time::sleep(delay).await;
+                if !running.load(Ordering::Relaxed) || lookup(&channels, &name).is_none() {
+                    return;
+                }
+            }
+        })
+    }
 
+    pub async fn start_all(&self) {
         self.running.store(true, Ordering::Relaxed);
 
         let running = self.running.clone();
         let bus = self.bus.clone();
         let channels_for_dispatch = self.channels.clone();
+        let delivery = self.delivery.clone();
+        let digest = self.digest.clone();
+        let loop_guard = self.loop_guard.clone();
+        let latency = self.latency.clone();
+        let reply_limits = self.reply_limits.clone();
         let dispatch = tokio::spawn(async move {
             while running.load(Ordering::Relaxed) {
                 if let Some(msg) = bus.consume_outbound().await {
-                    if let Some(channel) = channels_for_dispatch.get(&msg.channel) {
-                        let _ = channel.send(&msg).await;
+                    let msg = match bus.script_hooks() {
+                        Some(hooks) => match hooks.on_outbound(msg) {
+                            Some(msg) => msg,
+                            None => continue,
+                        },
+                        None => msg,
+                    };
+                    if let Some(guard) = &loop_guard {
+                        guard.record_sent(&msg);
+                    }
+                    let channel = lookup(&channels_for_dispatch, &msg.channel);
+                    if let Some(digest) = &digest
+                        && is_proactive(&msg)
+                        && !is_urgent(&msg)
+                        && channel.is_some()
+                    {
+                        digest.push(msg);
+                    } else if let Some(channel) = channel
+                        && !delivery.hold_if_closed(&msg, Local::now().naive_local())
+                    {
+                        match send_in_parts(channel.as_ref(), &msg, &reply_limits).await {
+                            Ok(()) => {
+                                latency.record_delivery(&msg, Local::now().timestamp_millis())
+                            }
+                            Err(err) => events::record(
+                                EventLevel::Error,
+                                "channel",
+                                Some(&msg.channel),
+                                format!("send to {} failed: {err}", msg.chat_id),
+                            ),
+                        }
                     }
                 } else {
-                    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+                    tokio::time::sleep(Duration::from_millis(100)).await;
                 }
             }
         });
         *self.dispatch_task.lock().await = Some(dispatch);
 
-        let mut tasks = self.channel_tasks.lock().await;
-        for channel in self.channels.values() {
-            let ch = channel.clone();
-            let task = tokio::spawn(async move {
-                let _ = ch.start().await;
-            });
-            tasks.push(task);
+        // Typing indicators follow the agent's turns; they are best effort.
+        let running = self.running.clone();
+        let mut activity = self.bus.subscribe_activity();
+        let channels = self.channels.clone();
+        self.channel_tasks
+            .lock()
+            .await
+            .push(tokio::spawn(async move {
+                while running.load(Ordering::Relaxed) {
+                    let signal = match activity.recv().await {
+                        Ok(signal) => signal,
+                        Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    };
+                    if let Some(channel) = lookup(&channels, &signal.channel) {
+                        let _ = channel.set_typing(&signal).await;
+                    }
+                }
+            }));
+
+        if self.delivery.is_active() || self.digest.is_some() {
+            let running = self.running.clone();
+            let delivery = self.delivery.clone();
+            let digest = self.digest.clone();
+            let channels = self.channels.clone();
+            let reply_limits = self.reply_limits.clone();
+            self.channel_tasks
+                .lock()
+                .await
+                .push(tokio::spawn(async move {
+                    while running.load(Ordering::Relaxed) {
+                        let due = digest
+                            .as_ref()
+                            .map(|digest| digest.take_due(Instant::now()))
+                            .unwrap_or_default();
+                        for msg in due {
+                            if let Some(channel) = lookup(&channels, &msg.channel)
+                                && !delivery.hold_if_closed(&msg, Local::now().naive_local())
+                            {
+                                let _ = send_in_parts(channel.as_ref(), &msg, &reply_limits).await;
+                            }
+                        }
+                        for msg in delivery.take_ready(Local::now().naive_local()) {
+                            if let Some(channel) = lookup(&channels, &msg.channel) {
+                                let _ = send_in_parts(channel.as_ref(), &msg, &reply_limits).await;
+                            }
+                        }
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                    }
+                }));
+        }
+        let adapters = self
+            .channels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let mut tasks = self.adapter_tasks.lock().await;
+        for (name, channel) in adapters {
+            tasks.insert(name.clone(), self.spawn_adapter(name, channel));
         }
         drop(tasks);
 
         while self.running.load(Ordering::Relaxed) {
-            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            tokio::time::sleep(Duration::from_secs(1)).await;
         }
     }
 
+    // Starts one adapter from a freshly loaded config while the gateway runs.
+    // Returns false if the channel was already running.
+    pub async fn enable_channel(&self, config: &Config, name: &str) -> Result<bool> {
+        if lookup(&self.channels, name).is_some() {
+            return Ok(false);
+        }
+        let channel = build_channel(config, name, self.bus.clone())
+            .ok_or_else(|| anyhow!("unknown channel: {name}"))?;
+        self.channels
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(name.to_string(), channel.clone());
+        if self.running.load(Ordering::Relaxed) {
+            let task = self.spawn_adapter(name.to_string(), channel);
+            self.adapter_tasks
+                .lock()
+                .await
+                .insert(name.to_string(), task);
+        }
+        Ok(true)
+    }
+
+    // Stops one adapter; other channels keep running. Returns false if it
+    // was not running.
+    pub async fn disable_channel(&self, name: &str) -> Result<bool> {
+        let removed = self
+            .channels
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(name);
+        let Some(channel) = removed else {
+            return Ok(false);
+        };
+        let stopped = channel.stop().await;
+        let task = self.adapter_tasks.lock().await.remove(name);
+        if let Some(mut task) = task {
+            // Give the adapter a moment to wind down before forcing it.
+            if tokio::time::timeout(Duration::from_secs(5), &mut task)
+                .await
+                .is_err()
+            {
+                task.abort();
+                events::record(EventLevel::Info, "channel", Some(name), "stopped");
+            }
+        }
+        stopped.map(|()| true)
+    }
+
     pub async fn stop_all(&self) {
         self.running.store(false, Ordering::Relaxed);
-        for channel in self.channels.values() {
+        let channels = self
+            .channels
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        // Flush partially filled digests rather than dropping them on shutdown.
+        if let Some(digest) = &self.digest {
+            for msg in digest.take_all() {
+                if let Some(channel) = channels.get(&msg.channel)
+                    && !self
+                        .delivery
+                        .hold_if_closed(&msg, Local::now().naive_local())
+                {
+                    let _ = channel.send(&msg).await;
+                }
+            }
+        }
+        for channel in channels.values() {
             let _ = channel.stop().await;
         }
 
         if let Some(dispatch) = self.dispatch_task.lock().await.take() {
             dispatch.abort();
         }
-        let mut tasks = self.channel_tasks.lock().await;
-        for task in tasks.drain(..) {
+        for task in self.channel_tasks.lock().await.drain(..) {
+            task.abort();
+        }
+        for (_, task) in self.adapter_tasks.lock().await.drain() {
             task.abort();
         }
     }
 
     pub fn get_status(&self) -> serde_json::Value {
         let mut map = serde_json::Map::new();
-        for (name, channel) in &self.channels {
+        let channels = self.channels.read().unwrap_or_else(|e| e.into_inner());
+        let restarts = self.restarts.lock().unwrap_or_else(|e| e.into_inner());
+        for (name, channel) in channels.iter() {
             map.insert(
                 name.clone(),
                 serde_json::json!({
                     "enabled": true,
                     "running": channel.is_running(),
+                    "restarts": restarts.get(name).copied().unwrap_or(0),
                 }),
             );
         }
         serde_json::Value::Object(map)
     }
 
+    // p50/p95/p99 of delivered replies since the gateway started.
+    pub fn latency(&self) -> Vec<LatencyRow> {
+        self.latency.rows()
+    }
+
     pub fn get_channel(&self, name: &str) -> Option<Arc<dyn Channel>> {
-        self.channels.get(name).cloned()
+        lookup(&self.channels, name)
+    }
+
+    // Sends directly through the adapter, unless the message is proactive and
+    // its channel's delivery window is closed, in which case it is held.
+    pub async fn send_or_hold(&self, msg: &OutboundMessage) -> Result<()> {
+        let channel = self
+            .get_channel(&msg.channel)
+            .ok_or_else(|| anyhow!("channel not enabled: {}", msg.channel))?;
+        if self
+            .delivery
+            .hold_if_closed(msg, Local::now().naive_local())
+        {
+            return Ok(());
+        }
+        channel.send(msg).await
     }
 }
 
@@ -215,6 +507,7 @@ mod tests {
     struct MockChannel {
         name: String,
         running: AtomicBool,
+        crash_once: AtomicBool,
         allow_from: Vec<String>,
         bus: Arc<MessageBus>,
         sent: TokioMutex<Vec<OutboundMessage>>,
@@ -225,6 +518,7 @@ mod tests {
             Self {
                 name: name.to_string(),
                 running: AtomicBool::new(false),
+                crash_once: AtomicBool::new(false),
                 allow_from: Vec::new(),
                 bus,
                 sent: TokioMutex::new(Vec::new()),
@@ -251,6 +545,9 @@ mod tests {
         }
 
         async fn start(&self) -> Result<()> {
+            if self.crash_once.swap(false, Ordering::Relaxed) {
+                panic!("adapter crashed");
+            }
             self.running.store(true, Ordering::Relaxed);
             while self.running.load(Ordering::Relaxed) {
                 tokio::time::sleep(std::time::Duration::from_millis(10)).await;
@@ -317,4 +614,82 @@ mod tests {
         let _ = run_handle.await;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn disables_one_channel_while_others_keep_running() -> Result<()> {
+        let bus = Arc::new(MessageBus::new(16));
+        let first = Arc::new(MockChannel::new("first", bus.clone()));
+        let second = Arc::new(MockChannel::new("second", bus.clone()));
+        let mut channels: HashMap<String, Arc<dyn Channel>> = HashMap::new();
+        channels.insert("first".to_string(), first.clone());
+        channels.insert("second".to_string(), second.clone());
+        let manager = Arc::new(ChannelManager::from_channels(bus.clone(), channels));
+
+        let run_manager = manager.clone();
+        let run_handle = tokio::spawn(async move {
+            run_manager.start_all().await;
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        assert!(manager.disable_channel("first").await?);
+        assert!(!manager.disable_channel("first").await?);
+        assert!(!first.is_running());
+        assert!(second.is_running());
+        assert_eq!(manager.enabled_channels(), vec!["second".to_string()]);
+        assert!(
+            manager
+                .enable_channel(&Config::default(), "pager")
+                .await
+                .is_err()
+        );
+
+        manager.stop_all().await;
+        let _ = run_handle.await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn restarts_crashed_adapters_with_backoff() -> Result<()> {
+        assert_eq!(restart_delay(0), Duration::from_secs(1));
+        assert_eq!(restart_delay(3), Duration::from_secs(8));
+        assert_eq!(restart_delay(40), RESTART_BACKOFF_MAX);
+
+        let bus = Arc::new(MessageBus::new(16));
+        let mock = Arc::new(MockChannel::new("mock", bus.clone()));
+        mock.crash_once.store(true, Ordering::Relaxed);
+        let mut channels: HashMap<String, Arc<dyn Channel>> = HashMap::new();
+        channels.insert("mock".to_string(), mock.clone());
+        let manager = Arc::new(ChannelManager::from_channels(bus, channels));
+        let run_manager = manager.clone();
+        let run_handle = tokio::spawn(async move {
+            run_manager.start_all().await;
+        });
+
+        tokio::time::timeout(Duration::from_secs(3), async {
+            while !mock.is_running() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .map_err(|_| anyhow::anyhow!("adapter was not restarted"))?;
+        assert_eq!(manager.get_status()["mock"]["restarts"], 1);
+
+        manager.stop_all().await;
+        let _ = run_handle.await;
+
+        let restart = |channel: &str| Event {
+            at_ms: 0,
+            level: EventLevel::Warn,
+            kind: "channel".to_string(),
+            channel: Some(channel.to_string()),
+            message: format!("disconnected; {RESTART_MARKER} 1s"),
+        };
+        let mut events = vec![restart("slack"); FLAP_RESTARTS];
+        events.push(restart("discord"));
+        assert_eq!(
+            flapping_channels(&events),
+            vec![("slack".to_string(), FLAP_RESTARTS)]
+        );
+        Ok(())
+    }
 }
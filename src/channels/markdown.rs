@@ -0,0 +1,499 @@
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+// Per-channel output dialect. The renderer walks the parsed markdown tree and
+// hands each element's already-rendered children to the emitter, so nested
+// formatting composes without channel-specific parsing.
+pub trait Emitter {
+    fn escape(&self, text: &str) -> String;
+    fn strong(&self, inner: &str) -> String;
+    fn emphasis(&self, inner: &str) -> String;
+    fn strikethrough(&self, inner: &str) -> String;
+    fn inline_code(&self, code: &str) -> String;
+    fn code_block(&self, lang: Option<&str>, code: &str) -> String;
+    fn link(&self, text: &str, url: &str) -> String;
+    fn heading(&self, level: usize, inner: &str) -> String;
+    fn quote(&self, inner: &str) -> String;
+
+    fn image(&self, alt: &str, url: &str) -> String {
+        self.link(alt, url)
+    }
+
+    fn bullet(&self) -> &'static str {
+        "• "
+    }
+
+    fn rule(&self) -> String {
+        "———".to_string()
+    }
+
+    fn table(&self, rows: &[Vec<String>]) -> String {
+        rows.iter()
+            .map(|row| row.join(" | "))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+pub struct TelegramHtml;
+
+impl Emitter for TelegramHtml {
+    fn escape(&self, text: &str) -> String {
+        encode_text(text).to_string()
+    }
+
+    fn strong(&self, inner: &str) -> String {
+        format!("<b>{inner}</b>")
+    }
+
+    fn emphasis(&self, inner: &str) -> String {
+        format!("<i>{inner}</i>")
+    }
+
+    fn strikethrough(&self, inner: &str) -> String {
+        format!("<s>{inner}</s>")
+    }
+
+    fn inline_code(&self, code: &str) -> String {
+        format!("<code>{}</code>", encode_text(code))
+    }
+
+    fn code_block(&self, _lang: Option<&str>, code: &str) -> String {
+        format!("<pre><code>{}</code></pre>", encode_text(code))
+    }
+
+    fn link(&self, text: &str, url: &str) -> String {
+        format!(
+            r#"<a href="{}">{text}</a>"#,
+            encode_double_quoted_attribute(url)
+        )
+    }
+
+    fn heading(&self, _level: usize, inner: &str) -> String {
+        format!("<b>{inner}</b>")
+    }
+
+    fn quote(&self, inner: &str) -> String {
+        format!("<blockquote>{inner}</blockquote>")
+    }
+}
+
+pub struct SlackMrkdwn;
+
+impl Emitter for SlackMrkdwn {
+    fn escape(&self, text: &str) -> String {
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    }
+
+    fn strong(&self, inner: &str) -> String {
+        format!("*{inner}*")
+    }
+
+    fn emphasis(&self, inner: &str) -> String {
+        format!("_{inner}_")
+    }
+
+    fn strikethrough(&self, inner: &str) -> String {
+        format!("~{inner}~")
+    }
+
+    fn inline_code(&self, code: &str) -> String {
+        format!("`{}`", self.escape(code))
+    }
+
+    fn code_block(&self, _lang: Option<&str>, code: &str) -> String {
+        format!("```\n{}```", self.escape(code))
+    }
+
+    fn link(&self, text: &str, url: &str) -> String {
+        if text.is_empty() {
+            format!("<{url}>")
+        } else {
+            format!("<{url}|{text}>")
+        }
+    }
+
+    fn heading(&self, _level: usize, inner: &str) -> String {
+        format!("*{inner}*")
+    }
+
+    fn quote(&self, inner: &str) -> String {
+        prefix_lines(inner, "> ")
+    }
+}
+
+pub struct PlainText;
+
+impl Emitter for PlainText {
+    fn escape(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn strong(&self, inner: &str) -> String {
+        inner.to_string()
+    }
+
+    fn emphasis(&self, inner: &str) -> String {
+        inner.to_string()
+    }
+
+    fn strikethrough(&self, inner: &str) -> String {
+        inner.to_string()
+    }
+
+    fn inline_code(&self, code: &str) -> String {
+        code.to_string()
+    }
+
+    fn code_block(&self, _lang: Option<&str>, code: &str) -> String {
+        code.trim_end().to_string()
+    }
+
+    fn link(&self, text: &str, url: &str) -> String {
+        if text.is_empty() || text == url {
+            url.to_string()
+        } else {
+            format!("{text} ({url})")
+        }
+    }
+
+    fn heading(&self, _level: usize, inner: &str) -> String {
+        inner.to_string()
+    }
+
+    fn quote(&self, inner: &str) -> String {
+        prefix_lines(inner, "> ")
+    }
+
+    fn bullet(&self) -> &'static str {
+        "- "
+    }
+
+    fn rule(&self) -> String {
+        "----------".to_string()
+    }
+}
+
+enum Frame {
+    Root,
+    Block,
+    Heading(usize),
+    Quote,
+    CodeBlock(Option<String>),
+    List(Option<u64>),
+    Item(String),
+    Table(Vec<Vec<String>>),
+    Row(Vec<String>),
+    Cell,
+    Strong,
+    Emphasis,
+    Strike,
+    Link(String),
+    Image(String),
+    Inline,
+}
+
+struct Node {
+    frame: Frame,
+    buf: String,
+}
+
+impl Node {
+    fn new(frame: Frame) -> Self {
+        Self {
+            frame,
+            buf: String::new(),
+        }
+    }
+
+    fn push_block(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        while self.buf.ends_with('\n') {
+            self.buf.pop();
+        }
+        if !self.buf.is_empty() {
+            let separator = match self.frame {
+                Frame::List(_) | Frame::Item(_) => "\n",
+                _ => "\n\n",
+            };
+            self.buf.push_str(separator);
+        }
+        self.buf.push_str(text);
+    }
+}
+
+fn options() -> Options {
+    Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS
+}
+
+fn prefix_lines(text: &str, prefix: &str) -> String {
+    text.lines()
+        .map(|line| format!("{prefix}{line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn render(text: &str, emitter: &dyn Emitter) -> String {
+    let mut stack = vec![Node::new(Frame::Root)];
+    for event in Parser::new_ext(text, options()) {
+        match event {
+            Event::Start(tag) => {
+                let frame = match tag {
+                    Tag::Paragraph | Tag::HtmlBlock => Frame::Block,
+                    Tag::Heading { level, .. } => Frame::Heading(level as usize),
+                    Tag::BlockQuote(_) => Frame::Quote,
+                    Tag::CodeBlock(CodeBlockKind::Fenced(lang)) if !lang.is_empty() => {
+                        Frame::CodeBlock(Some(lang.to_string()))
+                    }
+                    Tag::CodeBlock(_) => Frame::CodeBlock(None),
+                    Tag::List(start) => Frame::List(start),
+                    Tag::Item => Frame::Item(next_marker(&mut stack, emitter)),
+                    Tag::Table(_) => Frame::Table(Vec::new()),
+                    Tag::TableHead | Tag::TableRow => Frame::Row(Vec::new()),
+                    Tag::TableCell => Frame::Cell,
+                    Tag::Strong => Frame::Strong,
+                    Tag::Emphasis => Frame::Emphasis,
+                    Tag::Strikethrough => Frame::Strike,
+                    Tag::Link { dest_url, .. } => Frame::Link(dest_url.to_string()),
+                    Tag::Image { dest_url, .. } => Frame::Image(dest_url.to_string()),
+                    _ => Frame::Inline,
+                };
+                stack.push(Node::new(frame));
+            }
+            Event::End(_) => {
+                if stack.len() < 2 {
+                    continue;
+                }
+                let Some(node) = stack.pop() else {
+                    continue;
+                };
+                if let Some(parent) = stack.last_mut() {
+                    close(node, parent, emitter);
+                }
+            }
+            Event::Text(value) => {
+                if let Some(top) = stack.last_mut() {
+                    if matches!(top.frame, Frame::CodeBlock(_)) {
+                        top.buf.push_str(&value);
+                    } else {
+                        top.buf.push_str(&emitter.escape(&value));
+                    }
+                }
+            }
+            Event::Code(value) => {
+                if let Some(top) = stack.last_mut() {
+                    top.buf.push_str(&emitter.inline_code(&value));
+                }
+            }
+            Event::Html(value) | Event::InlineHtml(value) => {
+                if let Some(top) = stack.last_mut() {
+                    top.buf.push_str(&emitter.escape(&value));
+                }
+            }
+            Event::SoftBreak | Event::HardBreak => {
+                if let Some(top) = stack.last_mut() {
+                    top.buf.push('\n');
+                }
+            }
+            Event::Rule => {
+                if let Some(top) = stack.last_mut() {
+                    top.push_block(&emitter.rule());
+                }
+            }
+            Event::TaskListMarker(done) => {
+                if let Some(top) = stack.last_mut() {
+                    top.buf.push_str(if done { "[x] " } else { "[ ] " });
+                }
+            }
+            Event::FootnoteReference(name) => {
+                if let Some(top) = stack.last_mut() {
+                    top.buf.push_str(&emitter.escape(&format!("[^{name}]")));
+                }
+            }
+            _ => {}
+        }
+    }
+    // Unclosed frames only happen on malformed input; fold them back in.
+    while stack.len() > 1 {
+        if let Some(node) = stack.pop()
+            && let Some(parent) = stack.last_mut()
+        {
+            close(node, parent, emitter);
+        }
+    }
+    stack
+        .pop()
+        .map(|root| root.buf.trim_end().to_string())
+        .unwrap_or_default()
+}
+
+fn next_marker(stack: &mut [Node], emitter: &dyn Emitter) -> String {
+    match stack.last_mut().map(|node| &mut node.frame) {
+        Some(Frame::List(Some(number))) => {
+            let marker = format!("{number}. ");
+            *number += 1;
+            marker
+        }
+        _ => emitter.bullet().to_string(),
+    }
+}
+
+fn close(node: Node, parent: &mut Node, emitter: &dyn Emitter) {
+    let Node { frame, buf } = node;
+    match frame {
+        Frame::Root | Frame::Block | Frame::List(_) => parent.push_block(buf.trim_end()),
+        Frame::Heading(level) => parent.push_block(&emitter.heading(level, buf.trim())),
+        Frame::Quote => parent.push_block(&emitter.quote(buf.trim_end())),
+        Frame::CodeBlock(lang) => parent.push_block(&emitter.code_block(lang.as_deref(), &buf)),
+        Frame::Item(marker) => {
+            let indent = " ".repeat(marker.chars().count());
+            let body = buf
+                .trim_end()
+                .lines()
+                .enumerate()
+                .map(|(idx, line)| {
+                    if idx == 0 || line.is_empty() {
+                        line.to_string()
+                    } else {
+                        format!("{indent}{line}")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            parent.push_block(&format!("{marker}{body}"));
+        }
+        Frame::Table(rows) => parent.push_block(&emitter.table(&rows)),
+        Frame::Row(cells) => {
+            if let Frame::Table(rows) = &mut parent.frame {
+                rows.push(cells);
+            }
+        }
+        Frame::Cell => {
+            if let Frame::Row(cells) = &mut parent.frame {
+                cells.push(buf.trim().to_string());
+            }
+        }
+        Frame::Strong => parent.buf.push_str(&emitter.strong(&buf)),
+        Frame::Emphasis => parent.buf.push_str(&emitter.emphasis(&buf)),
+        Frame::Strike => parent.buf.push_str(&emitter.strikethrough(&buf)),
+        Frame::Link(url) => parent.buf.push_str(&emitter.link(&buf, &url)),
+        Frame::Image(url) => parent.buf.push_str(&emitter.image(&buf, &url)),
+        Frame::Inline => parent.buf.push_str(&buf),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    Heading,
+    Table,
+    Other,
+}
+
+// Splits a document into top-level source ranges, merging consecutive
+// non-heading, non-table blocks, for emitters that build structured payloads.
+pub fn top_level_blocks(text: &str) -> Vec<(BlockKind, Range<usize>)> {
+    let mut blocks: Vec<(BlockKind, Range<usize>)> = Vec::new();
+    for (kind, range) in block_ranges(text) {
+        match blocks.last_mut() {
+            Some((BlockKind::Other, last)) if kind == BlockKind::Other => last.end = range.end,
+            _ => blocks.push((kind, range)),
+        }
+    }
+    blocks
+}
+
+// Source range of every top-level block (paragraph, list, code block, ...).
+pub fn block_ranges(text: &str) -> Vec<(BlockKind, Range<usize>)> {
+    let mut blocks = Vec::new();
+    let mut depth = 0usize;
+    for (event, range) in Parser::new_ext(text, options()).into_offset_iter() {
+        let kind = match &event {
+            Event::Start(tag) => {
+                depth += 1;
+                if depth > 1 {
+                    continue;
+                }
+                match tag {
+                    Tag::Heading { .. } => BlockKind::Heading,
+                    Tag::Table(_) => BlockKind::Table,
+                    _ => BlockKind::Other,
+                }
+            }
+            Event::End(_) => {
+                depth = depth.saturating_sub(1);
+                continue;
+            }
+            _ if depth == 0 => BlockKind::Other,
+            _ => continue,
+        };
+        blocks.push((kind, range));
+    }
+    blocks
+}
+
+pub fn table_rows(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut cell: Option<String> = None;
+    for event in Parser::new_ext(text, options()) {
+        match event {
+            Event::Start(Tag::TableCell) => cell = Some(String::new()),
+            Event::End(TagEnd::TableCell) => {
+                row.push(cell.take().unwrap_or_default().trim().to_string());
+            }
+            Event::End(TagEnd::TableHead | TagEnd::TableRow) => {
+                rows.push(std::mem::take(&mut row));
+            }
+            Event::Text(value) | Event::Code(value) => {
+                if let Some(cell) = cell.as_mut() {
+                    cell.push_str(&value);
+                }
+            }
+            _ => {}
+        }
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_formatting_composes() {
+        let out = render("**bold _and italic_** in snake_case_name", &TelegramHtml);
+        assert_eq!(out, "<b>bold <i>and italic</i></b> in snake_case_name");
+        let out = render("[**docs** & more](https://x.io?a=1&b=2)", &SlackMrkdwn);
+        assert_eq!(out, "<https://x.io?a=1&b=2|*docs* &amp; more>");
+    }
+
+    #[test]
+    fn lists_number_and_indent_nested_items() {
+        let input = "1. first\n   - inner\n2. second\n\n> quoted\n> text";
+        assert_eq!(
+            render(input, &PlainText),
+            "1. first\n   - inner\n2. second\n\n> quoted\n> text"
+        );
+    }
+
+    #[test]
+    fn top_level_blocks_merge_plain_runs() {
+        let input = "# Title\n\npara one\n\npara two\n\n| a | b |\n|---|---|\n| 1 | 2 |\n";
+        let blocks = top_level_blocks(input);
+        let kinds = blocks.iter().map(|(kind, _)| *kind).collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            vec![BlockKind::Heading, BlockKind::Other, BlockKind::Table]
+        );
+        assert_eq!(&input[blocks[1].1.clone()], "para one\n\npara two\n");
+        assert_eq!(
+            table_rows(&input[blocks[2].1.clone()]),
+            vec![vec!["a", "b"], vec!["1", "2"]]
+        );
+    }
+}
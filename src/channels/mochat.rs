@@ -2,21 +2,20 @@ use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
 use crate::channels::base::{Channel, is_allowed_sender};
 use crate::config::MochatConfig;
 use crate::pairing::{issue_pairing, pairing_prompt};
+use crate::storage::locate;
 use crate::utils::get_data_path;
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde_json::{Map, Value, json};
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::Mutex;
 use tokio::task::JoinHandle;
 use tokio::time::{Duration, sleep};
 
-const MAX_SEEN_MESSAGE_IDS: usize = 2000;
-
 #[derive(Default)]
 struct MochatShared {
     cursors: Mutex<HashMap<String, i64>>,
@@ -24,8 +23,6 @@ struct MochatShared {
     panel_set: Mutex<HashSet<String>>,
     cold_sessions: Mutex<HashSet<String>>,
     session_by_converse: Mutex<HashMap<String, String>>,
-    seen_set: Mutex<HashMap<String, HashSet<String>>>,
-    seen_queue: Mutex<HashMap<String, VecDeque<String>>>,
 }
 
 #[derive(Clone)]
@@ -407,15 +404,6 @@ async fn process_event(rt: &Runtime, target_id: &str, event: &Value, target_kind
         return;
     }
     let message_id = str_field(payload, &["messageId"]);
-    if is_seen(
-        &rt.shared,
-        &format!("{target_kind}:{target_id}"),
-        &message_id,
-    )
-    .await
-    {
-        return;
-    }
     let body = normalize_content(payload.get("content"));
     if body.is_empty() {
         return;
@@ -436,9 +424,9 @@ async fn process_event(rt: &Runtime, target_id: &str, event: &Value, target_kind
     }
 
     let mut inbound = InboundMessage::new("mochat", author, target_id.to_string(), body);
-    inbound
-        .metadata
-        .insert("message_id".to_string(), Value::String(message_id));
+    let reply_to = str_field(payload, &["replyTo"]);
+    inbound.message_id = (!message_id.is_empty()).then_some(message_id);
+    inbound.reply_to = (!reply_to.is_empty()).then_some(reply_to);
     inbound
         .metadata
         .insert("group_id".to_string(), Value::String(group_id));
@@ -454,29 +442,8 @@ async fn process_event(rt: &Runtime, target_id: &str, event: &Value, target_kind
     let _ = rt.bus.publish_inbound(inbound).await;
 }
 
-async fn is_seen(shared: &MochatShared, key: &str, message_id: &str) -> bool {
-    if message_id.is_empty() {
-        return false;
-    }
-    let mut seen_set_map = shared.seen_set.lock().await;
-    let mut seen_queue_map = shared.seen_queue.lock().await;
-    let set = seen_set_map.entry(key.to_string()).or_default();
-    if set.contains(message_id) {
-        return true;
-    }
-    set.insert(message_id.to_string());
-    let queue = seen_queue_map.entry(key.to_string()).or_default();
-    queue.push_back(message_id.to_string());
-    while queue.len() > MAX_SEEN_MESSAGE_IDS {
-        if let Some(old) = queue.pop_front() {
-            set.remove(&old);
-        }
-    }
-    false
-}
-
-async fn load_cursors(shared: &MochatShared, path: &PathBuf) {
-    let Ok(raw) = std::fs::read_to_string(path) else {
+async fn load_cursors(shared: &MochatShared, path: &Path) {
+    let Ok(Some(raw)) = locate(path).read() else {
         return;
     };
     let Ok(parsed) = serde_json::from_str::<Value>(&raw) else {
@@ -495,15 +462,10 @@ async fn load_cursors(shared: &MochatShared, path: &PathBuf) {
     }
 }
 
-async fn save_cursors(shared: &MochatShared, path: &PathBuf) {
+async fn save_cursors(shared: &MochatShared, path: &Path) {
     let data = json!({"schemaVersion": 1, "updatedAt": Utc::now().to_rfc3339(), "cursors": *shared.cursors.lock().await});
-    if let Some(parent) = path.parent() {
-        let _ = std::fs::create_dir_all(parent);
-    }
-    let _ = std::fs::write(
-        path,
-        serde_json::to_string_pretty(&data).unwrap_or_else(|_| "{}".to_string()),
-    );
+    let _ = locate(path)
+        .write(&serde_json::to_string_pretty(&data).unwrap_or_else(|_| "{}".to_string()));
 }
 
 async fn seed_targets(shared: &MochatShared, sessions: &[String], panels: &[String]) {
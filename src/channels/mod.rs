@@ -1,11 +1,23 @@
 pub mod base;
+pub mod botloop;
+pub mod chunking;
+pub mod control;
+pub mod dedup;
+pub mod delivery;
+pub mod digest;
 pub mod dingtalk;
+pub mod directory;
 pub mod discord;
 pub mod email;
 pub mod feishu;
+pub mod format;
+pub mod latency;
 pub mod manager;
+pub mod markdown;
 pub mod mochat;
+pub mod oauth;
 pub mod qq;
 pub mod slack;
 pub mod telegram;
+pub mod typing;
 pub mod whatsapp;
@@ -0,0 +1,310 @@
+use crate::config::EmailOAuthConfig;
+use crate::utils::{get_data_path, safe_filename};
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEVICE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+// Refresh this long before the access token actually expires.
+const EXPIRY_MARGIN_MS: i64 = 120_000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OAuthEndpoints {
+    pub device_url: String,
+    pub token_url: String,
+    pub scope: String,
+}
+
+impl OAuthEndpoints {
+    pub fn resolve(config: &EmailOAuthConfig) -> Result<Self> {
+        let base = match config.provider.trim().to_ascii_lowercase().as_str() {
+            "google" | "gmail" => Self {
+                device_url: "https://oauth2.googleapis.com/device/code".to_string(),
+                token_url: "https://oauth2.googleapis.com/token".to_string(),
+                scope: "https://mail.google.com/".to_string(),
+            },
+            "microsoft" | "outlook" | "office365" => {
+                let tenant = if config.tenant.trim().is_empty() {
+                    "common"
+                } else {
+                    config.tenant.trim()
+                };
+                let root = format!("https://login.microsoftonline.com/{tenant}/oauth2/v2.0");
+                Self {
+                    device_url: format!("{root}/devicecode"),
+                    token_url: format!("{root}/token"),
+                    scope: "offline_access https://outlook.office.com/IMAP.AccessAsUser.All \
+                            https://outlook.office.com/SMTP.Send"
+                        .to_string(),
+                }
+            }
+            _ => Self {
+                device_url: String::new(),
+                token_url: String::new(),
+                scope: String::new(),
+            },
+        };
+        let pick = |custom: &str, default: String| {
+            if custom.trim().is_empty() {
+                default
+            } else {
+                custom.trim().to_string()
+            }
+        };
+        let endpoints = Self {
+            device_url: pick(&config.device_url, base.device_url),
+            token_url: pick(&config.token_url, base.token_url),
+            scope: pick(&config.scope, base.scope),
+        };
+        if endpoints.device_url.is_empty() || endpoints.token_url.is_empty() {
+            bail!(
+                "email oauth: unknown provider '{}'; set deviceUrl and tokenUrl",
+                config.provider
+            );
+        }
+        Ok(endpoints)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OAuthToken {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    pub expires_at_ms: i64,
+}
+
+impl OAuthToken {
+    fn from_response(body: &Value, previous_refresh: Option<String>, now_ms: i64) -> Result<Self> {
+        let access_token = body
+            .get("access_token")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("token response has no access_token"))?
+            .to_string();
+        let expires_in = body
+            .get("expires_in")
+            .and_then(|v| v.as_i64().or_else(|| v.as_str()?.parse().ok()))
+            .unwrap_or(3600);
+        Ok(Self {
+            access_token,
+            // Providers may omit the refresh token on refresh; keep the old one.
+            refresh_token: body
+                .get("refresh_token")
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned)
+                .or(previous_refresh),
+            expires_at_ms: now_ms + expires_in * 1000,
+        })
+    }
+
+    pub fn is_fresh(&self, now_ms: i64) -> bool {
+        self.expires_at_ms - EXPIRY_MARGIN_MS > now_ms
+    }
+}
+
+// SASL XOAUTH2 initial response, before base64.
+pub fn xoauth2_string(user: &str, access_token: &str) -> String {
+    format!("user={user}\x01auth=Bearer {access_token}\x01\x01")
+}
+
+pub struct XOAuth2<'a> {
+    pub user: &'a str,
+    pub access_token: &'a str,
+}
+
+impl imap::Authenticator for XOAuth2<'_> {
+    type Response = String;
+
+    fn process(&self, _challenge: &[u8]) -> Self::Response {
+        xoauth2_string(self.user, self.access_token)
+    }
+}
+
+// Tokens for one mail account, persisted under ~/.nanobot/oauth/.
+pub struct OAuthSession {
+    config: EmailOAuthConfig,
+    account: String,
+    path: PathBuf,
+    client: reqwest::Client,
+}
+
+impl OAuthSession {
+    pub fn new(config: EmailOAuthConfig, account: &str) -> Result<Self> {
+        let path = get_data_path()?
+            .join("oauth")
+            .join(format!("email-{}.json", safe_filename(account)));
+        Ok(Self::from_path(config, account, path))
+    }
+
+    pub fn from_path(config: EmailOAuthConfig, account: &str, path: PathBuf) -> Self {
+        Self {
+            config,
+            account: account.to_string(),
+            path,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    pub fn load(&self) -> Option<OAuthToken> {
+        let raw = std::fs::read_to_string(&self.path).ok()?;
+        serde_json::from_str(&raw).ok()
+    }
+
+    fn save(&self, token: &OAuthToken) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(token)?)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(0o600))?;
+        }
+        Ok(())
+    }
+
+    fn client_form<'a>(&'a self, extra: &[(&'a str, &'a str)]) -> Vec<(&'a str, &'a str)> {
+        let mut form = vec![("client_id", self.config.client_id.as_str())];
+        if !self.config.client_secret.trim().is_empty() {
+            form.push(("client_secret", self.config.client_secret.as_str()));
+        }
+        form.extend_from_slice(extra);
+        form
+    }
+
+    async fn post_form(&self, url: &str, form: &[(&str, &str)]) -> Result<Value> {
+        let response = self
+            .client
+            .post(url)
+            .form(form)
+            .send()
+            .await
+            .with_context(|| format!("oauth request to {url} failed"))?;
+        Ok(response.json().await?)
+    }
+
+    // A valid access token, refreshed and saved when it is about to expire.
+    pub async fn access_token(&self) -> Result<String> {
+        let token = self.load().ok_or_else(|| {
+            anyhow!(
+                "no OAuth token for {}; run `nanobot channels login email`",
+                self.account
+            )
+        })?;
+        let now = Utc::now().timestamp_millis();
+        if token.is_fresh(now) {
+            return Ok(token.access_token);
+        }
+        let refresh = token.refresh_token.clone().ok_or_else(|| {
+            anyhow!(
+                "OAuth token for {} expired and cannot be refreshed; run `nanobot channels login email`",
+                self.account
+            )
+        })?;
+        let endpoints = OAuthEndpoints::resolve(&self.config)?;
+        let form = self.client_form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh.as_str()),
+        ]);
+        let body = self.post_form(&endpoints.token_url, &form).await?;
+        if let Some(err) = body.get("error").and_then(Value::as_str) {
+            bail!("OAuth refresh for {} failed: {err}", self.account);
+        }
+        let token = OAuthToken::from_response(&body, Some(refresh), now)?;
+        self.save(&token)?;
+        Ok(token.access_token)
+    }
+
+    // Interactive device flow: prints the code to enter, then polls until the
+    // user approves it in a browser.
+    pub async fn device_login(&self) -> Result<OAuthToken> {
+        if self.config.client_id.trim().is_empty() {
+            bail!("email oauth: clientId is required");
+        }
+        let endpoints = OAuthEndpoints::resolve(&self.config)?;
+        let form = self.client_form(&[("scope", endpoints.scope.as_str())]);
+        let start = self.post_form(&endpoints.device_url, &form).await?;
+        let field = |key: &str| {
+            start
+                .get(key)
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned)
+        };
+        let device_code =
+            field("device_code").ok_or_else(|| anyhow!("device authorization failed: {start}"))?;
+        let verification = field("verification_uri")
+            .or_else(|| field("verification_url"))
+            .unwrap_or_default();
+        let user_code = field("user_code").unwrap_or_default();
+        println!("To authorize {}, open {verification}", self.account);
+        println!("and enter the code: {user_code}");
+
+        let mut interval = start.get("interval").and_then(Value::as_u64).unwrap_or(5);
+        let expires_in = start
+            .get("expires_in")
+            .and_then(Value::as_i64)
+            .unwrap_or(900);
+        let deadline = Utc::now().timestamp_millis() + expires_in * 1000;
+        while Utc::now().timestamp_millis() < deadline {
+            tokio::time::sleep(Duration::from_secs(interval)).await;
+            let form = self.client_form(&[
+                ("grant_type", DEVICE_GRANT),
+                ("device_code", device_code.as_str()),
+            ]);
+            let body = self.post_form(&endpoints.token_url, &form).await?;
+            match body.get("error").and_then(Value::as_str) {
+                None => {
+                    let token =
+                        OAuthToken::from_response(&body, None, Utc::now().timestamp_millis())?;
+                    self.save(&token)?;
+                    return Ok(token);
+                }
+                Some("authorization_pending") => {}
+                Some("slow_down") => interval += 5,
+                Some(err) => bail!("authorization failed: {err}"),
+            }
+        }
+        bail!("the device code expired before it was approved")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_providers_and_keeps_refresh_tokens() {
+        let google = OAuthEndpoints::resolve(&EmailOAuthConfig {
+            provider: "google".to_string(),
+            ..EmailOAuthConfig::default()
+        })
+        .expect("google");
+        assert_eq!(google.scope, "https://mail.google.com/");
+        let microsoft = OAuthEndpoints::resolve(&EmailOAuthConfig {
+            provider: "microsoft".to_string(),
+            tenant: "contoso.onmicrosoft.com".to_string(),
+            scope: "custom".to_string(),
+            ..EmailOAuthConfig::default()
+        })
+        .expect("microsoft");
+        assert!(microsoft.token_url.contains("/contoso.onmicrosoft.com/"));
+        assert_eq!(microsoft.scope, "custom");
+        assert!(OAuthEndpoints::resolve(&EmailOAuthConfig::default()).is_err());
+
+        let body = serde_json::json!({ "access_token": "new", "expires_in": 3600 });
+        let token = OAuthToken::from_response(&body, Some("old-refresh".to_string()), 1_000)
+            .expect("token");
+        assert_eq!(token.refresh_token.as_deref(), Some("old-refresh"));
+        assert!(token.is_fresh(1_000));
+        assert!(!token.is_fresh(3_600_000));
+
+        assert_eq!(
+            xoauth2_string("me@example.com", "tok"),
+            "user=me@example.com\x01auth=Bearer tok\x01\x01"
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use crate::bus::{MessageBus, OutboundMessage};
 use crate::channels::base::Channel;
+use crate::channels::format::{ChannelCapabilities, channel_capabilities};
 use crate::config::QQConfig;
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
@@ -17,21 +18,13 @@ use botrs::models::message::C2CMessageParams;
 #[cfg(feature = "qq-botrs")]
 use botrs::{C2CMessage, Context as QQContext, EventHandler, Intents, Ready, Token};
 #[cfg(feature = "qq-botrs")]
-use serde_json::Value;
-#[cfg(feature = "qq-botrs")]
-use std::collections::VecDeque;
-#[cfg(feature = "qq-botrs")]
 use tokio::sync::Mutex;
 
-#[cfg(feature = "qq-botrs")]
-const QQ_DEDUPE_CAPACITY: usize = 1000;
-
 #[cfg(feature = "qq-botrs")]
 struct QQShared {
     bus: Arc<MessageBus>,
     allow_from: Vec<String>,
     context: Mutex<Option<QQContext>>,
-    processed_ids: Mutex<VecDeque<String>>,
 }
 
 #[cfg(feature = "qq-botrs")]
@@ -39,24 +32,6 @@ struct QQEventHandler {
     shared: Arc<QQShared>,
 }
 
-#[cfg(feature = "qq-botrs")]
-impl QQEventHandler {
-    async fn dedupe_message(&self, message_id: &str) -> bool {
-        if message_id.is_empty() {
-            return false;
-        }
-        let mut ids = self.shared.processed_ids.lock().await;
-        if ids.iter().any(|v| v == message_id) {
-            return true;
-        }
-        ids.push_back(message_id.to_string());
-        if ids.len() > QQ_DEDUPE_CAPACITY {
-            ids.pop_front();
-        }
-        false
-    }
-}
-
 #[cfg(feature = "qq-botrs")]
 #[async_trait]
 impl EventHandler for QQEventHandler {
@@ -93,16 +68,9 @@ impl EventHandler for QQEventHandler {
         }
 
         let message_id = message.id.unwrap_or_default();
-        if self.dedupe_message(&message_id).await {
-            return;
-        }
 
         let mut inbound = InboundMessage::new("qq", sender.clone(), sender, content);
-        if !message_id.is_empty() {
-            inbound
-                .metadata
-                .insert("message_id".to_string(), Value::String(message_id));
-        }
+        inbound.message_id = (!message_id.is_empty()).then_some(message_id);
         let _ = self.shared.bus.publish_inbound(inbound).await;
     }
 }
@@ -123,7 +91,6 @@ impl QQChannel {
                 bus: bus.clone(),
                 allow_from: config.allow_from.clone(),
                 context: Mutex::new(None),
-                processed_ids: Mutex::new(VecDeque::new()),
             }),
             config,
             bus,
@@ -146,6 +113,10 @@ impl Channel for QQChannel {
         &self.config.allow_from
     }
 
+    fn capabilities(&self) -> ChannelCapabilities {
+        channel_capabilities("qq")
+    }
+
     fn bus(&self) -> Arc<MessageBus> {
         self.bus.clone()
     }
@@ -225,7 +196,7 @@ impl Channel for QQChannel {
             };
             let params = C2CMessageParams {
                 msg_type: 0,
-                content: Some(msg.content.clone()),
+                content: Some(self.format(&msg.content).text),
                 ..Default::default()
             };
             ctx.api
@@ -1,11 +1,16 @@
-use crate::bus::{MessageBus, OutboundMessage};
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage, TurnActivity};
 use crate::channels::base::Channel;
+use crate::channels::directory::{NameCache, mention_ids, replace_mentions};
+use crate::channels::format::{ChannelCapabilities, channel_capabilities};
 use crate::config::SlackConfig;
+use crate::feedback::{classify_reaction, link_sent_message, record_reaction};
 use crate::pairing::{issue_pairing, pairing_prompt};
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use futures_util::{SinkExt, StreamExt};
+use regex::Regex;
 use serde_json::{Map, Value, json};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::Mutex;
@@ -18,6 +23,8 @@ pub struct SlackChannel {
     running: AtomicBool,
     client: reqwest::Client,
     bot_user_id: Mutex<Option<String>>,
+    names: NameCache,
+    mention_re: Regex,
 }
 
 impl SlackChannel {
@@ -28,6 +35,8 @@ impl SlackChannel {
             running: AtomicBool::new(false),
             client: reqwest::Client::new(),
             bot_user_id: Mutex::new(None),
+            names: NameCache::default(),
+            mention_re: Regex::new(r"<@([UW][A-Z0-9]+)(?:\|[^>]*)?>").expect("valid mention regex"),
         }
     }
 
@@ -49,6 +58,49 @@ impl SlackChannel {
             .map(ToOwned::to_owned)
     }
 
+    // Display name from users.info (needs the users:read scope), cached.
+    async fn user_name(&self, user_id: &str) -> Option<String> {
+        if let Some(name) = self.names.get(user_id) {
+            return Some(name);
+        }
+        let response = self
+            .client
+            .get("https://slack.com/api/users.info")
+            .query(&[("user", user_id)])
+            .bearer_auth(&self.config.bot_token)
+            .send()
+            .await
+            .ok()?;
+        let payload: Value = response.json().await.ok()?;
+        if !payload.get("ok").and_then(Value::as_bool).unwrap_or(false) {
+            return None;
+        }
+        let user = payload.get("user")?;
+        let name = [
+            "/profile/display_name",
+            "/profile/real_name",
+            "/real_name",
+            "/name",
+        ]
+        .iter()
+        .filter_map(|path| user.pointer(path).and_then(Value::as_str))
+        .map(str::trim)
+        .find(|name| !name.is_empty())?
+        .to_string();
+        self.names.insert(user_id, &name);
+        Some(name)
+    }
+
+    async fn resolve_mentions(&self, text: &str) -> String {
+        let mut names = HashMap::new();
+        for id in mention_ids(text, &self.mention_re) {
+            if let Some(name) = self.user_name(&id).await {
+                names.insert(id, name);
+            }
+        }
+        replace_mentions(text, &self.mention_re, &names)
+    }
+
     async fn open_socket_url(&self) -> Option<String> {
         let response = self
             .client
@@ -129,9 +181,47 @@ impl SlackChannel {
         }
     }
 
+    fn handle_reaction_event(&self, event: &Value, added: bool) {
+        let user = event.get("user").and_then(Value::as_str).unwrap_or("");
+        let item = event.get("item");
+        let chat_id = item
+            .and_then(|v| v.get("channel"))
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        let ts = item
+            .and_then(|v| v.get("ts"))
+            .and_then(Value::as_str)
+            .unwrap_or("");
+        if user.is_empty() || chat_id.is_empty() || ts.is_empty() {
+            return;
+        }
+        let channel_type = if chat_id.starts_with('D') { "im" } else { "" };
+        if !self.is_allowed(user, chat_id, channel_type) {
+            return;
+        }
+        let Some(rating) = event
+            .get("reaction")
+            .and_then(Value::as_str)
+            .and_then(classify_reaction)
+        else {
+            return;
+        };
+        record_reaction(
+            self.name(),
+            chat_id,
+            ts,
+            user,
+            if added { Some(rating) } else { None },
+        );
+    }
+
     async fn handle_event_payload(&self, payload: &Value) -> Result<()> {
         let event = payload.get("event").cloned().unwrap_or_else(|| json!({}));
         let event_type = event.get("type").and_then(Value::as_str).unwrap_or("");
+        if event_type == "reaction_added" || event_type == "reaction_removed" {
+            self.handle_reaction_event(&event, event_type == "reaction_added");
+            return Ok(());
+        }
         if event_type != "message" && event_type != "app_mention" {
             return Ok(());
         }
@@ -199,15 +289,14 @@ impl SlackChannel {
         if text.is_empty() {
             return Ok(());
         }
+        let text = self.resolve_mentions(&text).await;
+        let sender_name = self.user_name(&sender_id).await;
 
-        let thread_ts = event
-            .get("thread_ts")
+        let ts = event
+            .get("ts")
             .and_then(Value::as_str)
-            .or_else(|| event.get("ts").and_then(Value::as_str))
-            .unwrap_or("")
-            .to_string();
-
-        if let Some(ts) = event.get("ts").and_then(Value::as_str) {
+            .map(ToOwned::to_owned);
+        if let Some(ts) = &ts {
             let _ = self
                 .post_slack_api(
                     "reactions.add",
@@ -221,17 +310,32 @@ impl SlackChannel {
         }
 
         let mut slack_meta = Map::new();
-        if !thread_ts.is_empty() {
-            slack_meta.insert("thread_ts".to_string(), Value::String(thread_ts));
-        }
         if !channel_type.is_empty() {
-            slack_meta.insert("channel_type".to_string(), Value::String(channel_type));
+            slack_meta.insert(
+                "channel_type".to_string(),
+                Value::String(channel_type.clone()),
+            );
         }
-        let mut metadata = Map::new();
-        metadata.insert("slack".to_string(), Value::Object(slack_meta));
-
-        self.handle_message(sender_id, chat_id, text, Vec::new(), metadata)
-            .await
+        let mut inbound = InboundMessage::new(self.name(), sender_id, chat_id, text);
+        inbound
+            .metadata
+            .insert("slack".to_string(), Value::Object(slack_meta));
+        if let Some(name) = sender_name {
+            inbound
+                .metadata
+                .insert("sender_name".to_string(), Value::String(name));
+        }
+        // Replies in channels always go to a thread, starting one on the
+        // triggering message if needed; DMs stay flat.
+        if channel_type != "im" {
+            inbound.thread_id = event
+                .get("thread_ts")
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned)
+                .or_else(|| ts.clone());
+        }
+        inbound.message_id = ts;
+        self.handle_inbound(inbound).await
     }
 }
 
@@ -249,6 +353,10 @@ impl Channel for SlackChannel {
         &self.config.dm.allow_from
     }
 
+    fn capabilities(&self) -> ChannelCapabilities {
+        channel_capabilities("slack")
+    }
+
     fn bus(&self) -> Arc<MessageBus> {
         self.bus.clone()
     }
@@ -321,25 +429,43 @@ impl Channel for SlackChannel {
     }
 
     async fn send(&self, msg: &OutboundMessage) -> Result<()> {
-        let slack_meta = msg.metadata.get("slack").and_then(Value::as_object);
-        let thread_ts = slack_meta
-            .and_then(|m| m.get("thread_ts"))
-            .and_then(Value::as_str);
-        let channel_type = slack_meta
-            .and_then(|m| m.get("channel_type"))
-            .and_then(Value::as_str)
-            .unwrap_or("");
-        let use_thread = thread_ts.is_some() && channel_type != "im";
-
+        let formatted = self.format(&msg.content);
         let mut body = json!({
             "channel": msg.chat_id,
-            "text": msg.content,
+            "text": formatted.text,
         });
-        if use_thread {
-            body["thread_ts"] = Value::String(thread_ts.unwrap_or_default().to_string());
+        if let Some(blocks) = formatted.rich {
+            body["blocks"] = blocks;
         }
+        if let Some(thread_ts) = &msg.thread_id {
+            body["thread_ts"] = Value::String(thread_ts.clone());
+        }
+
+        let sent = self.post_slack_api("chat.postMessage", body).await?;
+        if let Some(ts) = sent.get("ts").and_then(Value::as_str) {
+            link_sent_message(msg, ts);
+        }
+        Ok(())
+    }
 
-        let _ = self.post_slack_api("chat.postMessage", body).await?;
+    // Bots have no typing event outside the legacy RTM API; the assistant
+    // thread status ("is typing...") is the closest. It needs the
+    // `assistant:write` scope, and failures are ignored.
+    async fn set_typing(&self, activity: &TurnActivity) -> Result<()> {
+        let Some(thread_ts) = activity.thread_id.as_ref().or(activity.message_id.as_ref()) else {
+            return Ok(());
+        };
+        let status = if activity.active { "is typing..." } else { "" };
+        let _ = self
+            .post_slack_api(
+                "assistant.threads.setStatus",
+                json!({
+                    "channel_id": activity.chat_id,
+                    "thread_ts": thread_ts,
+                    "status": status,
+                }),
+            )
+            .await;
         Ok(())
     }
 }
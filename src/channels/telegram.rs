@@ -1,84 +1,18 @@
-use crate::bus::{MessageBus, OutboundMessage};
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage, TurnActivity};
 use crate::channels::base::Channel;
+use crate::channels::format::{ChannelCapabilities, channel_capabilities};
+use crate::channels::typing::TypingLoops;
 use crate::config::TelegramConfig;
+use crate::feedback::{classify_reaction, link_sent_message, record_reaction};
 use crate::providers::transcription::GroqTranscriptionProvider;
 use anyhow::Result;
 use async_trait::async_trait;
-use html_escape::encode_text;
-use regex::Regex;
 use reqwest::{Client, Proxy};
 use serde_json::{Map, Value, json};
-use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::Mutex;
-use tokio::task::JoinHandle;
-
-fn markdown_to_telegram_html(text: &str) -> String {
-    if text.is_empty() {
-        return String::new();
-    }
-    let mut content = text.to_string();
-
-    let code_block_re =
-        Regex::new(r"(?s)```[\w]*\n?([\s\S]*?)```").expect("valid code block regex");
-    let inline_code_re = Regex::new(r"`([^`]+)`").expect("valid inline code regex");
-    let header_re = Regex::new(r"(?m)^#{1,6}\s+(.+)$").expect("valid header regex");
-    let quote_re = Regex::new(r"(?m)^>\s*(.*)$").expect("valid quote regex");
-    let link_re = Regex::new(r"\[([^\]]+)\]\(([^)]+)\)").expect("valid link regex");
-    let bold_star_re = Regex::new(r"\*\*(.+?)\*\*").expect("valid bold regex");
-    let bold_underscore_re = Regex::new(r"__(.+?)__").expect("valid bold underscore regex");
-    let italic_re =
-        Regex::new(r"(?m)(^|[^A-Za-z0-9])_([^_]+)_([^A-Za-z0-9]|$)").expect("valid italic regex");
-    let strike_re = Regex::new(r"~~(.+?)~~").expect("valid strike regex");
-    let bullet_re = Regex::new(r"(?m)^[-*]\s+").expect("valid bullet regex");
-
-    let mut code_blocks = Vec::new();
-    content = code_block_re
-        .replace_all(&content, |caps: &regex::Captures<'_>| {
-            let idx = code_blocks.len();
-            code_blocks.push(caps[1].to_string());
-            format!("\u{0001}CB{idx}\u{0002}")
-        })
-        .to_string();
-
-    let mut inline_codes = Vec::new();
-    content = inline_code_re
-        .replace_all(&content, |caps: &regex::Captures<'_>| {
-            let idx = inline_codes.len();
-            inline_codes.push(caps[1].to_string());
-            format!("\u{0001}IC{idx}\u{0002}")
-        })
-        .to_string();
-
-    content = header_re.replace_all(&content, "$1").to_string();
-    content = quote_re.replace_all(&content, "$1").to_string();
-    content = encode_text(&content).to_string();
-    content = link_re
-        .replace_all(&content, r#"<a href="$2">$1</a>"#)
-        .to_string();
-    content = bold_star_re.replace_all(&content, "<b>$1</b>").to_string();
-    content = bold_underscore_re
-        .replace_all(&content, "<b>$1</b>")
-        .to_string();
-    content = italic_re.replace_all(&content, "$1<i>$2</i>$3").to_string();
-    content = strike_re.replace_all(&content, "<s>$1</s>").to_string();
-    content = bullet_re.replace_all(&content, "• ").to_string();
-
-    for (idx, value) in inline_codes.iter().enumerate() {
-        let token = format!("\u{0001}IC{idx}\u{0002}");
-        let escaped = encode_text(value);
-        content = content.replace(&token, &format!("<code>{escaped}</code>"));
-    }
-    for (idx, value) in code_blocks.iter().enumerate() {
-        let token = format!("\u{0001}CB{idx}\u{0002}");
-        let escaped = encode_text(value);
-        content = content.replace(&token, &format!("<pre><code>{escaped}</code></pre>"));
-    }
-
-    content
-}
 
 pub struct TelegramChannel {
     config: TelegramConfig,
@@ -87,30 +21,7 @@ pub struct TelegramChannel {
     client: Client,
     offset: Mutex<i64>,
     groq_api_key: String,
-    typing_tasks: Mutex<HashMap<String, JoinHandle<()>>>,
-}
-
-#[cfg(test)]
-mod tests {
-    use super::markdown_to_telegram_html;
-
-    #[test]
-    fn markdown_converter_preserves_code_blocks_and_escapes_html() {
-        let input = "```rust\nlet x = 1 < 2;\n```\ntext";
-        let out = markdown_to_telegram_html(input);
-        assert!(out.contains("<pre><code>let x = 1 &lt; 2;\n</code></pre>"));
-        assert!(out.contains("text"));
-    }
-
-    #[test]
-    fn markdown_converter_formats_links_and_styles() {
-        let input = "[site](https://example.com) **b** _i_ ~~s~~";
-        let out = markdown_to_telegram_html(input);
-        assert!(out.contains(r#"<a href="https://example.com">site</a>"#));
-        assert!(out.contains("<b>b</b>"));
-        assert!(out.contains("<i>i</i>"));
-        assert!(out.contains("<s>s</s>"));
-    }
+    typing: TypingLoops,
 }
 
 impl TelegramChannel {
@@ -147,7 +58,7 @@ impl TelegramChannel {
             client,
             offset: Mutex::new(0),
             groq_api_key,
-            typing_tasks: Mutex::new(HashMap::new()),
+            typing: TypingLoops::default(),
         }
     }
 
@@ -205,7 +116,12 @@ impl TelegramChannel {
         Some(save_path)
     }
 
-    async fn send_text_message(&self, chat_id: &str, text: &str, parse_mode: Option<&str>) {
+    async fn send_text_message(
+        &self,
+        chat_id: &str,
+        text: &str,
+        parse_mode: Option<&str>,
+    ) -> Option<Value> {
         let mut payload = json!({
             "chat_id": chat_id,
             "text": text
@@ -213,42 +129,19 @@ impl TelegramChannel {
         if let Some(parse_mode) = parse_mode {
             payload["parse_mode"] = Value::String(parse_mode.to_string());
         }
-        let _ = self
-            .client
+        self.post_message(&payload).await
+    }
+
+    async fn post_message(&self, payload: &Value) -> Option<Value> {
+        self.client
             .post(self.api_url("sendMessage"))
             .json(&payload)
             .send()
-            .await;
-    }
-
-    async fn start_typing(&self, chat_id: &str) {
-        self.stop_typing(chat_id).await;
-        let api_url = self.api_url("sendChatAction");
-        let chat_id_owned = chat_id.to_string();
-        let client = self.client.clone();
-        let task = tokio::spawn(async move {
-            loop {
-                let _ = client
-                    .post(&api_url)
-                    .json(&json!({
-                        "chat_id": chat_id_owned,
-                        "action": "typing"
-                    }))
-                    .send()
-                    .await;
-                tokio::time::sleep(std::time::Duration::from_secs(4)).await;
-            }
-        });
-        self.typing_tasks
-            .lock()
             .await
-            .insert(chat_id.to_string(), task);
-    }
-
-    async fn stop_typing(&self, chat_id: &str) {
-        if let Some(task) = self.typing_tasks.lock().await.remove(chat_id) {
-            task.abort();
-        }
+            .ok()?
+            .json()
+            .await
+            .ok()
     }
 
     fn get_extension(&self, media_type: &str, mime_type: Option<&str>) -> &'static str {
@@ -271,7 +164,118 @@ impl TelegramChannel {
         }
     }
 
+    fn handle_reaction(&self, reaction: &Value) {
+        // Anonymous reactions (from channels or anonymous admins) carry no user.
+        let Some(user_id) = reaction
+            .get("user")
+            .and_then(|u| u.get("id"))
+            .and_then(Value::as_i64)
+        else {
+            return;
+        };
+        let username = reaction
+            .get("user")
+            .and_then(|u| u.get("username"))
+            .and_then(Value::as_str);
+        let sender_id = if let Some(username) = username {
+            format!("{user_id}|{username}")
+        } else {
+            user_id.to_string()
+        };
+        if !self.is_allowed(&sender_id) {
+            return;
+        }
+        let chat_id = reaction
+            .get("chat")
+            .and_then(|v| v.get("id"))
+            .and_then(Value::as_i64)
+            .unwrap_or_default();
+        let Some(message_id) = reaction.get("message_id").and_then(Value::as_i64) else {
+            return;
+        };
+        let rating = reaction
+            .get("new_reaction")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(|r| r.get("emoji").and_then(Value::as_str))
+            .find_map(classify_reaction);
+        record_reaction(
+            self.name(),
+            &chat_id.to_string(),
+            &message_id.to_string(),
+            &user_id.to_string(),
+            rating,
+        );
+    }
+
+    // A tapped inline button sends its command as if the user had typed it.
+    async fn handle_callback(&self, query: &Value) -> Result<()> {
+        let _ = self
+            .client
+            .post(self.api_url("answerCallbackQuery"))
+            .json(&json!({ "callback_query_id": query.get("id").cloned().unwrap_or(Value::Null) }))
+            .send()
+            .await;
+        let Some(user_id) = query
+            .get("from")
+            .and_then(|u| u.get("id"))
+            .and_then(Value::as_i64)
+        else {
+            return Ok(());
+        };
+        let sender_id = match query
+            .get("from")
+            .and_then(|u| u.get("username"))
+            .and_then(Value::as_str)
+        {
+            Some(username) => format!("{user_id}|{username}"),
+            None => user_id.to_string(),
+        };
+        let Some(message) = query.get("message") else {
+            return Ok(());
+        };
+        let Some(chat_id) = message
+            .get("chat")
+            .and_then(|v| v.get("id"))
+            .and_then(Value::as_i64)
+        else {
+            return Ok(());
+        };
+        let Some(command) = query
+            .get("data")
+            .and_then(Value::as_str)
+            .filter(|data| data.starts_with('/'))
+        else {
+            return Ok(());
+        };
+        // Drop the buttons so the same choice can't be made twice.
+        if let Some(message_id) = message.get("message_id").and_then(Value::as_i64) {
+            let _ = self
+                .client
+                .post(self.api_url("editMessageReplyMarkup"))
+                .json(&json!({ "chat_id": chat_id, "message_id": message_id }))
+                .send()
+                .await;
+        }
+        self.handle_message(
+            sender_id,
+            chat_id.to_string(),
+            command.to_string(),
+            Vec::new(),
+            Map::new(),
+        )
+        .await
+    }
+
     async fn handle_update(&self, update: &Value) -> Result<()> {
+        if let Some(reaction) = update.get("message_reaction") {
+            self.handle_reaction(reaction);
+            return Ok(());
+        }
+        if let Some(query) = update.get("callback_query") {
+            return self.handle_callback(query).await;
+        }
         let Some(message) = update.get("message") else {
             return Ok(());
         };
@@ -331,11 +335,16 @@ impl TelegramChannel {
                         )
                         .await;
                     }
-                    "help" | "new" | "reset" => {
-                        let forwarded = if command == "reset" {
-                            "/new".to_string()
-                        } else {
-                            format!("/{command}")
+                    "help" | "new" | "reset" | "snooze" | "pause" | "resume" | "retry" => {
+                        let forwarded = match command {
+                            "reset" => "/new".to_string(),
+                            "snooze" | "pause" | "retry" => {
+                                match text.split_once(char::is_whitespace) {
+                                    Some((_, args)) => format!("/{command} {}", args.trim()),
+                                    None => format!("/{command}"),
+                                }
+                            }
+                            _ => format!("/{command}"),
                         };
                         self.handle_message(sender_id, chat_id, forwarded, Vec::new(), Map::new())
                             .await?;
@@ -418,10 +427,6 @@ impl TelegramChannel {
         }
 
         let mut metadata = Map::new();
-        metadata.insert(
-            "message_id".to_string(),
-            message.get("message_id").cloned().unwrap_or(Value::Null),
-        );
         metadata.insert("user_id".to_string(), Value::Number(user_id.into()));
         metadata.insert(
             "username".to_string(),
@@ -444,10 +449,13 @@ impl TelegramChannel {
                     .unwrap_or(false),
             ),
         );
+        metadata.insert(
+            "is_bot".to_string(),
+            user.get("is_bot").cloned().unwrap_or(Value::Bool(false)),
+        );
 
-        self.start_typing(&chat_id).await;
-
-        self.handle_message(
+        let mut inbound = InboundMessage::new(
+            self.name(),
             sender_id,
             chat_id,
             if content_parts.is_empty() {
@@ -455,10 +463,20 @@ impl TelegramChannel {
             } else {
                 content_parts.join("\n")
             },
-            media_paths,
-            metadata,
-        )
-        .await
+        );
+        inbound.media = media_paths;
+        inbound.metadata = metadata;
+        inbound.message_id = id_field(message, "message_id");
+        // Forum topics: every message in a topic carries the topic id, and
+        // Telegram reports the topic's opening message as the reply target.
+        if message.get("is_topic_message").and_then(Value::as_bool) == Some(true) {
+            inbound.thread_id = id_field(message, "message_thread_id");
+        }
+        inbound.reply_to = message
+            .get("reply_to_message")
+            .and_then(|reply| id_field(reply, "message_id"))
+            .filter(|id| inbound.thread_id.as_ref() != Some(id));
+        self.handle_inbound(inbound).await
     }
 }
 
@@ -476,6 +494,10 @@ impl Channel for TelegramChannel {
         &self.config.allow_from
     }
 
+    fn capabilities(&self) -> ChannelCapabilities {
+        channel_capabilities("telegram")
+    }
+
     fn bus(&self) -> Arc<MessageBus> {
         self.bus.clone()
     }
@@ -494,7 +516,7 @@ impl Channel for TelegramChannel {
                 .json(&json!({
                     "offset": if offset > 0 { Value::Number(offset.into()) } else { Value::Null },
                     "timeout": 20,
-                    "allowed_updates": ["message"]
+                    "allowed_updates": ["message", "message_reaction", "callback_query"]
                 }))
                 .send()
                 .await;
@@ -538,33 +560,111 @@ impl Channel for TelegramChannel {
 
     async fn stop(&self) -> Result<()> {
         self.running.store(false, Ordering::Relaxed);
-        let mut typing_tasks = self.typing_tasks.lock().await;
-        for (_, task) in typing_tasks.drain() {
-            task.abort();
+        self.typing.stop_all().await;
+        Ok(())
+    }
+
+    async fn set_typing(&self, activity: &TurnActivity) -> Result<()> {
+        if !activity.active {
+            self.typing.stop(&activity.chat_id).await;
+            return Ok(());
         }
+        let url = self.api_url("sendChatAction");
+        let client = self.client.clone();
+        let chat_id = activity.chat_id.clone();
+        self.typing
+            .start(
+                &activity.chat_id,
+                std::time::Duration::from_secs(4),
+                move || {
+                    let request = client
+                        .post(&url)
+                        .json(&json!({ "chat_id": chat_id, "action": "typing" }))
+                        .send();
+                    async move {
+                        let _ = request.await;
+                    }
+                },
+            )
+            .await;
         Ok(())
     }
 
     async fn send(&self, msg: &OutboundMessage) -> Result<()> {
-        self.stop_typing(&msg.chat_id).await;
-        let html = markdown_to_telegram_html(&msg.content);
+        let html = self.format(&msg.content).text;
+        let mut payload = json!({
+            "chat_id": msg.chat_id,
+            "text": html,
+            "parse_mode": "HTML"
+        });
+        apply_threading(&mut payload, msg);
+        apply_buttons(&mut payload, msg);
         let first_try = self
             .client
             .post(self.api_url("sendMessage"))
-            .json(&json!({
-                "chat_id": msg.chat_id,
-                "text": html,
-                "parse_mode": "HTML"
-            }))
+            .json(&payload)
             .send()
             .await?;
 
-        if first_try.status().is_success() {
-            return Ok(());
+        let sent = if first_try.status().is_success() {
+            first_try.json::<Value>().await.ok()
+        } else {
+            let mut payload = json!({
+                "chat_id": msg.chat_id,
+                "text": msg.content
+            });
+            apply_threading(&mut payload, msg);
+            apply_buttons(&mut payload, msg);
+            self.post_message(&payload).await
+        };
+        if let Some(message_id) = sent
+            .as_ref()
+            .and_then(|body| body.pointer("/result/message_id"))
+            .and_then(Value::as_i64)
+        {
+            link_sent_message(msg, &message_id.to_string());
         }
-
-        self.send_text_message(&msg.chat_id, &msg.content, None)
-            .await;
         Ok(())
     }
 }
+
+fn id_field(value: &Value, key: &str) -> Option<String> {
+    let id = value.get(key)?;
+    id.as_i64()
+        .map(|id| id.to_string())
+        .or_else(|| id.as_str().map(ToOwned::to_owned))
+}
+
+// Outbound "buttons" metadata ([{text, command}]) becomes one row of inline
+// buttons whose callback data is the command.
+fn apply_buttons(payload: &mut Value, msg: &OutboundMessage) {
+    let Some(buttons) = msg.metadata.get("buttons").and_then(Value::as_array) else {
+        return;
+    };
+    let row = buttons
+        .iter()
+        .filter_map(|button| {
+            let text = button.get("text").and_then(Value::as_str)?;
+            let command = button.get("command").and_then(Value::as_str)?;
+            // Telegram caps callback data at 64 bytes.
+            (command.len() <= 64).then(|| json!({ "text": text, "callback_data": command }))
+        })
+        .collect::<Vec<_>>();
+    if !row.is_empty() {
+        payload["reply_markup"] = json!({ "inline_keyboard": [row] });
+    }
+}
+
+// The bus carries platform ids as strings; the Bot API wants integers.
+fn apply_threading(payload: &mut Value, msg: &OutboundMessage) {
+    let parse = |id: &Option<String>| id.as_deref().and_then(|id| id.parse::<i64>().ok());
+    if let Some(thread_id) = parse(&msg.thread_id) {
+        payload["message_thread_id"] = json!(thread_id);
+    }
+    if let Some(reply_to) = parse(&msg.reply_to) {
+        payload["reply_parameters"] = json!({
+            "message_id": reply_to,
+            "allow_sending_without_reply": true
+        });
+    }
+}
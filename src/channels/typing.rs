@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+// Repeats a platform's "typing" call per chat until stopped, since platforms
+// expire the indicator after a few seconds.
+#[derive(Default)]
+pub struct TypingLoops {
+    tasks: Mutex<HashMap<String, JoinHandle<()>>>,
+}
+
+impl TypingLoops {
+    pub async fn start<F, Fut>(&self, key: &str, every: Duration, ping: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let task = tokio::spawn(async move {
+            loop {
+                ping().await;
+                tokio::time::sleep(every).await;
+            }
+        });
+        if let Some(previous) = self.tasks.lock().await.insert(key.to_string(), task) {
+            previous.abort();
+        }
+    }
+
+    pub async fn stop(&self, key: &str) {
+        if let Some(task) = self.tasks.lock().await.remove(key) {
+            task.abort();
+        }
+    }
+
+    pub async fn stop_all(&self) {
+        for (_, task) in self.tasks.lock().await.drain() {
+            task.abort();
+        }
+    }
+
+    pub async fn is_active(&self, key: &str) -> bool {
+        self.tasks.lock().await.contains_key(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn pings_until_stopped() {
+        let loops = TypingLoops::default();
+        let pings = Arc::new(AtomicUsize::new(0));
+        let counter = pings.clone();
+        loops
+            .start("chat", Duration::from_millis(5), move || {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+            .await;
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        assert!(loops.is_active("chat").await);
+        loops.stop("chat").await;
+        assert!(!loops.is_active("chat").await);
+        let seen = pings.load(Ordering::SeqCst);
+        assert!(seen >= 2);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!(pings.load(Ordering::SeqCst), seen);
+    }
+}
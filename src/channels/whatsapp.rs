@@ -1,5 +1,6 @@
-use crate::bus::{MessageBus, OutboundMessage};
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
 use crate::channels::base::Channel;
+use crate::channels::format::{ChannelCapabilities, channel_capabilities};
 use crate::config::WhatsAppConfig;
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
@@ -44,6 +45,10 @@ impl Channel for WhatsAppChannel {
         &self.config.allow_from
     }
 
+    fn capabilities(&self) -> ChannelCapabilities {
+        channel_capabilities("whatsapp")
+    }
+
     fn bus(&self) -> Arc<MessageBus> {
         self.bus.clone()
     }
@@ -122,10 +127,6 @@ impl Channel for WhatsAppChannel {
                                     .to_string();
                         }
                         let mut metadata = Map::new();
-                        metadata.insert(
-                            "message_id".to_string(),
-                            data.get("id").cloned().unwrap_or(Value::Null),
-                        );
                         metadata.insert(
                             "timestamp".to_string(),
                             data.get("timestamp").cloned().unwrap_or(Value::Null),
@@ -135,8 +136,14 @@ impl Channel for WhatsAppChannel {
                             "is_group".to_string(),
                             data.get("isGroup").cloned().unwrap_or(Value::Bool(false)),
                         );
-                        self.handle_message(sender_id, sender, content, Vec::new(), metadata)
-                            .await?;
+                        let mut inbound =
+                            InboundMessage::new(self.name(), sender_id, sender, content);
+                        inbound.metadata = metadata;
+                        inbound.message_id = data
+                            .get("id")
+                            .and_then(Value::as_str)
+                            .map(ToOwned::to_owned);
+                        self.handle_inbound(inbound).await?;
                     }
                     "status" => {
                         let status = data
@@ -188,7 +195,7 @@ impl Channel for WhatsAppChannel {
         let payload = json!({
             "type": "send",
             "to": msg.chat_id,
-            "text": msg.content
+            "text": self.format(&msg.content).text
         })
         .to_string();
         let tx = self
@@ -0,0 +1,215 @@
+use crate::config::ClusterConfig;
+use crate::cron::time::format_local;
+use crate::events::{self, EventLevel};
+use crate::storage::locate;
+use crate::utils::get_data_path;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaseRecord {
+    pub holder: String,
+    pub acquired_at_ms: i64,
+    pub expires_at_ms: i64,
+}
+
+impl LeaseRecord {
+    pub fn describe(&self, now_ms: i64) -> String {
+        format!(
+            "leader {} since {}",
+            self.holder,
+            format_local(self.acquired_at_ms, now_ms)
+        )
+    }
+}
+
+// Leader election between gateways that share a storage backend. The lease
+// is a record in storage that its holder renews every third of the lease;
+// once it lapses another instance claims it. Storage has no compare-and-swap,
+// so a claim only counts after it is read back unchanged one renewal later:
+// of two instances racing for a free lease, the last writer wins and the
+// other backs off.
+#[derive(Debug, Clone)]
+pub struct LeaderLease {
+    path: PathBuf,
+    instance_id: String,
+    lease_ms: i64,
+    // This instance acts as leader until then. Ends a renewal before the
+    // record expires, so a leader that cannot reach storage steps down before
+    // anyone else may take over.
+    held_until_ms: Arc<AtomicI64>,
+}
+
+impl LeaderLease {
+    pub fn new(config: &ClusterConfig) -> Result<Self> {
+        let instance_id = match config.instance_id.trim() {
+            "" => default_instance_id(),
+            id => id.to_string(),
+        };
+        Ok(Self::from_path(
+            get_data_path()?.join("cluster").join("leader.json"),
+            instance_id,
+            Duration::from_secs(config.lease_seconds.max(3)),
+        ))
+    }
+
+    pub fn from_path(path: PathBuf, instance_id: String, lease: Duration) -> Self {
+        Self {
+            path,
+            instance_id,
+            lease_ms: lease.as_millis() as i64,
+            held_until_ms: Arc::new(AtomicI64::new(0)),
+        }
+    }
+
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+
+    fn renew_every_ms(&self) -> i64 {
+        self.lease_ms / 3
+    }
+
+    pub fn is_leader_at(&self, now_ms: i64) -> bool {
+        now_ms < self.held_until_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn is_leader(&self) -> bool {
+        self.is_leader_at(chrono::Utc::now().timestamp_millis())
+    }
+
+    // The unexpired lease at `now_ms`, whoever holds it.
+    pub fn holder_at(&self, now_ms: i64) -> Option<LeaseRecord> {
+        let raw = locate(&self.path).read().ok()??;
+        serde_json::from_str::<LeaseRecord>(&raw)
+            .ok()
+            .filter(|record| record.expires_at_ms > now_ms)
+    }
+
+    // One election round: renew the lease if it is ours, claim it if it is
+    // free, back off if someone else holds it. Returns whether this instance
+    // is the leader afterwards.
+    pub fn step(&self, now_ms: i64) -> Result<bool> {
+        let current = self.holder_at(now_ms);
+        let leader = match &current {
+            Some(record) if record.holder != self.instance_id => false,
+            _ => {
+                let record = LeaseRecord {
+                    holder: self.instance_id.clone(),
+                    acquired_at_ms: current.as_ref().map_or(now_ms, |r| r.acquired_at_ms),
+                    expires_at_ms: now_ms + self.lease_ms,
+                };
+                locate(&self.path).write(&serde_json::to_string_pretty(&record)?)?;
+                current.is_some()
+            }
+        };
+        let held_until_ms = if leader {
+            now_ms + self.lease_ms - self.renew_every_ms()
+        } else {
+            0
+        };
+        self.held_until_ms.store(held_until_ms, Ordering::Relaxed);
+        Ok(leader)
+    }
+
+    // Gives the lease up on shutdown so another instance takes over within a
+    // couple of renewals instead of waiting for it to expire.
+    pub fn release(&self, now_ms: i64) -> Result<()> {
+        self.held_until_ms.store(0, Ordering::Relaxed);
+        if self
+            .holder_at(now_ms)
+            .is_some_and(|record| record.holder == self.instance_id)
+        {
+            locate(&self.path).remove()?;
+        }
+        Ok(())
+    }
+
+    pub fn spawn(&self) -> JoinHandle<()> {
+        let lease = self.clone();
+        tokio::spawn(async move {
+            let mut was_leader = false;
+            loop {
+                let now_ms = chrono::Utc::now().timestamp_millis();
+                if let Err(err) = lease.step(now_ms) {
+                    events::record(
+                        EventLevel::Warn,
+                        "cluster",
+                        None,
+                        format!("lease renewal failed: {err:#}"),
+                    );
+                }
+                let leader = lease.is_leader();
+                if leader != was_leader {
+                    events::record(
+                        EventLevel::Info,
+                        "cluster",
+                        None,
+                        if leader {
+                            format!("{} is now the leader", lease.instance_id)
+                        } else {
+                            format!("{} is no longer the leader", lease.instance_id)
+                        },
+                    );
+                    was_leader = leader;
+                }
+                tokio::time::sleep(Duration::from_millis(lease.renew_every_ms() as u64)).await;
+            }
+        })
+    }
+}
+
+fn default_instance_id() -> String {
+    let host = std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "nanobot".to_string());
+    let suffix = uuid::Uuid::new_v4().simple().to_string();
+    format!("{host}-{}", &suffix[..6])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_instance_leads_and_another_takes_over_when_it_stops() {
+        let dir = std::env::temp_dir().join(format!("nanobot-cluster-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("leader.json");
+        let lease = Duration::from_secs(30);
+        let a = LeaderLease::from_path(path.clone(), "a".to_string(), lease);
+        let b = LeaderLease::from_path(path.clone(), "b".to_string(), lease);
+
+        // A claim turns into leadership on the next renewal.
+        assert!(!a.step(0).expect("claim"));
+        assert!(!b.step(0).expect("held by a"));
+        assert!(a.step(10_000).expect("renew"));
+        assert!(!b.step(10_000).expect("held by a"));
+        assert!(a.is_leader_at(25_000));
+        assert!(!b.is_leader_at(25_000));
+
+        // a hangs: it stops acting as leader before its record expires.
+        assert!(!a.is_leader_at(30_000));
+        assert_eq!(b.holder_at(39_999).expect("held").holder, "a");
+        assert!(!b.step(40_000).expect("claim"));
+        assert!(b.step(50_000).expect("renew"));
+        assert!(!a.step(50_000).expect("back off"));
+        let record = b.holder_at(50_000).expect("held");
+        assert_eq!(record.acquired_at_ms, 40_000);
+        assert!(record.describe(50_000).starts_with("leader b since "));
+
+        // A clean shutdown frees the lease right away.
+        a.release(50_000).expect("release");
+        assert!(b.holder_at(50_000).is_some());
+        b.release(50_000).expect("release");
+        assert!(b.holder_at(50_000).is_none());
+        assert!(!a.step(51_000).expect("claim"));
+        assert!(a.step(61_000).expect("renew"));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
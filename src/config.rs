@@ -1,3 +1,4 @@
+use crate::providers::base::SamplingParams;
 use crate::utils::{expand_tilde, get_data_path};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
@@ -9,10 +10,49 @@ use std::path::{Path, PathBuf};
 #[serde(default, rename_all = "camelCase")]
 pub struct ProviderConfig {
     pub api_key: String,
+    // Extra keys pooled with `apiKey`; calls are spread across them and a key
+    // that runs out of quota is skipped until its cooldown ends.
+    pub api_keys: Vec<String>,
+    pub key_pool: KeyPoolConfig,
     pub api_base: Option<String>,
     pub extra_headers: Option<HashMap<String, String>>,
 }
 
+impl ProviderConfig {
+    // `apiKey` first, then `apiKeys`, without blanks or duplicates.
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = Vec::new();
+        for key in std::iter::once(&self.api_key).chain(&self.api_keys) {
+            let key = key.trim();
+            if !key.is_empty() && !keys.iter().any(|k| k == key) {
+                keys.push(key.to_string());
+            }
+        }
+        keys
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct KeyPoolConfig {
+    // "roundRobin", "leastUsed" or "scheduled".
+    pub strategy: String,
+    // With "scheduled", each key serves alone for this many minutes in turn.
+    pub rotate_minutes: u64,
+    // How long a key that hit its quota or rate limit is left out.
+    pub cooldown_minutes: u64,
+}
+
+impl Default for KeyPoolConfig {
+    fn default() -> Self {
+        Self {
+            strategy: "roundRobin".to_string(),
+            rotate_minutes: 24 * 60,
+            cooldown_minutes: 60,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename_all = "camelCase")]
 pub struct ProvidersConfig {
@@ -56,8 +96,60 @@ pub struct AgentDefaults {
     pub model: String,
     pub max_tokens: u32,
     pub temperature: f32,
+    pub top_p: Option<f32>,
+    // Sampling for background calls: memory consolidation, session summaries
+    // and action-item extraction.
+    pub maintenance_max_tokens: u32,
+    pub maintenance_temperature: f32,
     pub max_tool_iterations: u32,
     pub memory_window: usize,
+    pub extract_action_items: bool,
+    // Keep the workspace in git and commit whatever the agent writes there.
+    pub git_versioning: bool,
+    // List the pages, files and search results a reply drew on.
+    pub citations: bool,
+    // Turn "REMIND: ..." / "TASK: ..." messages and email subjects into cron
+    // jobs and tasks without an LLM turn.
+    pub structured_commands: bool,
+    // In group chats, only owners' messages feed MEMORY.md unless an owner
+    // replies "remember this".
+    pub confirm_group_memory: bool,
+    // Order/budget overrides for context provider sections, keyed by provider name.
+    pub context_sections: HashMap<String, ContextSectionConfig>,
+    // Tone variants by time of day; the first rule whose window is open wins.
+    pub personas: Vec<PersonaRule>,
+}
+
+// A persona variant, e.g. terse during weekday working hours. `when` uses the
+// delivery-window format; without it the rule always applies, so put it last
+// as the fallback. Times may be left empty to cover whole days.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PersonaRule {
+    pub name: String,
+    pub when: Option<DeliveryWindow>,
+    // Instructions added to the system prompt while the rule applies...
+    pub prompt: String,
+    // ...or read from this workspace file instead.
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ContextSectionConfig {
+    pub enabled: bool,
+    pub order: Option<i32>,
+    pub max_tokens: Option<usize>,
+}
+
+impl Default for ContextSectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            order: None,
+            max_tokens: None,
+        }
+    }
 }
 
 impl Default for AgentDefaults {
@@ -67,8 +159,36 @@ impl Default for AgentDefaults {
             model: "anthropic/claude-opus-4-5".to_string(),
             max_tokens: 8192,
             temperature: 0.7,
+            top_p: None,
+            maintenance_max_tokens: 1200,
+            maintenance_temperature: 0.0,
             max_tool_iterations: 20,
             memory_window: 50,
+            extract_action_items: false,
+            git_versioning: false,
+            citations: false,
+            structured_commands: true,
+            confirm_group_memory: true,
+            context_sections: HashMap::new(),
+            personas: Vec::new(),
+        }
+    }
+}
+
+impl AgentDefaults {
+    pub fn sampling(&self) -> SamplingParams {
+        SamplingParams {
+            max_tokens: self.max_tokens,
+            temperature: self.temperature,
+            top_p: self.top_p,
+        }
+    }
+
+    pub fn maintenance_sampling(&self) -> SamplingParams {
+        SamplingParams {
+            max_tokens: self.maintenance_max_tokens,
+            temperature: self.maintenance_temperature,
+            top_p: None,
         }
     }
 }
@@ -77,6 +197,21 @@ impl Default for AgentDefaults {
 #[serde(default, rename_all = "camelCase")]
 pub struct AgentsConfig {
     pub defaults: AgentDefaults,
+    // Named subagent setups the `spawn` tool can pick with `profile`.
+    pub subagents: HashMap<String, SubagentProfile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SubagentProfile {
+    pub description: String,
+    // Falls back to the agent's model.
+    pub model: Option<String>,
+    // Appended to the base subagent instructions.
+    pub system_prompt: Option<String>,
+    // Allowed tool names; empty allows every subagent tool.
+    pub tools: Vec<String>,
+    pub max_iterations: Option<u32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -161,6 +296,8 @@ pub struct ToolsConfig {
     pub web: WebToolsConfig,
     pub exec: ExecToolConfig,
     pub restrict_to_workspace: bool,
+    // Tool names not offered to the model; reloaded by a running gateway.
+    pub disabled: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -168,6 +305,9 @@ pub struct ToolsConfig {
 pub struct GatewayConfig {
     pub host: String,
     pub port: u16,
+    // Open the provider connection at startup so the first turn skips the handshake.
+    pub prewarm: bool,
+    pub cluster: ClusterConfig,
 }
 
 impl Default for GatewayConfig {
@@ -175,6 +315,30 @@ impl Default for GatewayConfig {
         Self {
             host: "0.0.0.0".to_string(),
             port: 18790,
+            prewarm: false,
+            cluster: ClusterConfig::default(),
+        }
+    }
+}
+
+// Several gateways sharing one storage backend: all of them serve channels,
+// only the holder of the leader lease runs cron jobs and the heartbeat.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ClusterConfig {
+    pub enabled: bool,
+    // Name of this instance in the lease; defaults to "<host>-<random>".
+    pub instance_id: String,
+    // A leader that stops renewing for this long is replaced.
+    pub lease_seconds: u64,
+}
+
+impl Default for ClusterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            instance_id: String::new(),
+            lease_seconds: 30,
         }
     }
 }
@@ -193,6 +357,16 @@ impl Default for ServiceConfig {
     }
 }
 
+// Local-time hours during which proactive (cron, heartbeat) messages may be
+// delivered. `start` > `end` wraps past midnight; empty `days` means every day.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DeliveryWindow {
+    pub start: String,
+    pub end: String,
+    pub days: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default, rename_all = "camelCase")]
 pub struct TelegramConfig {
@@ -200,6 +374,10 @@ pub struct TelegramConfig {
     pub token: String,
     pub allow_from: Vec<String>,
     pub proxy: Option<String>,
+    pub read_only: bool,
+    pub delivery_window: Option<DeliveryWindow>,
+    pub max_reply_chars: Option<usize>,
+    pub citations: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -209,6 +387,10 @@ pub struct WhatsAppConfig {
     pub bridge_url: String,
     pub bridge_token: String,
     pub allow_from: Vec<String>,
+    pub read_only: bool,
+    pub delivery_window: Option<DeliveryWindow>,
+    pub max_reply_chars: Option<usize>,
+    pub citations: Option<bool>,
 }
 
 impl Default for WhatsAppConfig {
@@ -218,6 +400,10 @@ impl Default for WhatsAppConfig {
             bridge_url: "ws://localhost:3001".to_string(),
             bridge_token: String::new(),
             allow_from: Vec::new(),
+            read_only: false,
+            delivery_window: None,
+            max_reply_chars: None,
+            citations: None,
         }
     }
 }
@@ -230,6 +416,10 @@ pub struct DiscordConfig {
     pub allow_from: Vec<String>,
     pub gateway_url: String,
     pub intents: u32,
+    pub read_only: bool,
+    pub delivery_window: Option<DeliveryWindow>,
+    pub max_reply_chars: Option<usize>,
+    pub citations: Option<bool>,
 }
 
 impl Default for DiscordConfig {
@@ -239,7 +429,11 @@ impl Default for DiscordConfig {
             token: String::new(),
             allow_from: Vec::new(),
             gateway_url: "wss://gateway.discord.gg/?v=10&encoding=json".to_string(),
-            intents: 37377,
+            intents: 46593,
+            read_only: false,
+            delivery_window: None,
+            max_reply_chars: None,
+            citations: None,
         }
     }
 }
@@ -253,6 +447,10 @@ pub struct FeishuConfig {
     pub encrypt_key: String,
     pub verification_token: String,
     pub allow_from: Vec<String>,
+    pub read_only: bool,
+    pub delivery_window: Option<DeliveryWindow>,
+    pub max_reply_chars: Option<usize>,
+    pub citations: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -262,6 +460,10 @@ pub struct DingTalkConfig {
     pub client_id: String,
     pub client_secret: String,
     pub allow_from: Vec<String>,
+    pub read_only: bool,
+    pub delivery_window: Option<DeliveryWindow>,
+    pub max_reply_chars: Option<usize>,
+    pub citations: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -317,6 +519,10 @@ pub struct MochatConfig {
     pub groups: std::collections::HashMap<String, MochatGroupRule>,
     pub reply_delay_mode: String,
     pub reply_delay_ms: u64,
+    pub read_only: bool,
+    pub delivery_window: Option<DeliveryWindow>,
+    pub max_reply_chars: Option<usize>,
+    pub citations: Option<bool>,
 }
 
 impl Default for MochatConfig {
@@ -344,6 +550,10 @@ impl Default for MochatConfig {
             groups: std::collections::HashMap::new(),
             reply_delay_mode: "non-mention".to_string(),
             reply_delay_ms: 120000,
+            read_only: false,
+            delivery_window: None,
+            max_reply_chars: None,
+            citations: None,
         }
     }
 }
@@ -371,7 +581,40 @@ pub struct EmailConfig {
     pub mark_seen: bool,
     pub max_body_chars: usize,
     pub subject_prefix: String,
+    // Exact addresses, or "*@example.com" / "*@*.example.com" for whole domains.
     pub allow_from: Vec<String>,
+    pub read_only: bool,
+    pub delivery_window: Option<DeliveryWindow>,
+    pub max_reply_chars: Option<usize>,
+    pub citations: Option<bool>,
+    // Ignore autoresponders, mailing lists and no-reply senders instead of
+    // answering them.
+    pub skip_automated: bool,
+    // The same body arriving more often than this within a day is ignored;
+    // 0 turns the check off.
+    pub max_identical_bodies: u32,
+    // XOAUTH2 instead of passwords; run `nanobot channels login email` once.
+    pub oauth: Option<EmailOAuthConfig>,
+    // Set on entries of `accounts`; prefixes their chat ids ("work:alice@...").
+    pub id: String,
+    // Further mailboxes, each with its own IMAP/SMTP settings and allowlist.
+    // `enabled` and `consentGranted` of the parent apply to all of them.
+    pub accounts: Vec<EmailConfig>,
+}
+
+// OAuth2 device flow for IMAP/SMTP. `provider` ("google" or "microsoft") fills
+// in the endpoints and scope; the URL and scope fields override them.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct EmailOAuthConfig {
+    pub provider: String,
+    pub client_id: String,
+    pub client_secret: String,
+    // Microsoft tenant; defaults to "common".
+    pub tenant: String,
+    pub device_url: String,
+    pub token_url: String,
+    pub scope: String,
 }
 
 impl Default for EmailConfig {
@@ -398,6 +641,15 @@ impl Default for EmailConfig {
             max_body_chars: 12_000,
             subject_prefix: "Re: ".to_string(),
             allow_from: Vec::new(),
+            read_only: false,
+            delivery_window: None,
+            max_reply_chars: None,
+            citations: None,
+            skip_automated: true,
+            max_identical_bodies: 2,
+            oauth: None,
+            id: String::new(),
+            accounts: Vec::new(),
         }
     }
 }
@@ -432,6 +684,10 @@ pub struct SlackConfig {
     pub group_policy: String,
     pub group_allow_from: Vec<String>,
     pub dm: SlackDMConfig,
+    pub read_only: bool,
+    pub delivery_window: Option<DeliveryWindow>,
+    pub max_reply_chars: Option<usize>,
+    pub citations: Option<bool>,
 }
 
 impl Default for SlackConfig {
@@ -446,6 +702,10 @@ impl Default for SlackConfig {
             group_policy: "mention".to_string(),
             group_allow_from: Vec::new(),
             dm: SlackDMConfig::default(),
+            read_only: false,
+            delivery_window: None,
+            max_reply_chars: None,
+            citations: None,
         }
     }
 }
@@ -457,9 +717,13 @@ pub struct QQConfig {
     pub app_id: String,
     pub secret: String,
     pub allow_from: Vec<String>,
+    pub read_only: bool,
+    pub delivery_window: Option<DeliveryWindow>,
+    pub max_reply_chars: Option<usize>,
+    pub citations: Option<bool>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default, rename_all = "camelCase")]
 pub struct ChannelsConfig {
     pub whatsapp: WhatsAppConfig,
@@ -471,6 +735,235 @@ pub struct ChannelsConfig {
     pub email: EmailConfig,
     pub slack: SlackConfig,
     pub qq: QQConfig,
+    // Coalesce proactive messages per chat into one digest every N minutes; 0 disables.
+    pub digest_minutes: u64,
+    // Consecutive bot-authored (or echoed) messages answered per chat before
+    // the agent goes quiet until a human speaks again.
+    pub max_bot_exchanges: u32,
+    // Sender ids ("123" or "telegram:123") allowed to see and change every
+    // user's cron jobs; everyone else only manages their own.
+    pub owners: Vec<String>,
+    // Question unknown senders can answer to get read-only guest access
+    // without waiting for the owner to approve a pairing code.
+    pub pairing_challenge: Option<PairingChallenge>,
+    pub abuse: AbuseConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct PairingChallenge {
+    pub question: String,
+    pub answer: String,
+    pub max_attempts: u32,
+}
+
+// Per-sender abuse detection for public deployments. Jailbreak attempts,
+// rejected tool calls and honeypot hits count as incidents; a sender with
+// `demoteAt` incidents inside the window is limited to read-only tools,
+// `notifyAt` alerts the owner and `muteAt` ignores them for `muteMinutes`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AbuseConfig {
+    pub enabled: bool,
+    pub window_minutes: u64,
+    pub demote_at: u32,
+    pub notify_at: u32,
+    pub mute_at: u32,
+    pub mute_minutes: u64,
+    // "channel:chat_id" that receives owner alerts, e.g. "telegram:123456".
+    pub notify: String,
+    // Decoy strings (a fake key planted in the workspace, a secret command);
+    // anyone using one is muted at once.
+    pub canaries: Vec<String>,
+    // Extra case-insensitive regexes treated as jailbreak attempts.
+    pub patterns: Vec<String>,
+}
+
+impl Default for AbuseConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            window_minutes: 60,
+            demote_at: 3,
+            notify_at: 5,
+            mute_at: 8,
+            mute_minutes: 24 * 60,
+            notify: String::new(),
+            canaries: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+impl Default for PairingChallenge {
+    fn default() -> Self {
+        Self {
+            question: String::new(),
+            answer: String::new(),
+            max_attempts: 3,
+        }
+    }
+}
+
+impl Default for ChannelsConfig {
+    fn default() -> Self {
+        Self {
+            whatsapp: WhatsAppConfig::default(),
+            telegram: TelegramConfig::default(),
+            discord: DiscordConfig::default(),
+            feishu: FeishuConfig::default(),
+            mochat: MochatConfig::default(),
+            dingtalk: DingTalkConfig::default(),
+            email: EmailConfig::default(),
+            slack: SlackConfig::default(),
+            qq: QQConfig::default(),
+            digest_minutes: 0,
+            max_bot_exchanges: 3,
+            owners: Vec::new(),
+            pairing_challenge: None,
+            abuse: AbuseConfig::default(),
+        }
+    }
+}
+
+pub const CHANNEL_NAMES: &[&str] = &[
+    "whatsapp", "telegram", "discord", "feishu", "mochat", "dingtalk", "email", "slack", "qq",
+];
+
+impl ChannelsConfig {
+    pub fn is_enabled(&self, channel: &str) -> bool {
+        match channel {
+            "whatsapp" => self.whatsapp.enabled,
+            "telegram" => self.telegram.enabled,
+            "discord" => self.discord.enabled,
+            "feishu" => self.feishu.enabled,
+            "mochat" => self.mochat.enabled,
+            "dingtalk" => self.dingtalk.enabled,
+            "email" => self.email.enabled,
+            "slack" => self.slack.enabled,
+            "qq" => self.qq.enabled,
+            _ => false,
+        }
+    }
+
+    // Returns false for an unknown channel name.
+    pub fn set_enabled(&mut self, channel: &str, enabled: bool) -> bool {
+        let flag = match channel {
+            "whatsapp" => &mut self.whatsapp.enabled,
+            "telegram" => &mut self.telegram.enabled,
+            "discord" => &mut self.discord.enabled,
+            "feishu" => &mut self.feishu.enabled,
+            "mochat" => &mut self.mochat.enabled,
+            "dingtalk" => &mut self.dingtalk.enabled,
+            "email" => &mut self.email.enabled,
+            "slack" => &mut self.slack.enabled,
+            "qq" => &mut self.qq.enabled,
+            _ => return false,
+        };
+        *flag = enabled;
+        true
+    }
+
+    pub fn is_read_only(&self, channel: &str) -> bool {
+        match channel {
+            "whatsapp" => self.whatsapp.read_only,
+            "telegram" => self.telegram.read_only,
+            "discord" => self.discord.read_only,
+            "feishu" => self.feishu.read_only,
+            "mochat" => self.mochat.read_only,
+            "dingtalk" => self.dingtalk.read_only,
+            "email" => self.email.read_only,
+            "slack" => self.slack.read_only,
+            "qq" => self.qq.read_only,
+            _ => false,
+        }
+    }
+
+    pub fn delivery_window(&self, channel: &str) -> Option<&DeliveryWindow> {
+        match channel {
+            "whatsapp" => self.whatsapp.delivery_window.as_ref(),
+            "telegram" => self.telegram.delivery_window.as_ref(),
+            "discord" => self.discord.delivery_window.as_ref(),
+            "feishu" => self.feishu.delivery_window.as_ref(),
+            "mochat" => self.mochat.delivery_window.as_ref(),
+            "dingtalk" => self.dingtalk.delivery_window.as_ref(),
+            "email" => self.email.delivery_window.as_ref(),
+            "slack" => self.slack.delivery_window.as_ref(),
+            "qq" => self.qq.delivery_window.as_ref(),
+            _ => None,
+        }
+    }
+
+    // Replies longer than this are split into several messages; `None` keeps
+    // the adapter's own limit and 0 never splits.
+    pub fn max_reply_chars(&self, channel: &str) -> Option<usize> {
+        match channel {
+            "whatsapp" => self.whatsapp.max_reply_chars,
+            "telegram" => self.telegram.max_reply_chars,
+            "discord" => self.discord.max_reply_chars,
+            "feishu" => self.feishu.max_reply_chars,
+            "mochat" => self.mochat.max_reply_chars,
+            "dingtalk" => self.dingtalk.max_reply_chars,
+            "email" => self.email.max_reply_chars,
+            "slack" => self.slack.max_reply_chars,
+            "qq" => self.qq.max_reply_chars,
+            _ => None,
+        }
+    }
+
+    pub fn citations(&self, channel: &str) -> Option<bool> {
+        match channel {
+            "whatsapp" => self.whatsapp.citations,
+            "telegram" => self.telegram.citations,
+            "discord" => self.discord.citations,
+            "feishu" => self.feishu.citations,
+            "mochat" => self.mochat.citations,
+            "dingtalk" => self.dingtalk.citations,
+            "email" => self.email.citations,
+            "slack" => self.slack.citations,
+            "qq" => self.qq.citations,
+            _ => None,
+        }
+    }
+
+    pub fn citation_overrides(&self) -> HashMap<String, bool> {
+        CHANNEL_NAMES
+            .iter()
+            .copied()
+            .filter_map(|name| self.citations(name).map(|on| (name.to_string(), on)))
+            .collect()
+    }
+
+    pub fn reply_limits(&self) -> HashMap<String, usize> {
+        CHANNEL_NAMES
+            .iter()
+            .copied()
+            .filter_map(|name| {
+                self.max_reply_chars(name)
+                    .map(|limit| (name.to_string(), limit))
+            })
+            .collect()
+    }
+
+    pub fn delivery_windows(&self) -> HashMap<String, DeliveryWindow> {
+        CHANNEL_NAMES
+            .iter()
+            .copied()
+            .filter_map(|name| {
+                self.delivery_window(name)
+                    .map(|window| (name.to_string(), window.clone()))
+            })
+            .collect()
+    }
+
+    pub fn read_only_channels(&self) -> Vec<String> {
+        CHANNEL_NAMES
+            .iter()
+            .copied()
+            .filter(|name| self.is_read_only(name))
+            .map(ToOwned::to_owned)
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -482,6 +975,72 @@ pub struct Config {
     pub gateway: GatewayConfig,
     pub service: ServiceConfig,
     pub tools: ToolsConfig,
+    // Capability corrections for unknown or self-hosted models, keyed by
+    // model name with or without its provider prefix.
+    pub models: HashMap<String, ModelCapabilitiesOverride>,
+    pub hooks: HooksConfig,
+    pub events: EventsConfig,
+    pub storage: StorageConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StorageConfig {
+    // "filesystem" (files under ~/.nanobot) or "sqlite" (needs the `sqlite` feature).
+    pub backend: String,
+    // SQLite database file; defaults to ~/.nanobot/storage.sqlite.
+    pub path: String,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: "filesystem".to_string(),
+            path: String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct EventsConfig {
+    // Days of history kept by `nanobot events`; 0 keeps everything.
+    pub retention_days: u32,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        Self { retention_days: 14 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HooksConfig {
+    pub enabled: bool,
+    // Per-call budget so a runaway script cannot stall the agent.
+    pub max_operations: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_operations: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ModelCapabilitiesOverride {
+    pub context_window: Option<usize>,
+    pub max_output_tokens: Option<u32>,
+    pub vision: Option<bool>,
+    pub tool_calling: Option<bool>,
+    pub reasoning: Option<bool>,
+    pub input_cost_per_mtok: Option<f64>,
+    pub output_cost_per_mtok: Option<f64>,
 }
 
 impl Config {
@@ -511,7 +1070,7 @@ impl Config {
 
         for (name, keywords) in mapping {
             let provider = self.provider_by_name(name);
-            if keywords.iter().any(|kw| m.contains(kw)) && !provider.api_key.is_empty() {
+            if keywords.iter().any(|kw| m.contains(kw)) && !provider.keys().is_empty() {
                 return (Some(provider), Some(name));
             }
         }
@@ -531,7 +1090,7 @@ impl Config {
             "groq",
         ] {
             let provider = self.provider_by_name(name);
-            if !provider.api_key.is_empty() {
+            if !provider.keys().is_empty() {
                 return (Some(provider), Some(name));
             }
         }
@@ -567,10 +1126,8 @@ impl Config {
     }
 
     pub fn get_api_key(&self, model: Option<&str>) -> Option<String> {
-        if let Some(provider) = self.get_provider(model) {
-            return Some(provider.api_key.clone());
-        }
-        None
+        self.get_provider(model)
+            .and_then(|provider| provider.keys().into_iter().next())
     }
 
     pub fn get_api_base(&self, model: Option<&str>) -> Option<String> {
@@ -664,43 +1221,43 @@ pub fn providers_status(config: &Config) -> Map<String, Value> {
     let mut map = Map::new();
     map.insert(
         "openrouter".to_string(),
-        Value::Bool(!config.providers.openrouter.api_key.is_empty()),
+        Value::Bool(!config.providers.openrouter.keys().is_empty()),
     );
     map.insert(
         "aihubmix".to_string(),
-        Value::Bool(!config.providers.aihubmix.api_key.is_empty()),
+        Value::Bool(!config.providers.aihubmix.keys().is_empty()),
     );
     map.insert(
         "anthropic".to_string(),
-        Value::Bool(!config.providers.anthropic.api_key.is_empty()),
+        Value::Bool(!config.providers.anthropic.keys().is_empty()),
     );
     map.insert(
         "openai".to_string(),
-        Value::Bool(!config.providers.openai.api_key.is_empty()),
+        Value::Bool(!config.providers.openai.keys().is_empty()),
     );
     map.insert(
         "deepseek".to_string(),
-        Value::Bool(!config.providers.deepseek.api_key.is_empty()),
+        Value::Bool(!config.providers.deepseek.keys().is_empty()),
     );
     map.insert(
         "gemini".to_string(),
-        Value::Bool(!config.providers.gemini.api_key.is_empty()),
+        Value::Bool(!config.providers.gemini.keys().is_empty()),
     );
     map.insert(
         "minimax".to_string(),
-        Value::Bool(!config.providers.minimax.api_key.is_empty()),
+        Value::Bool(!config.providers.minimax.keys().is_empty()),
     );
     map.insert(
         "zhipu".to_string(),
-        Value::Bool(!config.providers.zhipu.api_key.is_empty()),
+        Value::Bool(!config.providers.zhipu.keys().is_empty()),
     );
     map.insert(
         "dashscope".to_string(),
-        Value::Bool(!config.providers.dashscope.api_key.is_empty()),
+        Value::Bool(!config.providers.dashscope.keys().is_empty()),
     );
     map.insert(
         "moonshot".to_string(),
-        Value::Bool(!config.providers.moonshot.api_key.is_empty()),
+        Value::Bool(!config.providers.moonshot.keys().is_empty()),
     );
     map.insert(
         "vllm".to_string(),
@@ -708,7 +1265,7 @@ pub fn providers_status(config: &Config) -> Map<String, Value> {
     );
     map.insert(
         "groq".to_string(),
-        Value::Bool(!config.providers.groq.api_key.is_empty()),
+        Value::Bool(!config.providers.groq.keys().is_empty()),
     );
     map
 }
@@ -1,5 +1,8 @@
+pub mod reminder;
 pub mod service;
+pub mod template;
+pub mod time;
 pub mod types;
 
 pub use service::{CronJobCallback, CronService};
-pub use types::{CronJob, CronJobState, CronPayload, CronSchedule, CronStore};
+pub use types::{CronJob, CronJobState, CronPayload, CronSchedule, CronStore, CronTemplate};
@@ -0,0 +1,303 @@
+use crate::bus::OutboundMessage;
+use crate::channels::format::BUTTON_CHANNELS;
+use crate::cron::time::{ensure_future, parse_at, resolve_local};
+use crate::cron::{CronJob, CronSchedule, CronService};
+use crate::storage::locate;
+use crate::utils::get_data_path;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Duration, Local, NaiveTime};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// Payload kind of jobs made by the remind tool. They are delivered as-is,
+// without an agent turn.
+pub const REMINDER_KIND: &str = "reminder";
+
+// (argument to /snooze, button label)
+pub const SNOOZE_CHOICES: &[(&str, &str)] = &[
+    ("10m", "⏰ 10 min"),
+    ("1h", "⏰ 1 hour"),
+    ("tomorrow", "🌅 Tomorrow"),
+];
+
+const TOMORROW_DEFAULT: &str = "09:00";
+const FIRED_KEEP_MS: i64 = 7 * 86_400_000;
+
+fn parse_duration(raw: &str) -> Option<Duration> {
+    let raw = raw.trim();
+    let split = raw.find(|c: char| !c.is_ascii_digit())?;
+    let amount: i64 = raw[..split].parse().ok()?;
+    let duration = match raw[split..].trim() {
+        "m" | "min" | "mins" | "minute" | "minutes" => Duration::minutes(amount),
+        "h" | "hr" | "hrs" | "hour" | "hours" => Duration::hours(amount),
+        "d" | "day" | "days" => Duration::days(amount),
+        _ => return None,
+    };
+    (amount > 0).then_some(duration)
+}
+
+fn parse_clock(raw: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(raw.trim(), "%H:%M").ok()
+}
+
+// "10m", "in 2 hours", "tomorrow", "tomorrow 08:30", "18:00" (next occurrence),
+// or anything `parse_at` accepts.
+pub fn parse_when(raw: &str, now: DateTime<Local>) -> Result<i64> {
+    let lowered = raw.trim().to_ascii_lowercase();
+    let text = lowered.strip_prefix("in ").unwrap_or(&lowered).trim();
+    let at_ms = if let Some(duration) = parse_duration(text) {
+        (now + duration).timestamp_millis()
+    } else if let Some(rest) = text.strip_prefix("tomorrow") {
+        let rest = rest.trim().trim_start_matches("at").trim();
+        let clock = parse_clock(if rest.is_empty() {
+            TOMORROW_DEFAULT
+        } else {
+            rest
+        })
+        .ok_or_else(|| anyhow!("invalid time '{rest}': expected HH:MM"))?;
+        let date = now.date_naive() + Duration::days(1);
+        resolve_local(date.and_time(clock))?.timestamp_millis()
+    } else if let Some(clock) = parse_clock(text.strip_prefix("at ").unwrap_or(text)) {
+        let mut at = resolve_local(now.date_naive().and_time(clock))?;
+        if at <= now {
+            at = resolve_local((now.date_naive() + Duration::days(1)).and_time(clock))?;
+        }
+        at.timestamp_millis()
+    } else {
+        parse_at(raw)?
+    };
+    ensure_future(at_ms, now.timestamp_millis())?;
+    Ok(at_ms)
+}
+
+fn short_name(text: &str) -> String {
+    let first = text.lines().next().unwrap_or_default().trim();
+    let mut name: String = first.chars().take(40).collect();
+    if first.chars().count() > 40 {
+        name.push('…');
+    }
+    format!("reminder: {name}")
+}
+
+// A one-shot delivering job that removes itself after firing.
+pub async fn schedule_reminder(
+    cron: &CronService,
+    text: &str,
+    at_ms: i64,
+    channel: &str,
+    to: &str,
+    owner: Option<String>,
+) -> Result<CronJob> {
+    let schedule = CronSchedule {
+        kind: "at".to_string(),
+        at_ms: Some(at_ms),
+        ..CronSchedule::default()
+    };
+    add_reminder(cron, text, schedule, channel, to, owner).await
+}
+
+// A delivering reminder job on any schedule; only one-shots remove themselves.
+pub async fn add_reminder(
+    cron: &CronService,
+    text: &str,
+    schedule: CronSchedule,
+    channel: &str,
+    to: &str,
+    owner: Option<String>,
+) -> Result<CronJob> {
+    let one_shot = schedule.kind == "at";
+    let job = cron
+        .add_job(
+            short_name(text),
+            schedule,
+            text.to_string(),
+            true,
+            Some(channel.to_string()),
+            Some(to.to_string()),
+            one_shot,
+        )
+        .await?;
+    let updated = cron
+        .update_payload(&job.id, |payload| {
+            payload.kind = REMINDER_KIND.to_string();
+            // The user asked for this moment; don't hold it for a quiet window.
+            payload.urgent = true;
+            payload.owner = owner;
+        })
+        .await?;
+    Ok(updated.unwrap_or(job))
+}
+
+// What a firing reminder sends: the text plus snooze buttons, or a hint with
+// the equivalent commands on channels without buttons.
+pub fn reminder_message(job: &CronJob, channel: &str, to: &str) -> OutboundMessage {
+    let mut content = format!("⏰ Reminder: {}", job.payload.message);
+    let mut msg = OutboundMessage::new(channel, to, String::new());
+    if BUTTON_CHANNELS.contains(&channel) {
+        let buttons = SNOOZE_CHOICES
+            .iter()
+            .map(|(choice, label)| {
+                json!({ "text": label, "command": format!("/snooze {} {choice}", job.id) })
+            })
+            .collect::<Vec<_>>();
+        msg.metadata
+            .insert("buttons".to_string(), Value::Array(buttons));
+    } else {
+        let choices = SNOOZE_CHOICES
+            .iter()
+            .map(|(choice, _)| *choice)
+            .collect::<Vec<_>>();
+        content.push_str(&format!(
+            "\n\nSnooze: /snooze {} {}",
+            job.id,
+            choices.join(" | ")
+        ));
+    }
+    msg.content = content;
+    msg
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FiredReminder {
+    pub text: String,
+    pub channel: String,
+    pub to: String,
+    #[serde(default)]
+    pub owner: Option<String>,
+    pub fired_at_ms: i64,
+}
+
+// Reminders that already fired, kept for a week so a snooze can recreate them
+// after their job deleted itself.
+#[derive(Clone)]
+pub struct FiredReminders {
+    path: PathBuf,
+}
+
+impl FiredReminders {
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_path(
+            get_data_path()?.join("cron").join("reminders.json"),
+        ))
+    }
+
+    pub fn from_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn load(&self) -> BTreeMap<String, FiredReminder> {
+        locate(&self.path)
+            .read()
+            .ok()
+            .flatten()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn record(&self, job: &CronJob, channel: &str, to: &str, now_ms: i64) -> Result<()> {
+        let mut fired = self.load();
+        fired.retain(|_, reminder| now_ms - reminder.fired_at_ms < FIRED_KEEP_MS);
+        fired.insert(
+            job.id.clone(),
+            FiredReminder {
+                text: job.payload.message.clone(),
+                channel: channel.to_string(),
+                to: to.to_string(),
+                owner: job.payload.owner.clone(),
+                fired_at_ms: now_ms,
+            },
+        );
+        locate(&self.path).write(&serde_json::to_string_pretty(&fired)?)
+    }
+
+    pub fn get(&self, id: &str) -> Option<FiredReminder> {
+        self.load().remove(id)
+    }
+}
+
+// Build the outgoing message for a firing reminder and remember it for snoozes.
+pub fn fire_reminder(
+    fired: &FiredReminders,
+    job: &CronJob,
+    channel: &str,
+    to: &str,
+) -> OutboundMessage {
+    if let Err(err) = fired.record(job, channel, to, Local::now().timestamp_millis()) {
+        eprintln!("Warning: failed to record reminder {}: {err}", job.id);
+    }
+    reminder_message(job, channel, to)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cron::CronPayload;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parses_reminder_times_and_renders_snooze_options() {
+        let now = Local
+            .with_ymd_and_hms(2030, 1, 15, 20, 0, 0)
+            .single()
+            .expect("valid local time");
+        let at = |raw: &str| {
+            Local
+                .timestamp_millis_opt(parse_when(raw, now).expect(raw))
+                .single()
+                .expect("valid timestamp")
+        };
+        assert_eq!(at("10m"), now + Duration::minutes(10));
+        assert_eq!(at("in 2 hours"), now + Duration::hours(2));
+        assert_eq!(at("tomorrow").format("%d %H:%M").to_string(), "16 09:00");
+        assert_eq!(
+            at("tomorrow at 07:15").format("%d %H:%M").to_string(),
+            "16 07:15"
+        );
+        assert_eq!(at("21:30").format("%d %H:%M").to_string(), "15 21:30");
+        assert_eq!(at("08:00").format("%d %H:%M").to_string(), "16 08:00");
+        assert!(parse_when("2020-01-01 10:00", now).is_err());
+        assert!(parse_when("someday", now).is_err());
+
+        let job = CronJob {
+            id: "abcd1234".to_string(),
+            name: "reminder: stretch".to_string(),
+            enabled: true,
+            schedule: CronSchedule::default(),
+            payload: CronPayload {
+                kind: REMINDER_KIND.to_string(),
+                message: "stretch".to_string(),
+                ..CronPayload::default()
+            },
+            state: Default::default(),
+            created_at_ms: 0,
+            updated_at_ms: 0,
+            delete_after_run: true,
+        };
+        let telegram = reminder_message(&job, "telegram", "42");
+        assert_eq!(telegram.content, "⏰ Reminder: stretch");
+        let buttons = telegram.metadata["buttons"].as_array().expect("buttons");
+        assert_eq!(buttons.len(), SNOOZE_CHOICES.len());
+        assert_eq!(buttons[0]["command"], "/snooze abcd1234 10m");
+
+        let email = reminder_message(&job, "email", "a@example.com");
+        assert!(!email.metadata.contains_key("buttons"));
+        assert!(
+            email
+                .content
+                .ends_with("Snooze: /snooze abcd1234 10m | 1h | tomorrow")
+        );
+
+        let fired = FiredReminders::from_path(
+            std::env::temp_dir().join(format!("nanobot-reminders-{}.json", uuid::Uuid::new_v4())),
+        );
+        fired
+            .record(&job, "email", "a@example.com", 0)
+            .expect("record");
+        assert_eq!(fired.get("abcd1234").expect("fired").to, "a@example.com");
+        assert!(fired.get("missing").is_none());
+        let _ = std::fs::remove_file(&fired.path);
+    }
+}
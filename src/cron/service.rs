@@ -1,6 +1,12 @@
-use crate::cron::types::{CronJob, CronJobState, CronPayload, CronSchedule, CronStore};
+use crate::cluster::LeaderLease;
+use crate::cron::types::{
+    CronJob, CronJobState, CronPayload, CronSchedule, CronStore, CronTemplate,
+};
+use crate::events::{self, EventLevel};
+use crate::pause::PauseSwitch;
+use crate::storage::locate;
 use anyhow::Result;
-use chrono::{TimeZone, Utc};
+use chrono::{Local, TimeZone, Utc};
 use cron::Schedule;
 use futures_util::future::BoxFuture;
 use std::str::FromStr;
@@ -17,6 +23,24 @@ fn now_ms() -> i64 {
     Utc::now().timestamp_millis()
 }
 
+fn record_run(job: &CronJob, result: &Result<Option<String>>) {
+    let channel = job.payload.channel.as_deref();
+    match result {
+        Ok(_) => events::record(
+            EventLevel::Info,
+            "cron",
+            channel,
+            format!("job '{}' ({}) ran", job.name, job.id),
+        ),
+        Err(err) => events::record(
+            EventLevel::Error,
+            "cron",
+            channel,
+            format!("job '{}' ({}) failed: {err}", job.name, job.id),
+        ),
+    }
+}
+
 fn compute_next_run(schedule: &CronSchedule, now_ms: i64) -> Option<i64> {
     match schedule.kind.as_str() {
         "at" => schedule.at_ms.filter(|at| *at > now_ms),
@@ -31,6 +55,11 @@ fn compute_next_run(schedule: &CronSchedule, now_ms: i64) -> Option<i64> {
         "cron" => {
             let expr = schedule.expr.as_ref()?;
             let parsed = Schedule::from_str(expr).ok()?;
+            // Fields are read in UTC unless the schedule asks for local time.
+            if schedule.tz.as_deref() == Some("local") {
+                let now = Local.timestamp_millis_opt(now_ms).single()?;
+                return parsed.after(&now).next().map(|dt| dt.timestamp_millis());
+            }
             let now = Utc.timestamp_millis_opt(now_ms).single()?;
             parsed.after(&now).next().map(|dt| dt.timestamp_millis())
         }
@@ -38,10 +67,36 @@ fn compute_next_run(schedule: &CronSchedule, now_ms: i64) -> Option<i64> {
     }
 }
 
+// Schedules every enabled job from `now`, so runs missed while nobody was
+// running the schedule are skipped instead of firing all at once.
+fn recompute_store(store: &mut CronStore, now: i64) {
+    for job in store.jobs.iter_mut().filter(|job| job.enabled) {
+        job.state.next_run_at_ms = compute_next_run(&job.schedule, now);
+    }
+}
+
+fn skip_paused_runs(store: &mut CronStore, due: &[String], now: i64) {
+    for job in store
+        .jobs
+        .iter_mut()
+        .filter(|job| due.contains(&job.id) && job.schedule.kind != "at")
+    {
+        job.state.next_run_at_ms = compute_next_run(&job.schedule, now);
+        events::record(
+            EventLevel::Info,
+            "cron",
+            job.payload.channel.as_deref(),
+            format!("job '{}' ({}) skipped while paused", job.name, job.id),
+        );
+    }
+}
+
 pub struct CronService {
     store_path: std::path::PathBuf,
     on_job: Arc<Mutex<Option<CronJobCallback>>>,
     store: Arc<Mutex<CronStore>>,
+    pause: Option<PauseSwitch>,
+    lease: Option<LeaderLease>,
     running: Arc<AtomicBool>,
     runner: Arc<Mutex<Option<JoinHandle<()>>>>,
 }
@@ -52,11 +107,30 @@ impl CronService {
             store_path,
             on_job: Arc::new(Mutex::new(None)),
             store: Arc::new(Mutex::new(CronStore::default())),
+            pause: None,
+            lease: None,
             running: Arc::new(AtomicBool::new(false)),
             runner: Arc::new(Mutex::new(None)),
         }
     }
 
+    // While the switch is on, recurring jobs skip their runs and one-shot
+    // jobs wait until it is turned off.
+    pub fn with_pause_switch(mut self, pause: PauseSwitch) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    // Cluster mode: only the instance holding the lease runs jobs, and every
+    // instance re-reads the shared store before touching it, since the others
+    // write it too. Storage has no compare-and-swap, so two instances that
+    // change jobs within the same read-modify-write (a few milliseconds) race
+    // and the later save wins; changes made one after the other all survive.
+    pub fn with_leader_lease(mut self, lease: LeaderLease) -> Self {
+        self.lease = Some(lease);
+        self
+    }
+
     pub async fn set_on_job(&self, callback: CronJobCallback) {
         let mut guard = self.on_job.lock().await;
         *guard = Some(callback);
@@ -65,15 +139,37 @@ impl CronService {
     pub async fn start(&self) -> Result<()> {
         self.running.store(true, Ordering::Relaxed);
         self.load_store().await?;
-        self.recompute_next_runs().await;
-        self.save_store().await?;
+        // A clustered instance joins a schedule the leader keeps running;
+        // recomputing it on every restart would push jobs back. The runner
+        // recomputes it when this instance takes the lease instead.
+        if self.lease.is_none() {
+            self.recompute_next_runs().await;
+            self.save_store().await?;
+        }
 
         let running = self.running.clone();
         let store = self.store.clone();
         let on_job = self.on_job.clone();
         let store_path = self.store_path.clone();
+        let pause = self.pause.clone();
+        let lease = self.lease.clone();
         let runner = tokio::spawn(async move {
+            let mut was_leader = false;
             while running.load(Ordering::Relaxed) {
+                if let Some(lease) = &lease {
+                    if !lease.is_leader() {
+                        was_leader = false;
+                        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                        continue;
+                    }
+                    reload_store_static(&store_path, &store).await;
+                    // Nobody ran the schedule between the old leader stopping
+                    // and this takeover; start it afresh like a restart.
+                    if !was_leader {
+                        was_leader = true;
+                        recompute_store(&mut *store.lock().await, now_ms());
+                    }
+                }
                 let mut due_jobs = Vec::new();
                 {
                     let snapshot = store.lock().await;
@@ -86,14 +182,20 @@ impl CronService {
                         due_jobs.push(job.id.clone());
                     }
                 }
+                if !due_jobs.is_empty() && pause.as_ref().is_some_and(|p| p.is_paused_at(now_ms()))
+                {
+                    skip_paused_runs(&mut *store.lock().await, &due_jobs, now_ms());
+                    due_jobs.clear();
+                }
 
                 for id in due_jobs {
                     let mut job_to_run = None;
+                    let started_ms = now_ms();
                     {
                         let mut data = store.lock().await;
                         if let Some(job) = data.jobs.iter_mut().find(|j| j.id == id) {
                             job_to_run = Some(job.clone());
-                            job.state.last_run_at_ms = Some(now_ms());
+                            job.state.last_run_at_ms = Some(started_ms);
                         }
                     }
 
@@ -105,8 +207,13 @@ impl CronService {
                         } else {
                             Ok(None)
                         };
+                        record_run(&job, &result);
+                        if lease.is_some() {
+                            reload_store_static(&store_path, &store).await;
+                        }
                         let mut data = store.lock().await;
                         if let Some(target) = data.jobs.iter_mut().find(|j| j.id == job.id) {
+                            target.state.last_run_at_ms = Some(started_ms);
                             if let Err(err) = &result {
                                 target.state.last_status = Some("error".to_string());
                                 target.state.last_error = Some(err.to_string());
@@ -151,23 +258,14 @@ impl CronService {
     }
 
     async fn recompute_next_runs(&self) {
-        let mut store = self.store.lock().await;
-        let now = now_ms();
-        for job in &mut store.jobs {
-            if job.enabled {
-                job.state.next_run_at_ms = compute_next_run(&job.schedule, now);
-            }
-        }
+        recompute_store(&mut *self.store.lock().await, now_ms());
     }
 
     async fn load_store(&self) -> Result<()> {
-        if !self.store_path.exists() {
-            *self.store.lock().await = CronStore::default();
-            return Ok(());
-        }
-
-        let raw = tokio::fs::read_to_string(&self.store_path).await?;
-        let store: CronStore = serde_json::from_str(&raw).unwrap_or_default();
+        let store = match locate(&self.store_path).read()? {
+            Some(raw) => serde_json::from_str(&raw).unwrap_or_default(),
+            None => CronStore::default(),
+        };
         *self.store.lock().await = store;
         Ok(())
     }
@@ -176,7 +274,14 @@ impl CronService {
         save_store_static(&self.store_path, &self.store).await
     }
 
+    async fn reload_shared(&self) {
+        if self.lease.is_some() {
+            reload_store_static(&self.store_path, &self.store).await;
+        }
+    }
+
     pub async fn list_jobs(&self, include_disabled: bool) -> Vec<CronJob> {
+        self.reload_shared().await;
         let store = self.store.lock().await;
         let mut jobs = if include_disabled {
             store.jobs.clone()
@@ -209,6 +314,11 @@ impl CronService {
                 deliver,
                 channel,
                 to,
+                urgent: false,
+                template: None,
+                owner: None,
+                message_template: None,
+                template_vars: Default::default(),
             },
             state: CronJobState {
                 next_run_at_ms: compute_next_run(&schedule, now),
@@ -219,6 +329,7 @@ impl CronService {
             delete_after_run,
         };
 
+        self.reload_shared().await;
         {
             let mut store = self.store.lock().await;
             store.jobs.push(job.clone());
@@ -230,6 +341,7 @@ impl CronService {
     }
 
     pub async fn remove_job(&self, job_id: &str) -> Result<bool> {
+        self.reload_shared().await;
         let mut store = self.store.lock().await;
         let before = store.jobs.len();
         store.jobs.retain(|j| j.id != job_id);
@@ -242,6 +354,7 @@ impl CronService {
     }
 
     pub async fn enable_job(&self, job_id: &str, enabled: bool) -> Result<Option<CronJob>> {
+        self.reload_shared().await;
         let mut store = self.store.lock().await;
         if let Some(job) = store.jobs.iter_mut().find(|j| j.id == job_id) {
             job.enabled = enabled;
@@ -259,7 +372,58 @@ impl CronService {
         Ok(None)
     }
 
+    pub async fn set_template(
+        &self,
+        job_id: &str,
+        template: Option<CronTemplate>,
+    ) -> Result<Option<CronJob>> {
+        self.reload_shared().await;
+        let mut store = self.store.lock().await;
+        let Some(job) = store.jobs.iter_mut().find(|j| j.id == job_id) else {
+            return Ok(None);
+        };
+        job.payload.template = template.filter(|t| !t.is_empty());
+        job.updated_at_ms = now_ms();
+        let out = job.clone();
+        drop(store);
+        self.save_store().await?;
+        Ok(Some(out))
+    }
+
+    pub async fn set_owner(&self, job_id: &str, owner: Option<String>) -> Result<Option<CronJob>> {
+        self.reload_shared().await;
+        let mut store = self.store.lock().await;
+        let Some(job) = store.jobs.iter_mut().find(|j| j.id == job_id) else {
+            return Ok(None);
+        };
+        job.payload.owner = owner;
+        job.updated_at_ms = now_ms();
+        let out = job.clone();
+        drop(store);
+        self.save_store().await?;
+        Ok(Some(out))
+    }
+
+    pub async fn update_payload(
+        &self,
+        job_id: &str,
+        update: impl FnOnce(&mut CronPayload),
+    ) -> Result<Option<CronJob>> {
+        self.reload_shared().await;
+        let mut store = self.store.lock().await;
+        let Some(job) = store.jobs.iter_mut().find(|j| j.id == job_id) else {
+            return Ok(None);
+        };
+        update(&mut job.payload);
+        job.updated_at_ms = now_ms();
+        let out = job.clone();
+        drop(store);
+        self.save_store().await?;
+        Ok(Some(out))
+    }
+
     pub async fn run_job(&self, job_id: &str, force: bool) -> Result<bool> {
+        self.reload_shared().await;
         let job_opt = {
             let store = self.store.lock().await;
             store.jobs.iter().find(|j| j.id == job_id).cloned()
@@ -277,6 +441,8 @@ impl CronService {
         } else {
             Ok(None)
         };
+        record_run(&job, &result);
+        self.reload_shared().await;
         let mut store = self.store.lock().await;
         if let Some(target) = store.jobs.iter_mut().find(|j| j.id == job_id) {
             if let Err(err) = &result {
@@ -300,6 +466,7 @@ impl CronService {
     }
 
     pub async fn status(&self) -> serde_json::Value {
+        self.reload_shared().await;
         let store = self.store.lock().await;
         let next_wake = store
             .jobs
@@ -315,16 +482,22 @@ impl CronService {
     }
 }
 
-async fn save_store_static(path: &std::path::Path, store: &Arc<Mutex<CronStore>>) -> Result<()> {
-    if let Some(parent) = path.parent() {
-        tokio::fs::create_dir_all(parent).await?;
+// Picks up changes other instances made to the shared store. One that does
+// not parse leaves the copy in memory alone rather than emptying it.
+async fn reload_store_static(path: &std::path::Path, store: &Arc<Mutex<CronStore>>) {
+    if let Ok(Some(raw)) = locate(path).read()
+        && let Ok(shared) = serde_json::from_str::<CronStore>(&raw)
+    {
+        *store.lock().await = shared;
     }
+}
+
+async fn save_store_static(path: &std::path::Path, store: &Arc<Mutex<CronStore>>) -> Result<()> {
     let text = {
         let data = store.lock().await;
         serde_json::to_string_pretty(&*data)?
     };
-    tokio::fs::write(path, text).await?;
-    Ok(())
+    locate(path).write(&text)
 }
 
 #[cfg(test)]
@@ -474,4 +647,191 @@ mod tests {
         let _ = std::fs::remove_file(store_path);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn clustered_services_share_jobs_and_only_the_leader_runs_them() -> Result<()> {
+        let store_path = temp_store_path();
+        let lease_path = store_path.with_extension("lease.json");
+        let lease = |id: &str| {
+            LeaderLease::from_path(
+                lease_path.clone(),
+                id.to_string(),
+                std::time::Duration::from_secs(30),
+            )
+        };
+        let (leader_lease, follower_lease) = (lease("a"), lease("b"));
+        let now = now_ms();
+        leader_lease.step(now)?;
+        assert!(leader_lease.step(now + 1)?);
+
+        let runs = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut services = Vec::new();
+        for (name, lease) in [("leader", leader_lease), ("follower", follower_lease)] {
+            let service = CronService::new(store_path.clone()).with_leader_lease(lease);
+            let runs = runs.clone();
+            service
+                .set_on_job(Arc::new(move |_| {
+                    runs.lock().expect("runs").push(name);
+                    Box::pin(async { Ok(None) })
+                }))
+                .await;
+            service.start().await?;
+            services.push(service);
+        }
+        let (leader, follower) = (&services[0], &services[1]);
+
+        let schedule = CronSchedule {
+            kind: "at".to_string(),
+            at_ms: Some(now_ms() + 200),
+            ..Default::default()
+        };
+        let job = follower
+            .add_job(
+                "shared".to_string(),
+                schedule,
+                "ping".to_string(),
+                false,
+                None,
+                None,
+                false,
+            )
+            .await?;
+        assert_eq!(leader.list_jobs(true).await[0].id, job.id);
+
+        for _ in 0..40 {
+            if follower.list_jobs(true).await[0]
+                .state
+                .last_status
+                .is_some()
+            {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        assert_eq!(*runs.lock().expect("runs"), vec!["leader"]);
+        let ran = &follower.list_jobs(true).await[0];
+        assert_eq!(ran.state.last_status.as_deref(), Some("ok"));
+        assert!(!ran.enabled);
+
+        for service in &services {
+            service.stop().await;
+        }
+        let _ = std::fs::remove_file(store_path);
+        let _ = std::fs::remove_file(lease_path);
+        Ok(())
+    }
+
+    fn every_hour() -> CronSchedule {
+        CronSchedule {
+            kind: "every".to_string(),
+            every_ms: Some(3_600_000),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn new_leader_skips_runs_missed_while_no_one_led() -> Result<()> {
+        let store_path = temp_store_path();
+        let lease_path = store_path.with_extension("lease.json");
+        let lease = |id: &str| {
+            LeaderLease::from_path(
+                lease_path.clone(),
+                id.to_string(),
+                std::time::Duration::from_secs(30),
+            )
+        };
+
+        // The old leader scheduled a job, then the whole cluster was down
+        // for hours.
+        let old = CronService::new(store_path.clone()).with_leader_lease(lease("a"));
+        old.add_job(
+            "hourly".to_string(),
+            every_hour(),
+            "ping".to_string(),
+            false,
+            None,
+            None,
+            false,
+        )
+        .await?;
+        {
+            let mut store = old.store.lock().await;
+            store.jobs[0].state.next_run_at_ms = Some(now_ms() - 5 * 3_600_000);
+        }
+        old.save_store().await?;
+
+        let takeover = lease("b");
+        let now = now_ms();
+        takeover.step(now)?;
+        assert!(takeover.step(now + 1)?);
+        let service = CronService::new(store_path.clone()).with_leader_lease(takeover);
+        let runs = Arc::new(AtomicBool::new(false));
+        let ran = runs.clone();
+        service
+            .set_on_job(Arc::new(move |_| {
+                ran.store(true, Ordering::SeqCst);
+                Box::pin(async { Ok(None) })
+            }))
+            .await;
+        service.start().await?;
+        tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+        service.stop().await;
+
+        assert!(!runs.load(Ordering::SeqCst));
+        let next = service.list_jobs(true).await[0].state.next_run_at_ms;
+        assert!(next.is_some_and(|at| at > now_ms()));
+        let _ = std::fs::remove_file(store_path);
+        let _ = std::fs::remove_file(lease_path);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn changes_from_two_instances_in_turn_both_survive() -> Result<()> {
+        let store_path = temp_store_path();
+        let lease_path = store_path.with_extension("lease.json");
+        let lease = |id: &str| {
+            LeaderLease::from_path(
+                lease_path.clone(),
+                id.to_string(),
+                std::time::Duration::from_secs(30),
+            )
+        };
+        let a = CronService::new(store_path.clone()).with_leader_lease(lease("a"));
+        let b = CronService::new(store_path.clone()).with_leader_lease(lease("b"));
+        a.load_store().await?;
+        b.load_store().await?;
+
+        // Each writer re-reads the store first, so neither works from the
+        // copy it loaded before the other's change.
+        let first = a
+            .add_job(
+                "first".to_string(),
+                every_hour(),
+                "one".to_string(),
+                false,
+                None,
+                None,
+                false,
+            )
+            .await?;
+        b.add_job(
+            "second".to_string(),
+            every_hour(),
+            "two".to_string(),
+            false,
+            None,
+            None,
+            false,
+        )
+        .await?;
+        a.enable_job(&first.id, false).await?;
+
+        let jobs = b.list_jobs(true).await;
+        assert_eq!(jobs.len(), 2);
+        assert!(jobs.iter().any(|job| job.name == "first" && !job.enabled));
+        assert!(jobs.iter().any(|job| job.name == "second" && job.enabled));
+        let _ = std::fs::remove_file(store_path);
+        let _ = std::fs::remove_file(lease_path);
+        Ok(())
+    }
 }
@@ -0,0 +1,119 @@
+use crate::bus::OutboundMessage;
+use crate::cron::types::{CronJob, CronTemplate};
+use chrono::{DateTime, Local};
+use serde_json::Value;
+
+// {response} goes last so placeholders inside the model text stay literal.
+fn fill(pattern: &str, job: &CronJob, response: &str, now: DateTime<Local>) -> String {
+    pattern
+        .replace("{name}", &job.name)
+        .replace("{date}", &now.format("%Y-%m-%d").to_string())
+        .replace("{time}", &now.format("%H:%M").to_string())
+        .replace("{response}", response)
+}
+
+impl CronTemplate {
+    pub fn is_empty(&self) -> bool {
+        self.title.as_deref().is_none_or(|t| t.trim().is_empty())
+            && self.body.as_deref().is_none_or(|b| b.trim().is_empty())
+    }
+}
+
+// The message a delivering job sends.
+pub fn delivery_message(
+    job: &CronJob,
+    channel: &str,
+    to: &str,
+    response: &str,
+    now: DateTime<Local>,
+) -> OutboundMessage {
+    let Some(template) = &job.payload.template else {
+        return OutboundMessage::new(channel, to, response);
+    };
+    let content = template
+        .body
+        .as_deref()
+        .filter(|body| !body.trim().is_empty())
+        .map(|body| fill(body, job, response, now))
+        .unwrap_or_else(|| response.to_string());
+    let title = template
+        .title
+        .as_deref()
+        .map(|title| fill(title, job, response, now));
+    titled_message(channel, to, title.as_deref(), content)
+}
+
+// The title becomes the email subject or Feishu card header; channels without
+// a title slot get it as a bold line. Only its first line counts.
+pub fn titled_message(
+    channel: &str,
+    to: &str,
+    title: Option<&str>,
+    mut content: String,
+) -> OutboundMessage {
+    let title = title
+        .and_then(|title| title.lines().next().map(|line| line.trim().to_string()))
+        .filter(|title| !title.is_empty());
+
+    let mut msg = OutboundMessage::new(channel, to, String::new());
+    if let Some(title) = title {
+        match channel {
+            "email" => {
+                msg.metadata
+                    .insert("subject".to_string(), Value::String(title));
+            }
+            "feishu" => {
+                msg.metadata
+                    .insert("title".to_string(), Value::String(title));
+            }
+            _ => content = format!("**{title}**\n\n{content}"),
+        }
+    }
+    msg.content = content;
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cron::{CronPayload, CronSchedule};
+    use chrono::TimeZone;
+
+    #[test]
+    fn templates_map_titles_per_channel() {
+        let now = Local
+            .with_ymd_and_hms(2026, 3, 9, 8, 0, 0)
+            .single()
+            .expect("valid time");
+        let mut job = CronJob {
+            id: "j1".to_string(),
+            name: "digest".to_string(),
+            enabled: true,
+            schedule: CronSchedule::default(),
+            payload: CronPayload::default(),
+            state: Default::default(),
+            created_at_ms: 0,
+            updated_at_ms: 0,
+            delete_after_run: false,
+        };
+        let raw = delivery_message(&job, "telegram", "1", "hi {name}", now);
+        assert_eq!(raw.content, "hi {name}");
+
+        job.payload.template = Some(CronTemplate {
+            title: Some("{name} for {date}".to_string()),
+            body: Some("Good morning.\n\n{response}".to_string()),
+        });
+        let email = delivery_message(&job, "email", "a@b.c", "News", now);
+        assert_eq!(email.metadata["subject"], "digest for 2026-03-09");
+        assert_eq!(email.content, "Good morning.\n\nNews");
+
+        let feishu = delivery_message(&job, "feishu", "oc_1", "News", now);
+        assert_eq!(feishu.metadata["title"], "digest for 2026-03-09");
+
+        let telegram = delivery_message(&job, "telegram", "1", "News", now);
+        assert_eq!(
+            telegram.content,
+            "**digest for 2026-03-09**\n\nGood morning.\n\nNews"
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local, LocalResult, NaiveDateTime, TimeZone};
+
+const LOCAL_FORMATS: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+];
+
+// Wall-clock time in the local zone. A time repeated when clocks go back
+// resolves to its first occurrence; one skipped when clocks go forward is an
+// error rather than a silent shift.
+pub fn resolve_local(naive: NaiveDateTime) -> Result<DateTime<Local>> {
+    match Local.from_local_datetime(&naive) {
+        LocalResult::Single(dt) => Ok(dt),
+        LocalResult::Ambiguous(earliest, _) => Ok(earliest),
+        LocalResult::None => Err(anyhow!(
+            "{} does not exist in local time (daylight saving gap); pick another time",
+            naive.format("%Y-%m-%d %H:%M")
+        )),
+    }
+}
+
+// RFC3339 with an explicit offset, or local wall-clock "YYYY-MM-DD HH:MM[:SS]".
+pub fn parse_at(raw: &str) -> Result<i64> {
+    let raw = raw.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Ok(dt.timestamp_millis());
+    }
+    let naive = LOCAL_FORMATS
+        .iter()
+        .find_map(|fmt| NaiveDateTime::parse_from_str(raw, fmt).ok())
+        .ok_or_else(|| {
+            anyhow!(
+                "invalid at datetime: expected ISO datetime string or local \"YYYY-MM-DD HH:MM\""
+            )
+        })?;
+    Ok(resolve_local(naive)?.timestamp_millis())
+}
+
+pub fn ensure_future(at_ms: i64, now_ms: i64) -> Result<()> {
+    if at_ms <= now_ms {
+        return Err(anyhow!("{} is in the past", format_local(at_ms, now_ms)));
+    }
+    Ok(())
+}
+
+fn relative(delta_ms: i64) -> String {
+    let minutes = delta_ms.abs() / 60_000;
+    let span = match minutes {
+        0 => return "now".to_string(),
+        m if m < 60 => format!("{m}m"),
+        m if m < 24 * 60 => format!("{}h {}m", m / 60, m % 60),
+        m => format!("{}d {}h", m / (24 * 60), m % (24 * 60) / 60),
+    };
+    if delta_ms > 0 {
+        format!("in {span}")
+    } else {
+        format!("{span} ago")
+    }
+}
+
+// "2026-03-09 09:00 Mon +01:00 (in 2d 3h)"
+pub fn format_local(ms: i64, now_ms: i64) -> String {
+    match Local.timestamp_millis_opt(ms).single() {
+        Some(dt) => format!(
+            "{} ({})",
+            dt.format("%Y-%m-%d %H:%M %a %:z"),
+            relative(ms - now_ms)
+        ),
+        None => ms.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_local_times_and_rejects_the_past() {
+        let rfc = parse_at("2026-02-12T10:30:00+08:00").expect("rfc3339");
+        assert_eq!(rfc, 1_770_863_400_000);
+
+        let local = parse_at("2030-01-15 09:00").expect("local");
+        let expected = Local
+            .with_ymd_and_hms(2030, 1, 15, 9, 0, 0)
+            .single()
+            .expect("valid local time");
+        assert_eq!(local, expected.timestamp_millis());
+        assert_eq!(parse_at("2030-01-15T09:00:00").expect("iso local"), local);
+        assert!(parse_at("tomorrow 9am").is_err());
+
+        assert!(ensure_future(rfc, rfc + 1).is_err());
+        assert!(ensure_future(local, rfc).is_ok());
+        assert!(format_local(rfc + 90 * 60_000, rfc).ends_with("(in 1h 30m)"));
+        assert!(format_local(rfc - 3 * 86_400_000, rfc).ends_with("(3d 0h ago)"));
+    }
+}
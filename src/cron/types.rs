@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -7,7 +8,7 @@ pub struct CronSchedule {
     pub at_ms: Option<i64>,
     pub every_ms: Option<i64>,
     pub expr: Option<String>,
-    pub tz: Option<String>,
+    pub tz: Option<String>, // "local" reads cron fields in local time, otherwise UTC
 }
 
 impl Default for CronSchedule {
@@ -25,11 +26,39 @@ impl Default for CronSchedule {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CronPayload {
-    pub kind: String, // system_event | agent_turn
+    pub kind: String, // system_event | agent_turn | reminder | template
     pub message: String,
     pub deliver: bool,
     pub channel: Option<String>,
     pub to: Option<String>,
+    // Delivered even outside the channel's delivery window.
+    #[serde(default)]
+    pub urgent: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<CronTemplate>,
+    // "channel:sender_id" of the chat user who created the job. Jobs made from
+    // the CLI, or before ownership was tracked, have none.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    // Name of a workspace message template (`templates/<name>.md`), rendered
+    // with `template_vars` on every run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_template: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub template_vars: BTreeMap<String, String>,
+}
+
+// How a delivered response is presented. Both fields accept the placeholders
+// {response}, {name}, {date} and {time}.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CronTemplate {
+    // Email subject, Feishu card title, or a bold first line elsewhere.
+    #[serde(default)]
+    pub title: Option<String>,
+    // Wraps the response, e.g. "Morning digest\n\n{response}".
+    #[serde(default)]
+    pub body: Option<String>,
 }
 
 impl Default for CronPayload {
@@ -40,6 +69,11 @@ impl Default for CronPayload {
             deliver: false,
             channel: None,
             to: None,
+            urgent: false,
+            template: None,
+            owner: None,
+            message_template: None,
+            template_vars: BTreeMap::new(),
         }
     }
 }
@@ -67,6 +101,20 @@ pub struct CronJob {
     pub delete_after_run: bool,
 }
 
+impl CronJob {
+    // Whether `requester` ("channel:sender_id") created the job. Untagged jobs
+    // count as owned by everyone in the chat they deliver to.
+    pub fn owned_by(&self, requester: &str, channel: &str, chat_id: &str) -> bool {
+        match &self.payload.owner {
+            Some(owner) => owner == requester,
+            None => {
+                self.payload.channel.as_deref() == Some(channel)
+                    && self.payload.to.as_deref() == Some(chat_id)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CronStore {
@@ -0,0 +1,326 @@
+use crate::storage::locate;
+use crate::utils::get_data_path;
+use anyhow::{Result, anyhow};
+use chrono::{Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+const PRUNE_INTERVAL_MS: i64 = 6 * 60 * 60 * 1000;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+static EVENT_LOG: OnceLock<EventLog> = OnceLock::new();
+static MIRROR: OnceLock<EventSink> = OnceLock::new();
+
+type EventSink = Box<dyn Fn(&Event) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl EventLevel {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_lowercase().as_str() {
+            "info" => Some(Self::Info),
+            "warn" | "warning" => Some(Self::Warn),
+            "error" => Some(Self::Error),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warn => "warn",
+            Self::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub at_ms: i64,
+    pub level: EventLevel,
+    pub kind: String, // channel | turn | cron | ...
+    #[serde(default)]
+    pub channel: Option<String>,
+    pub message: String,
+}
+
+impl Event {
+    pub fn render(&self) -> String {
+        let at = Local
+            .timestamp_millis_opt(self.at_ms)
+            .single()
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default();
+        let channel = self
+            .channel
+            .as_deref()
+            .map(|channel| format!(" [{channel}]"))
+            .unwrap_or_default();
+        format!(
+            "{at} {:<5} {}{channel} {}",
+            self.level.as_str(),
+            self.kind,
+            self.message
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub since_ms: Option<i64>,
+    pub channel: Option<String>,
+    pub min_level: Option<EventLevel>,
+    pub kind: Option<String>,
+    pub limit: usize,
+}
+
+impl EventFilter {
+    fn matches(&self, event: &Event) -> bool {
+        self.since_ms.is_none_or(|since| event.at_ms >= since)
+            && self
+                .channel
+                .as_ref()
+                .is_none_or(|channel| event.channel.as_ref() == Some(channel))
+            && self.min_level.is_none_or(|level| event.level >= level)
+            && self.kind.as_ref().is_none_or(|kind| &event.kind == kind)
+    }
+}
+
+// Accepts relative ages ("30m", "2h", "7d") or a local date/datetime.
+pub fn parse_since(raw: &str, now_ms: i64) -> Result<i64> {
+    let raw = raw.trim();
+    if let Some(unit) = raw.chars().last().filter(|c| c.is_ascii_alphabetic())
+        && let Ok(amount) = raw[..raw.len() - 1].parse::<i64>()
+    {
+        let unit_ms = match unit {
+            's' => 1000,
+            'm' => 60 * 1000,
+            'h' => 60 * 60 * 1000,
+            'd' => DAY_MS,
+            _ => return Err(anyhow!("unknown unit in '{raw}' (use s, m, h or d)")),
+        };
+        return amount
+            .checked_mul(unit_ms)
+            .and_then(|age_ms| now_ms.checked_sub(age_ms))
+            .ok_or_else(|| anyhow!("'{raw}' is too far back"));
+    }
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Ok(dt.timestamp_millis());
+    }
+    let naive = chrono::NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(raw, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+        .ok_or_else(|| {
+            anyhow!("invalid --since '{raw}': use 2h, 7d, YYYY-MM-DD or YYYY-MM-DD HH:MM")
+        })?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|dt| dt.timestamp_millis())
+        .ok_or_else(|| anyhow!("invalid local time '{raw}'"))
+}
+
+// Append-only JSONL log of runtime events, pruned to the retention window.
+pub struct EventLog {
+    path: PathBuf,
+    retention_days: u32,
+    lock: Mutex<()>,
+    last_prune_ms: AtomicI64,
+}
+
+impl EventLog {
+    pub fn new(retention_days: u32) -> Result<Self> {
+        Ok(Self::from_path(
+            get_data_path()?.join("events").join("events.jsonl"),
+            retention_days,
+        ))
+    }
+
+    pub fn from_path(path: PathBuf, retention_days: u32) -> Self {
+        Self {
+            path,
+            retention_days,
+            lock: Mutex::new(()),
+            last_prune_ms: AtomicI64::new(0),
+        }
+    }
+
+    pub fn append(&self, event: &Event) -> Result<()> {
+        if event.at_ms - self.last_prune_ms.load(Ordering::Relaxed) > PRUNE_INTERVAL_MS {
+            self.prune(event.at_ms)?;
+        }
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        locate(&self.path).append(&format!("{}\n", serde_json::to_string(event)?))
+    }
+
+    fn load(&self) -> Result<Vec<Event>> {
+        let Some(raw) = locate(&self.path).read()? else {
+            return Ok(Vec::new());
+        };
+        Ok(raw
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    pub fn prune(&self, now_ms: i64) -> Result<usize> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.last_prune_ms.store(now_ms, Ordering::Relaxed);
+        if self.retention_days == 0 {
+            return Ok(0);
+        }
+        let cutoff = now_ms - i64::from(self.retention_days) * DAY_MS;
+        let events = self.load()?;
+        let before = events.len();
+        let kept = events
+            .into_iter()
+            .filter(|event| event.at_ms >= cutoff)
+            .collect::<Vec<_>>();
+        let removed = before - kept.len();
+        if removed > 0 {
+            let body = kept
+                .iter()
+                .filter_map(|event| serde_json::to_string(event).ok())
+                .map(|line| line + "\n")
+                .collect::<String>();
+            locate(&self.path).write(&body)?;
+        }
+        Ok(removed)
+    }
+
+    // Newest last; `limit` keeps the most recent matches (0 means all).
+    pub fn query(&self, filter: &EventFilter) -> Result<Vec<Event>> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut events = self
+            .load()?
+            .into_iter()
+            .filter(|event| filter.matches(event))
+            .collect::<Vec<_>>();
+        if filter.limit > 0 && events.len() > filter.limit {
+            events.drain(..events.len() - filter.limit);
+        }
+        Ok(events)
+    }
+}
+
+// Turns on event recording for this process; until then `record` is a no-op.
+pub fn init(retention_days: u32) {
+    match EventLog::new(retention_days) {
+        Ok(log) => {
+            let _ = EVENT_LOG.set(log);
+        }
+        Err(err) => eprintln!("Warning: event log disabled: {err}"),
+    }
+}
+
+// Also hands every recorded event to `sink`, e.g. the Windows event log
+// while the gateway runs as a service. Only the first sink is kept.
+pub fn mirror(sink: impl Fn(&Event) + Send + Sync + 'static) {
+    let _ = MIRROR.set(Box::new(sink));
+}
+
+pub fn record(level: EventLevel, kind: &str, channel: Option<&str>, message: impl Into<String>) {
+    let log = EVENT_LOG.get();
+    let mirror = MIRROR.get();
+    if log.is_none() && mirror.is_none() {
+        return;
+    }
+    let event = Event {
+        at_ms: Utc::now().timestamp_millis(),
+        level,
+        kind: kind.to_string(),
+        channel: channel.map(ToOwned::to_owned),
+        message: message.into(),
+    };
+    if let Some(sink) = mirror {
+        sink(&event);
+    }
+    let Some(log) = log else {
+        return;
+    };
+    if let Err(err) = log.append(&event) {
+        eprintln!("Warning: failed to record event: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(at_ms: i64, level: EventLevel, kind: &str, channel: Option<&str>) -> Event {
+        Event {
+            at_ms,
+            level,
+            kind: kind.to_string(),
+            channel: channel.map(ToOwned::to_owned),
+            message: "x".to_string(),
+        }
+    }
+
+    #[test]
+    fn filters_and_prunes_by_retention() {
+        let dir = std::env::temp_dir().join(format!("nanobot-events-{}", uuid::Uuid::new_v4()));
+        let log = EventLog::from_path(dir.join("events.jsonl"), 7);
+        let now = Utc::now().timestamp_millis();
+        log.prune(now).expect("prune empty");
+        log.append(&event(now - 10 * DAY_MS, EventLevel::Error, "cron", None))
+            .expect("append");
+        log.append(&event(
+            now - 60_000,
+            EventLevel::Info,
+            "channel",
+            Some("telegram"),
+        ))
+        .expect("append");
+        log.append(&event(now, EventLevel::Warn, "channel", Some("slack")))
+            .expect("append");
+
+        let warn_and_up = log
+            .query(&EventFilter {
+                min_level: Some(EventLevel::Warn),
+                ..Default::default()
+            })
+            .expect("query");
+        assert_eq!(warn_and_up.len(), 2);
+
+        let telegram = log
+            .query(&EventFilter {
+                since_ms: Some(parse_since("2h", now).expect("since")),
+                channel: Some("telegram".to_string()),
+                ..Default::default()
+            })
+            .expect("query");
+        assert_eq!(telegram.len(), 1);
+
+        assert_eq!(log.prune(now).expect("prune"), 1);
+        assert_eq!(log.query(&EventFilter::default()).expect("query").len(), 2);
+        assert!(parse_since("3w", now).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn since_rejects_ages_that_overflow() {
+        let now = 1_700_000_000_000;
+        assert_eq!(
+            parse_since("30m", now).expect("since"),
+            now - 30 * 60 * 1000
+        );
+        assert!(parse_since("9223372036854775807d", now).is_err());
+        assert!(parse_since("9223372036854776s", now).is_err());
+        assert!(parse_since("-9223372036854775807s", now).is_err());
+        assert!(parse_since("-9223372036854775s", now).is_err());
+    }
+}
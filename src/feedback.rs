@@ -0,0 +1,411 @@
+use crate::bus::OutboundMessage;
+use crate::utils::get_data_path;
+use anyhow::Result;
+use chrono::{Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+const MAX_TURNS: usize = 500;
+const MAX_EXCERPT_CHARS: usize = 2000;
+
+// Channels and the agent loop write the same file from different tasks.
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserRating {
+    pub user: String,
+    pub rating: Rating,
+    pub at_ms: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TurnRecord {
+    pub id: String,
+    pub session_key: String,
+    pub channel: String,
+    pub chat_id: String,
+    pub model: String,
+    pub question: String,
+    pub answer: String,
+    #[serde(default)]
+    pub tools_used: Vec<String>,
+    #[serde(default)]
+    pub message_ids: Vec<String>,
+    #[serde(default)]
+    pub ratings: Vec<UserRating>,
+    pub at_ms: i64,
+}
+
+impl TurnRecord {
+    pub fn new(
+        session_key: &str,
+        channel: &str,
+        chat_id: &str,
+        model: &str,
+        question: &str,
+        answer: &str,
+        tools_used: &[String],
+    ) -> Self {
+        let mut tools_used = tools_used.to_vec();
+        tools_used.sort();
+        tools_used.dedup();
+        Self {
+            id: Uuid::new_v4().simple().to_string()[..12].to_string(),
+            session_key: session_key.to_string(),
+            channel: channel.to_string(),
+            chat_id: chat_id.to_string(),
+            model: model.to_string(),
+            question: excerpt(question),
+            answer: excerpt(answer),
+            tools_used,
+            message_ids: Vec::new(),
+            ratings: Vec::new(),
+            at_ms: Utc::now().timestamp_millis(),
+        }
+    }
+
+    pub fn score(&self) -> (usize, usize) {
+        let up = self
+            .ratings
+            .iter()
+            .filter(|r| r.rating == Rating::Up)
+            .count();
+        (up, self.ratings.len() - up)
+    }
+
+    // Answers are grouped by the tools they relied on, which is the most
+    // useful proxy for "kind of answer" we have.
+    pub fn kind(&self) -> String {
+        if self.tools_used.is_empty() {
+            "no tools".to_string()
+        } else {
+            self.tools_used.join("+")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeedbackFile {
+    version: u32,
+    turns: Vec<TurnRecord>,
+}
+
+impl Default for FeedbackFile {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            turns: Vec::new(),
+        }
+    }
+}
+
+fn excerpt(text: &str) -> String {
+    let mut out = text.chars().take(MAX_EXCERPT_CHARS).collect::<String>();
+    if out.len() < text.len() {
+        out.push_str("...");
+    }
+    out
+}
+
+pub fn classify_reaction(raw: &str) -> Option<Rating> {
+    let name = raw.trim().trim_matches(':');
+    // Slack appends skin tones as "::skin-tone-N"; emoji carry modifier code points.
+    let name = name.split("::").next().unwrap_or(name);
+    let base = name
+        .chars()
+        .filter(|c| !('\u{1F3FB}'..='\u{1F3FF}').contains(c) && *c != '\u{FE0F}')
+        .collect::<String>();
+    match base.as_str() {
+        "👍" | "+1" | "thumbsup" => Some(Rating::Up),
+        "👎" | "-1" | "thumbsdown" => Some(Rating::Down),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FeedbackStore {
+    path: PathBuf,
+}
+
+impl FeedbackStore {
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_path(
+            get_data_path()?.join("feedback").join("turns.json"),
+        ))
+    }
+
+    pub fn from_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn load(&self) -> Result<FeedbackFile> {
+        if !self.path.exists() {
+            return Ok(FeedbackFile::default());
+        }
+        let raw = std::fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&raw).unwrap_or_default())
+    }
+
+    fn save(&self, file: &FeedbackFile) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(file)?)?;
+        Ok(())
+    }
+
+    fn update<T>(&self, f: impl FnOnce(&mut FeedbackFile) -> T) -> Result<T> {
+        let _guard = FILE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.load()?;
+        let out = f(&mut file);
+        self.save(&file)?;
+        Ok(out)
+    }
+
+    pub fn turns(&self) -> Result<Vec<TurnRecord>> {
+        Ok(self.load()?.turns)
+    }
+
+    pub fn record_turn(&self, record: TurnRecord) -> Result<()> {
+        self.update(|file| {
+            file.turns.push(record);
+            // Rated turns are kept over unrated ones when trimming.
+            while file.turns.len() > MAX_TURNS {
+                let idx = file
+                    .turns
+                    .iter()
+                    .position(|t| t.ratings.is_empty())
+                    .unwrap_or(0);
+                file.turns.remove(idx);
+            }
+        })
+    }
+
+    pub fn link_message(&self, turn_id: &str, message_id: &str) -> Result<bool> {
+        self.update(|file| {
+            let Some(turn) = file.turns.iter_mut().find(|t| t.id == turn_id) else {
+                return false;
+            };
+            if !turn.message_ids.iter().any(|id| id == message_id) {
+                turn.message_ids.push(message_id.to_string());
+            }
+            true
+        })
+    }
+
+    // Sets (or with `None`, clears) one user's rating for the turn that
+    // produced the given bot message. Returns false for unknown messages.
+    pub fn rate_message(
+        &self,
+        channel: &str,
+        chat_id: &str,
+        message_id: &str,
+        user: &str,
+        rating: Option<Rating>,
+    ) -> Result<bool> {
+        self.update(|file| {
+            let Some(turn) = file.turns.iter_mut().rev().find(|t| {
+                t.channel == channel
+                    && t.chat_id == chat_id
+                    && t.message_ids.iter().any(|id| id == message_id)
+            }) else {
+                return false;
+            };
+            turn.ratings.retain(|r| r.user != user);
+            if let Some(rating) = rating {
+                turn.ratings.push(UserRating {
+                    user: user.to_string(),
+                    rating,
+                    at_ms: Utc::now().timestamp_millis(),
+                });
+            }
+            true
+        })
+    }
+}
+
+// Called by channels once a send reports the platform message id, so later
+// reactions on that message can be traced back to the turn.
+pub fn link_sent_message(msg: &OutboundMessage, message_id: &str) {
+    let Some(turn_id) = msg.metadata.get("turn_id").and_then(Value::as_str) else {
+        return;
+    };
+    if message_id.is_empty() {
+        return;
+    }
+    if let Err(err) = FeedbackStore::new().and_then(|s| s.link_message(turn_id, message_id)) {
+        eprintln!("Warning: failed to link feedback message: {err}");
+    }
+}
+
+pub fn record_reaction(
+    channel: &str,
+    chat_id: &str,
+    message_id: &str,
+    user: &str,
+    rating: Option<Rating>,
+) {
+    if let Err(err) = FeedbackStore::new()
+        .and_then(|s| s.rate_message(channel, chat_id, message_id, user, rating))
+    {
+        eprintln!("Warning: failed to record reaction: {err}");
+    }
+}
+
+fn tally<'a>(
+    turns: impl Iterator<Item = &'a TurnRecord>,
+    key: impl Fn(&TurnRecord) -> String,
+) -> Vec<(String, usize, usize)> {
+    let mut groups: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for turn in turns {
+        let (up, down) = turn.score();
+        let entry = groups.entry(key(turn)).or_default();
+        entry.0 += up;
+        entry.1 += down;
+    }
+    let mut rows = groups
+        .into_iter()
+        .map(|(name, (up, down))| (name, up, down))
+        .collect::<Vec<_>>();
+    rows.sort_by(|a, b| b.2.cmp(&a.2).then(a.1.cmp(&b.1)).then(a.0.cmp(&b.0)));
+    rows
+}
+
+pub fn render_report(turns: &[TurnRecord], limit: usize) -> String {
+    let rated = turns
+        .iter()
+        .filter(|t| !t.ratings.is_empty())
+        .collect::<Vec<_>>();
+    let (up, down) = rated.iter().fold((0, 0), |acc, t| {
+        let (u, d) = t.score();
+        (acc.0 + u, acc.1 + d)
+    });
+    let mut lines = vec![format!(
+        "Feedback: {up} 👍 / {down} 👎 across {} rated of {} recorded turns",
+        rated.len(),
+        turns.len()
+    )];
+    if rated.is_empty() {
+        lines.push("No reactions recorded yet.".to_string());
+        return lines.join("\n");
+    }
+
+    let sections = [
+        (
+            "By answer kind",
+            tally(rated.iter().copied(), TurnRecord::kind),
+        ),
+        (
+            "By model",
+            tally(rated.iter().copied(), |t| t.model.clone()),
+        ),
+        (
+            "By channel",
+            tally(rated.iter().copied(), |t| t.channel.clone()),
+        ),
+    ];
+    for (title, rows) in sections {
+        lines.push(String::new());
+        lines.push(format!("{title}:"));
+        for (name, up, down) in rows {
+            lines.push(format!("- {name}: {up} 👍 / {down} 👎"));
+        }
+    }
+
+    let mut failing = rated
+        .iter()
+        .filter(|t| {
+            let (u, d) = t.score();
+            d > u
+        })
+        .collect::<Vec<_>>();
+    failing.sort_by_key(|t| std::cmp::Reverse(t.at_ms));
+    if !failing.is_empty() {
+        lines.push(String::new());
+        lines.push("Recent 👎 turns:".to_string());
+        for turn in failing.into_iter().take(limit) {
+            let when = Local
+                .timestamp_millis_opt(turn.at_ms)
+                .single()
+                .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "-".to_string());
+            let question = turn.question.replace('\n', " ");
+            let question = question.chars().take(120).collect::<String>();
+            lines.push(format!(
+                "- [{when}] {} ({}, {}): {question}",
+                turn.session_key,
+                turn.kind(),
+                turn.model
+            ));
+        }
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_emoji_and_slack_names() {
+        assert_eq!(classify_reaction("👍"), Some(Rating::Up));
+        assert_eq!(classify_reaction("👍🏽"), Some(Rating::Up));
+        assert_eq!(classify_reaction("+1::skin-tone-3"), Some(Rating::Up));
+        assert_eq!(classify_reaction("thumbsdown"), Some(Rating::Down));
+        assert_eq!(classify_reaction("eyes"), None);
+    }
+
+    #[test]
+    fn reactions_attach_to_linked_turns() {
+        let dir = std::env::temp_dir().join(format!("nanobot-feedback-{}", Uuid::new_v4()));
+        let store = FeedbackStore::from_path(dir.join("turns.json"));
+        let turn = TurnRecord::new(
+            "telegram:1",
+            "telegram",
+            "1",
+            "gpt-4o",
+            "weather?",
+            "sunny",
+            &["web_search".to_string()],
+        );
+        let id = turn.id.clone();
+        store.record_turn(turn).expect("record");
+        assert!(store.link_message(&id, "42").expect("link"));
+
+        assert!(
+            store
+                .rate_message("telegram", "1", "42", "u1", Some(Rating::Down))
+                .expect("rate")
+        );
+        assert!(
+            !store
+                .rate_message("telegram", "1", "43", "u1", Some(Rating::Up))
+                .expect("rate")
+        );
+        let turns = store.turns().expect("turns");
+        assert_eq!(turns[0].score(), (0, 1));
+        let report = render_report(&turns, 5);
+        assert!(report.contains("- web_search: 0 👍 / 1 👎"));
+        assert!(report.contains("weather?"));
+
+        store
+            .rate_message("telegram", "1", "42", "u1", None)
+            .expect("clear");
+        assert!(store.turns().expect("turns")[0].ratings.is_empty());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,573 @@
+use crate::session::SessionManager;
+use crate::storage::locate;
+use crate::utils::get_data_path;
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+const INDEX_VERSION: u32 = 1;
+// Source name of the workspace's HISTORY.md entries.
+pub const HISTORY_SOURCE: &str = "HISTORY.md";
+const SNIPPET_BEFORE: usize = 80;
+const SNIPPET_AFTER: usize = 200;
+// BM25 parameters.
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+const STOPWORDS: &[&str] = &[
+    "a", "about", "an", "and", "are", "as", "at", "be", "by", "did", "do", "does", "for", "from",
+    "had", "has", "have", "how", "i", "in", "is", "it", "me", "my", "of", "on", "or", "our", "so",
+    "that", "the", "this", "to", "was", "we", "were", "what", "when", "where", "which", "who",
+    "why", "with", "you", "your",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Doc {
+    // RFC 3339 for session messages, "YYYY-MM-DD HH:MM" for history entries.
+    at: String,
+    role: String,
+    text: String,
+    len: u32,
+}
+
+// One session (or HISTORY.md): its documents and term -> (doc, frequency)
+// postings. Rebuilt only when the source's content changes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourceIndex {
+    fingerprint: u64,
+    docs: Vec<Doc>,
+    postings: HashMap<String, Vec<(u32, u32)>>,
+}
+
+impl SourceIndex {
+    fn build(docs: Vec<Doc>, fingerprint: u64) -> Self {
+        let mut postings: HashMap<String, Vec<(u32, u32)>> = HashMap::new();
+        for (idx, doc) in docs.iter().enumerate() {
+            let mut counts: HashMap<String, u32> = HashMap::new();
+            for token in tokens(&doc.text) {
+                *counts.entry(token).or_default() += 1;
+            }
+            for (token, count) in counts {
+                postings.entry(token).or_default().push((idx as u32, count));
+            }
+        }
+        Self {
+            fingerprint,
+            docs,
+            postings,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexFile {
+    version: u32,
+    sources: BTreeMap<String, SourceIndex>,
+}
+
+// Size and modification time of HISTORY.md when it was last indexed.
+type FileStamp = (u64, Option<SystemTime>);
+
+fn file_stamp(path: &Path) -> FileStamp {
+    std::fs::metadata(path)
+        .map(|meta| (meta.len(), meta.modified().ok()))
+        .unwrap_or((0, None))
+}
+
+// The index as this process last brought it up to date.
+struct IndexState {
+    file: IndexFile,
+    // Session manager generation the index has caught up with.
+    generation: u64,
+    history: Option<FileStamp>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    pub text: String,
+    // Only this session (or "HISTORY.md").
+    pub source: Option<String>,
+    pub since_ms: Option<i64>,
+    pub limit: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub source: String,
+    pub at: String,
+    pub role: String,
+    pub snippet: String,
+    pub score: f64,
+}
+
+impl SearchHit {
+    pub fn render(&self) -> String {
+        let at = self
+            .at
+            .chars()
+            .take(16)
+            .collect::<String>()
+            .replace('T', " ");
+        let role = if self.role.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", self.role.to_ascii_uppercase())
+        };
+        format!("[{at}] {}{role}: {}", self.source, self.snippet)
+    }
+}
+
+// Full-text index over saved session messages and the workspace's
+// HISTORY.md, persisted between runs. The first search in a process checks
+// every session against the saved index; after that a search only re-indexes
+// the sessions the session manager has saved since, and HISTORY.md when its
+// size or modification time changed.
+pub struct HistoryIndex {
+    path: PathBuf,
+    sessions: Arc<SessionManager>,
+    history_file: PathBuf,
+    index: Mutex<Option<IndexState>>,
+}
+
+impl HistoryIndex {
+    pub fn new(sessions: Arc<SessionManager>, workspace: &Path) -> Result<Self> {
+        Ok(Self::from_path(
+            get_data_path()?.join("search").join("index.json"),
+            sessions,
+            workspace.join("memory").join("HISTORY.md"),
+        ))
+    }
+
+    pub fn from_path(path: PathBuf, sessions: Arc<SessionManager>, history_file: PathBuf) -> Self {
+        Self {
+            path,
+            sessions,
+            history_file,
+            index: Mutex::new(None),
+        }
+    }
+
+    fn load(&self) -> IndexFile {
+        locate(&self.path)
+            .read()
+            .ok()
+            .flatten()
+            .and_then(|raw| serde_json::from_str::<IndexFile>(&raw).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or(IndexFile {
+                version: INDEX_VERSION,
+                sources: BTreeMap::new(),
+            })
+    }
+
+    // Brings the index up to date; returns how many sources were re-indexed
+    // or dropped.
+    pub fn refresh(&self) -> Result<usize> {
+        let mut guard = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        let mut changed = 0;
+        let state = match guard.as_mut() {
+            Some(state) => state,
+            None => {
+                let generation = self.sessions.generation();
+                let mut file = self.load();
+                changed += self.reconcile(&mut file)?;
+                guard.insert(IndexState {
+                    file,
+                    generation,
+                    history: None,
+                })
+            }
+        };
+
+        let (generation, keys) = self.sessions.changed_since(state.generation);
+        for key in keys {
+            let updated = match self.sessions.load_session(&key) {
+                Ok(session) => update_source(&mut state.file, key, session_docs(&session.messages)),
+                Err(_) => state.file.sources.remove(&key).is_some(),
+            };
+            changed += usize::from(updated);
+        }
+        state.generation = generation;
+
+        let stamp = file_stamp(&self.history_file);
+        if state.history != Some(stamp) {
+            let history = std::fs::read_to_string(&self.history_file).unwrap_or_default();
+            let docs = history_docs(&history);
+            changed += usize::from(update_source(
+                &mut state.file,
+                HISTORY_SOURCE.to_string(),
+                docs,
+            ));
+            state.history = Some(stamp);
+        }
+
+        if changed > 0 {
+            locate(&self.path).write(&serde_json::to_string(&state.file)?)?;
+        }
+        Ok(changed)
+    }
+
+    // Checks every saved session against the index, for sessions written
+    // before this process started or by another one.
+    fn reconcile(&self, file: &mut IndexFile) -> Result<usize> {
+        let mut changed = 0;
+        let mut keys = HashSet::from([HISTORY_SOURCE.to_string()]);
+        for key in self.sessions.list_session_keys()? {
+            let Ok(session) = self.sessions.load_session(&key) else {
+                continue;
+            };
+            let docs = session_docs(&session.messages);
+            keys.insert(session.key.clone());
+            changed += usize::from(update_source(file, session.key, docs));
+        }
+        file.sources.retain(|key, _| {
+            let keep = keys.contains(key);
+            changed += usize::from(!keep);
+            keep
+        });
+        Ok(changed)
+    }
+
+    // Best matches first, ranked by BM25 over every indexed source.
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>> {
+        self.refresh()?;
+        let guard = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(index) = guard.as_ref().map(|state| &state.file) else {
+            return Ok(Vec::new());
+        };
+        let terms = tokens(&query.text)
+            .into_iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sources = index
+            .sources
+            .iter()
+            .filter(|(key, _)| query.source.as_ref().is_none_or(|only| only == *key))
+            .collect::<Vec<_>>();
+        let total_docs = sources
+            .iter()
+            .map(|(_, source)| source.docs.len())
+            .sum::<usize>();
+        if total_docs == 0 {
+            return Ok(Vec::new());
+        }
+        let avg_len = sources
+            .iter()
+            .flat_map(|(_, source)| source.docs.iter().map(|doc| doc.len as f64))
+            .sum::<f64>()
+            / total_docs as f64;
+
+        let mut scores: HashMap<(&str, u32), f64> = HashMap::new();
+        for term in &terms {
+            let df = sources
+                .iter()
+                .filter_map(|(_, source)| source.postings.get(term))
+                .map(Vec::len)
+                .sum::<usize>() as f64;
+            if df == 0.0 {
+                continue;
+            }
+            let idf = (1.0 + (total_docs as f64 - df + 0.5) / (df + 0.5)).ln();
+            for (key, source) in &sources {
+                for &(doc_idx, tf) in source.postings.get(term).into_iter().flatten() {
+                    let len = source.docs[doc_idx as usize].len as f64;
+                    let tf = tf as f64;
+                    let score =
+                        idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * len / avg_len.max(1.0)));
+                    *scores.entry((key.as_str(), doc_idx)).or_default() += score;
+                }
+            }
+        }
+
+        let mut hits = scores
+            .into_iter()
+            .filter_map(|((key, doc_idx), score)| {
+                let doc = &index.sources[key].docs[doc_idx as usize];
+                // Entries without a readable time are kept rather than guessed at.
+                if let Some(since) = query.since_ms
+                    && parse_at(&doc.at).is_some_and(|at| at < since)
+                {
+                    return None;
+                }
+                Some(SearchHit {
+                    source: key.to_string(),
+                    at: doc.at.clone(),
+                    role: doc.role.clone(),
+                    snippet: snippet(&doc.text, &terms),
+                    score,
+                })
+            })
+            .collect::<Vec<_>>();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| b.at.cmp(&a.at)));
+        hits.truncate(query.limit.max(1));
+        Ok(hits)
+    }
+}
+
+// Rebuilds one source unless its content is what was indexed already.
+fn update_source(file: &mut IndexFile, key: String, docs: Vec<Doc>) -> bool {
+    let fingerprint = fingerprint(&docs);
+    if file
+        .sources
+        .get(&key)
+        .is_some_and(|source| source.fingerprint == fingerprint)
+    {
+        return false;
+    }
+    file.sources
+        .insert(key, SourceIndex::build(docs, fingerprint));
+    true
+}
+
+fn session_docs(messages: &[Value]) -> Vec<Doc> {
+    messages
+        .iter()
+        .filter_map(|message| {
+            let text = message.get("content").and_then(Value::as_str)?.trim();
+            let role = message.get("role").and_then(Value::as_str)?;
+            (!text.is_empty() && matches!(role, "user" | "assistant")).then(|| {
+                doc(
+                    message
+                        .get("timestamp")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default(),
+                    role,
+                    text,
+                )
+            })
+        })
+        .collect()
+}
+
+fn doc(at: &str, role: &str, text: &str) -> Doc {
+    Doc {
+        at: at.to_string(),
+        role: role.to_string(),
+        text: text.to_string(),
+        len: tokens(text).len() as u32,
+    }
+}
+
+// HISTORY.md entries are blank-line separated paragraphs that start with
+// "[YYYY-MM-DD HH:MM]".
+fn history_docs(history: &str) -> Vec<Doc> {
+    history
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let at = entry
+                .strip_prefix('[')
+                .and_then(|rest| rest.split_once(']'))
+                .map(|(at, _)| at)
+                .unwrap_or_default();
+            doc(at, "", entry)
+        })
+        .collect()
+}
+
+fn fingerprint(docs: &[Doc]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for doc in docs {
+        (&doc.at, &doc.role, &doc.text).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+fn parse_at(raw: &str) -> Option<i64> {
+    if let Ok(at) = DateTime::parse_from_rfc3339(raw) {
+        return Some(at.timestamp_millis());
+    }
+    let naive = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M").ok()?;
+    Some(
+        Local
+            .from_local_datetime(&naive)
+            .earliest()?
+            .timestamp_millis(),
+    )
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3040}'..='\u{30FF}' | '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{AC00}'..='\u{D7AF}')
+}
+
+// Lowercased words without stopwords or a plural "s"; runs of CJK text,
+// which has no spaces, become overlapping character pairs.
+fn tokens(text: &str) -> Vec<String> {
+    fn flush_word(word: &mut String, out: &mut Vec<String>) {
+        if !word.is_empty() && !STOPWORDS.contains(&word.as_str()) {
+            if word.len() > 3 && word.ends_with('s') && !word.ends_with("ss") {
+                word.pop();
+            }
+            out.push(std::mem::take(word));
+        }
+        word.clear();
+    }
+    fn flush_cjk(run: &mut Vec<char>, out: &mut Vec<String>) {
+        if run.len() == 1 {
+            out.push(run[0].to_string());
+        }
+        out.extend(run.windows(2).map(|pair| pair.iter().collect::<String>()));
+        run.clear();
+    }
+    let mut out = Vec::new();
+    let mut word = String::new();
+    let mut run = Vec::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if is_cjk(c) {
+            flush_word(&mut word, &mut out);
+            run.push(c);
+        } else if c.is_alphanumeric() {
+            flush_cjk(&mut run, &mut out);
+            word.push(c);
+        } else {
+            flush_word(&mut word, &mut out);
+            flush_cjk(&mut run, &mut out);
+        }
+    }
+    flush_word(&mut word, &mut out);
+    flush_cjk(&mut run, &mut out);
+    out
+}
+
+// The text around the first matching term, on one line.
+fn snippet(text: &str, terms: &[String]) -> String {
+    let chars = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let chars = chars.chars().collect::<Vec<_>>();
+    let lowered = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect::<Vec<_>>();
+    let found = terms
+        .iter()
+        .filter_map(|term| {
+            let term = term.chars().collect::<Vec<_>>();
+            lowered
+                .windows(term.len())
+                .position(|window| window == term)
+        })
+        .min()
+        .unwrap_or(0);
+    let start = found.saturating_sub(SNIPPET_BEFORE);
+    let end = (found + SNIPPET_AFTER).min(chars.len());
+    let mut out = chars[start..end].iter().collect::<String>();
+    if start > 0 {
+        out.insert(0, '…');
+    }
+    if end < chars.len() {
+        out.push('…');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_matching_messages_and_history_and_reindexes_changes() {
+        let dir = std::env::temp_dir().join(format!("nanobot-fulltext-{}", uuid::Uuid::new_v4()));
+        let sessions = Arc::new(SessionManager::from_dir(dir.join("sessions")).expect("sessions"));
+        let mut chat = sessions.get_or_create("telegram:1");
+        chat.add_message("user", "The boiler is making noises again");
+        chat.add_message(
+            "assistant",
+            "Let's book the plumber for the boiler service next week.",
+        );
+        chat.add_message("user", "Thanks, what about dinner?");
+        sessions.save(&chat).expect("save");
+        let history_file = dir.join("HISTORY.md");
+        std::fs::write(
+            &history_file,
+            "[2026-09-01 10:00] Decided to replace the boiler instead of repairing it.\n\n[2026-09-02 11:00] 讨论了周末的旅行计划。\n\n",
+        )
+        .expect("history");
+        let index = HistoryIndex::from_path(
+            dir.join("index.json"),
+            sessions.clone(),
+            history_file.clone(),
+        );
+
+        let query = |text: &str| SearchQuery {
+            text: text.to_string(),
+            limit: 5,
+            ..SearchQuery::default()
+        };
+        let hits = index
+            .search(&query("what did we decide about the boiler?"))
+            .expect("search");
+        assert_eq!(hits.len(), 3);
+        assert!(hits.iter().all(|hit| hit.snippet.contains("boiler")));
+        let entry = hits
+            .iter()
+            .find(|hit| hit.source == HISTORY_SOURCE)
+            .expect("history entry");
+        assert!(entry.snippet.starts_with("[2026-09-01 10:00] Decided"));
+        // Shorter messages rank higher for the same match.
+        assert_eq!(hits[0].snippet, "The boiler is making noises again");
+
+        let cjk = index.search(&query("旅行")).expect("search");
+        assert_eq!(cjk.len(), 1);
+        assert_eq!(cjk[0].at, "2026-09-02 11:00");
+
+        let only_chat = SearchQuery {
+            source: Some("telegram:1".to_string()),
+            ..query("boilers")
+        };
+        assert_eq!(index.search(&only_chat).expect("search").len(), 2);
+        let since = SearchQuery {
+            since_ms: parse_at("2026-09-01 12:00"),
+            source: Some(HISTORY_SOURCE.to_string()),
+            ..query("boiler")
+        };
+        assert!(index.search(&since).expect("search").is_empty());
+
+        // Unchanged sources are not re-indexed; a saved session is, without
+        // rescanning the others.
+        assert_eq!(index.refresh().expect("refresh"), 0);
+        chat.add_message("user", "The thermostat arrived");
+        sessions.save(&chat).expect("save");
+        assert_eq!(index.refresh().expect("refresh"), 1);
+        assert_eq!(index.search(&query("thermostat")).expect("search").len(), 1);
+        std::fs::write(&history_file, "[2026-09-03 09:00] Thermostat fitted.\n\n")
+            .expect("history");
+        assert_eq!(index.refresh().expect("refresh"), 1);
+        assert!(index.search(&query("旅行")).expect("search").is_empty());
+
+        // Sessions another process wrote are picked up when the index is
+        // next opened, by checking everything against the saved index.
+        let other = SessionManager::from_dir(dir.join("sessions")).expect("sessions");
+        let mut elsewhere = other.get_or_create("slack:C1");
+        elsewhere.add_message("user", "Thermostat manual is in the drawer");
+        other.save(&elsewhere).expect("save");
+        assert_eq!(index.refresh().expect("refresh"), 0);
+        let reopened = HistoryIndex::from_path(dir.join("index.json"), sessions, history_file);
+        assert_eq!(reopened.refresh().expect("refresh"), 1);
+        assert_eq!(
+            reopened.search(&query("thermostat")).expect("search").len(),
+            3
+        );
+        assert!(
+            reopened
+                .search(&query("the and"))
+                .expect("search")
+                .is_empty()
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
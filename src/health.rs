@@ -1,8 +1,12 @@
 use crate::VERSION;
+use crate::channels::manager::{FLAP_WINDOW_MS, flapping_channels};
 use crate::config::{Config, get_config_path, providers_status, save_config};
+use crate::events::{EventFilter, EventLog};
+use crate::pause::PauseSwitch;
+use crate::storage::locate;
 use crate::utils::{get_data_path, get_workspace_path};
 use anyhow::{Result, anyhow};
-use chrono::Local;
+use chrono::{Local, Utc};
 use semver::Version;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
@@ -129,9 +133,9 @@ fn enabled_channels(config: &Config) -> Vec<&'static str> {
 
 fn cron_jobs_count(data_dir: &Path) -> usize {
     let path = data_dir.join("cron").join("jobs.json");
-    let raw = match std::fs::read_to_string(path) {
-        Ok(v) => v,
-        Err(_) => return 0,
+    let raw = match locate(&path).read() {
+        Ok(Some(v)) => v,
+        _ => return 0,
     };
     let value: serde_json::Value = match serde_json::from_str(&raw) {
         Ok(v) => v,
@@ -144,6 +148,19 @@ fn cron_jobs_count(data_dir: &Path) -> usize {
         .unwrap_or(0)
 }
 
+// Read from the event log, since the supervisor runs in the gateway process.
+fn recent_flapping(config: &Config) -> Vec<(String, usize)> {
+    let filter = EventFilter {
+        since_ms: Some(Utc::now().timestamp_millis() - FLAP_WINDOW_MS),
+        kind: Some("channel".to_string()),
+        ..Default::default()
+    };
+    EventLog::new(config.events.retention_days)
+        .and_then(|log| log.query(&filter))
+        .map(|events| flapping_channels(&events))
+        .unwrap_or_default()
+}
+
 fn has_any_provider(config: &Config) -> bool {
     providers_status(config)
         .values()
@@ -153,29 +170,131 @@ fn has_any_provider(config: &Config) -> bool {
 
 fn check_workspace_files(workspace: &Path) -> (bool, Vec<String>) {
     let required = [
-        workspace.join("AGENTS.md"),
-        workspace.join("SOUL.md"),
-        workspace.join("USER.md"),
-        workspace.join("HEARTBEAT.md"),
-        workspace.join("memory").join("MEMORY.md"),
-        workspace.join("memory").join("HISTORY.md"),
+        "AGENTS.md",
+        "SOUL.md",
+        "USER.md",
+        "HEARTBEAT.md",
+        "memory/MEMORY.md",
+        "memory/HISTORY.md",
     ];
     let mut missing = Vec::new();
-    for path in required {
-        if !path.exists() {
-            missing.push(path.display().to_string());
+    for name in required {
+        if !workspace.join(name).exists() {
+            missing.push(name.to_string());
         }
     }
     (missing.is_empty(), missing)
 }
 
+// The default model needs a key of its own; Bedrock signs with AWS
+// credentials instead.
+fn has_model_key(config: &Config) -> bool {
+    let model = &config.agents.defaults.model;
+    model
+        .strip_prefix("litellm/")
+        .unwrap_or(model)
+        .starts_with("bedrock/")
+        || config.get_api_key(Some(model)).is_some()
+}
+
+// Config keys an enabled channel cannot connect without, as
+// "channels.<name>.<key>".
+fn missing_channel_credentials(config: &Config) -> Vec<String> {
+    let c = &config.channels;
+    let mut missing = Vec::new();
+    let mut require = |enabled: bool, channel: &str, fields: &[(&str, &str)]| {
+        if !enabled {
+            return;
+        }
+        for (key, value) in fields {
+            if value.trim().is_empty() {
+                missing.push(format!("channels.{channel}.{key}"));
+            }
+        }
+    };
+    require(
+        c.telegram.enabled,
+        "telegram",
+        &[("token", &c.telegram.token)],
+    );
+    require(c.discord.enabled, "discord", &[("token", &c.discord.token)]);
+    require(
+        c.whatsapp.enabled,
+        "whatsapp",
+        &[("bridgeUrl", &c.whatsapp.bridge_url)],
+    );
+    require(
+        c.feishu.enabled,
+        "feishu",
+        &[
+            ("appId", &c.feishu.app_id),
+            ("appSecret", &c.feishu.app_secret),
+        ],
+    );
+    require(
+        c.mochat.enabled,
+        "mochat",
+        &[("clawToken", &c.mochat.claw_token)],
+    );
+    require(
+        c.dingtalk.enabled,
+        "dingtalk",
+        &[
+            ("clientId", &c.dingtalk.client_id),
+            ("clientSecret", &c.dingtalk.client_secret),
+        ],
+    );
+    require(
+        c.email.enabled,
+        "email",
+        &[
+            ("imapHost", &c.email.imap_host),
+            ("imapUsername", &c.email.imap_username),
+            ("smtpHost", &c.email.smtp_host),
+        ],
+    );
+    // OAuth mailboxes log in with a token instead of passwords.
+    require(
+        c.email.enabled && c.email.oauth.is_none(),
+        "email",
+        &[
+            ("imapPassword", &c.email.imap_password),
+            ("smtpPassword", &c.email.smtp_password),
+        ],
+    );
+    require(
+        c.slack.enabled,
+        "slack",
+        &[("botToken", &c.slack.bot_token)],
+    );
+    require(
+        c.slack.enabled && c.slack.mode == "socket",
+        "slack",
+        &[("appToken", &c.slack.app_token)],
+    );
+    require(
+        c.qq.enabled,
+        "qq",
+        &[("appId", &c.qq.app_id), ("secret", &c.qq.secret)],
+    );
+    if c.email.enabled && !c.email.consent_granted {
+        missing.push("channels.email.consentGranted".to_string());
+    }
+    missing
+}
+
 pub fn collect_health(config: &Config) -> Result<HealthReport> {
     let config_path = get_config_path()?;
     let data_path = get_data_path()?;
     let workspace = config.workspace_path();
     let channels = enabled_channels(config);
     let cron_count = cron_jobs_count(&data_path);
+    let flapping = recent_flapping(config);
     let (workspace_ok, missing_workspace_files) = check_workspace_files(&workspace);
+    let model_key = has_model_key(config);
+    let missing_credentials = missing_channel_credentials(config);
+    let now_ms = Utc::now().timestamp_millis();
+    let paused = PauseSwitch::new()?.state_at(now_ms);
     let checks = vec![
         HealthCheck {
             id: "config.file".to_string(),
@@ -195,16 +314,20 @@ pub fn collect_health(config: &Config) -> Result<HealthReport> {
         HealthCheck {
             id: "workspace.dir".to_string(),
             label: "Workspace directory".to_string(),
+            // The gateway and agent create a missing workspace themselves.
             level: if workspace.exists() {
                 CheckLevel::Ok
             } else {
-                CheckLevel::Fail
+                CheckLevel::Warn
             },
             detail: format!("{}", workspace.display()),
             fix_hint: if workspace.exists() {
                 None
             } else {
-                Some("Run `nanobot-rs onboard` or `nanobot-rs doctor --fix`.".to_string())
+                Some(
+                    "Created on first start; run `nanobot-rs doctor --fix` to add the baseline files now."
+                        .to_string(),
+                )
             },
         },
         HealthCheck {
@@ -218,7 +341,7 @@ pub fn collect_health(config: &Config) -> Result<HealthReport> {
             detail: if workspace_ok {
                 "required files present".to_string()
             } else {
-                format!("missing {} file(s)", missing_workspace_files.len())
+                format!("missing {}", missing_workspace_files.join(", "))
             },
             fix_hint: if workspace_ok {
                 None
@@ -229,16 +352,25 @@ pub fn collect_health(config: &Config) -> Result<HealthReport> {
         HealthCheck {
             id: "provider.api".to_string(),
             label: "Provider API credentials".to_string(),
-            level: if has_any_provider(config) {
+            level: if model_key {
                 CheckLevel::Ok
             } else {
                 CheckLevel::Fail
             },
-            detail: "at least one providers.*.apiKey".to_string(),
-            fix_hint: if has_any_provider(config) {
+            detail: if model_key {
+                format!("key for {}", config.agents.defaults.model)
+            } else if has_any_provider(config) {
+                format!("no key matches {}", config.agents.defaults.model)
+            } else {
+                "no providers.*.apiKey set".to_string()
+            },
+            fix_hint: if model_key {
                 None
             } else {
-                Some("Set providers.*.apiKey in ~/.nanobot/config.json.".to_string())
+                Some(
+                    "Set providers.<name>.apiKey for the default model in ~/.nanobot/config.json."
+                        .to_string(),
+                )
             },
         },
         HealthCheck {
@@ -279,6 +411,70 @@ pub fn collect_health(config: &Config) -> Result<HealthReport> {
                 None
             },
         },
+        HealthCheck {
+            id: "channels.credentials".to_string(),
+            label: "Channel credentials".to_string(),
+            level: if missing_credentials.is_empty() {
+                CheckLevel::Ok
+            } else {
+                CheckLevel::Warn
+            },
+            detail: if missing_credentials.is_empty() {
+                "enabled channels are configured".to_string()
+            } else {
+                format!("missing {}", missing_credentials.join(", "))
+            },
+            fix_hint: if missing_credentials.is_empty() {
+                None
+            } else {
+                Some(
+                    "Fill these in ~/.nanobot/config.json or disable the channel; it cannot connect without them."
+                        .to_string(),
+                )
+            },
+        },
+        HealthCheck {
+            id: "channels.flapping".to_string(),
+            label: "Channel restarts (last hour)".to_string(),
+            level: if flapping.is_empty() {
+                CheckLevel::Ok
+            } else {
+                CheckLevel::Warn
+            },
+            detail: if flapping.is_empty() {
+                "no flapping channels".to_string()
+            } else {
+                flapping
+                    .iter()
+                    .map(|(channel, count)| format!("{channel} restarted {count}x"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            },
+            fix_hint: if flapping.is_empty() {
+                None
+            } else {
+                Some(
+                    "See `nanobot-rs events --kind channel --since 1h` for disconnect reasons."
+                        .to_string(),
+                )
+            },
+        },
+        HealthCheck {
+            id: "proactive.paused".to_string(),
+            label: "Heartbeat and scheduled jobs".to_string(),
+            level: if paused.is_some() {
+                CheckLevel::Warn
+            } else {
+                CheckLevel::Ok
+            },
+            detail: paused
+                .as_ref()
+                .map(|state| state.describe(now_ms))
+                .unwrap_or_else(|| "active".to_string()),
+            fix_hint: paused
+                .as_ref()
+                .map(|_| "Run `nanobot-rs resume` or send /resume when you are back.".to_string()),
+        },
         HealthCheck {
             id: "cron.jobs".to_string(),
             label: "Scheduled jobs".to_string(),
@@ -302,6 +498,58 @@ pub fn collect_health(config: &Config) -> Result<HealthReport> {
     })
 }
 
+// Failures the gateway cannot run with. A missing config file only means
+// defaults, which the other checks judge on their own.
+pub fn startup_blockers(report: &HealthReport) -> Vec<&HealthCheck> {
+    report
+        .checks
+        .iter()
+        .filter(|check| matches!(check.level, CheckLevel::Fail) && check.id != "config.file")
+        .collect()
+}
+
+// Printed when the gateway starts: enabled channels, then one row per
+// check that is not OK, with its fix underneath.
+pub fn startup_banner(config: &Config, report: &HealthReport) -> String {
+    let channels = enabled_channels(config);
+    let mut out = format!(
+        "nanobot v{VERSION} - channels: {}\n",
+        if channels.is_empty() {
+            "none".to_string()
+        } else {
+            channels.join(", ")
+        }
+    );
+    let problems = report
+        .checks
+        .iter()
+        .filter(|check| !matches!(check.level, CheckLevel::Ok))
+        .collect::<Vec<_>>();
+    if problems.is_empty() {
+        out.push_str("All startup checks passed.\n");
+        return out;
+    }
+    let width = problems
+        .iter()
+        .map(|check| check.label.chars().count())
+        .max()
+        .unwrap_or(0);
+    for check in problems {
+        let tag = match check.level {
+            CheckLevel::Fail => "FAIL",
+            _ => "WARN",
+        };
+        out.push_str(&format!(
+            "  {tag:<4}  {:<width$}  {}\n",
+            check.label, check.detail
+        ));
+        if let Some(hint) = &check.fix_hint {
+            out.push_str(&format!("        {:<width$}  fix: {hint}\n", ""));
+        }
+    }
+    out
+}
+
 fn ensure_workspace_baseline(workspace: &Path, actions: &mut Vec<String>) -> Result<()> {
     std::fs::create_dir_all(workspace)?;
     let templates = [
@@ -477,3 +725,83 @@ pub async fn check_update(crate_name: &str) -> Result<UpdateReport> {
         git: git_status(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::EmailOAuthConfig;
+
+    fn check(id: &str, level: CheckLevel, hint: Option<&str>) -> HealthCheck {
+        HealthCheck {
+            id: id.to_string(),
+            label: id.to_string(),
+            level,
+            detail: format!("{id} detail"),
+            fix_hint: hint.map(ToOwned::to_owned),
+        }
+    }
+
+    #[test]
+    fn startup_summary_lists_problems_and_blocks_only_on_failures() {
+        let mut config = Config::default();
+        config.channels.telegram.enabled = true;
+        config.channels.slack.enabled = true;
+        config.channels.slack.bot_token = "xoxb-1".to_string();
+        config.channels.email.enabled = true;
+        config.channels.email.imap_host = "imap.example.com".to_string();
+        config.channels.email.imap_username = "bot".to_string();
+        config.channels.email.smtp_host = "smtp.example.com".to_string();
+        config.channels.email.oauth = Some(EmailOAuthConfig::default());
+        assert_eq!(
+            missing_channel_credentials(&config),
+            vec![
+                "channels.telegram.token",
+                "channels.slack.appToken",
+                "channels.email.consentGranted",
+            ]
+        );
+
+        let checks = vec![
+            check("config.file", CheckLevel::Fail, Some("run onboard")),
+            check("cron.jobs", CheckLevel::Warn, None),
+            check("agent.model", CheckLevel::Ok, None),
+        ];
+        let mut report = HealthReport {
+            generated_at: String::new(),
+            summary: count_summary(&checks),
+            checks,
+        };
+        assert!(startup_blockers(&report).is_empty());
+        let banner = startup_banner(&config, &report);
+        assert!(banner.contains("channels: telegram, email, slack"));
+        assert!(banner.contains("  FAIL  config.file  config.file detail\n"));
+        assert!(banner.contains("fix: run onboard"));
+        assert!(banner.contains("WARN  cron.jobs"));
+        assert!(!banner.contains("agent.model"));
+
+        report
+            .checks
+            .push(check("provider.api", CheckLevel::Fail, None));
+        let blockers = startup_blockers(&report);
+        assert_eq!(blockers.len(), 1);
+        assert_eq!(blockers[0].id, "provider.api");
+
+        // A fresh custom workspace is created on start, not a blocker.
+        config.agents.defaults.workspace = std::env::temp_dir()
+            .join(format!("nanobot-health-{}", uuid::Uuid::new_v4()))
+            .display()
+            .to_string();
+        let report = collect_health(&config).expect("health");
+        let workspace = report
+            .checks
+            .iter()
+            .find(|check| check.id == "workspace.dir")
+            .expect("workspace check");
+        assert!(matches!(workspace.level, CheckLevel::Warn));
+        assert!(
+            startup_blockers(&report)
+                .iter()
+                .all(|check| check.id == "provider.api")
+        );
+    }
+}
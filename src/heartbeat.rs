@@ -1,3 +1,6 @@
+use crate::cluster::LeaderLease;
+use crate::pause::PauseSwitch;
+use crate::tasks::TaskStore;
 use futures_util::future::BoxFuture;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -7,6 +10,7 @@ use tokio::task::JoinHandle;
 pub const DEFAULT_HEARTBEAT_INTERVAL_S: u64 = 30 * 60;
 pub const HEARTBEAT_PROMPT: &str = "Read HEARTBEAT.md in your workspace (if it exists).\nFollow any instructions or tasks listed there.\nIf nothing needs attention, reply with just: HEARTBEAT_OK";
 pub const HEARTBEAT_OK_TOKEN: &str = "HEARTBEAT_OK";
+pub const TASK_DUE_HORIZON_MS: i64 = 24 * 60 * 60 * 1000;
 
 pub type HeartbeatCallback = Arc<dyn Fn(String) -> BoxFuture<'static, String> + Send + Sync>;
 
@@ -29,27 +33,68 @@ pub fn is_heartbeat_empty(content: Option<&str>) -> bool {
     true
 }
 
+pub fn heartbeat_prompt(task_digest: Option<&str>) -> String {
+    match task_digest {
+        Some(digest) => format!("{HEARTBEAT_PROMPT}\n\n{digest}"),
+        None => HEARTBEAT_PROMPT.to_string(),
+    }
+}
+
+fn task_digest(task_store: Option<&TaskStore>) -> Option<String> {
+    let store = task_store?;
+    match store.due_digest(chrono::Utc::now().timestamp_millis(), TASK_DUE_HORIZON_MS) {
+        Ok(digest) => digest,
+        Err(err) => {
+            eprintln!("Warning: failed to read task store: {err}");
+            None
+        }
+    }
+}
+
 pub struct HeartbeatService {
     workspace: std::path::PathBuf,
     on_heartbeat: Arc<Mutex<Option<HeartbeatCallback>>>,
     interval_s: u64,
     enabled: bool,
+    task_store: Option<TaskStore>,
+    pause: Option<PauseSwitch>,
+    lease: Option<LeaderLease>,
     running: Arc<AtomicBool>,
     task: Arc<Mutex<Option<JoinHandle<()>>>>,
 }
 
 impl HeartbeatService {
-    pub fn new(workspace: std::path::PathBuf, interval_s: u64, enabled: bool) -> Self {
+    pub fn new(
+        workspace: std::path::PathBuf,
+        interval_s: u64,
+        enabled: bool,
+        task_store: Option<TaskStore>,
+    ) -> Self {
         Self {
             workspace,
             on_heartbeat: Arc::new(Mutex::new(None)),
             interval_s,
             enabled,
+            task_store,
+            pause: None,
+            lease: None,
             running: Arc::new(AtomicBool::new(false)),
             task: Arc::new(Mutex::new(None)),
         }
     }
 
+    // Ticks that fall while the switch is on are skipped.
+    pub fn with_pause_switch(mut self, pause: PauseSwitch) -> Self {
+        self.pause = Some(pause);
+        self
+    }
+
+    // In cluster mode only the leader's ticks run.
+    pub fn with_leader_lease(mut self, lease: LeaderLease) -> Self {
+        self.lease = Some(lease);
+        self
+    }
+
     pub async fn set_on_heartbeat(&self, callback: HeartbeatCallback) {
         let mut guard = self.on_heartbeat.lock().await;
         *guard = Some(callback);
@@ -68,6 +113,9 @@ impl HeartbeatService {
         let heartbeat_file = self.heartbeat_file();
         let on_heartbeat = self.on_heartbeat.clone();
         let interval_s = self.interval_s;
+        let task_store = self.task_store.clone();
+        let pause = self.pause.clone();
+        let lease = self.lease.clone();
 
         let handle = tokio::spawn(async move {
             while running.load(Ordering::Relaxed) {
@@ -75,15 +123,25 @@ impl HeartbeatService {
                 if !running.load(Ordering::Relaxed) {
                     break;
                 }
+                if pause
+                    .as_ref()
+                    .is_some_and(|p| p.is_paused_at(chrono::Utc::now().timestamp_millis()))
+                {
+                    continue;
+                }
+                if lease.as_ref().is_some_and(|lease| !lease.is_leader()) {
+                    continue;
+                }
 
                 let content = tokio::fs::read_to_string(&heartbeat_file).await.ok();
-                if is_heartbeat_empty(content.as_deref()) {
+                let digest = task_digest(task_store.as_ref());
+                if is_heartbeat_empty(content.as_deref()) && digest.is_none() {
                     continue;
                 }
 
                 let callback = on_heartbeat.lock().await.clone();
                 if let Some(callback) = callback {
-                    let response = callback(HEARTBEAT_PROMPT.to_string()).await;
+                    let response = callback(heartbeat_prompt(digest.as_deref())).await;
                     let normalized = response.to_uppercase().replace('_', "");
                     let ok = HEARTBEAT_OK_TOKEN.to_uppercase().replace('_', "");
                     if normalized.contains(&ok) {
@@ -106,8 +164,9 @@ impl HeartbeatService {
 
     pub async fn trigger_now(&self) -> Option<String> {
         let callback = self.on_heartbeat.lock().await.clone();
+        let digest = task_digest(self.task_store.as_ref());
         match callback {
-            Some(cb) => Some(cb(HEARTBEAT_PROMPT.to_string()).await),
+            Some(cb) => Some(cb(heartbeat_prompt(digest.as_deref())).await),
             None => None,
         }
     }
@@ -124,9 +183,17 @@ mod tests {
         assert!(!is_heartbeat_empty(Some("# Header\n- [ ]\nCall mom")));
     }
 
+    #[test]
+    fn heartbeat_prompt_appends_task_digest() {
+        assert_eq!(heartbeat_prompt(None), HEARTBEAT_PROMPT);
+        let prompt = heartbeat_prompt(Some("Open action items due soon:\n- [a1] Pay rent"));
+        assert!(prompt.starts_with(HEARTBEAT_PROMPT));
+        assert!(prompt.contains("Pay rent"));
+    }
+
     #[tokio::test]
     async fn trigger_now_invokes_callback() {
-        let service = HeartbeatService::new(std::path::PathBuf::from("."), 60, true);
+        let service = HeartbeatService::new(std::path::PathBuf::from("."), 60, true, None);
         service
             .set_on_heartbeat(Arc::new(|prompt| {
                 Box::pin(async move { format!("received:{prompt}") })
@@ -0,0 +1,235 @@
+use crate::bus::{InboundMessage, OutboundMessage};
+use crate::config::Config;
+use anyhow::Result;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// User scripts in `workspace/hooks/*.rhai`, run in file-name order. Each may
+// define any of:
+//   fn on_inbound(msg)                       -> (), false, new content, or changed msg
+//   fn on_outbound(msg)                      -> (), false, new content, or changed msg
+//   fn on_tool_result(name, args, result)    -> () or replacement result
+// Returning false drops the message. Script errors are logged and ignored.
+pub struct ScriptHooks {
+    #[cfg(feature = "scripting")]
+    engine: rhai::Engine,
+    #[cfg(feature = "scripting")]
+    scripts: Vec<(String, rhai::AST)>,
+}
+
+fn script_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+impl ScriptHooks {
+    pub fn from_config(config: &Config) -> Option<Arc<Self>> {
+        if !config.hooks.enabled {
+            return None;
+        }
+        let dir = config.workspace_path().join("hooks");
+        match Self::load(&dir, config.hooks.max_operations) {
+            Ok(hooks) => hooks.map(Arc::new),
+            Err(err) => {
+                eprintln!("Warning: failed to load script hooks: {err}");
+                None
+            }
+        }
+    }
+
+    #[cfg(feature = "scripting")]
+    pub fn load(dir: &Path, max_operations: u64) -> Result<Option<Self>> {
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(max_operations);
+        let mut scripts = Vec::new();
+        for path in script_files(dir) {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let source = std::fs::read_to_string(&path)?;
+            match engine.compile(&source) {
+                Ok(ast) => scripts.push((name, ast)),
+                Err(err) => eprintln!("Warning: skipping hook script {name}: {err}"),
+            }
+        }
+        if scripts.is_empty() {
+            return Ok(None);
+        }
+        println!(
+            "Loaded script hooks: {}",
+            scripts
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        Ok(Some(Self { engine, scripts }))
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    pub fn load(dir: &Path, _max_operations: u64) -> Result<Option<Self>> {
+        if !script_files(dir).is_empty() {
+            eprintln!(
+                "Warning: {} contains hook scripts but this build lacks the `scripting` feature",
+                dir.display()
+            );
+        }
+        Ok(None)
+    }
+
+    // Threads `subject` through every script defining `hook`, called as
+    // hook(extra..., subject). None means a script dropped it.
+    #[cfg(feature = "scripting")]
+    fn run(&self, hook: &str, mut subject: Value, extra: &[Value]) -> Option<Value> {
+        use rhai::Dynamic;
+        use rhai::serde::{from_dynamic, to_dynamic};
+
+        let arity = extra.len() + 1;
+        for (name, ast) in &self.scripts {
+            if !ast
+                .iter_functions()
+                .any(|f| f.name == hook && f.params.len() == arity)
+            {
+                continue;
+            }
+            let args = extra
+                .iter()
+                .chain(std::iter::once(&subject))
+                .map(to_dynamic)
+                .collect::<Result<Vec<Dynamic>, _>>();
+            let result = args.and_then(|args| {
+                self.engine
+                    .call_fn::<Dynamic>(&mut rhai::Scope::new(), ast, hook, args)
+            });
+            let returned = match result {
+                Ok(returned) => returned,
+                Err(err) => {
+                    eprintln!("Warning: hook {name}::{hook} failed: {err}");
+                    continue;
+                }
+            };
+            if returned.is_unit() {
+                continue;
+            }
+            if returned.as_bool() == Ok(false) {
+                return None;
+            }
+            let Ok(returned) = from_dynamic::<Value>(&returned) else {
+                eprintln!("Warning: hook {name}::{hook} returned an unsupported value");
+                continue;
+            };
+            subject = merge(subject, returned);
+        }
+        Some(subject)
+    }
+
+    #[cfg(not(feature = "scripting"))]
+    fn run(&self, _hook: &str, subject: Value, _extra: &[Value]) -> Option<Value> {
+        Some(subject)
+    }
+
+    fn run_message<T: Serialize + DeserializeOwned>(&self, hook: &str, msg: T) -> Option<T> {
+        let Ok(subject) = serde_json::to_value(&msg) else {
+            return Some(msg);
+        };
+        let changed = self.run(hook, subject, &[])?;
+        match serde_json::from_value(changed) {
+            Ok(changed) => Some(changed),
+            Err(err) => {
+                eprintln!("Warning: ignoring {hook} hook result: {err}");
+                Some(msg)
+            }
+        }
+    }
+
+    pub fn on_inbound(&self, msg: InboundMessage) -> Option<InboundMessage> {
+        self.run_message("on_inbound", msg)
+    }
+
+    pub fn on_outbound(&self, msg: OutboundMessage) -> Option<OutboundMessage> {
+        self.run_message("on_outbound", msg)
+    }
+
+    pub fn on_tool_result(&self, name: &str, args: &Map<String, Value>, result: String) -> String {
+        let extra = [Value::String(name.to_string()), Value::Object(args.clone())];
+        match self.run("on_tool_result", Value::String(result.clone()), &extra) {
+            Some(Value::String(changed)) => changed,
+            Some(other) => other.to_string(),
+            None => result,
+        }
+    }
+}
+
+// A bare string replaces a message's content; a map overrides its fields.
+#[cfg_attr(not(feature = "scripting"), allow(dead_code))]
+fn merge(subject: Value, returned: Value) -> Value {
+    match (subject, returned) {
+        (Value::Object(mut fields), Value::String(content)) => {
+            fields.insert("content".to_string(), Value::String(content));
+            Value::Object(fields)
+        }
+        (Value::Object(mut fields), Value::Object(changes)) => {
+            fields.extend(changes);
+            Value::Object(fields)
+        }
+        (_, returned) => returned,
+    }
+}
+
+#[cfg(all(test, feature = "scripting"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts_rewrite_drop_and_annotate() {
+        let dir = std::env::temp_dir().join(format!("nanobot-hooks-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        std::fs::write(
+            dir.join("10-filter.rhai"),
+            r#"
+            fn on_inbound(msg) {
+                if msg.content.contains("unsubscribe") { return false; }
+                msg.content.trim();
+                msg
+            }
+            fn on_outbound(msg) { msg.content + "\n-- sent by nanobot" }
+            fn on_tool_result(name, args, result) {
+                if name == "exec" { result.replace("hunter2", "***"); result }
+            }
+            "#,
+        )
+        .expect("write script");
+        let hooks = ScriptHooks::load(&dir, 10_000)
+            .expect("load")
+            .expect("scripts present");
+
+        let spam = InboundMessage::new("email", "a@b.c", "a@b.c", "please unsubscribe me");
+        assert!(hooks.on_inbound(spam).is_none());
+        let kept = hooks
+            .on_inbound(InboundMessage::new("telegram", "1", "1", "  hi  "))
+            .expect("kept");
+        assert_eq!(kept.content, "hi");
+
+        let out = hooks
+            .on_outbound(OutboundMessage::new("telegram", "1", "done"))
+            .expect("kept");
+        assert_eq!(out.content, "done\n-- sent by nanobot");
+
+        let masked = hooks.on_tool_result("exec", &Map::new(), "password=hunter2".to_string());
+        assert_eq!(masked, "password=***");
+        let untouched = hooks.on_tool_result("read_file", &Map::new(), "hunter2".to_string());
+        assert_eq!(untouched, "hunter2");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
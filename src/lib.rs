@@ -1,18 +1,33 @@
+pub mod abuse;
 pub mod agent;
+pub mod batch;
 pub mod bus;
 pub mod channels;
+pub mod cluster;
 pub mod config;
 pub mod cron;
+pub mod events;
+pub mod feedback;
+pub mod fulltext;
 pub mod health;
 pub mod heartbeat;
+pub mod hooks;
 pub mod memory;
+pub mod message_templates;
+pub mod metrics;
 pub mod pairing;
+pub mod pause;
 pub mod providers;
 pub mod service;
 pub mod session;
+pub mod simulate;
 pub mod skills;
+pub mod storage;
+pub mod tasks;
+pub mod templates;
 pub mod tools;
 pub mod utils;
 pub mod webui;
+pub mod workspace_git;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
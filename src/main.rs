@@ -1,24 +1,54 @@
 use anyhow::{Result, anyhow};
-use clap::{ArgAction, Parser, Subcommand};
+use clap::{ArgAction, Args, Parser, Subcommand};
 use nanobot::VERSION;
+use nanobot::abuse::AbuseMonitor;
 use nanobot::agent::AgentLoop;
-use nanobot::bus::{MessageBus, OutboundMessage};
+use nanobot::batch::{BatchService, BatchStore};
+use nanobot::bus::MessageBus;
+use nanobot::channels::control;
+use nanobot::channels::delivery::{mark_proactive, proactive_metadata};
+use nanobot::channels::latency::render_latency;
 use nanobot::channels::manager::ChannelManager;
-use nanobot::config::{Config, get_config_path, load_config, providers_status, save_config};
-use nanobot::cron::{CronSchedule, CronService};
-use nanobot::health::{CheckLevel, HealthReport, check_update, collect_health, run_doctor};
+use nanobot::channels::oauth::OAuthSession;
+use nanobot::cluster::LeaderLease;
+use nanobot::config::{
+    CHANNEL_NAMES, Config, StorageConfig, get_config_path, load_config, providers_status,
+    save_config,
+};
+use nanobot::cron::reminder::{FiredReminders, REMINDER_KIND, fire_reminder};
+use nanobot::cron::template::delivery_message;
+use nanobot::cron::time::{ensure_future, format_local, parse_at};
+use nanobot::cron::{CronSchedule, CronService, CronTemplate};
+use nanobot::events::{self, EventFilter, EventLevel, EventLog, parse_since};
+use nanobot::feedback::{FeedbackStore, render_report};
+use nanobot::fulltext::{HistoryIndex, SearchQuery};
+use nanobot::health::{
+    CheckLevel, HealthReport, check_update, collect_health, run_doctor, startup_banner,
+    startup_blockers,
+};
 use nanobot::heartbeat::{DEFAULT_HEARTBEAT_INTERVAL_S, HeartbeatService};
-use nanobot::pairing::{approve_pairing, list_pending, reject_pairing};
-use nanobot::providers::base::LLMProvider;
+use nanobot::hooks::ScriptHooks;
+use nanobot::memory::MemoryStore;
+use nanobot::message_templates::{MessageTemplates, TEMPLATE_KIND};
+use nanobot::pairing::{approve_pairing, list_guests, list_pending, reject_pairing, revoke_guest};
+use nanobot::pause::{self, PauseSwitch};
+use nanobot::providers::base::{LLMProvider, spawn_warm_up};
 use nanobot::providers::litellm::LiteLLMProvider;
-use nanobot::service::{self, ServiceAccount, ServiceInstallOptions};
+use nanobot::service::{self, RecoveryOptions, ServiceAccount, ServiceInstallOptions};
 use nanobot::session::SessionManager;
+use nanobot::simulate::{load_scenario, run_scenario, scenario_paths};
+use nanobot::storage::{self, STORE_PREFIXES};
+use nanobot::tasks::TaskStore;
+use nanobot::templates;
 use nanobot::utils::{get_data_path, get_workspace_path};
 use nanobot::webui::run_webui_server;
+use nanobot::workspace_git::WorkspaceGit;
+use std::collections::BTreeMap;
 use std::fs;
 use std::io::BufRead;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::process::Command;
 use which::which;
 
@@ -34,7 +64,19 @@ struct Cli {
 
 #[derive(Debug, Subcommand)]
 enum Commands {
-    Onboard,
+    Onboard {
+        // Persona preset: coder, assistant, ops or researcher
+        #[arg(long)]
+        template: Option<String>,
+    },
+    Templates {
+        #[command(subcommand)]
+        command: TemplatesCommand,
+    },
+    Tools {
+        #[command(subcommand)]
+        command: ToolsCommand,
+    },
     Health {
         #[arg(long, default_value_t = false)]
         json: bool,
@@ -63,9 +105,23 @@ enum Commands {
         message: Option<String>,
         #[arg(short, long, default_value = "cli:direct")]
         session: String,
+        #[arg(long, action = ArgAction::SetTrue)]
+        explain: bool,
+    },
+    Status {
+        // Reply latency percentiles from the running gateway
+        #[arg(long, default_value_t = false)]
+        latency: bool,
+        #[arg(long)]
+        port: Option<u16>,
     },
-    Status,
     Version,
+    // Runs scripted scenarios (a YAML file or a directory of them) against a mock provider
+    Simulate {
+        scenario: PathBuf,
+        #[arg(short, long, default_value_t = false)]
+        verbose: bool,
+    },
     Channels {
         #[command(subcommand)]
         command: ChannelCommand,
@@ -78,20 +134,88 @@ enum Commands {
         #[command(subcommand)]
         command: SessionCommand,
     },
+    // Full-text search over every session and HISTORY.md
+    Search {
+        query: String,
+        #[arg(long)]
+        session: Option<String>,
+        #[arg(long)]
+        since: Option<String>,
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+    },
     Cron {
         #[command(subcommand)]
         command: CronCommand,
     },
+    // Outbound message templates in <workspace>/templates
+    MessageTemplates {
+        #[command(subcommand)]
+        command: MessageTemplateCommand,
+    },
+    Feedback {
+        #[arg(short, long, default_value_t = 10)]
+        limit: usize,
+    },
+    Memory {
+        #[command(subcommand)]
+        command: MemoryCommand,
+    },
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommand,
+    },
+    Storage {
+        #[command(subcommand)]
+        command: StorageCommand,
+    },
+    Events {
+        #[arg(long)]
+        since: Option<String>,
+        #[arg(long)]
+        channel: Option<String>,
+        #[arg(long)]
+        level: Option<String>,
+        #[arg(long)]
+        kind: Option<String>,
+        #[arg(short, long, default_value_t = 50)]
+        limit: usize,
+    },
     Service {
         #[command(subcommand)]
         command: ServiceCommand,
     },
+    // Stop heartbeat and scheduled jobs; chat keeps working.
+    Pause {
+        // "7d", "tomorrow 08:00", "2026-08-01 09:00"; open-ended if omitted.
+        #[arg(long)]
+        until: Option<String>,
+    },
+    Resume,
 }
 
 #[derive(Debug, Subcommand)]
 enum ChannelCommand {
     Status,
-    Login,
+    // WhatsApp QR pairing, or the OAuth device flow for email
+    Login {
+        #[arg(default_value = "whatsapp")]
+        channel: String,
+        // Email account id from channels.email.accounts
+        #[arg(long)]
+        account: Option<String>,
+    },
+    Enable {
+        name: String,
+        // Gateway port to notify; defaults to gateway.port from config
+        #[arg(long)]
+        port: Option<u16>,
+    },
+    Disable {
+        name: String,
+        #[arg(long)]
+        port: Option<u16>,
+    },
 }
 
 #[derive(Debug, Subcommand)]
@@ -99,6 +223,9 @@ enum PairingCommand {
     List,
     Approve { channel: String, code: String },
     Reject { channel: String, code: String },
+    // Senders admitted by answering `channels.pairingChallenge`
+    Guests,
+    Revoke { channel: String, sender: String },
 }
 
 #[derive(Debug, Subcommand)]
@@ -119,11 +246,15 @@ enum CronCommand {
     List {
         #[arg(short, long, default_value_t = false)]
         all: bool,
+        // Only jobs created by this user ("telegram:123")
+        #[arg(long)]
+        owner: Option<String>,
     },
     Add {
         #[arg(short, long)]
         name: String,
-        #[arg(short, long)]
+        // Prompt for the agent; optional with --template-name
+        #[arg(short, long, default_value = "")]
         message: String,
         #[arg(short = 'e', long)]
         every: Option<i64>,
@@ -137,6 +268,8 @@ enum CronCommand {
         to: Option<String>,
         #[arg(long)]
         channel: Option<String>,
+        #[command(flatten)]
+        format: Box<CronFormatArgs>,
     },
     Remove {
         job_id: String,
@@ -153,6 +286,108 @@ enum CronCommand {
     },
 }
 
+// How `cron add` presents what it delivers.
+#[derive(Debug, Args)]
+struct CronFormatArgs {
+    // Subject/title for the delivered response; supports {name} and {date}
+    #[arg(long)]
+    title: Option<String>,
+    // Wraps the response, e.g. "Digest for {date}:\n\n{response}"
+    #[arg(long)]
+    template: Option<String>,
+    // Workspace message template to send (or to wrap the response)
+    #[arg(long)]
+    template_name: Option<String>,
+    // Template variable, key=value (repeatable)
+    #[arg(long = "var")]
+    vars: Vec<String>,
+}
+
+#[derive(Debug, Subcommand)]
+enum MessageTemplateCommand {
+    List,
+    // Renders a template as it would be sent now
+    Show {
+        name: String,
+        // Template variable, key=value (repeatable)
+        #[arg(long = "var")]
+        vars: Vec<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum MemoryCommand {
+    // Lists consolidations, or shows one with its MEMORY.md diff
+    Log {
+        id: Option<String>,
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+    // Restores MEMORY.md to its state before a consolidation
+    Rollback {
+        id: String,
+        #[arg(long, default_value_t = false)]
+        force: bool,
+    },
+    // Lists the saved MEMORY.md versions
+    Versions,
+    // Restores a saved MEMORY.md version (saved as a new version itself)
+    Revert {
+        version: u32,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum WorkspaceCommand {
+    // Lists the commits made for agent edits and memory consolidations
+    Log {
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+    // Undoes one commit with a new revert commit
+    Revert {
+        commit: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum StorageCommand {
+    // Shows the active backend and how many entries each store holds
+    Status,
+    // Copies every store into another backend and switches config.json to it
+    Migrate {
+        // filesystem or sqlite
+        to: String,
+        #[arg(long)]
+        path: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TemplatesCommand {
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+enum ToolsCommand {
+    List {
+        // Print the OpenAI-compatible schemas instead of a summary
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    Enable {
+        name: String,
+        // Gateway port to notify; defaults to gateway.port from config
+        #[arg(long)]
+        port: Option<u16>,
+    },
+    Disable {
+        name: String,
+        #[arg(long)]
+        port: Option<u16>,
+    },
+}
+
 #[derive(Debug, Subcommand)]
 enum ServiceCommand {
     Install {
@@ -160,8 +395,8 @@ enum ServiceCommand {
         name: Option<String>,
         #[arg(long)]
         bin: Option<PathBuf>,
-        #[arg(long, default_value = "gateway")]
-        args: String,
+        #[arg(short, long, default_value_t = 18790)]
+        port: u16,
         #[arg(long)]
         workdir: Option<PathBuf>,
         #[arg(long, action = ArgAction::SetTrue)]
@@ -171,9 +406,23 @@ enum ServiceCommand {
         #[arg(long)]
         password: Option<String>,
         #[arg(long, default_value_t = true, action = ArgAction::Set)]
-        auto_install_nssm: bool,
-        #[arg(long, default_value_t = true, action = ArgAction::Set)]
         autostart: bool,
+        // Seconds before the first restart after a failure; 0 disables restarts.
+        #[arg(long, default_value_t = 10)]
+        restart_delay: u64,
+        // Hours without a failure after which the restart delay starts over.
+        #[arg(long, default_value_t = 24)]
+        reset_hours: u64,
+    },
+    // Started by the Service Control Manager, not by hand.
+    #[command(hide = true)]
+    Run {
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(short, long, default_value_t = 18790)]
+        port: u16,
+        #[arg(long)]
+        workdir: Option<PathBuf>,
     },
     Remove {
         #[arg(long)]
@@ -200,39 +449,104 @@ enum ServiceCommand {
 #[tokio::main]
 async fn main() -> Result<()> {
     let cli = Cli::parse();
+    storage::init(&load_config(None).unwrap_or_default().storage)?;
     match cli.command {
-        Commands::Onboard => cmd_onboard()?,
+        Commands::Onboard { template } => cmd_onboard(template.as_deref())?,
+        Commands::Templates { command } => cmd_templates(command),
+        Commands::Tools { command } => match command {
+            ToolsCommand::List { json } => cmd_tools_list(json)?,
+            ToolsCommand::Enable { name, port } => cmd_tools_toggle(&name, true, port).await?,
+            ToolsCommand::Disable { name, port } => cmd_tools_toggle(&name, false, port).await?,
+        },
         Commands::Health { json } => cmd_health(json)?,
         Commands::Doctor { fix, json } => cmd_doctor(fix, json)?,
         Commands::Update => cmd_update().await?,
         Commands::Webui { host, port } => cmd_webui(&host, port)?,
-        Commands::Status => cmd_status()?,
+        Commands::Status {
+            latency: true,
+            port,
+        } => cmd_status_latency(port).await?,
+        Commands::Status { .. } => cmd_status()?,
         Commands::Version => println!("nanobot-rs v{VERSION}"),
+        Commands::Simulate { scenario, verbose } => cmd_simulate(&scenario, verbose).await?,
         Commands::Gateway { port, verbose } => cmd_gateway(port, verbose).await?,
-        Commands::Agent { message, session } => cmd_agent(message, &session).await?,
+        Commands::Agent {
+            message,
+            session,
+            explain,
+        } => cmd_agent(message, &session, explain).await?,
         Commands::Channels { command } => cmd_channels(command).await?,
         Commands::Pairing { command } => cmd_pairing(command)?,
         Commands::Sessions { command } => cmd_sessions(command)?,
+        Commands::Search {
+            query,
+            session,
+            since,
+            limit,
+        } => cmd_search(query, session, since, limit)?,
         Commands::Cron { command } => cmd_cron(command).await?,
+        Commands::MessageTemplates { command } => cmd_message_templates(command)?,
+        Commands::Feedback { limit } => cmd_feedback(limit)?,
+        Commands::Memory { command } => cmd_memory(command)?,
+        Commands::Workspace { command } => cmd_workspace(command)?,
+        Commands::Storage { command } => cmd_storage(command)?,
+        Commands::Events {
+            since,
+            channel,
+            level,
+            kind,
+            limit,
+        } => cmd_events(since, channel, level, kind, limit)?,
         Commands::Service { command } => cmd_service(command)?,
+        Commands::Pause { until } => cmd_pause(until.as_deref())?,
+        Commands::Resume => cmd_resume()?,
     }
     Ok(())
 }
 
-fn cmd_onboard() -> Result<()> {
+fn cmd_onboard(template: Option<&str>) -> Result<()> {
+    let template = template.map(templates::find).transpose()?;
     let config_path = get_config_path()?;
-    if config_path.exists() {
-        println!("Config already exists at {}", config_path.display());
-        return Ok(());
-    }
-
-    let config = Config::default();
-    save_config(&config, Some(&config_path))?;
-    println!("Created config at {}", config_path.display());
+    let config = if config_path.exists() {
+        let Some(template) = template else {
+            println!("Config already exists at {}", config_path.display());
+            return Ok(());
+        };
+        // Re-running with a preset on an existing install applies its policy
+        // and adds whichever of its files are missing.
+        let mut config = load_config(Some(&config_path))?;
+        template.policy.apply(&mut config);
+        save_config(&config, Some(&config_path))?;
+        println!("Updated config at {}", config_path.display());
+        config
+    } else {
+        let mut config = Config::default();
+        if let Some(template) = template {
+            template.policy.apply(&mut config);
+        }
+        save_config(&config, Some(&config_path))?;
+        println!("Created config at {}", config_path.display());
+        config
+    };
 
     let workspace = get_workspace_path(Some(&config.agents.defaults.workspace))?;
     println!("Created workspace at {}", workspace.display());
 
+    if let Some(template) = template {
+        let (written, skipped) = template.seed(&workspace)?;
+        for path in written {
+            println!("Created {}", path.display());
+        }
+        for path in skipped {
+            println!("Kept existing {}", path.display());
+        }
+        println!(
+            "Applied '{}' template ({})",
+            template.name,
+            template.policy.describe()
+        );
+    }
+
     let templates = [
         (
             "AGENTS.md",
@@ -285,6 +599,107 @@ fn cmd_onboard() -> Result<()> {
     Ok(())
 }
 
+fn cmd_templates(command: TemplatesCommand) {
+    match command {
+        TemplatesCommand::List => {
+            for template in templates::TEMPLATES {
+                println!("{:<11} {}", template.name, template.description);
+                println!("            {}", template.policy.describe());
+            }
+            println!("Use: nanobot-rs onboard --template <name>");
+        }
+    }
+}
+
+// Builds the agent the way the gateway does (without starting anything) to
+// show the tools the model is offered.
+fn cmd_tools_list(json: bool) -> Result<()> {
+    let config = load_config(None).unwrap_or_default();
+    let model = config.agents.defaults.model.clone();
+    let api_key = config
+        .get_api_key(Some(&model))
+        .unwrap_or_else(|| "dummy".to_string());
+    let bus = Arc::new(MessageBus::new(16));
+    let cron = Arc::new(CronService::new(
+        get_data_path()?.join("cron").join("jobs.json"),
+    ));
+    let batch = Arc::new(BatchService::new(
+        config.clone(),
+        BatchStore::new()?,
+        bus.clone(),
+    ));
+    let agent = AgentLoop::new(
+        bus.clone(),
+        build_provider(&config, &model, api_key),
+        config.workspace_path(),
+        Some(model.clone()),
+        config.agents.defaults.max_tool_iterations,
+        config.agents.defaults.memory_window,
+        config.tools.web.search.clone(),
+        config.tools.exec.timeout,
+        config.tools.restrict_to_workspace,
+        Some(cron),
+        None,
+    )?
+    .with_read_only_channels(config.channels.read_only_channels())
+    .with_action_item_extraction(config.agents.defaults.extract_action_items)
+    .with_sampling(config.agents.defaults.sampling())
+    .with_maintenance_sampling(config.agents.defaults.maintenance_sampling())
+    .with_provider_factory(LiteLLMProvider::factory(config.clone()))
+    .with_subagent_profiles(config.agents.subagents.clone())
+    .with_batch_service(batch)
+    .with_disabled_tools(&config.tools.disabled);
+
+    let schemas = agent.tool_schemas();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&schemas)?);
+        return Ok(());
+    }
+    for schema in &schemas {
+        let function = &schema["function"];
+        let description = function["description"].as_str().unwrap_or_default();
+        println!(
+            "{:<18} {}",
+            function["name"].as_str().unwrap_or_default(),
+            description.lines().next().unwrap_or_default()
+        );
+    }
+    println!("{} tools. Use --json for the full schemas.", schemas.len());
+    if !config.tools.disabled.is_empty() {
+        println!("Disabled: {}", config.tools.disabled.join(", "));
+    }
+    Ok(())
+}
+
+async fn cmd_tools_toggle(name: &str, enabled: bool, port: Option<u16>) -> Result<()> {
+    let config_path = get_config_path()?;
+    let mut config = load_config(Some(&config_path))?;
+    config.tools.disabled.retain(|tool| tool != name);
+    if !enabled {
+        config.tools.disabled.push(name.to_string());
+    }
+    save_config(&config, Some(&config_path))?;
+    println!(
+        "Tool {name} {} in {}",
+        if enabled { "enabled" } else { "disabled" },
+        config_path.display()
+    );
+
+    let port = port.unwrap_or(config.gateway.port);
+    match control::reload_tools(port).await {
+        Ok(message) => println!("Gateway: {message}"),
+        Err(err)
+            if err
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(|err| err.is_connect()) =>
+        {
+            println!("No gateway on port {port}; the change applies on its next start.")
+        }
+        Err(err) => println!("Gateway: {err}"),
+    }
+    Ok(())
+}
+
 fn check_level_tag(level: &CheckLevel) -> &'static str {
     match level {
         CheckLevel::Ok => "OK",
@@ -313,6 +728,23 @@ fn print_health_report(report: &HealthReport) {
     }
 }
 
+fn cmd_pause(until: Option<&str>) -> Result<()> {
+    let reply = pause::pause_reply(
+        &PauseSwitch::new()?,
+        until.unwrap_or_default(),
+        "cli",
+        chrono::Local::now(),
+    )?;
+    println!("{reply}");
+    Ok(())
+}
+
+fn cmd_resume() -> Result<()> {
+    let reply = pause::resume_reply(&PauseSwitch::new()?, chrono::Utc::now().timestamp_millis())?;
+    println!("{reply}");
+    Ok(())
+}
+
 fn cmd_health(json_output: bool) -> Result<()> {
     let config = load_config(None).unwrap_or_default();
     let report = collect_health(&config)?;
@@ -378,6 +810,43 @@ fn cmd_webui(host: &str, port: u16) -> Result<()> {
     run_webui_server(host, port)
 }
 
+async fn cmd_simulate(path: &Path, verbose: bool) -> Result<()> {
+    let paths = scenario_paths(path)?;
+    if paths.is_empty() {
+        return Err(anyhow!("no scenarios found in {}", path.display()));
+    }
+    let mut failed = 0;
+    for path in &paths {
+        let report = run_scenario(&load_scenario(path)?).await?;
+        println!("{}\n", report.render(verbose));
+        if !report.passed() {
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{failed} of {} scenario(s) failed", paths.len()));
+    }
+    println!("All {} scenario(s) passed.", paths.len());
+    Ok(())
+}
+
+async fn cmd_status_latency(port: Option<u16>) -> Result<()> {
+    let config = load_config(None).unwrap_or_default();
+    let port = port.unwrap_or(config.gateway.port);
+    match control::latency(port).await {
+        Ok(rows) => println!("{}", render_latency(&rows)),
+        Err(err)
+            if err
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(|err| err.is_connect()) =>
+        {
+            println!("No gateway on port {port}; latency is measured while it runs.")
+        }
+        Err(err) => println!("Gateway: {err}"),
+    }
+    Ok(())
+}
+
 fn cmd_status() -> Result<()> {
     let config_path = get_config_path()?;
     let config = load_config(Some(&config_path)).unwrap_or_default();
@@ -399,6 +868,17 @@ fn cmd_status() -> Result<()> {
         if workspace.exists() { "OK" } else { "MISSING" }
     );
     println!("Model: {}", config.agents.defaults.model);
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    match PauseSwitch::new()?.state_at(now_ms) {
+        Some(state) => println!("Proactive: {}", state.describe(now_ms)),
+        None => println!("Proactive: active"),
+    }
+    if config.gateway.cluster.enabled {
+        match LeaderLease::new(&config.gateway.cluster)?.holder_at(now_ms) {
+            Some(record) => println!("Cluster: {}", record.describe(now_ms)),
+            None => println!("Cluster: no leader (cron and heartbeat idle)"),
+        }
+    }
 
     let status = providers_status(&config);
     println!(
@@ -483,37 +963,80 @@ fn build_provider(config: &Config, model: &str, api_key: String) -> Arc<dyn LLMP
         .get_provider(Some(model))
         .and_then(|p| p.extra_headers.clone());
     let provider_name = config.get_provider_name(Some(model));
-    Arc::new(LiteLLMProvider::new(
+    LiteLLMProvider::new(
         api_key,
         api_base,
         model.to_string(),
         extra_headers,
         provider_name.as_deref(),
-    ))
+    )
+    .pooled(config)
 }
 
 async fn cmd_gateway(port: u16, _verbose: bool) -> Result<()> {
+    run_gateway(port, async {
+        tokio::signal::ctrl_c().await?;
+        Ok(())
+    })
+    .await
+}
+
+// Runs until `shutdown` resolves: Ctrl+C in a terminal, the SCM's stop
+// request as a Windows service.
+async fn run_gateway(port: u16, shutdown: impl Future<Output = Result<()>>) -> Result<()> {
     let config = load_config(None).unwrap_or_default();
+    events::init(config.events.retention_days);
     let model = config.agents.defaults.model.clone();
-    let normalized_model = model.strip_prefix("litellm/").unwrap_or(&model);
-    let is_bedrock = normalized_model.starts_with("bedrock/");
-    let api_key = config.get_api_key(Some(&model));
-    if api_key.is_none() && !is_bedrock {
-        return Err(anyhow!("No API key configured."));
+    let report = collect_health(&config)?;
+    print!("{}", startup_banner(&config, &report));
+    let blockers = startup_blockers(&report);
+    if !blockers.is_empty() {
+        let labels = blockers
+            .iter()
+            .map(|check| check.label.as_str())
+            .collect::<Vec<_>>();
+        return Err(anyhow!(
+            "Gateway not started: {}. See the fixes above.",
+            labels.join(", ")
+        ));
     }
+    let api_key = config.get_api_key(Some(&model));
 
     let bus = Arc::new(MessageBus::new(1024));
+
+    if let Some(hooks) = ScriptHooks::from_config(&config) {
+        bus.set_script_hooks(hooks);
+    }
     let provider = build_provider(
         &config,
         &model,
         api_key.unwrap_or_else(|| "dummy".to_string()),
     );
+    spawn_warm_up(provider.clone(), config.gateway.prewarm);
     let session_manager = Arc::new(SessionManager::new()?);
 
+    let lease = if config.gateway.cluster.enabled {
+        let lease = LeaderLease::new(&config.gateway.cluster)?;
+        println!("Cluster mode: instance {}", lease.instance_id());
+        Some(lease)
+    } else {
+        None
+    };
+    let lease_task = lease.as_ref().map(LeaderLease::spawn);
+
     let cron_store_path = get_data_path()?.join("cron").join("jobs.json");
-    let cron = Arc::new(CronService::new(cron_store_path));
+    let mut cron = CronService::new(cron_store_path).with_pause_switch(PauseSwitch::new()?);
+    if let Some(lease) = &lease {
+        cron = cron.with_leader_lease(lease.clone());
+    }
+    let cron = Arc::new(cron);
+    let mut batch = BatchService::new(config.clone(), BatchStore::new()?, bus.clone());
+    if let Some(lease) = &lease {
+        batch = batch.with_leader_lease(lease.clone());
+    }
+    let batch = Arc::new(batch);
 
-    let agent = Arc::new(AgentLoop::new(
+    let mut agent = AgentLoop::new(
         bus.clone(),
         provider,
         config.workspace_path(),
@@ -525,29 +1048,68 @@ async fn cmd_gateway(port: u16, _verbose: bool) -> Result<()> {
         config.tools.restrict_to_workspace,
         Some(cron.clone()),
         Some(session_manager.clone()),
-    )?);
+    )?
+    .with_read_only_channels(config.channels.read_only_channels())
+    .with_action_item_extraction(config.agents.defaults.extract_action_items)
+    .with_sampling(config.agents.defaults.sampling())
+    .with_maintenance_sampling(config.agents.defaults.maintenance_sampling())
+    .with_provider_factory(LiteLLMProvider::factory(config.clone()))
+    .with_context_sections(config.agents.defaults.context_sections.clone())
+    .with_personas(config.agents.defaults.personas.clone())
+    .with_model_overrides(config.models.clone())
+    .with_subagent_profiles(config.agents.subagents.clone())
+    .with_batch_service(batch.clone())
+    .with_disabled_tools(&config.tools.disabled)
+    .with_owners(config.channels.owners.clone())
+    .with_git_versioning(config.agents.defaults.git_versioning)
+    .with_citations(
+        config.agents.defaults.citations,
+        config.channels.citation_overrides(),
+    )
+    .with_structured_commands(config.agents.defaults.structured_commands)
+    .with_group_memory_confirmation(config.agents.defaults.confirm_group_memory);
+    if config.channels.abuse.enabled {
+        agent = agent.with_abuse_monitor(AbuseMonitor::new(config.channels.abuse.clone())?);
+    }
+    let agent = Arc::new(agent);
 
     let bus_for_cron = bus.clone();
     let agent_for_cron = agent.clone();
+    let fired_reminders = FiredReminders::new()?;
+    let message_templates = MessageTemplates::new(&config.workspace_path());
     cron.set_on_job(Arc::new(move |job| {
         let bus = bus_for_cron.clone();
         let agent = agent_for_cron.clone();
+        let fired = fired_reminders.clone();
+        let templates = message_templates.clone();
         Box::pin(async move {
-            let response = agent
-                .process_direct(
-                    &job.payload.message,
-                    Some(&format!("cron:{}", job.id)),
-                    job.payload.channel.as_deref(),
-                    job.payload.to.as_deref(),
-                )
-                .await?;
+            let job = templates.expand_job(&job, chrono::Local::now())?;
+            let reminder = job.payload.kind == REMINDER_KIND;
+            let response = if reminder || job.payload.kind == TEMPLATE_KIND {
+                job.payload.message.clone()
+            } else {
+                agent
+                    .process_direct_with_metadata(
+                        &job.payload.message,
+                        Some(&format!("cron:{}", job.id)),
+                        job.payload.channel.as_deref(),
+                        job.payload.to.as_deref(),
+                        proactive_metadata(job.payload.urgent),
+                    )
+                    .await?
+            };
 
             if job.payload.deliver {
                 if let (Some(channel), Some(to)) =
                     (job.payload.channel.clone(), job.payload.to.clone())
                 {
-                    bus.publish_outbound(OutboundMessage::new(channel, to, response.clone()))
-                        .await?;
+                    let mut outbound = if reminder {
+                        fire_reminder(&fired, &job, &channel, &to)
+                    } else {
+                        delivery_message(&job, &channel, &to, &response, chrono::Local::now())
+                    };
+                    mark_proactive(&mut outbound, job.payload.urgent);
+                    bus.publish_outbound(outbound).await?;
                 }
             }
             Ok(Some(response))
@@ -555,19 +1117,32 @@ async fn cmd_gateway(port: u16, _verbose: bool) -> Result<()> {
     }))
     .await;
     cron.start().await?;
+    batch.start().await;
 
-    let heartbeat = Arc::new(HeartbeatService::new(
+    let mut heartbeat = HeartbeatService::new(
         config.workspace_path(),
         DEFAULT_HEARTBEAT_INTERVAL_S,
         true,
-    ));
+        TaskStore::new().ok(),
+    )
+    .with_pause_switch(PauseSwitch::new()?);
+    if let Some(lease) = &lease {
+        heartbeat = heartbeat.with_leader_lease(lease.clone());
+    }
+    let heartbeat = Arc::new(heartbeat);
     let agent_for_heartbeat = agent.clone();
     heartbeat
         .set_on_heartbeat(Arc::new(move |prompt| {
             let agent = agent_for_heartbeat.clone();
             Box::pin(async move {
                 agent
-                    .process_direct(&prompt, Some("heartbeat"), None, None)
+                    .process_direct_with_metadata(
+                        &prompt,
+                        Some("heartbeat"),
+                        None,
+                        None,
+                        proactive_metadata(false),
+                    )
                     .await
                     .unwrap_or_default()
             })
@@ -576,11 +1151,8 @@ async fn cmd_gateway(port: u16, _verbose: bool) -> Result<()> {
     heartbeat.start().await;
 
     let channels = Arc::new(ChannelManager::new(&config, bus.clone()));
-    let enabled_channels = channels.enabled_channels();
-    if enabled_channels.is_empty() {
-        println!("Warning: No channels enabled");
-    } else {
-        println!("Channels enabled: {}", enabled_channels.join(", "));
+    if let Err(err) = control::serve(channels.clone(), agent.clone(), port) {
+        println!("Warning: control API unavailable on port {port}: {err}");
     }
     println!("Gateway started on port {port}");
 
@@ -597,19 +1169,25 @@ async fn cmd_gateway(port: u16, _verbose: bool) -> Result<()> {
         })
     };
 
-    tokio::signal::ctrl_c().await?;
+    shutdown.await?;
     println!("Shutting down...");
     agent.stop();
     heartbeat.stop().await;
     cron.stop().await;
+    batch.stop().await;
     channels.stop_all().await;
     agent_task.abort();
     channels_task.abort();
+    if let (Some(lease), Some(task)) = (&lease, lease_task) {
+        task.abort();
+        lease.release(chrono::Utc::now().timestamp_millis())?;
+    }
     Ok(())
 }
 
-async fn cmd_agent(message: Option<String>, session: &str) -> Result<()> {
+async fn cmd_agent(message: Option<String>, session: &str, explain: bool) -> Result<()> {
     let config = load_config(None).unwrap_or_default();
+    events::init(config.events.retention_days);
     let model = config.agents.defaults.model.clone();
     let normalized_model = model.strip_prefix("litellm/").unwrap_or(&model);
     let is_bedrock = normalized_model.starts_with("bedrock/");
@@ -621,6 +1199,10 @@ async fn cmd_agent(message: Option<String>, session: &str) -> Result<()> {
     }
 
     let bus = Arc::new(MessageBus::new(1024));
+
+    if let Some(hooks) = ScriptHooks::from_config(&config) {
+        bus.set_script_hooks(hooks);
+    }
     let provider = build_provider(
         &config,
         &model,
@@ -628,49 +1210,85 @@ async fn cmd_agent(message: Option<String>, session: &str) -> Result<()> {
     );
     let session_manager = Arc::new(SessionManager::new()?);
     let cron_store_path = get_data_path()?.join("cron").join("jobs.json");
-    let cron = Arc::new(CronService::new(cron_store_path));
+    let cron = Arc::new(CronService::new(cron_store_path).with_pause_switch(PauseSwitch::new()?));
     let channels = Arc::new(ChannelManager::new(&config, bus.clone()));
 
-    let agent_loop = Arc::new(AgentLoop::new(
-        bus.clone(),
-        provider,
-        config.workspace_path(),
-        Some(model.clone()),
-        config.agents.defaults.max_tool_iterations,
-        config.agents.defaults.memory_window,
-        config.tools.web.search.clone(),
-        config.tools.exec.timeout,
-        config.tools.restrict_to_workspace,
-        Some(cron.clone()),
-        Some(session_manager.clone()),
-    )?);
+    let agent_loop = Arc::new(
+        AgentLoop::new(
+            bus.clone(),
+            provider,
+            config.workspace_path(),
+            Some(model.clone()),
+            config.agents.defaults.max_tool_iterations,
+            config.agents.defaults.memory_window,
+            config.tools.web.search.clone(),
+            config.tools.exec.timeout,
+            config.tools.restrict_to_workspace,
+            Some(cron.clone()),
+            Some(session_manager.clone()),
+        )?
+        .with_read_only_channels(config.channels.read_only_channels())
+        .with_action_item_extraction(config.agents.defaults.extract_action_items)
+        .with_sampling(config.agents.defaults.sampling())
+        .with_maintenance_sampling(config.agents.defaults.maintenance_sampling())
+        .with_provider_factory(LiteLLMProvider::factory(config.clone()))
+        .with_context_sections(config.agents.defaults.context_sections.clone())
+        .with_personas(config.agents.defaults.personas.clone())
+        .with_model_overrides(config.models.clone())
+        .with_subagent_profiles(config.agents.subagents.clone())
+        .with_disabled_tools(&config.tools.disabled)
+        .with_owners(config.channels.owners.clone())
+        .with_git_versioning(config.agents.defaults.git_versioning)
+        .with_citations(
+            config.agents.defaults.citations,
+            config.channels.citation_overrides(),
+        )
+        .with_structured_commands(config.agents.defaults.structured_commands)
+        .with_group_memory_confirmation(config.agents.defaults.confirm_group_memory),
+    );
 
     let bus_for_cron = bus.clone();
     let agent_for_cron = agent_loop.clone();
     let channels_for_cron = channels.clone();
+    let fired_reminders = FiredReminders::new()?;
+    let message_templates = MessageTemplates::new(&config.workspace_path());
     cron.set_on_job(Arc::new(move |job| {
         let bus = bus_for_cron.clone();
         let agent = agent_for_cron.clone();
         let channels = channels_for_cron.clone();
+        let fired = fired_reminders.clone();
+        let templates = message_templates.clone();
         Box::pin(async move {
-            let response = agent
-                .process_direct(
-                    &job.payload.message,
-                    Some(&format!("cron:{}", job.id)),
-                    job.payload.channel.as_deref(),
-                    job.payload.to.as_deref(),
-                )
-                .await?;
+            let job = templates.expand_job(&job, chrono::Local::now())?;
+            let reminder = job.payload.kind == REMINDER_KIND;
+            let response = if reminder || job.payload.kind == TEMPLATE_KIND {
+                job.payload.message.clone()
+            } else {
+                agent
+                    .process_direct_with_metadata(
+                        &job.payload.message,
+                        Some(&format!("cron:{}", job.id)),
+                        job.payload.channel.as_deref(),
+                        job.payload.to.as_deref(),
+                        proactive_metadata(job.payload.urgent),
+                    )
+                    .await?
+            };
 
             if job.payload.deliver
                 && let (Some(channel), Some(to)) =
                     (job.payload.channel.clone(), job.payload.to.clone())
             {
-                let outbound = OutboundMessage::new(channel.clone(), to, response.clone());
+                let mut outbound = if reminder {
+                    fire_reminder(&fired, &job, &channel, &to)
+                } else {
+                    delivery_message(&job, &channel, &to, &response, chrono::Local::now())
+                };
+                mark_proactive(&mut outbound, job.payload.urgent);
                 if channel == "cli" {
-                    println!("nanobot-rs[cron]: {response}");
-                } else if let Some(adapter) = channels.get_channel(&channel) {
-                    adapter.send(&outbound).await?;
+                    println!("nanobot-rs[cron]: {}", outbound.content);
+                } else if channels.get_channel(&channel).is_some() {
+                    channels.send_or_hold(&outbound).await?;
                 } else {
                     bus.publish_outbound(outbound).await?;
                 }
@@ -686,6 +1304,9 @@ async fn cmd_agent(message: Option<String>, session: &str) -> Result<()> {
             .process_direct(&content, Some(session), None, None)
             .await?;
         println!("nanobot-rs: {response}");
+        if explain {
+            print_context_report(&agent_loop, session);
+        }
     } else {
         println!("nanobot-rs interactive mode (type exit/quit or Ctrl+C to exit)");
         let stdin = std::io::stdin();
@@ -702,6 +1323,9 @@ async fn cmd_agent(message: Option<String>, session: &str) -> Result<()> {
                 .process_direct(&input, Some(session), None, None)
                 .await?;
             println!("nanobot-rs: {response}");
+            if explain {
+                print_context_report(&agent_loop, session);
+            }
         }
         println!("Goodbye!");
     }
@@ -709,6 +1333,13 @@ async fn cmd_agent(message: Option<String>, session: &str) -> Result<()> {
     Ok(())
 }
 
+fn print_context_report(agent: &AgentLoop, session: &str) {
+    match agent.last_context_report(session) {
+        Some(report) => println!("\n{}", report.render()),
+        None => println!("\nNo context was assembled for this turn."),
+    }
+}
+
 fn is_exit_command(command: &str) -> bool {
     matches!(
         command.to_ascii_lowercase().as_str(),
@@ -811,13 +1442,14 @@ fn cmd_service(command: ServiceCommand) -> Result<()> {
         ServiceCommand::Install {
             name,
             bin,
-            args,
+            port,
             workdir,
             system,
             use_current_user,
             password,
-            auto_install_nssm,
             autostart,
+            restart_delay,
+            reset_hours,
         } => {
             let resolved_name = resolve_service_name(&config, name.as_deref())?;
             persist_service_name_if_overridden(&mut config, name.as_deref())?;
@@ -833,17 +1465,39 @@ fn cmd_service(command: ServiceCommand) -> Result<()> {
             let options = ServiceInstallOptions {
                 name: resolved_name.clone(),
                 binary_path,
-                arguments: args,
+                port,
                 working_directory,
-                log_directory: get_data_path()?.join("logs"),
                 account,
-                auto_install_nssm,
                 autostart,
+                recovery: RecoveryOptions {
+                    restart_delay: Duration::from_secs(restart_delay),
+                    reset_after: Duration::from_secs(reset_hours * 3600),
+                },
             };
             service::install_service(&options)?;
             println!("Service '{}' configured successfully.", resolved_name);
             println!("Use `nanobot-rs service start` to start it.");
         }
+        ServiceCommand::Run {
+            name,
+            port,
+            workdir,
+        } => {
+            let resolved_name = resolve_service_name(&config, name.as_deref())?;
+            if let Some(dir) = workdir {
+                std::env::set_current_dir(&dir)?;
+            }
+            let runtime = tokio::runtime::Handle::current();
+            service::run_service(
+                &resolved_name,
+                Box::new(move |stop| {
+                    runtime.block_on(run_gateway(port, async move {
+                        let _ = stop.await;
+                        Ok(())
+                    }))
+                }),
+            )?;
+        }
         ServiceCommand::Remove { name } => {
             let resolved_name = resolve_service_name(&config, name.as_deref())?;
             persist_service_name_if_overridden(&mut config, name.as_deref())?;
@@ -1033,9 +1687,13 @@ async fn cmd_channels(command: ChannelCommand) -> Result<()> {
                 qq_app
             );
         }
-        ChannelCommand::Login => {
-            cmd_channels_login().await?;
-        }
+        ChannelCommand::Login { channel, account } => match channel.as_str() {
+            "whatsapp" => cmd_channels_login().await?,
+            "email" => cmd_email_login(account.as_deref()).await?,
+            other => return Err(anyhow!("no login flow for channel '{other}'")),
+        },
+        ChannelCommand::Enable { name, port } => cmd_channels_toggle(&name, true, port).await?,
+        ChannelCommand::Disable { name, port } => cmd_channels_toggle(&name, false, port).await?,
     }
     Ok(())
 }
@@ -1078,6 +1736,93 @@ fn cmd_pairing(command: PairingCommand) -> Result<()> {
                 println!("No pending pairing request found for channel={channel} code={code}");
             }
         }
+        PairingCommand::Guests => {
+            let guests = list_guests()?;
+            if guests.is_empty() {
+                println!("No guests.");
+                return Ok(());
+            }
+            println!("Guests (read-only):");
+            for guest in guests {
+                println!(
+                    "- channel={} sender={} approved_ms={}",
+                    guest.channel, guest.sender_id, guest.approved_at_ms
+                );
+            }
+        }
+        PairingCommand::Revoke { channel, sender } => {
+            if revoke_guest(&channel, &sender)? {
+                println!("Revoked guest access: channel={channel} sender={sender}");
+            } else {
+                println!("No guest found for channel={channel} sender={sender}");
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_search(
+    query: String,
+    session: Option<String>,
+    since: Option<String>,
+    limit: usize,
+) -> Result<()> {
+    let config = load_config(None).unwrap_or_default();
+    let index = HistoryIndex::new(Arc::new(SessionManager::new()?), &config.workspace_path())?;
+    let since_ms = since
+        .as_deref()
+        .map(|raw| parse_since(raw, chrono::Utc::now().timestamp_millis()))
+        .transpose()?;
+    let hits = index.search(&SearchQuery {
+        text: query,
+        source: session,
+        since_ms,
+        limit,
+    })?;
+    if hits.is_empty() {
+        println!("No matches.");
+    }
+    for hit in hits {
+        println!("{}", hit.render());
+    }
+    Ok(())
+}
+
+// `--var key=value` flags.
+fn parse_template_vars(raw: &[String]) -> Result<BTreeMap<String, String>> {
+    raw.iter()
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+                .ok_or_else(|| anyhow!("invalid --var '{pair}', expected key=value"))
+        })
+        .collect()
+}
+
+fn cmd_message_templates(command: MessageTemplateCommand) -> Result<()> {
+    let config = load_config(None).unwrap_or_default();
+    let templates = MessageTemplates::new(&config.workspace_path());
+    match command {
+        MessageTemplateCommand::List => {
+            let names = templates.list();
+            if names.is_empty() {
+                println!(
+                    "No message templates in {}",
+                    config.workspace_path().join("templates").display()
+                );
+            }
+            for name in names {
+                println!("- {name}");
+            }
+        }
+        MessageTemplateCommand::Show { name, vars } => {
+            let vars = parse_template_vars(&vars)?;
+            let rendered = templates.render_named(&name, &vars, chrono::Local::now())?;
+            if let Some(title) = rendered.title {
+                println!("Title: {title}\n");
+            }
+            println!("{}", rendered.body);
+        }
     }
     Ok(())
 }
@@ -1126,6 +1871,211 @@ fn cmd_sessions(command: SessionCommand) -> Result<()> {
     Ok(())
 }
 
+fn cmd_feedback(limit: usize) -> Result<()> {
+    let turns = FeedbackStore::new()?.turns()?;
+    println!("{}", render_report(&turns, limit));
+    Ok(())
+}
+
+fn cmd_memory(command: MemoryCommand) -> Result<()> {
+    let config = load_config(None).unwrap_or_default();
+    let memory = MemoryStore::new(config.workspace_path())?;
+    match command {
+        MemoryCommand::Log { id: Some(id), .. } => {
+            let record = memory.find_consolidation(&id)?;
+            println!("{}", record.render());
+            if record.changed() {
+                print!("\n{}", record.diff());
+            } else {
+                println!("\nMEMORY.md unchanged.");
+            }
+        }
+        MemoryCommand::Log { id: None, limit } => {
+            let records = memory.consolidations();
+            if records.is_empty() {
+                println!("No memory consolidations recorded.");
+                return Ok(());
+            }
+            for record in records.iter().rev().take(limit) {
+                println!("{}", record.render());
+            }
+            println!("Use: nanobot-rs memory log <id> to see what changed");
+        }
+        MemoryCommand::Rollback { id, force } => {
+            let record = memory.rollback_consolidation(&id, force)?;
+            println!(
+                "Restored MEMORY.md to its state before consolidation {}",
+                record.id
+            );
+        }
+        MemoryCommand::Versions => {
+            let versions = memory.versions();
+            if versions.is_empty() {
+                println!("No MEMORY.md versions saved yet.");
+                return Ok(());
+            }
+            let current = memory.read_long_term();
+            for version in &versions {
+                let content = fs::read_to_string(&version.path).unwrap_or_default();
+                let saved_at =
+                    chrono::NaiveDateTime::parse_from_str(&version.saved_at, "%Y%m%d%H%M%S")
+                        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_else(|_| version.saved_at.clone());
+                println!(
+                    "{:>4}  {saved_at}  {} lines{}",
+                    version.number,
+                    content.lines().count(),
+                    if content == current {
+                        "  (current)"
+                    } else {
+                        ""
+                    }
+                );
+            }
+            println!("Use: nanobot-rs memory revert <version>");
+        }
+        MemoryCommand::Revert { version } => {
+            memory.revert_to_version(version)?;
+            println!("MEMORY.md restored to version {version}");
+        }
+    }
+    Ok(())
+}
+
+fn cmd_storage(command: StorageCommand) -> Result<()> {
+    let current = storage::current()?;
+    match command {
+        StorageCommand::Status => {
+            println!("Backend: {}", current.backend());
+            for prefix in STORE_PREFIXES {
+                println!("  {prefix:<10} {} entries", current.list(prefix)?.len());
+            }
+        }
+        StorageCommand::Migrate { to, path } => {
+            let config_path = get_config_path()?;
+            let mut config = load_config(Some(&config_path)).unwrap_or_default();
+            let target_config = StorageConfig {
+                backend: to,
+                path: path.unwrap_or_else(|| config.storage.path.clone()),
+            };
+            let target = storage::open(&target_config)?;
+            if target.backend() == current.backend() {
+                return Err(anyhow!("storage is already on {}", current.backend()));
+            }
+            let copied = storage::copy_all(current.as_ref(), target.as_ref())?;
+            config.storage = target_config;
+            save_config(&config, Some(&config_path))?;
+            println!(
+                "Copied {copied} entries from {} to {}; config.json now uses {}.",
+                current.backend(),
+                target.backend(),
+                target.backend()
+            );
+        }
+    }
+    Ok(())
+}
+
+fn cmd_workspace(command: WorkspaceCommand) -> Result<()> {
+    let config = load_config(None).unwrap_or_default();
+    let workspace = config.workspace_path();
+    if !workspace.join(".git").exists() {
+        println!(
+            "{} is not versioned. Set agents.defaults.gitVersioning to true in config.json.",
+            workspace.display()
+        );
+        return Ok(());
+    }
+    let repo = WorkspaceGit::open(&workspace)?;
+    match command {
+        WorkspaceCommand::Log { limit } => {
+            let commits = repo.log(limit)?;
+            if commits.is_empty() {
+                println!("No workspace commits yet.");
+                return Ok(());
+            }
+            for commit in &commits {
+                println!("{}  {}  {}", commit.id, commit.date, commit.subject);
+            }
+            println!("Use: nanobot-rs workspace revert <commit>");
+        }
+        WorkspaceCommand::Revert { commit } => {
+            let id = repo.revert(&commit)?;
+            println!("Reverted {commit} in {id}");
+        }
+    }
+    Ok(())
+}
+
+fn cmd_events(
+    since: Option<String>,
+    channel: Option<String>,
+    level: Option<String>,
+    kind: Option<String>,
+    limit: usize,
+) -> Result<()> {
+    let config = load_config(None).unwrap_or_default();
+    let min_level = match level.as_deref() {
+        Some(raw) => Some(
+            EventLevel::parse(raw)
+                .ok_or_else(|| anyhow!("unknown level '{raw}' (info, warn, error)"))?,
+        ),
+        None => None,
+    };
+    let now = chrono::Utc::now().timestamp_millis();
+    let since_ms = since
+        .as_deref()
+        .map(|raw| parse_since(raw, now))
+        .transpose()?;
+    let events = EventLog::new(config.events.retention_days)?.query(&EventFilter {
+        since_ms,
+        channel,
+        min_level,
+        kind,
+        limit,
+    })?;
+    if events.is_empty() {
+        println!("No events.");
+    }
+    for event in events {
+        println!("{}", event.render());
+    }
+    Ok(())
+}
+
+// Persists the flag in config.json, then tells a running gateway to start or
+// stop just that adapter.
+async fn cmd_channels_toggle(name: &str, enabled: bool, port: Option<u16>) -> Result<()> {
+    let config_path = get_config_path()?;
+    let mut config = load_config(Some(&config_path))?;
+    if !config.channels.set_enabled(name, enabled) {
+        return Err(anyhow!(
+            "unknown channel '{name}' (known: {})",
+            CHANNEL_NAMES.join(", ")
+        ));
+    }
+    save_config(&config, Some(&config_path))?;
+    println!(
+        "Channel {name} {} in {}",
+        if enabled { "enabled" } else { "disabled" },
+        config_path.display()
+    );
+
+    let port = port.unwrap_or(config.gateway.port);
+    match control::set_channel(port, name, enabled).await {
+        Ok(message) => println!("Gateway: {message}"),
+        Err(err)
+            if err
+                .downcast_ref::<reqwest::Error>()
+                .is_some_and(|err| err.is_connect()) =>
+        {
+            println!("No gateway on port {port}; the change applies on its next start.")
+        }
+        Err(err) => println!("Gateway: {err}"),
+    }
+    Ok(())
+}
+
 async fn cmd_channels_login() -> Result<()> {
     let config = load_config(None).unwrap_or_default();
     let bridge_dir = prepare_bridge_dir().await?;
@@ -1141,6 +2091,39 @@ async fn cmd_channels_login() -> Result<()> {
     run_npm(&["start"], &bridge_dir, &env_vars).await
 }
 
+async fn cmd_email_login(account: Option<&str>) -> Result<()> {
+    let config = load_config(None).unwrap_or_default();
+    let email = match account {
+        Some(id) => config
+            .channels
+            .email
+            .accounts
+            .iter()
+            .find(|account| account.id == id)
+            .ok_or_else(|| anyhow!("no email account '{id}' in channels.email.accounts"))?,
+        None => &config.channels.email,
+    };
+    let oauth = email
+        .oauth
+        .clone()
+        .ok_or_else(|| anyhow!("oauth is not configured for this email account"))?;
+    if email.imap_username.trim().is_empty() {
+        return Err(anyhow!("channels.email.imapUsername is required"));
+    }
+    let session = OAuthSession::new(oauth, &email.imap_username)?;
+    let token = session.device_login().await?;
+    println!(
+        "✓ Authorized {}{}",
+        email.imap_username,
+        if token.refresh_token.is_some() {
+            ""
+        } else {
+            " (no refresh token; you will need to log in again when it expires)"
+        }
+    );
+    Ok(())
+}
+
 async fn prepare_bridge_dir() -> Result<PathBuf> {
     let user_bridge = get_data_path()?.join("bridge");
     if user_bridge.join("dist").join("index.js").exists() {
@@ -1247,24 +2230,36 @@ async fn cmd_cron(command: CronCommand) -> Result<()> {
     let _ = cron.start().await;
 
     match command {
-        CronCommand::List { all } => {
-            let jobs = cron.list_jobs(all).await;
+        CronCommand::List { all, owner } => {
+            let mut jobs = cron.list_jobs(all).await;
+            if let Some(owner) = &owner {
+                jobs.retain(|job| job.payload.owner.as_ref() == Some(owner));
+            }
             if jobs.is_empty() {
                 println!("No scheduled jobs.");
             } else {
+                let now = chrono::Utc::now().timestamp_millis();
                 for job in jobs {
                     let schedule = match job.schedule.kind.as_str() {
                         "every" => format!("every {}s", job.schedule.every_ms.unwrap_or(0) / 1000),
                         "cron" => job.schedule.expr.unwrap_or_default(),
-                        "at" => format!("at {}", job.schedule.at_ms.unwrap_or_default()),
+                        "at" => "once".to_string(),
                         _ => "unknown".to_string(),
                     };
+                    let next = job
+                        .state
+                        .next_run_at_ms
+                        .map(|ms| format_local(ms, now))
+                        .unwrap_or_else(|| "-".to_string());
+                    let owner = job
+                        .payload
+                        .owner
+                        .as_deref()
+                        .map(|owner| format!(" owner={owner}"))
+                        .unwrap_or_default();
                     println!(
-                        "{} {} [{}] next={}",
-                        job.id,
-                        job.name,
-                        schedule,
-                        job.state.next_run_at_ms.unwrap_or_default()
+                        "{} {} [{}] next={}{owner}",
+                        job.id, job.name, schedule, next
                     );
                 }
             }
@@ -1278,7 +2273,21 @@ async fn cmd_cron(command: CronCommand) -> Result<()> {
             deliver,
             to,
             channel,
+            format,
         } => {
+            let CronFormatArgs {
+                title,
+                template,
+                template_name,
+                vars,
+            } = *format;
+            let vars = parse_template_vars(&vars)?;
+            if let Some(name) = &template_name {
+                MessageTemplates::new(&load_config(None).unwrap_or_default().workspace_path())
+                    .check_for_job(name, &vars, chrono::Local::now())?;
+            } else if message.is_empty() {
+                return Err(anyhow!("Must specify --message or --template-name"));
+            }
             let schedule = if let Some(every) = every {
                 CronSchedule {
                     kind: "every".to_string(),
@@ -1292,21 +2301,53 @@ async fn cmd_cron(command: CronCommand) -> Result<()> {
                     ..Default::default()
                 }
             } else if let Some(at) = at {
-                let ts = chrono::DateTime::parse_from_rfc3339(&at)
+                let at_ms = parse_at(&at)
+                    .and_then(|at_ms| {
+                        ensure_future(at_ms, chrono::Utc::now().timestamp_millis())?;
+                        Ok(at_ms)
+                    })
                     .map_err(|e| anyhow!("invalid --at value: {e}"))?;
                 CronSchedule {
                     kind: "at".to_string(),
-                    at_ms: Some(ts.timestamp_millis()),
+                    at_ms: Some(at_ms),
                     ..Default::default()
                 }
             } else {
                 return Err(anyhow!("Must specify --every, --cron, or --at"));
             };
 
-            let job = cron
+            let mut job = cron
                 .add_job(name, schedule, message, deliver, channel, to, false)
                 .await?;
+            if let Some(template_name) = template_name
+                && let Some(updated) = cron
+                    .update_payload(&job.id, |payload| {
+                        if payload.message.is_empty() {
+                            payload.kind = TEMPLATE_KIND.to_string();
+                        }
+                        payload.message_template = Some(template_name);
+                        payload.template_vars = vars;
+                    })
+                    .await?
+            {
+                job = updated;
+            }
+            if title.is_some() || template.is_some() {
+                let template = CronTemplate {
+                    title,
+                    body: template,
+                };
+                if let Some(updated) = cron.set_template(&job.id, Some(template)).await? {
+                    job = updated;
+                }
+            }
             println!("Added job '{}' ({})", job.name, job.id);
+            if let Some(next) = job.state.next_run_at_ms {
+                println!(
+                    "Next run: {}",
+                    format_local(next, chrono::Utc::now().timestamp_millis())
+                );
+            }
         }
         CronCommand::Remove { job_id } => {
             if cron.remove_job(&job_id).await? {
@@ -1340,6 +2381,10 @@ async fn cmd_cron(command: CronCommand) -> Result<()> {
             }
 
             let bus = Arc::new(MessageBus::new(1024));
+
+            if let Some(hooks) = ScriptHooks::from_config(&config) {
+                bus.set_script_hooks(hooks);
+            }
             let provider = build_provider(
                 &config,
                 &model,
@@ -1347,44 +2392,78 @@ async fn cmd_cron(command: CronCommand) -> Result<()> {
             );
             let session_manager = Arc::new(SessionManager::new()?);
             let channels = Arc::new(ChannelManager::new(&config, bus.clone()));
-            let agent = Arc::new(AgentLoop::new(
-                bus.clone(),
-                provider,
-                config.workspace_path(),
-                Some(model),
-                config.agents.defaults.max_tool_iterations,
-                config.agents.defaults.memory_window,
-                config.tools.web.search.clone(),
-                config.tools.exec.timeout,
-                config.tools.restrict_to_workspace,
-                Some(cron.clone()),
-                Some(session_manager),
-            )?);
+            let agent = Arc::new(
+                AgentLoop::new(
+                    bus.clone(),
+                    provider,
+                    config.workspace_path(),
+                    Some(model),
+                    config.agents.defaults.max_tool_iterations,
+                    config.agents.defaults.memory_window,
+                    config.tools.web.search.clone(),
+                    config.tools.exec.timeout,
+                    config.tools.restrict_to_workspace,
+                    Some(cron.clone()),
+                    Some(session_manager),
+                )?
+                .with_read_only_channels(config.channels.read_only_channels())
+                .with_action_item_extraction(config.agents.defaults.extract_action_items)
+                .with_sampling(config.agents.defaults.sampling())
+                .with_maintenance_sampling(config.agents.defaults.maintenance_sampling())
+                .with_provider_factory(LiteLLMProvider::factory(config.clone()))
+                .with_context_sections(config.agents.defaults.context_sections.clone())
+                .with_personas(config.agents.defaults.personas.clone())
+                .with_model_overrides(config.models.clone())
+                .with_subagent_profiles(config.agents.subagents.clone())
+                .with_disabled_tools(&config.tools.disabled)
+                .with_owners(config.channels.owners.clone())
+                .with_git_versioning(config.agents.defaults.git_versioning)
+                .with_citations(
+                    config.agents.defaults.citations,
+                    config.channels.citation_overrides(),
+                )
+                .with_structured_commands(config.agents.defaults.structured_commands)
+                .with_group_memory_confirmation(config.agents.defaults.confirm_group_memory),
+            );
 
             let bus_for_cron = bus.clone();
             let agent_for_cron = agent.clone();
             let channels_for_cron = channels.clone();
+            let fired_reminders = FiredReminders::new()?;
+            let message_templates = MessageTemplates::new(&config.workspace_path());
             cron.set_on_job(Arc::new(move |job| {
                 let bus = bus_for_cron.clone();
                 let agent = agent_for_cron.clone();
                 let channels = channels_for_cron.clone();
+                let fired = fired_reminders.clone();
+                let templates = message_templates.clone();
                 Box::pin(async move {
-                    let response = agent
-                        .process_direct(
-                            &job.payload.message,
-                            Some(&format!("cron:{}", job.id)),
-                            job.payload.channel.as_deref(),
-                            job.payload.to.as_deref(),
-                        )
-                        .await?;
+                    let job = templates.expand_job(&job, chrono::Local::now())?;
+                    let reminder = job.payload.kind == REMINDER_KIND;
+                    let response = if reminder || job.payload.kind == TEMPLATE_KIND {
+                        job.payload.message.clone()
+                    } else {
+                        agent
+                            .process_direct(
+                                &job.payload.message,
+                                Some(&format!("cron:{}", job.id)),
+                                job.payload.channel.as_deref(),
+                                job.payload.to.as_deref(),
+                            )
+                            .await?
+                    };
 
                     if job.payload.deliver
                         && let (Some(channel), Some(to)) =
                             (job.payload.channel.clone(), job.payload.to.clone())
                     {
-                        let outbound = OutboundMessage::new(channel.clone(), to, response.clone());
+                        let outbound = if reminder {
+                            fire_reminder(&fired, &job, &channel, &to)
+                        } else {
+                            delivery_message(&job, &channel, &to, &response, chrono::Local::now())
+                        };
                         if channel == "cli" {
-                            println!("nanobot-rs[cron]: {response}");
+                            println!("nanobot-rs[cron]: {}", outbound.content);
                         } else if let Some(adapter) = channels.get_channel(&channel) {
                             adapter.send(&outbound).await?;
                         } else {
@@ -1,6 +1,91 @@
 use crate::utils::ensure_dir;
+use anyhow::{Result, anyhow};
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
 use std::path::PathBuf;
 
+const MAX_CONSOLIDATIONS: usize = 200;
+const MAX_VERSIONS: usize = 100;
+
+// A numbered snapshot of MEMORY.md, taken whenever its content changes.
+#[derive(Debug, Clone)]
+pub struct MemoryVersion {
+    pub number: u32,
+    pub saved_at: String,
+    pub path: PathBuf,
+}
+
+// One memory consolidation run, kept so users can audit what was remembered
+// or dropped and put MEMORY.md back as it was.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsolidationRecord {
+    pub id: String,
+    pub at_ms: i64,
+    pub session_key: String,
+    pub model: String,
+    pub duration_ms: u64,
+    pub input_messages: usize,
+    pub input_chars: usize,
+    pub history_chars: usize,
+    pub memory_before: String,
+    pub memory_after: String,
+    #[serde(default)]
+    pub rolled_back: bool,
+}
+
+impl ConsolidationRecord {
+    pub fn changed(&self) -> bool {
+        self.memory_before.trim() != self.memory_after.trim()
+    }
+
+    // (added, removed) MEMORY.md lines.
+    pub fn line_changes(&self) -> (usize, usize) {
+        let diff = TextDiff::from_lines(&self.memory_before, &self.memory_after);
+        diff.iter_all_changes()
+            .fold((0, 0), |(added, removed), change| match change.tag() {
+                ChangeTag::Insert => (added + 1, removed),
+                ChangeTag::Delete => (added, removed + 1),
+                ChangeTag::Equal => (added, removed),
+            })
+    }
+
+    pub fn diff(&self) -> String {
+        TextDiff::from_lines(&self.memory_before, &self.memory_after)
+            .unified_diff()
+            .context_radius(2)
+            .header("MEMORY.md (before)", "MEMORY.md (after)")
+            .to_string()
+    }
+
+    pub fn render(&self) -> String {
+        let at = Local
+            .timestamp_millis_opt(self.at_ms)
+            .single()
+            .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let (added, removed) = self.line_changes();
+        format!(
+            "{} {at} {} {} msgs/{} chars -> history {} chars, memory {} -> {} chars (+{added}/-{removed} lines), {} in {:.1}s{}",
+            self.id,
+            self.session_key,
+            self.input_messages,
+            self.input_chars,
+            self.history_chars,
+            self.memory_before.chars().count(),
+            self.memory_after.chars().count(),
+            self.model,
+            self.duration_ms as f64 / 1000.0,
+            if self.rolled_back {
+                " [rolled back]"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct MemoryStore {
     pub memory_dir: PathBuf,
@@ -24,8 +109,74 @@ impl MemoryStore {
         std::fs::read_to_string(&self.memory_file).unwrap_or_default()
     }
 
+    // Every change is also saved as the next numbered version, so a bad
+    // update can be reverted; the content before the first tracked change is
+    // kept as a version too.
     pub fn write_long_term(&self, content: &str) -> std::io::Result<()> {
-        std::fs::write(&self.memory_file, content)
+        let current = self.read_long_term();
+        if current == content {
+            return Ok(());
+        }
+        if self.versions().is_empty() && !current.trim().is_empty() {
+            self.save_version(&current)?;
+        }
+        std::fs::write(&self.memory_file, content)?;
+        self.save_version(content)
+    }
+
+    fn versions_dir(&self) -> PathBuf {
+        self.memory_dir.join("versions")
+    }
+
+    // Oldest first.
+    pub fn versions(&self) -> Vec<MemoryVersion> {
+        let Ok(dir) = std::fs::read_dir(self.versions_dir()) else {
+            return Vec::new();
+        };
+        let mut versions = dir
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter_map(|path| {
+                let stem = path.file_stem()?.to_str()?.to_string();
+                let (number, saved_at) = stem.split_once('-')?;
+                Some(MemoryVersion {
+                    number: number.parse().ok()?,
+                    saved_at: saved_at.to_string(),
+                    path,
+                })
+            })
+            .collect::<Vec<_>>();
+        versions.sort_by_key(|version| version.number);
+        versions
+    }
+
+    fn save_version(&self, content: &str) -> std::io::Result<()> {
+        let dir = ensure_dir(&self.versions_dir())?;
+        let versions = self.versions();
+        let number = versions.last().map(|v| v.number + 1).unwrap_or(1);
+        let stamp = Local::now().format("%Y%m%d%H%M%S");
+        std::fs::write(dir.join(format!("{number:04}-{stamp}.md")), content)?;
+        let excess = (versions.len() + 1).saturating_sub(MAX_VERSIONS);
+        for old in versions.iter().take(excess) {
+            std::fs::remove_file(&old.path)?;
+        }
+        Ok(())
+    }
+
+    pub fn read_version(&self, number: u32) -> Result<String> {
+        let version = self
+            .versions()
+            .into_iter()
+            .find(|version| version.number == number)
+            .ok_or_else(|| anyhow!("no MEMORY.md version {number}"))?;
+        Ok(std::fs::read_to_string(version.path)?)
+    }
+
+    // Restores an earlier version; the restore itself becomes a new version,
+    // so it can be undone the same way.
+    pub fn revert_to_version(&self, number: u32) -> Result<()> {
+        let content = self.read_version(number)?;
+        self.write_long_term(&content)?;
+        Ok(())
     }
 
     pub fn append_history(&self, entry: &str) -> std::io::Result<()> {
@@ -35,6 +186,74 @@ impl MemoryStore {
         std::fs::write(&self.history_file, existing)
     }
 
+    fn consolidations_file(&self) -> PathBuf {
+        self.memory_dir.join("consolidations.jsonl")
+    }
+
+    pub fn consolidations(&self) -> Vec<ConsolidationRecord> {
+        std::fs::read_to_string(self.consolidations_file())
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    fn save_consolidations(&self, records: &[ConsolidationRecord]) -> Result<()> {
+        let start = records.len().saturating_sub(MAX_CONSOLIDATIONS);
+        let body = records[start..]
+            .iter()
+            .filter_map(|record| serde_json::to_string(record).ok())
+            .map(|line| line + "\n")
+            .collect::<String>();
+        std::fs::write(self.consolidations_file(), body)?;
+        Ok(())
+    }
+
+    pub fn record_consolidation(&self, record: ConsolidationRecord) -> Result<()> {
+        let mut records = self.consolidations();
+        records.push(record);
+        self.save_consolidations(&records)
+    }
+
+    pub fn find_consolidation(&self, id: &str) -> Result<ConsolidationRecord> {
+        let mut matches = self
+            .consolidations()
+            .into_iter()
+            .filter(|record| record.id.starts_with(id.trim()))
+            .collect::<Vec<_>>();
+        match matches.len() {
+            0 => Err(anyhow!("no consolidation with id '{id}'")),
+            1 => Ok(matches.remove(0)),
+            _ => Err(anyhow!("id '{id}' is ambiguous")),
+        }
+    }
+
+    // Restores MEMORY.md to what it was before the given consolidation. When
+    // memory changed again since, `force` is required so later edits are not
+    // dropped silently.
+    pub fn rollback_consolidation(&self, id: &str, force: bool) -> Result<ConsolidationRecord> {
+        let target = self.find_consolidation(id)?;
+        if !target.changed() {
+            return Err(anyhow!(
+                "consolidation {} did not change MEMORY.md",
+                target.id
+            ));
+        }
+        if !force && self.read_long_term().trim() != target.memory_after.trim() {
+            return Err(anyhow!(
+                "MEMORY.md changed after consolidation {}; use --force to restore it anyway",
+                target.id
+            ));
+        }
+        self.write_long_term(&target.memory_before)?;
+        let mut records = self.consolidations();
+        for record in records.iter_mut().filter(|record| record.id == target.id) {
+            record.rolled_back = true;
+        }
+        self.save_consolidations(&records)?;
+        Ok(target)
+    }
+
     pub fn get_memory_context(&self) -> String {
         let long_term = self.read_long_term();
         if long_term.is_empty() {
@@ -44,3 +263,86 @@ impl MemoryStore {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: &str, before: &str, after: &str) -> ConsolidationRecord {
+        ConsolidationRecord {
+            id: id.to_string(),
+            at_ms: 0,
+            session_key: "cli:direct".to_string(),
+            model: "test-model".to_string(),
+            duration_ms: 1500,
+            input_messages: 12,
+            input_chars: 900,
+            history_chars: 200,
+            memory_before: before.to_string(),
+            memory_after: after.to_string(),
+            rolled_back: false,
+        }
+    }
+
+    #[test]
+    fn consolidations_are_logged_and_rolled_back() {
+        let dir = std::env::temp_dir().join(format!("nanobot-memory-{}", uuid::Uuid::new_v4()));
+        let store = MemoryStore::new(dir.clone()).expect("store");
+        store
+            .write_long_term("- likes tea\n- lives in Berlin\n")
+            .expect("write");
+        store
+            .record_consolidation(record(
+                "a1",
+                "- likes tea\n",
+                "- likes tea\n- lives in Berlin\n",
+            ))
+            .expect("record");
+        store
+            .record_consolidation(record(
+                "b2",
+                "- likes tea\n- lives in Berlin\n",
+                "- lives in Berlin\n",
+            ))
+            .expect("record");
+        store.write_long_term("- lives in Berlin\n").expect("write");
+
+        let log = store.consolidations();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[1].line_changes(), (0, 1));
+        assert!(log[1].diff().contains("-- likes tea"));
+        assert!(log[1].render().contains("(+0/-1 lines)"));
+
+        assert!(store.rollback_consolidation("a", false).is_err());
+        let rolled = store.rollback_consolidation("b", false).expect("rollback");
+        assert_eq!(rolled.id, "b2");
+        assert_eq!(store.read_long_term(), "- likes tea\n- lives in Berlin\n");
+        assert!(store.find_consolidation("b2").expect("find").rolled_back);
+        assert!(store.find_consolidation("zz").is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn memory_changes_are_versioned_and_revertible() {
+        let dir = std::env::temp_dir().join(format!("nanobot-memory-{}", uuid::Uuid::new_v4()));
+        let store = MemoryStore::new(dir.clone()).expect("store");
+        std::fs::write(&store.memory_file, "hand written\n").expect("seed");
+
+        store.write_long_term("v2\n").expect("write");
+        store.write_long_term("v2\n").expect("unchanged write");
+        store.write_long_term("bad update\n").expect("write");
+        let numbers = store
+            .versions()
+            .iter()
+            .map(|version| version.number)
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, vec![1, 2, 3]);
+        assert_eq!(store.read_version(1).expect("v1"), "hand written\n");
+
+        store.revert_to_version(2).expect("revert");
+        assert_eq!(store.read_long_term(), "v2\n");
+        assert_eq!(store.versions().len(), 4);
+        assert!(store.revert_to_version(9).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,374 @@
+use crate::cron::types::{CronJob, CronTemplate};
+use crate::metrics::MetricStore;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Local};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+// Payload kind of cron jobs that send a rendered template as-is, without an
+// agent turn.
+pub const TEMPLATE_KIND: &str = "template";
+
+// Placeholders cron delivery fills in later; rendering leaves them alone.
+const DELIVERY_PLACEHOLDERS: [&str; 2] = ["response", "name"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageTemplate {
+    pub name: String,
+    // From a `title:` line in the frontmatter: email subject, Feishu card
+    // title, or a bold first line elsewhere.
+    pub title: Option<String>,
+    pub body: String,
+}
+
+// Reusable outbound messages kept as `<workspace>/templates/<name>.md`.
+// Placeholders: {date}, {time}, {weekday}, {recipient}, {metric:<name>} (the
+// latest logged value) and any variable the caller passes.
+#[derive(Debug, Clone)]
+pub struct MessageTemplates {
+    dir: PathBuf,
+    metrics: MetricStore,
+}
+
+impl MessageTemplates {
+    pub fn new(workspace: &Path) -> Self {
+        Self {
+            dir: workspace.join("templates"),
+            metrics: MetricStore::new(workspace.join("metrics")),
+        }
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut names = entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                (path.extension()? == "md")
+                    .then(|| path.file_stem()?.to_str().map(ToOwned::to_owned))
+                    .flatten()
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    pub fn load(&self, name: &str) -> Result<MessageTemplate> {
+        let name = name.trim().trim_end_matches(".md");
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(anyhow!("invalid template name '{name}'"));
+        }
+        let path = self.dir.join(format!("{name}.md"));
+        let raw = std::fs::read_to_string(&path).map_err(|_| {
+            let known = self.list();
+            if known.is_empty() {
+                anyhow!(
+                    "template '{name}' not found; add it as templates/{name}.md in the workspace"
+                )
+            } else {
+                anyhow!(
+                    "template '{name}' not found; available: {}",
+                    known.join(", ")
+                )
+            }
+        })?;
+        let (title, body) = split_frontmatter(&raw);
+        Ok(MessageTemplate {
+            name: name.to_string(),
+            title,
+            body,
+        })
+    }
+
+    // Fills every placeholder except `keep`. Fails with the full list of
+    // missing values so the caller can supply them in one go.
+    pub fn render(
+        &self,
+        template: &MessageTemplate,
+        vars: &BTreeMap<String, String>,
+        keep: &[&str],
+        now: DateTime<Local>,
+    ) -> Result<MessageTemplate> {
+        let mut missing = Vec::new();
+        let title = template
+            .title
+            .as_deref()
+            .map(|title| self.fill(title, vars, keep, now, &mut missing));
+        let body = self.fill(&template.body, vars, keep, now, &mut missing);
+        if !missing.is_empty() {
+            return Err(anyhow!(
+                "template '{}' needs values for: {}",
+                template.name,
+                missing.join(", ")
+            ));
+        }
+        Ok(MessageTemplate {
+            name: template.name.clone(),
+            title,
+            body,
+        })
+    }
+
+    pub fn render_named(
+        &self,
+        name: &str,
+        vars: &BTreeMap<String, String>,
+        now: DateTime<Local>,
+    ) -> Result<MessageTemplate> {
+        self.render(&self.load(name)?, vars, &[], now)
+    }
+
+    // Whether a cron job could render `name` now; run before creating one.
+    pub fn check_for_job(
+        &self,
+        name: &str,
+        vars: &BTreeMap<String, String>,
+        now: DateTime<Local>,
+    ) -> Result<()> {
+        self.render(&self.load(name)?, vars, &DELIVERY_PLACEHOLDERS, now)
+            .map(|_| ())
+    }
+
+    // Resolves a job's message template at run time, so edits to the file
+    // apply from the next run. Template jobs get the rendered body as their
+    // message; agent turns get it as the wrapper around {response}.
+    pub fn expand_job(&self, job: &CronJob, now: DateTime<Local>) -> Result<CronJob> {
+        let Some(name) = &job.payload.message_template else {
+            return Ok(job.clone());
+        };
+        let rendered = self.render(
+            &self.load(name)?,
+            &job.payload.template_vars,
+            &DELIVERY_PLACEHOLDERS,
+            now,
+        )?;
+        let mut job = job.clone();
+        let body = if job.payload.kind == TEMPLATE_KIND {
+            job.payload.message = rendered.body;
+            None
+        } else {
+            Some(rendered.body)
+        };
+        job.payload.template = Some(CronTemplate {
+            title: rendered.title,
+            body,
+        });
+        Ok(job)
+    }
+
+    fn value(
+        &self,
+        key: &str,
+        vars: &BTreeMap<String, String>,
+        now: DateTime<Local>,
+    ) -> Option<String> {
+        if let Some(value) = vars.get(key) {
+            return Some(value.clone());
+        }
+        if let Some(metric) = key.strip_prefix("metric:") {
+            let latest = self.metrics.entries(metric).ok()?.pop()?;
+            return Some(if latest.unit.is_empty() {
+                latest.value.to_string()
+            } else {
+                format!("{} {}", latest.value, latest.unit)
+            });
+        }
+        match key {
+            "date" => Some(now.format("%Y-%m-%d").to_string()),
+            "time" => Some(now.format("%H:%M").to_string()),
+            "weekday" => Some(now.format("%A").to_string()),
+            _ => None,
+        }
+    }
+
+    fn fill(
+        &self,
+        text: &str,
+        vars: &BTreeMap<String, String>,
+        keep: &[&str],
+        now: DateTime<Local>,
+        missing: &mut Vec<String>,
+    ) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let Some(key) = after
+                .find('}')
+                .map(|end| &after[..end])
+                .filter(|key| is_placeholder(key))
+            else {
+                out.push('{');
+                rest = after;
+                continue;
+            };
+            match self.value(key, vars, now) {
+                Some(value) if !keep.contains(&key) => out.push_str(&value),
+                _ => {
+                    if !keep.contains(&key) && !missing.iter().any(|m| m == key) {
+                        missing.push(key.to_string());
+                    }
+                    out.push_str(&rest[start..start + key.len() + 2]);
+                }
+            }
+            rest = &after[key.len() + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+// The `vars` object of a tool call; numbers and booleans are taken as text.
+pub fn template_vars(raw: Option<&Value>) -> BTreeMap<String, String> {
+    raw.and_then(Value::as_object)
+        .map(|vars| {
+            vars.iter()
+                .map(|(key, value)| {
+                    let value = match value {
+                        Value::String(text) => text.clone(),
+                        other => other.to_string(),
+                    };
+                    (key.clone(), value)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// `{name}` or `{metric:name}`; other braces (JSON, code) stay literal.
+fn is_placeholder(key: &str) -> bool {
+    let key = key.strip_prefix("metric:").unwrap_or(key);
+    !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+fn split_frontmatter(raw: &str) -> (Option<String>, String) {
+    let Some(rest) = raw
+        .strip_prefix("---\n")
+        .or_else(|| raw.strip_prefix("---\r\n"))
+    else {
+        return (None, raw.trim().to_string());
+    };
+    let Some(end) = rest.find("\n---") else {
+        return (None, raw.trim().to_string());
+    };
+    let title = rest[..end].lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == "title")
+            .then(|| {
+                value
+                    .trim()
+                    .trim_matches('"')
+                    .trim_matches('\'')
+                    .to_string()
+            })
+            .filter(|title| !title.is_empty())
+    });
+    let body = rest[end + 4..].trim_start_matches('-');
+    (title, body.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cron::CronPayload;
+    use crate::metrics::MetricEntry;
+    use chrono::TimeZone;
+
+    #[test]
+    fn renders_workspace_templates_with_variables_and_metrics() {
+        let workspace =
+            std::env::temp_dir().join(format!("nanobot-templates-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(workspace.join("templates")).expect("templates dir");
+        std::fs::write(
+            workspace.join("templates/weekly.md"),
+            "---\ntitle: Week of {date}\n---\nHi {recipient}, weight {metric:weight} on {weekday}. {\"raw\": 1}\n\n{response}",
+        )
+        .expect("write template");
+        let templates = MessageTemplates::new(&workspace);
+        let now = Local
+            .with_ymd_and_hms(2026, 3, 9, 8, 0, 0)
+            .single()
+            .expect("valid time");
+        assert_eq!(templates.list(), vec!["weekly"]);
+        assert!(templates.load("../secret").is_err());
+        assert!(
+            templates
+                .load("nope")
+                .unwrap_err()
+                .to_string()
+                .contains("available: weekly")
+        );
+
+        let mut vars = BTreeMap::new();
+        let err = templates
+            .render_named("weekly", &vars, now)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("recipient, metric:weight, response"), "{err}");
+
+        MetricStore::new(workspace.join("metrics"))
+            .log(
+                "weight",
+                &MetricEntry {
+                    at: now,
+                    value: 72.4,
+                    unit: "kg".to_string(),
+                    note: String::new(),
+                },
+            )
+            .expect("log metric");
+        vars.insert("recipient".to_string(), "Ana".to_string());
+        let template = templates.load("weekly").expect("load");
+        let rendered = templates
+            .render(&template, &vars, &DELIVERY_PLACEHOLDERS, now)
+            .expect("render");
+        assert_eq!(rendered.title.as_deref(), Some("Week of 2026-03-09"));
+        assert_eq!(
+            rendered.body,
+            "Hi Ana, weight 72.4 kg on Monday. {\"raw\": 1}\n\n{response}"
+        );
+
+        let mut job = CronJob {
+            id: "j1".to_string(),
+            name: "weekly".to_string(),
+            enabled: true,
+            schedule: Default::default(),
+            payload: CronPayload {
+                kind: TEMPLATE_KIND.to_string(),
+                message_template: Some("weekly".to_string()),
+                template_vars: vars,
+                ..Default::default()
+            },
+            state: Default::default(),
+            created_at_ms: 0,
+            updated_at_ms: 0,
+            delete_after_run: false,
+        };
+        let expanded = templates.expand_job(&job, now).expect("expand");
+        assert!(expanded.payload.message.starts_with("Hi Ana"));
+        let template = expanded.payload.template.expect("delivery template");
+        assert_eq!(template.body, None);
+
+        job.payload.kind = "agent_turn".to_string();
+        let expanded = templates.expand_job(&job, now).expect("expand");
+        assert!(
+            expanded
+                .payload
+                .template
+                .and_then(|t| t.body)
+                .is_some_and(|body| body.ends_with("{response}"))
+        );
+        let _ = std::fs::remove_dir_all(workspace);
+    }
+}
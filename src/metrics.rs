@@ -0,0 +1,325 @@
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+const CSV_HEADER: &str = "timestamp,value,unit,note";
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const BAR_WIDTH: usize = 24;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricEntry {
+    pub at: DateTime<Local>,
+    pub value: f64,
+    pub unit: String,
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    Day,
+    Week,
+    Month,
+}
+
+impl Bucket {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_lowercase().as_str() {
+            "day" | "daily" => Some(Self::Day),
+            "week" | "weekly" => Some(Self::Week),
+            "month" | "monthly" => Some(Self::Month),
+            _ => None,
+        }
+    }
+
+    fn start_of(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date - Duration::days(i64::from(date.weekday().num_days_from_monday())),
+            Self::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+}
+
+// Metric names become file names: "Body Weight" -> "body_weight".
+pub fn metric_slug(name: &str) -> String {
+    let slug = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    slug.trim_matches('_').to_string()
+}
+
+fn csv_field(raw: &str) -> String {
+    if raw.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", raw.replace('"', "\"\""))
+    } else {
+        raw.to_string()
+    }
+}
+
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+fn format_value(value: f64) -> String {
+    let rounded = (value * 100.0).round() / 100.0;
+    if rounded.fract() == 0.0 {
+        format!("{rounded:.0}")
+    } else {
+        format!("{rounded}")
+    }
+}
+
+// One CSV per metric under `workspace/metrics`, so the files stay easy to
+// open in a spreadsheet.
+#[derive(Debug, Clone)]
+pub struct MetricStore {
+    dir: PathBuf,
+}
+
+impl MetricStore {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        let slug = metric_slug(name);
+        if slug.is_empty() {
+            return Err(anyhow!("metric name must contain letters or digits"));
+        }
+        Ok(self.dir.join(format!("{slug}.csv")))
+    }
+
+    pub fn log(&self, name: &str, entry: &MetricEntry) -> Result<PathBuf> {
+        let path = self.path(name)?;
+        std::fs::create_dir_all(&self.dir)?;
+        let fresh = !path.exists();
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        if fresh {
+            writeln!(file, "{CSV_HEADER}")?;
+        }
+        writeln!(
+            file,
+            "{},{},{},{}",
+            entry.at.to_rfc3339(),
+            entry.value,
+            csv_field(&entry.unit),
+            csv_field(&entry.note)
+        )?;
+        Ok(path)
+    }
+
+    pub fn entries(&self, name: &str) -> Result<Vec<MetricEntry>> {
+        let path = self.path(name)?;
+        if !path.exists() {
+            return Err(anyhow!("no entries for metric '{}'", metric_slug(name)));
+        }
+        let raw = std::fs::read_to_string(path)?;
+        let mut entries = raw
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let fields = split_csv_line(line);
+                Some(MetricEntry {
+                    at: DateTime::parse_from_rfc3339(fields.first()?)
+                        .ok()?
+                        .with_timezone(&Local),
+                    value: fields.get(1)?.trim().parse().ok()?,
+                    unit: fields.get(2).cloned().unwrap_or_default(),
+                    note: fields.get(3).cloned().unwrap_or_default(),
+                })
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by_key(|entry| entry.at);
+        Ok(entries)
+    }
+
+    // (slug, entry count) for every metric on disk.
+    pub fn list(&self) -> Vec<(String, usize)> {
+        let Ok(dir) = std::fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut metrics = dir
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "csv"))
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_string_lossy().to_string();
+                let count = self.entries(&name).map(|e| e.len()).unwrap_or(0);
+                Some((name, count))
+            })
+            .collect::<Vec<_>>();
+        metrics.sort();
+        metrics
+    }
+}
+
+pub fn since_days(entries: &[MetricEntry], days: i64, now: DateTime<Local>) -> Vec<MetricEntry> {
+    let cutoff = now - Duration::days(days);
+    entries
+        .iter()
+        .filter(|entry| entry.at >= cutoff)
+        .cloned()
+        .collect()
+}
+
+pub fn summarize(name: &str, entries: &[MetricEntry]) -> String {
+    let (Some(first), Some(last)) = (entries.first(), entries.last()) else {
+        return format!("{name}: no entries in this period");
+    };
+    let unit = if last.unit.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", last.unit)
+    };
+    let values = entries.iter().map(|entry| entry.value).collect::<Vec<_>>();
+    let sum = values.iter().sum::<f64>();
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let change = last.value - first.value;
+    format!(
+        "{name}: {} entries from {} to {}\n- latest: {}{unit}\n- average: {}{unit}\n- min/max: {} / {}{unit}\n- total: {}{unit}\n- change: {}{}{unit}",
+        entries.len(),
+        first.at.format("%Y-%m-%d"),
+        last.at.format("%Y-%m-%d"),
+        format_value(last.value),
+        format_value(sum / values.len() as f64),
+        format_value(min),
+        format_value(max),
+        format_value(sum),
+        if change > 0.0 { "+" } else { "" },
+        format_value(change),
+    )
+}
+
+// Text chart with one bar per bucket; `sum` adds values up (expenses),
+// otherwise they are averaged (weight, mood).
+pub fn chart(entries: &[MetricEntry], bucket: Bucket, sum: bool) -> String {
+    let mut buckets = BTreeMap::<NaiveDate, Vec<f64>>::new();
+    for entry in entries {
+        buckets
+            .entry(bucket.start_of(entry.at.date_naive()))
+            .or_default()
+            .push(entry.value);
+    }
+    let rows = buckets
+        .into_iter()
+        .map(|(date, values)| {
+            let total = values.iter().sum::<f64>();
+            let value = if sum {
+                total
+            } else {
+                total / values.len() as f64
+            };
+            (date, value)
+        })
+        .collect::<Vec<_>>();
+    if rows.is_empty() {
+        return "(no data)".to_string();
+    }
+    let min = rows.iter().map(|(_, v)| *v).fold(f64::INFINITY, f64::min);
+    let max = rows
+        .iter()
+        .map(|(_, v)| *v)
+        .fold(f64::NEG_INFINITY, f64::max);
+    // Bars start at zero for totals; averages are scaled between min and max
+    // so small changes (like weight) stay visible.
+    let floor = if sum { min.min(0.0) } else { min };
+    let span = (max - floor).max(f64::EPSILON);
+    let spark = rows
+        .iter()
+        .map(|(_, value)| {
+            let level = ((value - floor) / span * (SPARKS.len() - 1) as f64).round() as usize;
+            SPARKS[level.min(SPARKS.len() - 1)]
+        })
+        .collect::<String>();
+    let mut out = format!("{spark}\n");
+    for (date, value) in &rows {
+        let len = 1 + ((value - floor) / span * (BAR_WIDTH - 1) as f64).round() as usize;
+        out.push_str(&format!(
+            "{} {:<width$} {}\n",
+            date.format("%Y-%m-%d"),
+            "█".repeat(len),
+            format_value(*value),
+            width = BAR_WIDTH
+        ));
+    }
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(day: u32, hour: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2026, 3, day, hour, 0, 0)
+            .single()
+            .expect("valid time")
+    }
+
+    #[test]
+    fn logs_csv_and_summarizes_trends() {
+        let dir = std::env::temp_dir().join(format!("nanobot-metrics-{}", uuid::Uuid::new_v4()));
+        let store = MetricStore::new(dir.clone());
+        for (day, value, note) in [
+            (2, 12.5, "lunch, with \"team\""),
+            (2, 30.0, ""),
+            (4, 7.5, "coffee"),
+        ] {
+            let entry = MetricEntry {
+                at: at(day, 12),
+                value,
+                unit: "EUR".to_string(),
+                note: note.to_string(),
+            };
+            store.log("Expenses", &entry).expect("log");
+        }
+
+        let entries = store.entries("expenses").expect("entries");
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].note, "lunch, with \"team\"");
+        assert_eq!(store.list(), vec![("expenses".to_string(), 3)]);
+        assert!(store.entries("weight").is_err());
+
+        let summary = summarize("expenses", &entries);
+        assert!(summary.contains("total: 50 EUR"));
+        assert!(summary.contains("change: -5 EUR"));
+
+        let daily = chart(&entries, Bucket::Day, true);
+        assert!(daily.contains("2026-03-02"));
+        assert!(daily.ends_with("7.5"));
+        assert!(chart(&entries, Bucket::Week, true).contains("2026-03-02"));
+        assert_eq!(since_days(&entries, 1, at(4, 18)).len(), 1);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
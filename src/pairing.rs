@@ -1,4 +1,5 @@
-use crate::config::{Config, load_config, save_config};
+use crate::config::{Config, PairingChallenge, load_config, save_config};
+use crate::storage::locate;
 use crate::utils::get_data_path;
 use anyhow::{Result, anyhow};
 use chrono::Utc;
@@ -18,12 +19,36 @@ pub struct PendingPairing {
     pub created_at_ms: i64,
     pub last_seen_at_ms: i64,
     pub request_count: u32,
+    // Wrong answers to the owner's challenge question so far.
+    #[serde(default)]
+    pub challenge_attempts: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "camelCase")]
 struct PairingStore {
     pending: Vec<PendingPairing>,
+    // Senders who answered the challenge; their turns run in read-only mode.
+    #[serde(default)]
+    guests: Vec<Guest>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Guest {
+    pub channel: String,
+    pub sender_id: String,
+    pub approved_at_ms: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChallengeOutcome {
+    // First contact: the sender is shown the question.
+    Asked(String),
+    Approved,
+    Retry { remaining: u32 },
+    // Out of attempts; only the owner can approve the pairing code now.
+    Exhausted(String),
 }
 
 #[derive(Debug, Clone)]
@@ -41,23 +66,16 @@ fn store_path() -> Result<PathBuf> {
 }
 
 fn load_store() -> Result<PairingStore> {
-    let path = store_path()?;
-    if !path.exists() {
+    let Some(raw) = locate(&store_path()?).read()? else {
         return Ok(PairingStore::default());
-    }
-    let raw = std::fs::read_to_string(path)?;
+    };
     let store = serde_json::from_str(&raw).unwrap_or_default();
     Ok(store)
 }
 
 fn save_store(store: &PairingStore) -> Result<()> {
-    let path = store_path()?;
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
     let text = serde_json::to_string_pretty(store)?;
-    std::fs::write(path, text)?;
-    Ok(())
+    locate(&store_path()?).write(&text)
 }
 
 fn cleanup_expired(store: &mut PairingStore) {
@@ -99,6 +117,12 @@ pub fn issue_pairing(channel: &str, sender_id: &str, chat_id: &str) -> Result<Pa
         });
     }
 
+    let code = push_pending(&mut store, channel, sender_id, chat_id);
+    save_store(&store)?;
+    Ok(PairingIssue { code, is_new: true })
+}
+
+fn push_pending(store: &mut PairingStore, channel: &str, sender_id: &str, chat_id: &str) -> String {
     let pending = PendingPairing {
         channel: channel.to_string(),
         sender_id: sender_id.to_string(),
@@ -107,11 +131,129 @@ pub fn issue_pairing(channel: &str, sender_id: &str, chat_id: &str) -> Result<Pa
         created_at_ms: now_ms(),
         last_seen_at_ms: now_ms(),
         request_count: 1,
+        challenge_attempts: 0,
     };
     let code = pending.code.clone();
     store.pending.push(pending);
+    code
+}
+
+// The owner-defined question from `channels.pairingChallenge`, if one is set.
+pub fn configured_challenge() -> Option<PairingChallenge> {
+    load_config(None)
+        .ok()?
+        .channels
+        .pairing_challenge
+        .filter(|challenge| !challenge.answer.trim().is_empty())
+}
+
+fn normalize_answer(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn apply_challenge(
+    store: &mut PairingStore,
+    challenge: &PairingChallenge,
+    channel: &str,
+    sender_id: &str,
+    chat_id: &str,
+    answer: &str,
+) -> ChallengeOutcome {
+    let Some(idx) = store
+        .pending
+        .iter()
+        .position(|p| p.channel == channel && p.sender_id == sender_id)
+    else {
+        push_pending(store, channel, sender_id, chat_id);
+        return ChallengeOutcome::Asked(challenge.question.clone());
+    };
+    let entry = &mut store.pending[idx];
+    entry.last_seen_at_ms = now_ms();
+    entry.request_count = entry.request_count.saturating_add(1);
+    if entry.challenge_attempts >= challenge.max_attempts {
+        return ChallengeOutcome::Exhausted(entry.code.clone());
+    }
+    if normalize_answer(answer) == normalize_answer(&challenge.answer) {
+        store.pending.remove(idx);
+        store.guests.push(Guest {
+            channel: channel.to_string(),
+            sender_id: sender_id.to_string(),
+            approved_at_ms: now_ms(),
+        });
+        return ChallengeOutcome::Approved;
+    }
+    entry.challenge_attempts += 1;
+    match challenge.max_attempts - entry.challenge_attempts {
+        0 => ChallengeOutcome::Exhausted(entry.code.clone()),
+        remaining => ChallengeOutcome::Retry { remaining },
+    }
+}
+
+// Treats the unknown sender's message as an answer to the challenge question;
+// the first message only asks it.
+pub fn answer_challenge(
+    challenge: &PairingChallenge,
+    channel: &str,
+    sender_id: &str,
+    chat_id: &str,
+    answer: &str,
+) -> Result<ChallengeOutcome> {
+    if channel.trim().is_empty() || sender_id.trim().is_empty() || chat_id.trim().is_empty() {
+        return Err(anyhow!("channel/sender/chat cannot be empty"));
+    }
+    let mut store = load_store()?;
+    cleanup_expired(&mut store);
+    let outcome = apply_challenge(&mut store, challenge, channel, sender_id, chat_id, answer);
     save_store(&store)?;
-    Ok(PairingIssue { code, is_new: true })
+    Ok(outcome)
+}
+
+pub fn challenge_prompt(outcome: &ChallengeOutcome) -> String {
+    match outcome {
+        ChallengeOutcome::Asked(question) => {
+            format!(
+                "Access requires pairing. Answer this question to continue as a guest:\n{question}"
+            )
+        }
+        ChallengeOutcome::Approved => {
+            "Correct. You now have guest access; send your message again.".to_string()
+        }
+        ChallengeOutcome::Retry { remaining } => {
+            format!("That is not the expected answer. Attempts left: {remaining}")
+        }
+        ChallengeOutcome::Exhausted(code) => format!(
+            "No attempts left. Ask the owner to approve your pairing.\nCode: {code}\nOwner command: nanobot-rs pairing approve <channel> {code}"
+        ),
+    }
+}
+
+pub fn is_guest(channel: &str, sender_id: &str) -> bool {
+    load_store().is_ok_and(|store| {
+        store
+            .guests
+            .iter()
+            .any(|g| g.channel == channel && g.sender_id == sender_id)
+    })
+}
+
+pub fn list_guests() -> Result<Vec<Guest>> {
+    Ok(load_store()?.guests)
+}
+
+pub fn revoke_guest(channel: &str, sender_id: &str) -> Result<bool> {
+    let mut store = load_store()?;
+    let before = store.guests.len();
+    store
+        .guests
+        .retain(|g| !(g.channel == channel && g.sender_id == sender_id));
+    let changed = store.guests.len() != before;
+    if changed {
+        save_store(&store)?;
+    }
+    Ok(changed)
 }
 
 pub fn list_pending() -> Result<Vec<PendingPairing>> {
@@ -124,7 +266,23 @@ pub fn list_pending() -> Result<Vec<PendingPairing>> {
     Ok(store.pending)
 }
 
-fn channel_allowlist_mut<'a>(config: &'a mut Config, channel: &str) -> Option<&'a mut Vec<String>> {
+fn channel_allowlist_mut<'a>(
+    config: &'a mut Config,
+    channel: &str,
+    chat_id: &str,
+) -> Option<&'a mut Vec<String>> {
+    // Mail to a secondary account arrives as "<account>:<address>".
+    if channel == "email"
+        && let Some((id, _)) = chat_id.split_once(':')
+        && let Some(account) = config
+            .channels
+            .email
+            .accounts
+            .iter_mut()
+            .find(|account| account.id == id)
+    {
+        return Some(&mut account.allow_from);
+    }
     match channel {
         "telegram" => Some(&mut config.channels.telegram.allow_from),
         "discord" => Some(&mut config.channels.discord.allow_from),
@@ -153,7 +311,7 @@ pub fn approve_pairing(channel: &str, code: &str) -> Result<PendingPairing> {
     if channel == "slack" {
         config.channels.slack.dm.policy = "allowlist".to_string();
     }
-    let allowlist = channel_allowlist_mut(&mut config, channel)
+    let allowlist = channel_allowlist_mut(&mut config, channel, &pending.chat_id)
         .ok_or_else(|| anyhow!("channel '{channel}' does not support allowlist pairing"))?;
 
     if !allowlist.iter().any(|v| v == &pending.sender_id) {
@@ -191,3 +349,48 @@ pub fn pairing_prompt(issue: &PairingIssue) -> String {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn challenge_admits_guests_and_falls_back_to_code() {
+        let challenge = PairingChallenge {
+            question: "Which city hosts the office?".to_string(),
+            answer: "New  York".to_string(),
+            max_attempts: 2,
+        };
+        let mut store = PairingStore::default();
+        let ask = |store: &mut PairingStore, sender: &str, answer: &str| {
+            apply_challenge(store, &challenge, "telegram", sender, "c1", answer)
+        };
+
+        assert_eq!(
+            ask(&mut store, "u1", "hello"),
+            ChallengeOutcome::Asked(challenge.question.clone())
+        );
+        assert_eq!(
+            ask(&mut store, "u1", " new york "),
+            ChallengeOutcome::Approved
+        );
+        assert!(store.pending.is_empty());
+        assert_eq!(store.guests[0].sender_id, "u1");
+
+        ask(&mut store, "u2", "hi");
+        assert_eq!(
+            ask(&mut store, "u2", "Boston"),
+            ChallengeOutcome::Retry { remaining: 1 }
+        );
+        let code = store.pending[0].code.clone();
+        assert_eq!(
+            ask(&mut store, "u2", "Paris"),
+            ChallengeOutcome::Exhausted(code.clone())
+        );
+        assert_eq!(
+            ask(&mut store, "u2", "New York"),
+            ChallengeOutcome::Exhausted(code)
+        );
+        assert_eq!(store.guests.len(), 1);
+    }
+}
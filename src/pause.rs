@@ -0,0 +1,137 @@
+use crate::cron::reminder::parse_when;
+use crate::cron::time::format_local;
+use crate::storage::locate;
+use crate::utils::get_data_path;
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PauseState {
+    pub paused_at_ms: i64,
+    // Proactive work resumes by itself after this; None waits for `resume`.
+    #[serde(default)]
+    pub until_ms: Option<i64>,
+    // Who paused: "cli", or "<channel>:<sender>" for chat commands.
+    #[serde(default)]
+    pub by: String,
+}
+
+impl PauseState {
+    pub fn describe(&self, now_ms: i64) -> String {
+        let mut text = format!("paused since {}", format_local(self.paused_at_ms, now_ms));
+        if let Some(until_ms) = self.until_ms {
+            text.push_str(&format!(", until {}", format_local(until_ms, now_ms)));
+        }
+        if !self.by.is_empty() {
+            text.push_str(&format!(" by {}", self.by));
+        }
+        text
+    }
+}
+
+// Global off switch for proactive work (heartbeat, scheduled jobs). Chat
+// replies are unaffected. The state lives in a file so a pause survives
+// restarts and a CLI `pause` reaches a gateway that is already running.
+#[derive(Debug, Clone)]
+pub struct PauseSwitch {
+    path: PathBuf,
+}
+
+impl PauseSwitch {
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_path(get_data_path()?.join("paused.json")))
+    }
+
+    pub fn from_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    // The pause in force at `now_ms`; one whose `until` has passed is over.
+    pub fn state_at(&self, now_ms: i64) -> Option<PauseState> {
+        let raw = locate(&self.path).read().ok()??;
+        let state = serde_json::from_str::<PauseState>(&raw).ok()?;
+        match state.until_ms {
+            Some(until_ms) if until_ms <= now_ms => None,
+            _ => Some(state),
+        }
+    }
+
+    pub fn is_paused_at(&self, now_ms: i64) -> bool {
+        self.state_at(now_ms).is_some()
+    }
+
+    pub fn pause(&self, now_ms: i64, until_ms: Option<i64>, by: &str) -> Result<PauseState> {
+        let state = PauseState {
+            paused_at_ms: now_ms,
+            until_ms,
+            by: by.to_string(),
+        };
+        locate(&self.path).write(&serde_json::to_string_pretty(&state)?)?;
+        Ok(state)
+    }
+
+    // Returns the pause that was lifted, if one was in force.
+    pub fn resume(&self, now_ms: i64) -> Result<Option<PauseState>> {
+        let previous = self.state_at(now_ms);
+        locate(&self.path).remove()?;
+        Ok(previous)
+    }
+}
+
+// Shared by `nanobot pause` and the /pause chat command. `until` is empty
+// for an open-ended pause, or anything `parse_when` takes ("7d", "tomorrow").
+pub fn pause_reply(
+    switch: &PauseSwitch,
+    until: &str,
+    by: &str,
+    now: DateTime<Local>,
+) -> Result<String> {
+    let until_ms = match until.trim() {
+        "" => None,
+        raw => Some(parse_when(raw, now)?),
+    };
+    let now_ms = now.timestamp_millis();
+    let state = switch.pause(now_ms, until_ms, by)?;
+    Ok(format!(
+        "⏸️ Heartbeat and scheduled jobs {}. Chat replies keep working.",
+        state.describe(now_ms)
+    ))
+}
+
+pub fn resume_reply(switch: &PauseSwitch, now_ms: i64) -> Result<String> {
+    Ok(match switch.resume(now_ms)? {
+        Some(_) => "▶️ Heartbeat and scheduled jobs resumed.".to_string(),
+        None => "Nothing was paused.".to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pause_persists_until_resumed_or_expired() {
+        let dir = std::env::temp_dir().join(format!("nanobot-pause-{}", uuid::Uuid::new_v4()));
+        let switch = PauseSwitch::from_path(dir.join("paused.json"));
+        assert!(!switch.is_paused_at(0));
+        assert_eq!(switch.resume(0).expect("resume"), None);
+
+        switch.pause(1_000, None, "cli").expect("pause");
+        // A second handle on the same file, as a running gateway would have.
+        let gateway = PauseSwitch::from_path(dir.join("paused.json"));
+        assert!(gateway.is_paused_at(i64::MAX));
+        let lifted = switch.resume(2_000).expect("resume").expect("was paused");
+        assert_eq!(lifted.by, "cli");
+        assert!(!gateway.is_paused_at(2_000));
+
+        switch
+            .pause(1_000, Some(5_000), "telegram:42")
+            .expect("pause");
+        assert!(gateway.is_paused_at(4_999));
+        assert!(!gateway.is_paused_at(5_000));
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -1,6 +1,10 @@
+use crate::events::{self, EventLevel};
+use crate::providers::models::ModelCapabilities;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
+use std::sync::Arc;
+use tokio::task::JoinHandle;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCallRequest {
@@ -24,6 +28,159 @@ impl LLMResponse {
     }
 }
 
+// How providers word a request that does not fit the model's context window.
+const CONTEXT_OVERFLOW_MARKERS: &[&str] = &[
+    "context_length_exceeded",
+    "maximum context length",
+    "context length",
+    "context window",
+    "prompt is too long",
+    "input is too long",
+    "reduce the length of the messages",
+];
+
+pub fn is_context_overflow(error: &str) -> bool {
+    let lower = error.to_lowercase();
+    CONTEXT_OVERFLOW_MARKERS
+        .iter()
+        .any(|marker| lower.contains(marker))
+}
+
+// How providers word an exhausted key: out of quota or credit, or rate limited.
+const QUOTA_MARKERS: &[&str] = &[
+    "429",
+    "insufficient_quota",
+    "quota",
+    "rate limit",
+    "rate_limit",
+    "too many requests",
+    "credit balance",
+    "billing",
+];
+
+pub fn is_quota_error(error: &str) -> bool {
+    let lower = error.to_lowercase();
+    QUOTA_MARKERS.iter().any(|marker| lower.contains(marker))
+}
+
+// A failed call's message, whether it surfaced as an error or as an "error"
+// response.
+fn call_error(result: &anyhow::Result<LLMResponse>) -> Option<String> {
+    match result {
+        Err(err) => Some(format!("{err:#}")),
+        Ok(response) if response.finish_reason == "error" => response.content.clone(),
+        Ok(_) => None,
+    }
+}
+
+// The provider's complaint when a call failed because the request was too long.
+pub fn context_overflow(result: &anyhow::Result<LLMResponse>) -> Option<String> {
+    call_error(result).filter(|error| is_context_overflow(error))
+}
+
+// The provider's complaint when a call failed because the key ran out.
+pub fn quota_exhausted(result: &anyhow::Result<LLMResponse>) -> Option<String> {
+    call_error(result).filter(|error| is_quota_error(error))
+}
+
+// Builds a provider for a model name; errors when no credentials are configured.
+pub type ProviderFactory = Arc<dyn Fn(&str) -> anyhow::Result<Arc<dyn LLMProvider>> + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplingParams {
+    pub max_tokens: u32,
+    pub temperature: f32,
+    pub top_p: Option<f32>,
+}
+
+impl Default for SamplingParams {
+    fn default() -> Self {
+        Self {
+            max_tokens: 8192,
+            temperature: 0.7,
+            top_p: None,
+        }
+    }
+}
+
+impl SamplingParams {
+    // Per-turn overrides come from a `sampling` metadata object, e.g.
+    // {"maxTokens": 1024, "temperature": 0.2, "topP": 0.9}.
+    pub fn with_overrides(self, overrides: Option<&Value>) -> Self {
+        let Some(overrides) = overrides.and_then(Value::as_object) else {
+            return self;
+        };
+        let number = |camel: &str, snake: &str| {
+            overrides
+                .get(camel)
+                .or_else(|| overrides.get(snake))
+                .and_then(Value::as_f64)
+        };
+        Self {
+            max_tokens: number("maxTokens", "max_tokens")
+                .filter(|v| *v >= 1.0)
+                .map(|v| v as u32)
+                .unwrap_or(self.max_tokens),
+            temperature: number("temperature", "temperature")
+                .map(|v| v as f32)
+                .unwrap_or(self.temperature),
+            top_p: number("topP", "top_p").map(|v| v as f32).or(self.top_p),
+        }
+    }
+
+    // Rejects per-turn overrides a provider would refuse or silently clamp:
+    // temperature outside 0..=2, top_p outside (0, 1], or more output tokens
+    // than the model can produce.
+    pub fn check_overrides(
+        overrides: Option<&Value>,
+        caps: &ModelCapabilities,
+    ) -> Result<(), String> {
+        let Some(overrides) = overrides else {
+            return Ok(());
+        };
+        let Some(overrides) = overrides.as_object() else {
+            return Err("sampling must be an object".to_string());
+        };
+        let number = |camel: &str, snake: &str| -> Result<Option<f64>, String> {
+            match overrides.get(camel).or_else(|| overrides.get(snake)) {
+                None | Some(Value::Null) => Ok(None),
+                Some(value) => value
+                    .as_f64()
+                    .map(Some)
+                    .ok_or_else(|| format!("{camel} must be a number")),
+            }
+        };
+        if let Some(t) = number("temperature", "temperature")?
+            && !(0.0..=2.0).contains(&t)
+        {
+            return Err(format!("temperature {t} is outside 0..2"));
+        }
+        if let Some(p) = number("topP", "top_p")?
+            && !(p > 0.0 && p <= 1.0)
+        {
+            return Err(format!("topP {p} is outside (0, 1]"));
+        }
+        if let Some(n) = number("maxTokens", "max_tokens")?
+            && caps.max_output_tokens > 0
+            && n > caps.max_output_tokens as f64
+        {
+            return Err(format!(
+                "maxTokens {n} exceeds the model limit of {}",
+                caps.max_output_tokens
+            ));
+        }
+        Ok(())
+    }
+
+    // Never ask for more output than the model can produce.
+    pub fn capped_for(mut self, caps: &ModelCapabilities) -> Self {
+        if caps.max_output_tokens > 0 {
+            self.max_tokens = self.max_tokens.min(caps.max_output_tokens);
+        }
+        self
+    }
+}
+
 #[async_trait]
 pub trait LLMProvider: Send + Sync {
     async fn chat(
@@ -35,5 +192,154 @@ pub trait LLMProvider: Send + Sync {
         temperature: f32,
     ) -> anyhow::Result<LLMResponse>;
 
+    async fn chat_with_sampling(
+        &self,
+        messages: &[Value],
+        tools: Option<&[Value]>,
+        model: Option<&str>,
+        sampling: &SamplingParams,
+    ) -> anyhow::Result<LLMResponse> {
+        self.chat(
+            messages,
+            tools,
+            model,
+            sampling.max_tokens,
+            sampling.temperature,
+        )
+        .await
+    }
+
+    // Opens a connection ahead of the first turn; providers without a pooled
+    // HTTP client have nothing to warm.
+    async fn warm_up(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     fn default_model(&self) -> &str;
 }
+
+// Warms the provider connection in the background when `gateway.prewarm` is
+// on; a failure is only worth an event since the first turn dials anyway.
+pub fn spawn_warm_up(provider: Arc<dyn LLMProvider>, prewarm: bool) -> Option<JoinHandle<()>> {
+    if !prewarm {
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        if let Err(err) = provider.warm_up().await {
+            events::record(
+                EventLevel::Warn,
+                "provider",
+                None,
+                format!("connection pre-warm failed: {err:#}"),
+            );
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LLMProvider, LLMResponse, SamplingParams, context_overflow, spawn_warm_up};
+    use crate::providers::models::ModelRegistry;
+    use async_trait::async_trait;
+    use serde_json::{Map, Value, json};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingProvider {
+        warm_ups: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl LLMProvider for CountingProvider {
+        async fn chat(
+            &self,
+            _messages: &[Value],
+            _tools: Option<&[Value]>,
+            _model: Option<&str>,
+            _max_tokens: u32,
+            _temperature: f32,
+        ) -> anyhow::Result<LLMResponse> {
+            anyhow::bail!("not used")
+        }
+
+        async fn warm_up(&self) -> anyhow::Result<()> {
+            self.warm_ups.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+
+        fn default_model(&self) -> &str {
+            "counting"
+        }
+    }
+
+    #[tokio::test]
+    async fn warm_up_runs_only_when_prewarm_is_on() {
+        let provider = Arc::new(CountingProvider::default());
+        assert!(spawn_warm_up(provider.clone(), false).is_none());
+        assert_eq!(provider.warm_ups.load(Ordering::SeqCst), 0);
+
+        spawn_warm_up(provider.clone(), true)
+            .expect("spawned")
+            .await
+            .expect("warm-up task");
+        assert_eq!(provider.warm_ups.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn sampling_overrides_accept_camel_and_snake_case() {
+        let base = SamplingParams::default();
+        assert_eq!(base.with_overrides(None), base);
+
+        let merged = base.with_overrides(Some(&json!({
+            "maxTokens": 512,
+            "top_p": 0.5
+        })));
+        assert_eq!(merged.max_tokens, 512);
+        assert_eq!(merged.temperature, base.temperature);
+        assert_eq!(merged.top_p, Some(0.5));
+
+        let ignored = base.with_overrides(Some(&json!({ "maxTokens": 0 })));
+        assert_eq!(ignored.max_tokens, base.max_tokens);
+    }
+
+    #[test]
+    fn recognizes_context_overflow_errors() {
+        let overflow = LLMResponse {
+            content: Some(
+                r#"Error calling LLM: {"error":{"code":"context_length_exceeded"}}"#.to_string(),
+            ),
+            tool_calls: Vec::new(),
+            finish_reason: "error".to_string(),
+            usage: Map::new(),
+            reasoning_content: None,
+        };
+        assert!(context_overflow(&Ok(overflow.clone())).is_some());
+        assert!(
+            context_overflow(&Err(anyhow::anyhow!(
+                "prompt is too long: 210000 tokens > 200000 maximum"
+            )))
+            .is_some()
+        );
+        let rate_limited = anyhow::anyhow!("Rate limit reached for tokens per min");
+        assert!(context_overflow(&Err(rate_limited)).is_none());
+        let answer = LLMResponse {
+            finish_reason: "stop".to_string(),
+            ..overflow
+        };
+        assert!(context_overflow(&Ok(answer)).is_none());
+    }
+
+    #[test]
+    fn sampling_overrides_are_range_checked_against_the_model() {
+        let caps = ModelRegistry::default().lookup("gpt-4o");
+        let check = |value: Value| SamplingParams::check_overrides(Some(&value), &caps);
+        assert!(SamplingParams::check_overrides(None, &caps).is_ok());
+        assert!(check(json!({ "temperature": 0.2, "top_p": 1.0, "maxTokens": 1024 })).is_ok());
+        assert!(check(json!({ "temperature": 3 })).is_err());
+        assert!(check(json!({ "topP": 0 })).is_err());
+        assert!(check(json!({ "temperature": "hot" })).is_err());
+        assert!(check(json!({ "maxTokens": caps.max_output_tokens + 1 })).is_err());
+        assert!(check(json!("fast")).is_err());
+    }
+}
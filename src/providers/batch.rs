@@ -0,0 +1,329 @@
+use crate::config::Config;
+use crate::providers::openai::pooled_client;
+use anyhow::{Result, anyhow, bail};
+use reqwest::Client;
+use reqwest::multipart::{Form, Part};
+use serde_json::{Value, json};
+
+#[derive(Debug, Clone)]
+pub struct BatchItem {
+    pub custom_id: String,
+    pub prompt: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchResult {
+    pub custom_id: String,
+    pub text: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchPoll {
+    Pending(String),
+    Done(Vec<BatchResult>),
+    Failed(String),
+}
+
+// Provider batch endpoints: requests are processed asynchronously within a
+// day at roughly half the price of interactive calls.
+#[derive(Clone)]
+pub struct BatchApi {
+    provider: String,
+    api_key: String,
+    api_base: String,
+    client: Client,
+}
+
+impl BatchApi {
+    pub fn for_provider(config: &Config, provider: &str) -> Result<Self> {
+        let (settings, default_base) = match provider {
+            "anthropic" => (&config.providers.anthropic, "https://api.anthropic.com/v1"),
+            "openai" => (&config.providers.openai, "https://api.openai.com/v1"),
+            other => bail!("batch jobs are not supported for provider '{other}'"),
+        };
+        if settings.api_key.is_empty() {
+            bail!("providers.{provider}.apiKey is not set");
+        }
+        Ok(Self {
+            provider: provider.to_string(),
+            api_key: settings.api_key.clone(),
+            api_base: settings
+                .api_base
+                .clone()
+                .unwrap_or_else(|| default_base.to_string())
+                .trim_end_matches('/')
+                .to_string(),
+            client: pooled_client(),
+        })
+    }
+
+    // Picks the batch provider for a configured model name and returns it
+    // together with the model id the provider expects.
+    pub fn for_model(config: &Config, model: &str) -> Result<(Self, String)> {
+        let model = model.strip_prefix("litellm/").unwrap_or(model);
+        let (prefix, bare) = model.split_once('/').unwrap_or(("", model));
+        let provider = match prefix {
+            "anthropic" | "openai" => prefix,
+            "" if bare.contains("claude") => "anthropic",
+            "" if bare.starts_with("gpt") || is_o_series(bare) => "openai",
+            _ => bail!("batch jobs need an Anthropic or OpenAI model, not '{model}'"),
+        };
+        Ok((Self::for_provider(config, provider)?, bare.to_string()))
+    }
+
+    pub fn provider(&self) -> &str {
+        &self.provider
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{path}", self.api_base);
+        let req = self.client.request(method, url);
+        if self.provider == "anthropic" {
+            req.header("x-api-key", &self.api_key)
+                .header("anthropic-version", "2023-06-01")
+        } else {
+            req.bearer_auth(&self.api_key)
+        }
+    }
+
+    async fn send(req: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let response = req.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("batch API returned {status}: {body}");
+        }
+        Ok(response)
+    }
+
+    pub async fn submit(
+        &self,
+        model: &str,
+        system: &str,
+        items: &[BatchItem],
+        max_tokens: u32,
+    ) -> Result<String> {
+        if self.provider == "anthropic" {
+            let requests = items
+                .iter()
+                .map(|item| {
+                    json!({
+                        "custom_id": item.custom_id,
+                        "params": {
+                            "model": model,
+                            "max_tokens": max_tokens,
+                            "system": system,
+                            "messages": [{ "role": "user", "content": item.prompt }],
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+            let response = Self::send(
+                self.request(reqwest::Method::POST, "messages/batches")
+                    .json(&json!({ "requests": requests })),
+            )
+            .await?;
+            let value: Value = response.json().await?;
+            return string_field(&value, "id");
+        }
+
+        let lines = items
+            .iter()
+            .map(|item| {
+                json!({
+                    "custom_id": item.custom_id,
+                    "method": "POST",
+                    "url": "/v1/chat/completions",
+                    "body": {
+                        "model": model,
+                        "max_tokens": max_tokens,
+                        "messages": [
+                            { "role": "system", "content": system },
+                            { "role": "user", "content": item.prompt },
+                        ],
+                    }
+                })
+                .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let form = Form::new().text("purpose", "batch").part(
+            "file",
+            Part::bytes(lines.into_bytes()).file_name("batch.jsonl"),
+        );
+        let file: Value = Self::send(self.request(reqwest::Method::POST, "files").multipart(form))
+            .await?
+            .json()
+            .await?;
+        let batch: Value =
+            Self::send(self.request(reqwest::Method::POST, "batches").json(&json!({
+                "input_file_id": string_field(&file, "id")?,
+                "endpoint": "/v1/chat/completions",
+                "completion_window": "24h",
+            })))
+            .await?
+            .json()
+            .await?;
+        string_field(&batch, "id")
+    }
+
+    pub async fn poll(&self, batch_id: &str) -> Result<BatchPoll> {
+        if self.provider == "anthropic" {
+            let path = format!("messages/batches/{batch_id}");
+            let batch: Value = Self::send(self.request(reqwest::Method::GET, &path))
+                .await?
+                .json()
+                .await?;
+            let status = string_field(&batch, "processing_status")?;
+            if status != "ended" {
+                return Ok(BatchPoll::Pending(status));
+            }
+            let Some(url) = batch.get("results_url").and_then(Value::as_str) else {
+                return Ok(BatchPoll::Failed("batch ended without results".to_string()));
+            };
+            let raw = Self::send(
+                self.client
+                    .get(url)
+                    .header("x-api-key", &self.api_key)
+                    .header("anthropic-version", "2023-06-01"),
+            )
+            .await?
+            .text()
+            .await?;
+            return Ok(BatchPoll::Done(parse_anthropic_results(&raw)));
+        }
+
+        let path = format!("batches/{batch_id}");
+        let batch: Value = Self::send(self.request(reqwest::Method::GET, &path))
+            .await?
+            .json()
+            .await?;
+        let status = string_field(&batch, "status")?;
+        match status.as_str() {
+            "completed" => {}
+            "failed" | "expired" | "cancelled" => return Ok(BatchPoll::Failed(status)),
+            _ => return Ok(BatchPoll::Pending(status)),
+        }
+        let mut results = Vec::new();
+        for key in ["output_file_id", "error_file_id"] {
+            let Some(file_id) = batch.get(key).and_then(Value::as_str) else {
+                continue;
+            };
+            let raw =
+                Self::send(self.request(reqwest::Method::GET, &format!("files/{file_id}/content")))
+                    .await?
+                    .text()
+                    .await?;
+            results.extend(parse_openai_results(&raw));
+        }
+        Ok(BatchPoll::Done(results))
+    }
+}
+
+fn is_o_series(model: &str) -> bool {
+    model
+        .strip_prefix('o')
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+fn string_field(value: &Value, key: &str) -> Result<String> {
+    value
+        .get(key)
+        .and_then(Value::as_str)
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| anyhow!("batch API response is missing '{key}': {value}"))
+}
+
+fn json_lines(raw: &str) -> impl Iterator<Item = Value> + '_ {
+    raw.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+}
+
+fn result(custom_id: &Value, text: Option<String>, error: Option<String>) -> BatchResult {
+    BatchResult {
+        custom_id: custom_id.as_str().unwrap_or_default().to_string(),
+        text,
+        error,
+    }
+}
+
+pub fn parse_anthropic_results(raw: &str) -> Vec<BatchResult> {
+    json_lines(raw)
+        .map(|line| {
+            let outcome = &line["result"];
+            if outcome["type"] == "succeeded" {
+                let text = outcome["message"]["content"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|block| block["text"].as_str())
+                    .collect::<Vec<_>>()
+                    .join("");
+                result(&line["custom_id"], Some(text), None)
+            } else {
+                let error = outcome["error"]["message"]
+                    .as_str()
+                    .or_else(|| outcome["type"].as_str())
+                    .unwrap_or("unknown error")
+                    .to_string();
+                result(&line["custom_id"], None, Some(error))
+            }
+        })
+        .collect()
+}
+
+pub fn parse_openai_results(raw: &str) -> Vec<BatchResult> {
+    json_lines(raw)
+        .map(|line| {
+            let response = &line["response"];
+            let text = response["body"]["choices"][0]["message"]["content"].as_str();
+            match text {
+                Some(text) if response["status_code"] == 200 => {
+                    result(&line["custom_id"], Some(text.to_string()), None)
+                }
+                _ => {
+                    let error = line["error"]["message"]
+                        .as_str()
+                        .or_else(|| response["body"]["error"]["message"].as_str())
+                        .unwrap_or("request failed")
+                        .to_string();
+                    result(&line["custom_id"], None, Some(error))
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_provider_result_files() {
+        let anthropic = concat!(
+            r#"{"custom_id":"item-1","result":{"type":"succeeded","message":{"content":[{"type":"text","text":"Short summary."}]}}}"#,
+            "\n",
+            r#"{"custom_id":"item-2","result":{"type":"errored","error":{"type":"invalid_request","message":"too long"}}}"#,
+            "\n"
+        );
+        let parsed = parse_anthropic_results(anthropic);
+        assert_eq!(parsed[0].text.as_deref(), Some("Short summary."));
+        assert_eq!(parsed[1].error.as_deref(), Some("too long"));
+
+        let openai = concat!(
+            r#"{"custom_id":"item-1","response":{"status_code":200,"body":{"choices":[{"message":{"content":"Done."}}]}}}"#,
+            "\n",
+            r#"{"custom_id":"item-2","response":null,"error":{"message":"rate limited"}}"#
+        );
+        let parsed = parse_openai_results(openai);
+        assert_eq!(parsed[0].text.as_deref(), Some("Done."));
+        assert_eq!(parsed[1].custom_id, "item-2");
+        assert_eq!(parsed[1].error.as_deref(), Some("rate limited"));
+
+        let config = Config::default();
+        assert!(BatchApi::for_model(&config, "deepseek/deepseek-chat").is_err());
+        assert!(BatchApi::for_model(&config, "anthropic/claude-sonnet-4-5").is_err());
+    }
+}
@@ -1,5 +1,9 @@
-use crate::providers::base::{LLMProvider, LLMResponse, ToolCallRequest};
+use crate::config::Config;
+use crate::providers::base::{
+    LLMProvider, LLMResponse, ProviderFactory, SamplingParams, ToolCallRequest,
+};
 use crate::providers::openai::OpenAIProvider as OpenAICompatProvider;
+use crate::providers::pool::PooledProvider;
 use anyhow::Result;
 use async_trait::async_trait;
 use litellm_rs::core::types::content::ContentPart;
@@ -7,6 +11,7 @@ use litellm_rs::core::types::tools::{Tool, ToolChoice};
 use litellm_rs::{CompletionOptions, Message, MessageContent, MessageRole, completion};
 use serde_json::{Map, Value};
 use std::collections::HashMap;
+use std::sync::Arc;
 
 #[derive(Clone, Copy)]
 struct ModelOverride {
@@ -273,6 +278,60 @@ pub struct LiteLLMProvider {
 }
 
 impl LiteLLMProvider {
+    pub fn for_model(config: &Config, model: &str) -> Result<Self> {
+        let normalized_model = model.strip_prefix("litellm/").unwrap_or(model);
+        let is_bedrock = normalized_model.starts_with("bedrock/");
+        let api_key = match config.get_api_key(Some(model)) {
+            Some(key) => key,
+            None if is_bedrock => "dummy".to_string(),
+            None => return Err(anyhow::anyhow!("no API key configured for model '{model}'")),
+        };
+        let extra_headers = config
+            .get_provider(Some(model))
+            .and_then(|p| p.extra_headers.clone());
+        let provider_name = config.get_provider_name(Some(model));
+        Ok(Self::new(
+            api_key,
+            config.get_api_base(Some(model)),
+            model.to_string(),
+            extra_headers,
+            provider_name.as_deref(),
+        ))
+    }
+
+    pub fn factory(config: Config) -> ProviderFactory {
+        Arc::new(move |model: &str| Ok(Self::for_model(&config, model)?.pooled(&config)))
+    }
+
+    // Spreads calls over the provider's key pool when it has more than one key.
+    pub fn pooled(self, config: &Config) -> Arc<dyn LLMProvider> {
+        let model = self.default_model.clone();
+        let Some(provider) = config.get_provider(Some(&model)) else {
+            return Arc::new(self);
+        };
+        let keys = provider.keys();
+        if keys.len() < 2 {
+            return Arc::new(self);
+        }
+        let name = config.get_provider_name(Some(&model)).unwrap_or_default();
+        let members = keys
+            .into_iter()
+            .map(|key| {
+                let member: Arc<dyn LLMProvider> = Arc::new(Self {
+                    api_key: key.clone(),
+                    ..self.clone()
+                });
+                (key, member)
+            })
+            .collect();
+        Arc::new(PooledProvider::new(
+            &name,
+            members,
+            &provider.key_pool,
+            &model,
+        ))
+    }
+
     pub fn new(
         api_key: impl Into<String>,
         api_base: Option<String>,
@@ -395,6 +454,16 @@ impl LiteLLMProvider {
         matches!(find_by_model(model), Some(spec) if spec.name == "openai")
     }
 
+    // Cheap to build per call: the HTTP client behind it is shared.
+    fn openai_compat(&self, model: &str) -> OpenAICompatProvider {
+        OpenAICompatProvider::new(
+            self.api_key.clone(),
+            self.effective_api_base(model),
+            model.to_string(),
+            Some(self.extra_headers.clone()),
+        )
+    }
+
     fn set_env_var(key: &str, value: &str, overwrite: bool) {
         if key.is_empty() || value.is_empty() {
             return;
@@ -482,27 +551,32 @@ impl LLMProvider for LiteLLMProvider {
         model: Option<&str>,
         max_tokens: u32,
         temperature: f32,
+    ) -> Result<LLMResponse> {
+        let sampling = SamplingParams {
+            max_tokens,
+            temperature,
+            top_p: None,
+        };
+        self.chat_with_sampling(messages, tools, model, &sampling)
+            .await
+    }
+
+    async fn chat_with_sampling(
+        &self,
+        messages: &[Value],
+        tools: Option<&[Value]>,
+        model: Option<&str>,
+        sampling: &SamplingParams,
     ) -> Result<LLMResponse> {
         let selected_model = model.unwrap_or(&self.default_model);
-        let mut effective_temperature = temperature;
+        let mut effective = *sampling;
         let resolved_model = self.resolve_model(selected_model);
-        self.apply_model_overrides(&resolved_model, &mut effective_temperature);
+        self.apply_model_overrides(&resolved_model, &mut effective.temperature);
 
         if self.use_openai_compat_path(selected_model) {
-            let provider = OpenAICompatProvider::new(
-                self.api_key.clone(),
-                self.effective_api_base(selected_model),
-                selected_model.to_string(),
-                Some(self.extra_headers.clone()),
-            );
-            return provider
-                .chat(
-                    messages,
-                    tools,
-                    Some(selected_model),
-                    max_tokens,
-                    effective_temperature,
-                )
+            return self
+                .openai_compat(selected_model)
+                .chat_with_sampling(messages, tools, Some(selected_model), &effective)
                 .await;
         }
 
@@ -511,8 +585,9 @@ impl LLMProvider for LiteLLMProvider {
             .map(Self::convert_message)
             .collect::<Vec<_>>();
         let mut options = CompletionOptions {
-            max_tokens: Some(max_tokens),
-            temperature: Some(effective_temperature),
+            max_tokens: Some(effective.max_tokens),
+            temperature: Some(effective.temperature),
+            top_p: effective.top_p,
             api_key: if self.api_key.is_empty() {
                 None
             } else {
@@ -636,6 +711,15 @@ impl LLMProvider for LiteLLMProvider {
         })
     }
 
+    // litellm-rs keeps its own clients, so only the OpenAI-compatible path
+    // can be warmed from here.
+    async fn warm_up(&self) -> Result<()> {
+        if !self.use_openai_compat_path(&self.default_model) {
+            return Ok(());
+        }
+        self.openai_compat(&self.default_model).warm_up().await
+    }
+
     fn default_model(&self) -> &str {
         &self.default_model
     }
@@ -645,6 +729,20 @@ impl LLMProvider for LiteLLMProvider {
 mod tests {
     use super::*;
 
+    #[test]
+    fn for_model_requires_configured_key() {
+        let mut config = Config::default();
+        let err = LiteLLMProvider::for_model(&config, "openai/gpt-4o")
+            .err()
+            .expect("missing key should fail");
+        assert!(err.to_string().contains("no API key configured"));
+        assert!(LiteLLMProvider::for_model(&config, "bedrock/claude").is_ok());
+
+        config.providers.openai.api_key = "sk-test".to_string();
+        let provider = LiteLLMProvider::for_model(&config, "openai/gpt-4o").expect("provider");
+        assert_eq!(provider.default_model(), "openai/gpt-4o");
+    }
+
     #[test]
     fn gateway_detects_by_provider_name_and_key_prefix() {
         let by_name = find_gateway(Some("vllm"), None, None).expect("expected vllm gateway");
@@ -1,4 +1,7 @@
 pub mod base;
+pub mod batch;
 pub mod litellm;
+pub mod models;
 pub mod openai;
+pub mod pool;
 pub mod transcription;
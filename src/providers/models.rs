@@ -0,0 +1,252 @@
+use crate::config::ModelCapabilitiesOverride;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelCapabilities {
+    pub context_window: usize,
+    pub max_output_tokens: u32,
+    pub vision: bool,
+    pub tool_calling: bool,
+    pub reasoning: bool,
+    // USD per million tokens; 0 when unknown or self-hosted.
+    pub input_cost_per_mtok: f64,
+    pub output_cost_per_mtok: f64,
+}
+
+impl ModelCapabilities {
+    const fn new(
+        context_window: usize,
+        max_output_tokens: u32,
+        vision: bool,
+        tool_calling: bool,
+        reasoning: bool,
+        costs: (f64, f64),
+    ) -> Self {
+        Self {
+            context_window,
+            max_output_tokens,
+            vision,
+            tool_calling,
+            reasoning,
+            input_cost_per_mtok: costs.0,
+            output_cost_per_mtok: costs.1,
+        }
+    }
+
+    pub fn estimate_cost(&self, input_tokens: usize, output_tokens: usize) -> f64 {
+        (input_tokens as f64 * self.input_cost_per_mtok
+            + output_tokens as f64 * self.output_cost_per_mtok)
+            / 1_000_000.0
+    }
+
+    fn apply(mut self, o: &ModelCapabilitiesOverride) -> Self {
+        self.context_window = o.context_window.unwrap_or(self.context_window);
+        self.max_output_tokens = o.max_output_tokens.unwrap_or(self.max_output_tokens);
+        self.vision = o.vision.unwrap_or(self.vision);
+        self.tool_calling = o.tool_calling.unwrap_or(self.tool_calling);
+        self.reasoning = o.reasoning.unwrap_or(self.reasoning);
+        self.input_cost_per_mtok = o.input_cost_per_mtok.unwrap_or(self.input_cost_per_mtok);
+        self.output_cost_per_mtok = o.output_cost_per_mtok.unwrap_or(self.output_cost_per_mtok);
+        self
+    }
+}
+
+// Conservative guess for models nobody told us about.
+pub const UNKNOWN_MODEL: ModelCapabilities =
+    ModelCapabilities::new(32_768, 4_096, false, true, false, (0.0, 0.0));
+
+// Matched by substring against the lowercased model name without its provider
+// prefix; first hit wins, so more specific names come first. Figures are list
+// prices at the time of writing and can be corrected through config.
+const KNOWN_MODELS: &[(&str, ModelCapabilities)] = &[
+    (
+        "claude-opus-4",
+        ModelCapabilities::new(200_000, 32_000, true, true, true, (15.0, 75.0)),
+    ),
+    (
+        "claude-sonnet-4",
+        ModelCapabilities::new(200_000, 64_000, true, true, true, (3.0, 15.0)),
+    ),
+    (
+        "claude-3-7-sonnet",
+        ModelCapabilities::new(200_000, 64_000, true, true, true, (3.0, 15.0)),
+    ),
+    (
+        "claude-3-5-sonnet",
+        ModelCapabilities::new(200_000, 8_192, true, true, false, (3.0, 15.0)),
+    ),
+    (
+        "claude-haiku-4",
+        ModelCapabilities::new(200_000, 64_000, true, true, true, (1.0, 5.0)),
+    ),
+    (
+        "claude-3-5-haiku",
+        ModelCapabilities::new(200_000, 8_192, true, true, false, (0.8, 4.0)),
+    ),
+    (
+        "gpt-4o-mini",
+        ModelCapabilities::new(128_000, 16_384, true, true, false, (0.15, 0.6)),
+    ),
+    (
+        "gpt-4o",
+        ModelCapabilities::new(128_000, 16_384, true, true, false, (2.5, 10.0)),
+    ),
+    (
+        "gpt-4.1-mini",
+        ModelCapabilities::new(1_047_576, 32_768, true, true, false, (0.4, 1.6)),
+    ),
+    (
+        "gpt-4.1",
+        ModelCapabilities::new(1_047_576, 32_768, true, true, false, (2.0, 8.0)),
+    ),
+    (
+        "o4-mini",
+        ModelCapabilities::new(200_000, 100_000, true, true, true, (1.1, 4.4)),
+    ),
+    (
+        "o3",
+        ModelCapabilities::new(200_000, 100_000, true, true, true, (2.0, 8.0)),
+    ),
+    (
+        "deepseek-reasoner",
+        ModelCapabilities::new(128_000, 32_768, false, false, true, (0.55, 2.19)),
+    ),
+    (
+        "deepseek",
+        ModelCapabilities::new(128_000, 8_192, false, true, false, (0.27, 1.1)),
+    ),
+    (
+        "gemini-2.5-pro",
+        ModelCapabilities::new(1_048_576, 65_536, true, true, true, (1.25, 10.0)),
+    ),
+    (
+        "gemini-2.5-flash",
+        ModelCapabilities::new(1_048_576, 65_536, true, true, true, (0.3, 2.5)),
+    ),
+    (
+        "gemini",
+        ModelCapabilities::new(1_048_576, 8_192, true, true, false, (0.0, 0.0)),
+    ),
+    (
+        "kimi-k2",
+        ModelCapabilities::new(131_072, 16_384, false, true, false, (0.6, 2.5)),
+    ),
+    (
+        "moonshot",
+        ModelCapabilities::new(131_072, 8_192, false, true, false, (0.0, 0.0)),
+    ),
+    (
+        "glm-4",
+        ModelCapabilities::new(128_000, 8_192, false, true, false, (0.0, 0.0)),
+    ),
+    (
+        "qwen",
+        ModelCapabilities::new(131_072, 8_192, false, true, false, (0.0, 0.0)),
+    ),
+    (
+        "minimax",
+        ModelCapabilities::new(1_000_000, 8_192, false, true, false, (0.0, 0.0)),
+    ),
+    (
+        "llama",
+        ModelCapabilities::new(128_000, 4_096, false, true, false, (0.0, 0.0)),
+    ),
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct ModelRegistry {
+    overrides: HashMap<String, ModelCapabilitiesOverride>,
+}
+
+impl ModelRegistry {
+    pub fn new(overrides: HashMap<String, ModelCapabilitiesOverride>) -> Self {
+        Self {
+            overrides: overrides
+                .into_iter()
+                .map(|(name, o)| (name.to_lowercase(), o))
+                .collect(),
+        }
+    }
+
+    fn builtin(model: &str) -> Option<ModelCapabilities> {
+        let bare = bare_name(model);
+        KNOWN_MODELS
+            .iter()
+            .find(|(pattern, _)| bare.contains(pattern))
+            .map(|(_, caps)| *caps)
+    }
+
+    fn user_override(&self, model: &str) -> Option<&ModelCapabilitiesOverride> {
+        let lower = model.to_lowercase();
+        self.overrides
+            .get(&lower)
+            .or_else(|| self.overrides.get(&bare_name(model)))
+    }
+
+    pub fn is_known(&self, model: &str) -> bool {
+        self.user_override(model).is_some() || Self::builtin(model).is_some()
+    }
+
+    pub fn lookup(&self, model: &str) -> ModelCapabilities {
+        let caps = Self::builtin(model).unwrap_or(UNKNOWN_MODEL);
+        match self.user_override(model) {
+            Some(o) => caps.apply(o),
+            None => caps,
+        }
+    }
+}
+
+fn bare_name(model: &str) -> String {
+    model.rsplit('/').next().unwrap_or(model).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_builtins_and_applies_overrides() {
+        let registry = ModelRegistry::new(HashMap::from([
+            (
+                "my-local-llm".to_string(),
+                ModelCapabilitiesOverride {
+                    context_window: Some(65_536),
+                    vision: Some(true),
+                    ..Default::default()
+                },
+            ),
+            (
+                "gpt-4o".to_string(),
+                ModelCapabilitiesOverride {
+                    input_cost_per_mtok: Some(2.0),
+                    ..Default::default()
+                },
+            ),
+        ]));
+
+        let opus = registry.lookup("anthropic/claude-opus-4-5");
+        assert_eq!(opus.context_window, 200_000);
+        assert!(opus.vision && opus.reasoning);
+        assert!(!registry.lookup("deepseek/deepseek-reasoner").tool_calling);
+        assert!(
+            registry
+                .lookup("openrouter/openai/gpt-4o-mini")
+                .input_cost_per_mtok
+                < 1.0
+        );
+
+        let gpt4o = registry.lookup("openai/gpt-4o");
+        assert_eq!(gpt4o.input_cost_per_mtok, 2.0);
+        assert_eq!(gpt4o.context_window, 128_000);
+
+        let local = registry.lookup("vllm/My-Local-LLM");
+        assert!(registry.is_known("vllm/My-Local-LLM"));
+        assert_eq!(local.context_window, 65_536);
+        assert!(local.vision);
+        assert_eq!(local.max_output_tokens, UNKNOWN_MODEL.max_output_tokens);
+
+        assert!(!registry.is_known("mystery-7b"));
+        assert_eq!(registry.lookup("mystery-7b"), UNKNOWN_MODEL);
+        assert!((opus.estimate_cost(1_000_000, 0) - 15.0).abs() < 1e-9);
+    }
+}
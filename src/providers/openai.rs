@@ -1,9 +1,30 @@
-use crate::providers::base::{LLMProvider, LLMResponse, ToolCallRequest};
+use crate::providers::base::{LLMProvider, LLMResponse, SamplingParams, ToolCallRequest};
 use anyhow::Context;
 use async_trait::async_trait;
 use reqwest::Client;
 use serde_json::{Map, Value, json};
 use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// One connection pool shared by every provider built in this process, so each
+// turn reuses the warm TLS/HTTP/2 connection instead of dialing a new one.
+pub fn pooled_client() -> Client {
+    static CLIENT: OnceLock<Client> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| {
+            Client::builder()
+                .pool_idle_timeout(Duration::from_secs(300))
+                .pool_max_idle_per_host(8)
+                .tcp_keepalive(Duration::from_secs(30))
+                .http2_keep_alive_interval(Duration::from_secs(30))
+                .http2_keep_alive_timeout(Duration::from_secs(10))
+                .http2_keep_alive_while_idle(true)
+                .build()
+                .unwrap_or_default()
+        })
+        .clone()
+}
 
 #[derive(Clone)]
 pub struct OpenAIProvider {
@@ -26,7 +47,7 @@ impl OpenAIProvider {
             api_base: api_base.unwrap_or_else(|| "https://api.openai.com/v1".to_string()),
             default_model: default_model.into(),
             extra_headers: extra_headers.unwrap_or_default(),
-            client: Client::new(),
+            client: pooled_client(),
         }
     }
 }
@@ -40,14 +61,33 @@ impl LLMProvider for OpenAIProvider {
         model: Option<&str>,
         max_tokens: u32,
         temperature: f32,
+    ) -> anyhow::Result<LLMResponse> {
+        let sampling = SamplingParams {
+            max_tokens,
+            temperature,
+            top_p: None,
+        };
+        self.chat_with_sampling(messages, tools, model, &sampling)
+            .await
+    }
+
+    async fn chat_with_sampling(
+        &self,
+        messages: &[Value],
+        tools: Option<&[Value]>,
+        model: Option<&str>,
+        sampling: &SamplingParams,
     ) -> anyhow::Result<LLMResponse> {
         let model_name = model.unwrap_or(&self.default_model).to_string();
         let mut body = json!({
             "model": model_name,
             "messages": messages,
-            "max_tokens": max_tokens,
-            "temperature": temperature,
+            "max_tokens": sampling.max_tokens,
+            "temperature": sampling.temperature,
         });
+        if let Some(top_p) = sampling.top_p {
+            body["top_p"] = json!(top_p);
+        }
 
         if let Some(tool_defs) = tools {
             body["tools"] = Value::Array(tool_defs.to_vec());
@@ -145,7 +185,72 @@ impl LLMProvider for OpenAIProvider {
         })
     }
 
+    // Any response means the connection is up; the status is irrelevant.
+    async fn warm_up(&self) -> anyhow::Result<()> {
+        let url = format!("{}/models", self.api_base.trim_end_matches('/'));
+        let mut req = self
+            .client
+            .get(url)
+            .bearer_auth(&self.api_key)
+            .timeout(Duration::from_secs(10));
+        for (k, v) in &self.extra_headers {
+            req = req.header(k, v);
+        }
+        req.send()
+            .await
+            .context("failed to reach OpenAI-compatible endpoint")?;
+        Ok(())
+    }
+
     fn default_model(&self) -> &str {
         &self.default_model
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Answers every request with an empty 200 and counts TCP connections.
+    fn keep_alive_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind");
+        let base = format!("http://{}/v1", listener.local_addr().expect("addr"));
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                std::thread::spawn(move || {
+                    let mut stream = stream;
+                    let mut buf = [0u8; 4096];
+                    while let Ok(n) = stream.read(&mut buf) {
+                        if n == 0 {
+                            break;
+                        }
+                        let reply = b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n";
+                        if stream.write_all(reply).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (base, connections)
+    }
+
+    #[tokio::test]
+    async fn providers_share_one_connection_pool() {
+        let (base, connections) = keep_alive_server();
+        let first = OpenAIProvider::new("key", Some(base.clone()), "gpt-4o", None);
+        let second = OpenAIProvider::new("key", Some(base), "gpt-4o-mini", None);
+
+        first.warm_up().await.expect("first warm-up");
+        second.warm_up().await.expect("second warm-up");
+        first.warm_up().await.expect("repeat warm-up");
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+}
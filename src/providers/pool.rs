@@ -0,0 +1,247 @@
+use crate::config::KeyPoolConfig;
+use crate::events::{self, EventLevel};
+use crate::providers::base::{LLMProvider, LLMResponse, SamplingParams, quota_exhausted};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+
+const MINUTE_MS: i64 = 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Strategy {
+    RoundRobin,
+    LeastUsed,
+    Scheduled,
+}
+
+impl Strategy {
+    fn parse(value: &str) -> Self {
+        match value.to_lowercase().replace(['-', '_'], "").as_str() {
+            "leastused" => Self::LeastUsed,
+            "scheduled" => Self::Scheduled,
+            _ => Self::RoundRobin,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PoolState {
+    next: usize,
+    uses: Vec<u64>,
+    cooling_until_ms: Vec<i64>,
+}
+
+// Decides which of a provider's keys serves the next call. Keys that hit
+// their quota sit out a cooldown; if every key is cooling, the one that
+// recovers first is tried anyway.
+#[derive(Debug)]
+pub struct KeyPool {
+    strategy: Strategy,
+    rotate_ms: i64,
+    cooldown_ms: i64,
+    state: Mutex<PoolState>,
+}
+
+impl KeyPool {
+    pub fn new(len: usize, config: &KeyPoolConfig) -> Self {
+        Self {
+            strategy: Strategy::parse(&config.strategy),
+            rotate_ms: config.rotate_minutes.max(1) as i64 * MINUTE_MS,
+            cooldown_ms: config.cooldown_minutes as i64 * MINUTE_MS,
+            state: Mutex::new(PoolState {
+                next: 0,
+                uses: vec![0; len],
+                cooling_until_ms: vec![0; len],
+            }),
+        }
+    }
+
+    // Keys to try for one call, best first.
+    fn order_at(&self, now_ms: i64) -> Vec<usize> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let len = state.uses.len();
+        if len == 0 {
+            return Vec::new();
+        }
+        let start = match self.strategy {
+            Strategy::RoundRobin => {
+                let start = state.next % len;
+                state.next = (start + 1) % len;
+                start
+            }
+            Strategy::Scheduled => (now_ms.max(0) / self.rotate_ms) as usize % len,
+            Strategy::LeastUsed => 0,
+        };
+        let mut order = (0..len).map(|i| (start + i) % len).collect::<Vec<_>>();
+        if self.strategy == Strategy::LeastUsed {
+            order.sort_by_key(|&i| state.uses[i]);
+        }
+        let cooling = |i: usize| state.cooling_until_ms[i] > now_ms;
+        let (mut ready, mut waiting): (Vec<_>, Vec<_>) =
+            order.into_iter().partition(|&i| !cooling(i));
+        waiting.sort_by_key(|&i| state.cooling_until_ms[i]);
+        ready.append(&mut waiting);
+        ready
+    }
+
+    fn record_use(&self, key: usize) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(uses) = state.uses.get_mut(key) {
+            *uses += 1;
+        }
+    }
+
+    fn record_exhausted_at(&self, key: usize, now_ms: i64) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(until) = state.cooling_until_ms.get_mut(key) {
+            *until = now_ms + self.cooldown_ms;
+        }
+    }
+}
+
+// Last four characters, enough to tell keys apart in logs.
+fn key_hint(key: &str) -> String {
+    let skip = key.chars().count().saturating_sub(4);
+    format!("...{}", key.chars().skip(skip).collect::<String>())
+}
+
+// One provider per key behind a single LLMProvider. A call that fails on
+// quota moves on to the next key in the pool's order.
+pub struct PooledProvider {
+    name: String,
+    keys: Vec<(String, Arc<dyn LLMProvider>)>,
+    pool: KeyPool,
+    default_model: String,
+}
+
+impl PooledProvider {
+    pub fn new(
+        name: &str,
+        keys: Vec<(String, Arc<dyn LLMProvider>)>,
+        config: &KeyPoolConfig,
+        default_model: &str,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            pool: KeyPool::new(keys.len(), config),
+            keys,
+            default_model: default_model.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl LLMProvider for PooledProvider {
+    async fn chat(
+        &self,
+        messages: &[Value],
+        tools: Option<&[Value]>,
+        model: Option<&str>,
+        max_tokens: u32,
+        temperature: f32,
+    ) -> Result<LLMResponse> {
+        let sampling = SamplingParams {
+            max_tokens,
+            temperature,
+            top_p: None,
+        };
+        self.chat_with_sampling(messages, tools, model, &sampling)
+            .await
+    }
+
+    async fn chat_with_sampling(
+        &self,
+        messages: &[Value],
+        tools: Option<&[Value]>,
+        model: Option<&str>,
+        sampling: &SamplingParams,
+    ) -> Result<LLMResponse> {
+        let order = self.pool.order_at(Utc::now().timestamp_millis());
+        let mut last = None;
+        for (attempt, &index) in order.iter().enumerate() {
+            let (key, provider) = &self.keys[index];
+            self.pool.record_use(index);
+            let result = provider
+                .chat_with_sampling(messages, tools, model, sampling)
+                .await;
+            let Some(error) = quota_exhausted(&result) else {
+                return result;
+            };
+            self.pool
+                .record_exhausted_at(index, Utc::now().timestamp_millis());
+            let remaining = order.len() - attempt - 1;
+            events::record(
+                EventLevel::Warn,
+                "key_pool",
+                None,
+                format!(
+                    "{} key {} hit its quota, {remaining} other key(s) left: {}",
+                    self.name,
+                    key_hint(key),
+                    error.chars().take(200).collect::<String>()
+                ),
+            );
+            last = Some(result);
+        }
+        last.unwrap_or_else(|| Err(anyhow::anyhow!("{} has no API keys", self.name)))
+    }
+
+    async fn warm_up(&self) -> Result<()> {
+        match self.keys.first() {
+            Some((_, provider)) => provider.warm_up().await,
+            None => Ok(()),
+        }
+    }
+
+    fn default_model(&self) -> &str {
+        &self.default_model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(strategy: &str) -> KeyPool {
+        KeyPool::new(
+            3,
+            &KeyPoolConfig {
+                strategy: strategy.to_string(),
+                rotate_minutes: 60,
+                cooldown_minutes: 10,
+            },
+        )
+    }
+
+    #[test]
+    fn orders_keys_by_strategy_and_skips_exhausted_ones() {
+        let robin = pool("roundRobin");
+        assert_eq!(robin.order_at(0), vec![0, 1, 2]);
+        assert_eq!(robin.order_at(0), vec![1, 2, 0]);
+        robin.record_exhausted_at(2, 0);
+        // Key 2 is up next but cooling, so it goes last.
+        assert_eq!(robin.order_at(MINUTE_MS), vec![0, 1, 2]);
+        assert_eq!(robin.order_at(11 * MINUTE_MS), vec![0, 1, 2]);
+        assert_eq!(robin.order_at(11 * MINUTE_MS), vec![1, 2, 0]);
+
+        let least = pool("least-used");
+        least.record_use(0);
+        least.record_use(0);
+        least.record_use(2);
+        assert_eq!(least.order_at(0), vec![1, 2, 0]);
+
+        let scheduled = pool("scheduled");
+        assert_eq!(scheduled.order_at(30 * MINUTE_MS), vec![0, 1, 2]);
+        assert_eq!(scheduled.order_at(90 * MINUTE_MS), vec![1, 2, 0]);
+        assert_eq!(scheduled.order_at(150 * MINUTE_MS), vec![2, 0, 1]);
+
+        for key in 0..3 {
+            scheduled.record_exhausted_at(key, 100 - key as i64);
+        }
+        // Every key is cooling: the one that recovers first goes first.
+        assert_eq!(scheduled.order_at(200), vec![2, 1, 0]);
+        assert_eq!(key_hint("sk-abcdef123456"), "...3456");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::providers::openai::pooled_client;
 use anyhow::Result;
 use reqwest::multipart::{Form, Part};
 use serde_json::Value;
@@ -36,7 +37,7 @@ impl GroqTranscriptionProvider {
             .part("file", part)
             .text("model", "whisper-large-v3");
 
-        let client = reqwest::Client::new();
+        let client = pooled_client();
         let response = client
             .post(&self.api_url)
             .bearer_auth(&self.api_key)
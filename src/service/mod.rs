@@ -2,6 +2,8 @@ use anyhow::Result;
 #[cfg(not(windows))]
 use anyhow::anyhow;
 use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::oneshot;
 
 #[derive(Debug, Clone)]
 pub enum ServiceAccount {
@@ -10,16 +12,38 @@ pub enum ServiceAccount {
     CurrentUser { username: String, password: String },
 }
 
+// What the Service Control Manager does when the gateway crashes or exits
+// with an error: restart after `restart_delay`, doubling for the second and
+// third failure, and forget past failures after `reset_after` without one.
+// A zero delay leaves the service stopped.
+#[derive(Debug, Clone)]
+pub struct RecoveryOptions {
+    pub restart_delay: Duration,
+    pub reset_after: Duration,
+}
+
+impl RecoveryOptions {
+    pub fn restart_delays(&self) -> Vec<Duration> {
+        if self.restart_delay.is_zero() {
+            return Vec::new();
+        }
+        vec![
+            self.restart_delay,
+            self.restart_delay * 2,
+            self.restart_delay * 4,
+        ]
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ServiceInstallOptions {
     pub name: String,
     pub binary_path: PathBuf,
-    pub arguments: String,
+    pub port: u16,
     pub working_directory: PathBuf,
-    pub log_directory: PathBuf,
     pub account: ServiceAccount,
-    pub auto_install_nssm: bool,
     pub autostart: bool,
+    pub recovery: RecoveryOptions,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -28,6 +52,10 @@ pub struct ServiceStatus {
     pub state: Option<String>,
 }
 
+// The gateway as run by the service: it gets a signal that fires when the
+// SCM asks it to stop, and returns once it has shut down.
+pub type ServiceMain = Box<dyn FnOnce(oneshot::Receiver<()>) -> Result<()> + Send>;
+
 #[cfg(windows)]
 mod windows;
 
@@ -102,3 +130,42 @@ pub fn status_service(_name: &str) -> Result<ServiceStatus> {
         "Service management is currently supported on Windows only."
     ))
 }
+
+// Entry point of the installed service; blocks until the SCM stops it.
+#[cfg(windows)]
+pub fn run_service(name: &str, main: ServiceMain) -> Result<()> {
+    windows::run_service(name, main)
+}
+
+#[cfg(not(windows))]
+pub fn run_service(_name: &str, _main: ServiceMain) -> Result<()> {
+    Err(anyhow!(
+        "Service management is currently supported on Windows only."
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restart_delays_back_off_and_zero_disables_them() {
+        let recovery = RecoveryOptions {
+            restart_delay: Duration::from_secs(10),
+            reset_after: Duration::from_secs(86_400),
+        };
+        assert_eq!(
+            recovery.restart_delays(),
+            vec![
+                Duration::from_secs(10),
+                Duration::from_secs(20),
+                Duration::from_secs(40)
+            ]
+        );
+        let off = RecoveryOptions {
+            restart_delay: Duration::ZERO,
+            ..recovery
+        };
+        assert!(off.restart_delays().is_empty());
+    }
+}
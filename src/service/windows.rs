@@ -1,9 +1,38 @@
-use super::{ServiceAccount, ServiceInstallOptions, ServiceStatus};
+use super::{ServiceAccount, ServiceInstallOptions, ServiceMain, ServiceStatus};
+use crate::events::{self, EventLevel};
 use anyhow::{Context, Result, anyhow};
-use std::fs;
+use std::ffi::{OsStr, OsString};
+use std::os::windows::ffi::OsStrExt;
 use std::process::{Command, Output};
+use std::ptr::{null, null_mut};
+use std::sync::{Mutex, OnceLock};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+use windows_service::service::{
+    Service, ServiceAccess, ServiceAction, ServiceActionType, ServiceControl, ServiceControlAccept,
+    ServiceErrorControl, ServiceExitCode, ServiceFailureActions, ServiceFailureResetPeriod,
+    ServiceInfo, ServiceStartType, ServiceState, ServiceStatus as ScmStatus, ServiceType,
+};
+use windows_service::service_control_handler::{self, ServiceControlHandlerResult};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+use windows_service::{define_windows_service, service_dispatcher};
+use windows_sys::Win32::System::EventLog::{
+    DeregisterEventSource, EVENTLOG_ERROR_TYPE, EVENTLOG_INFORMATION_TYPE, EVENTLOG_WARNING_TYPE,
+    RegisterEventSourceW, ReportEventW,
+};
+
+const ERROR_ACCESS_DENIED: i32 = 5;
+const ERROR_SERVICE_ALREADY_RUNNING: i32 = 1056;
+const ERROR_SERVICE_DOES_NOT_EXIST: i32 = 1060;
+const ERROR_SERVICE_NOT_ACTIVE: i32 = 1062;
+const ERROR_SERVICE_MARKED_FOR_DELETE: i32 = 1072;
+const STATE_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Event sources registered against EventCreate.exe take any text for event
+// ids 1-1000, so no message DLL has to ship with the binary.
+const EVENT_SOURCES_KEY: &str = r"HKLM\SYSTEM\CurrentControlSet\Services\EventLog\Application";
+const EVENT_MESSAGE_FILE: &str = r"%SystemRoot%\System32\EventCreate.exe";
 
 fn output_text(bytes: &[u8]) -> String {
     String::from_utf8_lossy(bytes).trim().to_string()
@@ -30,14 +59,19 @@ fn run_checked(program: &str, args: &[&str]) -> Result<Output> {
     ))
 }
 
-fn is_marked_for_delete_error(message: &str) -> bool {
-    let lower = message.to_lowercase();
-    lower.contains("marked for deletion") || message.contains("已标记为删除")
+fn os_error(error: &windows_service::Error) -> Option<i32> {
+    match error {
+        windows_service::Error::Winapi(err) => err.raw_os_error(),
+        _ => None,
+    }
 }
 
-fn with_service_hints(error: anyhow::Error, service_name: &str) -> anyhow::Error {
-    let message = error.to_string();
-    if is_marked_for_delete_error(&message) {
+fn with_service_hints(error: windows_service::Error, service_name: &str) -> anyhow::Error {
+    let message = match &error {
+        windows_service::Error::Winapi(err) => err.to_string(),
+        other => other.to_string(),
+    };
+    if os_error(&error) == Some(ERROR_SERVICE_MARKED_FOR_DELETE) {
         return anyhow!(
             "{message}\n\
              hint: service '{service_name}' is pending deletion.\n\
@@ -45,10 +79,7 @@ fn with_service_hints(error: anyhow::Error, service_name: &str) -> anyhow::Error
              if it still persists, reboot Windows or use a different name via --name."
         );
     }
-
-    let lower = message.to_lowercase();
-    let is_access_denied = lower.contains("access is denied") || message.contains("拒绝访问");
-    if is_access_denied {
+    if os_error(&error) == Some(ERROR_ACCESS_DENIED) {
         return anyhow!(
             "{message}\n\
              hint: run the terminal as Administrator and retry.\n\
@@ -58,12 +89,6 @@ fn with_service_hints(error: anyhow::Error, service_name: &str) -> anyhow::Error
     anyhow!(message)
 }
 
-fn command_exists(name: &str) -> bool {
-    run_command("where", &[name])
-        .map(|out| out.status.success() && !out.stdout.is_empty())
-        .unwrap_or(false)
-}
-
 fn is_elevated() -> bool {
     run_command("net", &["session"])
         .map(|out| out.status.success())
@@ -80,287 +105,430 @@ fn ensure_admin(command_name: &str) -> Result<()> {
     ))
 }
 
-fn ensure_nssm(auto_install: bool) -> Result<()> {
-    if command_exists("nssm") {
-        return Ok(());
-    }
-
-    if !auto_install {
-        return Err(anyhow!(
-            "nssm is not installed. Install it with: winget install --id NSSM.NSSM -e"
-        ));
-    }
-
-    if !command_exists("winget") {
-        return Err(anyhow!(
-            "nssm is missing and winget is unavailable. Install nssm manually first."
-        ));
-    }
-
-    println!("nssm not found, installing via winget...");
-    run_checked(
-        "winget",
-        &[
-            "install",
-            "--id",
-            "NSSM.NSSM",
-            "-e",
-            "--accept-source-agreements",
-            "--accept-package-agreements",
-        ],
-    )?;
+fn open_manager(access: ServiceManagerAccess, name: &str) -> Result<ServiceManager> {
+    ServiceManager::local_computer(None::<&str>, access).map_err(|e| with_service_hints(e, name))
+}
 
-    if !command_exists("nssm") {
-        return Err(anyhow!(
-            "nssm install command completed but nssm is still not found in PATH."
-        ));
+// None when no service of that name is installed.
+fn open_service(name: &str, access: ServiceAccess) -> Result<Option<Service>> {
+    let manager = open_manager(ServiceManagerAccess::CONNECT, name)?;
+    match manager.open_service(name, access) {
+        Ok(service) => Ok(Some(service)),
+        Err(err) if os_error(&err) == Some(ERROR_SERVICE_DOES_NOT_EXIST) => Ok(None),
+        Err(err) => Err(with_service_hints(err, name)),
     }
-
-    println!("nssm installed successfully.");
-    Ok(())
 }
 
-fn service_exists(name: &str) -> bool {
-    run_command("sc", &["query", name])
-        .map(|out| out.status.success())
-        .unwrap_or(false)
+fn require_service(name: &str, access: ServiceAccess) -> Result<Service> {
+    open_service(name, access)?.ok_or_else(|| anyhow!("service '{}' is not installed", name))
 }
 
-fn wait_for_service(name: &str, retries: usize, delay: Duration) -> bool {
-    for _ in 0..retries {
-        if service_exists(name) {
-            return true;
-        }
-        sleep(delay);
+fn state_name(state: ServiceState) -> &'static str {
+    match state {
+        ServiceState::Stopped => "STOPPED",
+        ServiceState::StartPending => "START_PENDING",
+        ServiceState::StopPending => "STOP_PENDING",
+        ServiceState::Running => "RUNNING",
+        ServiceState::ContinuePending => "CONTINUE_PENDING",
+        ServiceState::PausePending => "PAUSE_PENDING",
+        ServiceState::Paused => "PAUSED",
     }
-    false
 }
 
-fn parse_state(sc_output: &str) -> Option<String> {
-    for line in sc_output.lines() {
-        if !line.contains("STATE") {
-            continue;
-        }
-        let (_, right) = line.split_once(':')?;
-        let tokens = right.split_whitespace().collect::<Vec<_>>();
-        if tokens.is_empty() {
-            return None;
+fn wait_for_state(service: &Service, name: &str, wanted: ServiceState) -> Result<()> {
+    let started = Instant::now();
+    loop {
+        let status = service
+            .query_status()
+            .map_err(|e| with_service_hints(e, name))?;
+        if status.current_state == wanted {
+            return Ok(());
         }
-        if tokens[0].chars().all(|c| c.is_ascii_digit()) {
-            return tokens.get(1).map(|s| s.to_string());
+        if started.elapsed() > STATE_TIMEOUT {
+            return Err(anyhow!(
+                "service '{}' is still {} after {}s",
+                name,
+                state_name(status.current_state),
+                STATE_TIMEOUT.as_secs()
+            ));
         }
-        return Some(tokens[0].to_string());
+        sleep(Duration::from_millis(250));
+    }
+}
+
+fn service_info(options: &ServiceInstallOptions) -> ServiceInfo {
+    let (account_name, account_password) = match &options.account {
+        ServiceAccount::Inherit => (None, None),
+        ServiceAccount::LocalSystem => (Some(OsString::from("LocalSystem")), None),
+        ServiceAccount::CurrentUser { username, password } => (
+            Some(OsString::from(username)),
+            Some(OsString::from(password)),
+        ),
+    };
+    ServiceInfo {
+        name: OsString::from(&options.name),
+        display_name: OsString::from(&options.name),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: if options.autostart {
+            ServiceStartType::AutoStart
+        } else {
+            ServiceStartType::OnDemand
+        },
+        error_control: ServiceErrorControl::Normal,
+        executable_path: options.binary_path.clone(),
+        launch_arguments: vec![
+            OsString::from("service"),
+            OsString::from("run"),
+            OsString::from("--name"),
+            OsString::from(&options.name),
+            OsString::from("--port"),
+            OsString::from(options.port.to_string()),
+            OsString::from("--workdir"),
+            options.working_directory.clone().into_os_string(),
+        ],
+        dependencies: Vec::new(),
+        account_name,
+        account_password,
     }
-    None
 }
 
-fn set_service_value(name: &str, key: &str, value: &str) -> Result<()> {
-    run_checked("nssm", &["set", name, key, value]).map_err(|e| with_service_hints(e, name))?;
+fn set_recovery(service: &Service, options: &ServiceInstallOptions) -> Result<()> {
+    let actions = options
+        .recovery
+        .restart_delays()
+        .into_iter()
+        .map(|delay| ServiceAction {
+            action_type: ServiceActionType::Restart,
+            delay,
+        })
+        .collect::<Vec<_>>();
+    let restarts = !actions.is_empty();
+    service
+        .update_failure_actions(ServiceFailureActions {
+            reset_period: ServiceFailureResetPeriod::After(options.recovery.reset_after),
+            reboot_msg: None,
+            command: None,
+            actions: Some(actions),
+        })
+        .map_err(|e| with_service_hints(e, &options.name))?;
+    // Also restart when the gateway exits with an error, not only on crashes.
+    service
+        .set_failure_actions_on_non_crash_failures(restarts)
+        .map_err(|e| with_service_hints(e, &options.name))?;
     Ok(())
 }
 
-fn set_service_values(name: &str, key: &str, values: &[&str]) -> Result<()> {
-    let mut args = vec!["set", name, key];
-    args.extend(values);
-    run_checked("nssm", &args).map_err(|e| with_service_hints(e, name))?;
+fn register_event_source(name: &str) -> Result<()> {
+    let key = format!(r"{EVENT_SOURCES_KEY}\{name}");
+    run_checked(
+        "reg",
+        &[
+            "add",
+            &key,
+            "/v",
+            "EventMessageFile",
+            "/t",
+            "REG_EXPAND_SZ",
+            "/d",
+            EVENT_MESSAGE_FILE,
+            "/f",
+        ],
+    )?;
+    run_checked(
+        "reg",
+        &[
+            "add",
+            &key,
+            "/v",
+            "TypesSupported",
+            "/t",
+            "REG_DWORD",
+            "/d",
+            "7",
+            "/f",
+        ],
+    )?;
     Ok(())
 }
 
-fn set_service_account(name: &str, account: &ServiceAccount) -> Result<()> {
-    match account {
-        ServiceAccount::Inherit => Ok(()),
-        ServiceAccount::LocalSystem => {
-            run_checked("nssm", &["set", name, "ObjectName", "LocalSystem"])
-                .map_err(|e| with_service_hints(e, name))?;
-            Ok(())
-        }
-        ServiceAccount::CurrentUser { username, password } => {
-            run_checked("nssm", &["set", name, "ObjectName", username, password])
-                .map_err(|e| with_service_hints(e, name))?;
-            Ok(())
+fn remove_event_source(name: &str) {
+    let key = format!(r"{EVENT_SOURCES_KEY}\{name}");
+    let _ = run_command("reg", &["delete", &key, "/f"]);
+}
+
+fn wide(text: &str) -> Vec<u16> {
+    OsStr::new(text)
+        .encode_wide()
+        .map(|c| if c == 0 { u16::from(b' ') } else { c })
+        .chain(Some(0))
+        .collect()
+}
+
+// Writes one entry to the Application event log under the service's name.
+pub fn report_event(source: &str, level: EventLevel, message: &str) {
+    let (kind, id) = match level {
+        EventLevel::Info => (EVENTLOG_INFORMATION_TYPE, 1),
+        EventLevel::Warn => (EVENTLOG_WARNING_TYPE, 2),
+        EventLevel::Error => (EVENTLOG_ERROR_TYPE, 3),
+    };
+    let source = wide(source);
+    let message = wide(message);
+    let strings = [message.as_ptr()];
+    // SAFETY: both strings are NUL-terminated and outlive the calls; the
+    // handle is released before returning.
+    unsafe {
+        let handle = RegisterEventSourceW(null(), source.as_ptr());
+        if handle.is_null() {
+            return;
         }
+        ReportEventW(
+            handle,
+            kind,
+            0,
+            id,
+            null_mut(),
+            1,
+            0,
+            strings.as_ptr(),
+            null(),
+        );
+        DeregisterEventSource(handle);
     }
 }
 
 pub fn install_service(options: &ServiceInstallOptions) -> Result<()> {
     ensure_admin("install")?;
-    ensure_nssm(options.auto_install_nssm)?;
-    fs::create_dir_all(&options.log_directory).with_context(|| {
-        format!(
-            "failed to create log directory: {}",
-            options.log_directory.display()
-        )
-    })?;
-
-    let binary = options.binary_path.to_string_lossy().to_string();
-    let workdir = options.working_directory.to_string_lossy().to_string();
-    let stdout_log = options
-        .log_directory
-        .join(format!("{}.out.log", options.name))
-        .to_string_lossy()
-        .to_string();
-    let stderr_log = options
-        .log_directory
-        .join(format!("{}.err.log", options.name))
-        .to_string_lossy()
-        .to_string();
-
-    if service_exists(&options.name) {
-        println!(
-            "Service '{}' already exists, updating configuration...",
-            options.name
-        );
-    } else {
-        let mut cmd = Command::new("nssm");
-        cmd.arg("install").arg(&options.name).arg(&binary);
-        if !options.arguments.trim().is_empty() {
-            cmd.arg(options.arguments.trim());
-        }
-        let out = cmd.output().with_context(|| {
-            format!(
-                "failed to execute command: nssm install {} {}",
-                options.name, binary
-            )
-        })?;
-        if !out.status.success() {
-            return Err(with_service_hints(
-                anyhow!(
-                    "command failed: nssm install {}\nstdout: {}\nstderr: {}",
-                    options.name,
-                    output_text(&out.stdout),
-                    output_text(&out.stderr),
-                ),
-                &options.name,
-            ));
+    let manager = open_manager(
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+        &options.name,
+    )?;
+    let info = service_info(options);
+    let access = ServiceAccess::QUERY_CONFIG
+        | ServiceAccess::CHANGE_CONFIG
+        | ServiceAccess::QUERY_STATUS
+        | ServiceAccess::START;
+    let service = match manager.open_service(&options.name, access) {
+        Ok(service) => {
+            println!(
+                "Service '{}' already exists, updating configuration...",
+                options.name
+            );
+            service
+                .change_config(&info)
+                .map_err(|e| with_service_hints(e, &options.name))?;
+            service
         }
-        if !wait_for_service(&options.name, 25, Duration::from_millis(200)) {
-            return Err(anyhow!(
-                "nssm install reported success but service '{}' is still unavailable.\n\
-                 install stdout: {}\n\
-                 install stderr: {}\n\
-                 hint: run terminal as Administrator and retry.",
-                options.name,
-                output_text(&out.stdout),
-                output_text(&out.stderr),
-            ));
+        Err(err) if os_error(&err) == Some(ERROR_SERVICE_DOES_NOT_EXIST) => {
+            let service = manager
+                .create_service(&info, access)
+                .map_err(|e| with_service_hints(e, &options.name))?;
+            println!("Service '{}' installed.", options.name);
+            service
         }
-        println!("Service '{}' installed.", options.name);
-    }
-
-    if !wait_for_service(&options.name, 10, Duration::from_millis(150)) {
-        return Err(anyhow!(
-            "service '{}' is not visible to SCM yet; cannot apply configuration",
-            options.name
-        ));
-    }
-
-    set_service_value(&options.name, "Application", &binary)?;
-    if !options.arguments.trim().is_empty() {
-        set_service_value(&options.name, "AppParameters", options.arguments.trim())?;
+        Err(err) => return Err(with_service_hints(err, &options.name)),
+    };
+    set_recovery(&service, options)?;
+    if let Err(err) = register_event_source(&options.name) {
+        println!("Warning: event log source not registered: {err}");
     }
-    set_service_value(&options.name, "AppDirectory", &workdir)?;
-    set_service_value(&options.name, "AppStdout", &stdout_log)?;
-    set_service_value(&options.name, "AppStderr", &stderr_log)?;
-    set_service_value(&options.name, "AppRotateFiles", "1")?;
-    set_service_value(&options.name, "AppRotateOnline", "1")?;
-    set_service_values(&options.name, "AppExit", &["Default", "Restart"])?;
-    set_service_account(&options.name, &options.account)?;
-    set_service_value(
-        &options.name,
-        "Start",
-        if options.autostart {
-            "SERVICE_AUTO_START"
-        } else {
-            "SERVICE_DEMAND_START"
-        },
-    )?;
-
     Ok(())
 }
 
 pub fn remove_service(name: &str) -> Result<()> {
     ensure_admin("remove")?;
-    ensure_nssm(false)?;
-    if !service_exists(name) {
+    let access = ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE;
+    let Some(service) = open_service(name, access)? else {
         println!("Service '{}' is not installed.", name);
         return Ok(());
+    };
+    if let Ok(status) = service.query_status()
+        && status.current_state != ServiceState::Stopped
+    {
+        let _ = service.stop();
+        let _ = wait_for_state(&service, name, ServiceState::Stopped);
     }
-    if let Err(err) = run_checked("nssm", &["remove", name, "confirm"]) {
-        let msg = err.to_string();
-        if is_marked_for_delete_error(&msg) {
+    match service.delete() {
+        Ok(()) => {}
+        Err(err) if os_error(&err) == Some(ERROR_SERVICE_MARKED_FOR_DELETE) => {
             println!(
                 "Service '{}' is already marked for deletion. It will disappear shortly.",
                 name
             );
-            return Ok(());
         }
-        return Err(with_service_hints(err, name));
+        Err(err) => return Err(with_service_hints(err, name)),
     }
+    remove_event_source(name);
     Ok(())
 }
 
 pub fn start_service(name: &str) -> Result<()> {
     ensure_admin("start")?;
-    ensure_nssm(false)?;
-    if !service_exists(name) {
-        return Err(anyhow!("service '{}' is not installed", name));
+    let service = require_service(name, ServiceAccess::START | ServiceAccess::QUERY_STATUS)?;
+    match service.start(&[] as &[&OsStr]) {
+        Ok(()) => {}
+        Err(err) if os_error(&err) == Some(ERROR_SERVICE_ALREADY_RUNNING) => return Ok(()),
+        Err(err) => return Err(with_service_hints(err, name)),
     }
-    run_checked("nssm", &["start", name])?;
-    Ok(())
+    wait_for_state(&service, name, ServiceState::Running)
 }
 
 pub fn stop_service(name: &str) -> Result<()> {
     ensure_admin("stop")?;
-    ensure_nssm(false)?;
-    if !service_exists(name) {
-        return Err(anyhow!("service '{}' is not installed", name));
+    let service = require_service(name, ServiceAccess::STOP | ServiceAccess::QUERY_STATUS)?;
+    match service.stop() {
+        Ok(_) => {}
+        Err(err) if os_error(&err) == Some(ERROR_SERVICE_NOT_ACTIVE) => return Ok(()),
+        Err(err) => return Err(with_service_hints(err, name)),
     }
-    run_checked("nssm", &["stop", name])?;
-    Ok(())
+    wait_for_state(&service, name, ServiceState::Stopped)
 }
 
 pub fn restart_service(name: &str) -> Result<()> {
     ensure_admin("restart")?;
-    ensure_nssm(false)?;
-    if !service_exists(name) {
-        return Err(anyhow!("service '{}' is not installed", name));
-    }
-    let current = status_service(name)?;
-    if current.state.as_deref() == Some("RUNNING") {
-        run_checked("nssm", &["stop", name])?;
+    let service = require_service(
+        name,
+        ServiceAccess::START | ServiceAccess::STOP | ServiceAccess::QUERY_STATUS,
+    )?;
+    let current = service
+        .query_status()
+        .map_err(|e| with_service_hints(e, name))?;
+    if current.current_state != ServiceState::Stopped {
+        match service.stop() {
+            Ok(_) => {}
+            Err(err) if os_error(&err) == Some(ERROR_SERVICE_NOT_ACTIVE) => {}
+            Err(err) => return Err(with_service_hints(err, name)),
+        }
+        wait_for_state(&service, name, ServiceState::Stopped)?;
     }
-    run_checked("nssm", &["start", name])?;
-    Ok(())
+    service
+        .start(&[] as &[&OsStr])
+        .map_err(|e| with_service_hints(e, name))?;
+    wait_for_state(&service, name, ServiceState::Running)
 }
 
 pub fn status_service(name: &str) -> Result<ServiceStatus> {
-    if !service_exists(name) {
+    let Some(service) = open_service(name, ServiceAccess::QUERY_STATUS)? else {
         return Ok(ServiceStatus {
             exists: false,
             state: None,
         });
-    }
-    let output = run_checked("sc", &["query", name])?;
-    let state = parse_state(&String::from_utf8_lossy(&output.stdout));
+    };
+    let status = service
+        .query_status()
+        .map_err(|e| with_service_hints(e, name))?;
     Ok(ServiceStatus {
         exists: true,
-        state,
+        state: Some(state_name(status.current_state).to_string()),
+    })
+}
+
+// The dispatcher calls back into a plain function, so the service's name and
+// gateway wait here until it does.
+static RUNNING: OnceLock<(String, Mutex<Option<ServiceMain>>)> = OnceLock::new();
+
+define_windows_service!(ffi_service_main, service_main);
+
+pub fn run_service(name: &str, main: ServiceMain) -> Result<()> {
+    RUNNING
+        .set((name.to_string(), Mutex::new(Some(main))))
+        .map_err(|_| anyhow!("service '{}' is already running", name))?;
+    service_dispatcher::start(name, ffi_service_main).map_err(|e| {
+        anyhow!(
+            "{}\nhint: `service run` is started by Windows; use `nanobot service start` instead.",
+            with_service_hints(e, name)
+        )
     })
 }
 
+fn scm_status(state: ServiceState, exit_code: ServiceExitCode) -> ScmStatus {
+    ScmStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted: if state == ServiceState::Running {
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+        } else {
+            ServiceControlAccept::empty()
+        },
+        exit_code,
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    }
+}
+
+fn service_main(_arguments: Vec<OsString>) {
+    let Some((name, main)) = RUNNING.get() else {
+        return;
+    };
+    let Some(main) = main.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+        return;
+    };
+    if let Err(err) = serve(name, main) {
+        report_event(name, EventLevel::Error, &format!("{err:#}"));
+    }
+}
+
+fn serve(name: &str, main: ServiceMain) -> Result<()> {
+    let (stop_tx, stop_rx) = oneshot::channel();
+    let mut stop_tx = Some(stop_tx);
+    let handler = move |control| match control {
+        ServiceControl::Stop | ServiceControl::Shutdown => {
+            if let Some(tx) = stop_tx.take() {
+                let _ = tx.send(());
+            }
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    };
+    let handle = service_control_handler::register(name, handler)
+        .map_err(|e| with_service_hints(e, name))?;
+    handle
+        .set_service_status(scm_status(ServiceState::Running, ServiceExitCode::NO_ERROR))
+        .map_err(|e| with_service_hints(e, name))?;
+
+    // Warnings and errors the gateway records also reach the event log.
+    let source = name.to_string();
+    events::mirror(move |event| {
+        if event.level != EventLevel::Info {
+            report_event(&source, event.level, &event.render());
+        }
+    });
+    report_event(name, EventLevel::Info, "nanobot gateway started");
+
+    // A non-zero exit code makes the SCM apply the recovery actions.
+    let exit_code = match main(stop_rx) {
+        Ok(()) => {
+            report_event(name, EventLevel::Info, "nanobot gateway stopped");
+            ServiceExitCode::NO_ERROR
+        }
+        Err(err) => {
+            report_event(
+                name,
+                EventLevel::Error,
+                &format!("nanobot gateway failed: {err:#}"),
+            );
+            ServiceExitCode::ServiceSpecific(1)
+        }
+    };
+    handle
+        .set_service_status(scm_status(ServiceState::Stopped, exit_code))
+        .map_err(|e| with_service_hints(e, name))?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use super::parse_state;
+    use super::*;
 
     #[test]
-    fn parse_state_extracts_running() {
-        let sample = "SERVICE_NAME: nanobot-rs\n        STATE              : 4  RUNNING";
-        assert_eq!(parse_state(sample).as_deref(), Some("RUNNING"));
+    fn state_name_matches_sc_output() {
+        assert_eq!(state_name(ServiceState::Running), "RUNNING");
+        assert_eq!(state_name(ServiceState::StopPending), "STOP_PENDING");
     }
 
     #[test]
-    fn parse_state_handles_missing_line() {
-        let sample = "SERVICE_NAME: nanobot-rs";
-        assert_eq!(parse_state(sample), None);
+    fn wide_strings_are_nul_terminated_without_inner_nuls() {
+        assert_eq!(wide("a\0b"), vec![97, 32, 98, 0]);
     }
 }
@@ -1,10 +1,10 @@
+use crate::storage::{Location, locate};
 use crate::utils::{get_data_path, safe_filename, timestamp};
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value, json};
 use std::collections::HashMap;
-use std::ffi::OsStr;
 use std::path::PathBuf;
 use std::sync::Mutex;
 
@@ -82,6 +82,52 @@ impl Session {
     }
 }
 
+pub fn transcript_lines(messages: &[Value]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for msg in messages {
+        let Some(content) = msg.get("content").and_then(Value::as_str) else {
+            continue;
+        };
+        if content.trim().is_empty() {
+            continue;
+        }
+        let timestamp = msg
+            .get("timestamp")
+            .and_then(Value::as_str)
+            .unwrap_or("?")
+            .chars()
+            .take(16)
+            .collect::<String>();
+        let role = msg
+            .get("role")
+            .and_then(Value::as_str)
+            .unwrap_or("user")
+            .to_ascii_uppercase();
+        let tools_suffix = msg
+            .get("tools_used")
+            .and_then(Value::as_array)
+            .filter(|tools| !tools.is_empty())
+            .map(|tools| {
+                let list = tools
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if list.is_empty() {
+                    String::new()
+                } else {
+                    format!(" [tools: {list}]")
+                }
+            })
+            .unwrap_or_default();
+        lines.push(format!(
+            "[{timestamp}] {role}{tools_suffix}: {content}",
+            content = content.trim()
+        ));
+    }
+    lines
+}
+
 #[cfg(test)]
 mod tests {
     use super::Session;
@@ -103,23 +149,61 @@ mod tests {
 }
 
 pub struct SessionManager {
-    sessions_dir: PathBuf,
+    sessions: Location,
     cache: Mutex<HashMap<String, Session>>,
+    changes: Mutex<ChangeLog>,
+}
+
+// Which sessions this manager saved or deleted, and when, so the history
+// index re-reads only those instead of every session on each search.
+#[derive(Default)]
+struct ChangeLog {
+    generation: u64,
+    sessions: HashMap<String, u64>,
 }
 
 impl SessionManager {
     pub fn new() -> Result<Self> {
-        let sessions_dir = get_data_path()?.join("sessions");
-        std::fs::create_dir_all(&sessions_dir)?;
+        Self::from_dir(get_data_path()?.join("sessions"))
+    }
+
+    pub fn from_dir(sessions_dir: PathBuf) -> Result<Self> {
         Ok(Self {
-            sessions_dir,
+            sessions: locate(&sessions_dir),
             cache: Mutex::new(HashMap::new()),
+            changes: Mutex::new(ChangeLog::default()),
         })
     }
 
-    fn session_path(&self, key: &str) -> PathBuf {
+    fn mark_changed(&self, key: &str) {
+        let mut changes = self.changes.lock().unwrap_or_else(|e| e.into_inner());
+        changes.generation += 1;
+        let generation = changes.generation;
+        changes.sessions.insert(key.to_string(), generation);
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.changes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .generation
+    }
+
+    // The current generation and the sessions saved or deleted after `since`.
+    pub fn changed_since(&self, since: u64) -> (u64, Vec<String>) {
+        let changes = self.changes.lock().unwrap_or_else(|e| e.into_inner());
+        let keys = changes
+            .sessions
+            .iter()
+            .filter(|(_, generation)| **generation > since)
+            .map(|(key, _)| key.clone())
+            .collect();
+        (changes.generation, keys)
+    }
+
+    fn session_file(&self, key: &str) -> Location {
         let safe_key = safe_filename(&key.replace(':', "_"));
-        self.sessions_dir.join(format!("{safe_key}.jsonl"))
+        self.sessions.child(&format!("{safe_key}.jsonl"))
     }
 
     pub fn get_or_create(&self, key: &str) -> Session {
@@ -135,11 +219,6 @@ impl SessionManager {
     }
 
     pub fn save(&self, session: &Session) -> Result<()> {
-        let path = self.session_path(&session.key);
-        if let Some(parent) = path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-
         let mut lines = Vec::new();
         lines.push(serde_json::to_string(&json!({
             "_type": "metadata",
@@ -152,11 +231,13 @@ impl SessionManager {
         for msg in &session.messages {
             lines.push(serde_json::to_string(msg)?);
         }
-        std::fs::write(&path, format!("{}\n", lines.join("\n")))?;
+        self.session_file(&session.key)
+            .write(&format!("{}\n", lines.join("\n")))?;
 
         if let Ok(mut cache) = self.cache.lock() {
             cache.insert(session.key.clone(), session.clone());
         }
+        self.mark_changed(&session.key);
         Ok(())
     }
 
@@ -164,12 +245,9 @@ impl SessionManager {
         if let Ok(mut cache) = self.cache.lock() {
             cache.remove(key);
         }
-        let path = self.session_path(key);
-        if path.exists() {
-            std::fs::remove_file(path).is_ok()
-        } else {
-            false
-        }
+        let removed = self.session_file(key).remove().unwrap_or(false);
+        self.mark_changed(key);
+        removed
     }
 
     pub fn load_session(&self, key: &str) -> Result<Session> {
@@ -178,16 +256,14 @@ impl SessionManager {
 
     pub fn list_session_keys(&self) -> Result<Vec<String>> {
         let mut keys = Vec::new();
-        for entry in std::fs::read_dir(&self.sessions_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.extension() != Some(OsStr::new("jsonl")) {
+        for name in self.sessions.children()? {
+            let Some(stem) = name.strip_suffix(".jsonl") else {
                 continue;
-            }
+            };
 
-            let content = match std::fs::read_to_string(&path) {
-                Ok(v) => v,
-                Err(_) => continue,
+            let content = match self.sessions.child(&name).read() {
+                Ok(Some(v)) => v,
+                _ => continue,
             };
             let mut restored_key = None::<String>;
             if let Some(first_line) = content.lines().find(|line| !line.trim().is_empty())
@@ -200,14 +276,7 @@ impl SessionManager {
                     .map(ToOwned::to_owned);
             }
 
-            let key = if let Some(key) = restored_key {
-                key
-            } else if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                stem.replace('_', ":")
-            } else {
-                continue;
-            };
-            keys.push(key);
+            keys.push(restored_key.unwrap_or_else(|| stem.replace('_', ":")));
         }
 
         keys.sort();
@@ -215,9 +284,10 @@ impl SessionManager {
     }
 
     fn load(&self, key: &str) -> Result<Session> {
-        let path = self.session_path(key);
-        let content = std::fs::read_to_string(&path)
-            .with_context(|| format!("failed reading {}", path.display()))?;
+        let content = self
+            .session_file(key)
+            .read()?
+            .with_context(|| format!("no saved session {key}"))?;
 
         let mut session = Session::new(key);
         for line in content
@@ -0,0 +1,624 @@
+use crate::agent::AgentLoop;
+use crate::bus::{InboundMessage, MessageBus, OutboundMessage};
+use crate::channels::chunking::split_reply;
+use crate::channels::format::{channel_capabilities, format_message};
+use crate::config::{AgentDefaults, WebSearchConfig};
+use crate::providers::base::{LLMProvider, LLMResponse, ToolCallRequest};
+use crate::session::SessionManager;
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const MOCK_MODEL: &str = "mock";
+
+// A scripted conversation: each step sends one inbound message, answers the
+// agent's provider calls from `replies`, and checks what came out.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Scenario {
+    pub name: String,
+    pub channel: String,
+    pub chat_id: String,
+    pub sender: String,
+    pub model: String,
+    pub read_only_channels: Vec<String>,
+    // Seeded into the scratch workspace before the first step.
+    pub files: BTreeMap<String, String>,
+    pub steps: Vec<Step>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            channel: "cli".to_string(),
+            chat_id: "direct".to_string(),
+            sender: "user".to_string(),
+            model: MOCK_MODEL.to_string(),
+            read_only_channels: Vec::new(),
+            files: BTreeMap::new(),
+            steps: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Step {
+    pub user: String,
+    pub channel: Option<String>,
+    pub chat_id: Option<String>,
+    pub sender: Option<String>,
+    pub metadata: Map<String, Value>,
+    // Provider responses for this turn, consumed in order.
+    pub replies: Vec<ScriptedReply>,
+    pub expect: Expect,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScriptedReply {
+    pub content: Option<String>,
+    pub tool_calls: Vec<ScriptedToolCall>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScriptedToolCall {
+    pub name: String,
+    pub arguments: Map<String, Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Expect {
+    // Tools the loop executed this turn, in order.
+    pub tool_calls: Option<Vec<String>>,
+    pub tool_results_contain: Vec<String>,
+    pub tools_offered: Vec<String>,
+    pub tools_not_offered: Vec<String>,
+    pub system_prompt_contains: Vec<String>,
+    pub reply: Option<String>,
+    pub reply_contains: Vec<String>,
+    pub reply_not_contains: Vec<String>,
+    pub reply_matches: Option<String>,
+    // Outbound messages published during the turn, the reply included.
+    pub outbound: Option<usize>,
+    // Messages the reply is split into under the channel's length limit.
+    pub parts: Option<usize>,
+    // Checked against the reply rendered in the channel's native markup.
+    pub formatted_contains: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepResult {
+    pub index: usize,
+    pub user: String,
+    pub reply: String,
+    pub tool_calls: Vec<String>,
+    pub failures: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub steps: Vec<StepResult>,
+}
+
+impl ScenarioReport {
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.failures.is_empty())
+    }
+
+    pub fn render(&self, verbose: bool) -> String {
+        let mut lines = vec![format!("Scenario: {}", self.name)];
+        for step in &self.steps {
+            let status = if step.failures.is_empty() {
+                "ok"
+            } else {
+                "FAIL"
+            };
+            lines.push(format!(
+                "  [{status}] step {}: {}",
+                step.index + 1,
+                truncate(&step.user, 60)
+            ));
+            if verbose || !step.failures.is_empty() {
+                if !step.tool_calls.is_empty() {
+                    lines.push(format!("         tools: {}", step.tool_calls.join(", ")));
+                }
+                lines.push(format!("         reply: {}", truncate(&step.reply, 200)));
+            }
+            for failure in &step.failures {
+                lines.push(format!("         - {failure}"));
+            }
+        }
+        let failed = self
+            .steps
+            .iter()
+            .filter(|step| !step.failures.is_empty())
+            .count();
+        lines.push(format!(
+            "{} step(s), {} passed, {failed} failed",
+            self.steps.len(),
+            self.steps.len() - failed
+        ));
+        lines.join("\n")
+    }
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    let flat = text.replace('\n', " ");
+    if flat.chars().count() <= max {
+        flat
+    } else {
+        format!("{}...", flat.chars().take(max).collect::<String>())
+    }
+}
+
+// Answers chat calls from the current step's script and keeps every request
+// so the runner can inspect prompts, offered tools and tool results.
+struct MockProvider {
+    replies: Mutex<VecDeque<LLMResponse>>,
+    requests: Mutex<Vec<(Vec<Value>, Vec<String>)>>,
+    next_call: Mutex<usize>,
+}
+
+impl MockProvider {
+    fn new() -> Self {
+        Self {
+            replies: Mutex::new(VecDeque::new()),
+            requests: Mutex::new(Vec::new()),
+            next_call: Mutex::new(0),
+        }
+    }
+
+    fn load(&self, replies: &[ScriptedReply], workspace: &str) {
+        let mut next_call = self.next_call.lock().unwrap_or_else(|e| e.into_inner());
+        let mut queue = self.replies.lock().unwrap_or_else(|e| e.into_inner());
+        queue.clear();
+        for reply in replies {
+            let tool_calls = reply
+                .tool_calls
+                .iter()
+                .map(|call| {
+                    *next_call += 1;
+                    ToolCallRequest {
+                        id: format!("call_{}", *next_call),
+                        name: call.name.clone(),
+                        arguments: substitute(&Value::Object(call.arguments.clone()), workspace)
+                            .as_object()
+                            .cloned()
+                            .unwrap_or_default(),
+                    }
+                })
+                .collect();
+            queue.push_back(LLMResponse {
+                content: reply
+                    .content
+                    .as_ref()
+                    .map(|text| text.replace("{workspace}", workspace)),
+                tool_calls,
+                finish_reason: "stop".to_string(),
+                usage: Map::new(),
+                reasoning_content: None,
+            });
+        }
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+
+    fn unused(&self) -> usize {
+        self.replies.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    fn requests(&self) -> Vec<(Vec<Value>, Vec<String>)> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+#[async_trait]
+impl LLMProvider for MockProvider {
+    async fn chat(
+        &self,
+        messages: &[Value],
+        tools: Option<&[Value]>,
+        _model: Option<&str>,
+        _max_tokens: u32,
+        _temperature: f32,
+    ) -> Result<LLMResponse> {
+        // The turn guard double-checks final answers with a classifier call;
+        // scripts only cover the conversation itself.
+        let is_classifier = messages
+            .first()
+            .and_then(|m| m["content"].as_str())
+            .is_some_and(|text| text.starts_with("You are a strict classifier"));
+        if is_classifier {
+            return Ok(LLMResponse {
+                content: Some(r#"{"claims_no_tools": false}"#.to_string()),
+                tool_calls: Vec::new(),
+                finish_reason: "stop".to_string(),
+                usage: Map::new(),
+                reasoning_content: None,
+            });
+        }
+        let offered = tools
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|tool| tool["function"]["name"].as_str().map(str::to_string))
+            .collect();
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push((messages.to_vec(), offered));
+        self.replies
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .pop_front()
+            .ok_or_else(|| anyhow!("scenario has no scripted reply left for this turn"))
+    }
+
+    fn default_model(&self) -> &str {
+        MOCK_MODEL
+    }
+}
+
+// Replaces `{workspace}` in every string, so scripted paths can point into
+// the scratch workspace.
+fn substitute(value: &Value, workspace: &str) -> Value {
+    match value {
+        Value::String(text) => Value::String(text.replace("{workspace}", workspace)),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| substitute(item, workspace))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, item)| (key.clone(), substitute(item, workspace)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+pub fn load_scenario(path: &Path) -> Result<Scenario> {
+    let raw = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read scenario {}", path.display()))?;
+    let mut scenario: Scenario = serde_yaml::from_str(&raw)
+        .with_context(|| format!("invalid scenario {}", path.display()))?;
+    if scenario.name.is_empty() {
+        scenario.name = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+    }
+    Ok(scenario)
+}
+
+// Runs a scenario through a real agent loop in a scratch directory, so
+// sessions, tasks and files never touch ~/.nanobot.
+pub async fn run_scenario(scenario: &Scenario) -> Result<ScenarioReport> {
+    let root = std::env::temp_dir().join(format!("nanobot-simulate-{}", uuid::Uuid::new_v4()));
+    let result = run_in(scenario, &root).await;
+    let _ = std::fs::remove_dir_all(&root);
+    result
+}
+
+async fn run_in(scenario: &Scenario, root: &Path) -> Result<ScenarioReport> {
+    let workspace = root.join("workspace");
+    std::fs::create_dir_all(&workspace)?;
+    let workspace = workspace.canonicalize()?;
+    for (name, content) in &scenario.files {
+        let path = workspace.join(name);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+    }
+    let workspace_str = workspace.display().to_string();
+
+    let bus = Arc::new(MessageBus::new(100));
+    let provider = Arc::new(MockProvider::new());
+    let defaults = AgentDefaults::default();
+    let agent = AgentLoop::new(
+        bus.clone(),
+        provider.clone(),
+        workspace.clone(),
+        Some(scenario.model.clone()),
+        defaults.max_tool_iterations,
+        defaults.memory_window,
+        WebSearchConfig::default(),
+        60,
+        true,
+        None,
+        Some(Arc::new(SessionManager::from_dir(root.join("sessions"))?)),
+    )?
+    .with_read_only_channels(scenario.read_only_channels.clone())
+    .with_sampling(defaults.sampling())
+    .with_maintenance_sampling(defaults.maintenance_sampling())
+    .with_data_dir(root);
+
+    let mut steps = Vec::new();
+    for (index, step) in scenario.steps.iter().enumerate() {
+        provider.load(&step.replies, &workspace_str);
+        let channel = step.channel.as_deref().unwrap_or(&scenario.channel);
+        let mut msg = InboundMessage::new(
+            channel,
+            step.sender.as_deref().unwrap_or(&scenario.sender),
+            step.chat_id.as_deref().unwrap_or(&scenario.chat_id),
+            step.user.replace("{workspace}", &workspace_str),
+        );
+        msg.metadata = step.metadata.clone();
+        agent.handle_inbound(msg).await;
+
+        let mut outbound = Vec::new();
+        while bus.outbound_size() > 0 {
+            match bus.consume_outbound().await {
+                Some(out) => outbound.push(out),
+                None => break,
+            }
+        }
+        let requests = provider.requests();
+        let mut result = check_step(step, channel, &outbound, &requests, &workspace_str);
+        result.index = index;
+        let unused = provider.unused();
+        if unused > 0 {
+            result
+                .failures
+                .push(format!("{unused} scripted reply(ies) were never requested"));
+        }
+        steps.push(result);
+    }
+    Ok(ScenarioReport {
+        name: scenario.name.clone(),
+        steps,
+    })
+}
+
+fn check_step(
+    step: &Step,
+    channel: &str,
+    outbound: &[OutboundMessage],
+    requests: &[(Vec<Value>, Vec<String>)],
+    workspace: &str,
+) -> StepResult {
+    let expect = &step.expect;
+    let text = |value: &str| value.replace("{workspace}", workspace);
+    let mut failures = Vec::new();
+    // The reply is the last outbound message for the chat that asked.
+    let reply = outbound
+        .iter()
+        .rev()
+        .find(|out| out.channel == channel)
+        .map(|out| out.content.clone())
+        .unwrap_or_default();
+
+    // Only the current turn is sent to the model, so the last request holds
+    // every tool result of this step.
+    let tool_results = requests
+        .last()
+        .map(|(messages, _)| {
+            messages
+                .iter()
+                .filter(|m| m["role"] == "tool")
+                .map(|m| {
+                    (
+                        m["name"].as_str().unwrap_or_default().to_string(),
+                        m["content"].as_str().unwrap_or_default().to_string(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let tool_calls = tool_results
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+
+    if let Some(expected) = &expect.tool_calls
+        && expected != &tool_calls
+    {
+        failures.push(format!(
+            "expected tool calls [{}], got [{}]",
+            expected.join(", "),
+            tool_calls.join(", ")
+        ));
+    }
+    for needle in &expect.tool_results_contain {
+        let needle = text(needle);
+        if !tool_results.iter().any(|(_, out)| out.contains(&needle)) {
+            failures.push(format!("no tool result contains \"{needle}\""));
+        }
+    }
+    let offered = requests
+        .first()
+        .map(|(_, tools)| tools.clone())
+        .unwrap_or_default();
+    for name in &expect.tools_offered {
+        if !offered.contains(name) {
+            failures.push(format!("tool {name} was not offered to the model"));
+        }
+    }
+    for name in &expect.tools_not_offered {
+        if offered.contains(name) {
+            failures.push(format!("tool {name} was offered to the model"));
+        }
+    }
+    let system_prompt = requests
+        .first()
+        .and_then(|(messages, _)| messages.first())
+        .filter(|m| m["role"] == "system")
+        .and_then(|m| m["content"].as_str())
+        .unwrap_or_default();
+    for needle in &expect.system_prompt_contains {
+        if !system_prompt.contains(&text(needle)) {
+            failures.push(format!("system prompt does not contain \"{needle}\""));
+        }
+    }
+
+    if let Some(expected) = &expect.reply
+        && reply.trim() != text(expected).trim()
+    {
+        failures.push(format!("expected reply \"{expected}\", got \"{reply}\""));
+    }
+    for needle in &expect.reply_contains {
+        if !reply.contains(&text(needle)) {
+            failures.push(format!("reply does not contain \"{needle}\""));
+        }
+    }
+    for needle in &expect.reply_not_contains {
+        if reply.contains(&text(needle)) {
+            failures.push(format!("reply contains \"{needle}\""));
+        }
+    }
+    if let Some(pattern) = &expect.reply_matches {
+        match Regex::new(pattern) {
+            Ok(re) if !re.is_match(&reply) => {
+                failures.push(format!("reply does not match /{pattern}/"));
+            }
+            Ok(_) => {}
+            Err(err) => failures.push(format!("invalid reply_matches pattern: {err}")),
+        }
+    }
+    if let Some(expected) = expect.outbound
+        && outbound.len() != expected
+    {
+        failures.push(format!(
+            "expected {expected} outbound message(s), got {}",
+            outbound.len()
+        ));
+    }
+    let capabilities = channel_capabilities(channel);
+    if let Some(expected) = expect.parts {
+        let parts = split_reply(&reply, capabilities.max_message_chars).len();
+        if parts != expected {
+            failures.push(format!(
+                "expected {expected} part(s) on {channel}, got {parts}"
+            ));
+        }
+    }
+    if !expect.formatted_contains.is_empty() {
+        let formatted = format_message(capabilities, &reply).text;
+        for needle in &expect.formatted_contains {
+            if !formatted.contains(&text(needle)) {
+                failures.push(format!("{channel} rendering does not contain \"{needle}\""));
+            }
+        }
+    }
+
+    StepResult {
+        index: 0,
+        user: step.user.clone(),
+        reply,
+        tool_calls,
+        failures,
+    }
+}
+
+pub fn scenario_paths(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut paths = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            matches!(
+                path.extension().and_then(|ext| ext.to_str()),
+                Some("yaml" | "yml")
+            )
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCENARIO: &str = r#"
+name: notes
+channel: telegram
+chat_id: "42"
+files:
+  notes.md: "buy milk\n"
+steps:
+  - user: What is in my notes?
+    replies:
+      - tool_calls:
+          - name: read_file
+            arguments: { path: "{workspace}/notes.md" }
+      - content: "Your notes say: **buy milk**"
+    expect:
+      tool_calls: [read_file]
+      tool_results_contain: [buy milk]
+      tools_offered: [write_file]
+      reply_contains: [buy milk]
+      outbound: 1
+      parts: 1
+      formatted_contains: ["<b>buy milk</b>"]
+  - user: Thanks
+    channel: qq
+    replies:
+      - content: Anytime
+    expect:
+      tool_calls: []
+      tools_not_offered: [write_file]
+      reply: Nope
+"#;
+
+    #[tokio::test]
+    async fn runs_scripted_turns_through_the_agent_loop() {
+        let mut scenario: Scenario = serde_yaml::from_str(SCENARIO).unwrap();
+        scenario.read_only_channels = vec!["qq".to_string()];
+        let report = run_scenario(&scenario).await.unwrap();
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(report.steps[0].failures, Vec::<String>::new());
+        assert_eq!(report.steps[0].tool_calls, vec!["read_file".to_string()]);
+        assert_eq!(
+            report.steps[1].failures,
+            vec!["expected reply \"Nope\", got \"Anytime\"".to_string()]
+        );
+        assert!(!report.passed());
+        assert!(report.render(false).contains("[FAIL] step 2"));
+    }
+
+    #[tokio::test]
+    async fn only_owners_can_override_the_model_per_turn() {
+        let scenario: Scenario = serde_yaml::from_str(
+            r#"
+name: overrides
+channel: telegram
+chat_id: "42"
+steps:
+  - user: Use the big model
+    sender: stranger
+    metadata: { model: no-such-model, sampling: { temperature: 9 } }
+    replies:
+      - content: Answered on the session model
+    expect:
+      reply: Answered on the session model
+  - user: Use the big model
+    sender: user
+    metadata: { model: no-such-model }
+    expect:
+      reply_contains: [no-such-model]
+"#,
+        )
+        .unwrap();
+        let report = run_scenario(&scenario).await.unwrap();
+        assert!(report.passed(), "{}", report.render(true));
+    }
+}
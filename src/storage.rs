@@ -0,0 +1,459 @@
+use crate::config::StorageConfig;
+use crate::utils::get_data_path;
+use anyhow::{Result, anyhow};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+
+// Where sessions, cron jobs, tasks, batches, held deliveries, cursors,
+// pairing state and the event/abuse logs live. Keys are `/`-separated paths relative to the data
+// directory, such as "sessions/telegram_42.jsonl", so the filesystem backend
+// keeps the layout of ~/.nanobot unchanged.
+pub trait Storage: Send + Sync {
+    fn backend(&self) -> &'static str;
+    fn read(&self, key: &str) -> Result<Option<String>>;
+    fn write(&self, key: &str, body: &str) -> Result<()>;
+    fn append(&self, key: &str, text: &str) -> Result<()>;
+    fn remove(&self, key: &str) -> Result<bool>;
+    // Names of the entries directly under `prefix`.
+    fn list(&self, prefix: &str) -> Result<Vec<String>>;
+    // Every key, for copying one backend into another.
+    fn keys(&self) -> Result<Vec<String>>;
+}
+
+// Top-level entries that belong to a backend; the rest of the data directory
+// (config, workspace, logs) always stays on disk.
+pub const STORE_PREFIXES: &[&str] = &[
+    "sessions", "cron", "tasks", "batches", "outbox", "mochat", "pairing", "events", "abuse",
+];
+
+static STORAGE: OnceLock<Arc<dyn Storage>> = OnceLock::new();
+
+pub struct FileStorage {
+    root: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        key.split('/')
+            .filter(|part| !part.is_empty())
+            .fold(self.root.clone(), |path, part| path.join(part))
+    }
+}
+
+impl Storage for FileStorage {
+    fn backend(&self) -> &'static str {
+        "filesystem"
+    }
+
+    fn read(&self, key: &str) -> Result<Option<String>> {
+        match std::fs::read_to_string(self.path(key)) {
+            Ok(body) => Ok(Some(body)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn write(&self, key: &str, body: &str) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // Written aside and renamed into place, so another process reading
+        // the entry (a second gateway in cluster mode) never sees half of it.
+        // Each write stages its own file: concurrent saves of one key must
+        // not rename each other's half-written data.
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let staging = path.with_file_name(format!(
+            ".{name}.{}.{}.tmp",
+            std::process::id(),
+            uuid::Uuid::new_v4().simple()
+        ));
+        std::fs::write(&staging, body)?;
+        std::fs::rename(&staging, &path)?;
+        Ok(())
+    }
+
+    fn append(&self, key: &str, text: &str) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        file.write_all(text.as_bytes())?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<bool> {
+        match std::fs::remove_file(self.path(key)) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let Ok(entries) = std::fs::read_dir(self.path(prefix)) else {
+            return Ok(Vec::new());
+        };
+        let mut names = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().map(|t| t.is_file()).unwrap_or(false))
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    }
+
+    fn keys(&self) -> Result<Vec<String>> {
+        fn walk(dir: &Path, prefix: &str, keys: &mut Vec<String>) {
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                return;
+            };
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let key = if prefix.is_empty() {
+                    name
+                } else {
+                    format!("{prefix}/{name}")
+                };
+                match entry.file_type() {
+                    Ok(t) if t.is_dir() => walk(&entry.path(), &key, keys),
+                    Ok(t) if t.is_file() => keys.push(key),
+                    _ => {}
+                }
+            }
+        }
+        let mut keys = Vec::new();
+        walk(&self.root, "", &mut keys);
+        keys.sort();
+        Ok(keys)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+pub struct SqliteStorage {
+    conn: std::sync::Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = rusqlite::Connection::open(path)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+             CREATE TABLE IF NOT EXISTS entries (
+                 key TEXT PRIMARY KEY,
+                 body TEXT NOT NULL,
+                 updated_at INTEGER NOT NULL
+             );",
+        )?;
+        Ok(Self {
+            conn: std::sync::Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, rusqlite::Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+    fn backend(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn read(&self, key: &str) -> Result<Option<String>> {
+        use rusqlite::OptionalExtension;
+        Ok(self
+            .conn()
+            .query_row("SELECT body FROM entries WHERE key = ?1", [key], |row| {
+                row.get(0)
+            })
+            .optional()?)
+    }
+
+    fn write(&self, key: &str, body: &str) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO entries (key, body, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(key) DO UPDATE SET body = excluded.body, updated_at = excluded.updated_at",
+            rusqlite::params![key, body, chrono::Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    }
+
+    fn append(&self, key: &str, text: &str) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO entries (key, body, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(key) DO UPDATE SET body = body || excluded.body, updated_at = excluded.updated_at",
+            rusqlite::params![key, text, chrono::Utc::now().timestamp_millis()],
+        )?;
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> Result<bool> {
+        Ok(self
+            .conn()
+            .execute("DELETE FROM entries WHERE key = ?1", [key])?
+            > 0)
+    }
+
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let prefix = format!("{}/", prefix.trim_end_matches('/'));
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT key FROM entries WHERE substr(key, 1, length(?1)) = ?1 ORDER BY key",
+        )?;
+        let keys = stmt
+            .query_map([&prefix], |row| row.get::<_, String>(0))?
+            .filter_map(|key| key.ok())
+            .filter_map(|key| {
+                let name = key[prefix.len()..].to_string();
+                (!name.contains('/')).then_some(name)
+            })
+            .collect();
+        Ok(keys)
+    }
+
+    fn keys(&self) -> Result<Vec<String>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT key FROM entries ORDER BY key")?;
+        let keys = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter_map(|key| key.ok())
+            .collect();
+        Ok(keys)
+    }
+}
+
+pub fn open(config: &StorageConfig) -> Result<Arc<dyn Storage>> {
+    match config.backend.as_str() {
+        "" | "filesystem" => Ok(Arc::new(FileStorage::new(get_data_path()?))),
+        "sqlite" => open_sqlite(config),
+        other => Err(anyhow!(
+            "unknown storage backend '{other}': use filesystem or sqlite"
+        )),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn open_sqlite(config: &StorageConfig) -> Result<Arc<dyn Storage>> {
+    let path = if config.path.trim().is_empty() {
+        get_data_path()?.join("storage.sqlite")
+    } else {
+        crate::utils::expand_tilde(&config.path)
+    };
+    Ok(Arc::new(SqliteStorage::open(&path)?))
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn open_sqlite(_config: &StorageConfig) -> Result<Arc<dyn Storage>> {
+    Err(anyhow!(
+        "storage backend 'sqlite' needs a build with `--features sqlite`"
+    ))
+}
+
+// Selects the backend for this process. Must run before any store is used;
+// until then everything goes to the filesystem.
+pub fn init(config: &StorageConfig) -> Result<()> {
+    let storage = open(config)?;
+    let _ = STORAGE.set(storage);
+    Ok(())
+}
+
+pub fn current() -> Result<Arc<dyn Storage>> {
+    if let Some(storage) = STORAGE.get() {
+        return Ok(storage.clone());
+    }
+    Ok(Arc::new(FileStorage::new(get_data_path()?)))
+}
+
+fn is_store_key(key: &str) -> bool {
+    let top = key.split('/').next().unwrap_or_default();
+    STORE_PREFIXES.contains(&top)
+}
+
+// Copies every store entry into `target`, returning how many were copied.
+pub fn copy_all(source: &dyn Storage, target: &dyn Storage) -> Result<usize> {
+    let keys = source
+        .keys()?
+        .into_iter()
+        .filter(|key| is_store_key(key))
+        .collect::<Vec<_>>();
+    for key in &keys {
+        if let Some(body) = source.read(key)? {
+            target.write(key, &body)?;
+        }
+    }
+    Ok(keys.len())
+}
+
+// A single entry in a storage backend. Stores keep taking plain paths; those
+// under the data directory are routed to the configured backend, anything
+// else (tests, scratch runs) stays on the filesystem.
+#[derive(Clone)]
+pub struct Location {
+    storage: Arc<dyn Storage>,
+    key: String,
+}
+
+impl Location {
+    pub fn read(&self) -> Result<Option<String>> {
+        self.storage.read(&self.key)
+    }
+
+    pub fn write(&self, body: &str) -> Result<()> {
+        self.storage.write(&self.key, body)
+    }
+
+    pub fn append(&self, text: &str) -> Result<()> {
+        self.storage.append(&self.key, text)
+    }
+
+    pub fn remove(&self) -> Result<bool> {
+        self.storage.remove(&self.key)
+    }
+
+    pub fn child(&self, name: &str) -> Location {
+        Location {
+            storage: self.storage.clone(),
+            key: if self.key.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{name}", self.key)
+            },
+        }
+    }
+
+    pub fn children(&self) -> Result<Vec<String>> {
+        self.storage.list(&self.key)
+    }
+}
+
+pub fn locate(path: &Path) -> Location {
+    if let Ok(data_dir) = get_data_path()
+        && let Ok(relative) = path.strip_prefix(&data_dir)
+        && let Ok(storage) = current()
+    {
+        let key = relative
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<_>>()
+            .join("/");
+        return Location { storage, key };
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => Location {
+            storage: Arc::new(FileStorage::new(parent.to_path_buf())),
+            key: name.to_string_lossy().to_string(),
+        },
+        _ => Location {
+            storage: Arc::new(FileStorage::new(path.to_path_buf())),
+            key: String::new(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exercise(storage: &dyn Storage) {
+        assert_eq!(storage.read("cron/jobs.json").unwrap(), None);
+        storage.write("cron/jobs.json", "{}").unwrap();
+        storage.append("events/events.jsonl", "a\n").unwrap();
+        storage.append("events/events.jsonl", "b\n").unwrap();
+        storage.write("sessions/cli_direct.jsonl", "x").unwrap();
+        storage.write("sessions/nested/deep.jsonl", "y").unwrap();
+        assert_eq!(
+            storage.read("events/events.jsonl").unwrap().as_deref(),
+            Some("a\nb\n")
+        );
+        assert_eq!(
+            storage.list("sessions").unwrap(),
+            vec!["cli_direct.jsonl".to_string()]
+        );
+        storage.write("config.json", "{}").unwrap();
+        assert_eq!(storage.keys().unwrap().len(), 5);
+        assert!(storage.remove("cron/jobs.json").unwrap());
+        assert!(!storage.remove("cron/jobs.json").unwrap());
+    }
+
+    #[test]
+    fn backends_share_key_semantics() {
+        let root = std::env::temp_dir().join(format!("nanobot-storage-{}", uuid::Uuid::new_v4()));
+        let files = FileStorage::new(root.join("files"));
+        exercise(&files);
+        assert!(root.join("files/events/events.jsonl").exists());
+
+        let location = locate(&root.join("files").join("sessions"));
+        assert_eq!(
+            location.children().unwrap(),
+            vec!["cli_direct.jsonl".to_string()]
+        );
+        assert_eq!(
+            location
+                .child("cli_direct.jsonl")
+                .read()
+                .unwrap()
+                .as_deref(),
+            Some("x")
+        );
+
+        #[cfg(feature = "sqlite")]
+        {
+            let db = SqliteStorage::open(&root.join("storage.sqlite")).unwrap();
+            exercise(&db);
+            let copied = FileStorage::new(root.join("copy"));
+            assert_eq!(copy_all(&db, &copied).unwrap(), 3);
+            assert_eq!(
+                copied
+                    .read("sessions/nested/deep.jsonl")
+                    .unwrap()
+                    .as_deref(),
+                Some("y")
+            );
+        }
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn concurrent_writes_of_one_key_each_land_whole() {
+        let root = std::env::temp_dir().join(format!("nanobot-storage-{}", uuid::Uuid::new_v4()));
+        let storage = Arc::new(FileStorage::new(root.clone()));
+        let bodies = (0..8)
+            .map(|i| i.to_string().repeat(4096))
+            .collect::<Vec<_>>();
+        let handles = bodies
+            .iter()
+            .cloned()
+            .map(|body| {
+                let storage = storage.clone();
+                std::thread::spawn(move || {
+                    (0..20).try_for_each(|_| storage.write("tasks/tasks.json", &body))
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().expect("writer").expect("write");
+        }
+        let stored = storage.read("tasks/tasks.json").unwrap().expect("entry");
+        assert!(bodies.contains(&stored));
+        assert_eq!(
+            storage.list("tasks").unwrap(),
+            vec!["tasks.json".to_string()]
+        );
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,436 @@
+use crate::agent::context::{ContextProvider, ContextRequest};
+use crate::storage::locate;
+use crate::utils::get_data_path;
+use anyhow::Result;
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+// Tools, action-item extraction and the heartbeat digest all rewrite the
+// task list from different threads of this process. Only they are
+// serialized: another gateway sharing the storage backend can still
+// interleave, and its save wins.
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskItem {
+    pub id: String,
+    pub title: String,
+    pub status: String, // open | done
+    pub due_at_ms: Option<i64>,
+    pub session_key: Option<String>,
+    pub source: String, // manual | extracted | command
+    #[serde(default)]
+    pub reminded_at_ms: Option<i64>,
+    pub created_at_ms: i64,
+    pub updated_at_ms: i64,
+}
+
+impl TaskItem {
+    pub fn in_scope(&self, scope: Option<&str>) -> bool {
+        scope.is_none_or(|session| self.session_key.as_deref() == Some(session))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskFile {
+    version: u32,
+    tasks: Vec<TaskItem>,
+}
+
+impl Default for TaskFile {
+    fn default() -> Self {
+        Self {
+            version: 1,
+            tasks: Vec::new(),
+        }
+    }
+}
+
+fn now_ms() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+fn normalize_title(title: &str) -> String {
+    title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub fn parse_due_ms(raw: &str) -> Option<i64> {
+    let raw = raw.trim();
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.timestamp_millis());
+    }
+    let parse_local = |naive: NaiveDateTime| -> Option<i64> {
+        let local = Local.from_local_datetime(&naive).earliest()?;
+        Some(local.timestamp_millis())
+    };
+    for fmt in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(raw, fmt) {
+            return parse_local(naive);
+        }
+    }
+    // Date-only due dates surface on the morning of the day.
+    let date = NaiveDate::parse_from_str(raw, "%Y-%m-%d").ok()?;
+    parse_local(date.and_hms_opt(9, 0, 0)?)
+}
+
+pub fn format_due(due_at_ms: Option<i64>) -> String {
+    due_at_ms
+        .and_then(|ms| Local.timestamp_millis_opt(ms).single())
+        .map(|dt| dt.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "no due date".to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskStore {
+    path: PathBuf,
+}
+
+impl TaskStore {
+    pub fn new() -> Result<Self> {
+        Ok(Self::from_path(
+            get_data_path()?.join("tasks").join("tasks.json"),
+        ))
+    }
+
+    pub fn from_path(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn load(&self) -> Result<TaskFile> {
+        Ok(match locate(&self.path).read()? {
+            Some(raw) => serde_json::from_str(&raw).unwrap_or_default(),
+            None => TaskFile::default(),
+        })
+    }
+
+    fn save(&self, file: &TaskFile) -> Result<()> {
+        locate(&self.path).write(&serde_json::to_string_pretty(file)?)
+    }
+
+    pub fn list(&self, include_done: bool) -> Result<Vec<TaskItem>> {
+        let mut tasks = self
+            .load()?
+            .tasks
+            .into_iter()
+            .filter(|task| include_done || task.status == "open")
+            .collect::<Vec<_>>();
+        tasks.sort_by_key(|task| task.due_at_ms.unwrap_or(i64::MAX));
+        Ok(tasks)
+    }
+
+    pub fn add(
+        &self,
+        title: &str,
+        due_at_ms: Option<i64>,
+        session_key: Option<String>,
+        source: &str,
+    ) -> Result<TaskItem> {
+        let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.load()?;
+        let normalized = normalize_title(title);
+        if let Some(existing) = file.tasks.iter_mut().find(|task| {
+            task.status == "open"
+                && task.session_key == session_key
+                && normalize_title(&task.title) == normalized
+        }) {
+            if due_at_ms.is_none() || existing.due_at_ms == due_at_ms {
+                return Ok(existing.clone());
+            }
+            existing.due_at_ms = due_at_ms;
+            existing.reminded_at_ms = None;
+            existing.updated_at_ms = now_ms();
+            let out = existing.clone();
+            self.save(&file)?;
+            return Ok(out);
+        }
+
+        let now = now_ms();
+        let task = TaskItem {
+            id: Uuid::new_v4().simple().to_string()[..8].to_string(),
+            title: title.trim().to_string(),
+            status: "open".to_string(),
+            due_at_ms,
+            session_key,
+            source: source.to_string(),
+            reminded_at_ms: None,
+            created_at_ms: now,
+            updated_at_ms: now,
+        };
+        file.tasks.push(task.clone());
+        self.save(&file)?;
+        Ok(task)
+    }
+
+    // `scope` limits the lookup to one session's tasks; None reaches them all.
+    pub fn complete(&self, task_id: &str, scope: Option<&str>) -> Result<Option<TaskItem>> {
+        let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.load()?;
+        let Some(task) = file
+            .tasks
+            .iter_mut()
+            .find(|task| task.id == task_id && task.in_scope(scope))
+        else {
+            return Ok(None);
+        };
+        task.status = "done".to_string();
+        task.updated_at_ms = now_ms();
+        let out = task.clone();
+        self.save(&file)?;
+        Ok(Some(out))
+    }
+
+    pub fn remove(&self, task_id: &str, scope: Option<&str>) -> Result<bool> {
+        let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.load()?;
+        let before = file.tasks.len();
+        file.tasks
+            .retain(|task| task.id != task_id || !task.in_scope(scope));
+        let removed = file.tasks.len() < before;
+        if removed {
+            self.save(&file)?;
+        }
+        Ok(removed)
+    }
+
+    // Each task is included at most once per horizon so heartbeats don't nag.
+    pub fn due_digest(&self, now_ms: i64, horizon_ms: i64) -> Result<Option<String>> {
+        let _guard = STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = self.load()?;
+        let mut due = Vec::new();
+        for task in file.tasks.iter_mut() {
+            let is_due = task.status == "open"
+                && task
+                    .due_at_ms
+                    .is_some_and(|due_at| due_at <= now_ms + horizon_ms);
+            let recently_reminded = task
+                .reminded_at_ms
+                .is_some_and(|reminded_at| now_ms - reminded_at < horizon_ms);
+            if is_due && !recently_reminded {
+                task.reminded_at_ms = Some(now_ms);
+                due.push(task.clone());
+            }
+        }
+        if due.is_empty() {
+            return Ok(None);
+        }
+        self.save(&file)?;
+        due.sort_by_key(|task| task.due_at_ms.unwrap_or(i64::MAX));
+
+        let lines = due
+            .iter()
+            .map(|task| {
+                let overdue = if task.due_at_ms.unwrap_or(i64::MAX) < now_ms {
+                    " (overdue)"
+                } else {
+                    ""
+                };
+                format!(
+                    "- [{id}] {title} — due {due}{overdue}, from {session}",
+                    id = task.id,
+                    title = task.title,
+                    due = format_due(task.due_at_ms),
+                    session = task.session_key.as_deref().unwrap_or("unknown session"),
+                )
+            })
+            .collect::<Vec<_>>();
+        Ok(Some(format!(
+            "Open action items due soon:\n{}\nRemind the user about these via the message tool on the listed session (channel:chat_id), and mark finished ones done with the tasks tool.",
+            lines.join("\n")
+        )))
+    }
+}
+
+// Open tasks as a context section, so the agent knows what is outstanding
+// without having to call the tasks tool first.
+impl ContextProvider for TaskStore {
+    fn name(&self) -> &str {
+        "tasks"
+    }
+
+    fn title(&self) -> &str {
+        "Open Tasks"
+    }
+
+    // Only the current chat's tasks, and none on read-only turns: guests and
+    // other groups must not see what the owner has on their list.
+    fn render(&self, request: &ContextRequest<'_>) -> Option<String> {
+        if request.read_only {
+            return None;
+        }
+        let session = format!("{}:{}", request.channel?, request.chat_id?);
+        let tasks = self.list(false).ok()?;
+        let lines = tasks
+            .iter()
+            .filter(|task| task.session_key.as_deref() == Some(session.as_str()))
+            .map(|task| {
+                format!(
+                    "- [{}] {} — due {}",
+                    task.id,
+                    task.title,
+                    format_due(task.due_at_ms)
+                )
+            })
+            .collect::<Vec<_>>();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store() -> TaskStore {
+        TaskStore::from_path(
+            std::env::temp_dir().join(format!("nanobot-rs-tasks-{}.json", Uuid::new_v4())),
+        )
+    }
+
+    #[test]
+    fn add_dedupes_open_tasks_per_session() -> Result<()> {
+        let store = temp_store();
+        let first = store.add(
+            "Send the doc",
+            None,
+            Some("telegram:1".to_string()),
+            "extracted",
+        )?;
+        let second = store.add(
+            "send  the DOC",
+            Some(1_000),
+            Some("telegram:1".to_string()),
+            "extracted",
+        )?;
+        assert_eq!(first.id, second.id);
+        assert_eq!(second.due_at_ms, Some(1_000));
+
+        // A duplicate that changes nothing leaves the stored list untouched.
+        let compact = serde_json::to_string(&store.load()?)?;
+        std::fs::write(&store.path, &compact)?;
+        store.add(
+            "Send the doc",
+            Some(1_000),
+            Some("telegram:1".to_string()),
+            "manual",
+        )?;
+        assert_eq!(std::fs::read_to_string(&store.path)?, compact);
+
+        store.add(
+            "Send the doc",
+            None,
+            Some("telegram:2".to_string()),
+            "manual",
+        )?;
+        assert_eq!(store.list(false)?.len(), 2);
+
+        assert!(store.complete(&first.id, Some("telegram:2"))?.is_none());
+        assert!(store.complete(&first.id, Some("telegram:1"))?.is_some());
+        assert_eq!(store.list(false)?.len(), 1);
+        assert_eq!(store.list(true)?.len(), 2);
+        assert!(!store.remove(&first.id, Some("telegram:2"))?);
+        assert!(store.remove(&first.id, None)?);
+        let _ = std::fs::remove_file(&store.path);
+        Ok(())
+    }
+
+    #[test]
+    fn due_digest_only_lists_tasks_inside_horizon() -> Result<()> {
+        let store = temp_store();
+        let now = now_ms();
+        store.add("Pay rent", Some(now - 1_000), None, "manual")?;
+        store.add(
+            "Renew passport",
+            Some(now + 30 * 86_400_000),
+            None,
+            "manual",
+        )?;
+        store.add("Someday", None, None, "manual")?;
+
+        let digest = store.due_digest(now, 86_400_000)?.expect("digest");
+        assert!(digest.contains("Pay rent"));
+        assert!(digest.contains("(overdue)"));
+        assert!(!digest.contains("Renew passport"));
+        assert!(!digest.contains("Someday"));
+        assert!(store.due_digest(now + 1_000, 86_400_000)?.is_none());
+        let _ = std::fs::remove_file(&store.path);
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_adds_keep_every_task() -> Result<()> {
+        let store = temp_store();
+        let handles = (0..8)
+            .map(|i| {
+                let store = store.clone();
+                std::thread::spawn(move || store.add(&format!("task {i}"), None, None, "manual"))
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().expect("thread")?;
+        }
+        assert_eq!(store.list(false)?.len(), 8);
+        let _ = std::fs::remove_file(&store.path);
+        Ok(())
+    }
+
+    #[test]
+    fn tasks_added_on_one_instance_reach_another_digest() -> Result<()> {
+        let follower = temp_store();
+        let leader = TaskStore::from_path(follower.path.clone());
+        let now = now_ms();
+        follower.add(
+            "Call the plumber",
+            Some(now),
+            Some("telegram:1".to_string()),
+            "extracted",
+        )?;
+        let digest = leader.due_digest(now, 86_400_000)?.expect("digest");
+        assert!(digest.contains("Call the plumber"));
+        let _ = std::fs::remove_file(&follower.path);
+        Ok(())
+    }
+
+    #[test]
+    fn context_section_only_shows_the_current_chat_tasks() -> Result<()> {
+        let store = temp_store();
+        store.add(
+            "Book flights",
+            None,
+            Some("telegram:1".to_string()),
+            "manual",
+        )?;
+        store.add("Team offsite", None, Some("slack:C9".to_string()), "manual")?;
+        let request = |channel, chat_id, read_only| ContextRequest {
+            channel: Some(channel),
+            chat_id: Some(chat_id),
+            message: "hi",
+            read_only,
+        };
+
+        let own = store
+            .render(&request("telegram", "1", false))
+            .expect("own tasks");
+        assert!(own.contains("Book flights"));
+        assert!(!own.contains("Team offsite"));
+        assert!(store.render(&request("telegram", "2", false)).is_none());
+        assert!(store.render(&request("telegram", "1", true)).is_none());
+        let _ = std::fs::remove_file(&store.path);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_due_accepts_dates_and_datetimes() {
+        assert!(parse_due_ms("2026-03-06").is_some());
+        assert!(parse_due_ms("2026-03-06 17:00").is_some());
+        assert!(parse_due_ms("2026-03-06T17:00:00+08:00").is_some());
+        assert!(parse_due_ms("friday").is_none());
+    }
+}
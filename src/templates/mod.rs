@@ -0,0 +1,177 @@
+use crate::config::Config;
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+
+// Tool settings a preset writes into config.json at onboarding.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToolPolicy {
+    pub restrict_to_workspace: bool,
+    pub exec_timeout: u64,
+    pub max_tool_iterations: u32,
+    pub temperature: f32,
+}
+
+impl ToolPolicy {
+    pub fn apply(&self, config: &mut Config) {
+        config.tools.restrict_to_workspace = self.restrict_to_workspace;
+        config.tools.exec.timeout = self.exec_timeout;
+        config.agents.defaults.max_tool_iterations = self.max_tool_iterations;
+        config.agents.defaults.temperature = self.temperature;
+    }
+
+    pub fn describe(&self) -> String {
+        format!(
+            "restrictToWorkspace={}, exec.timeout={}s, maxToolIterations={}, temperature={}",
+            self.restrict_to_workspace,
+            self.exec_timeout,
+            self.max_tool_iterations,
+            self.temperature
+        )
+    }
+}
+
+// A persona preset: workspace files (paths relative to the workspace) plus
+// the tool policy that suits it.
+pub struct WorkspaceTemplate {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub files: &'static [(&'static str, &'static str)],
+    pub policy: ToolPolicy,
+}
+
+pub const TEMPLATES: &[WorkspaceTemplate] = &[
+    WorkspaceTemplate {
+        name: "coder",
+        description: "Pair programmer: reads before editing, runs builds and tests, reviews diffs",
+        files: &[
+            ("AGENTS.md", include_str!("coder/AGENTS.md")),
+            ("SOUL.md", include_str!("coder/SOUL.md")),
+            (
+                "skills/code-review/SKILL.md",
+                include_str!("coder/skills/code-review/SKILL.md"),
+            ),
+        ],
+        policy: ToolPolicy {
+            restrict_to_workspace: true,
+            exec_timeout: 300,
+            max_tool_iterations: 40,
+            temperature: 0.2,
+        },
+    },
+    WorkspaceTemplate {
+        name: "assistant",
+        description: "Personal assistant: reminders, daily briefings, remembers preferences",
+        files: &[
+            ("AGENTS.md", include_str!("assistant/AGENTS.md")),
+            ("SOUL.md", include_str!("assistant/SOUL.md")),
+            (
+                "skills/daily-briefing/SKILL.md",
+                include_str!("assistant/skills/daily-briefing/SKILL.md"),
+            ),
+        ],
+        policy: ToolPolicy {
+            restrict_to_workspace: true,
+            exec_timeout: 30,
+            max_tool_iterations: 20,
+            temperature: 0.7,
+        },
+    },
+    WorkspaceTemplate {
+        name: "ops",
+        description: "On-call helper: read-only triage first, confirms before changes, incident timelines",
+        files: &[
+            ("AGENTS.md", include_str!("ops/AGENTS.md")),
+            ("SOUL.md", include_str!("ops/SOUL.md")),
+            (
+                "skills/incident-response/SKILL.md",
+                include_str!("ops/skills/incident-response/SKILL.md"),
+            ),
+        ],
+        policy: ToolPolicy {
+            restrict_to_workspace: false,
+            exec_timeout: 120,
+            max_tool_iterations: 30,
+            temperature: 0.2,
+        },
+    },
+    WorkspaceTemplate {
+        name: "researcher",
+        description: "Research assistant: searches, cites sources, saves findings to files",
+        files: &[
+            ("AGENTS.md", include_str!("researcher/AGENTS.md")),
+            ("SOUL.md", include_str!("researcher/SOUL.md")),
+            (
+                "skills/source-check/SKILL.md",
+                include_str!("researcher/skills/source-check/SKILL.md"),
+            ),
+        ],
+        policy: ToolPolicy {
+            restrict_to_workspace: true,
+            exec_timeout: 60,
+            max_tool_iterations: 30,
+            temperature: 0.4,
+        },
+    },
+];
+
+pub fn find(name: &str) -> Result<&'static WorkspaceTemplate> {
+    let name = name.trim().to_lowercase();
+    TEMPLATES.iter().find(|t| t.name == name).ok_or_else(|| {
+        let names = TEMPLATES.iter().map(|t| t.name).collect::<Vec<_>>();
+        anyhow!(
+            "unknown template '{name}' (available: {})",
+            names.join(", ")
+        )
+    })
+}
+
+impl WorkspaceTemplate {
+    // Writes the preset's files, leaving any that already exist untouched.
+    // Returns (written, skipped) paths.
+    pub fn seed(&self, workspace: &Path) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+        let mut written = Vec::new();
+        let mut skipped = Vec::new();
+        for (relative, content) in self.files {
+            let path = workspace.join(relative);
+            if path.exists() {
+                skipped.push(path);
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, content)?;
+            written.push(path);
+        }
+        Ok((written, skipped))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeds_files_without_overwriting_and_applies_policy() {
+        let dir = std::env::temp_dir().join(format!("nanobot-tpl-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        std::fs::write(dir.join("SOUL.md"), "mine").expect("write soul");
+
+        let template = find("Ops").expect("ops preset");
+        let (written, skipped) = template.seed(&dir).expect("seed");
+        assert_eq!(written.len(), 2);
+        assert_eq!(skipped, vec![dir.join("SOUL.md")]);
+        assert!(dir.join("skills/incident-response/SKILL.md").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("SOUL.md")).expect("read"),
+            "mine"
+        );
+
+        let mut config = Config::default();
+        template.policy.apply(&mut config);
+        assert!(!config.tools.restrict_to_workspace);
+        assert_eq!(config.tools.exec.timeout, 120);
+        assert!(find("poet").is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -9,6 +9,11 @@ pub trait Tool: Send + Sync {
 
     async fn execute(&self, params: &Map<String, Value>) -> anyhow::Result<String>;
 
+    // Tools opt in to being offered on read-only (guest) channels.
+    fn read_only(&self) -> bool {
+        false
+    }
+
     fn validate_params(&self, params: &Map<String, Value>) -> Vec<String> {
         let schema = self.parameters();
         let schema_type = schema
@@ -307,11 +312,70 @@ mod tests {
 
     #[tokio::test]
     async fn registry_returns_validation_error() {
-        let mut registry = ToolRegistry::new();
+        let registry = ToolRegistry::new();
         registry.register(std::sync::Arc::new(SampleTool));
         let result = registry
             .execute("sample", json!({ "query": "hi" }).as_object().unwrap())
             .await;
         assert!(result.contains("Invalid parameters"));
     }
+
+    #[tokio::test]
+    async fn registry_read_only_definitions_skip_mutating_tools() {
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let registry = ToolRegistry::new();
+        registry.register(std::sync::Arc::new(SampleTool));
+        registry.register(std::sync::Arc::new(
+            crate::tools::message::MessageTool::new(tx),
+        ));
+
+        let names = registry.read_only_tool_names();
+        assert_eq!(names, vec!["message".to_string()]);
+        assert_eq!(registry.get_read_only_definitions().len(), 1);
+        assert!(!registry.is_read_only("sample"));
+    }
+
+    #[tokio::test]
+    async fn registry_exports_function_schemas_sorted_by_name() {
+        let (tx, _rx) = tokio::sync::mpsc::channel(1);
+        let registry = ToolRegistry::new();
+        registry.register(std::sync::Arc::new(SampleTool));
+        registry.register(std::sync::Arc::new(
+            crate::tools::message::MessageTool::new(tx),
+        ));
+
+        let exported = registry.export_definitions();
+        let names = exported
+            .iter()
+            .filter_map(|schema| schema["function"]["name"].as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["message", "sample"]);
+        assert_eq!(exported[1], SampleTool.to_schema());
+        assert_eq!(exported[1]["type"], "function");
+        assert_eq!(exported[1]["function"]["description"], "sample tool");
+        assert_eq!(
+            exported[1]["function"]["parameters"]["required"],
+            json!(["query", "count"])
+        );
+    }
+
+    #[tokio::test]
+    async fn registry_changes_are_visible_through_shared_handles() {
+        let registry = std::sync::Arc::new(ToolRegistry::new());
+        let handle = registry.clone();
+        tokio::spawn(async move {
+            handle.register(std::sync::Arc::new(SampleTool));
+        })
+        .await
+        .expect("register task");
+        assert_eq!(registry.tool_names(), vec!["sample".to_string()]);
+        assert_eq!(registry.export_definitions().len(), 1);
+
+        assert!(registry.unregister("sample").is_some());
+        assert!(registry.unregister("sample").is_none());
+        let result = registry
+            .execute("sample", json!({ "query": "hi" }).as_object().unwrap())
+            .await;
+        assert!(result.contains("not found"));
+    }
 }
@@ -0,0 +1,186 @@
+use crate::batch::{BatchRequest, BatchService};
+use crate::providers::batch::BatchItem;
+use crate::tools::base::Tool;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde_json::{Map, Value, json};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct BatchContext {
+    channel: String,
+    chat_id: String,
+}
+
+pub struct BatchTool {
+    service: Arc<BatchService>,
+    context: Mutex<BatchContext>,
+}
+
+impl BatchTool {
+    pub fn new(service: Arc<BatchService>) -> Self {
+        Self {
+            service,
+            context: Mutex::new(BatchContext::default()),
+        }
+    }
+
+    pub fn set_context(&self, channel: impl Into<String>, chat_id: impl Into<String>) {
+        if let Ok(mut guard) = self.context.lock() {
+            guard.channel = channel.into();
+            guard.chat_id = chat_id.into();
+        }
+    }
+
+    // Items are plain strings or {"id": ..., "text": ...} objects.
+    fn parse_items(values: &[Value]) -> Vec<BatchItem> {
+        values
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| {
+                let fallback_id = format!("item-{}", index + 1);
+                match value {
+                    Value::String(text) => Some(BatchItem {
+                        custom_id: fallback_id,
+                        prompt: text.clone(),
+                    }),
+                    Value::Object(obj) => Some(BatchItem {
+                        custom_id: obj
+                            .get("id")
+                            .and_then(Value::as_str)
+                            .map(ToOwned::to_owned)
+                            .unwrap_or(fallback_id),
+                        prompt: obj.get("text").and_then(Value::as_str)?.to_string(),
+                    }),
+                    _ => None,
+                }
+            })
+            .filter(|item| !item.prompt.trim().is_empty())
+            .collect()
+    }
+
+    async fn submit(&self, params: &Map<String, Value>) -> Result<String> {
+        let instruction = params
+            .get("instruction")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if instruction.trim().is_empty() {
+            return Ok("Error: instruction is required for submit".to_string());
+        }
+        let values = match (params.get("items"), params.get("items_file")) {
+            (Some(Value::Array(values)), _) => values.clone(),
+            (_, Some(Value::String(path))) => {
+                let raw = std::fs::read_to_string(path)
+                    .map_err(|err| anyhow!("failed to read {path}: {err}"))?;
+                match serde_json::from_str::<Value>(&raw)? {
+                    Value::Array(values) => values,
+                    _ => return Ok("Error: items_file must contain a JSON array".to_string()),
+                }
+            }
+            _ => return Ok("Error: items or items_file is required for submit".to_string()),
+        };
+        let items = Self::parse_items(&values);
+
+        let (channel, chat_id) = {
+            let guard = self
+                .context
+                .lock()
+                .map_err(|_| anyhow!("failed to lock batch context"))?;
+            (guard.channel.clone(), guard.chat_id.clone())
+        };
+        if channel.is_empty() || chat_id.is_empty() {
+            return Ok("Error: no session context (channel/chat_id)".to_string());
+        }
+
+        let label = params
+            .get("label")
+            .and_then(Value::as_str)
+            .unwrap_or("batch");
+        let max_tokens = params
+            .get("max_tokens")
+            .and_then(Value::as_u64)
+            .unwrap_or(1024) as u32;
+        match self
+            .service
+            .submit(
+                BatchRequest {
+                    model: params
+                        .get("model")
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    label: label.to_string(),
+                    instruction: instruction.to_string(),
+                    items,
+                    max_tokens,
+                },
+                &channel,
+                &chat_id,
+            )
+            .await
+        {
+            Ok(job) => Ok(format!(
+                "Submitted batch '{}' (id: {}, {} items, {}). Results usually arrive within a few hours; you will be notified in this chat.",
+                job.label, job.id, job.item_count, job.model
+            )),
+            Err(err) => Ok(format!("Error: {err}")),
+        }
+    }
+
+    fn status(&self) -> Result<String> {
+        let jobs = self.service.jobs()?;
+        if jobs.is_empty() {
+            return Ok("No batch jobs.".to_string());
+        }
+        Ok(jobs
+            .iter()
+            .rev()
+            .take(20)
+            .map(|job| job.describe())
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+#[async_trait]
+impl Tool for BatchTool {
+    fn name(&self) -> &str {
+        "batch"
+    }
+
+    fn description(&self) -> &str {
+        "Run one instruction over many items through the provider batch API (much cheaper, finishes within hours). Use for bulk, non-urgent work such as summarizing dozens of feed items. Actions: submit, status."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": { "type": "string", "enum": ["submit", "status"] },
+                "instruction": { "type": "string", "description": "System prompt applied to every item" },
+                "items": {
+                    "type": "array",
+                    "description": "Strings, or objects with id and text",
+                    "items": {}
+                },
+                "items_file": { "type": "string", "description": "Path to a JSON array of items, instead of items" },
+                "label": { "type": "string" },
+                "model": { "type": "string", "description": "Anthropic or OpenAI model; defaults to the agent model" },
+                "max_tokens": { "type": "integer" }
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, params: &Map<String, Value>) -> Result<String> {
+        let action = params
+            .get("action")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("missing required string field: action"))?;
+
+        match action {
+            "submit" => self.submit(params).await,
+            "status" => self.status(),
+            _ => Ok(format!("Unknown action: {action}")),
+        }
+    }
+}
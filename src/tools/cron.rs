@@ -1,8 +1,10 @@
-use crate::cron::{CronSchedule, CronService};
+use crate::cron::time::{ensure_future, format_local, parse_at};
+use crate::cron::{CronJob, CronSchedule, CronService, CronTemplate};
+use crate::message_templates::{MessageTemplates, TEMPLATE_KIND, template_vars};
 use crate::tools::base::Tool;
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
-use chrono::{Local, NaiveDateTime, TimeZone};
+use chrono::Utc;
 use serde_json::{Map, Value, json};
 use std::sync::{Arc, Mutex};
 
@@ -10,11 +12,14 @@ use std::sync::{Arc, Mutex};
 struct CronContext {
     channel: String,
     chat_id: String,
+    sender_id: String,
+    is_owner: bool,
 }
 
 pub struct CronTool {
     cron: Arc<CronService>,
     context: Mutex<CronContext>,
+    templates: Option<MessageTemplates>,
 }
 
 impl CronTool {
@@ -22,15 +27,50 @@ impl CronTool {
         Self {
             cron,
             context: Mutex::new(CronContext::default()),
+            templates: None,
         }
     }
 
+    // Lets jobs send workspace message templates by name.
+    pub fn with_templates(mut self, templates: MessageTemplates) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+
     pub fn set_context(&self, channel: impl Into<String>, chat_id: impl Into<String>) {
         if let Ok(mut guard) = self.context.lock() {
             guard.channel = channel.into();
             guard.chat_id = chat_id.into();
         }
     }
+
+    // Who is asking this turn. Owners see and change every job; other users
+    // only the jobs they created.
+    pub fn set_requester(&self, sender_id: impl Into<String>, is_owner: bool) {
+        if let Ok(mut guard) = self.context.lock() {
+            guard.sender_id = sender_id.into();
+            guard.is_owner = is_owner;
+        }
+    }
+
+    // (requester, channel, chat_id, is_owner)
+    fn requester(&self) -> Result<(String, String, String, bool)> {
+        let guard = self
+            .context
+            .lock()
+            .map_err(|_| anyhow!("failed to lock cron context"))?;
+        Ok((
+            format!("{}:{}", guard.channel, guard.sender_id),
+            guard.channel.clone(),
+            guard.chat_id.clone(),
+            guard.is_owner,
+        ))
+    }
+
+    fn may_manage(&self, job: &CronJob) -> Result<bool> {
+        let (requester, channel, chat_id, is_owner) = self.requester()?;
+        Ok(is_owner || job.owned_by(&requester, &channel, &chat_id))
+    }
 }
 
 #[async_trait]
@@ -52,6 +92,10 @@ impl Tool for CronTool {
                 "every_seconds": { "type": "integer" },
                 "cron_expr": { "type": "string" },
                 "at": { "type": "string" },
+                "title": { "type": "string", "description": "Email subject / card title for each delivery; supports {name}, {date}, {time}" },
+                "template": { "type": "string", "description": "Wraps each response, e.g. \"Weekly report\\n\\n{response}\"" },
+                "template_name": { "type": "string", "description": "Workspace template templates/<name>.md to send on each run; without a message it is sent as-is, with one it wraps the response" },
+                "vars": { "type": "object", "additionalProperties": { "type": "string" }, "description": "Values for the template's {placeholders}, e.g. {\"recipient\": \"Ana\"}" },
                 "job_id": { "type": "string" }
             },
             "required": ["action"]
@@ -75,19 +119,7 @@ impl Tool for CronTool {
 
 impl CronTool {
     fn parse_at_ms(raw: &str) -> Result<i64> {
-        if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(raw) {
-            return Ok(dt.timestamp_millis());
-        }
-
-        let parse_local = |fmt: &str| -> Option<i64> {
-            let naive = NaiveDateTime::parse_from_str(raw, fmt).ok()?;
-            let local = Local.from_local_datetime(&naive).single()?;
-            Some(local.timestamp_millis())
-        };
-
-        parse_local("%Y-%m-%dT%H:%M:%S")
-            .or_else(|| parse_local("%Y-%m-%d %H:%M:%S"))
-            .ok_or_else(|| anyhow!("invalid at datetime: expected ISO datetime string"))
+        parse_at(raw)
     }
 
     async fn add_job(&self, params: &Map<String, Value>) -> Result<String> {
@@ -96,17 +128,25 @@ impl CronTool {
             .and_then(Value::as_str)
             .unwrap_or_default()
             .to_string();
-        if message.is_empty() {
-            return Ok("Error: message is required for add".to_string());
+        let template_name = params
+            .get("template_name")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .filter(|name| !name.is_empty());
+        if message.is_empty() && template_name.is_none() {
+            return Ok("Error: message or template_name is required for add".to_string());
+        }
+        let vars = template_vars(params.get("vars"));
+        if let Some(name) = template_name {
+            let Some(templates) = &self.templates else {
+                return Ok("Error: message templates are not available here".to_string());
+            };
+            if let Err(err) = templates.check_for_job(name, &vars, chrono::Local::now()) {
+                return Ok(format!("Error: {err}"));
+            }
         }
 
-        let (channel, chat_id) = {
-            let guard = self
-                .context
-                .lock()
-                .map_err(|_| anyhow!("failed to lock cron context"))?;
-            (guard.channel.clone(), guard.chat_id.clone())
-        };
+        let (requester, channel, chat_id, _) = self.requester()?;
         if channel.is_empty() || chat_id.is_empty() {
             return Ok("Error: no session context (channel/chat_id)".to_string());
         }
@@ -129,6 +169,7 @@ impl CronTool {
             }
         } else if let Some(at_raw) = at {
             let at_ms = Self::parse_at_ms(at_raw)?;
+            ensure_future(at_ms, Utc::now().timestamp_millis())?;
             delete_after_run = true;
             CronSchedule {
                 kind: "at".to_string(),
@@ -139,10 +180,14 @@ impl CronTool {
             return Ok("Error: either every_seconds, cron_expr, or at is required".to_string());
         };
 
+        let name = match template_name {
+            Some(template) if message.is_empty() => template.to_string(),
+            _ => message.chars().take(30).collect::<String>(),
+        };
         let job = self
             .cron
             .add_job(
-                message.chars().take(30).collect::<String>(),
+                name,
                 schedule,
                 message,
                 true,
@@ -151,17 +196,64 @@ impl CronTool {
                 delete_after_run,
             )
             .await?;
+        let template = CronTemplate {
+            title: params
+                .get("title")
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned),
+            body: params
+                .get("template")
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned),
+        };
+        if !template.is_empty() {
+            self.cron.set_template(&job.id, Some(template)).await?;
+        }
+        if let Some(template) = template_name {
+            self.cron
+                .update_payload(&job.id, |payload| {
+                    if payload.message.is_empty() {
+                        payload.kind = TEMPLATE_KIND.to_string();
+                    }
+                    payload.message_template = Some(template.to_string());
+                    payload.template_vars = vars;
+                })
+                .await?;
+        }
+        self.cron.set_owner(&job.id, Some(requester)).await?;
         Ok(format!("Created job '{}' (id: {})", job.name, job.id))
     }
 
     async fn list_jobs(&self) -> Result<String> {
-        let jobs = self.cron.list_jobs(false).await;
+        let mut jobs = Vec::new();
+        for job in self.cron.list_jobs(false).await {
+            if self.may_manage(&job)? {
+                jobs.push(job);
+            }
+        }
         if jobs.is_empty() {
             return Ok("No scheduled jobs.".to_string());
         }
+        let now = Utc::now().timestamp_millis();
         let lines = jobs
             .iter()
-            .map(|j| format!("- {} (id: {}, {})", j.name, j.id, j.schedule.kind))
+            .map(|j| {
+                let next = j
+                    .state
+                    .next_run_at_ms
+                    .map(|ms| format_local(ms, now))
+                    .unwrap_or_else(|| "not scheduled".to_string());
+                let owner = j
+                    .payload
+                    .owner
+                    .as_deref()
+                    .map(|owner| format!(", owner: {owner}"))
+                    .unwrap_or_default();
+                format!(
+                    "- {} (id: {}, {}, next: {next}{owner})",
+                    j.name, j.id, j.schedule.kind
+                )
+            })
             .collect::<Vec<_>>();
         Ok(format!("Scheduled jobs:\n{}", lines.join("\n")))
     }
@@ -170,6 +262,19 @@ impl CronTool {
         let Some(job_id) = params.get("job_id").and_then(Value::as_str) else {
             return Ok("Error: job_id is required for remove".to_string());
         };
+        let job = self
+            .cron
+            .list_jobs(true)
+            .await
+            .into_iter()
+            .find(|job| job.id == job_id);
+        if let Some(job) = &job
+            && !self.may_manage(job)?
+        {
+            return Ok(format!(
+                "Error: job {job_id} was created by another user; only they or an owner can remove it"
+            ));
+        }
         if self.cron.remove_job(job_id).await? {
             Ok(format!("Removed job {job_id}"))
         } else {
@@ -180,7 +285,7 @@ impl CronTool {
 
 #[cfg(test)]
 mod tests {
-    use super::CronTool;
+    use super::*;
 
     #[test]
     fn parse_at_ms_accepts_rfc3339() {
@@ -193,4 +298,51 @@ mod tests {
         let err = CronTool::parse_at_ms("not-a-time").expect_err("should fail");
         assert!(err.to_string().contains("invalid at datetime"));
     }
+
+    #[tokio::test]
+    async fn group_members_only_manage_their_own_jobs() {
+        let path = std::env::temp_dir().join(format!("nanobot-cron-{}.json", uuid::Uuid::new_v4()));
+        let tool = CronTool::new(Arc::new(CronService::new(path.clone())));
+        let params = |value: Value| value.as_object().cloned().unwrap_or_default();
+
+        tool.set_context("telegram", "group1");
+        tool.set_requester("alice", false);
+        let created = tool
+            .execute(&params(
+                json!({"action": "add", "message": "water plants", "every_seconds": 3600}),
+            ))
+            .await
+            .expect("add");
+        let job_id = created
+            .rsplit("id: ")
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches(')')
+            .to_string();
+
+        tool.set_requester("bob", false);
+        let listed = tool
+            .execute(&params(json!({"action": "list"})))
+            .await
+            .expect("list");
+        assert_eq!(listed, "No scheduled jobs.");
+        let denied = tool
+            .execute(&params(json!({"action": "remove", "job_id": job_id})))
+            .await
+            .expect("remove");
+        assert!(denied.contains("created by another user"));
+
+        tool.set_requester("carol", true);
+        let listed = tool
+            .execute(&params(json!({"action": "list"})))
+            .await
+            .expect("list");
+        assert!(listed.contains("owner: telegram:alice"));
+        let removed = tool
+            .execute(&params(json!({"action": "remove", "job_id": job_id})))
+            .await
+            .expect("remove");
+        assert!(removed.starts_with("Removed job"));
+        let _ = std::fs::remove_file(path);
+    }
 }
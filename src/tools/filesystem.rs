@@ -2,7 +2,105 @@ use crate::tools::base::Tool;
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use serde_json::{Map, Value, json};
+use similar::TextDiff;
 use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+const DIFF_PREVIEW_LINES: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    pub path: String,
+    pub diff: String,
+    pub added: usize,
+    pub removed: usize,
+}
+
+// Shared by the write/edit tools so the agent loop can report what changed in a turn.
+#[derive(Debug, Clone, Default)]
+pub struct FileChanges {
+    inner: Arc<Mutex<Vec<FileChange>>>,
+}
+
+impl FileChanges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, path: &str, before: &str, after: &str) {
+        if before == after {
+            return;
+        }
+        let diff = TextDiff::from_lines(before, after);
+        let mut added = 0;
+        let mut removed = 0;
+        for change in diff.iter_all_changes() {
+            match change.tag() {
+                similar::ChangeTag::Insert => added += 1,
+                similar::ChangeTag::Delete => removed += 1,
+                similar::ChangeTag::Equal => {}
+            }
+        }
+        let unified = diff
+            .unified_diff()
+            .context_radius(2)
+            .header(&format!("a/{path}"), &format!("b/{path}"))
+            .to_string();
+        if let Ok(mut guard) = self.inner.lock() {
+            guard.push(FileChange {
+                path: path.to_string(),
+                diff: unified,
+                added,
+                removed,
+            });
+        }
+    }
+
+    pub fn take(&self) -> Vec<FileChange> {
+        self.inner
+            .lock()
+            .map(|mut guard| std::mem::take(&mut *guard))
+            .unwrap_or_default()
+    }
+}
+
+pub fn full_diff(changes: &[FileChange]) -> String {
+    changes
+        .iter()
+        .map(|change| change.diff.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn render_diff_preview(changes: &[FileChange]) -> (String, bool) {
+    let mut sections = Vec::new();
+    let mut truncated = false;
+    for change in changes {
+        let body = change
+            .diff
+            .lines()
+            .filter(|line| !line.starts_with("---") && !line.starts_with("+++"))
+            .collect::<Vec<_>>();
+        let mut shown = body
+            .iter()
+            .take(DIFF_PREVIEW_LINES)
+            .copied()
+            .collect::<Vec<_>>()
+            .join("\n");
+        if body.len() > DIFF_PREVIEW_LINES {
+            truncated = true;
+            shown.push_str(&format!(
+                "\n… {} more lines",
+                body.len() - DIFF_PREVIEW_LINES
+            ));
+        }
+        sections.push(format!(
+            "✏️ {} (+{} -{})\n```diff\n{shown}\n```",
+            change.path, change.added, change.removed
+        ));
+    }
+    (sections.join("\n"), truncated)
+}
 
 fn normalize_path(path: &Path) -> PathBuf {
     let mut out = PathBuf::new();
@@ -94,11 +192,20 @@ impl Tool for ReadFileTool {
 
 pub struct WriteFileTool {
     allowed_dir: Option<PathBuf>,
+    changes: Option<FileChanges>,
 }
 
 impl WriteFileTool {
     pub fn new(allowed_dir: Option<PathBuf>) -> Self {
-        Self { allowed_dir }
+        Self {
+            allowed_dir,
+            changes: None,
+        }
+    }
+
+    pub fn with_changes(mut self, changes: FileChanges) -> Self {
+        self.changes = Some(changes);
+        self
     }
 }
 
@@ -131,7 +238,17 @@ impl Tool for WriteFileTool {
         if let Some(parent) = resolved.parent() {
             tokio::fs::create_dir_all(parent).await?;
         }
+        let before = if self.changes.is_some() {
+            tokio::fs::read_to_string(&resolved)
+                .await
+                .unwrap_or_default()
+        } else {
+            String::new()
+        };
         tokio::fs::write(&resolved, content).await?;
+        if let Some(changes) = &self.changes {
+            changes.record(path, &before, content);
+        }
         Ok(format!(
             "Successfully wrote {} bytes to {path}",
             content.len()
@@ -141,11 +258,20 @@ impl Tool for WriteFileTool {
 
 pub struct EditFileTool {
     allowed_dir: Option<PathBuf>,
+    changes: Option<FileChanges>,
 }
 
 impl EditFileTool {
     pub fn new(allowed_dir: Option<PathBuf>) -> Self {
-        Self { allowed_dir }
+        Self {
+            allowed_dir,
+            changes: None,
+        }
+    }
+
+    pub fn with_changes(mut self, changes: FileChanges) -> Self {
+        self.changes = Some(changes);
+        self
     }
 }
 
@@ -195,7 +321,10 @@ impl Tool for EditFileTool {
         }
 
         let updated = content.replacen(old_text, new_text, 1);
-        tokio::fs::write(&resolved, updated).await?;
+        tokio::fs::write(&resolved, &updated).await?;
+        if let Some(changes) = &self.changes {
+            changes.record(path, &content, &updated);
+        }
         Ok(format!("Successfully edited {path}"))
     }
 }
@@ -257,3 +386,54 @@ impl Tool for ListDirTool {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn edit_file_records_unified_diff() {
+        let dir = std::env::temp_dir().join(format!("nanobot-rs-diff-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let file = dir.join("notes.md");
+        std::fs::write(&file, "one\ntwo\nthree\n").expect("write file");
+
+        let changes = FileChanges::new();
+        let tool = EditFileTool::new(Some(dir.clone())).with_changes(changes.clone());
+        let params = json!({
+            "path": file.to_string_lossy(),
+            "old_text": "two",
+            "new_text": "TWO"
+        });
+        let result = tool
+            .execute(params.as_object().expect("object"))
+            .await
+            .expect("edit");
+        assert!(result.starts_with("Successfully edited"));
+
+        let recorded = changes.take();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!((recorded[0].added, recorded[0].removed), (1, 1));
+        assert!(recorded[0].diff.contains("-two\n+TWO"));
+        assert!(changes.take().is_empty());
+
+        let (preview, truncated) = render_diff_preview(&recorded);
+        assert!(preview.contains("(+1 -1)"));
+        assert!(preview.contains("```diff"));
+        assert!(!truncated);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn diff_preview_truncates_long_changes() {
+        let changes = FileChanges::new();
+        let after = (0..50).map(|i| format!("line {i}\n")).collect::<String>();
+        changes.record("big.txt", "", &after);
+        let recorded = changes.take();
+        let (preview, truncated) = render_diff_preview(&recorded);
+        assert!(truncated);
+        assert!(preview.contains("more lines"));
+        assert!(full_diff(&recorded).contains("+line 49"));
+    }
+}
@@ -0,0 +1,80 @@
+use crate::events::parse_since;
+use crate::fulltext::{HistoryIndex, SearchQuery};
+use crate::tools::base::Tool;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::{Map, Value, json};
+
+pub struct HistorySearchTool {
+    index: HistoryIndex,
+}
+
+impl HistorySearchTool {
+    pub fn new(index: HistoryIndex) -> Self {
+        Self { index }
+    }
+}
+
+#[async_trait]
+impl Tool for HistorySearchTool {
+    fn name(&self) -> &str {
+        "history_search"
+    }
+
+    fn description(&self) -> &str {
+        "Full-text search over past conversations in every session and the HISTORY.md log. \
+         Use it to recall earlier decisions and facts (\"what did we decide about the boiler?\"); \
+         results are ranked by relevance with their session and time."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "query": { "type": "string", "description": "Keywords to look for" },
+                "session": { "type": "string", "description": "Only this session key, or HISTORY.md" },
+                "since": { "type": "string", "description": "Only newer entries: 30d, 12h, YYYY-MM-DD" },
+                "limit": { "type": "integer", "minimum": 1, "maximum": 50, "description": "Max results, default 10" }
+            },
+            "required": ["query"]
+        })
+    }
+
+    async fn execute(&self, params: &Map<String, Value>) -> Result<String> {
+        let text = params
+            .get("query")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("missing required string field: query"))?;
+        let since_ms = match params.get("since").and_then(Value::as_str) {
+            Some(raw) => match parse_since(raw, Utc::now().timestamp_millis()) {
+                Ok(since_ms) => Some(since_ms),
+                Err(err) => return Ok(format!("Error: {err}")),
+            },
+            None => None,
+        };
+        let query = SearchQuery {
+            text: text.to_string(),
+            source: params
+                .get("session")
+                .and_then(Value::as_str)
+                .map(ToOwned::to_owned),
+            since_ms,
+            limit: params
+                .get("limit")
+                .and_then(Value::as_u64)
+                .unwrap_or(10)
+                .clamp(1, 50) as usize,
+        };
+        let hits = self.index.search(&query)?;
+        if hits.is_empty() {
+            return Ok(format!("No past messages match \"{text}\"."));
+        }
+        let lines = hits
+            .iter()
+            .enumerate()
+            .map(|(idx, hit)| format!("{}. {}", idx + 1, hit.render()))
+            .collect::<Vec<_>>();
+        Ok(lines.join("\n"))
+    }
+}
@@ -1,20 +1,34 @@
 use crate::bus::OutboundMessage;
+use crate::channels::delivery::mark_proactive;
+use crate::cron::template::titled_message;
+use crate::message_templates::{MessageTemplates, template_vars};
 use crate::tools::base::Tool;
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use serde_json::{Map, Value, json};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 
 #[derive(Default)]
 struct MessageContext {
     channel: String,
     chat_id: String,
+    thread_id: Option<String>,
+    read_only: bool,
+    proactive: bool,
+    // Display name of whoever wrote in the current chat; {recipient} in
+    // templates sent back to that chat.
+    recipient_name: Option<String>,
 }
 
 pub struct MessageTool {
     sender: mpsc::Sender<OutboundMessage>,
     context: Mutex<MessageContext>,
+    prefix: Option<String>,
+    min_interval: Option<Duration>,
+    last_sent: Mutex<Option<Instant>>,
+    templates: Option<MessageTemplates>,
 }
 
 impl MessageTool {
@@ -22,15 +36,61 @@ impl MessageTool {
         Self {
             sender,
             context: Mutex::new(MessageContext::default()),
+            prefix: None,
+            min_interval: None,
+            last_sent: Mutex::new(None),
+            templates: None,
         }
     }
 
+    // Prepended to every message, e.g. "[paper search] ".
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    // Refuses messages sent sooner than `interval` after the previous one.
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.min_interval = Some(interval);
+        self
+    }
+
+    // Lets the model send workspace message templates by name.
+    pub fn with_templates(mut self, templates: MessageTemplates) -> Self {
+        self.templates = Some(templates);
+        self
+    }
+
     pub fn set_context(&self, channel: impl Into<String>, chat_id: impl Into<String>) {
         if let Ok(mut guard) = self.context.lock() {
             guard.channel = channel.into();
             guard.chat_id = chat_id.into();
         }
     }
+
+    pub fn set_thread(&self, thread_id: Option<String>) {
+        if let Ok(mut guard) = self.context.lock() {
+            guard.thread_id = thread_id;
+        }
+    }
+
+    pub fn set_read_only(&self, read_only: bool) {
+        if let Ok(mut guard) = self.context.lock() {
+            guard.read_only = read_only;
+        }
+    }
+
+    pub fn set_recipient_name(&self, name: Option<String>) {
+        if let Ok(mut guard) = self.context.lock() {
+            guard.recipient_name = name;
+        }
+    }
+
+    pub fn set_proactive(&self, proactive: bool) {
+        if let Ok(mut guard) = self.context.lock() {
+            guard.proactive = proactive;
+        }
+    }
 }
 
 #[async_trait]
@@ -40,7 +100,11 @@ impl Tool for MessageTool {
     }
 
     fn description(&self) -> &str {
-        "Send a message to the user. Use this when you need to communicate a progress update to a chat channel."
+        "Send a message to the user. Use this when you need to communicate a progress update to a chat channel, or to send a saved message template from templates/ in the workspace."
+    }
+
+    fn read_only(&self) -> bool {
+        true
     }
 
     fn parameters(&self) -> Value {
@@ -48,18 +112,22 @@ impl Tool for MessageTool {
             "type": "object",
             "properties": {
                 "content": { "type": "string", "description": "The message content to send" },
+                "template_name": { "type": "string", "description": "Send the workspace template templates/<name>.md instead of content" },
+                "vars": { "type": "object", "additionalProperties": { "type": "string" }, "description": "Values for the template's {placeholders}; {date}, {time}, {weekday} and {metric:<name>} fill themselves" },
                 "channel": { "type": "string", "description": "Optional target channel" },
-                "chat_id": { "type": "string", "description": "Optional target chat/user ID" }
+                "chat_id": { "type": "string", "description": "Optional target chat/user ID" },
+                "urgent": { "type": "boolean", "description": "Deliver immediately even outside the channel's delivery window" }
             },
-            "required": ["content"]
+            "required": []
         })
     }
 
     async fn execute(&self, params: &Map<String, Value>) -> Result<String> {
-        let content = params
-            .get("content")
-            .and_then(Value::as_str)
-            .ok_or_else(|| anyhow!("missing required string field: content"))?;
+        let content = params.get("content").and_then(Value::as_str);
+        let template_name = params.get("template_name").and_then(Value::as_str);
+        if content.is_none() && template_name.is_none() {
+            return Err(anyhow!("missing required string field: content"));
+        }
 
         let explicit_channel = params
             .get("channel")
@@ -70,22 +138,87 @@ impl Tool for MessageTool {
             .and_then(Value::as_str)
             .map(ToOwned::to_owned);
 
+        let urgent = params
+            .get("urgent")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+
+        let (context_channel, context_chat_id, thread_id, read_only, proactive, recipient_name) = {
+            let guard = self
+                .context
+                .lock()
+                .map_err(|_| anyhow!("failed to lock message tool context"))?;
+            (
+                guard.channel.clone(),
+                guard.chat_id.clone(),
+                guard.thread_id.clone(),
+                guard.read_only,
+                guard.proactive,
+                guard.recipient_name.clone(),
+            )
+        };
         let (channel, chat_id) =
             if let (Some(channel), Some(chat_id)) = (explicit_channel, explicit_chat_id) {
                 (channel, chat_id)
             } else {
-                let guard = self
-                    .context
-                    .lock()
-                    .map_err(|_| anyhow!("failed to lock message tool context"))?;
-                (guard.channel.clone(), guard.chat_id.clone())
+                (context_channel.clone(), context_chat_id.clone())
             };
 
+        if read_only && (channel != context_channel || chat_id != context_chat_id) {
+            return Ok(
+                "Error: Messaging other chats is disabled on this read-only channel".to_string(),
+            );
+        }
+
         if channel.is_empty() || chat_id.is_empty() {
             return Ok("Error: No target channel/chat specified".to_string());
         }
 
-        let msg = OutboundMessage::new(channel.clone(), chat_id.clone(), content);
+        let same_chat = channel == context_channel && chat_id == context_chat_id;
+        let (title, content) = match template_name {
+            Some(name) => {
+                let Some(templates) = &self.templates else {
+                    return Ok("Error: message templates are not available here".to_string());
+                };
+                let mut vars = template_vars(params.get("vars"));
+                if same_chat && let Some(name) = recipient_name {
+                    vars.entry("recipient".to_string()).or_insert(name);
+                }
+                match templates.render_named(name, &vars, chrono::Local::now()) {
+                    Ok(rendered) => (rendered.title, rendered.body),
+                    Err(err) => return Ok(format!("Error: {err}")),
+                }
+            }
+            None => (None, content.unwrap_or_default().to_string()),
+        };
+
+        if let Some(interval) = self.min_interval {
+            let mut last_sent = self
+                .last_sent
+                .lock()
+                .map_err(|_| anyhow!("failed to lock message tool rate limit"))?;
+            if let Some(wait) = last_sent.and_then(|at| interval.checked_sub(at.elapsed())) {
+                return Ok(format!(
+                    "Error: updates are limited to one every {}s; wait {}s and keep working meanwhile",
+                    interval.as_secs(),
+                    wait.as_secs().max(1)
+                ));
+            }
+            *last_sent = Some(Instant::now());
+        }
+
+        let content = match &self.prefix {
+            Some(prefix) => format!("{prefix}{content}"),
+            None => content,
+        };
+        let mut msg = titled_message(&channel, &chat_id, title.as_deref(), content);
+        // Stay in the current thread unless the model addressed another chat.
+        if same_chat {
+            msg.thread_id = thread_id;
+        }
+        if proactive {
+            mark_proactive(&mut msg, urgent);
+        }
         self.sender
             .send(msg)
             .await
@@ -94,3 +227,111 @@ impl Tool for MessageTool {
         Ok(format!("Message sent to {channel}:{chat_id}"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn read_only_context_blocks_other_targets() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let tool = MessageTool::new(tx);
+        tool.set_context("telegram", "123");
+        tool.set_read_only(true);
+
+        let other = json!({ "content": "hi", "channel": "telegram", "chat_id": "999" });
+        let result = tool
+            .execute(other.as_object().expect("object"))
+            .await
+            .expect("execute");
+        assert!(result.contains("read-only"));
+        assert!(rx.try_recv().is_err());
+
+        let same = json!({ "content": "hi" });
+        let result = tool
+            .execute(same.as_object().expect("object"))
+            .await
+            .expect("execute");
+        assert!(result.contains("telegram:123"));
+        assert_eq!(rx.try_recv().expect("sent").chat_id, "123");
+    }
+
+    #[tokio::test]
+    async fn stays_in_thread_only_for_current_chat() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let tool = MessageTool::new(tx);
+        tool.set_context("slack", "C1");
+        tool.set_thread(Some("1700000000.000100".to_string()));
+
+        let same = json!({ "content": "progress" });
+        tool.execute(same.as_object().expect("object"))
+            .await
+            .expect("execute");
+        let sent = rx.try_recv().expect("sent");
+        assert_eq!(sent.thread_id.as_deref(), Some("1700000000.000100"));
+
+        let other = json!({ "content": "fyi", "channel": "slack", "chat_id": "C2" });
+        tool.execute(other.as_object().expect("object"))
+            .await
+            .expect("execute");
+        assert_eq!(rx.try_recv().expect("sent").thread_id, None);
+    }
+
+    #[tokio::test]
+    async fn rate_limited_tool_prefixes_and_throttles() {
+        let (tx, mut rx) = mpsc::channel(4);
+        let tool = MessageTool::new(tx)
+            .with_prefix("[papers] ")
+            .with_min_interval(Duration::from_secs(30));
+        tool.set_context("telegram", "123");
+
+        let update = json!({ "content": "found 12 candidates" });
+        tool.execute(update.as_object().expect("object"))
+            .await
+            .expect("execute");
+        assert_eq!(
+            rx.try_recv().expect("sent").content,
+            "[papers] found 12 candidates"
+        );
+
+        let result = tool
+            .execute(update.as_object().expect("object"))
+            .await
+            .expect("execute");
+        assert!(result.contains("one every 30s"));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn sends_workspace_templates_with_title_and_recipient() {
+        let workspace =
+            std::env::temp_dir().join(format!("nanobot-msg-templates-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(workspace.join("templates")).expect("templates dir");
+        std::fs::write(
+            workspace.join("templates/rent.md"),
+            "---\ntitle: Rent for {month}\n---\nHi {recipient}, rent is due.",
+        )
+        .expect("write template");
+        let (tx, mut rx) = mpsc::channel(4);
+        let tool = MessageTool::new(tx).with_templates(MessageTemplates::new(&workspace));
+        tool.set_context("email", "ana@example.com");
+        tool.set_recipient_name(Some("Ana".to_string()));
+
+        let missing = json!({ "template_name": "rent" });
+        let result = tool
+            .execute(missing.as_object().expect("object"))
+            .await
+            .expect("execute");
+        assert!(result.contains("needs values for: month"), "{result}");
+        assert!(rx.try_recv().is_err());
+
+        let send = json!({ "template_name": "rent", "vars": { "month": "March" } });
+        tool.execute(send.as_object().expect("object"))
+            .await
+            .expect("execute");
+        let sent = rx.try_recv().expect("sent");
+        assert_eq!(sent.metadata["subject"], "Rent for March");
+        assert_eq!(sent.content, "Hi Ana, rent is due.");
+        let _ = std::fs::remove_dir_all(workspace);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::metrics::{Bucket, MetricEntry, MetricStore, chart, since_days, summarize};
+use crate::tools::base::Tool;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::Local;
+use serde_json::{Map, Value, json};
+
+pub struct MetricTool {
+    store: MetricStore,
+}
+
+impl MetricTool {
+    pub fn new(store: MetricStore) -> Self {
+        Self { store }
+    }
+}
+
+fn string_param<'a>(params: &'a Map<String, Value>, key: &str) -> &'a str {
+    params
+        .get(key)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .unwrap_or_default()
+}
+
+#[async_trait]
+impl Tool for MetricTool {
+    fn name(&self) -> &str {
+        "log_metric"
+    }
+
+    fn description(&self) -> &str {
+        "Track numbers the user reports over time (weight, expenses, mood, sleep...). \
+         Actions: log (append a value), summary (stats over the last N days), \
+         chart (text chart per day/week/month), list (known metrics). \
+         Entries are stored as CSV under metrics/ in the workspace. \
+         For recurring reports, schedule a cron job that asks for a summary or chart."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": { "type": "string", "enum": ["log", "summary", "chart", "list"] },
+                "metric": { "type": "string", "description": "Metric name, e.g. weight or expenses" },
+                "value": { "type": "number" },
+                "unit": { "type": "string", "description": "e.g. kg, EUR, 1-10" },
+                "note": { "type": "string" },
+                "days": { "type": "integer", "minimum": 1, "description": "Look-back window (default 30)" },
+                "bucket": { "type": "string", "enum": ["day", "week", "month"] },
+                "aggregate": {
+                    "type": "string",
+                    "enum": ["avg", "sum"],
+                    "description": "sum for amounts like expenses, avg for levels like weight (default avg)"
+                }
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, params: &Map<String, Value>) -> Result<String> {
+        let action = params
+            .get("action")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("missing required string field: action"))?;
+        if action == "list" {
+            let metrics = self.store.list();
+            if metrics.is_empty() {
+                return Ok("No metrics logged yet.".to_string());
+            }
+            let lines = metrics
+                .iter()
+                .map(|(name, count)| format!("- {name} ({count} entries)"))
+                .collect::<Vec<_>>();
+            return Ok(format!("Metrics:\n{}", lines.join("\n")));
+        }
+
+        let metric = string_param(params, "metric");
+        if metric.is_empty() {
+            return Ok(format!("Error: metric is required for {action}"));
+        }
+        match action {
+            "log" => {
+                let Some(value) = params.get("value").and_then(Value::as_f64) else {
+                    return Ok("Error: a numeric value is required for log".to_string());
+                };
+                let entry = MetricEntry {
+                    at: Local::now(),
+                    value,
+                    unit: string_param(params, "unit").to_string(),
+                    note: string_param(params, "note").to_string(),
+                };
+                let path = self.store.log(metric, &entry)?;
+                Ok(format!(
+                    "Logged {metric} = {value}{} ({})",
+                    if entry.unit.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" {}", entry.unit)
+                    },
+                    path.display()
+                ))
+            }
+            "summary" | "chart" => {
+                let entries = match self.store.entries(metric) {
+                    Ok(entries) => entries,
+                    Err(err) => return Ok(format!("Error: {err}")),
+                };
+                let days = params
+                    .get("days")
+                    .and_then(Value::as_i64)
+                    .unwrap_or(30)
+                    .max(1);
+                let recent = since_days(&entries, days, Local::now());
+                if action == "summary" {
+                    return Ok(format!(
+                        "Last {days} days of {}",
+                        summarize(metric, &recent)
+                    ));
+                }
+                let raw_bucket = string_param(params, "bucket");
+                let bucket = if raw_bucket.is_empty() {
+                    Bucket::Day
+                } else {
+                    match Bucket::parse(raw_bucket) {
+                        Some(bucket) => bucket,
+                        None => return Ok(format!("Error: unknown bucket '{raw_bucket}'")),
+                    }
+                };
+                let sum = string_param(params, "aggregate") == "sum";
+                Ok(format!(
+                    "{metric}, last {days} days ({}):\n{}",
+                    if sum { "total" } else { "average" },
+                    chart(&recent, bucket, sum)
+                ))
+            }
+            _ => Ok(format!("Unknown action: {action}")),
+        }
+    }
+}
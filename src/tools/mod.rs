@@ -1,10 +1,17 @@
 pub mod base;
+pub mod batch;
 pub mod cron;
 pub mod filesystem;
+pub mod history;
 pub mod http;
 pub mod message;
+pub mod metrics;
 pub mod registry;
+pub mod remind;
+pub mod search;
 pub mod sessions;
 pub mod shell;
 pub mod spawn;
+pub mod status;
+pub mod tasks;
 pub mod web;
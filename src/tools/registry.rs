@@ -1,41 +1,75 @@
 use crate::tools::base::Tool;
 use serde_json::{Map, Value};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 
+// Tools can be added and removed while the agent is running (config reload,
+// external tool servers connecting); each turn reads the current set, so a
+// change shows up in the next turn's tool definitions and runtime facts.
 pub struct ToolRegistry {
-    tools: HashMap<String, Arc<dyn Tool>>,
+    tools: RwLock<HashMap<String, Arc<dyn Tool>>>,
 }
 
 impl ToolRegistry {
     pub fn new() -> Self {
         Self {
-            tools: HashMap::new(),
+            tools: RwLock::new(HashMap::new()),
         }
     }
 
-    pub fn register(&mut self, tool: Arc<dyn Tool>) {
-        self.tools.insert(tool.name().to_string(), tool);
+    fn snapshot(&self) -> Vec<Arc<dyn Tool>> {
+        self.tools
+            .read()
+            .map(|tools| tools.values().cloned().collect())
+            .unwrap_or_default()
     }
 
-    pub fn unregister(&mut self, name: &str) {
-        self.tools.remove(name);
+    // Returns the tool previously registered under the same name, if any.
+    pub fn register(&self, tool: Arc<dyn Tool>) -> Option<Arc<dyn Tool>> {
+        let mut tools = self.tools.write().ok()?;
+        tools.insert(tool.name().to_string(), tool)
+    }
+
+    pub fn unregister(&self, name: &str) -> Option<Arc<dyn Tool>> {
+        self.tools.write().ok()?.remove(name)
     }
 
     pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
-        self.tools.get(name).cloned()
+        self.tools.read().ok()?.get(name).cloned()
     }
 
     pub fn has(&self, name: &str) -> bool {
-        self.tools.contains_key(name)
+        self.get(name).is_some()
     }
 
     pub fn get_definitions(&self) -> Vec<Value> {
-        self.tools.values().map(|tool| tool.to_schema()).collect()
+        self.snapshot()
+            .iter()
+            .map(|tool| tool.to_schema())
+            .collect()
+    }
+
+    // Definitions sorted by tool name, for exporting a stable schema list.
+    pub fn export_definitions(&self) -> Vec<Value> {
+        let mut tools = self.snapshot();
+        tools.sort_by(|a, b| a.name().cmp(b.name()));
+        tools.iter().map(|tool| tool.to_schema()).collect()
+    }
+
+    pub fn get_read_only_definitions(&self) -> Vec<Value> {
+        self.snapshot()
+            .iter()
+            .filter(|tool| tool.read_only())
+            .map(|tool| tool.to_schema())
+            .collect()
+    }
+
+    pub fn is_read_only(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|tool| tool.read_only())
     }
 
     pub async fn execute(&self, name: &str, params: &Map<String, Value>) -> String {
-        let Some(tool) = self.tools.get(name) else {
+        let Some(tool) = self.get(name) else {
             return format!("Error: Tool '{name}' not found");
         };
 
@@ -54,11 +88,22 @@ impl ToolRegistry {
     }
 
     pub fn tool_names(&self) -> Vec<String> {
-        self.tools.keys().cloned().collect()
+        self.tools
+            .read()
+            .map(|tools| tools.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn read_only_tool_names(&self) -> Vec<String> {
+        self.snapshot()
+            .iter()
+            .filter(|tool| tool.read_only())
+            .map(|tool| tool.name().to_string())
+            .collect()
     }
 
     pub fn len(&self) -> usize {
-        self.tools.len()
+        self.tools.read().map(|tools| tools.len()).unwrap_or(0)
     }
 }
 
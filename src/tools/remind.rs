@@ -0,0 +1,215 @@
+use crate::bus::InboundMessage;
+use crate::cron::CronService;
+use crate::cron::reminder::{FiredReminders, parse_when, schedule_reminder};
+use crate::cron::time::format_local;
+use crate::tools::base::Tool;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use chrono::Local;
+use serde_json::{Map, Value, json};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+struct RemindContext {
+    channel: String,
+    chat_id: String,
+    sender_id: String,
+}
+
+pub struct RemindTool {
+    cron: Arc<CronService>,
+    fired: FiredReminders,
+    context: Mutex<RemindContext>,
+}
+
+impl RemindTool {
+    pub fn new(cron: Arc<CronService>, fired: FiredReminders) -> Self {
+        Self {
+            cron,
+            fired,
+            context: Mutex::new(RemindContext::default()),
+        }
+    }
+
+    pub fn set_context(
+        &self,
+        channel: impl Into<String>,
+        chat_id: impl Into<String>,
+        sender_id: impl Into<String>,
+    ) {
+        if let Ok(mut guard) = self.context.lock() {
+            guard.channel = channel.into();
+            guard.chat_id = chat_id.into();
+            guard.sender_id = sender_id.into();
+        }
+    }
+
+    // Recreate a fired reminder, or move a pending one, to `when`. Only the
+    // person it was set for may snooze it, unless `owner`; reminders without
+    // one belong to the chat they are delivered to.
+    pub async fn snooze(
+        &self,
+        id: &str,
+        when: &str,
+        requester: &InboundMessage,
+        owner: bool,
+    ) -> Result<String> {
+        let now = Local::now();
+        let at_ms = match parse_when(when, now) {
+            Ok(at_ms) => at_ms,
+            Err(err) => return Ok(format!("Error: {err}")),
+        };
+        let pending = self
+            .cron
+            .list_jobs(false)
+            .await
+            .into_iter()
+            .find(|job| job.id == id);
+        let (text, channel, to, set_for, delete_after_run) = if let Some(job) = pending {
+            (
+                job.payload.message,
+                job.payload.channel.unwrap_or_default(),
+                job.payload.to.unwrap_or_default(),
+                job.payload.owner,
+                job.delete_after_run,
+            )
+        } else if let Some(fired) = self.fired.get(id) {
+            (fired.text, fired.channel, fired.to, fired.owner, false)
+        } else {
+            return Ok(format!("Error: no reminder {id} to snooze"));
+        };
+        let allowed = owner
+            || match &set_for {
+                Some(set_for) => {
+                    *set_for == format!("{}:{}", requester.channel, requester.sender_id)
+                }
+                None => channel == requester.channel && to == requester.chat_id,
+            };
+        // Someone else's reminder reads the same as a missing one.
+        if !allowed {
+            return Ok(format!("Error: no reminder {id} to snooze"));
+        }
+        // A repeating reminder keeps its schedule; the snooze is a one-off copy.
+        if delete_after_run {
+            self.cron.remove_job(id).await?;
+        }
+        let job = schedule_reminder(&self.cron, &text, at_ms, &channel, &to, set_for).await?;
+        Ok(format!(
+            "⏰ Snoozed \"{text}\" until {} (id: {})",
+            format_local(at_ms, now.timestamp_millis()),
+            job.id
+        ))
+    }
+}
+
+#[async_trait]
+impl Tool for RemindTool {
+    fn name(&self) -> &str {
+        "remind"
+    }
+
+    fn description(&self) -> &str {
+        "Remind the user of something at a given time in this chat, e.g. \"remind me to \
+         call mom at 18:00\". The reminder text is delivered verbatim with snooze options. \
+         Use the cron tool instead for recurring or agent-run tasks."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "text": { "type": "string", "description": "What to remind about, phrased for the user" },
+                "when": {
+                    "type": "string",
+                    "description": "\"10m\", \"in 2 hours\", \"tomorrow\", \"tomorrow 08:30\", \"18:00\", or \"YYYY-MM-DD HH:MM\" local time"
+                }
+            },
+            "required": ["text", "when"]
+        })
+    }
+
+    async fn execute(&self, params: &Map<String, Value>) -> Result<String> {
+        let text = params
+            .get("text")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .unwrap_or_default();
+        if text.is_empty() {
+            return Ok("Error: text is required".to_string());
+        }
+        let when = params
+            .get("when")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("missing required string field: when"))?;
+        let (channel, chat_id, sender_id) = {
+            let guard = self
+                .context
+                .lock()
+                .map_err(|_| anyhow!("failed to lock remind context"))?;
+            (
+                guard.channel.clone(),
+                guard.chat_id.clone(),
+                guard.sender_id.clone(),
+            )
+        };
+        if channel.is_empty() || chat_id.is_empty() {
+            return Ok("Error: no session context (channel/chat_id)".to_string());
+        }
+        let now = Local::now();
+        let at_ms = match parse_when(when, now) {
+            Ok(at_ms) => at_ms,
+            Err(err) => return Ok(format!("Error: {err}")),
+        };
+        let owner = (!sender_id.is_empty()).then(|| format!("{channel}:{sender_id}"));
+        let job = schedule_reminder(&self.cron, text, at_ms, &channel, &chat_id, owner).await?;
+        Ok(format!(
+            "Reminder set for {} (id: {})",
+            format_local(at_ms, now.timestamp_millis()),
+            job.id
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn only_the_reminded_user_or_an_owner_can_snooze() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("nanobot-remind-{}", Uuid::new_v4()));
+        let cron = Arc::new(CronService::new(dir.join("jobs.json")));
+        let tool = RemindTool::new(
+            cron.clone(),
+            FiredReminders::from_path(dir.join("reminders.json")),
+        );
+        let at_ms = Local::now().timestamp_millis() + 3_600_000;
+        let job = schedule_reminder(
+            &cron,
+            "call mom",
+            at_ms,
+            "telegram",
+            "-100",
+            Some("telegram:alice".to_string()),
+        )
+        .await?;
+
+        let bob = InboundMessage::new("telegram", "bob", "-100", "/snooze");
+        let refused = tool.snooze(&job.id, "1h", &bob, false).await?;
+        assert!(refused.starts_with("Error: no reminder"));
+        assert_eq!(cron.list_jobs(true).await.len(), 1);
+
+        let alice = InboundMessage::new("telegram", "alice", "-100", "/snooze");
+        let snoozed = tool.snooze(&job.id, "1h", &alice, false).await?;
+        assert!(snoozed.starts_with("⏰ Snoozed \"call mom\""), "{snoozed}");
+        let jobs = cron.list_jobs(true).await;
+        assert_eq!(jobs.len(), 1);
+        assert_ne!(jobs[0].id, job.id);
+        assert_eq!(jobs[0].payload.owner.as_deref(), Some("telegram:alice"));
+
+        let moved = tool.snooze(&jobs[0].id, "2h", &bob, true).await?;
+        assert!(moved.starts_with("⏰ Snoozed"));
+        let _ = std::fs::remove_dir_all(dir);
+        Ok(())
+    }
+}
@@ -0,0 +1,625 @@
+use crate::events::{self, EventLevel};
+use anyhow::{Result, anyhow};
+use reqwest::header::{ACCEPT, HeaderMap, RETRY_AFTER, USER_AGENT};
+use reqwest::{RequestBuilder, StatusCode};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+const DEFAULT_USER_AGENT: &str =
+    "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_7_2) AppleWebKit/537.36";
+const BRAVE_SEARCH_ENDPOINT: &str = "https://api.search.brave.com/res/v1/web/search";
+const DUCKDUCKGO_INSTANT_ENDPOINT: &str = "https://api.duckduckgo.com/";
+const GROK_RESPONSES_ENDPOINT: &str = "https://api.x.ai/v1/responses";
+
+// Short waits (per-second windows) are absorbed; longer cool-downs fail fast
+// so the caller can fall back right away.
+const MAX_QUOTA_WAIT: Duration = Duration::from_secs(3);
+const QUOTA_POLL: Duration = Duration::from_millis(250);
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+// (title, url, snippet)
+pub type SearchHit = (String, String, String);
+
+// The tightest window a provider reported through its rate-limit headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub limit: Option<u64>,
+    pub remaining: u64,
+    pub reset: Duration,
+}
+
+fn header_values(headers: &HeaderMap, names: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .find_map(|name| headers.get(*name))
+        .and_then(|value| value.to_str().ok())
+        .map(|raw| raw.split(',').map(|part| part.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+// "1" / "1.5" seconds, "6m0s" / "20ms" durations, or an epoch timestamp in
+// seconds or milliseconds.
+fn parse_reset(raw: &str) -> Option<Duration> {
+    if let Ok(number) = raw.parse::<f64>() {
+        if number < 0.0 {
+            return None;
+        }
+        if number < 1e9 {
+            return Some(Duration::from_secs_f64(number));
+        }
+        let at_ms = if number < 1e11 {
+            number * 1000.0
+        } else {
+            number
+        };
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()?
+            .as_millis() as f64;
+        return Some(Duration::from_millis((at_ms - now_ms).max(0.0) as u64));
+    }
+    let mut total = Duration::ZERO;
+    let mut digits = String::new();
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            digits.push(c);
+            continue;
+        }
+        let value = digits.parse::<f64>().ok()?;
+        digits.clear();
+        let seconds = match c {
+            'h' => value * 3600.0,
+            'm' if chars.peek() == Some(&'s') => {
+                chars.next();
+                value / 1000.0
+            }
+            'm' => value * 60.0,
+            's' => value,
+            _ => return None,
+        };
+        total += Duration::from_secs_f64(seconds);
+    }
+    digits.is_empty().then_some(total)
+}
+
+// Understands Brave's comma-separated per-window headers and the
+// OpenAI-style `x-ratelimit-*-requests` headers used by Perplexity,
+// OpenRouter and xAI.
+pub fn parse_rate_limit(headers: &HeaderMap) -> Option<RateLimit> {
+    let limits = header_values(
+        headers,
+        &["x-ratelimit-limit", "x-ratelimit-limit-requests"],
+    );
+    let remaining = header_values(
+        headers,
+        &["x-ratelimit-remaining", "x-ratelimit-remaining-requests"],
+    );
+    let resets = header_values(
+        headers,
+        &["x-ratelimit-reset", "x-ratelimit-reset-requests"],
+    );
+    remaining
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, raw)| {
+            Some(RateLimit {
+                limit: limits.get(idx).and_then(|raw| raw.parse().ok()),
+                remaining: raw.parse().ok()?,
+                reset: resets.get(idx).and_then(|raw| parse_reset(raw))?,
+            })
+        })
+        .min_by_key(|window| window.remaining)
+}
+
+#[derive(Debug, Default)]
+struct Quota {
+    limit: Option<u64>,
+    window: Duration,
+    remaining: Option<u64>,
+    reset_at: Option<Instant>,
+    blocked_until: Option<Instant>,
+}
+
+impl Quota {
+    // Takes a request slot, or says how long to wait for one. Slots are
+    // counted down locally so concurrent turns don't all spend the last one.
+    fn reserve(&mut self, now: Instant) -> Result<(), Duration> {
+        if let Some(until) = self.blocked_until {
+            if until > now {
+                return Err(until - now);
+            }
+            self.blocked_until = None;
+        }
+        if self.reset_at.is_some_and(|reset| reset <= now) {
+            self.remaining = self.limit;
+            self.reset_at = self.limit.map(|_| now + self.window);
+        }
+        match self.remaining {
+            Some(0) => Err(self
+                .reset_at
+                .map(|reset| reset.saturating_duration_since(now))
+                .unwrap_or(QUOTA_POLL)),
+            Some(n) => {
+                self.remaining = Some(n - 1);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn observe(&mut self, limit: RateLimit, now: Instant) {
+        self.limit = limit.limit;
+        self.window = limit.reset;
+        self.remaining = Some(limit.remaining);
+        self.reset_at = Some(now + limit.reset);
+    }
+
+    fn block(&mut self, cooldown: Duration, now: Instant) {
+        self.blocked_until = Some(now + cooldown);
+    }
+}
+
+// Quota state per provider, shared by every web_search tool in the process:
+// the main agent's turns and all subagents.
+fn quotas() -> &'static Mutex<HashMap<&'static str, Quota>> {
+    static QUOTAS: OnceLock<Mutex<HashMap<&'static str, Quota>>> = OnceLock::new();
+    QUOTAS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn http() -> &'static reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+async fn acquire(provider: &'static str) -> Result<()> {
+    let deadline = Instant::now() + MAX_QUOTA_WAIT;
+    loop {
+        let now = Instant::now();
+        let wait = match quotas().lock() {
+            Ok(mut quotas) => match quotas.entry(provider).or_default().reserve(now) {
+                Ok(()) => return Ok(()),
+                Err(wait) => wait,
+            },
+            Err(_) => return Ok(()),
+        };
+        if now + wait > deadline {
+            return Err(anyhow!(
+                "{provider} rate limit reached, cooling down for {}s",
+                wait.as_secs().max(1)
+            ));
+        }
+        tokio::time::sleep(wait.min(QUOTA_POLL)).await;
+    }
+}
+
+// Sends a provider request through the shared quota and returns its JSON body.
+async fn send_json(provider: &'static str, request: RequestBuilder) -> Result<Value> {
+    acquire(provider).await?;
+    let response = request.send().await?;
+    let now = Instant::now();
+    let status = response.status();
+    let headers = response.headers();
+    let limit = parse_rate_limit(headers);
+    if let Ok(mut quotas) = quotas().lock() {
+        let quota = quotas.entry(provider).or_default();
+        if let Some(limit) = limit {
+            quota.observe(limit, now);
+        }
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            let cooldown = headers
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_reset)
+                .or(limit.map(|limit| limit.reset))
+                .unwrap_or(DEFAULT_COOLDOWN);
+            quota.block(cooldown, now);
+            events::record(
+                EventLevel::Warn,
+                "search",
+                None,
+                format!(
+                    "{provider} returned 429; pausing searches for {}s",
+                    cooldown.as_secs()
+                ),
+            );
+        }
+    }
+    Ok(response.error_for_status()?.json().await?)
+}
+
+fn citations(payload: &Value) -> Vec<String> {
+    payload
+        .get("citations")
+        .and_then(Value::as_array)
+        .map(|arr| {
+            arr.iter()
+                .filter_map(Value::as_str)
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(ToOwned::to_owned)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default()
+}
+
+pub struct BraveClient {
+    api_key: String,
+}
+
+impl BraveClient {
+    pub fn new(api_key: String) -> Self {
+        Self { api_key }
+    }
+
+    pub async fn search(&self, query: &str, n: u64) -> Result<Vec<SearchHit>> {
+        let request = http()
+            .get(BRAVE_SEARCH_ENDPOINT)
+            .query(&[("q", query), ("count", &n.to_string())])
+            .header(ACCEPT, "application/json")
+            .header("X-Subscription-Token", &self.api_key);
+        let payload = send_json("brave", request).await?;
+        let results = payload
+            .get("web")
+            .and_then(|v| v.get("results"))
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut out = Vec::new();
+        for item in results {
+            let title = item
+                .get("title")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .trim();
+            let url = item
+                .get("url")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .trim();
+            let desc = item
+                .get("description")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .trim();
+            if !title.is_empty() && !url.is_empty() {
+                out.push((title.to_string(), url.to_string(), desc.to_string()));
+            }
+        }
+        Ok(out)
+    }
+}
+
+fn push_duckduckgo_result(output: &mut Vec<SearchHit>, title: &str, url: &str, snippet: &str) {
+    let title = title.trim();
+    let url = url.trim();
+    let snippet = snippet.trim();
+    if title.is_empty() || url.is_empty() {
+        return;
+    }
+    if output
+        .iter()
+        .any(|(_, existing_url, _)| existing_url == url)
+    {
+        return;
+    }
+    output.push((title.to_string(), url.to_string(), snippet.to_string()));
+}
+
+fn collect_duckduckgo_related_topics(topics: &[Value], output: &mut Vec<SearchHit>) {
+    for topic in topics {
+        if let Some(nested) = topic.get("Topics").and_then(Value::as_array) {
+            collect_duckduckgo_related_topics(nested, output);
+            continue;
+        }
+
+        let text = topic
+            .get("Text")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .unwrap_or_default();
+        let url = topic
+            .get("FirstURL")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .unwrap_or_default();
+
+        if text.is_empty() || url.is_empty() {
+            continue;
+        }
+
+        let title = text.split(" - ").next().unwrap_or(text);
+        push_duckduckgo_result(output, title, url, text);
+    }
+}
+
+// Keyless fallback used when the configured provider is unavailable.
+pub struct DuckDuckGoClient;
+
+impl DuckDuckGoClient {
+    pub async fn search(&self, query: &str, n: u64) -> Result<Vec<SearchHit>> {
+        let request = http()
+            .get(DUCKDUCKGO_INSTANT_ENDPOINT)
+            .query(&[
+                ("q", query),
+                ("format", "json"),
+                ("no_html", "1"),
+                ("skip_disambig", "1"),
+                ("no_redirect", "1"),
+            ])
+            .header(ACCEPT, "application/json")
+            .header(USER_AGENT, DEFAULT_USER_AGENT);
+        let payload = send_json("duckduckgo", request).await?;
+
+        let mut out = Vec::new();
+        let abstract_text = payload
+            .get("AbstractText")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .unwrap_or_default();
+        let abstract_url = payload
+            .get("AbstractURL")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .unwrap_or_default();
+        let heading = payload
+            .get("Heading")
+            .and_then(Value::as_str)
+            .map(str::trim)
+            .unwrap_or_default();
+        if !abstract_text.is_empty() && !abstract_url.is_empty() {
+            let title = if heading.is_empty() {
+                abstract_text
+                    .split('.')
+                    .next()
+                    .unwrap_or(abstract_text)
+                    .trim()
+            } else {
+                heading
+            };
+            push_duckduckgo_result(&mut out, title, abstract_url, abstract_text);
+        }
+
+        if let Some(topics) = payload.get("RelatedTopics").and_then(Value::as_array) {
+            collect_duckduckgo_related_topics(topics, &mut out);
+        }
+
+        out.truncate(n as usize);
+        Ok(out)
+    }
+}
+
+pub struct PerplexityClient {
+    api_key: String,
+    base_url: String,
+    model: String,
+}
+
+impl PerplexityClient {
+    pub fn new(api_key: String, base_url: String, model: String) -> Self {
+        Self {
+            api_key,
+            base_url,
+            model,
+        }
+    }
+
+    pub fn has_key(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    fn request_model(base_url: &str, model: &str) -> String {
+        let parsed = Url::parse(base_url).ok();
+        let host = parsed
+            .and_then(|u| u.host_str().map(|s| s.to_ascii_lowercase()))
+            .unwrap_or_default();
+        if host == "api.perplexity.ai" {
+            model
+                .strip_prefix("perplexity/")
+                .unwrap_or(model)
+                .to_string()
+        } else {
+            model.to_string()
+        }
+    }
+
+    pub async fn search(&self, query: &str) -> Result<(String, Vec<String>)> {
+        let endpoint = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let request = http()
+            .post(endpoint)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("HTTP-Referer", "https://github.com/open-vibe/nanobot-rs")
+            .header("X-Title", "nanobot-rs web_search")
+            .json(&json!({
+                "model": Self::request_model(&self.base_url, &self.model),
+                "messages": [{ "role": "user", "content": query }],
+            }));
+        let payload = send_json("perplexity", request).await?;
+        let answer = payload
+            .get("choices")
+            .and_then(Value::as_array)
+            .and_then(|arr| arr.first())
+            .and_then(|v| v.get("message"))
+            .and_then(|v| v.get("content"))
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .trim()
+            .to_string();
+        Ok((answer, citations(&payload)))
+    }
+}
+
+pub struct GrokClient {
+    api_key: String,
+    model: String,
+}
+
+impl GrokClient {
+    pub fn new(api_key: String, model: String) -> Self {
+        Self { api_key, model }
+    }
+
+    pub fn has_key(&self) -> bool {
+        !self.api_key.is_empty()
+    }
+
+    fn extract_output_text(payload: &Value) -> Option<String> {
+        if let Some(output_text) = payload.get("output_text").and_then(Value::as_str) {
+            let output_text = output_text.trim();
+            if !output_text.is_empty() {
+                return Some(output_text.to_string());
+            }
+        }
+        let output = payload.get("output").and_then(Value::as_array)?;
+        for item in output {
+            if item.get("type").and_then(Value::as_str) != Some("message") {
+                continue;
+            }
+            let Some(content) = item.get("content").and_then(Value::as_array) else {
+                continue;
+            };
+            for block in content {
+                if block.get("type").and_then(Value::as_str) != Some("output_text") {
+                    continue;
+                }
+                if let Some(text) = block.get("text").and_then(Value::as_str) {
+                    let text = text.trim();
+                    if !text.is_empty() {
+                        return Some(text.to_string());
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    pub async fn search(&self, query: &str) -> Result<(String, Vec<String>)> {
+        let request = http()
+            .post(GROK_RESPONSES_ENDPOINT)
+            .header("Content-Type", "application/json")
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .json(&json!({
+                "model": self.model,
+                "input": [{ "role": "user", "content": query }],
+                "tools": [{ "type": "web_search" }],
+            }));
+        let payload = send_json("grok", request).await?;
+        let answer = Self::extract_output_text(&payload).unwrap_or_default();
+        Ok((answer, citations(&payload)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn collect_duckduckgo_related_topics_handles_nested_topics() {
+        let payload = json!([
+            {
+                "Text": "Rust - Programming language",
+                "FirstURL": "https://duckduckgo.com/Rust_(programming_language)"
+            },
+            {
+                "Name": "Nested",
+                "Topics": [
+                    {
+                        "Text": "Tokio - Async runtime",
+                        "FirstURL": "https://duckduckgo.com/Tokio"
+                    }
+                ]
+            }
+        ]);
+
+        let mut out = Vec::new();
+        collect_duckduckgo_related_topics(payload.as_array().expect("array"), &mut out);
+
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].0, "Rust");
+        assert!(out[0].1.contains("Rust_"));
+        assert_eq!(out[1].0, "Tokio");
+    }
+
+    #[test]
+    fn perplexity_request_model_strips_prefix_for_direct_base() {
+        let direct =
+            PerplexityClient::request_model("https://api.perplexity.ai", "perplexity/sonar-pro");
+        assert_eq!(direct, "sonar-pro");
+
+        let openrouter =
+            PerplexityClient::request_model("https://openrouter.ai/api/v1", "perplexity/sonar-pro");
+        assert_eq!(openrouter, "perplexity/sonar-pro");
+    }
+
+    #[test]
+    fn extract_grok_output_text_supports_output_array_shape() {
+        let payload = json!({
+            "output": [{
+                "type": "message",
+                "content": [{
+                    "type": "output_text",
+                    "text": "hello from grok"
+                }]
+            }]
+        });
+        let text = GrokClient::extract_output_text(&payload).expect("text");
+        assert_eq!(text, "hello from grok");
+    }
+
+    #[test]
+    fn rate_limit_headers_drive_shared_quota() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", HeaderValue::from_static("1, 15000"));
+        headers.insert(
+            "x-ratelimit-remaining",
+            HeaderValue::from_static("0, 14000"),
+        );
+        headers.insert("x-ratelimit-reset", HeaderValue::from_static("1, 1419704"));
+        let brave = parse_rate_limit(&headers).expect("brave headers");
+        assert_eq!(
+            brave,
+            RateLimit {
+                limit: Some(1),
+                remaining: 0,
+                reset: Duration::from_secs(1),
+            }
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-ratelimit-remaining-requests",
+            HeaderValue::from_static("4"),
+        );
+        headers.insert(
+            "x-ratelimit-reset-requests",
+            HeaderValue::from_static("1m30s"),
+        );
+        let openai_style = parse_rate_limit(&headers).expect("openai-style headers");
+        assert_eq!(openai_style.remaining, 4);
+        assert_eq!(openai_style.reset, Duration::from_secs(90));
+        assert_eq!(parse_reset("20ms"), Some(Duration::from_millis(20)));
+        assert!(parse_rate_limit(&HeaderMap::new()).is_none());
+
+        let now = Instant::now();
+        let mut quota = Quota::default();
+        assert!(quota.reserve(now).is_ok());
+        quota.observe(brave, now);
+        assert_eq!(quota.reserve(now), Err(Duration::from_secs(1)));
+        // Once the window resets one more request goes out, the next waits.
+        let later = now + Duration::from_secs(1);
+        assert!(quota.reserve(later).is_ok());
+        assert!(quota.reserve(later).is_err());
+
+        quota.block(Duration::from_secs(30), later);
+        assert_eq!(
+            quota.reserve(later + Duration::from_secs(10)),
+            Err(Duration::from_secs(20))
+        );
+    }
+}
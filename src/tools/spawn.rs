@@ -9,6 +9,7 @@ use std::sync::{Arc, Mutex};
 struct SpawnContext {
     origin_channel: String,
     origin_chat_id: String,
+    origin_thread: Option<String>,
 }
 
 pub struct SpawnTool {
@@ -23,6 +24,7 @@ impl SpawnTool {
             context: Mutex::new(SpawnContext {
                 origin_channel: "cli".to_string(),
                 origin_chat_id: "direct".to_string(),
+                origin_thread: None,
             }),
         }
     }
@@ -33,6 +35,12 @@ impl SpawnTool {
             guard.origin_chat_id = chat_id.into();
         }
     }
+
+    pub fn set_thread(&self, thread_id: Option<String>) {
+        if let Ok(mut guard) = self.context.lock() {
+            guard.origin_thread = thread_id;
+        }
+    }
 }
 
 #[async_trait]
@@ -46,14 +54,34 @@ impl Tool for SpawnTool {
     }
 
     fn parameters(&self) -> Value {
-        json!({
+        let mut params = json!({
             "type": "object",
             "properties": {
                 "task": { "type": "string", "description": "The task for the subagent to complete" },
                 "label": { "type": "string", "description": "Optional short label for the task" }
             },
             "required": ["task"]
-        })
+        });
+        let profiles = self.manager.profiles();
+        if !profiles.is_empty() {
+            let listing = profiles
+                .iter()
+                .map(|(name, description)| {
+                    if description.is_empty() {
+                        name.clone()
+                    } else {
+                        format!("{name} ({description})")
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("; ");
+            params["properties"]["profile"] = json!({
+                "type": "string",
+                "enum": profiles.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+                "description": format!("Optional subagent profile: {listing}"),
+            });
+        }
+        params
     }
 
     async fn execute(&self, params: &Map<String, Value>) -> Result<String> {
@@ -66,18 +94,30 @@ impl Tool for SpawnTool {
             .get("label")
             .and_then(Value::as_str)
             .map(ToOwned::to_owned);
+        let profile = params.get("profile").and_then(Value::as_str);
 
-        let (origin_channel, origin_chat_id) = {
+        let (origin_channel, origin_chat_id, origin_thread) = {
             let guard = self
                 .context
                 .lock()
                 .map_err(|_| anyhow!("failed to lock spawn context"))?;
-            (guard.origin_channel.clone(), guard.origin_chat_id.clone())
+            (
+                guard.origin_channel.clone(),
+                guard.origin_chat_id.clone(),
+                guard.origin_thread.clone(),
+            )
         };
 
         Ok(self
             .manager
-            .spawn(task, label, origin_channel, origin_chat_id)
+            .spawn(
+                task,
+                label,
+                profile,
+                origin_channel,
+                origin_chat_id,
+                origin_thread,
+            )
             .await)
     }
 }
@@ -0,0 +1,50 @@
+use crate::agent::status::{ActivityTracker, render_status};
+use crate::agent::subagent::SubagentManager;
+use crate::bus::MessageBus;
+use crate::tools::base::Tool;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::{Map, Value, json};
+use std::sync::Arc;
+
+pub struct AgentStatusTool {
+    tracker: ActivityTracker,
+    subagents: Arc<SubagentManager>,
+    bus: Arc<MessageBus>,
+}
+
+impl AgentStatusTool {
+    pub fn new(
+        tracker: ActivityTracker,
+        subagents: Arc<SubagentManager>,
+        bus: Arc<MessageBus>,
+    ) -> Self {
+        Self {
+            tracker,
+            subagents,
+            bus,
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for AgentStatusTool {
+    fn name(&self) -> &str {
+        "agent_status"
+    }
+
+    fn description(&self) -> &str {
+        "Report what the agent is doing right now: in-flight turns, running subagents, queued messages, and background tasks."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {}
+        })
+    }
+
+    async fn execute(&self, _params: &Map<String, Value>) -> Result<String> {
+        Ok(render_status(&self.tracker, &self.subagents, &self.bus).await)
+    }
+}
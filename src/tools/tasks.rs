@@ -0,0 +1,259 @@
+use crate::tasks::{TaskStore, format_due, parse_due_ms};
+use crate::tools::base::Tool;
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use serde_json::{Map, Value, json};
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct TaskContext {
+    channel: String,
+    chat_id: String,
+    is_owner: bool,
+}
+
+pub struct TaskTool {
+    store: TaskStore,
+    context: Mutex<TaskContext>,
+}
+
+impl TaskTool {
+    pub fn new(store: TaskStore) -> Self {
+        Self {
+            store,
+            context: Mutex::new(TaskContext::default()),
+        }
+    }
+
+    pub fn set_context(&self, channel: impl Into<String>, chat_id: impl Into<String>) {
+        if let Ok(mut guard) = self.context.lock() {
+            guard.channel = channel.into();
+            guard.chat_id = chat_id.into();
+        }
+    }
+
+    // Owners may list and change tasks from every chat; everyone else only
+    // reaches the current chat's.
+    pub fn set_owner(&self, is_owner: bool) {
+        if let Ok(mut guard) = self.context.lock() {
+            guard.is_owner = is_owner;
+        }
+    }
+
+    fn is_owner(&self) -> bool {
+        self.context
+            .lock()
+            .map(|guard| guard.is_owner)
+            .unwrap_or(false)
+    }
+
+    // The session a non-owner is confined to; None lets an owner reach all.
+    fn scope(&self) -> Result<Option<String>, String> {
+        if self.is_owner() {
+            return Ok(None);
+        }
+        self.session_key()
+            .map(Some)
+            .ok_or_else(|| "Error: no session context (channel/chat_id)".to_string())
+    }
+
+    fn session_key(&self) -> Option<String> {
+        let guard = self.context.lock().ok()?;
+        if guard.channel.is_empty() || guard.chat_id.is_empty() {
+            return None;
+        }
+        Some(format!("{}:{}", guard.channel, guard.chat_id))
+    }
+}
+
+#[async_trait]
+impl Tool for TaskTool {
+    fn name(&self) -> &str {
+        "tasks"
+    }
+
+    fn description(&self) -> &str {
+        "Track the user's action items and commitments. Actions: add, list, complete, remove."
+    }
+
+    fn parameters(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "action": { "type": "string", "enum": ["add", "list", "complete", "remove"] },
+                "title": { "type": "string" },
+                "due": { "type": "string", "description": "YYYY-MM-DD, YYYY-MM-DD HH:MM, or ISO datetime" },
+                "task_id": { "type": "string" },
+                "include_done": { "type": "boolean" },
+                "all_sessions": { "type": "boolean", "description": "Owner only: list tasks from every chat" }
+            },
+            "required": ["action"]
+        })
+    }
+
+    async fn execute(&self, params: &Map<String, Value>) -> Result<String> {
+        let action = params
+            .get("action")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("missing required string field: action"))?;
+
+        match action {
+            "add" => {
+                let title = params
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .map(str::trim)
+                    .unwrap_or_default();
+                if title.is_empty() {
+                    return Ok("Error: title is required for add".to_string());
+                }
+                let due_at_ms = match params.get("due").and_then(Value::as_str) {
+                    Some(raw) if !raw.trim().is_empty() => match parse_due_ms(raw) {
+                        Some(ms) => Some(ms),
+                        None => return Ok(format!("Error: invalid due date '{raw}'")),
+                    },
+                    _ => None,
+                };
+                let task = self
+                    .store
+                    .add(title, due_at_ms, self.session_key(), "manual")?;
+                Ok(format!(
+                    "Tracked task '{}' (id: {}, due: {})",
+                    task.title,
+                    task.id,
+                    format_due(task.due_at_ms)
+                ))
+            }
+            "list" => {
+                let include_done = params
+                    .get("include_done")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+                let all_sessions = params
+                    .get("all_sessions")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(false);
+                let scope = if all_sessions && self.is_owner() {
+                    None
+                } else {
+                    match self.session_key() {
+                        Some(session) => Some(session),
+                        None => {
+                            return Ok("Error: no session context (channel/chat_id)".to_string());
+                        }
+                    }
+                };
+                let tasks = self
+                    .store
+                    .list(include_done)?
+                    .into_iter()
+                    .filter(|task| task.in_scope(scope.as_deref()))
+                    .collect::<Vec<_>>();
+                if tasks.is_empty() {
+                    return Ok("No tasks.".to_string());
+                }
+                let lines = tasks
+                    .iter()
+                    .map(|task| {
+                        let session = match (&scope, &task.session_key) {
+                            (None, Some(session)) => format!(", from {session}"),
+                            _ => String::new(),
+                        };
+                        format!(
+                            "- [{}] {} (id: {}, due: {}, {}{session})",
+                            if task.status == "done" { "x" } else { " " },
+                            task.title,
+                            task.id,
+                            format_due(task.due_at_ms),
+                            task.source
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                Ok(format!("Tasks:\n{}", lines.join("\n")))
+            }
+            "complete" => {
+                let Some(task_id) = params.get("task_id").and_then(Value::as_str) else {
+                    return Ok("Error: task_id is required for complete".to_string());
+                };
+                let scope = match self.scope() {
+                    Ok(scope) => scope,
+                    Err(err) => return Ok(err),
+                };
+                // Another chat's task reads as missing rather than forbidden.
+                match self.store.complete(task_id, scope.as_deref())? {
+                    Some(task) => Ok(format!("Completed task '{}'", task.title)),
+                    None => Ok(format!("Task {task_id} not found")),
+                }
+            }
+            "remove" => {
+                let Some(task_id) = params.get("task_id").and_then(Value::as_str) else {
+                    return Ok("Error: task_id is required for remove".to_string());
+                };
+                let scope = match self.scope() {
+                    Ok(scope) => scope,
+                    Err(err) => return Ok(err),
+                };
+                if self.store.remove(task_id, scope.as_deref())? {
+                    Ok(format!("Removed task {task_id}"))
+                } else {
+                    Ok(format!("Task {task_id} not found"))
+                }
+            }
+            _ => Ok(format!("Unknown action: {action}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(value: Value) -> Map<String, Value> {
+        value.as_object().cloned().unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn chats_cannot_reach_each_others_tasks() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "nanobot-rs-task-tool-{}.json",
+            uuid::Uuid::new_v4()
+        ));
+        let tool = TaskTool::new(TaskStore::from_path(path.clone()));
+        tool.set_context("telegram", "A");
+        let added = tool
+            .execute(&params(json!({"action": "add", "title": "Renew lease"})))
+            .await?;
+        let id = added
+            .split("id: ")
+            .nth(1)
+            .and_then(|rest| rest.split(',').next())
+            .expect("task id")
+            .to_string();
+
+        tool.set_context("telegram", "B");
+        let listed = tool.execute(&params(json!({"action": "list"}))).await?;
+        assert_eq!(listed, "No tasks.");
+        let listed = tool
+            .execute(&params(json!({"action": "list", "all_sessions": true})))
+            .await?;
+        assert_eq!(listed, "No tasks.");
+        for action in ["complete", "remove"] {
+            let out = tool
+                .execute(&params(json!({"action": action, "task_id": id})))
+                .await?;
+            assert_eq!(out, format!("Task {id} not found"));
+        }
+
+        tool.set_owner(true);
+        let listed = tool
+            .execute(&params(json!({"action": "list", "all_sessions": true})))
+            .await?;
+        assert!(listed.contains("Renew lease") && listed.contains("from telegram:A"));
+        let out = tool
+            .execute(&params(json!({"action": "complete", "task_id": id})))
+            .await?;
+        assert_eq!(out, "Completed task 'Renew lease'");
+        let _ = std::fs::remove_file(path);
+        Ok(())
+    }
+}
@@ -1,20 +1,20 @@
 use crate::config::WebSearchConfig;
 use crate::tools::base::Tool;
+use crate::tools::search::{
+    BraveClient, DuckDuckGoClient, GrokClient, PerplexityClient, SearchHit,
+};
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use regex::Regex;
-use reqwest::header::{ACCEPT, USER_AGENT};
+use reqwest::header::USER_AGENT;
 use serde_json::{Map, Value, json};
 use url::Url;
 
 const DEFAULT_USER_AGENT: &str =
     "Mozilla/5.0 (Macintosh; Intel Mac OS X 14_7_2) AppleWebKit/537.36";
-const BRAVE_SEARCH_ENDPOINT: &str = "https://api.search.brave.com/res/v1/web/search";
-const DUCKDUCKGO_INSTANT_ENDPOINT: &str = "https://api.duckduckgo.com/";
 const PERPLEXITY_DIRECT_BASE_URL: &str = "https://api.perplexity.ai";
 const PERPLEXITY_OPENROUTER_BASE_URL: &str = "https://openrouter.ai/api/v1";
 const DEFAULT_PERPLEXITY_MODEL: &str = "perplexity/sonar-pro";
-const GROK_RESPONSES_ENDPOINT: &str = "https://api.x.ai/v1/responses";
 const DEFAULT_GROK_MODEL: &str = "grok-4-1-fast";
 
 fn strip_tags(text: &str) -> String {
@@ -57,64 +57,13 @@ enum WebSearchProvider {
 
 pub struct WebSearchTool {
     provider: WebSearchProvider,
-    brave_api_key: String,
-    perplexity_api_key: String,
-    perplexity_base_url: String,
-    perplexity_model: String,
-    grok_api_key: String,
-    grok_model: String,
+    brave: Option<BraveClient>,
+    perplexity: PerplexityClient,
+    grok: GrokClient,
     grok_inline_citations: bool,
     max_results: usize,
 }
 
-fn push_duckduckgo_result(
-    output: &mut Vec<(String, String, String)>,
-    title: &str,
-    url: &str,
-    snippet: &str,
-) {
-    let title = title.trim();
-    let url = url.trim();
-    let snippet = snippet.trim();
-    if title.is_empty() || url.is_empty() {
-        return;
-    }
-    if output
-        .iter()
-        .any(|(_, existing_url, _)| existing_url == url)
-    {
-        return;
-    }
-    output.push((title.to_string(), url.to_string(), snippet.to_string()));
-}
-
-fn collect_duckduckgo_related_topics(topics: &[Value], output: &mut Vec<(String, String, String)>) {
-    for topic in topics {
-        if let Some(nested) = topic.get("Topics").and_then(Value::as_array) {
-            collect_duckduckgo_related_topics(nested, output);
-            continue;
-        }
-
-        let text = topic
-            .get("Text")
-            .and_then(Value::as_str)
-            .map(str::trim)
-            .unwrap_or_default();
-        let url = topic
-            .get("FirstURL")
-            .and_then(Value::as_str)
-            .map(str::trim)
-            .unwrap_or_default();
-
-        if text.is_empty() || url.is_empty() {
-            continue;
-        }
-
-        let title = text.split(" - ").next().unwrap_or(text);
-        push_duckduckgo_result(output, title, url, text);
-    }
-}
-
 impl WebSearchTool {
     fn normalize_secret(secret: impl AsRef<str>) -> String {
         secret.as_ref().trim().to_string()
@@ -167,21 +116,6 @@ impl WebSearchTool {
             .unwrap_or_else(|| DEFAULT_PERPLEXITY_MODEL.to_string())
     }
 
-    fn resolve_perplexity_request_model(base_url: &str, model: &str) -> String {
-        let parsed = Url::parse(base_url).ok();
-        let host = parsed
-            .and_then(|u| u.host_str().map(|s| s.to_ascii_lowercase()))
-            .unwrap_or_default();
-        if host == "api.perplexity.ai" {
-            model
-                .strip_prefix("perplexity/")
-                .unwrap_or(model)
-                .to_string()
-        } else {
-            model.to_string()
-        }
-    }
-
     fn resolve_grok_api_key(config: &WebSearchConfig) -> String {
         let from_config = Self::normalize_secret(&config.grok.api_key);
         if !from_config.is_empty() {
@@ -215,12 +149,13 @@ impl WebSearchTool {
 
         Self {
             provider: Self::resolve_provider(&config.provider),
-            brave_api_key,
-            perplexity_api_key,
-            perplexity_base_url,
-            perplexity_model,
-            grok_api_key,
-            grok_model,
+            brave: (!brave_api_key.is_empty()).then(|| BraveClient::new(brave_api_key)),
+            perplexity: PerplexityClient::new(
+                perplexity_api_key,
+                perplexity_base_url,
+                perplexity_model,
+            ),
+            grok: GrokClient::new(grok_api_key, grok_model),
             grok_inline_citations: config.grok.inline_citations,
             max_results: config.max_results.clamp(1, 10),
         }
@@ -259,7 +194,7 @@ impl WebSearchTool {
     fn format_results(
         query: &str,
         provider_name: &str,
-        results: &[(String, String, String)],
+        results: &[SearchHit],
         limit: usize,
     ) -> String {
         if results.is_empty() {
@@ -275,220 +210,6 @@ impl WebSearchTool {
         }
         lines.join("\n")
     }
-
-    async fn search_brave(&self, query: &str, n: u64) -> Result<Vec<(String, String, String)>> {
-        let client = reqwest::Client::new();
-        let response = client
-            .get(BRAVE_SEARCH_ENDPOINT)
-            .query(&[("q", query), ("count", &n.to_string())])
-            .header(ACCEPT, "application/json")
-            .header("X-Subscription-Token", &self.brave_api_key)
-            .send()
-            .await?;
-        let response = response.error_for_status()?;
-        let payload: Value = response.json().await?;
-        let results = payload
-            .get("web")
-            .and_then(|v| v.get("results"))
-            .and_then(Value::as_array)
-            .cloned()
-            .unwrap_or_default();
-
-        let mut out = Vec::new();
-        for item in results {
-            let title = item
-                .get("title")
-                .and_then(Value::as_str)
-                .unwrap_or_default()
-                .trim();
-            let url = item
-                .get("url")
-                .and_then(Value::as_str)
-                .unwrap_or_default()
-                .trim();
-            let desc = item
-                .get("description")
-                .and_then(Value::as_str)
-                .unwrap_or_default()
-                .trim();
-            if !title.is_empty() && !url.is_empty() {
-                out.push((title.to_string(), url.to_string(), desc.to_string()));
-            }
-        }
-        Ok(out)
-    }
-
-    async fn search_duckduckgo(
-        &self,
-        query: &str,
-        n: u64,
-    ) -> Result<Vec<(String, String, String)>> {
-        let client = reqwest::Client::new();
-        let response = client
-            .get(DUCKDUCKGO_INSTANT_ENDPOINT)
-            .query(&[
-                ("q", query),
-                ("format", "json"),
-                ("no_html", "1"),
-                ("skip_disambig", "1"),
-                ("no_redirect", "1"),
-            ])
-            .header(ACCEPT, "application/json")
-            .header(USER_AGENT, DEFAULT_USER_AGENT)
-            .send()
-            .await?;
-        let response = response.error_for_status()?;
-        let payload: Value = response.json().await?;
-
-        let mut out = Vec::new();
-        let abstract_text = payload
-            .get("AbstractText")
-            .and_then(Value::as_str)
-            .map(str::trim)
-            .unwrap_or_default();
-        let abstract_url = payload
-            .get("AbstractURL")
-            .and_then(Value::as_str)
-            .map(str::trim)
-            .unwrap_or_default();
-        let heading = payload
-            .get("Heading")
-            .and_then(Value::as_str)
-            .map(str::trim)
-            .unwrap_or_default();
-        if !abstract_text.is_empty() && !abstract_url.is_empty() {
-            let title = if heading.is_empty() {
-                abstract_text
-                    .split('.')
-                    .next()
-                    .unwrap_or(abstract_text)
-                    .trim()
-            } else {
-                heading
-            };
-            push_duckduckgo_result(&mut out, title, abstract_url, abstract_text);
-        }
-
-        if let Some(topics) = payload.get("RelatedTopics").and_then(Value::as_array) {
-            collect_duckduckgo_related_topics(topics, &mut out);
-        }
-
-        out.truncate(n as usize);
-        Ok(out)
-    }
-
-    async fn search_perplexity(&self, query: &str) -> Result<(String, Vec<String>)> {
-        let client = reqwest::Client::new();
-        let endpoint = format!(
-            "{}/chat/completions",
-            self.perplexity_base_url.trim_end_matches('/')
-        );
-        let model = Self::resolve_perplexity_request_model(
-            &self.perplexity_base_url,
-            &self.perplexity_model,
-        );
-        let response = client
-            .post(endpoint)
-            .header("Content-Type", "application/json")
-            .header(
-                "Authorization",
-                format!("Bearer {}", self.perplexity_api_key),
-            )
-            .header("HTTP-Referer", "https://github.com/open-vibe/nanobot-rs")
-            .header("X-Title", "nanobot-rs web_search")
-            .json(&json!({
-                "model": model,
-                "messages": [{ "role": "user", "content": query }],
-            }))
-            .send()
-            .await?;
-        let response = response.error_for_status()?;
-        let payload: Value = response.json().await?;
-        let answer = payload
-            .get("choices")
-            .and_then(Value::as_array)
-            .and_then(|arr| arr.first())
-            .and_then(|v| v.get("message"))
-            .and_then(|v| v.get("content"))
-            .and_then(Value::as_str)
-            .unwrap_or_default()
-            .trim()
-            .to_string();
-        let citations = payload
-            .get("citations")
-            .and_then(Value::as_array)
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(Value::as_str)
-                    .map(str::trim)
-                    .filter(|v| !v.is_empty())
-                    .map(ToOwned::to_owned)
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default();
-        Ok((answer, citations))
-    }
-
-    fn extract_grok_output_text(payload: &Value) -> Option<String> {
-        if let Some(output_text) = payload.get("output_text").and_then(Value::as_str) {
-            let output_text = output_text.trim();
-            if !output_text.is_empty() {
-                return Some(output_text.to_string());
-            }
-        }
-        let output = payload.get("output").and_then(Value::as_array)?;
-        for item in output {
-            if item.get("type").and_then(Value::as_str) != Some("message") {
-                continue;
-            }
-            let Some(content) = item.get("content").and_then(Value::as_array) else {
-                continue;
-            };
-            for block in content {
-                if block.get("type").and_then(Value::as_str) != Some("output_text") {
-                    continue;
-                }
-                if let Some(text) = block.get("text").and_then(Value::as_str) {
-                    let text = text.trim();
-                    if !text.is_empty() {
-                        return Some(text.to_string());
-                    }
-                }
-            }
-        }
-        None
-    }
-
-    async fn search_grok(&self, query: &str) -> Result<(String, Vec<String>)> {
-        let client = reqwest::Client::new();
-        let response = client
-            .post(GROK_RESPONSES_ENDPOINT)
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", self.grok_api_key))
-            .json(&json!({
-                "model": self.grok_model,
-                "input": [{ "role": "user", "content": query }],
-                "tools": [{ "type": "web_search" }],
-            }))
-            .send()
-            .await?;
-        let response = response.error_for_status()?;
-        let payload: Value = response.json().await?;
-        let answer = Self::extract_grok_output_text(&payload).unwrap_or_default();
-        let citations = payload
-            .get("citations")
-            .and_then(Value::as_array)
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(Value::as_str)
-                    .map(str::trim)
-                    .filter(|v| !v.is_empty())
-                    .map(ToOwned::to_owned)
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default();
-        Ok((answer, citations))
-    }
 }
 
 #[async_trait]
@@ -501,6 +222,10 @@ impl Tool for WebSearchTool {
         "Search the web. Returns titles, URLs, and snippets."
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     fn parameters(&self) -> Value {
         json!({
             "type": "object",
@@ -524,8 +249,8 @@ impl Tool for WebSearchTool {
         let n = count.clamp(1, 10);
         let note = match self.provider {
             WebSearchProvider::Brave => {
-                if !self.brave_api_key.is_empty() {
-                    match self.search_brave(query, n).await {
+                if let Some(brave) = &self.brave {
+                    match brave.search(query, n).await {
                         Ok(results) if !results.is_empty() => {
                             return Ok(Self::format_results(query, "Brave", &results, n as usize));
                         }
@@ -545,13 +270,13 @@ impl Tool for WebSearchTool {
                 }
             }
             WebSearchProvider::Perplexity => {
-                if self.perplexity_api_key.is_empty() {
+                if !self.perplexity.has_key() {
                     Some(
                         "Perplexity API key not configured, using keyless DuckDuckGo fallback."
                             .to_string(),
                     )
                 } else {
-                    match self.search_perplexity(query).await {
+                    match self.perplexity.search(query).await {
                         Ok((answer, citations)) if !answer.trim().is_empty() => {
                             return Ok(Self::format_search_answer(
                                 query,
@@ -572,13 +297,13 @@ impl Tool for WebSearchTool {
                 }
             }
             WebSearchProvider::Grok => {
-                if self.grok_api_key.is_empty() {
+                if !self.grok.has_key() {
                     Some(
                         "XAI_API_KEY not configured, using keyless DuckDuckGo fallback."
                             .to_string(),
                     )
                 } else {
-                    match self.search_grok(query).await {
+                    match self.grok.search(query).await {
                         Ok((answer, citations)) if !answer.trim().is_empty() => {
                             return Ok(Self::format_search_answer(
                                 query,
@@ -600,7 +325,7 @@ impl Tool for WebSearchTool {
             }
         };
 
-        match self.search_duckduckgo(query, n).await {
+        match DuckDuckGoClient.search(query, n).await {
             Ok(results) => {
                 let content =
                     Self::format_results(query, "DuckDuckGo fallback", &results, n as usize);
@@ -655,6 +380,10 @@ impl Tool for WebFetchTool {
         "Fetch URL and extract readable content (HTML -> markdown/text)."
     }
 
+    fn read_only(&self) -> bool {
+        true
+    }
+
     fn parameters(&self) -> Value {
         json!({
             "type": "object",
@@ -750,35 +479,7 @@ impl Tool for WebFetchTool {
 
 #[cfg(test)]
 mod tests {
-    use super::{WebSearchProvider, WebSearchTool, collect_duckduckgo_related_topics};
-    use serde_json::json;
-
-    #[test]
-    fn collect_duckduckgo_related_topics_handles_nested_topics() {
-        let payload = json!([
-            {
-                "Text": "Rust - Programming language",
-                "FirstURL": "https://duckduckgo.com/Rust_(programming_language)"
-            },
-            {
-                "Name": "Nested",
-                "Topics": [
-                    {
-                        "Text": "Tokio - Async runtime",
-                        "FirstURL": "https://duckduckgo.com/Tokio"
-                    }
-                ]
-            }
-        ]);
-
-        let mut out = Vec::new();
-        collect_duckduckgo_related_topics(payload.as_array().expect("array"), &mut out);
-
-        assert_eq!(out.len(), 2);
-        assert_eq!(out[0].0, "Rust");
-        assert!(out[0].1.contains("Rust_"));
-        assert_eq!(out[1].0, "Tokio");
-    }
+    use super::{WebSearchProvider, WebSearchTool};
 
     #[test]
     fn resolve_provider_defaults_to_brave() {
@@ -795,34 +496,4 @@ mod tests {
             WebSearchProvider::Grok
         ));
     }
-
-    #[test]
-    fn resolve_perplexity_request_model_strips_prefix_for_direct_base() {
-        let direct = WebSearchTool::resolve_perplexity_request_model(
-            "https://api.perplexity.ai",
-            "perplexity/sonar-pro",
-        );
-        assert_eq!(direct, "sonar-pro");
-
-        let openrouter = WebSearchTool::resolve_perplexity_request_model(
-            "https://openrouter.ai/api/v1",
-            "perplexity/sonar-pro",
-        );
-        assert_eq!(openrouter, "perplexity/sonar-pro");
-    }
-
-    #[test]
-    fn extract_grok_output_text_supports_output_array_shape() {
-        let payload = json!({
-            "output": [{
-                "type": "message",
-                "content": [{
-                    "type": "output_text",
-                    "text": "hello from grok"
-                }]
-            }]
-        });
-        let text = WebSearchTool::extract_grok_output_text(&payload).expect("text");
-        assert_eq!(text, "hello from grok");
-    }
 }
@@ -14,10 +14,13 @@ const I18N = {
     section_cron: "$ cron.jobs",
     section_sessions: "$ sessions.list",
     section_pairing: "$ pairing.pending",
+    section_events: "$ events.recent",
+    section_abuse: "$ abuse.incidents",
     loading_text: "loading...",
     tip_doctor: "tips: nanobot-rs doctor --fix",
     tip_pairing: "tips: nanobot-rs pairing list",
     tip_cron: "tips: nanobot-rs cron list --all",
+    tip_events: "tips: nanobot-rs events --level warn --since 1d",
     empty: "(empty)",
     none: "none",
     unknown: "unknown",
@@ -28,6 +31,7 @@ const I18N = {
     key_sessions: "sessions",
     key_cron_jobs: "cron_jobs",
     key_pairing_pending: "pairing_pending",
+    key_abuse_incidents: "abuse_incidents",
     key_channel: "channel",
     key_next: "next",
     key_code: "code",
@@ -49,10 +53,14 @@ const I18N = {
     hint_run_doctor_fix: "Run `nanobot-rs doctor --fix`.",
     chat_session_placeholder: "session key (e.g. webui:default)",
     chat_input_placeholder: "type message and press Enter...",
+    chat_advanced: "advanced options",
+    chat_model_placeholder: "model for this message (default: agent model)",
+    chat_temperature_placeholder: "temperature",
     role_user: "[YOU]",
     role_assistant: "[BOT]",
     role_error: "[ERR]",
     chat_error_prefix: "chat failed",
+    chat_thinking: "thinking",
   },
   zh: {
     ui_title: "nanobot-rs 控制面板",
@@ -69,10 +77,13 @@ const I18N = {
     section_cron: "$ 定时任务",
     section_sessions: "$ 会话列表",
     section_pairing: "$ 待配对请求",
+    section_events: "$ 最近事件",
+    section_abuse: "$ 滥用事件",
     loading_text: "加载中...",
     tip_doctor: "提示: nanobot-rs doctor --fix",
     tip_pairing: "提示: nanobot-rs pairing list",
     tip_cron: "提示: nanobot-rs cron list --all",
+    tip_events: "提示: nanobot-rs events --level warn --since 1d",
     empty: "(空)",
     none: "无",
     unknown: "未知",
@@ -83,6 +94,7 @@ const I18N = {
     key_sessions: "会话",
     key_cron_jobs: "定时任务",
     key_pairing_pending: "待配对",
+    key_abuse_incidents: "滥用事件",
     key_channel: "渠道",
     key_next: "下次",
     key_code: "验证码",
@@ -104,10 +116,14 @@ const I18N = {
     hint_run_doctor_fix: "运行 `nanobot-rs doctor --fix`。",
     chat_session_placeholder: "会话键（例如 webui:default）",
     chat_input_placeholder: "输入消息后回车发送...",
+    chat_advanced: "高级选项",
+    chat_model_placeholder: "本条消息使用的模型（默认：agent 模型）",
+    chat_temperature_placeholder: "温度",
     role_user: "[你]",
     role_assistant: "[助手]",
     role_error: "[错误]",
     chat_error_prefix: "对话失败",
+    chat_thinking: "思考中",
   },
 };
 
@@ -203,6 +219,20 @@ async function fetchState() {
   return response.json();
 }
 
+// Advanced options apply to a single message; empty fields keep the defaults.
+function advancedOptions() {
+  const model = document.getElementById("chat-model")?.value.trim() || "";
+  const temperature = document.getElementById("chat-temperature")?.value.trim() || "";
+  const options = {};
+  if (model) {
+    options.model = model;
+  }
+  if (temperature) {
+    options.sampling = { temperature: Number(temperature) };
+  }
+  return options;
+}
+
 async function postChat(message, session) {
   const response = await fetch("/api/chat", {
     method: "POST",
@@ -210,6 +240,7 @@ async function postChat(message, session) {
     body: JSON.stringify({
       message,
       session: session || "webui:default",
+      ...advancedOptions(),
     }),
   });
   const payload = await response.json().catch(() => ({}));
@@ -219,6 +250,46 @@ async function postChat(message, session) {
   return payload.response || "";
 }
 
+async function fetchActivity() {
+  const response = await fetch("/api/activity", { cache: "no-store" });
+  if (!response.ok) {
+    return [];
+  }
+  const payload = await response.json().catch(() => ({}));
+  return payload.active || [];
+}
+
+const SPINNER_FRAMES = ["|", "/", "-", "\\"];
+
+// Shows a spinner line while the agent reports a turn in progress for the
+// session; returns a function that removes it.
+function startSpinner(session) {
+  const container = document.getElementById("chat-log");
+  const line = document.createElement("div");
+  line.className = "chat-line pending";
+  let frame = 0;
+  let active = false;
+  const timer = setInterval(async () => {
+    frame = (frame + 1) % SPINNER_FRAMES.length;
+    if (frame === 0) {
+      active = (await fetchActivity().catch(() => [])).includes(session);
+    }
+    if (!container || !active) {
+      line.remove();
+      return;
+    }
+    line.textContent = `${SPINNER_FRAMES[frame]} ${t("chat_thinking")}`;
+    if (!line.isConnected) {
+      container.appendChild(line);
+      container.scrollTop = container.scrollHeight;
+    }
+  }, 150);
+  return () => {
+    clearInterval(timer);
+    line.remove();
+  };
+}
+
 function renderList(container, items, mapItem) {
   if (!container) {
     return;
@@ -261,6 +332,9 @@ function levelToClass(level) {
   if (lower === "warn") {
     return "warn";
   }
+  if (lower === "error") {
+    return "fail";
+  }
   return "";
 }
 
@@ -295,6 +369,7 @@ function renderState(state) {
       `${t("key_sessions")}=${(state.sessions || []).length}`,
       `${t("key_cron_jobs")}=${(state.cronJobs || []).length}`,
       `${t("key_pairing_pending")}=${(state.pairingPending || []).length}`,
+      `${t("key_abuse_incidents")}=${(state.abuseIncidents || []).length}`,
     ].join("\n")
   );
 
@@ -332,6 +407,22 @@ function renderState(state) {
     buildItem(key, t("tag_session"))
   );
 
+  renderList(document.getElementById("events-list"), state.events || [], (event) =>
+    buildItem(
+      `${new Date(event.atMs).toLocaleString()} :: ${event.kind}${event.channel ? ` [${event.channel}]` : ""}`,
+      event.message,
+      levelToClass(event.level)
+    )
+  );
+
+  renderList(document.getElementById("abuse-list"), state.abuseIncidents || [], (incident) =>
+    buildItem(
+      `${new Date(incident.atMs).toLocaleString()} :: ${incident.channel}:${incident.senderId} ${incident.kind}`,
+      `${incident.detail}${incident.escalation ? ` -> ${incident.escalation}` : ""}`,
+      incident.escalation ? "warn" : ""
+    )
+  );
+
   renderList(document.getElementById("pairing-list"), state.pairingPending || [], (entry) =>
     buildItem(
       `${entry.channel}:${entry.sender_id || entry.senderId}`,
@@ -364,10 +455,13 @@ async function sendChatMessage() {
   appendChatLine("user", message);
   input.value = "";
   sendButton.disabled = true;
+  const stopSpinner = startSpinner(session);
   try {
     const reply = await postChat(message, session);
+    stopSpinner();
     appendChatLine("assistant", reply);
   } catch (err) {
+    stopSpinner();
     appendChatLine("error", `${t("chat_error_prefix")}: ${String(err)}`);
   } finally {
     sendButton.disabled = false;
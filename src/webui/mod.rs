@@ -1,19 +1,24 @@
 use crate::VERSION;
+use crate::abuse::recent_incidents;
 use crate::agent::AgentLoop;
 use crate::config::{load_config, providers_status};
+use crate::events::{EventFilter, EventLog};
 use crate::health::collect_health;
 use crate::pairing::list_pending;
 use crate::providers::base::LLMProvider;
 use crate::providers::litellm::LiteLLMProvider;
 use crate::session::SessionManager;
+use crate::storage::locate;
 use crate::utils::get_data_path;
 use anyhow::Result;
 use chrono::Local;
 use serde::Deserialize;
-use serde_json::{Value, json};
-use std::sync::Arc;
+use serde_json::{Map, Value, json};
+use std::collections::BTreeSet;
 use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
 use tiny_http::{Header, Method, Request, Response, Server, StatusCode};
+use tokio::sync::broadcast;
 
 const INDEX_HTML: &str = include_str!("index.html");
 const APP_CSS: &str = include_str!("app.css");
@@ -26,6 +31,86 @@ struct ChatPayload {
     session: Option<String>,
     channel: Option<String>,
     chat_id: Option<String>,
+    // Per-turn overrides, checked by the agent against the model registry.
+    model: Option<String>,
+    sampling: Option<Value>,
+}
+
+impl ChatPayload {
+    fn metadata(&self) -> Map<String, Value> {
+        let mut metadata = Map::new();
+        if let Some(model) = self
+            .model
+            .as_deref()
+            .map(str::trim)
+            .filter(|m| !m.is_empty())
+        {
+            metadata.insert("model".to_string(), Value::String(model.to_string()));
+        }
+        if let Some(sampling) = &self.sampling {
+            metadata.insert("sampling".to_string(), sampling.clone());
+        }
+        metadata
+    }
+}
+
+// OpenAI-style `POST /v1/chat/completions` body. Only the last user message
+// is sent: the agent keeps its own history per session, keyed by `user`.
+#[derive(Debug, Deserialize)]
+struct CompletionPayload {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    messages: Vec<Value>,
+    temperature: Option<f64>,
+    max_tokens: Option<u64>,
+    top_p: Option<f64>,
+    user: Option<String>,
+}
+
+impl CompletionPayload {
+    fn last_user_message(&self) -> Option<String> {
+        let message = self
+            .messages
+            .iter()
+            .rev()
+            .find(|m| m.get("role").and_then(Value::as_str) == Some("user"))?;
+        let text = match message.get("content")? {
+            Value::String(text) => text.clone(),
+            Value::Array(parts) => parts
+                .iter()
+                .filter_map(|part| part.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => return None,
+        };
+        Some(text).filter(|text| !text.trim().is_empty())
+    }
+
+    // "default" and "nanobot" keep the agent's configured model.
+    fn chat_payload(self) -> Option<ChatPayload> {
+        let message = self.last_user_message()?;
+        let model = Some(self.model).filter(|m| !m.is_empty() && m != "default" && m != "nanobot");
+        let mut sampling = Map::new();
+        if let Some(t) = self.temperature {
+            sampling.insert("temperature".to_string(), json!(t));
+        }
+        if let Some(n) = self.max_tokens {
+            sampling.insert("maxTokens".to_string(), json!(n));
+        }
+        if let Some(p) = self.top_p {
+            sampling.insert("topP".to_string(), json!(p));
+        }
+        let session = format!("api:{}", self.user.as_deref().unwrap_or("default"));
+        Some(ChatPayload {
+            message,
+            session: Some(session),
+            channel: None,
+            chat_id: None,
+            model,
+            sampling: (!sampling.is_empty()).then_some(Value::Object(sampling)),
+        })
+    }
 }
 
 struct ChatRequest {
@@ -33,16 +118,46 @@ struct ChatRequest {
     session: Option<String>,
     channel: Option<String>,
     chat_id: Option<String>,
+    metadata: Map<String, Value>,
     reply_tx: mpsc::Sender<Result<String>>,
 }
 
+// "channel:chat_id" of turns the agent is working on, from its activity
+// signals; the page shows a spinner for them.
+type ActiveTurns = Arc<Mutex<BTreeSet<String>>>;
+
 struct ChatWorker {
     tx: mpsc::Sender<ChatRequest>,
+    active: ActiveTurns,
+}
+
+fn track_activity(
+    mut activity: broadcast::Receiver<crate::bus::TurnActivity>,
+    active: ActiveTurns,
+) {
+    std::thread::spawn(move || {
+        loop {
+            let signal = match activity.blocking_recv() {
+                Ok(signal) => signal,
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            };
+            let key = format!("{}:{}", signal.channel, signal.chat_id);
+            let mut active = active.lock().unwrap_or_else(|e| e.into_inner());
+            if signal.active {
+                active.insert(key);
+            } else {
+                active.remove(&key);
+            }
+        }
+    });
 }
 
 impl ChatWorker {
     fn new() -> Self {
         let (tx, rx) = mpsc::channel::<ChatRequest>();
+        let active = ActiveTurns::default();
+        let active_for_worker = active.clone();
         std::thread::spawn(move || {
             let config = load_config(None).unwrap_or_default();
             let model = config.agents.defaults.model.clone();
@@ -50,8 +165,9 @@ impl ChatWorker {
             let is_bedrock = normalized_model.starts_with("bedrock/");
             let api_key = config.get_api_key(Some(&model));
             if api_key.is_none() && !is_bedrock {
-                let err = "No API key configured. Set providers.*.apiKey in ~/.nanobot/config.json."
-                    .to_string();
+                let err =
+                    "No API key configured. Set providers.*.apiKey in ~/.nanobot/config.json."
+                        .to_string();
                 while let Ok(req) = rx.recv() {
                     let _ = req.reply_tx.send(Err(anyhow::anyhow!(err.clone())));
                 }
@@ -74,6 +190,10 @@ impl ChatWorker {
             };
 
             let bus = Arc::new(crate::bus::MessageBus::new(1024));
+            track_activity(bus.subscribe_activity(), active_for_worker);
+            if let Some(hooks) = crate::hooks::ScriptHooks::from_config(&config) {
+                bus.set_script_hooks(hooks);
+            }
             let provider = build_provider(
                 &config,
                 &model,
@@ -83,9 +203,9 @@ impl ChatWorker {
                 Ok(m) => Arc::new(m),
                 Err(err) => {
                     while let Ok(req) = rx.recv() {
-                        let _ = req
-                            .reply_tx
-                            .send(Err(anyhow::anyhow!("failed to init session manager: {err}")));
+                        let _ = req.reply_tx.send(Err(anyhow::anyhow!(
+                            "failed to init session manager: {err}"
+                        )));
                     }
                     return;
                 }
@@ -102,7 +222,27 @@ impl ChatWorker {
                 config.tools.restrict_to_workspace,
                 None,
                 Some(session_manager),
-            ) {
+            )
+            .map(|agent| {
+                agent
+                    .with_read_only_channels(config.channels.read_only_channels())
+                    .with_action_item_extraction(config.agents.defaults.extract_action_items)
+                    .with_sampling(config.agents.defaults.sampling())
+                    .with_maintenance_sampling(config.agents.defaults.maintenance_sampling())
+                    .with_provider_factory(LiteLLMProvider::factory(config.clone()))
+                    .with_context_sections(config.agents.defaults.context_sections.clone())
+                    .with_personas(config.agents.defaults.personas.clone())
+                    .with_model_overrides(config.models.clone())
+                    .with_subagent_profiles(config.agents.subagents.clone())
+                    .with_disabled_tools(&config.tools.disabled)
+                    .with_owners(config.channels.owners.clone())
+                    .with_citations(
+                        config.agents.defaults.citations,
+                        config.channels.citation_overrides(),
+                    )
+                    .with_structured_commands(config.agents.defaults.structured_commands)
+                    .with_group_memory_confirmation(config.agents.defaults.confirm_group_memory)
+            }) {
                 Ok(agent) => Arc::new(agent),
                 Err(err) => {
                     while let Ok(req) = rx.recv() {
@@ -116,32 +256,38 @@ impl ChatWorker {
 
             while let Ok(req) = rx.recv() {
                 let session_key = req.session.as_deref().or(Some("webui:default"));
-                let answer = runtime.block_on(agent.process_direct(
+                let answer = runtime.block_on(agent.process_direct_with_metadata(
                     &req.message,
                     session_key,
                     req.channel.as_deref(),
                     req.chat_id.as_deref(),
+                    req.metadata,
                 ));
                 let _ = req.reply_tx.send(answer);
             }
         });
-        Self { tx }
+        Self { tx, active }
+    }
+
+    fn active_turns(&self) -> Vec<String> {
+        self.active
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .cloned()
+            .collect()
     }
 
-    fn chat(
-        &self,
-        message: String,
-        session: Option<String>,
-        channel: Option<String>,
-        chat_id: Option<String>,
-    ) -> Result<String> {
+    fn chat(&self, payload: ChatPayload) -> Result<String> {
         let (reply_tx, reply_rx) = mpsc::channel();
+        let metadata = payload.metadata();
         self.tx
             .send(ChatRequest {
-                message,
-                session,
-                channel,
-                chat_id,
+                message: payload.message,
+                session: payload.session,
+                channel: payload.channel,
+                chat_id: payload.chat_id,
+                metadata,
                 reply_tx,
             })
             .map_err(|err| anyhow::anyhow!("chat worker unavailable: {err}"))?;
@@ -165,13 +311,14 @@ fn build_provider(
         .get_provider(Some(model))
         .and_then(|p| p.extra_headers.clone());
     let provider_name = config.get_provider_name(Some(model));
-    Arc::new(LiteLLMProvider::new(
+    LiteLLMProvider::new(
         api_key,
         api_base,
         model.to_string(),
         extra_headers,
         provider_name.as_deref(),
-    ))
+    )
+    .pooled(config)
 }
 
 fn content_type_header(value: &str) -> Option<Header> {
@@ -191,9 +338,9 @@ fn read_cron_jobs() -> Vec<Value> {
         Ok(p) => p.join("cron").join("jobs.json"),
         Err(_) => return Vec::new(),
     };
-    let raw = match std::fs::read_to_string(path) {
-        Ok(v) => v,
-        Err(_) => return Vec::new(),
+    let raw = match locate(&path).read() {
+        Ok(Some(v)) => v,
+        _ => return Vec::new(),
     };
     let value: Value = match serde_json::from_str(&raw) {
         Ok(v) => v,
@@ -250,6 +397,15 @@ fn snapshot() -> Value {
     let cron_jobs = read_cron_jobs();
     let sessions = list_sessions();
     let pairing_pending = list_pending().unwrap_or_default();
+    let mut events = EventLog::new(config.events.retention_days)
+        .and_then(|log| {
+            log.query(&EventFilter {
+                limit: 30,
+                ..Default::default()
+            })
+        })
+        .unwrap_or_default();
+    events.reverse();
     json!({
         "version": VERSION,
         "generatedAt": Local::now().to_rfc3339(),
@@ -260,6 +416,8 @@ fn snapshot() -> Value {
         "sessions": sessions,
         "pairingPending": pairing_pending,
         "health": health,
+        "events": events,
+        "abuseIncidents": recent_incidents(20),
     })
 }
 
@@ -275,7 +433,9 @@ fn handle_request(mut req: Request, ctx: &WebUiContext) {
 
     match (method, url.as_str()) {
         (Method::Get, "/") => respond(req, 200, "text/html; charset=utf-8", INDEX_HTML.to_string()),
-        (Method::Get, "/app.css") => respond(req, 200, "text/css; charset=utf-8", APP_CSS.to_string()),
+        (Method::Get, "/app.css") => {
+            respond(req, 200, "text/css; charset=utf-8", APP_CSS.to_string())
+        }
         (Method::Get, "/app.js") => respond(
             req,
             200,
@@ -287,6 +447,10 @@ fn handle_request(mut req: Request, ctx: &WebUiContext) {
                 serde_json::to_string_pretty(&snapshot()).unwrap_or_else(|_| "{}".to_string());
             respond(req, 200, "application/json; charset=utf-8", body);
         }
+        (Method::Get, "/api/activity") => {
+            let body = json!({ "active": ctx.chat.active_turns() }).to_string();
+            respond(req, 200, "application/json; charset=utf-8", body);
+        }
         (Method::Post, "/api/chat") => {
             let raw = read_request_body(&mut req);
             let payload: ChatPayload = match serde_json::from_str(&raw) {
@@ -318,12 +482,7 @@ fn handle_request(mut req: Request, ctx: &WebUiContext) {
                 );
                 return;
             }
-            match ctx.chat.chat(
-                payload.message,
-                payload.session,
-                payload.channel,
-                payload.chat_id,
-            ) {
+            match ctx.chat.chat(payload) {
                 Ok(answer) => {
                     respond(
                         req,
@@ -354,7 +513,59 @@ fn handle_request(mut req: Request, ctx: &WebUiContext) {
                 json!({"ok": false, "error": "use POST /api/chat"}).to_string(),
             );
         }
-        (_, "/api/chat") | (_, "/api/state") | (_, "/app.css") | (_, "/app.js") | (_, "/") => {
+        (Method::Post, "/v1/chat/completions") => {
+            let raw = read_request_body(&mut req);
+            let openai_error = |message: String| {
+                json!({ "error": { "message": message, "type": "invalid_request_error" } })
+                    .to_string()
+            };
+            let payload = match serde_json::from_str::<CompletionPayload>(&raw) {
+                Ok(payload) => payload,
+                Err(err) => {
+                    let body = openai_error(format!("invalid JSON body: {err}"));
+                    respond(req, 400, "application/json; charset=utf-8", body);
+                    return;
+                }
+            };
+            let model = payload.model.clone();
+            let Some(chat) = payload.chat_payload() else {
+                let body = openai_error("no user message with text content".to_string());
+                respond(req, 400, "application/json; charset=utf-8", body);
+                return;
+            };
+            match ctx.chat.chat(chat) {
+                Ok(answer) => {
+                    let body = json!({
+                        "id": format!("chatcmpl-{}", uuid::Uuid::new_v4().simple()),
+                        "object": "chat.completion",
+                        "created": chrono::Utc::now().timestamp(),
+                        "model": model,
+                        "choices": [{
+                            "index": 0,
+                            "message": { "role": "assistant", "content": answer },
+                            "finish_reason": "stop"
+                        }]
+                    });
+                    respond(
+                        req,
+                        200,
+                        "application/json; charset=utf-8",
+                        body.to_string(),
+                    );
+                }
+                Err(err) => {
+                    let body = openai_error(err.to_string());
+                    respond(req, 500, "application/json; charset=utf-8", body);
+                }
+            }
+        }
+        (_, "/api/chat")
+        | (_, "/v1/chat/completions")
+        | (_, "/api/state")
+        | (_, "/api/activity")
+        | (_, "/app.css")
+        | (_, "/app.js")
+        | (_, "/") => {
             respond(
                 req,
                 405,
@@ -374,12 +585,17 @@ fn handle_request(mut req: Request, ctx: &WebUiContext) {
 pub fn run_webui_server(host: &str, port: u16) -> Result<()> {
     let addr = format!("{host}:{port}");
     let server = Server::http(&addr).map_err(|err| anyhow::anyhow!(err.to_string()))?;
-    let ctx = WebUiContext {
+    let config = load_config(None).unwrap_or_default();
+    crate::events::init(config.events.retention_days);
+    let ctx = Arc::new(WebUiContext {
         chat: ChatWorker::new(),
-    };
+    });
     println!("WebUI running at http://{addr}");
     for req in server.incoming_requests() {
-        handle_request(req, &ctx);
+        // A chat request blocks until the agent answers; keep serving the
+        // activity polls meanwhile.
+        let ctx = ctx.clone();
+        std::thread::spawn(move || handle_request(req, &ctx));
     }
     Ok(())
 }
@@ -0,0 +1,232 @@
+use anyhow::{Result, anyhow};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Used when the workspace repo has no identity of its own, so commits never
+// fail on a machine without a global git config.
+const AUTHOR_NAME: &str = "nanobot";
+const AUTHOR_EMAIL: &str = "nanobot@localhost";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceCommit {
+    pub id: String,
+    pub date: String,
+    pub subject: String,
+}
+
+// Versions the workspace with git: every file the agent writes and every
+// memory consolidation becomes a commit that can be inspected or reverted.
+#[derive(Debug, Clone)]
+pub struct WorkspaceGit {
+    root: PathBuf,
+}
+
+impl WorkspaceGit {
+    // Opens the workspace repo, running `git init` first if there is none.
+    pub fn open(root: &Path) -> Result<Self> {
+        let repo = Self {
+            root: root.to_path_buf(),
+        };
+        if !root.join(".git").exists() {
+            std::fs::create_dir_all(root)?;
+            repo.git(&["init", "--quiet"])?;
+        }
+        Ok(repo)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.root)
+            .args(args)
+            .output()
+            .map_err(|err| anyhow!("failed to run git: {err}"))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "git {} failed: {}",
+                args.first().copied().unwrap_or_default(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn has_identity(&self) -> bool {
+        self.git(&["config", "user.email"])
+            .map(|email| !email.is_empty())
+            .unwrap_or(false)
+    }
+
+    // Workspace-relative form of a path written by a tool; None if it lies
+    // outside the workspace.
+    fn relative(&self, path: &str) -> Option<String> {
+        let input = PathBuf::from(path);
+        let absolute = if input.is_absolute() {
+            input
+        } else {
+            std::env::current_dir().ok()?.join(input)
+        };
+        let root = self
+            .root
+            .canonicalize()
+            .unwrap_or_else(|_| self.root.clone());
+        let absolute = absolute
+            .parent()
+            .and_then(|parent| parent.canonicalize().ok())
+            .and_then(|parent| absolute.file_name().map(|name| parent.join(name)))
+            .unwrap_or(absolute);
+        absolute
+            .strip_prefix(&root)
+            .ok()
+            .map(|rel| rel.to_string_lossy().replace('\\', "/"))
+            .filter(|rel| !rel.is_empty() && !rel.starts_with(".git/") && rel != ".git")
+    }
+
+    // Commits only the given paths, leaving anything else the user has
+    // changed in the workspace untouched. Returns the new commit id, or None
+    // when there was nothing to commit.
+    pub fn commit_paths(&self, paths: &[String], message: &str) -> Result<Option<String>> {
+        let mut relative = paths
+            .iter()
+            .filter_map(|path| self.relative(path))
+            .collect::<Vec<_>>();
+        relative.sort();
+        relative.dedup();
+        if relative.is_empty() {
+            return Ok(None);
+        }
+        let mut add = vec!["add", "--all", "--"];
+        add.extend(relative.iter().map(String::as_str));
+        self.git(&add)?;
+        let mut staged = vec!["diff", "--cached", "--name-only", "--"];
+        staged.extend(relative.iter().map(String::as_str));
+        if self.git(&staged)?.is_empty() {
+            return Ok(None);
+        }
+        let name = format!("user.name={AUTHOR_NAME}");
+        let email = format!("user.email={AUTHOR_EMAIL}");
+        let mut commit = Vec::new();
+        if !self.has_identity() {
+            commit.extend(["-c", name.as_str(), "-c", email.as_str()]);
+        }
+        commit.extend(["commit", "--quiet", "--no-verify", "-m", message, "--"]);
+        commit.extend(relative.iter().map(String::as_str));
+        self.git(&commit)?;
+        self.git(&["rev-parse", "--short", "HEAD"]).map(Some)
+    }
+
+    pub fn log(&self, limit: usize) -> Result<Vec<WorkspaceCommit>> {
+        if self
+            .git(&["rev-parse", "--verify", "--quiet", "HEAD"])
+            .is_err()
+        {
+            return Ok(Vec::new());
+        }
+        let limit = format!("-{limit}");
+        let raw = self.git(&[
+            "log",
+            &limit,
+            "--format=%h%x09%ad%x09%s",
+            "--date=format:%Y-%m-%d %H:%M",
+        ])?;
+        Ok(raw
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                Some(WorkspaceCommit {
+                    id: fields.next()?.to_string(),
+                    date: fields.next()?.to_string(),
+                    subject: fields.next().unwrap_or_default().to_string(),
+                })
+            })
+            .collect())
+    }
+
+    // Undoes one commit with a new revert commit, so the rollback itself is
+    // part of the history.
+    pub fn revert(&self, id: &str) -> Result<String> {
+        let name = format!("user.name={AUTHOR_NAME}");
+        let email = format!("user.email={AUTHOR_EMAIL}");
+        let mut args = Vec::new();
+        if !self.has_identity() {
+            args.extend(["-c", name.as_str(), "-c", email.as_str()]);
+        }
+        args.extend(["revert", "--no-edit", id]);
+        if let Err(err) = self.git(&args) {
+            let _ = self.git(&["revert", "--abort"]);
+            return Err(err);
+        }
+        self.git(&["rev-parse", "--short", "HEAD"])
+    }
+}
+
+// "agent: edit notes.md, todo.md (+3 -1)" with the session on its own line.
+pub fn change_message(paths: &[String], added: usize, removed: usize, session: &str) -> String {
+    let mut names = paths
+        .iter()
+        .map(|path| {
+            Path::new(path)
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.clone())
+        })
+        .collect::<Vec<_>>();
+    names.dedup();
+    let shown = if names.len() > 3 {
+        format!("{} and {} more", names[..3].join(", "), names.len() - 3)
+    } else {
+        names.join(", ")
+    };
+    let mut message = format!("agent: edit {shown} (+{added} -{removed})\n\nsession: {session}");
+    for path in paths {
+        message.push_str(&format!("\nfile: {path}"));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commits_only_agent_paths_and_reverts() {
+        let dir = std::env::temp_dir().join(format!("nanobot-git-{}", uuid::Uuid::new_v4()));
+        let Ok(repo) = WorkspaceGit::open(&dir) else {
+            // git is not installed here.
+            return;
+        };
+        let notes = dir.join("notes.md");
+        std::fs::write(&notes, "one\n").unwrap();
+        std::fs::write(dir.join("scratch.txt"), "user file\n").unwrap();
+        let paths = vec![notes.display().to_string()];
+        let message = change_message(&paths, 1, 0, "cli:direct");
+        assert!(message.starts_with("agent: edit notes.md (+1 -0)"));
+        let first = repo.commit_paths(&paths, &message).unwrap();
+        assert!(first.is_some());
+        assert_eq!(repo.commit_paths(&paths, "noop").unwrap(), None);
+        let outside = vec!["/definitely/not/in/workspace.md".to_string()];
+        assert_eq!(repo.commit_paths(&outside, "noop").unwrap(), None);
+
+        std::fs::write(&notes, "one\ntwo\n").unwrap();
+        let second = repo
+            .commit_paths(&paths, "agent: edit notes.md")
+            .unwrap()
+            .unwrap();
+        let log = repo.log(10).unwrap();
+        assert_eq!(log.len(), 2);
+        assert_eq!(log[0].id, second);
+        assert!(
+            repo.git(&["status", "--porcelain"])
+                .unwrap()
+                .contains("scratch.txt")
+        );
+
+        repo.revert(&second).unwrap();
+        assert_eq!(std::fs::read_to_string(&notes).unwrap(), "one\n");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}